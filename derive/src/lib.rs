@@ -0,0 +1,352 @@
+#![warn(clippy::pedantic)]
+#![warn(clippy::style)]
+#![warn(clippy::cargo)]
+#![warn(clippy::unwrap_used)]
+#![warn(clippy::expect_used)]
+
+//! Derive macros for [usbd-human-interface-device](https://docs.rs/usbd-human-interface-device),
+//! use them through the re-exports in that crate
+
+use core::fmt::Display;
+use core::str::FromStr;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Index, LitInt, Token, Type};
+
+/// Derive `HidReport`, generating the report descriptor items and serialization of a report
+/// struct
+///
+/// Each field is an input with a usage set by `#[hid(...)]`:
+///
+/// - `usage` - the field's usage, required
+/// - `usage_page` - the field's usage page, defaults to the struct's `usage_page`
+/// - `logical_min` and `logical_max` - defaults to the range of the field's type
+/// - `relative` - the field is a relative value, such as a mouse movement
+/// - `physical_min` and `physical_max` - the physical values of the logical range, defaults to
+///   none
+/// - `unit` - a `Unit` constant, such as `unit = DEGREE`, defaults to none
+/// - `unit_exponent` - base 10 exponent of the physical values, defaults to 0
+/// - `string_index` - position of the field's name in the interface's report strings, defaults
+///   to none
+///
+/// Fields are `bool`, packed as single bits, or `u8`, `i8`, `u16`, `i16`, `u32` or `i32`, packed
+/// little endian and aligned to a byte boundary. The default logical maximum of `u32` fields is
+/// `i32::MAX`. The struct's `#[hid(...)]` sets the default `usage_page` and an optional
+/// `report_id`.
+#[proc_macro_derive(HidReport, attributes(hid))]
+pub fn derive_hid_report(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct ReportAttributes {
+    report_id: Option<u8>,
+    usage_page: Option<u16>,
+}
+
+#[derive(Default)]
+struct FieldAttributes {
+    usage_page: Option<u16>,
+    usage: Option<u16>,
+    logical_min: Option<i32>,
+    logical_max: Option<i32>,
+    relative: bool,
+    physical_min: Option<i32>,
+    physical_max: Option<i32>,
+    unit: Option<Ident>,
+    unit_exponent: Option<i8>,
+    string_index: Option<u8>,
+}
+
+/// Size in bits and default logical range of a field type
+fn field_layout(ty: &Type) -> Option<(u8, i32, i32)> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let ident = path.path.get_ident()?;
+    Some(match ident.to_string().as_str() {
+        "bool" => (1, 0, 1),
+        "u8" => (8, 0, u8::MAX.into()),
+        "i8" => (8, i8::MIN.into(), i8::MAX.into()),
+        "u16" => (16, 0, u16::MAX.into()),
+        "i16" => (16, i16::MIN.into(), i16::MAX.into()),
+        "u32" => (32, 0, i32::MAX),
+        "i32" => (32, i32::MIN, i32::MAX),
+        _ => return None,
+    })
+}
+
+fn parse_int<T>(meta: &ParseNestedMeta) -> syn::Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let input = meta.value()?;
+    let negative = input.parse::<Option<Token![-]>>()?.is_some();
+    let lit: LitInt = input.parse()?;
+    if negative {
+        format!("-{}", lit.base10_digits())
+            .parse()
+            .map_err(|e| Error::new(lit.span(), e))
+    } else {
+        lit.base10_parse()
+    }
+}
+
+fn report_attributes(input: &DeriveInput) -> syn::Result<ReportAttributes> {
+    let mut attributes = ReportAttributes::default();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("hid")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("report_id") {
+                let id = parse_int(&meta)?;
+                if id == 0 {
+                    return Err(meta.error("report ID 0 is reserved"));
+                }
+                attributes.report_id = Some(id);
+            } else if meta.path.is_ident("usage_page") {
+                attributes.usage_page = Some(parse_int(&meta)?);
+            } else {
+                return Err(meta.error("expected `report_id` or `usage_page`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attributes)
+}
+
+fn field_attributes(field: &syn::Field) -> syn::Result<FieldAttributes> {
+    let mut attributes = FieldAttributes::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("hid")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("usage_page") {
+                attributes.usage_page = Some(parse_int(&meta)?);
+            } else if meta.path.is_ident("usage") {
+                attributes.usage = Some(parse_int(&meta)?);
+            } else if meta.path.is_ident("logical_min") {
+                attributes.logical_min = Some(parse_int(&meta)?);
+            } else if meta.path.is_ident("logical_max") {
+                attributes.logical_max = Some(parse_int(&meta)?);
+            } else if meta.path.is_ident("relative") {
+                attributes.relative = true;
+            } else if meta.path.is_ident("physical_min") {
+                attributes.physical_min = Some(parse_int(&meta)?);
+            } else if meta.path.is_ident("physical_max") {
+                attributes.physical_max = Some(parse_int(&meta)?);
+            } else if meta.path.is_ident("unit") {
+                attributes.unit = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("unit_exponent") {
+                let exponent = parse_int(&meta)?;
+                if !(-8..=7).contains(&exponent) {
+                    return Err(meta.error("unit exponent out of range, expected -8 to 7"));
+                }
+                attributes.unit_exponent = Some(exponent);
+            } else if meta.path.is_ident("string_index") {
+                attributes.string_index = Some(parse_int(&meta)?);
+            } else {
+                return Err(meta.error(
+                    "expected `usage_page`, `usage`, `logical_min`, `logical_max`, `relative`, \
+                     `physical_min`, `physical_max`, `unit`, `unit_exponent` or `string_index`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attributes)
+}
+
+/// Descriptor items and bit position of the report fields as they are generated
+#[derive(Default)]
+struct Layout {
+    items: Vec<TokenStream2>,
+    pack: Vec<TokenStream2>,
+    bit: usize,
+    usage_page: Option<u16>,
+    logical: Option<(i32, i32)>,
+    physical: (i32, i32),
+    unit: Option<Ident>,
+    unit_exponent: i8,
+    report_size: Option<u8>,
+}
+
+impl Layout {
+    fn set_report_size(&mut self, bits: u8) {
+        if self.report_size != Some(bits) {
+            self.items.push(quote!(.report_size(#bits)));
+            self.report_size = Some(bits);
+        }
+    }
+
+    /// Pad to the next byte boundary
+    fn pad(&mut self) {
+        let remainder = self.bit % 8;
+        if remainder != 0 {
+            #[allow(clippy::cast_possible_truncation)]
+            self.set_report_size(8 - remainder as u8);
+            self.items.push(quote!(.input(ItemFlags::PADDING)));
+            self.bit += 8 - remainder;
+        }
+    }
+
+    /// Push the descriptor items and serialization of a field
+    fn push_field(
+        &mut self,
+        accessor: &TokenStream2,
+        bits: u8,
+        usage_page: u16,
+        logical: (i32, i32),
+        attributes: &FieldAttributes,
+    ) {
+        if bits > 1 {
+            self.pad();
+        }
+        if self.usage_page != Some(usage_page) {
+            self.items.push(quote!(.usage_page(#usage_page)));
+            self.usage_page = Some(usage_page);
+        }
+        if self.logical != Some(logical) {
+            let (min, max) = logical;
+            self.items
+                .push(quote!(.logical_minimum(#min).logical_maximum(#max)));
+            self.logical = Some(logical);
+        }
+        let physical = (
+            attributes.physical_min.unwrap_or_default(),
+            attributes.physical_max.unwrap_or_default(),
+        );
+        if self.physical != physical {
+            let (min, max) = physical;
+            self.items
+                .push(quote!(.physical_minimum(#min).physical_maximum(#max)));
+            self.physical = physical;
+        }
+        if self.unit != attributes.unit {
+            let unit = attributes
+                .unit
+                .clone()
+                .unwrap_or_else(|| Ident::new("NONE", Span::call_site()));
+            self.items.push(
+                quote!(.unit(::usbd_human_interface_device::descriptor::builder::Unit::#unit)),
+            );
+            self.unit.clone_from(&attributes.unit);
+        }
+        let unit_exponent = attributes.unit_exponent.unwrap_or_default();
+        if self.unit_exponent != unit_exponent {
+            self.items.push(quote!(.unit_exponent(#unit_exponent)));
+            self.unit_exponent = unit_exponent;
+        }
+        self.set_report_size(bits);
+        if let Some(index) = attributes.string_index {
+            self.items.push(quote!(.string_index(#index)));
+        }
+        let usage = attributes.usage.unwrap_or_default();
+        let flags = if attributes.relative {
+            quote!(ItemFlags::VARIABLE.with(ItemFlags::RELATIVE))
+        } else {
+            quote!(ItemFlags::DATA_VARIABLE_ABSOLUTE)
+        };
+        self.items.push(quote!(.usage(#usage).input(#flags)));
+
+        let byte = self.bit / 8;
+        if bits == 1 {
+            #[allow(clippy::cast_possible_truncation)]
+            let shift = (self.bit % 8) as u8;
+            self.pack
+                .push(quote!(data[#byte] |= u8::from(self.#accessor) << #shift;));
+        } else {
+            let end = byte + usize::from(bits / 8);
+            self.pack
+                .push(quote!(data[#byte..#end].copy_from_slice(&self.#accessor.to_le_bytes());));
+        }
+        self.bit += usize::from(bits);
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "HidReport can't be derived for generic structs",
+        ));
+    }
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.ident.span(),
+            "HidReport can only be derived for structs",
+        ));
+    };
+
+    let report = report_attributes(input)?;
+    let mut layout = Layout::default();
+    if let Some(id) = report.report_id {
+        layout.items.push(quote!(.report_id(#id)));
+    }
+    layout.items.push(quote!(.report_count(1)));
+
+    for (i, field) in data.fields.iter().enumerate() {
+        let accessor = field.ident.as_ref().map_or_else(
+            || {
+                let index = Index::from(i);
+                quote!(#index)
+            },
+            |ident| quote!(#ident),
+        );
+        let (bits, min, max) = field_layout(&field.ty).ok_or_else(|| {
+            Error::new(
+                field.ty.span(),
+                "unsupported field type, expected bool, u8, i8, u16, i16, u32 or i32",
+            )
+        })?;
+        let attributes = field_attributes(field)?;
+        if attributes.usage.is_none() {
+            return Err(Error::new(field.span(), "missing `#[hid(usage = ...)]`"));
+        }
+        let usage_page = attributes.usage_page.or(report.usage_page).ok_or_else(|| {
+            Error::new(
+                field.span(),
+                "missing `usage_page`, set it on the field or the struct",
+            )
+        })?;
+
+        let logical = (
+            attributes.logical_min.unwrap_or(min),
+            attributes.logical_max.unwrap_or(max),
+        );
+        layout.push_field(&accessor, bits, usage_page, logical, &attributes);
+    }
+    layout.pad();
+
+    let name = &input.ident;
+    let report_id = if let Some(id) = report.report_id {
+        quote!(::core::option::Option::Some(#id))
+    } else {
+        quote!(::core::option::Option::None)
+    };
+    let report_len = layout.bit / 8 + usize::from(report.report_id.is_some());
+    let items = &layout.items;
+    let pack = &layout.pack;
+
+    Ok(quote! {
+        impl ::usbd_human_interface_device::descriptor::HidReport for #name {
+            const REPORT_ID: ::core::option::Option<u8> = #report_id;
+            const REPORT_LEN: usize = #report_len;
+            const DESCRIPTOR_ITEMS: &'static [u8] = {
+                use ::usbd_human_interface_device::descriptor::builder::{
+                    DescriptorBuilder, ItemFlags,
+                };
+                const BUILDER: DescriptorBuilder = DescriptorBuilder::new() #(#items)*;
+                &BUILDER.build::<{ BUILDER.len() }>()
+            };
+
+            fn pack_fields(&self, data: &mut [u8]) {
+                #(#pack)*
+            }
+        }
+    })
+}
@@ -27,6 +27,7 @@ mod app {
     #[allow(clippy::wildcard_imports)]
     use usb_device::class_prelude::*;
     use usb_device::prelude::*;
+    use usbd_human_interface_device::channel::{ReportChannel, ReportReceiver, ReportSender};
     use usbd_human_interface_device::device::keyboard::NKROBootKeyboard;
     use usbd_human_interface_device::page::Keyboard;
     use usbd_human_interface_device::prelude::*;
@@ -48,9 +49,14 @@ mod app {
     struct Local {
         led: Pin<Gpio25, FunctionSio<SioOutput>, PullDown>,
         key: Pin<Gpio0, FunctionSio<hal::gpio::SioInput>, PullUp>,
+        sender: ReportSender<'static, Keyboard, 4>,
+        receiver: ReportReceiver<'static, Keyboard, 4>,
     }
 
-    #[init(local = [usb_alloc: Option<UsbBusAllocator<hal::usb::UsbBus>> = None])]
+    #[init(local = [
+        usb_alloc: Option<UsbBusAllocator<hal::usb::UsbBus>> = None,
+        channel: ReportChannel<Keyboard, 4> = ReportChannel::new()
+    ])]
     fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
         // Soft-reset does not release the hardware spinlocks
         // Release them now to avoid a deadlock after debug or watchdog reset
@@ -116,6 +122,9 @@ mod app {
             bsp::pac::NVIC::unmask(hal::pac::Interrupt::USBCTRL_IRQ);
         };
 
+        // Keys are sent to the USB interrupt handler, which owns writing the reports
+        let (sender, receiver) = cx.local.channel.split();
+
         let now = monotonics::now();
         tick::spawn(now).unwrap();
         write_keyboard::spawn(now).unwrap();
@@ -125,7 +134,12 @@ mod app {
                 keyboard,
                 usb_device,
             },
-            Local { led, key },
+            Local {
+                led,
+                key,
+                sender,
+                receiver,
+            },
             init::Monotonics(),
         )
     }
@@ -146,25 +160,17 @@ mod app {
         tick::spawn_at(next, next).unwrap();
     }
 
-    #[task(
-        shared = [keyboard],
-        local = [key]
-    )]
-    fn write_keyboard(mut cx: write_keyboard::Context, scheduled: Instant) {
-        cx.shared.keyboard.lock(|k| {
-            match k.device().write_report([if cx.local.key.is_low().unwrap() {
-                Keyboard::A
-            } else {
-                Keyboard::NoEventIndicated
-            }]) {
-                Err(UsbHidError::WouldBlock) => {}
-                Err(UsbHidError::Duplicate) => {}
-                Ok(_) => {}
-                Err(e) => {
-                    core::panic!("Failed to write keyboard report: {:?}", e)
-                }
-            }
-        });
+    #[task(local = [key, sender])]
+    fn write_keyboard(cx: write_keyboard::Context, scheduled: Instant) {
+        let key = if cx.local.key.is_low().unwrap() {
+            Keyboard::A
+        } else {
+            Keyboard::NoEventIndicated
+        };
+        // No lock is needed, if the channel is full the key is sampled again next time
+        if cx.local.sender.send(key).is_ok() {
+            rtic::pend(hal::pac::Interrupt::USBCTRL_IRQ);
+        }
 
         let next = scheduled + 50.millis();
         write_keyboard::spawn_at(next, next).unwrap();
@@ -173,7 +179,7 @@ mod app {
     #[task(
         binds = USBCTRL_IRQ,
         shared = [keyboard, usb_device],
-        local = [led]
+        local = [led, receiver]
     )]
     fn usb_irq(cx: usb_irq::Context) {
         (cx.shared.keyboard, cx.shared.usb_device).lock(|keyboard, usb_device| {
@@ -191,6 +197,18 @@ mod app {
                         .unwrap(),
                 }
             }
+
+            match cx
+                .local
+                .receiver
+                .write_with(|&key| keyboard.device().write_report([key]))
+            {
+                Err(UsbHidError::WouldBlock) => {}
+                Ok(_) => {}
+                Err(e) => {
+                    core::panic!("Failed to write keyboard report: {:?}", e)
+                }
+            }
         })
     }
 }
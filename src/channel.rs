@@ -0,0 +1,169 @@
+//! Channels handing reports from tasks to the USB interrupt handler
+//!
+//! With RTIC, or any other interrupt driven executor, [`UsbHidClass`] is polled from the USB
+//! interrupt handler. Sharing the class with the tasks building reports means locking it, holding
+//! off the USB interrupt, for each report written. Instead the class can be owned by the interrupt
+//! handler, with tasks sending reports through a [`ReportSender`], which is [`Send`] and needs no
+//! lock. The interrupt handler writes them to the devices with a [`ReportReceiver`] after each
+//! [`UsbDevice::poll`](usb_device::device::UsbDevice::poll).
+//!
+//! The USB interrupt only fires on bus activity, so pend it after sending a report, such as with
+//! `rtic::pend`, for the report to be written without waiting for the host's next request.
+//!
+//! ```rust
+//! use usbd_human_interface_device::channel::ReportChannel;
+//! use usbd_human_interface_device::page::Keyboard;
+//!
+//! let mut channel: ReportChannel<Keyboard, 4> = ReportChannel::new();
+//! let (mut sender, mut receiver) = channel.split();
+//!
+//! // In a task
+//! sender.send(Keyboard::A).unwrap();
+//!
+//! // In the USB interrupt handler, writing with `keyboard.device().write_report([key])`
+//! # let mut write_report = |key: Keyboard| Ok(());
+//! receiver.write_with(|&key| write_report(key)).unwrap();
+//! assert!(receiver.is_empty());
+//! ```
+//!
+//! [`UsbHidClass`]: crate::usb_class::UsbHidClass
+
+use crate::UsbHidError;
+use heapless::spsc::{Consumer, Producer, Queue};
+
+/// Queue of reports, holding up to `N - 1` reports, split into a [`ReportSender`] and a
+/// [`ReportReceiver`]
+///
+/// Declare the channel `static`, such as in RTIC's `#[init(local = [...])]`, for the halves to be
+/// `'static`.
+pub struct ReportChannel<R, const N: usize> {
+    queue: Queue<R, N>,
+}
+
+impl<R, const N: usize> ReportChannel<R, N> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            queue: Queue::new(),
+        }
+    }
+
+    /// Split the channel into the half sending reports, for tasks, and the half writing them, for
+    /// the USB interrupt handler
+    pub fn split(&mut self) -> (ReportSender<'_, R, N>, ReportReceiver<'_, R, N>) {
+        let (producer, consumer) = self.queue.split();
+        (ReportSender { producer }, ReportReceiver { consumer })
+    }
+}
+
+impl<R, const N: usize> Default for ReportChannel<R, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Half of a [`ReportChannel`] sending reports
+pub struct ReportSender<'q, R, const N: usize> {
+    producer: Producer<'q, R, N>,
+}
+
+impl<R, const N: usize> ReportSender<'_, R, N> {
+    /// Send `report`, returns [`UsbHidError::WouldBlock`] if the channel is full
+    pub fn send(&mut self, report: R) -> Result<(), UsbHidError> {
+        self.producer
+            .enqueue(report)
+            .map_err(|_| UsbHidError::WouldBlock)
+    }
+
+    /// Whether a report can be sent without the channel being full
+    #[must_use]
+    pub fn ready(&self) -> bool {
+        self.producer.ready()
+    }
+}
+
+/// Half of a [`ReportChannel`] writing the reports sent to devices
+pub struct ReportReceiver<'q, R, const N: usize> {
+    consumer: Consumer<'q, R, N>,
+}
+
+impl<R, const N: usize> ReportReceiver<'_, R, N> {
+    /// Write each report sent with `write_report`, in the order they were sent, stopping at the
+    /// first report it fails to write, such as with [`UsbHidError::WouldBlock`], to retry after the
+    /// next poll
+    ///
+    /// Reports that fail with [`UsbHidError::Duplicate`] are discarded.
+    pub fn write_with(
+        &mut self,
+        mut write_report: impl FnMut(&R) -> Result<(), UsbHidError>,
+    ) -> Result<(), UsbHidError> {
+        while let Some(report) = self.consumer.peek() {
+            match write_report(report) {
+                Ok(()) | Err(UsbHidError::Duplicate) => {
+                    self.consumer.dequeue();
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        !self.consumer.ready()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use std::vec::Vec;
+
+    use crate::channel::{ReportChannel, ReportReceiver, ReportSender};
+    use crate::page::Keyboard;
+    use crate::UsbHidError;
+
+    #[test]
+    fn reports_are_written_in_order_until_blocked() {
+        let mut channel: ReportChannel<Keyboard, 4> = ReportChannel::new();
+        let (mut sender, mut receiver) = channel.split();
+
+        sender.send(Keyboard::A).unwrap();
+        sender.send(Keyboard::B).unwrap();
+        sender.send(Keyboard::C).unwrap();
+        assert!(!sender.ready());
+        assert!(matches!(
+            sender.send(Keyboard::D),
+            Err(UsbHidError::WouldBlock)
+        ));
+
+        let mut written = Vec::new();
+        let result = receiver.write_with(|&key| {
+            if written.len() == 2 {
+                return Err(UsbHidError::WouldBlock);
+            }
+            written.push(key);
+            Ok(())
+        });
+        assert!(matches!(result, Err(UsbHidError::WouldBlock)));
+        assert_eq!(written, [Keyboard::A, Keyboard::B]);
+
+        // Blocked reports are retried, duplicates are discarded
+        receiver
+            .write_with(|&key| {
+                written.push(key);
+                Err(UsbHidError::Duplicate)
+            })
+            .unwrap();
+        assert_eq!(written, [Keyboard::A, Keyboard::B, Keyboard::C]);
+        assert!(receiver.is_empty());
+    }
+
+    #[test]
+    fn channel_halves_are_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<ReportSender<'static, Keyboard, 4>>();
+        assert_send::<ReportReceiver<'static, Keyboard, 4>>();
+    }
+}
@@ -1,10 +1,23 @@
 //! HID descriptor constants and enumerations
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use packed_struct::prelude::*;
+use usb_device::UsbError;
+
+pub mod builder;
+pub mod compose;
+pub mod decode;
+pub mod ms_os;
+pub mod optimize;
+pub mod parse;
+
+#[cfg(feature = "derive")]
+pub use usbd_human_interface_device_derive::HidReport;
 
 pub(crate) const USB_CLASS_HID: u8 = 0x03;
-pub(crate) const SPEC_VERSION_1_11: u16 = 0x0111; //1.11 in BCD
-pub(crate) const COUNTRY_CODE_NOT_SUPPORTED: u8 = 0x0;
+/// HID specification release 1.10 in BCD, for the `bcdHID` of the HID descriptor
+pub const SPEC_VERSION_1_10: u16 = 0x0110;
+/// HID specification release 1.11 in BCD, the default `bcdHID` of the HID descriptor
+pub const SPEC_VERSION_1_11: u16 = 0x0111;
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, TryFromPrimitive, IntoPrimitive)]
@@ -21,6 +34,7 @@ pub enum InterfaceProtocol {
 pub(crate) enum DescriptorType {
     Hid = 0x21,
     Report = 0x22,
+    Physical = 0x23,
 }
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -49,6 +63,53 @@ pub enum HidProtocol {
     Report = 0x01,
 }
 
+/// Country of the localized hardware, the `bCountryCode` of the HID descriptor
+///
+/// Most hardware isn't localized, but keyboards may declare their layout, such as a JIS keyboard
+/// declaring [`CountryCode::JapanKatakana`].
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum CountryCode {
+    #[default]
+    NotSupported = 0,
+    Arabic = 1,
+    Belgian = 2,
+    CanadianBilingual = 3,
+    CanadianFrench = 4,
+    CzechRepublic = 5,
+    Danish = 6,
+    Finnish = 7,
+    French = 8,
+    German = 9,
+    Greek = 10,
+    Hebrew = 11,
+    Hungary = 12,
+    InternationalIso = 13,
+    Italian = 14,
+    JapanKatakana = 15,
+    Korean = 16,
+    LatinAmerican = 17,
+    Netherlands = 18,
+    Norwegian = 19,
+    Persian = 20,
+    Poland = 21,
+    Portuguese = 22,
+    Russia = 23,
+    Slovakia = 24,
+    Spanish = 25,
+    Swedish = 26,
+    SwissFrench = 27,
+    SwissGerman = 28,
+    Switzerland = 29,
+    Taiwan = 30,
+    TurkishQ = 31,
+    UnitedKingdom = 32,
+    UnitedStates = 33,
+    Yugoslavia = 34,
+    TurkishF = 35,
+}
+
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
 #[repr(u8)]
@@ -60,3 +121,256 @@ pub(crate) enum HidRequest {
     SetIdle = 0x0A,
     SetProtocol = 0x0B,
 }
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub(crate) enum ReportType {
+    Input = 0x01,
+    Output = 0x02,
+    Feature = 0x03,
+}
+
+/// Report with the report descriptor items that describe it
+///
+/// Usually implemented with `#[derive(HidReport)]`, with the `derive` feature enabled, keeping the
+/// report descriptor and serialization of a report struct in step:
+///
+/// ```rust
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use usbd_human_interface_device::descriptor::builder::{Collection, DescriptorBuilder};
+/// use usbd_human_interface_device::descriptor::HidReport;
+///
+/// #[derive(HidReport)]
+/// #[hid(usage_page = 0x01)]
+/// struct DialReport {
+///     #[hid(usage_page = 0x09, usage = 0x01)]
+///     button: bool,
+///     #[hid(usage = 0x37, logical_min = -127, logical_max = 127, relative)]
+///     dial: i8,
+/// }
+///
+/// const BUILDER: DescriptorBuilder = DescriptorBuilder::new()
+///     .usage_page(0x01)
+///     .usage(0x0E)
+///     .collection(Collection::Application)
+///     .raw(DialReport::DESCRIPTOR_ITEMS)
+///     .end_collection();
+/// pub const DIAL_REPORT_DESCRIPTOR: &[u8] = &BUILDER.build::<{ BUILDER.len() }>();
+///
+/// let mut data = [0; DialReport::REPORT_LEN];
+/// DialReport { button: true, dial: -2 }.pack_report(&mut data).unwrap();
+/// assert_eq!(data, [0x01, 0xFE]);
+/// # }
+/// ```
+pub trait HidReport {
+    const REPORT_ID: Option<u8>;
+    /// Length of the report in bytes, including the report ID
+    const REPORT_LEN: usize;
+    /// Report descriptor items describing the report's fields, to be placed within a collection
+    const DESCRIPTOR_ITEMS: &'static [u8];
+
+    /// Write the report's fields, without the report ID, to `data`
+    ///
+    /// `data` is [`HidReport::REPORT_LEN`] zeroed bytes, less the report ID
+    fn pack_fields(&self, data: &mut [u8]);
+
+    /// Write the report, including the report ID, to `data` returning the report length
+    fn pack_report(&self, data: &mut [u8]) -> usb_device::Result<usize> {
+        let data = data
+            .get_mut(..Self::REPORT_LEN)
+            .ok_or(UsbError::BufferOverflow)?;
+        data.fill(0);
+        match Self::REPORT_ID {
+            Some(id) => {
+                data[0] = id;
+                self.pack_fields(&mut data[1..]);
+            }
+            None => self.pack_fields(data),
+        }
+        Ok(Self::REPORT_LEN)
+    }
+}
+
+/// Concatenate report descriptor fragments at compile time
+///
+/// Panics, at compile time if used in a const context, if `N` is not the total length of `parts`
+pub(crate) const fn concat<const N: usize>(parts: &[&[u8]]) -> [u8; N] {
+    let mut descriptor = [0; N];
+    let mut len = 0;
+    let mut p = 0;
+    while p < parts.len() {
+        let mut i = 0;
+        while i < parts[p].len() {
+            descriptor[len] = parts[p][i];
+            len += 1;
+            i += 1;
+        }
+        p += 1;
+    }
+    // `core::assert!` rather than the crate macro, the defmt assert is not usable in const fns
+    core::assert!(len == N, "descriptor length mismatch");
+    descriptor
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use crate::descriptor::HidReport;
+
+    #[derive(HidReport)]
+    #[hid(report_id = 2, usage_page = 0x01)]
+    struct PointerReport {
+        #[hid(usage_page = 0x09, usage = 0x01)]
+        left: bool,
+        #[hid(usage_page = 0x09, usage = 0x02)]
+        right: bool,
+        #[hid(usage = 0x30, logical_min = -2047, logical_max = 2047, relative)]
+        x: i16,
+        #[hid(usage = 0x31, logical_min = -2047, logical_max = 2047, relative)]
+        y: i16,
+        #[hid(usage = 0x38)]
+        wheel: i8,
+        #[hid(usage_page = 0x09, usage = 0x03)]
+        middle: bool,
+    }
+
+    #[derive(HidReport)]
+    #[hid(usage_page = 0x01)]
+    struct HatReport {
+        #[hid(usage = 0x39, logical_max = 7, physical_max = 315, unit = DEGREE)]
+        hat: u8,
+        #[hid(usage = 0x30, unit_exponent = -2, unit = CENTIMETRE)]
+        x: i16,
+        #[hid(usage = 0x31)]
+        y: i16,
+    }
+
+    #[derive(HidReport)]
+    #[hid(usage_page = 0x09)]
+    struct ButtonBoxReport {
+        #[hid(usage = 0x01, string_index = 0)]
+        start: bool,
+        #[hid(usage = 0x02, string_index = 1)]
+        stop: bool,
+    }
+
+    #[test]
+    fn derived_report_descriptor_items() {
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0x85, 0x02,             // Report ID (2),
+            0x95, 0x01,             // Report Count (1),
+            0x05, 0x09,             // Usage Page (Button),
+            0x15, 0x00,             // Logical Minimum (0),
+            0x25, 0x01,             // Logical Maximum (1),
+            0x75, 0x01,             // Report Size (1),
+            0x09, 0x01,             // Usage (1),
+            0x81, 0x02,             // Input (Data, Variable, Absolute),
+            0x09, 0x02,             // Usage (2),
+            0x81, 0x02,             // Input (Data, Variable, Absolute),
+            0x75, 0x06,             // Report Size (6),
+            0x81, 0x03,             // Input (Constant, Variable, Absolute),
+            0x05, 0x01,             // Usage Page (Generic Desktop),
+            0x16, 0x01, 0xF8,       // Logical Minimum (-2047),
+            0x26, 0xFF, 0x07,       // Logical Maximum (2047),
+            0x75, 0x10,             // Report Size (16),
+            0x09, 0x30,             // Usage (X),
+            0x81, 0x06,             // Input (Data, Variable, Relative),
+            0x09, 0x31,             // Usage (Y),
+            0x81, 0x06,             // Input (Data, Variable, Relative),
+            0x15, 0x80,             // Logical Minimum (-128),
+            0x25, 0x7F,             // Logical Maximum (127),
+            0x75, 0x08,             // Report Size (8),
+            0x09, 0x38,             // Usage (Wheel),
+            0x81, 0x02,             // Input (Data, Variable, Absolute),
+            0x05, 0x09,             // Usage Page (Button),
+            0x15, 0x00,             // Logical Minimum (0),
+            0x25, 0x01,             // Logical Maximum (1),
+            0x75, 0x01,             // Report Size (1),
+            0x09, 0x03,             // Usage (3),
+            0x81, 0x02,             // Input (Data, Variable, Absolute),
+            0x75, 0x07,             // Report Size (7),
+            0x81, 0x03,             // Input (Constant, Variable, Absolute),
+        ];
+
+        assert_eq!(PointerReport::DESCRIPTOR_ITEMS, expected);
+        assert_eq!(PointerReport::REPORT_ID, Some(2));
+        assert_eq!(PointerReport::REPORT_LEN, 8);
+    }
+
+    #[test]
+    fn derived_report_serialization() {
+        let report = PointerReport {
+            left: false,
+            right: true,
+            x: -2,
+            y: 0x0102,
+            wheel: 1,
+            middle: true,
+        };
+        let mut data = [0xFF; 10];
+
+        assert_eq!(report.pack_report(&mut data).unwrap(), 8);
+        assert_eq!(
+            data,
+            [0x02, 0x02, 0xFE, 0xFF, 0x02, 0x01, 0x01, 0x01, 0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn derived_report_physical_units() {
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0x95, 0x01,             // Report Count (1),
+            0x05, 0x01,             // Usage Page (Generic Desktop),
+            0x15, 0x00,             // Logical Minimum (0),
+            0x25, 0x07,             // Logical Maximum (7),
+            0x35, 0x00,             // Physical Minimum (0),
+            0x46, 0x3B, 0x01,       // Physical Maximum (315),
+            0x65, 0x14,             // Unit (Degrees),
+            0x75, 0x08,             // Report Size (8),
+            0x09, 0x39,             // Usage (Hat Switch),
+            0x81, 0x02,             // Input (Data, Variable, Absolute),
+            0x16, 0x00, 0x80,       // Logical Minimum (-32768),
+            0x26, 0xFF, 0x7F,       // Logical Maximum (32767),
+            0x35, 0x00,             // Physical Minimum (0),
+            0x45, 0x00,             // Physical Maximum (0),
+            0x65, 0x11,             // Unit (Centimetre),
+            0x55, 0x0E,             // Unit Exponent (-2),
+            0x75, 0x10,             // Report Size (16),
+            0x09, 0x30,             // Usage (X),
+            0x81, 0x02,             // Input (Data, Variable, Absolute),
+            0x65, 0x00,             // Unit (None),
+            0x55, 0x00,             // Unit Exponent (0),
+            0x09, 0x31,             // Usage (Y),
+            0x81, 0x02,             // Input (Data, Variable, Absolute),
+        ];
+
+        assert_eq!(HatReport::DESCRIPTOR_ITEMS, expected);
+    }
+
+    #[test]
+    fn derived_report_string_indices() {
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0x95, 0x01,             // Report Count (1),
+            0x05, 0x09,             // Usage Page (Button),
+            0x15, 0x00,             // Logical Minimum (0),
+            0x25, 0x01,             // Logical Maximum (1),
+            0x75, 0x01,             // Report Size (1),
+            0x79, 0x00,             // String Index (0),
+            0x09, 0x01,             // Usage (1),
+            0x81, 0x02,             // Input (Data, Variable, Absolute),
+            0x79, 0x01,             // String Index (1),
+            0x09, 0x02,             // Usage (2),
+            0x81, 0x02,             // Input (Data, Variable, Absolute),
+            0x75, 0x06,             // Report Size (6),
+            0x81, 0x03,             // Input (Constant, Variable, Absolute),
+        ];
+
+        assert_eq!(ButtonBoxReport::DESCRIPTOR_ITEMS, expected);
+    }
+}
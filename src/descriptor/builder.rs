@@ -0,0 +1,569 @@
+//! Compile time report descriptor builder
+//!
+//! Report descriptors are composed from typed items with [`DescriptorBuilder`], which picks the
+//! shortest encoding for each item's data, and converted to a fixed size array in a const
+//! context:
+//!
+//! ```rust
+//! use usbd_human_interface_device::descriptor::builder::{Collection, DescriptorBuilder, ItemFlags};
+//!
+//! const BUILDER: DescriptorBuilder = DescriptorBuilder::new()
+//!     .usage_page(0x01) // Generic Desktop
+//!     .usage(0x0C) // Wireless Radio Controls
+//!     .collection(Collection::Application)
+//!     .logical_minimum(0)
+//!     .logical_maximum(1)
+//!     .usage(0xC6) // Wireless Radio Button
+//!     .report_count(1)
+//!     .report_size(1)
+//!     .input(ItemFlags::VARIABLE.with(ItemFlags::RELATIVE))
+//!     .report_size(7)
+//!     .input(ItemFlags::CONSTANT.with(ItemFlags::VARIABLE))
+//!     .end_collection();
+//!
+//! pub const REPORT_DESCRIPTOR: &[u8] = &BUILDER.build::<{ BUILDER.len() }>();
+//! ```
+
+/// Default capacity of a [`DescriptorBuilder`] in bytes
+pub const DESCRIPTOR_BUILDER_CAPACITY: usize = 256;
+
+const MAIN_INPUT: u8 = 0x80;
+const MAIN_OUTPUT: u8 = 0x90;
+const MAIN_COLLECTION: u8 = 0xA0;
+const MAIN_FEATURE: u8 = 0xB0;
+const MAIN_END_COLLECTION: u8 = 0xC0;
+
+const GLOBAL_USAGE_PAGE: u8 = 0x04;
+const GLOBAL_LOGICAL_MINIMUM: u8 = 0x14;
+const GLOBAL_LOGICAL_MAXIMUM: u8 = 0x24;
+const GLOBAL_PHYSICAL_MINIMUM: u8 = 0x34;
+const GLOBAL_PHYSICAL_MAXIMUM: u8 = 0x44;
+const GLOBAL_UNIT_EXPONENT: u8 = 0x54;
+const GLOBAL_UNIT: u8 = 0x64;
+const GLOBAL_REPORT_SIZE: u8 = 0x74;
+const GLOBAL_REPORT_ID: u8 = 0x84;
+const GLOBAL_REPORT_COUNT: u8 = 0x94;
+
+const LOCAL_USAGE: u8 = 0x08;
+const LOCAL_USAGE_MINIMUM: u8 = 0x18;
+const LOCAL_USAGE_MAXIMUM: u8 = 0x28;
+const LOCAL_DESIGNATOR_INDEX: u8 = 0x38;
+const LOCAL_DESIGNATOR_MINIMUM: u8 = 0x48;
+const LOCAL_DESIGNATOR_MAXIMUM: u8 = 0x58;
+const LOCAL_STRING_INDEX: u8 = 0x78;
+const LOCAL_STRING_MINIMUM: u8 = 0x88;
+const LOCAL_STRING_MAXIMUM: u8 = 0x98;
+
+/// Collection types
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Collection {
+    Physical = 0x00,
+    Application = 0x01,
+    Logical = 0x02,
+    Report = 0x03,
+    NamedArray = 0x04,
+    UsageSwitch = 0x05,
+    UsageModifier = 0x06,
+}
+
+/// Data flags of Input, Output and Feature items
+///
+/// The flags are combined with [`ItemFlags::with`], unset flags take the zero valued option,
+/// i.e. Data, Array, Absolute
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct ItemFlags(u16);
+
+impl ItemFlags {
+    pub const DATA: Self = Self(0);
+    pub const CONSTANT: Self = Self(1 << 0);
+    pub const ARRAY: Self = Self(0);
+    pub const VARIABLE: Self = Self(1 << 1);
+    pub const ABSOLUTE: Self = Self(0);
+    pub const RELATIVE: Self = Self(1 << 2);
+    pub const WRAP: Self = Self(1 << 3);
+    pub const NON_LINEAR: Self = Self(1 << 4);
+    pub const NO_PREFERRED: Self = Self(1 << 5);
+    pub const NULL_STATE: Self = Self(1 << 6);
+    pub const VOLATILE: Self = Self(1 << 7);
+    pub const BUFFERED_BYTES: Self = Self(1 << 8);
+
+    /// Data, Variable, Absolute - the flags of most non-array fields
+    pub const DATA_VARIABLE_ABSOLUTE: Self = Self::VARIABLE;
+    /// Constant, Variable, Absolute - the flags of padding fields
+    pub const PADDING: Self = Self::CONSTANT.with(Self::VARIABLE);
+
+    #[must_use]
+    pub const fn with(self, flags: Self) -> Self {
+        Self(self.0 | flags.0)
+    }
+
+    #[must_use]
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+}
+
+impl core::ops::BitOr for ItemFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.with(rhs)
+    }
+}
+
+/// Unit systems of a [`Unit`]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum UnitSystem {
+    None = 0x00,
+    /// Centimetre, gram, second, kelvin, ampere, candela
+    SiLinear = 0x01,
+    /// Radian, gram, second, kelvin, ampere, candela
+    SiRotation = 0x02,
+    /// Inch, slug, second, degree Fahrenheit, ampere, candela
+    EnglishLinear = 0x03,
+    /// Degree, slug, second, degree Fahrenheit, ampere, candela
+    EnglishRotation = 0x04,
+}
+
+/// Unit of a Unit item, a unit system and the exponent of each of its base units
+///
+/// The exponents are in the range -8 to 7, e.g. acceleration in cm/s² is
+/// `Unit::new(UnitSystem::SiLinear).length(1).time(-2)`
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Unit(u32);
+
+impl Unit {
+    pub const NONE: Self = Self(0);
+    pub const CENTIMETRE: Self = Self::new(UnitSystem::SiLinear).length(1);
+    pub const INCH: Self = Self::new(UnitSystem::EnglishLinear).length(1);
+    pub const RADIAN: Self = Self::new(UnitSystem::SiRotation).length(1);
+    pub const DEGREE: Self = Self::new(UnitSystem::EnglishRotation).length(1);
+    pub const GRAM: Self = Self::new(UnitSystem::SiLinear).mass(1);
+    pub const SECOND: Self = Self::new(UnitSystem::SiLinear).time(1);
+    pub const KELVIN: Self = Self::new(UnitSystem::SiLinear).temperature(1);
+    pub const AMPERE: Self = Self::new(UnitSystem::SiLinear).current(1);
+    pub const CANDELA: Self = Self::new(UnitSystem::SiLinear).luminous_intensity(1);
+    pub const CENTIMETRE_PER_SECOND: Self = Self::CENTIMETRE.time(-1);
+    pub const DEGREE_PER_SECOND: Self = Self::DEGREE.time(-1);
+
+    #[must_use]
+    pub const fn new(system: UnitSystem) -> Self {
+        Self(system as u32)
+    }
+
+    #[must_use]
+    pub const fn length(self, exponent: i8) -> Self {
+        self.with_exponent(1, exponent)
+    }
+
+    #[must_use]
+    pub const fn mass(self, exponent: i8) -> Self {
+        self.with_exponent(2, exponent)
+    }
+
+    #[must_use]
+    pub const fn time(self, exponent: i8) -> Self {
+        self.with_exponent(3, exponent)
+    }
+
+    #[must_use]
+    pub const fn temperature(self, exponent: i8) -> Self {
+        self.with_exponent(4, exponent)
+    }
+
+    #[must_use]
+    pub const fn current(self, exponent: i8) -> Self {
+        self.with_exponent(5, exponent)
+    }
+
+    #[must_use]
+    pub const fn luminous_intensity(self, exponent: i8) -> Self {
+        self.with_exponent(6, exponent)
+    }
+
+    #[must_use]
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    const fn with_exponent(self, nibble: u32, exponent: i8) -> Self {
+        core::assert!(
+            exponent >= -8 && exponent <= 7,
+            "unit exponent out of range"
+        );
+        let shift = nibble * 4;
+        Self((self.0 & !(0x0F << shift)) | ((exponent as u8 as u32 & 0x0F) << shift))
+    }
+}
+
+/// Report descriptor builder, holding up to `N` bytes of short items
+///
+/// Panics, at compile time if used in a const context, if the items exceed `N` bytes or a report
+/// ID is 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DescriptorBuilder<const N: usize = DESCRIPTOR_BUILDER_CAPACITY> {
+    descriptor: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Default for DescriptorBuilder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> DescriptorBuilder<N> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            descriptor: [0; N],
+            len: 0,
+        }
+    }
+
+    /// Length of the descriptor built so far
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The descriptor built so far
+    #[must_use]
+    pub fn descriptor(&self) -> &[u8] {
+        &self.descriptor[..self.len]
+    }
+
+    /// The descriptor as an array, `M` must be [`DescriptorBuilder::len`]
+    #[must_use]
+    pub const fn build<const M: usize>(&self) -> [u8; M] {
+        core::assert!(M == self.len, "descriptor length mismatch");
+        let mut descriptor = [0; M];
+        let mut i = 0;
+        while i < M {
+            descriptor[i] = self.descriptor[i];
+            i += 1;
+        }
+        descriptor
+    }
+
+    #[must_use]
+    pub const fn usage_page(self, page: u16) -> Self {
+        self.unsigned_item(GLOBAL_USAGE_PAGE, page as u32)
+    }
+
+    #[must_use]
+    pub const fn usage(self, usage: u16) -> Self {
+        self.unsigned_item(LOCAL_USAGE, usage as u32)
+    }
+
+    /// Usage on `page`, rather than the current usage page
+    #[must_use]
+    pub const fn extended_usage(self, page: u16, usage: u16) -> Self {
+        self.item(LOCAL_USAGE, ((page as u32) << 16) | usage as u32, 4)
+    }
+
+    #[must_use]
+    pub const fn usage_minimum(self, usage: u16) -> Self {
+        self.unsigned_item(LOCAL_USAGE_MINIMUM, usage as u32)
+    }
+
+    #[must_use]
+    pub const fn usage_maximum(self, usage: u16) -> Self {
+        self.unsigned_item(LOCAL_USAGE_MAXIMUM, usage as u32)
+    }
+
+    /// Physical descriptor of the next control
+    #[must_use]
+    pub const fn designator_index(self, index: u8) -> Self {
+        self.unsigned_item(LOCAL_DESIGNATOR_INDEX, index as u32)
+    }
+
+    #[must_use]
+    pub const fn designator_minimum(self, index: u8) -> Self {
+        self.unsigned_item(LOCAL_DESIGNATOR_MINIMUM, index as u32)
+    }
+
+    #[must_use]
+    pub const fn designator_maximum(self, index: u8) -> Self {
+        self.unsigned_item(LOCAL_DESIGNATOR_MAXIMUM, index as u32)
+    }
+
+    /// Name of the next control, `index` is the position of the name in the interface's
+    /// [`report_strings`](crate::interface::InterfaceBuilder::report_strings)
+    ///
+    /// The index is rewritten to the name's USB string descriptor index when the report
+    /// descriptor is sent to the host.
+    #[must_use]
+    pub const fn string_index(self, index: u8) -> Self {
+        self.unsigned_item(LOCAL_STRING_INDEX, index as u32)
+    }
+
+    /// First of a range of names, see [`string_index`](Self::string_index)
+    #[must_use]
+    pub const fn string_minimum(self, index: u8) -> Self {
+        self.unsigned_item(LOCAL_STRING_MINIMUM, index as u32)
+    }
+
+    /// Last of a range of names, see [`string_index`](Self::string_index)
+    #[must_use]
+    pub const fn string_maximum(self, index: u8) -> Self {
+        self.unsigned_item(LOCAL_STRING_MAXIMUM, index as u32)
+    }
+
+    #[must_use]
+    pub const fn logical_minimum(self, minimum: i32) -> Self {
+        self.signed_item(GLOBAL_LOGICAL_MINIMUM, minimum)
+    }
+
+    #[must_use]
+    pub const fn logical_maximum(self, maximum: i32) -> Self {
+        self.signed_item(GLOBAL_LOGICAL_MAXIMUM, maximum)
+    }
+
+    /// Physical Minimum, the value of Logical Minimum in [`DescriptorBuilder::unit`]s
+    #[must_use]
+    pub const fn physical_minimum(self, minimum: i32) -> Self {
+        self.signed_item(GLOBAL_PHYSICAL_MINIMUM, minimum)
+    }
+
+    /// Physical Maximum, the value of Logical Maximum in [`DescriptorBuilder::unit`]s
+    #[must_use]
+    pub const fn physical_maximum(self, maximum: i32) -> Self {
+        self.signed_item(GLOBAL_PHYSICAL_MAXIMUM, maximum)
+    }
+
+    #[must_use]
+    pub const fn unit(self, unit: Unit) -> Self {
+        self.unsigned_item(GLOBAL_UNIT, unit.bits())
+    }
+
+    /// Base 10 exponent of the physical values, from -8 to 7
+    #[allow(clippy::cast_sign_loss)]
+    #[must_use]
+    pub const fn unit_exponent(self, exponent: i8) -> Self {
+        core::assert!(
+            exponent >= -8 && exponent <= 7,
+            "unit exponent out of range"
+        );
+        self.unsigned_item(GLOBAL_UNIT_EXPONENT, exponent as u8 as u32 & 0x0F)
+    }
+
+    /// Report size in bits
+    #[must_use]
+    pub const fn report_size(self, bits: u8) -> Self {
+        self.unsigned_item(GLOBAL_REPORT_SIZE, bits as u32)
+    }
+
+    #[must_use]
+    pub const fn report_count(self, count: u16) -> Self {
+        self.unsigned_item(GLOBAL_REPORT_COUNT, count as u32)
+    }
+
+    /// Report ID, must not be 0
+    #[must_use]
+    pub const fn report_id(self, id: u8) -> Self {
+        core::assert!(id != 0, "report ID 0 is reserved");
+        self.unsigned_item(GLOBAL_REPORT_ID, id as u32)
+    }
+
+    #[must_use]
+    pub const fn collection(self, collection: Collection) -> Self {
+        self.item(MAIN_COLLECTION, collection as u32, 1)
+    }
+
+    #[must_use]
+    pub const fn end_collection(self) -> Self {
+        self.item(MAIN_END_COLLECTION, 0, 0)
+    }
+
+    #[must_use]
+    pub const fn input(self, flags: ItemFlags) -> Self {
+        self.unsigned_item(MAIN_INPUT, flags.bits() as u32)
+    }
+
+    #[must_use]
+    pub const fn output(self, flags: ItemFlags) -> Self {
+        self.unsigned_item(MAIN_OUTPUT, flags.bits() as u32)
+    }
+
+    #[must_use]
+    pub const fn feature(self, flags: ItemFlags) -> Self {
+        self.unsigned_item(MAIN_FEATURE, flags.bits() as u32)
+    }
+
+    /// Append raw descriptor bytes, such as a fragment of an existing descriptor
+    #[must_use]
+    pub const fn raw(mut self, bytes: &[u8]) -> Self {
+        core::assert!(
+            self.len + bytes.len() <= N,
+            "descriptor exceeds builder capacity"
+        );
+        let mut i = 0;
+        while i < bytes.len() {
+            self.descriptor[self.len] = bytes[i];
+            self.len += 1;
+            i += 1;
+        }
+        self
+    }
+
+    const fn unsigned_item(self, prefix: u8, value: u32) -> Self {
+        let size = if value <= 0xFF {
+            1
+        } else if value <= 0xFFFF {
+            2
+        } else {
+            4
+        };
+        self.item(prefix, value, size)
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    const fn signed_item(self, prefix: u8, value: i32) -> Self {
+        let size = if value >= i8::MIN as i32 && value <= i8::MAX as i32 {
+            1
+        } else if value >= i16::MIN as i32 && value <= i16::MAX as i32 {
+            2
+        } else {
+            4
+        };
+        self.item(prefix, value as u32, size)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    const fn item(self, prefix: u8, value: u32, size: usize) -> Self {
+        let bytes = value.to_le_bytes();
+        match size {
+            0 => self.raw(&[prefix]),
+            1 => self.raw(&[prefix | 0x01, bytes[0]]),
+            2 => self.raw(&[prefix | 0x02, bytes[0], bytes[1]]),
+            _ => self.raw(&[prefix | 0x03, bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::descriptor::builder::{Collection, DescriptorBuilder, ItemFlags, Unit, UnitSystem};
+    use crate::device::mouse::BOOT_MOUSE_REPORT_DESCRIPTOR;
+    use crate::device::wireless_radio::WIRELESS_RADIO_REPORT_DESCRIPTOR;
+
+    #[test]
+    fn builder_matches_wireless_radio_descriptor() {
+        const BUILDER: DescriptorBuilder = DescriptorBuilder::new()
+            .usage_page(0x01)
+            .usage(0x0C)
+            .collection(Collection::Application)
+            .logical_minimum(0)
+            .logical_maximum(1)
+            .usage(0xC6)
+            .report_count(1)
+            .report_size(1)
+            .input(ItemFlags::VARIABLE.with(ItemFlags::RELATIVE))
+            .report_size(7)
+            .input(ItemFlags::PADDING)
+            .usage(0xC7)
+            .report_size(1)
+            .output(ItemFlags::DATA_VARIABLE_ABSOLUTE)
+            .report_size(7)
+            .output(ItemFlags::PADDING)
+            .end_collection();
+        const DESCRIPTOR: [u8; BUILDER.len()] = BUILDER.build();
+
+        assert_eq!(DESCRIPTOR, WIRELESS_RADIO_REPORT_DESCRIPTOR);
+    }
+
+    #[test]
+    fn builder_matches_boot_mouse_descriptor() {
+        let builder = DescriptorBuilder::<64>::new()
+            .usage_page(0x01)
+            .usage(0x02)
+            .collection(Collection::Application)
+            .usage(0x01)
+            .collection(Collection::Physical)
+            .report_count(3)
+            .report_size(1)
+            .usage_page(0x09)
+            .usage_minimum(1)
+            .usage_maximum(3)
+            .logical_minimum(0)
+            .logical_maximum(1)
+            .input(ItemFlags::DATA_VARIABLE_ABSOLUTE)
+            .report_count(1)
+            .report_size(5)
+            .input(ItemFlags::CONSTANT)
+            .report_size(8)
+            .report_count(2)
+            .usage_page(0x01)
+            .usage(0x30)
+            .usage(0x31)
+            .logical_minimum(-127)
+            .logical_maximum(127)
+            .input(ItemFlags::VARIABLE | ItemFlags::RELATIVE)
+            .end_collection()
+            .end_collection();
+
+        assert_eq!(builder.descriptor(), BOOT_MOUSE_REPORT_DESCRIPTOR);
+    }
+
+    #[test]
+    fn builder_uses_shortest_item_encoding() {
+        let builder = DescriptorBuilder::<32>::new()
+            .logical_minimum(-32768)
+            .logical_maximum(65535)
+            .usage(0x0238)
+            .extended_usage(0x0C, 0x0238)
+            .report_count(512)
+            .feature(ItemFlags::VARIABLE.with(ItemFlags::BUFFERED_BYTES));
+
+        assert_eq!(
+            builder.descriptor(),
+            [
+                0x16, 0x00, 0x80, 0x27, 0xFF, 0xFF, 0x00, 0x00, 0x0A, 0x38, 0x02, 0x0B, 0x38, 0x02,
+                0x0C, 0x00, 0x96, 0x00, 0x02, 0xB2, 0x02, 0x01
+            ]
+        );
+    }
+
+    #[test]
+    fn builder_encodes_physical_range_and_units() {
+        let builder = DescriptorBuilder::<32>::new()
+            .logical_minimum(0)
+            .logical_maximum(7)
+            .physical_minimum(0)
+            .physical_maximum(315)
+            .unit(Unit::DEGREE)
+            .unit_exponent(-3)
+            .unit(Unit::new(UnitSystem::SiLinear).length(1).time(-2))
+            .unit(Unit::GRAM)
+            .unit(Unit::NONE);
+
+        #[rustfmt::skip]
+        assert_eq!(
+            builder.descriptor(),
+            [
+                0x15, 0x00,             // Logical Minimum (0),
+                0x25, 0x07,             // Logical Maximum (7),
+                0x35, 0x00,             // Physical Minimum (0),
+                0x46, 0x3B, 0x01,       // Physical Maximum (315),
+                0x65, 0x14,             // Unit (Degrees),
+                0x55, 0x0D,             // Unit Exponent (-3),
+                0x66, 0x11, 0xE0,       // Unit (cm/s²),
+                0x66, 0x01, 0x01,       // Unit (Grams),
+                0x65, 0x00,             // Unit (None),
+            ]
+        );
+    }
+}
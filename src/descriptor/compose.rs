@@ -0,0 +1,257 @@
+//! Runtime report descriptor composition
+//!
+//! [`DescriptorComposer`] merges the report descriptors of several devices into the descriptor of
+//! a single interface, assigning each report a new, unique report ID. The report IDs assigned to
+//! each descriptor are returned as a [`ReportIdMap`], so reports can be written with their new
+//! report ID:
+//!
+//! ```rust
+//! use usbd_human_interface_device::descriptor::compose::DescriptorComposer;
+//! use usbd_human_interface_device::device::consumer::MULTIPLE_CODE_REPORT_DESCRIPTOR;
+//! use usbd_human_interface_device::device::keyboard::BOOT_KEYBOARD_REPORT_DESCRIPTOR;
+//! use usbd_human_interface_device::device::mouse::WHEEL_MOUSE_REPORT_DESCRIPTOR;
+//!
+//! let mut composer = DescriptorComposer::<256>::new();
+//! let keyboard = composer.push(BOOT_KEYBOARD_REPORT_DESCRIPTOR).unwrap();
+//! let consumer = composer.push(MULTIPLE_CODE_REPORT_DESCRIPTOR).unwrap();
+//! let mouse = composer.push(WHEEL_MOUSE_REPORT_DESCRIPTOR).unwrap();
+//!
+//! assert_eq!(keyboard.report_id(), 1);
+//! assert_eq!(consumer.report_id(), 2);
+//! assert_eq!(mouse.report_id(), 3);
+//! ```
+//!
+//! Reports of a composed descriptor always start with their report ID, so a composed boot
+//! keyboard or mouse is no longer boot compatible. Descriptors of more than 128 bytes must be
+//! `'static` to be passed to [`InterfaceBuilder`](crate::interface::InterfaceBuilder).
+
+use heapless::Vec;
+
+use crate::descriptor::optimize::optimize;
+use crate::descriptor::parse::{
+    parse, read_item, ParseError, ParseErrorKind, LONG_ITEM, REPORT_ID_ITEM,
+};
+
+/// Maximum number of report IDs in a single descriptor pushed to a [`DescriptorComposer`]
+pub const COMPOSE_MAX_REPORTS: usize = 8;
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComposeError {
+    /// The pushed descriptor isn't well formed
+    InvalidDescriptor(ParseError),
+    /// All 255 report IDs have been assigned, or the pushed descriptor has more than
+    /// [`COMPOSE_MAX_REPORTS`] report IDs
+    TooManyReports,
+    /// The composed descriptor doesn't fit in the composer
+    BufferOverflow,
+}
+
+impl From<ParseError> for ComposeError {
+    fn from(e: ParseError) -> Self {
+        Self::InvalidDescriptor(e)
+    }
+}
+
+/// Report IDs assigned to a descriptor pushed to a [`DescriptorComposer`]
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct ReportIdMap {
+    ids: Vec<(u8, u8), COMPOSE_MAX_REPORTS>,
+}
+
+impl ReportIdMap {
+    /// Report ID assigned to the descriptor's `report_id`, 0 for a descriptor without report IDs
+    #[must_use]
+    pub fn get(&self, report_id: u8) -> Option<u8> {
+        self.ids
+            .iter()
+            .find(|(original, _)| *original == report_id)
+            .map(|(_, assigned)| *assigned)
+    }
+
+    /// First report ID assigned to the descriptor, the only one for a descriptor without report
+    /// IDs
+    #[must_use]
+    pub fn report_id(&self) -> u8 {
+        self.ids.first().map_or(0, |(_, assigned)| *assigned)
+    }
+
+    /// Original and assigned report ID pairs
+    pub fn iter(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        self.ids.iter().copied()
+    }
+}
+
+/// Composes a report descriptor of up to `N` bytes from the descriptors of several devices
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DescriptorComposer<const N: usize> {
+    descriptor: Vec<u8, N>,
+    next_report_id: u8,
+}
+
+impl<const N: usize> Default for DescriptorComposer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> DescriptorComposer<N> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            descriptor: Vec::new(),
+            next_report_id: 1,
+        }
+    }
+
+    /// The composed descriptor
+    #[must_use]
+    pub fn descriptor(&self) -> &[u8] {
+        &self.descriptor
+    }
+
+    /// Append `descriptor`, assigning new report IDs to its reports
+    ///
+    /// A descriptor without report IDs is assigned a single report ID. On error the composed
+    /// descriptor is left unchanged.
+    pub fn push(&mut self, descriptor: &[u8]) -> Result<ReportIdMap, ComposeError> {
+        let reports = parse(descriptor)?;
+
+        let mut map = ReportIdMap::default();
+        let mut next_report_id = self.next_report_id;
+        for report in reports.iter() {
+            let assigned = (next_report_id != 0)
+                .then_some(next_report_id)
+                .ok_or(ComposeError::TooManyReports)?;
+            map.ids
+                .push((report.report_id, assigned))
+                .map_err(|_| ComposeError::TooManyReports)?;
+            next_report_id = next_report_id.wrapping_add(1);
+        }
+
+        let len = self.descriptor.len();
+        let result = if reports.uses_report_ids() {
+            self.extend_rewriting_report_ids(descriptor, &map)
+        } else {
+            self.descriptor
+                .extend_from_slice(&[REPORT_ID_ITEM | 0x01, map.report_id()])
+                .and_then(|()| self.descriptor.extend_from_slice(descriptor))
+                .map_err(|()| ComposeError::BufferOverflow)
+        };
+
+        match result {
+            Ok(()) => {
+                self.next_report_id = next_report_id;
+                Ok(map)
+            }
+            Err(e) => {
+                self.descriptor.truncate(len);
+                Err(e)
+            }
+        }
+    }
+
+    /// Shrink the composed descriptor with [`optimize`], removing the global items repeated by
+    /// each pushed descriptor
+    ///
+    /// More descriptors can be pushed afterwards.
+    pub fn optimize(&mut self) -> Result<(), ComposeError> {
+        let len = optimize(&mut self.descriptor)?;
+        self.descriptor.truncate(len);
+        Ok(())
+    }
+
+    fn extend_rewriting_report_ids(
+        &mut self,
+        descriptor: &[u8],
+        map: &ReportIdMap,
+    ) -> Result<(), ComposeError> {
+        let mut offset = 0;
+        while offset < descriptor.len() {
+            let item = read_item(descriptor, offset).map_err(|kind| ParseError { offset, kind })?;
+            let start = self.descriptor.len();
+            self.descriptor
+                .extend_from_slice(&descriptor[offset..offset + item.len])
+                .map_err(|()| ComposeError::BufferOverflow)?;
+
+            if item.prefix != LONG_ITEM && item.prefix & 0xFC == REPORT_ID_ITEM {
+                // Report IDs in use are known from parsing the descriptor
+                let report_id = u8::try_from(item.value)
+                    .ok()
+                    .and_then(|id| map.get(id))
+                    .ok_or(ParseError {
+                        offset,
+                        kind: ParseErrorKind::ReservedReportId,
+                    })?;
+                let data = &mut self.descriptor[start + 1..start + item.len];
+                data.fill(0);
+                data[0] = report_id;
+            }
+            offset += item.len;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use crate::descriptor::compose::{ComposeError, DescriptorComposer};
+    use crate::descriptor::parse::parse;
+    use crate::device::keyboard::BOOT_KEYBOARD_REPORT_DESCRIPTOR;
+    use crate::device::keyboard_pointer::KEYBOARD_POINTER_REPORT_DESCRIPTOR;
+    use crate::device::mouse::BOOT_MOUSE_REPORT_DESCRIPTOR;
+
+    #[test]
+    fn composed_descriptor_assigns_unique_report_ids() {
+        let mut composer = DescriptorComposer::<512>::new();
+
+        let keyboard = composer.push(BOOT_KEYBOARD_REPORT_DESCRIPTOR).unwrap();
+        let keyboard_pointer = composer.push(KEYBOARD_POINTER_REPORT_DESCRIPTOR).unwrap();
+        let mouse = composer.push(BOOT_MOUSE_REPORT_DESCRIPTOR).unwrap();
+
+        assert_eq!(keyboard.get(0), Some(1));
+        assert_eq!(
+            keyboard_pointer.iter().collect::<std::vec::Vec<_>>(),
+            [(1, 2), (2, 3)]
+        );
+        assert_eq!(mouse.report_id(), 4);
+
+        let reports = parse(composer.descriptor()).unwrap();
+        let sizes = reports
+            .iter()
+            .map(|r| (r.report_id, r.input_len(), r.output_len()))
+            .collect::<std::vec::Vec<_>>();
+        assert_eq!(sizes, [(1, 8, 1), (2, 8, 1), (3, 5, 0), (4, 3, 0)]);
+        assert_eq!(
+            &composer.descriptor()[..4],
+            &[
+                0x85,
+                0x01,
+                BOOT_KEYBOARD_REPORT_DESCRIPTOR[0],
+                BOOT_KEYBOARD_REPORT_DESCRIPTOR[1]
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_push_leaves_descriptor_unchanged() {
+        let mut composer = DescriptorComposer::<110>::new();
+        composer.push(BOOT_MOUSE_REPORT_DESCRIPTOR).unwrap();
+        let descriptor = std::vec::Vec::from(composer.descriptor());
+
+        assert_eq!(
+            composer.push(BOOT_KEYBOARD_REPORT_DESCRIPTOR),
+            Err(ComposeError::BufferOverflow)
+        );
+        assert_eq!(composer.descriptor(), descriptor);
+        assert_eq!(
+            composer
+                .push(BOOT_MOUSE_REPORT_DESCRIPTOR)
+                .unwrap()
+                .report_id(),
+            2
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! Report descriptor decoding for snapshot tests
+//!
+//! [`decode`] renders a report descriptor as normalized text, one item per line and indented by
+//! collection, so changes to a device's descriptor show as a readable diff against a snapshot:
+//!
+//! ```rust
+//! use usbd_human_interface_device::descriptor::decode::decode;
+//! use usbd_human_interface_device::device::mouse::BOOT_MOUSE_REPORT_DESCRIPTOR;
+//!
+//! let text = decode(BOOT_MOUSE_REPORT_DESCRIPTOR).to_string();
+//! assert!(text.starts_with("Usage Page (GenericDesktop)\nUsage (Mouse)\n"));
+//! ```
+//!
+//! Usages are named from the [`page`](crate::page) enums where the page has one, other values
+//! are written in hex.
+
+use core::fmt::{self, Debug, Display, Formatter};
+
+use crate::descriptor::parse::{read_item, LONG_ITEM};
+use crate::page::{
+    Consumer, Desktop, Digitizer, Game, Keyboard, Leds, Sensor, Simulation, Telephony, UsagePage,
+};
+
+/// Decode `descriptor`, rendered by its [`Display`] implementation
+#[must_use]
+pub fn decode(descriptor: &[u8]) -> Decoded<'_> {
+    Decoded { descriptor }
+}
+
+/// Report descriptor rendered as text by [`Display`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Decoded<'a> {
+    descriptor: &'a [u8],
+}
+
+/// The enum variant for `value`, if it has one rather than mapping to the default variant
+fn variant<E, T>(value: u16) -> Option<E>
+where
+    E: From<T> + Into<T> + Copy,
+    T: TryFrom<u16> + PartialEq + Copy,
+{
+    let value = T::try_from(value).ok()?;
+    let variant = E::from(value);
+    (variant.into() == value).then_some(variant)
+}
+
+fn write_named<E: Debug>(f: &mut Formatter<'_>, name: Option<E>, value: u16) -> fmt::Result {
+    match name {
+        Some(name) => write!(f, "{name:?}"),
+        None => write!(f, "0x{value:02X}"),
+    }
+}
+
+fn write_usage_page(f: &mut Formatter<'_>, page: u16) -> fmt::Result {
+    write_named(f, variant::<UsagePage, u16>(page), page)
+}
+
+fn write_usage(f: &mut Formatter<'_>, page: u16, usage: u16) -> fmt::Result {
+    match page {
+        0x01 => write_named(f, variant::<Desktop, u8>(usage), usage),
+        0x02 => write_named(f, variant::<Simulation, u8>(usage), usage),
+        0x05 => write_named(f, variant::<Game, u8>(usage), usage),
+        0x07 => write_named(f, variant::<Keyboard, u8>(usage), usage),
+        0x08 => write_named(f, variant::<Leds, u8>(usage), usage),
+        0x09 => write!(f, "Button {usage}"),
+        0x0B => write_named(f, variant::<Telephony, u8>(usage), usage),
+        0x0C => write_named(f, variant::<Consumer, u16>(usage), usage),
+        0x0D => write_named(f, variant::<Digitizer, u8>(usage), usage),
+        0x20 => write_named(f, variant::<Sensor, u16>(usage), usage),
+        _ => write!(f, "0x{usage:02X}"),
+    }
+}
+
+fn write_main_flags(f: &mut Formatter<'_>, value: u32, input: bool) -> fmt::Result {
+    let flag = |bit: u32| value & (1 << bit) != 0;
+    f.write_str(if flag(0) { "Constant" } else { "Data" })?;
+    f.write_str(if flag(1) { ", Variable" } else { ", Array" })?;
+    f.write_str(if flag(2) { ", Relative" } else { ", Absolute" })?;
+    for (bit, name) in [
+        (3, ", Wrap"),
+        (4, ", Non Linear"),
+        (5, ", No Preferred"),
+        (6, ", Null State"),
+        (8, ", Buffered Bytes"),
+    ] {
+        if flag(bit) {
+            f.write_str(name)?;
+        }
+    }
+    if !input && flag(7) {
+        f.write_str(", Volatile")?;
+    }
+    Ok(())
+}
+
+#[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
+fn signed(value: u32, size: usize) -> i32 {
+    match size {
+        1 => i32::from(value as u8 as i8),
+        2 => i32::from(value as u16 as i16),
+        _ => value as i32,
+    }
+}
+
+/// Global state needed to render items
+#[derive(Default)]
+struct State {
+    depth: usize,
+    usage_page: u16,
+    logical_minimum_negative: bool,
+    physical_minimum_negative: bool,
+}
+
+impl State {
+    #[allow(clippy::cast_possible_truncation)]
+    fn write_item(
+        &mut self,
+        f: &mut Formatter<'_>,
+        prefix: u8,
+        value: u32,
+        size: usize,
+    ) -> fmt::Result {
+        let item_type = (prefix >> 2) & 0x03;
+        let tag = prefix >> 4;
+
+        match (item_type, tag) {
+            (0, 0x8) => {
+                f.write_str("Input (")?;
+                write_main_flags(f, value, true)?;
+                f.write_str(")")
+            }
+            (0, 0x9 | 0xB) => {
+                f.write_str(if tag == 0x9 { "Output (" } else { "Feature (" })?;
+                write_main_flags(f, value, false)?;
+                f.write_str(")")
+            }
+            (0, 0xA) => {
+                self.depth += 1;
+                match value {
+                    0x00 => f.write_str("Collection (Physical)"),
+                    0x01 => f.write_str("Collection (Application)"),
+                    0x02 => f.write_str("Collection (Logical)"),
+                    0x03 => f.write_str("Collection (Report)"),
+                    0x04 => f.write_str("Collection (Named Array)"),
+                    0x05 => f.write_str("Collection (Usage Switch)"),
+                    0x06 => f.write_str("Collection (Usage Modifier)"),
+                    _ => write!(f, "Collection (0x{value:02X})"),
+                }
+            }
+            (0, 0xC) => f.write_str("End Collection"),
+            (1, 0x0) => {
+                self.usage_page = value as u16;
+                f.write_str("Usage Page (")?;
+                write_usage_page(f, self.usage_page)?;
+                f.write_str(")")
+            }
+            (1, 0x1) => {
+                let minimum = signed(value, size);
+                self.logical_minimum_negative = minimum < 0;
+                write!(f, "Logical Minimum ({minimum})")
+            }
+            (1, 0x2) if self.logical_minimum_negative => {
+                write!(f, "Logical Maximum ({})", signed(value, size))
+            }
+            (1, 0x2) => write!(f, "Logical Maximum ({value})"),
+            (1, 0x3) => {
+                let minimum = signed(value, size);
+                self.physical_minimum_negative = minimum < 0;
+                write!(f, "Physical Minimum ({minimum})")
+            }
+            (1, 0x4) if self.physical_minimum_negative => {
+                write!(f, "Physical Maximum ({})", signed(value, size))
+            }
+            (1, 0x4) => write!(f, "Physical Maximum ({value})"),
+            (1, 0x5) => write!(f, "Unit Exponent ({})", signed(value << 28, 4) >> 28),
+            (1, 0x6) => write!(f, "Unit (0x{value:02X})"),
+            (1, 0x7) => write!(f, "Report Size ({value})"),
+            (1, 0x8) => write!(f, "Report ID ({value})"),
+            (1, 0x9) => write!(f, "Report Count ({value})"),
+            (1, 0xA) => f.write_str("Push"),
+            (1, 0xB) => f.write_str("Pop"),
+            (2, 0x0..=0x2) => {
+                f.write_str(match tag {
+                    0x0 => "Usage (",
+                    0x1 => "Usage Minimum (",
+                    _ => "Usage Maximum (",
+                })?;
+                if size == 4 {
+                    // Extended usage, with its own usage page
+                    let page = (value >> 16) as u16;
+                    write_usage_page(f, page)?;
+                    f.write_str(": ")?;
+                    write_usage(f, page, value as u16)?;
+                } else {
+                    write_usage(f, self.usage_page, value as u16)?;
+                }
+                f.write_str(")")
+            }
+            (2, 0x3) => write!(f, "Designator Index ({value})"),
+            (2, 0x4) => write!(f, "Designator Minimum ({value})"),
+            (2, 0x5) => write!(f, "Designator Maximum ({value})"),
+            (2, 0x7) => write!(f, "String Index ({value})"),
+            (2, 0x8) => write!(f, "String Minimum ({value})"),
+            (2, 0x9) => write!(f, "String Maximum ({value})"),
+            (2, 0xA) => write!(f, "Delimiter ({value})"),
+            _ => write!(f, "Reserved (0x{prefix:02X}, 0x{value:02X})"),
+        }
+    }
+}
+
+impl Display for Decoded<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut state = State::default();
+        let mut offset = 0;
+
+        while offset < self.descriptor.len() {
+            let Ok(item) = read_item(self.descriptor, offset) else {
+                return writeln!(f, "Truncated item at offset {offset}");
+            };
+            // End Collection is written at the depth of its Collection
+            if item.prefix & 0xFC == 0xC0 {
+                state.depth = state.depth.saturating_sub(1);
+            }
+            for _ in 0..state.depth {
+                f.write_str("  ")?;
+            }
+            if item.prefix == LONG_ITEM {
+                write!(f, "Long Item ({} bytes)", item.len)?;
+            } else {
+                state.write_item(f, item.prefix, item.value, item.size)?;
+            }
+            f.write_str("\n")?;
+            offset += item.len;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::string::ToString;
+
+    use crate::descriptor::decode::decode;
+    use crate::device::consumer::MULTIPLE_CODE_REPORT_DESCRIPTOR;
+    use crate::device::mouse::BOOT_MOUSE_REPORT_DESCRIPTOR;
+
+    #[test]
+    fn decode_boot_mouse_descriptor() {
+        let expected = "\
+Usage Page (GenericDesktop)
+Usage (Mouse)
+Collection (Application)
+  Usage (Pointer)
+  Collection (Physical)
+    Report Count (3)
+    Report Size (1)
+    Usage Page (Button)
+    Usage Minimum (Button 1)
+    Usage Maximum (Button 3)
+    Logical Minimum (0)
+    Logical Maximum (1)
+    Input (Data, Variable, Absolute)
+    Report Count (1)
+    Report Size (5)
+    Input (Constant, Array, Absolute)
+    Report Size (8)
+    Report Count (2)
+    Usage Page (GenericDesktop)
+    Usage (X)
+    Usage (Y)
+    Logical Minimum (-127)
+    Logical Maximum (127)
+    Input (Data, Variable, Relative)
+  End Collection
+End Collection
+";
+
+        assert_eq!(decode(BOOT_MOUSE_REPORT_DESCRIPTOR).to_string(), expected);
+    }
+
+    #[test]
+    fn decode_names_usages_and_reports_truncation() {
+        let text = decode(MULTIPLE_CODE_REPORT_DESCRIPTOR).to_string();
+        assert!(text.starts_with("Usage Page (Consumer)\nUsage (ConsumerControl)\n"));
+
+        assert_eq!(
+            decode(&[0x05, 0x0C, 0x0A, 0x38, 0x02, 0x0B, 0x30, 0x00, 0x01, 0x00, 0x26, 0xFF])
+                .to_string(),
+            "Usage Page (Consumer)\nUsage (ACPan)\nUsage (GenericDesktop: X)\n\
+             Truncated item at offset 10\n"
+        );
+    }
+}
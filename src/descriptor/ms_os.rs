@@ -0,0 +1,300 @@
+//! Microsoft OS 2.0 descriptors, letting Windows bind WinUSB to an interface, such as a vendor
+//! configuration channel, or register device interface GUIDs for it without an INF file
+//!
+//! Interfaces are configured with an [`MsOs20Function`] by
+//! [`InterfaceConfig::with_ms_os_20`](crate::interface::InterfaceConfig::with_ms_os_20), and the
+//! descriptor set is served by the class once enabled with
+//! [`UsbHidClassBuilder::ms_os_20_descriptors`](crate::usb_class::UsbHidClassBuilder::ms_os_20_descriptors).
+//! Windows only reads the BOS descriptor announcing the set from devices built with
+//! `UsbDeviceBuilder::usb_rev(UsbRev::Usb210)`.
+
+use usb_device::UsbError;
+
+/// `wIndex` of the vendor request reading the descriptor set
+pub const MS_OS_20_DESCRIPTOR_INDEX: u16 = 7;
+/// Windows 8.1, the first release reading MS OS 2.0 descriptors
+pub const MS_OS_20_WINDOWS_VERSION: u32 = 0x0603_0000;
+/// Length of a device interface GUID string, such as `{D8DD60DF-4589-4CC7-9CD2-659D9E648A9F}`
+pub const GUID_STRING_LEN: usize = 38;
+
+/// `bDevCapabilityType` of a platform capability descriptor
+pub(crate) const PLATFORM_CAPABILITY: u8 = 0x05;
+/// Length of the MS OS 2.0 platform capability, after its `bDevCapabilityType`
+pub(crate) const PLATFORM_CAPABILITY_LEN: usize = 25;
+/// `{D8DD60DF-4589-4CC7-9CD2-659D9E648A9F}`
+const PLATFORM_CAPABILITY_UUID: [u8; 16] = [
+    0xDF, 0x60, 0xDD, 0xD8, 0x89, 0x45, 0xC7, 0x4C, 0x9C, 0xD2, 0x65, 0x9D, 0x9E, 0x64, 0x8A, 0x9F,
+];
+
+const SET_HEADER_DESCRIPTOR: u16 = 0x00;
+const SUBSET_HEADER_CONFIGURATION: u16 = 0x01;
+const SUBSET_HEADER_FUNCTION: u16 = 0x02;
+const FEATURE_COMPATIBLE_ID: u16 = 0x03;
+const FEATURE_REG_PROPERTY: u16 = 0x04;
+const REG_MULTI_SZ: u16 = 7;
+
+const SET_HEADER_LEN: usize = 10;
+const SUBSET_HEADER_LEN: usize = 8;
+const COMPATIBLE_ID_LEN: usize = 20;
+const DEVICE_INTERFACE_GUIDS: &str = "DeviceInterfaceGUIDs";
+/// Length of the registry property, with the null terminated UTF-16 name and the GUID as a
+/// `REG_MULTI_SZ` of one string
+const DEVICE_INTERFACE_GUIDS_LEN: usize =
+    10 + (DEVICE_INTERFACE_GUIDS.len() + 1) * 2 + (GUID_STRING_LEN + 2) * 2;
+
+/// Windows features of an interface, written to the MS OS 2.0 descriptor set
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MsOs20Function<'a> {
+    winusb: bool,
+    device_interface_guid: Option<&'a str>,
+}
+
+impl<'a> MsOs20Function<'a> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            winusb: false,
+            device_interface_guid: None,
+        }
+    }
+
+    /// Bind WinUSB to the interface with the `WINUSB` compatible ID, replacing the Windows HID
+    /// driver, so applications access its endpoints with WinUSB rather than as a HID
+    #[must_use]
+    pub const fn with_winusb(mut self) -> Self {
+        self.winusb = true;
+        self
+    }
+
+    /// Register the interface with the device interface GUID `guid`, such as
+    /// `{D8DD60DF-4589-4CC7-9CD2-659D9E648A9F}`, for applications to find it by
+    ///
+    /// # Panics
+    ///
+    /// Panics if `guid` isn't [`GUID_STRING_LEN`] characters long
+    #[must_use]
+    pub const fn with_device_interface_guid(mut self, guid: &'a str) -> Self {
+        // `core::assert!` rather than the crate macro, the defmt assert is not usable in const fns
+        core::assert!(
+            guid.len() == GUID_STRING_LEN,
+            "device interface GUID length mismatch"
+        );
+        self.device_interface_guid = Some(guid);
+        self
+    }
+
+    #[must_use]
+    pub fn winusb(&self) -> bool {
+        self.winusb
+    }
+
+    #[must_use]
+    pub fn device_interface_guid(&self) -> Option<&'a str> {
+        self.device_interface_guid
+    }
+
+    /// Length of the function's descriptors, with a function subset header if `composite`
+    pub(crate) fn len(&self, composite: bool) -> usize {
+        let mut len = if composite { SUBSET_HEADER_LEN } else { 0 };
+        if self.winusb {
+            len += COMPATIBLE_ID_LEN;
+        }
+        if self.device_interface_guid.is_some() {
+            len += DEVICE_INTERFACE_GUIDS_LEN;
+        }
+        len
+    }
+}
+
+/// Length of a descriptor set of functions with a total length of `functions_len`
+pub(crate) fn descriptor_set_len(functions_len: usize, composite: bool) -> usize {
+    SET_HEADER_LEN + if composite { SUBSET_HEADER_LEN } else { 0 } + functions_len
+}
+
+/// Body of the MS OS 2.0 platform capability descriptor for a descriptor set of `set_len` bytes
+/// read with the vendor request `vendor_code`
+pub(crate) fn platform_capability(set_len: u16, vendor_code: u8) -> [u8; PLATFORM_CAPABILITY_LEN] {
+    let mut capability = [0; PLATFORM_CAPABILITY_LEN];
+    capability[1..17].copy_from_slice(&PLATFORM_CAPABILITY_UUID);
+    capability[17..21].copy_from_slice(&MS_OS_20_WINDOWS_VERSION.to_le_bytes());
+    capability[21..23].copy_from_slice(&set_len.to_le_bytes());
+    capability[23] = vendor_code;
+    capability
+}
+
+/// Writer of an MS OS 2.0 descriptor set
+///
+/// Composite devices describe each interface in a function subset. Windows ignores function
+/// subsets of devices with a single interface, which are described by the set as a whole.
+pub(crate) struct DescriptorSetWriter<'b> {
+    buffer: &'b mut [u8],
+    position: usize,
+    composite: bool,
+}
+
+impl<'b> DescriptorSetWriter<'b> {
+    /// Start a descriptor set of `set_len` bytes, see [`descriptor_set_len`]
+    pub(crate) fn new(
+        buffer: &'b mut [u8],
+        set_len: u16,
+        composite: bool,
+    ) -> usb_device::Result<Self> {
+        let mut writer = Self {
+            buffer,
+            position: 0,
+            composite,
+        };
+        writer.header(SET_HEADER_LEN, SET_HEADER_DESCRIPTOR)?;
+        writer.write(&MS_OS_20_WINDOWS_VERSION.to_le_bytes())?;
+        writer.write_u16(set_len)?;
+        if composite {
+            writer.header(SUBSET_HEADER_LEN, SUBSET_HEADER_CONFIGURATION)?;
+            // The configuration index, rather than its bConfigurationValue
+            writer.write(&[0, 0])?;
+            #[allow(clippy::cast_possible_truncation)]
+            writer.write_u16(set_len - SET_HEADER_LEN as u16)?;
+        }
+        Ok(writer)
+    }
+
+    /// Write the descriptors of `function` for the interface `interface_number`
+    pub(crate) fn function(
+        &mut self,
+        interface_number: u8,
+        function: &MsOs20Function,
+    ) -> usb_device::Result<()> {
+        if self.composite {
+            self.header(SUBSET_HEADER_LEN, SUBSET_HEADER_FUNCTION)?;
+            self.write(&[interface_number, 0])?;
+            #[allow(clippy::cast_possible_truncation)]
+            self.write_u16(function.len(true) as u16)?;
+        }
+        if function.winusb {
+            self.header(COMPATIBLE_ID_LEN, FEATURE_COMPATIBLE_ID)?;
+            self.write(b"WINUSB\0\0")?;
+            self.write(&[0; 8])?;
+        }
+        if let Some(guid) = function.device_interface_guid {
+            self.header(DEVICE_INTERFACE_GUIDS_LEN, FEATURE_REG_PROPERTY)?;
+            self.write_u16(REG_MULTI_SZ)?;
+            #[allow(clippy::cast_possible_truncation)]
+            self.write_u16(((DEVICE_INTERFACE_GUIDS.len() + 1) * 2) as u16)?;
+            self.write_utf16(DEVICE_INTERFACE_GUIDS, 1)?;
+            #[allow(clippy::cast_possible_truncation)]
+            self.write_u16(((GUID_STRING_LEN + 2) * 2) as u16)?;
+            self.write_utf16(guid, 2)?;
+        }
+        Ok(())
+    }
+
+    /// Number of bytes written
+    pub(crate) fn position(&self) -> usize {
+        self.position
+    }
+
+    fn header(&mut self, len: usize, descriptor_type: u16) -> usb_device::Result<()> {
+        #[allow(clippy::cast_possible_truncation)]
+        self.write_u16(len as u16)?;
+        self.write_u16(descriptor_type)
+    }
+
+    fn write_u16(&mut self, value: u16) -> usb_device::Result<()> {
+        self.write(&value.to_le_bytes())
+    }
+
+    /// Write the ASCII string `s` as UTF-16, followed by `terminators` null characters
+    fn write_utf16(&mut self, s: &str, terminators: usize) -> usb_device::Result<()> {
+        for c in s.bytes() {
+            self.write_u16(u16::from(c))?;
+        }
+        for _ in 0..terminators {
+            self.write_u16(0)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        let end = self.position + data.len();
+        self.buffer
+            .get_mut(self.position..end)
+            .ok_or(UsbError::BufferOverflow)?
+            .copy_from_slice(data);
+        self.position = end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    const GUID: &str = "{D8DD60DF-4589-4CC7-9CD2-659D9E648A9F}";
+
+    #[test]
+    fn composite_descriptor_set() {
+        let function = MsOs20Function::new()
+            .with_winusb()
+            .with_device_interface_guid(GUID);
+        let set_len = descriptor_set_len(function.len(true), true);
+        assert_eq!(set_len, 10 + 8 + 8 + 20 + 132);
+
+        let mut buffer = [0; 256];
+        let mut writer =
+            DescriptorSetWriter::new(&mut buffer, u16::try_from(set_len).unwrap(), true).unwrap();
+        writer.function(2, &function).unwrap();
+        assert_eq!(writer.position(), set_len);
+
+        assert_eq!(
+            buffer[..46],
+            [
+                // Set header
+                0x0A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x06, 0xB2, 0x00,
+                // Configuration subset header
+                0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0xA8, 0x00, // Function subset header
+                0x08, 0x00, 0x02, 0x00, 0x02, 0x00, 0xA0, 0x00, // Compatible ID
+                0x14, 0x00, 0x03, 0x00, b'W', b'I', b'N', b'U', b'S', b'B', 0, 0, 0, 0, 0, 0, 0, 0,
+                0, 0,
+            ]
+        );
+        // Registry property
+        assert_eq!(
+            buffer[46..54],
+            [0x84, 0x00, 0x04, 0x00, 0x07, 0x00, 0x2A, 0x00]
+        );
+        assert_eq!(buffer[54..56], [b'D', 0]);
+        assert_eq!(buffer[94..100], [0x00, 0x00, 0x50, 0x00, b'{', 0]);
+        assert_eq!(buffer[172..178], [b'}', 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn single_interface_descriptor_set() {
+        let function = MsOs20Function::new().with_winusb();
+        let set_len = descriptor_set_len(function.len(false), false);
+
+        let mut buffer = [0; 64];
+        let mut writer =
+            DescriptorSetWriter::new(&mut buffer, u16::try_from(set_len).unwrap(), false).unwrap();
+        writer.function(0, &function).unwrap();
+        assert_eq!(writer.position(), 30);
+        assert_eq!(buffer[..4], [0x0A, 0x00, 0x00, 0x00]);
+        assert_eq!(buffer[8..14], [0x1E, 0x00, 0x14, 0x00, 0x03, 0x00]);
+
+        let mut buffer = [0; 16];
+        let mut writer = DescriptorSetWriter::new(&mut buffer, 30, false).unwrap();
+        assert_eq!(writer.function(0, &function), Err(UsbError::BufferOverflow));
+    }
+
+    #[test]
+    fn platform_capability_announces_the_descriptor_set() {
+        let capability = platform_capability(0xB2, 0x21);
+        assert_eq!(capability[0], 0);
+        assert_eq!(capability[1..17], PLATFORM_CAPABILITY_UUID);
+        assert_eq!(
+            capability[17..],
+            [0x00, 0x00, 0x03, 0x06, 0xB2, 0x00, 0x21, 0x00]
+        );
+    }
+}
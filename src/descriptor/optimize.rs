@@ -0,0 +1,266 @@
+//! Report descriptor size optimization
+//!
+//! [`optimize`] shrinks a report descriptor without changing the reports it describes:
+//!
+//! - Global items that set a value already in effect are removed, such as the Usage Page and
+//!   Logical Minimum items repeated by each descriptor pushed to a
+//!   [`DescriptorComposer`](crate::descriptor::compose::DescriptorComposer)
+//! - Three or more consecutive usages of an Input, Output or Feature item are collapsed into a
+//!   Usage Minimum and Usage Maximum
+//!
+//! ```rust
+//! use usbd_human_interface_device::descriptor::compose::DescriptorComposer;
+//! use usbd_human_interface_device::device::consumer::MULTIPLE_CODE_REPORT_DESCRIPTOR;
+//! use usbd_human_interface_device::device::mouse::WHEEL_MOUSE_REPORT_DESCRIPTOR;
+//!
+//! let mut composer = DescriptorComposer::<256>::new();
+//! composer.push(WHEEL_MOUSE_REPORT_DESCRIPTOR).unwrap();
+//! composer.push(MULTIPLE_CODE_REPORT_DESCRIPTOR).unwrap();
+//! let len = composer.descriptor().len();
+//!
+//! composer.optimize().unwrap();
+//! assert!(composer.descriptor().len() < len);
+//! ```
+
+use heapless::Vec;
+
+use crate::descriptor::parse::{
+    parse, read_item, Item, ParseError, LONG_ITEM, PARSE_MAX_PUSH_DEPTH,
+};
+
+const USAGE: u8 = 0x08;
+const USAGE_MINIMUM: u8 = 0x18;
+const USAGE_MAXIMUM: u8 = 0x28;
+const DELIMITER: u8 = 0xA8;
+const PUSH: u8 = 0xA4;
+const POP: u8 = 0xB4;
+
+/// Size and value of each global item in effect, indexed by tag
+type Globals = [Option<(usize, u32)>; 10];
+
+/// Optimize `descriptor` in place, returning its new length
+///
+/// The descriptor is validated with [`parse`] first, and left unchanged if it isn't well formed.
+pub fn optimize(descriptor: &mut [u8]) -> Result<usize, ParseError> {
+    parse(descriptor)?;
+
+    let mut globals: Globals = [None; 10];
+    let mut stack: Vec<Globals, PARSE_MAX_PUSH_DEPTH> = Vec::new();
+    // Usages left in a collapsed range, and the usage written as its Usage Maximum
+    let mut collapsed: Option<(usize, u32)> = None;
+    let mut read = 0;
+    let mut write = 0;
+
+    while read < descriptor.len() {
+        let item = read_item(descriptor, read).map_err(|kind| ParseError { offset: read, kind })?;
+        let item_type = (item.prefix >> 2) & 0x03;
+        let tag = item.prefix & 0xFC;
+
+        let keep = if item.prefix == LONG_ITEM {
+            true
+        } else if item_type == 0 {
+            collapsed = None;
+            true
+        } else if tag == PUSH {
+            // Validated by parse
+            stack.push(globals).ok();
+            true
+        } else if tag == POP {
+            globals = stack.pop().unwrap_or_default();
+            true
+        } else if item_type == 1 {
+            let state = &mut globals[usize::from(item.prefix >> 4)];
+            let redundant = *state == Some((item.size, item.value));
+            *state = Some((item.size, item.value));
+            !redundant
+        } else if tag == USAGE {
+            match collapsed {
+                None => match usage_range(descriptor, read) {
+                    Some((first, count, last)) => {
+                        // The range is never longer than the usages it replaces, so writing it
+                        // in place of the first and last usage can't overwrite unread items
+                        write += write_usage_item(&mut descriptor[write..], USAGE_MINIMUM, first);
+                        collapsed = Some((count - 1, last));
+                        read += item.len;
+                        continue;
+                    }
+                    None => true,
+                },
+                Some((1, last)) => {
+                    write += write_usage_item(&mut descriptor[write..], USAGE_MAXIMUM, last);
+                    collapsed = None;
+                    read += item.len;
+                    continue;
+                }
+                Some((remaining, last)) => {
+                    collapsed = Some((remaining - 1, last));
+                    false
+                }
+            }
+        } else {
+            true
+        };
+
+        if keep {
+            descriptor.copy_within(read..read + item.len, write);
+            write += item.len;
+        }
+        read += item.len;
+    }
+    Ok(write)
+}
+
+/// First usage, number of usages and last usage of the Usage items from `offset` up to the next
+/// main item, if they can be collapsed into a range
+fn usage_range(descriptor: &[u8], mut offset: usize) -> Option<(u32, usize, u32)> {
+    let mut first = None;
+    let mut last = 0;
+    let mut count = 0;
+
+    loop {
+        let Item {
+            prefix,
+            size,
+            value,
+            len,
+        } = read_item(descriptor, offset).ok()?;
+        let item_type = (prefix >> 2) & 0x03;
+        let tag = prefix & 0xFC;
+
+        if prefix == LONG_ITEM || tag == USAGE_MINIMUM || tag == USAGE_MAXIMUM || tag == DELIMITER {
+            return None;
+        }
+        if item_type == 0 {
+            // Only data items use every usage, a collection only uses its first
+            let data_item = matches!(tag, 0x80 | 0x90 | 0xB0);
+            return (data_item && count >= 3).then_some((first?, count, last));
+        }
+        if tag == USAGE {
+            // Extended usages have their own usage page
+            if size > 2 || first.is_some() && value != last + 1 {
+                return None;
+            }
+            first.get_or_insert(value);
+            last = value;
+            count += 1;
+        }
+        offset += len;
+    }
+}
+
+/// Write a Usage, Usage Minimum or Usage Maximum item in its shortest form, returning its length
+fn write_usage_item(data: &mut [u8], prefix: u8, value: u32) -> usize {
+    let bytes = value.to_le_bytes();
+    let size = match value {
+        0 => 0,
+        1..=0xFF => 1,
+        _ => 2,
+    };
+    data[0] = prefix | u8::try_from(size).unwrap_or(2);
+    data[1..=size].copy_from_slice(&bytes[..size]);
+    1 + size
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use crate::descriptor::compose::DescriptorComposer;
+    use crate::descriptor::optimize::optimize;
+    use crate::descriptor::parse::parse;
+    use crate::device::consumer::MULTIPLE_CODE_REPORT_DESCRIPTOR;
+    use crate::device::keyboard::BOOT_KEYBOARD_REPORT_DESCRIPTOR;
+    use crate::device::mouse::{BOOT_MOUSE_REPORT_DESCRIPTOR, WHEEL_MOUSE_REPORT_DESCRIPTOR};
+
+    #[test]
+    fn optimized_composed_descriptor_describes_same_reports() {
+        let mut composer = DescriptorComposer::<512>::new();
+        composer.push(BOOT_KEYBOARD_REPORT_DESCRIPTOR).unwrap();
+        composer.push(BOOT_MOUSE_REPORT_DESCRIPTOR).unwrap();
+        composer.push(WHEEL_MOUSE_REPORT_DESCRIPTOR).unwrap();
+        composer.push(MULTIPLE_CODE_REPORT_DESCRIPTOR).unwrap();
+
+        let mut optimized = std::vec::Vec::from(composer.descriptor());
+        let len = optimize(&mut optimized).unwrap();
+        optimized.truncate(len);
+
+        assert!(len < composer.descriptor().len());
+        assert_eq!(parse(&optimized), parse(composer.descriptor()));
+    }
+
+    #[test]
+    fn redundant_globals_are_removed() {
+        let mut descriptor = [
+            0x05, 0x01, // Usage Page (Generic Desktop)
+            0x09, 0x02, // Usage (Mouse)
+            0xA1, 0x01, // Collection (Application)
+            0x15, 0x00, // Logical Minimum (0)
+            0x25, 0x01, // Logical Maximum (1)
+            0x75, 0x01, // Report Size (1)
+            0x95, 0x08, // Report Count (8)
+            0x05, 0x01, // Usage Page (Generic Desktop)
+            0x09, 0x30, // Usage (X)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0xA4, // Push
+            0x75, 0x08, // Report Size (8)
+            0x95, 0x01, // Report Count (1)
+            0x09, 0x31, // Usage (Y)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0xB4, // Pop
+            0x75, 0x01, // Report Size (1)
+            0x15, 0x00, // Logical Minimum (0)
+            0x16, 0x00, 0x00, // Logical Minimum (0), not the same encoding
+            0x09, 0x32, // Usage (Z)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0xC0, // End Collection
+        ];
+
+        let len = optimize(&mut descriptor).unwrap();
+
+        assert_eq!(
+            &descriptor[..len],
+            &[
+                0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x08,
+                0x09, 0x30, 0x81, 0x02, 0xA4, 0x75, 0x08, 0x95, 0x01, 0x09, 0x31, 0x81, 0x02, 0xB4,
+                0x16, 0x00, 0x00, 0x09, 0x32, 0x81, 0x02, 0xC0,
+            ]
+        );
+    }
+
+    #[test]
+    fn consecutive_usages_are_collapsed() {
+        let mut descriptor = [
+            0x05, 0x0C, // Usage Page (Consumer)
+            0x09, 0x01, // Usage (Consumer Control)
+            0xA1, 0x01, // Collection (Application)
+            0x75, 0x01, // Report Size (1)
+            0x95, 0x04, // Report Count (4)
+            0x15, 0x00, // Logical Minimum (0)
+            0x25, 0x01, // Logical Maximum (1)
+            0x09, 0xE9, // Usage (Volume Increment)
+            0x09, 0xEA, // Usage (Volume Decrement)
+            0x79, 0x04, // String Index (4)
+            0x0A, 0xEB, 0x00, // Usage (0xEB)
+            0x0A, 0xEC, 0x00, // Usage (0xEC)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x09, 0xCD, // Usage (Play/Pause)
+            0x09, 0xB5, // Usage (Scan Next Track)
+            0x09, 0xB6, // Usage (Scan Previous Track)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0xC0, // End Collection
+        ];
+        let reports = parse(&descriptor).unwrap();
+
+        let len = optimize(&mut descriptor).unwrap();
+
+        assert_eq!(
+            &descriptor[..len],
+            &[
+                0x05, 0x0C, 0x09, 0x01, 0xA1, 0x01, 0x75, 0x01, 0x95, 0x04, 0x15, 0x00, 0x25, 0x01,
+                0x19, 0xE9, 0x79, 0x04, 0x29, 0xEC, 0x81, 0x02, 0x09, 0xCD, 0x09, 0xB5, 0x09, 0xB6,
+                0x81, 0x02, 0xC0,
+            ]
+        );
+        assert_eq!(parse(&descriptor[..len]).unwrap(), reports);
+    }
+}
@@ -0,0 +1,476 @@
+//! Report descriptor parser and validator
+//!
+//! [`parse`] walks a report descriptor, checking that its items are well formed and consistent,
+//! and computes the size of each report it describes. It is `no_std`, so is usable both in tests
+//! and on the device:
+//!
+//! ```rust
+//! use usbd_human_interface_device::descriptor::parse::parse;
+//! use usbd_human_interface_device::device::keyboard::BOOT_KEYBOARD_REPORT_DESCRIPTOR;
+//!
+//! let reports = parse(BOOT_KEYBOARD_REPORT_DESCRIPTOR).unwrap();
+//! let report = reports.report(0).unwrap();
+//! assert_eq!(report.input_len(), 8);
+//! assert_eq!(report.output_len(), 1);
+//! ```
+
+use heapless::Vec;
+use usb_device::UsbError;
+
+/// Maximum number of distinct report IDs in a descriptor parsed by [`parse`]
+pub const PARSE_MAX_REPORTS: usize = 32;
+/// Maximum depth of Push items in a descriptor parsed by [`parse`]
+pub const PARSE_MAX_PUSH_DEPTH: usize = 4;
+
+/// Problem found in a report descriptor, at byte `offset` of the descriptor
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub offset: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The descriptor ends part way through an item
+    Truncated,
+    /// An item with a reserved type or tag
+    ReservedItem,
+    /// End Collection without a matching Collection
+    UnbalancedEndCollection,
+    /// Collections still open at the end of the descriptor
+    UnclosedCollection,
+    /// Input, Output or Feature item outside of any collection
+    MainItemOutsideCollection,
+    /// Input, Output or Feature item before any Report Size item
+    MissingReportSize,
+    /// Input, Output or Feature item before any Report Count item
+    MissingReportCount,
+    /// Input, Output or Feature item without a report ID, in a descriptor that uses report IDs
+    MissingReportId,
+    /// Report ID 0, which is reserved
+    ReservedReportId,
+    /// Logical Minimum greater than Logical Maximum for a data field
+    InvalidLogicalRange,
+    /// Push deeper than [`PARSE_MAX_PUSH_DEPTH`]
+    PushOverflow,
+    /// Pop without a matching Push
+    PopUnderflow,
+    /// More than [`PARSE_MAX_REPORTS`] report IDs
+    TooManyReports,
+}
+
+/// Sizes of the reports with one report ID
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct ReportSizes {
+    /// Report ID, 0 if the descriptor doesn't use report IDs
+    pub report_id: u8,
+    pub input_bits: u32,
+    pub output_bits: u32,
+    pub feature_bits: u32,
+}
+
+impl ReportSizes {
+    /// Input report length in bytes, excluding the report ID
+    #[must_use]
+    pub fn input_len(&self) -> usize {
+        bits_to_bytes(self.input_bits)
+    }
+
+    /// Output report length in bytes, excluding the report ID
+    #[must_use]
+    pub fn output_len(&self) -> usize {
+        bits_to_bytes(self.output_bits)
+    }
+
+    /// Feature report length in bytes, excluding the report ID
+    #[must_use]
+    pub fn feature_len(&self) -> usize {
+        bits_to_bytes(self.feature_bits)
+    }
+}
+
+fn bits_to_bytes(bits: u32) -> usize {
+    bits.div_ceil(8) as usize
+}
+
+/// Reports described by a report descriptor, in order of first appearance
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Reports {
+    reports: Vec<ReportSizes, PARSE_MAX_REPORTS>,
+}
+
+impl Reports {
+    /// Sizes of the reports with `report_id`, 0 if the descriptor doesn't use report IDs
+    #[must_use]
+    pub fn report(&self, report_id: u8) -> Option<&ReportSizes> {
+        self.reports.iter().find(|r| r.report_id == report_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ReportSizes> {
+        self.reports.iter()
+    }
+
+    #[must_use]
+    pub fn uses_report_ids(&self) -> bool {
+        self.reports.iter().any(|r| r.report_id != 0)
+    }
+
+    fn report_mut(&mut self, report_id: u8) -> Option<&mut ReportSizes> {
+        if !self.reports.iter().any(|r| r.report_id == report_id) {
+            self.reports
+                .push(ReportSizes {
+                    report_id,
+                    ..Default::default()
+                })
+                .ok()?;
+        }
+        self.reports.iter_mut().find(|r| r.report_id == report_id)
+    }
+}
+
+/// Global items tracked by the parser, saved by Push and restored by Pop
+#[derive(Clone, Copy, Debug, Default)]
+struct Globals {
+    logical_minimum: i32,
+    logical_maximum: u32,
+    logical_maximum_size: usize,
+    report_size: Option<u32>,
+    report_count: Option<u32>,
+    report_id: Option<u8>,
+}
+
+impl Globals {
+    /// Logical Maximum is interpreted as unsigned if Logical Minimum isn't negative
+    fn logical_range_valid(&self) -> bool {
+        match u32::try_from(self.logical_minimum) {
+            Ok(minimum) => minimum <= self.logical_maximum,
+            Err(_) => {
+                self.logical_minimum <= sign_extend(self.logical_maximum, self.logical_maximum_size)
+            }
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
+fn sign_extend(value: u32, size: usize) -> i32 {
+    match size {
+        1 => i32::from(value as u8 as i8),
+        2 => i32::from(value as u16 as i16),
+        _ => value as i32,
+    }
+}
+
+const ITEM_TYPE_MAIN: u8 = 0;
+const ITEM_TYPE_GLOBAL: u8 = 1;
+const ITEM_TYPE_LOCAL: u8 = 2;
+pub(crate) const LONG_ITEM: u8 = 0xFE;
+/// Prefix of a Report ID item, less the size bits
+pub(crate) const REPORT_ID_ITEM: u8 = 0x84;
+
+/// Item of a report descriptor, long items have prefix [`LONG_ITEM`] and no data
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Item {
+    pub(crate) prefix: u8,
+    /// Size of the item data in bytes
+    pub(crate) size: usize,
+    pub(crate) value: u32,
+    /// Length of the whole item in bytes
+    pub(crate) len: usize,
+}
+
+/// Read the item at `offset` of `descriptor`
+pub(crate) fn read_item(descriptor: &[u8], offset: usize) -> Result<Item, ParseErrorKind> {
+    let prefix = *descriptor.get(offset).ok_or(ParseErrorKind::Truncated)?;
+
+    if prefix == LONG_ITEM {
+        let size = descriptor
+            .get(offset + 1)
+            .ok_or(ParseErrorKind::Truncated)?;
+        let len = 3 + usize::from(*size);
+        if offset + len > descriptor.len() {
+            return Err(ParseErrorKind::Truncated);
+        }
+        return Ok(Item {
+            prefix,
+            size: 0,
+            value: 0,
+            len,
+        });
+    }
+
+    let size = match prefix & 0x03 {
+        3 => 4,
+        s => usize::from(s),
+    };
+    let data = descriptor
+        .get(offset + 1..offset + 1 + size)
+        .ok_or(ParseErrorKind::Truncated)?;
+    let value = data
+        .iter()
+        .rev()
+        .fold(0_u32, |v, b| (v << 8) | u32::from(*b));
+    Ok(Item {
+        prefix,
+        size,
+        value,
+        len: 1 + size,
+    })
+}
+
+/// Copy `descriptor` to `buffer`, adding `offset` to the index of its String Index, String Minimum
+/// and String Maximum items
+pub(crate) fn offset_string_indices(
+    descriptor: &[u8],
+    offset: u8,
+    buffer: &mut [u8],
+) -> usb_device::Result<usize> {
+    let buffer = buffer
+        .get_mut(..descriptor.len())
+        .ok_or(UsbError::BufferOverflow)?;
+    buffer.copy_from_slice(descriptor);
+
+    let mut position = 0;
+    while position < descriptor.len() {
+        let item = read_item(descriptor, position).map_err(|_| UsbError::ParseError)?;
+        if item.prefix != LONG_ITEM && matches!(item.prefix & 0xFC, 0x78 | 0x88 | 0x98) {
+            // Items without data are index 0, and can't be rewritten in place
+            let index = u8::try_from(item.value)
+                .ok()
+                .filter(|_| item.size > 0)
+                .and_then(|i| i.checked_add(offset))
+                .ok_or(UsbError::ParseError)?;
+            let data = &mut buffer[position + 1..position + item.len];
+            data.fill(0);
+            data[0] = index;
+        }
+        position += item.len;
+    }
+    Ok(descriptor.len())
+}
+
+#[derive(Default)]
+struct Parser {
+    reports: Reports,
+    globals: Globals,
+    stack: Vec<Globals, PARSE_MAX_PUSH_DEPTH>,
+    depth: usize,
+    report_ids_used: bool,
+    main_without_report_id: bool,
+}
+
+impl Parser {
+    fn item(&mut self, prefix: u8, value: u32, size: usize) -> Result<(), ParseErrorKind> {
+        let item_type = (prefix >> 2) & 0x03;
+        let tag = prefix >> 4;
+
+        match (item_type, tag) {
+            // Input, Output, Feature
+            (ITEM_TYPE_MAIN, 0x8 | 0x9 | 0xB) => self.data_item(tag, value)?,
+            // Collection
+            (ITEM_TYPE_MAIN, 0xA) => self.depth += 1,
+            // End Collection
+            (ITEM_TYPE_MAIN, 0xC) => {
+                self.depth = self
+                    .depth
+                    .checked_sub(1)
+                    .ok_or(ParseErrorKind::UnbalancedEndCollection)?;
+            }
+            // Logical Minimum
+            (ITEM_TYPE_GLOBAL, 0x1) => self.globals.logical_minimum = sign_extend(value, size),
+            // Logical Maximum
+            (ITEM_TYPE_GLOBAL, 0x2) => {
+                self.globals.logical_maximum = value;
+                self.globals.logical_maximum_size = size;
+            }
+            // Report Size
+            (ITEM_TYPE_GLOBAL, 0x7) => self.globals.report_size = Some(value),
+            // Report ID
+            (ITEM_TYPE_GLOBAL, 0x8) => {
+                let report_id = u8::try_from(value)
+                    .ok()
+                    .filter(|id| *id != 0)
+                    .ok_or(ParseErrorKind::ReservedReportId)?;
+                if self.main_without_report_id {
+                    return Err(ParseErrorKind::MissingReportId);
+                }
+                self.report_ids_used = true;
+                self.globals.report_id = Some(report_id);
+            }
+            // Report Count
+            (ITEM_TYPE_GLOBAL, 0x9) => self.globals.report_count = Some(value),
+            // Push
+            (ITEM_TYPE_GLOBAL, 0xA) => self
+                .stack
+                .push(self.globals)
+                .map_err(|_| ParseErrorKind::PushOverflow)?,
+            // Pop
+            (ITEM_TYPE_GLOBAL, 0xB) => {
+                self.globals = self.stack.pop().ok_or(ParseErrorKind::PopUnderflow)?;
+            }
+            // Usage Page, Physical Minimum/Maximum, Unit Exponent, Unit and local items
+            (ITEM_TYPE_GLOBAL, 0x0 | 0x3..=0x6) | (ITEM_TYPE_LOCAL, 0x0..=0xA) => {}
+            _ => return Err(ParseErrorKind::ReservedItem),
+        }
+        Ok(())
+    }
+
+    /// Input, Output or Feature item
+    fn data_item(&mut self, tag: u8, value: u32) -> Result<(), ParseErrorKind> {
+        if self.depth == 0 {
+            return Err(ParseErrorKind::MainItemOutsideCollection);
+        }
+        let report_size = self
+            .globals
+            .report_size
+            .ok_or(ParseErrorKind::MissingReportSize)?;
+        let report_count = self
+            .globals
+            .report_count
+            .ok_or(ParseErrorKind::MissingReportCount)?;
+        let constant = value & 0x01 != 0;
+        if !constant && !self.globals.logical_range_valid() {
+            return Err(ParseErrorKind::InvalidLogicalRange);
+        }
+        if self.report_ids_used && self.globals.report_id.is_none() {
+            return Err(ParseErrorKind::MissingReportId);
+        }
+        self.main_without_report_id |= self.globals.report_id.is_none();
+
+        let report = self
+            .reports
+            .report_mut(self.globals.report_id.unwrap_or(0))
+            .ok_or(ParseErrorKind::TooManyReports)?;
+        let bits = match tag {
+            0x8 => &mut report.input_bits,
+            0x9 => &mut report.output_bits,
+            _ => &mut report.feature_bits,
+        };
+        *bits += report_size * report_count;
+        Ok(())
+    }
+}
+
+/// Parse and validate `descriptor`, returning the sizes of the reports it describes
+///
+/// As on Linux, Logical Maximum is interpreted as unsigned unless Logical Minimum is
+/// negative
+pub fn parse(descriptor: &[u8]) -> Result<Reports, ParseError> {
+    let mut parser = Parser::default();
+    let mut offset = 0;
+
+    while offset < descriptor.len() {
+        let error = |kind| ParseError { offset, kind };
+        let item = read_item(descriptor, offset).map_err(error)?;
+        // Long items have no defined tags, skip over them
+        if item.prefix != LONG_ITEM {
+            parser
+                .item(item.prefix, item.value, item.size)
+                .map_err(error)?;
+        }
+        offset += item.len;
+    }
+
+    if parser.depth != 0 {
+        return Err(ParseError {
+            offset,
+            kind: ParseErrorKind::UnclosedCollection,
+        });
+    }
+    Ok(parser.reports)
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use crate::descriptor::parse::{parse, ParseError, ParseErrorKind, ReportSizes};
+    use crate::device::consumer::MULTIPLE_CODE_REPORT_DESCRIPTOR;
+    use crate::device::joystick::JOYSTICK_DESCRIPTOR;
+    use crate::device::keyboard::{
+        BOOT_KEYBOARD_REPORT_DESCRIPTOR, NKRO_BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+    };
+    use crate::device::keyboard_pointer::KEYBOARD_POINTER_REPORT_DESCRIPTOR;
+    use crate::device::mouse::{
+        BOOT_MOUSE_REPORT_DESCRIPTOR, HIGH_RESOLUTION_WHEEL_MOUSE_REPORT_DESCRIPTOR,
+    };
+    use crate::device::presence::HUMAN_PRESENCE_REPORT_DESCRIPTOR;
+    use crate::device::telephony::SPEAKERPHONE_REPORT_DESCRIPTOR;
+
+    #[test]
+    fn device_descriptors_are_well_formed() {
+        for descriptor in [
+            BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+            NKRO_BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+            BOOT_MOUSE_REPORT_DESCRIPTOR,
+            HIGH_RESOLUTION_WHEEL_MOUSE_REPORT_DESCRIPTOR,
+            MULTIPLE_CODE_REPORT_DESCRIPTOR,
+            JOYSTICK_DESCRIPTOR,
+            HUMAN_PRESENCE_REPORT_DESCRIPTOR,
+        ] {
+            parse(descriptor).unwrap();
+        }
+    }
+
+    #[test]
+    fn report_sizes_by_report_id() {
+        let reports = parse(KEYBOARD_POINTER_REPORT_DESCRIPTOR).unwrap();
+
+        assert!(reports.uses_report_ids());
+        assert_eq!(
+            reports.iter().copied().collect::<std::vec::Vec<_>>(),
+            [
+                ReportSizes {
+                    report_id: 1,
+                    input_bits: 64,
+                    output_bits: 8,
+                    feature_bits: 0,
+                },
+                ReportSizes {
+                    report_id: 2,
+                    input_bits: 40,
+                    output_bits: 0,
+                    feature_bits: 0,
+                },
+            ]
+        );
+
+        let reports = parse(SPEAKERPHONE_REPORT_DESCRIPTOR).unwrap();
+        assert_eq!(reports.report(1).unwrap().input_len(), 1);
+        assert_eq!(reports.report(2).unwrap().input_len(), 1);
+        assert_eq!(reports.report(3).unwrap().output_len(), 1);
+    }
+
+    #[test]
+    fn inconsistent_descriptors_are_rejected() {
+        #[rustfmt::skip]
+        let cases: [(&[u8], ParseError); 5] = [
+            (
+                &[0xA1, 0x01, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02, 0xC0, 0xC0],
+                ParseError { offset: 9, kind: ParseErrorKind::UnbalancedEndCollection },
+            ),
+            (
+                &[0xA1, 0x01, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02],
+                ParseError { offset: 8, kind: ParseErrorKind::UnclosedCollection },
+            ),
+            (
+                &[0xA1, 0x01, 0x26, 0xFF],
+                ParseError { offset: 2, kind: ParseErrorKind::Truncated },
+            ),
+            (
+                &[0xA1, 0x01, 0x15, 0xFF, 0x25, 0x80, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02, 0xC0],
+                ParseError { offset: 10, kind: ParseErrorKind::InvalidLogicalRange },
+            ),
+            (
+                &[0xA1, 0x01, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02, 0x85, 0x01, 0x81, 0x02, 0xC0],
+                ParseError { offset: 8, kind: ParseErrorKind::MissingReportId },
+            ),
+        ];
+
+        for (descriptor, error) in cases {
+            assert_eq!(parse(descriptor), Err(error));
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//!HID ambient light sensor
+//!
+//! Reports illuminance as a HID Sensors Usages ambient light sensor, so the host can use it for
+//! adaptive display brightness. See [`sensor`](crate::device::sensor) for the host controlled
+//! sensor properties.
+use crate::descriptor::concat;
+use crate::device::sensor::{
+    Sensor, SensorEvent, SensorProperties, SensorState, SENSOR_PROPERTY_ITEMS,
+};
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the ambient light sensor input and feature reports
+pub const AMBIENT_LIGHT_REPORT_ID: u8 = 0x01;
+
+/// Maximum illuminance that can be reported, in lux
+pub const AMBIENT_LIGHT_MAX_LUX: u32 = 0x7FFF_FFFF;
+
+/// Illuminance is in lux
+#[rustfmt::skip]
+pub const AMBIENT_LIGHT_REPORT_DESCRIPTOR: &[u8] = &concat::<
+    { SENSOR_PROPERTY_ITEMS.len() + 32 }
+>(&[
+    &[
+        0x05, 0x20,    // Usage Page (Sensors),
+        0x09, 0x01,    // Usage (Sensor),
+        0xA1, 0x01,    // Collection (Application),
+        0x85, 0x01,    //   Report ID (1),
+        0x09, 0x41,    //   Usage (Ambient Light),
+        0xA1, 0x00,    //   Collection (Physical),
+    ],
+    SENSOR_PROPERTY_ITEMS,
+    &[
+        0x0A, 0xD1, 0x04,                   //     Usage (Illuminance),
+        0x15, 0x00,                         //     Logical Minimum (0),
+        0x27, 0xFF, 0xFF, 0xFF, 0x7F,       //     Logical Maximum (2147483647),
+        0x75, 0x20,                         //     Report Size (32),
+        0x95, 0x01,                         //     Report Count (1),
+        0x55, 0x00,                         //     Unit Exponent (0),
+        0x81, 0x02,                         //     Input (Data, Variable, Absolute),
+        0xC0,                               //   End Collection
+        0xC0,                               // End Collection
+    ],
+]);
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "6")]
+pub struct AmbientLightReport {
+    #[packed_field(bytes = "0", ty = "enum")]
+    pub sensor_state: SensorState,
+    #[packed_field(bytes = "1", ty = "enum")]
+    pub event: SensorEvent,
+    /// Illuminance in lux
+    #[packed_field(bytes = "2..=5")]
+    pub illuminance: u32,
+}
+
+/// Ambient light sensor
+///
+/// Illuminance passed to [`AmbientLightSensor::update`] is sent at the report interval requested
+/// by the host from [`DeviceClass::tick`], which must be called every 1ms, while the reporting
+/// state allows it and the sensor is at full power. State and property changes are reported to
+/// the host immediately.
+pub struct AmbientLightSensor<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    sensor: Sensor<u32>,
+    default_report_interval: u32,
+}
+
+impl<B: UsbBus> AmbientLightSensor<'_, B> {
+    /// Set the latest illuminance in lux, clamped to [`AMBIENT_LIGHT_MAX_LUX`]
+    pub fn update(&mut self, lux: u32) {
+        self.sensor.sample = Some(lux.min(AMBIENT_LIGHT_MAX_LUX));
+    }
+
+    /// Set the sensor state, e.g. to report a sensor error to the host
+    pub fn set_sensor_state(&mut self, state: SensorState) {
+        self.sensor.set_sensor_state(state);
+    }
+
+    #[must_use]
+    pub fn properties(&self) -> SensorProperties {
+        self.sensor.properties
+    }
+
+    fn write(&mut self, report: AmbientLightReport) -> Result<(), UsbHidError> {
+        let mut data = [0; 7];
+        data[0] = AMBIENT_LIGHT_REPORT_ID;
+        report.pack_to_slice(&mut data[1..]).map_err(|_| {
+            error!("Error packing AmbientLightReport");
+            UsbHidError::SerializationError
+        })?;
+        self.interface
+            .write_report(&data)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for AmbientLightSensor<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.sensor.reset();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        let Some((event, illuminance)) = self.sensor.tick() else {
+            return Ok(());
+        };
+
+        let report = AmbientLightReport {
+            sensor_state: self.sensor.properties.sensor_state,
+            event,
+            illuminance: illuminance.unwrap_or_default(),
+        };
+
+        match self.write(report) {
+            Ok(()) => {
+                self.sensor.report_written(self.default_report_interval);
+                Ok(())
+            }
+            Err(UsbHidError::WouldBlock) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        if report_id != AMBIENT_LIGHT_REPORT_ID {
+            return Err(UsbError::Unsupported);
+        }
+        self.sensor.get_properties_report(report_id, data)
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        if report_id != AMBIENT_LIGHT_REPORT_ID {
+            return Err(UsbError::Unsupported);
+        }
+        self.sensor.set_properties_report(report_id, data)
+    }
+}
+
+pub struct AmbientLightSensorConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+    default_report_interval: u32,
+}
+
+impl Default for AmbientLightSensorConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                AMBIENT_LIGHT_REPORT_DESCRIPTOR
+            ))
+            .description("Ambient Light Sensor")
+            .in_endpoint(10.millis()))
+            .without_out_endpoint()
+            .build(),
+            1000,
+        )
+    }
+}
+
+impl<'a> AmbientLightSensorConfig<'a> {
+    /// `default_report_interval` in milliseconds is used until the host sets a report interval
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+        default_report_interval: u32,
+    ) -> Self {
+        Self {
+            interface,
+            default_report_interval,
+        }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for AmbientLightSensorConfig<'a> {
+    type Allocated = AmbientLightSensor<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            sensor: Sensor::default(),
+            default_report_interval: self.default_report_interval.max(1),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::ambient_light::AmbientLightReport;
+    use crate::device::sensor::{SensorEvent, SensorState};
+
+    #[test]
+    fn ambient_light_report_layout() {
+        let bytes = AmbientLightReport {
+            sensor_state: SensorState::Ready,
+            event: SensorEvent::DataUpdated,
+            illuminance: 100_000,
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(bytes, [0x01, 0x03, 0xA0, 0x86, 0x01, 0x00]);
+    }
+}
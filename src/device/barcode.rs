@@ -0,0 +1,276 @@
+//!HID point of sale barcode scanner
+//!
+//! See [HID Point of Sale Usage Tables](<https://www.usb.org/sites/default/files/pos1_02.pdf>)
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use heapless::Vec;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the scanned data input report
+pub const BARCODE_SCANNED_DATA_REPORT_ID: u8 = 0x01;
+/// Report ID of the settings feature report
+pub const BARCODE_SETTINGS_REPORT_ID: u8 = 0x02;
+
+/// Decoded data bytes carried by each scanned data report
+pub const BARCODE_DATA_CHUNK_LEN: usize = 58;
+/// Default decoded data buffer length
+pub const BARCODE_DEFAULT_DATA_LEN: usize = 256;
+
+#[rustfmt::skip]
+pub const BARCODE_SCANNER_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x8C,        // Usage Page (Barcode Scanner),
+    0x09, 0x02,        // Usage (Barcode Scanner),
+    0xA1, 0x01,        // Collection (Application),
+    0x09, 0x12,        //   Usage (Scanned Data Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x01,        //     Report ID (1),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x09, 0xFB,        //     Usage (Symbology Identifier 1),
+    0x09, 0xFC,        //     Usage (Symbology Identifier 2),
+    0x09, 0xFD,        //     Usage (Symbology Identifier 3),
+    0x95, 0x03,        //     Report Count (3),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x3B,        //     Usage (Byte Count),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x8C,        //     Usage Page (Barcode Scanner),
+    0x09, 0xFE,        //     Usage (Decoded Data),
+    0x95, 0x3A,        //     Report Count (58),
+    0x82, 0x02, 0x01,  //     Input (Data, Variable, Absolute, Buffered Bytes),
+    0x09, 0xFF,        //     Usage (Decoded Data Continued),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x07,        //     Report Count (7),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0x11,        //   Usage (Settings Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x02,        //     Report ID (2),
+    0x0A, 0x58, 0x01,  //     Usage (Trigger Mode),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x03,        //     Logical Maximum (3),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x0A, 0x59, 0x01,  //       Usage (Blinking Laser On),
+    0x0A, 0x5A, 0x01,  //       Usage (Continuous Laser On),
+    0x0A, 0x5B, 0x01,  //       Usage (Laser On While Pulled),
+    0x0A, 0x5C, 0x01,  //       Usage (Laser Stays On After Release),
+    0xB1, 0x00,        //       Feature (Data, Array, Absolute),
+    0xC0,              //     End Collection
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum TriggerMode {
+    BlinkingLaserOn = 0,
+    ContinuousLaserOn = 1,
+    #[default]
+    LaserOnWhilePulled = 2,
+    LaserStaysOnAfterRelease = 3,
+}
+
+/// AIM symbology identifier, e.g. `*b"]E0"` for EAN-13
+pub type SymbologyIdentifier = [u8; 3];
+
+struct Transmit<const N: usize> {
+    symbology: SymbologyIdentifier,
+    data: Vec<u8, N>,
+    offset: usize,
+}
+
+impl<const N: usize> Transmit<N> {
+    fn report(&self) -> [u8; 64] {
+        let chunk = &self.data[self.offset..];
+        let len = chunk.len().min(BARCODE_DATA_CHUNK_LEN);
+
+        let mut report = [0; 64];
+        report[0] = BARCODE_SCANNED_DATA_REPORT_ID;
+        report[1..4].copy_from_slice(&self.symbology);
+        report[4] = u8::try_from(len).unwrap_or(u8::MAX);
+        report[5..5 + len].copy_from_slice(&chunk[..len]);
+        report[63] = u8::from(chunk.len() > BARCODE_DATA_CHUNK_LEN);
+        report
+    }
+
+    /// Advance to the next report, returns false once all data has been sent
+    fn advance(&mut self) -> bool {
+        self.offset += BARCODE_DATA_CHUNK_LEN;
+        self.offset < self.data.len()
+    }
+}
+
+/// Barcode scanner
+///
+/// Decoded data longer than [`BARCODE_DATA_CHUNK_LEN`] is split over multiple reports, each but
+/// the last marked as continued. [`DeviceClass::tick`] must be called every 1ms to send the
+/// remaining reports. `N` is the longest barcode that can be sent.
+pub struct BarcodeScanner<'a, B: UsbBus, const N: usize = BARCODE_DEFAULT_DATA_LEN> {
+    interface: Interface<'a, B, InBytes64, OutNone, ReportSingle>,
+    trigger_mode: TriggerMode,
+    tx: Option<Transmit<N>>,
+}
+
+impl<B: UsbBus, const N: usize> BarcodeScanner<'_, B, N> {
+    /// Send decoded barcode data, returns [`UsbHidError::WouldBlock`] if the previous barcode is
+    /// still being sent
+    pub fn write_barcode(
+        &mut self,
+        symbology: SymbologyIdentifier,
+        data: &[u8],
+    ) -> Result<(), UsbHidError> {
+        if self.tx.is_some() {
+            return Err(UsbHidError::WouldBlock);
+        }
+        let data = Vec::from_slice(data).map_err(|()| {
+            error!("Barcode too long");
+            UsbHidError::SerializationError
+        })?;
+        self.tx = Some(Transmit {
+            symbology,
+            data,
+            offset: 0,
+        });
+
+        match self.flush() {
+            Ok(()) | Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(UsbHidError::from(e)),
+        }
+    }
+
+    /// Trigger mode set by the host
+    #[must_use]
+    pub fn trigger_mode(&self) -> TriggerMode {
+        self.trigger_mode
+    }
+
+    fn flush(&mut self) -> usb_device::Result<()> {
+        while let Some(tx) = self.tx.as_mut() {
+            self.interface.write_report(&tx.report())?;
+            if !tx.advance() {
+                self.tx = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, B: UsbBus, const N: usize> DeviceClass<'a> for BarcodeScanner<'a, B, N> {
+    type I = Interface<'a, B, InBytes64, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.tx = None;
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        match self.flush() {
+            Ok(()) | Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(UsbHidError::from(e)),
+        }
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        if report_id != BARCODE_SETTINGS_REPORT_ID {
+            return Err(UsbError::Unsupported);
+        }
+        data.get_mut(..2)
+            .ok_or(UsbError::BufferOverflow)?
+            .copy_from_slice(&[report_id, self.trigger_mode.into()]);
+        Ok(2)
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        match data {
+            [BARCODE_SETTINGS_REPORT_ID, mode, ..] if report_id == BARCODE_SETTINGS_REPORT_ID => {
+                self.trigger_mode =
+                    TriggerMode::try_from(*mode).map_err(|_| UsbError::ParseError)?;
+                info!("Set barcode trigger mode {}", *mode);
+                Ok(())
+            }
+            _ => Err(UsbError::Unsupported),
+        }
+    }
+}
+
+pub struct BarcodeScannerConfig<'a, const N: usize = BARCODE_DEFAULT_DATA_LEN> {
+    interface: InterfaceConfig<'a, InBytes64, OutNone, ReportSingle>,
+}
+
+impl<const N: usize> Default for BarcodeScannerConfig<'_, N> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(
+                unwrap!(InterfaceBuilder::new(BARCODE_SCANNER_REPORT_DESCRIPTOR))
+                    .description("Barcode Scanner")
+                    .in_endpoint(1.millis())
+            )
+            .without_out_endpoint()
+            .build(),
+        )
+    }
+}
+
+impl<'a, const N: usize> BarcodeScannerConfig<'a, N> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes64, OutNone, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a, const N: usize> UsbAllocatable<'a, B> for BarcodeScannerConfig<'a, N> {
+    type Allocated = BarcodeScanner<'a, B, N>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            trigger_mode: TriggerMode::default(),
+            tx: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use heapless::Vec;
+
+    use crate::device::barcode::{Transmit, BARCODE_DATA_CHUNK_LEN};
+
+    #[test]
+    fn long_barcode_is_chunked() {
+        let data: [u8; 70] = core::array::from_fn(|i| u8::try_from(i).unwrap());
+        let mut tx = Transmit::<128> {
+            symbology: *b"]Q1",
+            data: Vec::from_slice(&data).unwrap(),
+            offset: 0,
+        };
+
+        let report = tx.report();
+        assert_eq!(report[..5], [0x01, b']', b'Q', b'1', 58]);
+        assert_eq!(report[5..63], data[..BARCODE_DATA_CHUNK_LEN]);
+        assert_eq!(report[63], 1);
+        assert!(tx.advance());
+
+        let report = tx.report();
+        assert_eq!(report[4], 12);
+        assert_eq!(report[5..17], data[BARCODE_DATA_CHUNK_LEN..]);
+        assert_eq!(report[63], 0);
+        assert!(!tx.advance());
+    }
+}
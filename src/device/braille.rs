@@ -0,0 +1,399 @@
+//!HID braille display
+//!
+//! Supported by the Windows HID braille driver and BRLTTY. See
+//! [HID Braille Display Page](<https://www.usb.org/sites/default/files/hutrr78_-_creation_of_a_braille_display_usage_page_0.pdf>)
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the [`BrailleReport`] input report
+pub const BRAILLE_INPUT_REPORT_ID: u8 = 0x01;
+/// Report ID of the braille cell output report
+pub const BRAILLE_CELLS_REPORT_ID: u8 = 0x02;
+/// Report ID of the number of braille cells feature report
+pub const BRAILLE_CELL_COUNT_REPORT_ID: u8 = 0x03;
+
+/// Maximum number of braille cells, limited by the 64 byte output report
+pub const BRAILLE_MAX_CELLS: u8 = 40;
+
+/// Report descriptor for a braille display with one row of 8 dot cells, generated at compile
+/// time
+///
+/// ```
+/// use usbd_human_interface_device::device::braille::*;
+///
+/// static DISPLAY: BrailleDescriptor = BrailleDescriptor::new(20);
+///
+/// let config = BrailleDisplayConfig::from_descriptor(&DISPLAY);
+/// ```
+pub struct BrailleDescriptor {
+    cells: u8,
+    descriptor: [u8; 128],
+    len: usize,
+}
+
+impl BrailleDescriptor {
+    /// Generate the report descriptor for a display with `cells` cells, each with a router key
+    ///
+    /// Panics, at compile time if used in a const context, if `cells` is zero or exceeds
+    /// [`BRAILLE_MAX_CELLS`]
+    #[must_use]
+    #[rustfmt::skip]
+    pub const fn new(cells: u8) -> Self {
+        core::assert!(cells > 0, "no braille cells");
+        core::assert!(cells <= BRAILLE_MAX_CELLS, "too many braille cells");
+
+        let mut d = Self {
+            cells,
+            descriptor: [0; 128],
+            len: 0,
+        };
+
+        d.push(&[
+            0x05, 0x41,                 // Usage Page (Braille Display),
+            0x09, 0x01,                 // Usage (Braille Display),
+            0xA1, 0x01,                 // Collection (Application),
+            0x85, 0x01,                 //   Report ID (1),
+            0x0A, 0x00, 0x02,           //   Usage (Braille Buttons),
+            0xA1, 0x02,                 //   Collection (Logical),
+            0x15, 0x00,                 //     Logical Minimum (0),
+            0x25, 0x01,                 //     Logical Maximum (1),
+            0x75, 0x01,                 //     Report Size (1),
+            0x1A, 0x01, 0x02,           //     Usage Minimum (Braille Keyboard Dot 1),
+            0x2A, 0x0B, 0x02,           //     Usage Maximum (Braille Keyboard Right Space),
+            0x95, 0x0B,                 //     Report Count (11),
+            0x81, 0x02,                 //     Input (Data, Variable, Absolute),
+            0x0A, 0x1A, 0x02,           //     Usage (Braille Pan Left),
+            0x0A, 0x1B, 0x02,           //     Usage (Braille Pan Right),
+            0x95, 0x02,                 //     Report Count (2),
+            0x81, 0x02,                 //     Input (Data, Variable, Absolute),
+            0x95, 0x03,                 //     Report Count (3),
+            0x81, 0x03,                 //     Input (Constant, Variable, Absolute),
+            0xC0,                       //   End Collection
+            0x09, 0xFA,                 //   Usage (Router Set 1),
+            0xA1, 0x02,                 //   Collection (Logical),
+            0x0A, 0x00, 0x01,           //     Usage (Router Key),
+            0x95, cells,                //     Report Count (cells),
+            0x81, 0x02,                 //     Input (Data, Variable, Absolute),
+        ]);
+
+        if !cells.is_multiple_of(8) {
+            d.push(&[
+                0x95, 8 - cells % 8,    //     Report Count (padding),
+                0x81, 0x03,             //     Input (Constant, Variable, Absolute),
+            ]);
+        }
+
+        d.push(&[
+            0xC0,                       //   End Collection
+            0x85, 0x02,                 //   Report ID (2),
+            0x09, 0x02,                 //   Usage (Braille Row),
+            0xA1, 0x02,                 //   Collection (Logical),
+            0x09, 0x03,                 //     Usage (8 Dot Braille Cell),
+            0x15, 0x00,                 //     Logical Minimum (0),
+            0x26, 0xFF, 0x00,           //     Logical Maximum (255),
+            0x75, 0x08,                 //     Report Size (8),
+            0x95, cells,                //     Report Count (cells),
+            0x91, 0x02,                 //     Output (Data, Variable, Absolute),
+            0xC0,                       //   End Collection
+            0x85, 0x03,                 //   Report ID (3),
+            0x09, 0x05,                 //   Usage (Number of Braille Cells),
+            0x95, 0x01,                 //   Report Count (1),
+            0xB1, 0x03,                 //   Feature (Constant, Variable, Absolute),
+            0xC0,                       // End Collection
+        ]);
+
+        d
+    }
+
+    const fn push(&mut self, items: &[u8]) {
+        let mut i = 0;
+        while i < items.len() {
+            self.descriptor[self.len] = items[i];
+            self.len += 1;
+            i += 1;
+        }
+    }
+
+    #[must_use]
+    pub const fn cells(&self) -> u8 {
+        self.cells
+    }
+
+    #[must_use]
+    pub fn descriptor(&self) -> &[u8] {
+        &self.descriptor[..self.len]
+    }
+}
+
+/// Report descriptor for a 40 cell braille display
+pub static DEFAULT_BRAILLE_DESCRIPTOR: BrailleDescriptor = BrailleDescriptor::new(40);
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "2")]
+pub struct BrailleButtons {
+    #[packed_field(bits = "7")]
+    pub dot_1: bool,
+    #[packed_field(bits = "6")]
+    pub dot_2: bool,
+    #[packed_field(bits = "5")]
+    pub dot_3: bool,
+    #[packed_field(bits = "4")]
+    pub dot_4: bool,
+    #[packed_field(bits = "3")]
+    pub dot_5: bool,
+    #[packed_field(bits = "2")]
+    pub dot_6: bool,
+    #[packed_field(bits = "1")]
+    pub dot_7: bool,
+    #[packed_field(bits = "0")]
+    pub dot_8: bool,
+    #[packed_field(bits = "15")]
+    pub space: bool,
+    #[packed_field(bits = "14")]
+    pub left_space: bool,
+    #[packed_field(bits = "13")]
+    pub right_space: bool,
+    #[packed_field(bits = "12")]
+    pub pan_left: bool,
+    #[packed_field(bits = "11")]
+    pub pan_right: bool,
+}
+
+/// Braille display input report
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct BrailleReport {
+    pub buttons: BrailleButtons,
+    /// Router key state, bit 0 is the router key above the first cell
+    pub router_keys: u64,
+}
+
+/// Braille cells set by the host, bit 0 of each cell is dot 1 and bit 7 is dot 8
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BrailleCells {
+    cells: [u8; BRAILLE_MAX_CELLS as usize],
+    len: u8,
+}
+
+impl BrailleCells {
+    #[must_use]
+    pub fn cells(&self) -> &[u8] {
+        &self.cells[..usize::from(self.len)]
+    }
+}
+
+impl Default for BrailleCells {
+    fn default() -> Self {
+        Self {
+            cells: [0; BRAILLE_MAX_CELLS as usize],
+            len: 0,
+        }
+    }
+}
+
+impl OutputReport for BrailleCells {
+    /// Parse the cells of a report, without the report ID
+    fn parse(data: &[u8]) -> usb_device::Result<Self> {
+        let mut report = Self::default();
+        report
+            .cells
+            .get_mut(..data.len())
+            .ok_or(UsbError::ParseError)?
+            .copy_from_slice(data);
+        report.len = u8::try_from(data.len()).map_err(|_| UsbError::ParseError)?;
+        Ok(report)
+    }
+}
+
+/// Braille display with a single row of 8 dot cells, router keys and braille keyboard
+///
+/// See [`BrailleDescriptor`]
+pub struct BrailleDisplay<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutBytes64, ReportSingle>,
+    cells: u8,
+    received: ReceivedReport<BrailleCells, { BRAILLE_MAX_CELLS as usize }>,
+}
+
+impl<B: UsbBus> BrailleDisplay<'_, B> {
+    pub fn write_report(&mut self, report: &BrailleReport) -> Result<(), UsbHidError> {
+        let mut data = [0; 8];
+        data[0] = BRAILLE_INPUT_REPORT_ID;
+        report.buttons.pack_to_slice(&mut data[1..3]).map_err(|_| {
+            error!("Error packing BrailleButtons");
+            UsbHidError::SerializationError
+        })?;
+
+        let router_len = usize::from(self.cells).div_ceil(8);
+        let router_keys = report.router_keys & ((1 << self.cells) - 1);
+        data[3..3 + router_len].copy_from_slice(&router_keys.to_le_bytes()[..router_len]);
+
+        self.interface
+            .write_report(&data[..3 + router_len])
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+
+    /// Read the cells last set by the host, returns [`UsbError::WouldBlock`] if none have been
+    /// set since the last read, see [`OutputReports::read_output_report`]
+    pub fn read_report(&mut self) -> usb_device::Result<BrailleCells> {
+        self.read_output_report()
+    }
+
+    /// Receive the cell reports, keeping the display's number of cells
+    fn receive_cells(&mut self) -> usb_device::Result<()> {
+        let interface = &mut self.interface;
+        let len = usize::from(self.cells);
+        self.received.receive(|report| {
+            let mut data = [0u8; 64];
+            let data_len = interface.read_report(&mut data)?;
+
+            match data[..data_len].split_first() {
+                Some((&BRAILLE_CELLS_REPORT_ID, cells)) if cells.len() >= len => {
+                    report[..len].copy_from_slice(&cells[..len]);
+                    Ok(len)
+                }
+                _ => Err(UsbError::ParseError),
+            }
+        })
+    }
+
+    #[must_use]
+    pub fn cells(&self) -> u8 {
+        self.cells
+    }
+}
+
+impl<B: UsbBus> OutputReports for BrailleDisplay<'_, B> {
+    type Report = BrailleCells;
+
+    fn read_output_report(&mut self) -> usb_device::Result<BrailleCells> {
+        self.receive_cells()?;
+        self.received.read()
+    }
+
+    fn output_report(&self) -> BrailleCells {
+        self.received.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<BrailleCells>> {
+        self.receive_cells()?;
+        Ok(self.received.changed())
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for BrailleDisplay<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutBytes64, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.received = ReceivedReport::default();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        if let Err(e) = self.receive_cells() {
+            warn!("Failed to receive braille cells: {:?}", e);
+        }
+        Ok(())
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        if report_id != BRAILLE_CELL_COUNT_REPORT_ID {
+            return Err(UsbError::Unsupported);
+        }
+        data.get_mut(..2)
+            .ok_or(UsbError::BufferOverflow)?
+            .copy_from_slice(&[report_id, self.cells]);
+        Ok(2)
+    }
+}
+
+pub struct BrailleDisplayConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutBytes64, ReportSingle>,
+    cells: u8,
+}
+
+impl Default for BrailleDisplayConfig<'_> {
+    fn default() -> Self {
+        Self::from_descriptor(&DEFAULT_BRAILLE_DESCRIPTOR)
+    }
+}
+
+impl<'a> BrailleDisplayConfig<'a> {
+    /// `interface` must use a report descriptor with `cells` cells, see [`BrailleDescriptor`]
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes8, OutBytes64, ReportSingle>,
+        cells: u8,
+    ) -> Self {
+        Self { interface, cells }
+    }
+
+    /// Configuration with the default interface settings for a generated descriptor
+    #[must_use]
+    pub fn from_descriptor(descriptor: &'static BrailleDescriptor) -> Self {
+        Self::new(
+            unwrap!(unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                descriptor.descriptor()
+            ))
+            .description("Braille Display")
+            .in_endpoint(10.millis()))
+            .with_out_endpoint(10.millis()))
+            .build(),
+            descriptor.cells(),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for BrailleDisplayConfig<'a> {
+    type Allocated = BrailleDisplay<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            cells: self.cells.min(BRAILLE_MAX_CELLS),
+            received: ReceivedReport::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::braille::{BrailleButtons, BrailleDescriptor, DEFAULT_BRAILLE_DESCRIPTOR};
+
+    #[test]
+    fn braille_buttons_bit_order() {
+        let bytes = BrailleButtons {
+            dot_1: true,
+            dot_8: true,
+            pan_right: true,
+            ..Default::default()
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(bytes, [0x81, 0x10]);
+    }
+
+    #[test]
+    fn router_keys_are_padded() {
+        static DESCRIPTOR: BrailleDescriptor = BrailleDescriptor::new(20);
+
+        let padded = DESCRIPTOR.descriptor();
+        let unpadded = DEFAULT_BRAILLE_DESCRIPTOR.descriptor();
+        assert_eq!(padded.len(), unpadded.len() + 4);
+        assert_eq!(padded[55..59], [0x95, 0x04, 0x81, 0x03]);
+    }
+}
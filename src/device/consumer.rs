@@ -1,14 +1,17 @@
 //!HID consumer control
 
-use fugit::ExtU32;
+use fugit::{ExtU32, MillisDurationU32};
 use packed_struct::prelude::*;
 #[allow(clippy::wildcard_imports)]
 use usb_device::class_prelude::*;
 
+use crate::interface::AutoRelease;
 use crate::page::Consumer;
 use crate::usb_class::prelude::*;
 
 ///Consumer control report descriptor - Four `u16` consumer control usage codes as an array (8 bytes)
+///
+/// The array covers every usage defined on the Consumer page, see [`Consumer`]
 #[rustfmt::skip]
 pub const MULTIPLE_CODE_REPORT_DESCRIPTOR: &[u8] = &[
     0x05, 0x0C, // Usage Page (Consumer),
@@ -17,9 +20,9 @@ pub const MULTIPLE_CODE_REPORT_DESCRIPTOR: &[u8] = &[
     0x75, 0x10, //     Report Size(16)
     0x95, 0x04, //     Report Count(4)
     0x15, 0x00, //     Logical Minimum(0)
-    0x26, 0x9C, 0x02, //     Logical Maximum(0x029C)
+    0x26, 0x14, 0x05, //     Logical Maximum(0x0514)
     0x19, 0x00, //     Usage Minimum(0)
-    0x2A, 0x9C, 0x02, //     Usage Maximum(0x029C)
+    0x2A, 0x14, 0x05, //     Usage Maximum(0x0514)
     0x81, 0x00, //     Input (Array, Data, Variable)
     0xC0, // End Collection
 ];
@@ -70,7 +73,7 @@ pub const FIXED_FUNCTION_REPORT_DESCRIPTOR: &[u8] = &[
 ];
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-#[derive(Clone, Copy, Debug, Eq, PartialEq, PackedStruct)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
 #[packed_struct(endian = "lsb", bit_numbering = "lsb0", size_bytes = "1")]
 pub struct FixedFunctionReport {
     #[packed_field(bits = "0")]
@@ -89,12 +92,25 @@ pub struct FixedFunctionReport {
     pub volume_decrement: bool,
 }
 
+/// Consumer control device
+///
+/// With [`ConsumerControlConfig::with_auto_release`], usages written with [`ConsumerControl::write_report`] are
+/// released automatically by [`DeviceClass::tick`], so only presses need writing
 pub struct ConsumerControl<'a, B: UsbBus> {
     interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    release: Option<AutoRelease>,
 }
 
 impl<B: UsbBus> ConsumerControl<'_, B> {
     pub fn write_report(&mut self, report: &MultipleConsumerReport) -> usb_device::Result<usize> {
+        let n = self.write(*report)?;
+        if let Some(release) = &mut self.release {
+            release.report_written(*report != MultipleConsumerReport::default());
+        }
+        Ok(n)
+    }
+
+    fn write(&mut self, report: MultipleConsumerReport) -> usb_device::Result<usize> {
         let data = report.pack().map_err(|_| {
             error!("Error packing MultipleConsumerReport");
             UsbError::ParseError
@@ -110,26 +126,54 @@ impl<'a, B: UsbBus> DeviceClass<'a> for ConsumerControl<'a, B> {
         &mut self.interface
     }
 
-    fn reset(&mut self) {}
+    fn reset(&mut self) {
+        if let Some(release) = &mut self.release {
+            release.released();
+        }
+    }
 
     fn tick(&mut self) -> Result<(), crate::UsbHidError> {
-        Ok(())
+        if !self.release.as_mut().is_some_and(AutoRelease::tick) {
+            return Ok(());
+        }
+
+        match self.write(MultipleConsumerReport::default()) {
+            Ok(_) => {
+                if let Some(release) = &mut self.release {
+                    release.released();
+                }
+                Ok(())
+            }
+            Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
     }
 }
 
 pub struct ConsumerControlConfig<'a> {
     interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+    release_delay: Option<MillisDurationU32>,
 }
 
 impl<'a> ConsumerControlConfig<'a> {
     #[must_use]
     pub fn new(interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>) -> Self {
-        Self { interface }
+        Self {
+            interface,
+            release_delay: None,
+        }
+    }
+
+    /// Release the usages written automatically, once the host has accepted the report and
+    /// `delay` has passed, such as 0ms to release them after the next poll
+    #[must_use]
+    pub fn with_auto_release(mut self, delay: MillisDurationU32) -> Self {
+        self.release_delay = Some(delay);
+        self
     }
 }
 
 impl Default for ConsumerControlConfig<'_> {
-    #[must_use]
     fn default() -> Self {
         Self::new(
             unwrap!(
@@ -146,21 +190,35 @@ impl Default for ConsumerControlConfig<'_> {
 impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for ConsumerControlConfig<'a> {
     type Allocated = ConsumerControl<'a, B>;
 
-    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        Self::Allocated {
-            interface: Interface::new(usb_alloc, self.interface),
-        }
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            release: self.release_delay.map(AutoRelease::new),
+        })
     }
 }
 
+/// Consumer control device
+///
+/// With [`ConsumerControlFixedConfig::with_auto_release`], usages written with [`ConsumerControlFixed::write_report`] are
+/// released automatically by [`DeviceClass::tick`], so only presses need writing
 pub struct ConsumerControlFixed<'a, B: UsbBus> {
     interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    release: Option<AutoRelease>,
 }
 
 impl<B: UsbBus> ConsumerControlFixed<'_, B> {
     pub fn write_report(&mut self, report: &FixedFunctionReport) -> usb_device::Result<usize> {
+        let n = self.write(*report)?;
+        if let Some(release) = &mut self.release {
+            release.report_written(*report != FixedFunctionReport::default());
+        }
+        Ok(n)
+    }
+
+    fn write(&mut self, report: FixedFunctionReport) -> usb_device::Result<usize> {
         let data = report.pack().map_err(|_| {
-            error!("Error packing MultipleConsumerReport");
+            error!("Error packing FixedFunctionReport");
             UsbError::ParseError
         })?;
         self.interface.write_report(&data)
@@ -174,25 +232,53 @@ impl<'a, B: UsbBus> DeviceClass<'a> for ConsumerControlFixed<'a, B> {
         &mut self.interface
     }
 
-    fn reset(&mut self) {}
+    fn reset(&mut self) {
+        if let Some(release) = &mut self.release {
+            release.released();
+        }
+    }
 
     fn tick(&mut self) -> Result<(), crate::UsbHidError> {
-        Ok(())
+        if !self.release.as_mut().is_some_and(AutoRelease::tick) {
+            return Ok(());
+        }
+
+        match self.write(FixedFunctionReport::default()) {
+            Ok(_) => {
+                if let Some(release) = &mut self.release {
+                    release.released();
+                }
+                Ok(())
+            }
+            Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
     }
 }
 
 pub struct ConsumerControlFixedConfig<'a> {
     interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+    release_delay: Option<MillisDurationU32>,
 }
 impl<'a> ConsumerControlFixedConfig<'a> {
     #[must_use]
     pub fn new(interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>) -> Self {
-        Self { interface }
+        Self {
+            interface,
+            release_delay: None,
+        }
+    }
+
+    /// Release the usages written automatically, once the host has accepted the report and
+    /// `delay` has passed, such as 0ms to release them after the next poll
+    #[must_use]
+    pub fn with_auto_release(mut self, delay: MillisDurationU32) -> Self {
+        self.release_delay = Some(delay);
+        self
     }
 }
 
 impl Default for ConsumerControlFixedConfig<'_> {
-    #[must_use]
     fn default() -> Self {
         Self::new(
             unwrap!(
@@ -209,9 +295,30 @@ impl Default for ConsumerControlFixedConfig<'_> {
 impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for ConsumerControlFixedConfig<'a> {
     type Allocated = ConsumerControlFixed<'a, B>;
 
-    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        Self::Allocated {
-            interface: Interface::new(usb_alloc, self.interface),
-        }
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            release: self.release_delay.map(AutoRelease::new),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::consumer::MULTIPLE_CODE_REPORT_DESCRIPTOR;
+    use crate::page::Consumer;
+
+    #[test]
+    fn multiple_code_descriptor_covers_consumer_page() {
+        let max = u16::from(Consumer::ContactMisc).to_le_bytes();
+
+        assert_eq!(
+            MULTIPLE_CODE_REPORT_DESCRIPTOR[12..15],
+            [0x26, max[0], max[1]]
+        );
+        assert_eq!(
+            MULTIPLE_CODE_REPORT_DESCRIPTOR[17..20],
+            [0x2A, max[0], max[1]]
+        );
     }
 }
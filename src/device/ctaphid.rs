@@ -0,0 +1,685 @@
+//! FIDO CTAPHID transport
+//!
+//! Implements the CTAPHID framing, channel allocation and the transport level commands so
+//! authenticator firmware only has to handle complete CTAP1/U2F and CTAP2 messages. See
+//! [CTAPHID](<https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#usb>)
+use crate::device::fido::FIDO_REPORT_DESCRIPTOR;
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use heapless::Vec;
+use num_enum::{FromPrimitive, IntoPrimitive};
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+pub const CTAPHID_PACKET_LEN: usize = 64;
+/// Largest message that can be framed, 57 bytes in the initialization packet and 128
+/// continuation packets of 59 bytes
+pub const CTAPHID_MAX_MESSAGE_LEN: usize = INIT_DATA_LEN + 128 * CONT_DATA_LEN;
+/// Default message buffer length, matches the CTAP2 default `maxMsgSize`
+pub const CTAPHID_DEFAULT_MESSAGE_LEN: usize = 1024;
+pub const CTAPHID_BROADCAST_CHANNEL: u32 = 0xFFFF_FFFF;
+
+const INIT_DATA_LEN: usize = CTAPHID_PACKET_LEN - 7;
+const CONT_DATA_LEN: usize = CTAPHID_PACKET_LEN - 5;
+const TRANSACTION_TIMEOUT_MS: u16 = 500;
+const PROTOCOL_VERSION: u8 = 2;
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, FromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum CtapHidCommand {
+    Ping = 0x01,
+    Msg = 0x03,
+    Lock = 0x04,
+    Init = 0x06,
+    Wink = 0x08,
+    Cbor = 0x10,
+    Cancel = 0x11,
+    Keepalive = 0x3B,
+    Error = 0x3F,
+    /// Vendor specific commands are in the range 0x40 to 0x7F
+    #[num_enum(catch_all)]
+    Other(u8),
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, IntoPrimitive)]
+#[repr(u8)]
+pub enum CtapHidError {
+    InvalidCmd = 0x01,
+    InvalidPar = 0x02,
+    InvalidLen = 0x03,
+    InvalidSeq = 0x04,
+    MsgTimeout = 0x05,
+    ChannelBusy = 0x06,
+    LockRequired = 0x0A,
+    InvalidChannel = 0x0B,
+    Other = 0x7F,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, IntoPrimitive)]
+#[repr(u8)]
+pub enum KeepaliveStatus {
+    Processing = 1,
+    UpNeeded = 2,
+}
+
+/// Capabilities reported in the INIT response
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct CtapHidCapabilities {
+    pub wink: bool,
+    pub cbor: bool,
+    /// Set if the device does not implement `CTAPHID_MSG`
+    pub nmsg: bool,
+}
+
+impl CtapHidCapabilities {
+    fn bits(self) -> u8 {
+        u8::from(self.wink) | u8::from(self.cbor) << 2 | u8::from(self.nmsg) << 3
+    }
+}
+
+/// A complete message received from the host
+///
+/// [`CtapHidCommand::Msg`], [`CtapHidCommand::Cbor`], [`CtapHidCommand::Wink`] and vendor
+/// commands must be answered with [`CtapHid::write_message`] on the same channel.
+/// [`CtapHidCommand::Cancel`] has no data and no response.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CtapHidMessage<const N: usize> {
+    pub channel: u32,
+    pub command: CtapHidCommand,
+    pub data: Vec<u8, N>,
+}
+
+struct Receive<const N: usize> {
+    channel: u32,
+    command: CtapHidCommand,
+    len: usize,
+    seq: u8,
+    data: Vec<u8, N>,
+    timeout_ms: u16,
+}
+
+struct Transmit<const N: usize> {
+    channel: u32,
+    command: CtapHidCommand,
+    data: Vec<u8, N>,
+    offset: usize,
+    seq: Option<u8>,
+}
+
+impl<const N: usize> Transmit<N> {
+    fn packet(&self) -> [u8; CTAPHID_PACKET_LEN] {
+        let mut packet = [0; CTAPHID_PACKET_LEN];
+        packet[..4].copy_from_slice(&self.channel.to_be_bytes());
+        let data = match self.seq {
+            None => {
+                packet[4] = 0x80 | u8::from(self.command);
+                #[allow(clippy::cast_possible_truncation)]
+                packet[5..7].copy_from_slice(&(self.data.len() as u16).to_be_bytes());
+                &mut packet[7..]
+            }
+            Some(seq) => {
+                packet[4] = seq;
+                &mut packet[5..]
+            }
+        };
+        let chunk = &self.data[self.offset..];
+        let len = chunk.len().min(data.len());
+        data[..len].copy_from_slice(&chunk[..len]);
+        packet
+    }
+
+    /// Advance to the next packet, returns false once the message is complete
+    fn advance(&mut self) -> bool {
+        let len = if self.seq.is_none() {
+            INIT_DATA_LEN
+        } else {
+            CONT_DATA_LEN
+        };
+        self.offset += len;
+        self.seq = Some(self.seq.map_or(0, |s| s + 1));
+        self.offset < self.data.len()
+    }
+}
+
+/// CTAPHID framing state machine, independent of the USB interface
+struct Framing<const N: usize> {
+    next_channel: u32,
+    busy: Option<u32>,
+    rx: Option<Receive<N>>,
+    tx: Option<Transmit<N>>,
+    device_version: [u8; 3],
+    capabilities: CtapHidCapabilities,
+}
+
+impl<const N: usize> Framing<N> {
+    fn new(device_version: [u8; 3], capabilities: CtapHidCapabilities) -> Self {
+        Self {
+            next_channel: 1,
+            busy: None,
+            rx: None,
+            tx: None,
+            device_version,
+            capabilities,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.busy = None;
+        self.rx = None;
+        self.tx = None;
+    }
+
+    fn queue(&mut self, channel: u32, command: CtapHidCommand, data: &[u8]) -> bool {
+        if self.tx.is_some() {
+            return false;
+        }
+        let Ok(data) = Vec::from_slice(data) else {
+            return false;
+        };
+        self.tx = Some(Transmit {
+            channel,
+            command,
+            data,
+            offset: 0,
+            seq: None,
+        });
+        true
+    }
+
+    fn error(&mut self, channel: u32, error: CtapHidError) {
+        warn!(
+            "CTAPHID error {:X} on channel {:X}",
+            u8::from(error),
+            channel
+        );
+        self.queue(channel, CtapHidCommand::Error, &[error.into()]);
+    }
+
+    fn abort(&mut self, channel: u32) {
+        if self.rx.as_ref().is_some_and(|rx| rx.channel == channel) {
+            self.rx = None;
+        }
+        if self.busy == Some(channel) {
+            self.busy = None;
+        }
+    }
+
+    fn allocate_channel(&mut self) -> u32 {
+        let channel = self.next_channel;
+        self.next_channel = match self.next_channel.wrapping_add(1) {
+            0 | CTAPHID_BROADCAST_CHANNEL => 1,
+            c => c,
+        };
+        channel
+    }
+
+    /// Process a received packet, must only be called once all pending packets have been sent
+    fn receive(&mut self, packet: &[u8; CTAPHID_PACKET_LEN]) -> Option<CtapHidMessage<N>> {
+        let channel = u32::from_be_bytes([packet[0], packet[1], packet[2], packet[3]]);
+
+        if packet[4] & 0x80 == 0 {
+            return self.receive_continuation(channel, packet[4], &packet[5..]);
+        }
+
+        let command = CtapHidCommand::from(packet[4] & 0x7F);
+        let len = usize::from(u16::from_be_bytes([packet[5], packet[6]]));
+        let data = &packet[7..];
+
+        if command == CtapHidCommand::Init {
+            self.init(channel, len, data);
+            return None;
+        }
+        if channel == 0 || channel == CTAPHID_BROADCAST_CHANNEL {
+            self.error(channel, CtapHidError::InvalidChannel);
+            return None;
+        }
+        if self.busy.is_some_and(|busy| busy != channel) {
+            self.error(channel, CtapHidError::ChannelBusy);
+            return None;
+        }
+        if command == CtapHidCommand::Cancel {
+            return (self.busy == Some(channel) && self.rx.is_none()).then(|| CtapHidMessage {
+                channel,
+                command,
+                data: Vec::new(),
+            });
+        }
+        if self.rx.is_some() {
+            self.abort(channel);
+            self.error(channel, CtapHidError::InvalidSeq);
+            return None;
+        }
+        if self.busy.is_some() {
+            // A request on this channel is still being processed
+            self.error(channel, CtapHidError::ChannelBusy);
+            return None;
+        }
+        if len > N || len > CTAPHID_MAX_MESSAGE_LEN {
+            self.error(channel, CtapHidError::InvalidLen);
+            return None;
+        }
+
+        let mut rx = Receive {
+            channel,
+            command,
+            len,
+            seq: 0,
+            data: Vec::new(),
+            timeout_ms: TRANSACTION_TIMEOUT_MS,
+        };
+        unwrap!(rx.data.extend_from_slice(&data[..len.min(INIT_DATA_LEN)]));
+        self.busy = Some(channel);
+        self.rx = Some(rx);
+        self.complete()
+    }
+
+    fn receive_continuation(
+        &mut self,
+        channel: u32,
+        seq: u8,
+        data: &[u8],
+    ) -> Option<CtapHidMessage<N>> {
+        // Spurious continuation packets are ignored
+        let rx = self.rx.as_mut().filter(|rx| rx.channel == channel)?;
+
+        if seq != rx.seq {
+            self.abort(channel);
+            self.error(channel, CtapHidError::InvalidSeq);
+            return None;
+        }
+
+        let remaining = rx.len - rx.data.len();
+        unwrap!(rx
+            .data
+            .extend_from_slice(&data[..remaining.min(CONT_DATA_LEN)]));
+        rx.seq += 1;
+        rx.timeout_ms = TRANSACTION_TIMEOUT_MS;
+        self.complete()
+    }
+
+    fn complete(&mut self) -> Option<CtapHidMessage<N>> {
+        if self.rx.as_ref().is_some_and(|rx| rx.data.len() < rx.len) {
+            return None;
+        }
+        let rx = self.rx.take()?;
+
+        match rx.command {
+            CtapHidCommand::Ping => {
+                self.busy = None;
+                self.queue(rx.channel, CtapHidCommand::Ping, &rx.data);
+                None
+            }
+            CtapHidCommand::Msg if self.capabilities.nmsg => {
+                self.busy = None;
+                self.error(rx.channel, CtapHidError::InvalidCmd);
+                None
+            }
+            CtapHidCommand::Wink if !self.capabilities.wink => {
+                self.busy = None;
+                self.error(rx.channel, CtapHidError::InvalidCmd);
+                None
+            }
+            CtapHidCommand::Cbor if !self.capabilities.cbor => {
+                self.busy = None;
+                self.error(rx.channel, CtapHidError::InvalidCmd);
+                None
+            }
+            CtapHidCommand::Msg | CtapHidCommand::Wink | CtapHidCommand::Cbor => {
+                Some(CtapHidMessage {
+                    channel: rx.channel,
+                    command: rx.command,
+                    data: rx.data,
+                })
+            }
+            CtapHidCommand::Other(command) if command >= 0x40 => Some(CtapHidMessage {
+                channel: rx.channel,
+                command: rx.command,
+                data: rx.data,
+            }),
+            _ => {
+                self.busy = None;
+                self.error(rx.channel, CtapHidError::InvalidCmd);
+                None
+            }
+        }
+    }
+
+    fn init(&mut self, channel: u32, len: usize, data: &[u8]) {
+        if len != 8 {
+            self.error(channel, CtapHidError::InvalidLen);
+            return;
+        }
+        if channel == 0 {
+            self.error(channel, CtapHidError::InvalidChannel);
+            return;
+        }
+
+        let new_channel = if channel == CTAPHID_BROADCAST_CHANNEL {
+            self.allocate_channel()
+        } else {
+            // Resynchronise an existing channel
+            self.abort(channel);
+            channel
+        };
+
+        let mut response = [0; 17];
+        response[..8].copy_from_slice(&data[..8]);
+        response[8..12].copy_from_slice(&new_channel.to_be_bytes());
+        response[12] = PROTOCOL_VERSION;
+        response[13..16].copy_from_slice(&self.device_version);
+        response[16] = self.capabilities.bits();
+        self.queue(channel, CtapHidCommand::Init, &response);
+    }
+
+    fn tick(&mut self) {
+        let Some(rx) = self.rx.as_mut() else {
+            return;
+        };
+        rx.timeout_ms = rx.timeout_ms.saturating_sub(1);
+        // Wait for the transmit buffer to be free before reporting the timeout
+        if rx.timeout_ms == 0 && self.tx.is_none() {
+            let channel = rx.channel;
+            self.abort(channel);
+            self.error(channel, CtapHidError::MsgTimeout);
+        }
+    }
+
+    fn packet(&self) -> Option<[u8; CTAPHID_PACKET_LEN]> {
+        self.tx.as_ref().map(Transmit::packet)
+    }
+
+    fn packet_sent(&mut self) {
+        if let Some(tx) = self.tx.as_mut() {
+            if !tx.advance() {
+                self.tx = None;
+            }
+        }
+    }
+}
+
+/// FIDO authenticator CTAPHID interface
+///
+/// `N` is the largest message that can be sent or received. [`CtapHid::read_message`] must be
+/// called regularly to process packets from the host, and [`DeviceClass::tick`] every 1ms to
+/// send responses and enforce transaction timeouts. PING and INIT are handled by the transport.
+pub struct CtapHid<'a, B: UsbBus, const N: usize = CTAPHID_DEFAULT_MESSAGE_LEN> {
+    interface: Interface<'a, B, InBytes64, OutBytes64, ReportSingle>,
+    framing: Framing<N>,
+}
+
+impl<B: UsbBus, const N: usize> CtapHid<'_, B, N> {
+    /// Read the next complete message from the host
+    pub fn read_message(&mut self) -> usb_device::Result<CtapHidMessage<N>> {
+        loop {
+            self.flush()?;
+
+            let mut packet = [0; CTAPHID_PACKET_LEN];
+            if self.interface.read_report(&mut packet)? != CTAPHID_PACKET_LEN {
+                warn!("Short CTAPHID packet");
+                continue;
+            }
+            if let Some(message) = self.framing.receive(&packet) {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Send a response message, returns [`UsbHidError::WouldBlock`] if a previous message is
+    /// still being sent
+    pub fn write_message(
+        &mut self,
+        channel: u32,
+        command: CtapHidCommand,
+        data: &[u8],
+    ) -> Result<(), UsbHidError> {
+        if self.framing.tx.is_some() {
+            return Err(UsbHidError::WouldBlock);
+        }
+        if data.len() > CTAPHID_MAX_MESSAGE_LEN || !self.framing.queue(channel, command, data) {
+            error!("CTAPHID message too long");
+            return Err(UsbHidError::SerializationError);
+        }
+        if command != CtapHidCommand::Keepalive && self.framing.busy == Some(channel) {
+            self.framing.busy = None;
+        }
+
+        match self.flush() {
+            Ok(()) | Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(UsbHidError::from(e)),
+        }
+    }
+
+    /// Send a keepalive while processing a request
+    pub fn write_keepalive(
+        &mut self,
+        channel: u32,
+        status: KeepaliveStatus,
+    ) -> Result<(), UsbHidError> {
+        self.write_message(channel, CtapHidCommand::Keepalive, &[status.into()])
+    }
+
+    /// Send an error response
+    pub fn write_error(&mut self, channel: u32, error: CtapHidError) -> Result<(), UsbHidError> {
+        self.write_message(channel, CtapHidCommand::Error, &[error.into()])
+    }
+
+    fn flush(&mut self) -> usb_device::Result<()> {
+        while let Some(packet) = self.framing.packet() {
+            self.interface.write_report(&packet)?;
+            self.framing.packet_sent();
+        }
+        Ok(())
+    }
+}
+
+impl<'a, B: UsbBus, const N: usize> DeviceClass<'a> for CtapHid<'a, B, N> {
+    type I = Interface<'a, B, InBytes64, OutBytes64, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.framing.reset();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        self.framing.tick();
+        match self.flush() {
+            Ok(()) | Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(UsbHidError::from(e)),
+        }
+    }
+}
+
+pub struct CtapHidConfig<'a, const N: usize = CTAPHID_DEFAULT_MESSAGE_LEN> {
+    interface: InterfaceConfig<'a, InBytes64, OutBytes64, ReportSingle>,
+    device_version: [u8; 3],
+    capabilities: CtapHidCapabilities,
+}
+
+impl<const N: usize> Default for CtapHidConfig<'_, N> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(
+                unwrap!(unwrap!(InterfaceBuilder::new(FIDO_REPORT_DESCRIPTOR))
+                    .description("FIDO Authenticator")
+                    .in_endpoint(5.millis()))
+                .with_out_endpoint(5.millis())
+            )
+            .build(),
+            [0, 0, 0],
+            CtapHidCapabilities {
+                wink: false,
+                cbor: true,
+                nmsg: false,
+            },
+        )
+    }
+}
+
+impl<'a, const N: usize> CtapHidConfig<'a, N> {
+    /// `device_version` is the major, minor and build version reported in the INIT response
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes64, OutBytes64, ReportSingle>,
+        device_version: [u8; 3],
+        capabilities: CtapHidCapabilities,
+    ) -> Self {
+        Self {
+            interface,
+            device_version,
+            capabilities,
+        }
+    }
+}
+
+impl<'a, B: UsbBus + 'a, const N: usize> UsbAllocatable<'a, B> for CtapHidConfig<'a, N> {
+    type Allocated = CtapHid<'a, B, N>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            framing: Framing::new(self.device_version, self.capabilities),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use crate::device::ctaphid::{
+        CtapHidCapabilities, CtapHidCommand, Framing, CTAPHID_BROADCAST_CHANNEL, CTAPHID_PACKET_LEN,
+    };
+
+    fn framing() -> Framing<256> {
+        Framing::new(
+            [1, 2, 3],
+            CtapHidCapabilities {
+                wink: true,
+                cbor: true,
+                nmsg: false,
+            },
+        )
+    }
+
+    fn init_packet(channel: u32, command: u8, len: u16, data: &[u8]) -> [u8; CTAPHID_PACKET_LEN] {
+        let mut packet = [0; CTAPHID_PACKET_LEN];
+        packet[..4].copy_from_slice(&channel.to_be_bytes());
+        packet[4] = 0x80 | command;
+        packet[5..7].copy_from_slice(&len.to_be_bytes());
+        packet[7..7 + data.len()].copy_from_slice(data);
+        packet
+    }
+
+    fn cont_packet(channel: u32, seq: u8, data: &[u8]) -> [u8; CTAPHID_PACKET_LEN] {
+        let mut packet = [0; CTAPHID_PACKET_LEN];
+        packet[..4].copy_from_slice(&channel.to_be_bytes());
+        packet[4] = seq;
+        packet[5..5 + data.len()].copy_from_slice(data);
+        packet
+    }
+
+    #[test]
+    fn init_allocates_channel() {
+        let mut framing = framing();
+        let nonce = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        assert!(framing
+            .receive(&init_packet(CTAPHID_BROADCAST_CHANNEL, 0x06, 8, &nonce))
+            .is_none());
+
+        let packet = framing.packet().unwrap();
+        assert_eq!(packet[..7], [0xFF, 0xFF, 0xFF, 0xFF, 0x86, 0x00, 0x11]);
+        assert_eq!(packet[7..15], nonce);
+        assert_eq!(packet[15..19], [0, 0, 0, 1]);
+        assert_eq!(packet[19..24], [2, 1, 2, 3, 0x05]);
+
+        framing.packet_sent();
+        assert!(framing.packet().is_none());
+    }
+
+    #[test]
+    fn fragmented_message_is_reassembled() {
+        let mut framing = framing();
+        let data: [u8; 100] = core::array::from_fn(|i| u8::try_from(i).unwrap());
+
+        assert!(framing
+            .receive(&init_packet(7, 0x10, 100, &data[..57]))
+            .is_none());
+        let message = framing.receive(&cont_packet(7, 0, &data[57..])).unwrap();
+
+        assert_eq!(message.channel, 7);
+        assert_eq!(message.command, CtapHidCommand::Cbor);
+        assert_eq!(message.data, data);
+        assert_eq!(framing.busy, Some(7));
+        assert!(framing.packet().is_none());
+    }
+
+    #[test]
+    fn ping_is_echoed_in_fragments() {
+        let mut framing = framing();
+        let data: [u8; 70] = core::array::from_fn(|i| u8::try_from(i).unwrap());
+
+        assert!(framing
+            .receive(&init_packet(7, 0x01, 70, &data[..57]))
+            .is_none());
+        assert!(framing.receive(&cont_packet(7, 0, &data[57..])).is_none());
+
+        let packet = framing.packet().unwrap();
+        assert_eq!(packet[..7], [0, 0, 0, 7, 0x81, 0x00, 70]);
+        assert_eq!(packet[7..], data[..57]);
+        framing.packet_sent();
+
+        let packet = framing.packet().unwrap();
+        assert_eq!(packet[..5], [0, 0, 0, 7, 0]);
+        assert_eq!(packet[5..18], data[57..]);
+        framing.packet_sent();
+
+        assert!(framing.packet().is_none());
+        assert_eq!(framing.busy, None);
+    }
+
+    #[test]
+    fn other_channel_is_busy() {
+        let mut framing = framing();
+
+        assert!(framing.receive(&init_packet(7, 0x10, 100, &[])).is_none());
+        assert!(framing.receive(&init_packet(8, 0x10, 1, &[])).is_none());
+
+        let packet = framing.packet().unwrap();
+        assert_eq!(packet[..8], [0, 0, 0, 8, 0xBF, 0x00, 0x01, 0x06]);
+    }
+
+    #[test]
+    fn out_of_sequence_continuation_is_rejected() {
+        let mut framing = framing();
+
+        assert!(framing.receive(&init_packet(7, 0x10, 100, &[])).is_none());
+        assert!(framing.receive(&cont_packet(7, 1, &[])).is_none());
+
+        let packet = framing.packet().unwrap();
+        assert_eq!(packet[..8], [0, 0, 0, 7, 0xBF, 0x00, 0x01, 0x04]);
+        assert_eq!(framing.busy, None);
+    }
+
+    #[test]
+    fn incomplete_message_times_out() {
+        let mut framing = framing();
+
+        assert!(framing.receive(&init_packet(7, 0x10, 100, &[])).is_none());
+        for _ in 0..500 {
+            framing.tick();
+        }
+
+        let packet = framing.packet().unwrap();
+        assert_eq!(packet[..8], [0, 0, 0, 7, 0xBF, 0x00, 0x01, 0x05]);
+        assert_eq!(framing.busy, None);
+    }
+}
@@ -0,0 +1,464 @@
+//!Firmware update over vendor defined HID reports
+//!
+//! Firmware images are sent by the host in order as 64 byte output reports, each holding a
+//! [`DfuChunk`] of up to [`DFU_CHUNK_MAX_DATA`] bytes with its offset in the image and a CRC.
+//! The host starts, finishes or aborts an update with a [`DfuCommand`] feature report and reads
+//! progress with a [`DfuStatus`] feature report. Writing the image to flash, and rebooting into
+//! it, is left to the application.
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Length of the output and feature reports
+pub const DFU_REPORT_LEN: usize = 64;
+/// Largest number of image bytes in a [`DfuChunk`]
+pub const DFU_CHUNK_MAX_DATA: usize = DFU_REPORT_LEN - 7;
+
+/// Vendor defined usage page 0xFF00, usage 0xDF
+#[rustfmt::skip]
+pub const DFU_REPORT_DESCRIPTOR: &[u8] = &[
+    0x06, 0x00, 0xFF,  // Usage Page (Vendor Defined 0xFF00),
+    0x09, 0xDF,        // Usage (0xDF),
+    0xA1, 0x01,        // Collection (Application),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x40,        //   Report Count (64),
+    0x09, 0x01,        //   Usage (1),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x09, 0x02,        //   Usage (2),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x09, 0x03,        //   Usage (3),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// CRC-16/CCITT-FALSE of `data`, used to check each [`DfuChunk`]
+#[must_use]
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, b| {
+        (0..8).fold(crc ^ (u16::from(*b) << 8), |crc, _| {
+            if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x1021
+            }
+        })
+    })
+}
+
+/// Continue a CRC-32 (IEEE 802.3), as used to check the whole image, with `data`
+///
+/// Start with a `crc` of 0
+#[must_use]
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    !data.iter().fold(!crc, |crc, b| {
+        (0..8).fold(crc ^ u32::from(*b), |crc, _| {
+            if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xEDB8_8320
+            }
+        })
+    })
+}
+
+/// A block of the firmware image, sent by the host as an output report
+///
+/// | Bytes   | Field                                              |
+/// |---------|----------------------------------------------------|
+/// | 0..4    | Offset of the block in the image, little endian    |
+/// | 4       | Number of image bytes, up to [`DFU_CHUNK_MAX_DATA`] |
+/// | 5..62   | Image bytes, zero padded                           |
+/// | 62..64  | [`crc16`] of bytes 0..62, little endian            |
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DfuChunk {
+    pub offset: u32,
+    len: u8,
+    data: [u8; DFU_CHUNK_MAX_DATA],
+}
+
+impl DfuChunk {
+    /// Parse an output report, returns `None` if the length or CRC are invalid
+    #[must_use]
+    pub fn from_report(report: &[u8; DFU_REPORT_LEN]) -> Option<Self> {
+        let (body, crc) = report.split_at(DFU_REPORT_LEN - 2);
+        if crc16(body).to_le_bytes() != *crc || usize::from(body[4]) > DFU_CHUNK_MAX_DATA {
+            return None;
+        }
+
+        let mut data = [0; DFU_CHUNK_MAX_DATA];
+        data.copy_from_slice(&body[5..]);
+        Some(Self {
+            offset: u32::from_le_bytes([body[0], body[1], body[2], body[3]]),
+            len: body[4],
+            data,
+        })
+    }
+
+    /// Build the output report for a block of the image, as sent by the host
+    ///
+    /// Returns `None` if `data` is longer than [`DFU_CHUNK_MAX_DATA`]
+    #[must_use]
+    pub fn to_report(offset: u32, data: &[u8]) -> Option<[u8; DFU_REPORT_LEN]> {
+        let len = u8::try_from(data.len())
+            .ok()
+            .filter(|l| usize::from(*l) <= DFU_CHUNK_MAX_DATA)?;
+
+        let mut report = [0; DFU_REPORT_LEN];
+        report[..4].copy_from_slice(&offset.to_le_bytes());
+        report[4] = len;
+        report[5..5 + data.len()].copy_from_slice(data);
+        let crc = crc16(&report[..DFU_REPORT_LEN - 2]);
+        report[DFU_REPORT_LEN - 2..].copy_from_slice(&crc.to_le_bytes());
+        Some(report)
+    }
+
+    /// Image bytes in the block
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data[..usize::from(self.len)]
+    }
+}
+
+impl Default for DfuChunk {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            len: 0,
+            data: [0; DFU_CHUNK_MAX_DATA],
+        }
+    }
+}
+
+impl OutputReport for DfuChunk {
+    fn parse(data: &[u8]) -> usb_device::Result<Self> {
+        data.try_into()
+            .ok()
+            .and_then(Self::from_report)
+            .ok_or(UsbError::ParseError)
+    }
+}
+
+/// Update request from the host, sent as a feature report
+///
+/// The first byte is the command, 1 to start, 2 to finish and 3 to abort, followed by the
+/// little endian `u32` argument
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DfuCommand {
+    /// Start an update of `length` bytes, the application should prepare, e.g. erase, the
+    /// update area
+    Start { length: u32 },
+    /// All chunks have been sent, `crc` is the [`crc32_update`] of the whole image. Only passed
+    /// to the application if the image was received completely and the CRC matches.
+    Finish { crc: u32 },
+    /// Abandon the update
+    Abort,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum DfuState {
+    #[default]
+    Idle = 0,
+    Receiving = 1,
+    Complete = 2,
+    Error = 3,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum DfuError {
+    #[default]
+    None = 0,
+    /// A chunk was received without a started update
+    NotStarted = 1,
+    /// A chunk was not at the next expected offset
+    UnexpectedOffset = 2,
+    /// A chunk failed its CRC check
+    ChunkCrc = 3,
+    /// A chunk extended beyond the image, or the update finished before the whole image was sent
+    Length = 4,
+    /// The CRC of the whole image did not match
+    ImageCrc = 5,
+    /// The application failed to store the image, see [`Dfu::set_error`]
+    Device = 6,
+}
+
+/// Update progress, read by the host as a feature report padded to [`DFU_REPORT_LEN`]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "10")]
+pub struct DfuStatus {
+    #[packed_field(bytes = "0", ty = "enum")]
+    pub state: DfuState,
+    #[packed_field(bytes = "1", ty = "enum")]
+    pub error: DfuError,
+    /// Offset of the next chunk expected
+    #[packed_field(bytes = "2..=5")]
+    pub offset: u32,
+    /// Length of the image being received
+    #[packed_field(bytes = "6..=9")]
+    pub length: u32,
+}
+
+/// Firmware update interface
+///
+/// Chunks must be read with [`Dfu::read_chunk`] and commands with [`Dfu::read_command`] as they
+/// arrive, chunks are received one at a time as they're read, so none are dropped. The host should read the [`DfuStatus`] after each chunk, so that it doesn't send
+/// chunks faster than the application can store them.
+pub struct Dfu<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes64, OutBytes64, ReportSingle>,
+    status: DfuStatus,
+    crc: u32,
+    command: Option<DfuCommand>,
+    received: ReceivedReport<DfuChunk, DFU_REPORT_LEN>,
+}
+
+impl<B: UsbBus> Dfu<'_, B> {
+    /// Read the next chunk of the image
+    ///
+    /// Returns [`UsbError::WouldBlock`] if no chunk is available and [`UsbError::ParseError`] if
+    /// the chunk is invalid or out of order, in which case the update is put in the error state,
+    /// see [`OutputReports::read_output_report`]
+    pub fn read_chunk(&mut self) -> usb_device::Result<DfuChunk> {
+        self.read_output_report()
+    }
+
+    /// Take the last command from the host, if any
+    pub fn read_command(&mut self) -> Option<DfuCommand> {
+        self.command.take()
+    }
+
+    /// Put the update in the error state, e.g. if writing to flash fails
+    pub fn set_error(&mut self, error: DfuError) {
+        self.fail(error);
+    }
+
+    #[must_use]
+    pub fn status(&self) -> DfuStatus {
+        self.status
+    }
+
+    /// Receive the next chunk, if any, and add it to the image
+    fn receive_chunk(&mut self) -> usb_device::Result<()> {
+        let mut error = None;
+        let received = self.received.receive_next(|data| {
+            let len = self.interface.read_report(data)?;
+            if len != DFU_REPORT_LEN {
+                return Err(UsbError::ParseError);
+            }
+            error = Self::check_chunk(&self.status, &data[..len]).err();
+            error.map_or(Ok(len), |_| Err(UsbError::ParseError))
+        });
+        if let Some(error) = error {
+            return Err(self.fail(error));
+        }
+
+        if received? {
+            let chunk = self.received.last();
+            self.crc = crc32_update(self.crc, chunk.data());
+            self.status.offset += u32::from(chunk.len);
+        }
+        Ok(())
+    }
+
+    /// Check that `report` is a valid chunk following the last for the update in `status`
+    fn check_chunk(status: &DfuStatus, report: &[u8]) -> Result<(), DfuError> {
+        let chunk = DfuChunk::parse(report).map_err(|_| DfuError::ChunkCrc)?;
+
+        if status.state != DfuState::Receiving {
+            return Err(DfuError::NotStarted);
+        }
+        if chunk.offset != status.offset {
+            return Err(DfuError::UnexpectedOffset);
+        }
+        if status.offset.saturating_add(u32::from(chunk.len)) > status.length {
+            return Err(DfuError::Length);
+        }
+        Ok(())
+    }
+
+    fn fail(&mut self, error: DfuError) -> UsbError {
+        warn!("Firmware update failed - {}", error as u8);
+        self.status.state = DfuState::Error;
+        self.status.error = error;
+        UsbError::ParseError
+    }
+}
+
+impl<B: UsbBus> OutputReports for Dfu<'_, B> {
+    type Report = DfuChunk;
+
+    fn read_output_report(&mut self) -> usb_device::Result<DfuChunk> {
+        self.receive_chunk()?;
+        self.received.read()
+    }
+
+    fn output_report(&self) -> DfuChunk {
+        self.received.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<DfuChunk>> {
+        self.receive_chunk()?;
+        Ok(self.received.changed())
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for Dfu<'a, B> {
+    type I = Interface<'a, B, InBytes64, OutBytes64, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.status = DfuStatus::default();
+        self.crc = 0;
+        self.command = None;
+        self.received = ReceivedReport::default();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        if report_id != 0 {
+            return Err(UsbError::Unsupported);
+        }
+        let out = data
+            .get_mut(..DFU_REPORT_LEN)
+            .ok_or(UsbError::BufferOverflow)?;
+        out.fill(0);
+        self.status
+            .pack_to_slice(&mut out[..10])
+            .map_err(|_| UsbError::ParseError)?;
+        Ok(DFU_REPORT_LEN)
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        if report_id != 0 {
+            return Err(UsbError::Unsupported);
+        }
+        let (command, argument) = match data {
+            [command, a, b, c, d, ..] => (*command, u32::from_le_bytes([*a, *b, *c, *d])),
+            _ => return Err(UsbError::ParseError),
+        };
+
+        info!("Firmware update command {}, argument {}", command, argument);
+        let command = match command {
+            1 => {
+                self.status = DfuStatus {
+                    state: DfuState::Receiving,
+                    error: DfuError::None,
+                    offset: 0,
+                    length: argument,
+                };
+                self.crc = 0;
+                DfuCommand::Start { length: argument }
+            }
+            2 => {
+                if self.status.state != DfuState::Receiving {
+                    self.fail(DfuError::NotStarted);
+                    return Ok(());
+                }
+                if self.status.offset != self.status.length {
+                    self.fail(DfuError::Length);
+                    return Ok(());
+                }
+                if self.crc != argument {
+                    self.fail(DfuError::ImageCrc);
+                    return Ok(());
+                }
+                self.status.state = DfuState::Complete;
+                DfuCommand::Finish { crc: argument }
+            }
+            3 => {
+                self.status = DfuStatus::default();
+                DfuCommand::Abort
+            }
+            _ => return Err(UsbError::ParseError),
+        };
+
+        self.command = Some(command);
+        Ok(())
+    }
+}
+
+pub struct DfuConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes64, OutBytes64, ReportSingle>,
+}
+
+impl Default for DfuConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(
+                unwrap!(unwrap!(InterfaceBuilder::new(DFU_REPORT_DESCRIPTOR))
+                    .description("Firmware Update")
+                    .in_endpoint(10.millis()))
+                .with_out_endpoint(1.millis())
+            )
+            .build(),
+        )
+    }
+}
+
+impl<'a> DfuConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes64, OutBytes64, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for DfuConfig<'a> {
+    type Allocated = Dfu<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            status: DfuStatus::default(),
+            crc: 0,
+            command: None,
+            received: ReceivedReport::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use crate::device::dfu::{crc16, crc32_update, DfuChunk};
+
+    #[test]
+    fn crc_check_values() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(crc32_update(0, b"123456789"), 0xCBF4_3926);
+        assert_eq!(
+            crc32_update(crc32_update(0, b"1234"), b"56789"),
+            0xCBF4_3926
+        );
+    }
+
+    #[test]
+    fn chunk_round_trip() {
+        let mut report = DfuChunk::to_report(0x0001_0200, &[0xAA, 0xBB, 0xCC]).unwrap();
+        assert_eq!(
+            report[..8],
+            [0x00, 0x02, 0x01, 0x00, 0x03, 0xAA, 0xBB, 0xCC]
+        );
+
+        let chunk = DfuChunk::from_report(&report).unwrap();
+        assert_eq!(chunk.offset, 0x0001_0200);
+        assert_eq!(chunk.data(), [0xAA, 0xBB, 0xCC]);
+
+        report[5] = 0;
+        assert!(DfuChunk::from_report(&report).is_none());
+    }
+}
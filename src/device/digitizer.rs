@@ -0,0 +1,792 @@
+//!HID digitizers
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use heapless::{Deque, Vec};
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the [`MultiTouchReport`] input report
+pub const MULTI_TOUCH_INPUT_REPORT_ID: u8 = 0x01;
+/// Report ID of the Contact Count Maximum feature report
+pub const MULTI_TOUCH_CONTACT_COUNT_MAXIMUM_REPORT_ID: u8 = 0x02;
+/// Number of contacts carried by a single [`MultiTouchReport`]
+pub const MULTI_TOUCH_CONTACTS_PER_REPORT: usize = 5;
+/// Maximum number of simultaneous contacts tracked by a [`ContactTracker`]
+pub const MULTI_TOUCH_MAX_TRACKED_CONTACTS: usize = 10;
+/// Maximum number of contacts in a frame from [`ContactTracker::update`], the tracked contacts
+/// and the contacts lifted off since the last frame
+pub const MULTI_TOUCH_MAX_FRAME_CONTACTS: usize = 2 * MULTI_TOUCH_MAX_TRACKED_CONTACTS;
+const MULTI_TOUCH_MAX_FRAME_REPORTS: usize =
+    MULTI_TOUCH_MAX_FRAME_CONTACTS.div_ceil(MULTI_TOUCH_CONTACTS_PER_REPORT);
+
+/// Multi-touch touchscreen report descriptor
+///
+/// Input report 1 carries five contacts, each with tip switch, in range, contact identifier and
+/// absolute X/Y in the range 0-32767, followed by the number of valid contacts. Feature report 2
+/// is the Contact Count Maximum report required by Windows.
+///
+/// Reference: <https://learn.microsoft.com/en-us/windows-hardware/design/component-guidelines/touchscreen-required-hid-top-level-collections>
+#[rustfmt::skip]
+pub const MULTI_TOUCH_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0D,        // Usage Page (Digitizers),
+    0x09, 0x04,        // Usage (Touch Screen),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+
+    // contact 1
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x09, 0x32,        //     Usage (In Range),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Contact Identifier),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+
+    // contact 2
+    0x05, 0x0D,        //   Usage Page (Digitizers),
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x09, 0x32,        //     Usage (In Range),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Contact Identifier),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+
+    // contact 3
+    0x05, 0x0D,        //   Usage Page (Digitizers),
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x09, 0x32,        //     Usage (In Range),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Contact Identifier),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+
+    // contact 4
+    0x05, 0x0D,        //   Usage Page (Digitizers),
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x09, 0x32,        //     Usage (In Range),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Contact Identifier),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+
+    // contact 5
+    0x05, 0x0D,        //   Usage Page (Digitizers),
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x09, 0x32,        //     Usage (In Range),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Contact Identifier),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+
+    0x05, 0x0D,        //   Usage Page (Digitizers),
+    0x09, 0x54,        //   Usage (Contact Count),
+    0x25, 0x7F,        //   Logical Maximum (127),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x01,        //   Report Count (1),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+
+    0x85, 0x02,        //   Report ID (2),
+    0x09, 0x55,        //   Usage (Contact Count Maximum),
+    0x25, 0x7F,        //   Logical Maximum (127),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// A single touch contact
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "6")]
+pub struct TouchContact {
+    #[packed_field(bits = "7")]
+    pub tip_switch: bool,
+    #[packed_field(bits = "6")]
+    pub in_range: bool,
+    /// Identifier that must remain stable for the lifetime of the contact
+    #[packed_field(bytes = "1")]
+    pub contact_id: u8,
+    /// Absolute X position, 0-32767
+    #[packed_field(bytes = "2..=3")]
+    pub x: u16,
+    /// Absolute Y position, 0-32767
+    #[packed_field(bytes = "4..=5")]
+    pub y: u16,
+}
+
+/// Multi-touch input report, excluding the report ID
+///
+/// Devices supporting more than [`MULTI_TOUCH_CONTACTS_PER_REPORT`] contacts should use hybrid
+/// mode: the first report of a frame carries the total `contact_count` and subsequent reports for
+/// the same frame carry a `contact_count` of zero.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "31")]
+pub struct MultiTouchReport {
+    #[packed_field(bytes = "0..30", element_size_bytes = "6")]
+    pub contacts: [TouchContact; 5],
+    #[packed_field(bytes = "30")]
+    pub contact_count: u8,
+}
+
+impl MultiTouchReport {
+    /// The reports of a frame of `contacts` in hybrid mode, [`MULTI_TOUCH_CONTACTS_PER_REPORT`]
+    /// contacts per report with the total contact count in the first report only
+    pub fn hybrid_reports(contacts: &[TouchContact]) -> impl Iterator<Item = Self> + '_ {
+        #[allow(clippy::cast_possible_truncation)]
+        let contact_count = contacts.len().min(usize::from(u8::MAX)) as u8;
+        contacts
+            .chunks(MULTI_TOUCH_CONTACTS_PER_REPORT)
+            .enumerate()
+            .map(move |(i, chunk)| {
+                let mut report = Self {
+                    contact_count: if i == 0 { contact_count } else { 0 },
+                    ..Default::default()
+                };
+                report.contacts[..chunk.len()].copy_from_slice(chunk);
+                report
+            })
+    }
+}
+
+/// Raw touch point, such as read from a touch controller, before it's tracked as a contact
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct TouchPoint {
+    /// Absolute X position, 0-32767
+    pub x: u16,
+    /// Absolute Y position, 0-32767
+    pub y: u16,
+}
+
+/// Tracks touch points between frames as contacts with stable contact identifiers
+///
+/// Each point is matched to the nearest contact of the last frame within the maximum distance,
+/// keeping its contact identifier, other points are new contacts with the lowest free identifier.
+/// Contacts of the last frame without a point are reported once more with the tip switch cleared,
+/// as the host requires, before their identifier is freed.
+#[derive(Clone, Debug)]
+pub struct ContactTracker {
+    contacts: Vec<TouchContact, MULTI_TOUCH_MAX_TRACKED_CONTACTS>,
+    contact_count_maximum: usize,
+    max_distance: u16,
+}
+
+impl ContactTracker {
+    /// Track up to `contact_count_maximum` contacts, at most
+    /// [`MULTI_TOUCH_MAX_TRACKED_CONTACTS`], matching points within `max_distance` of a contact,
+    /// in either axis, to the contact
+    #[must_use]
+    pub fn new(contact_count_maximum: u8, max_distance: u16) -> Self {
+        Self {
+            contacts: Vec::new(),
+            contact_count_maximum: usize::from(contact_count_maximum)
+                .min(MULTI_TOUCH_MAX_TRACKED_CONTACTS),
+            max_distance,
+        }
+    }
+
+    /// Track the points touching in this frame, returning the contacts to report: the points
+    /// touching, then the contacts lifted off
+    ///
+    /// Points beyond the contact count maximum are ignored.
+    pub fn update(
+        &mut self,
+        points: &[TouchPoint],
+    ) -> Vec<TouchContact, MULTI_TOUCH_MAX_FRAME_CONTACTS> {
+        let points = &points[..points.len().min(self.contact_count_maximum)];
+        let mut matched: Vec<Option<u8>, MULTI_TOUCH_MAX_TRACKED_CONTACTS> =
+            points.iter().map(|_| None).collect();
+        let mut frame = Vec::new();
+        let mut lifted: Vec<TouchContact, MULTI_TOUCH_MAX_TRACKED_CONTACTS> = Vec::new();
+
+        for contact in &self.contacts {
+            let nearest = points
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| matched[*i].is_none())
+                .map(|(i, point)| (i, Self::distance(*contact, *point)))
+                .filter(|(_, distance)| *distance <= self.max_distance)
+                .min_by_key(|(_, distance)| *distance);
+            if let Some((i, _)) = nearest {
+                matched[i] = Some(contact.contact_id);
+            } else {
+                trace!("Contact {} lifted off", contact.contact_id);
+                // Capacity is the same as the tracked contacts
+                let _ = lifted.push(TouchContact {
+                    tip_switch: false,
+                    in_range: false,
+                    ..*contact
+                });
+            }
+        }
+
+        self.contacts.clear();
+        for (point, contact_id) in points.iter().zip(&matched) {
+            let contact_id = contact_id.unwrap_or_else(|| {
+                (0..=u8::MAX)
+                    .find(|id| {
+                        !matched.contains(&Some(*id))
+                            && !self.contacts.iter().any(|c| c.contact_id == *id)
+                            && !lifted.iter().any(|c| c.contact_id == *id)
+                    })
+                    .unwrap_or_default()
+            });
+            let contact = TouchContact {
+                tip_switch: true,
+                in_range: true,
+                contact_id,
+                x: point.x,
+                y: point.y,
+            };
+            // Points are limited to the tracked contacts capacity
+            let _ = self.contacts.push(contact);
+            let _ = frame.push(contact);
+        }
+        let _ = frame.extend_from_slice(&lifted);
+        frame
+    }
+
+    /// The contacts touching in the last frame
+    #[must_use]
+    pub fn contacts(&self) -> &[TouchContact] {
+        &self.contacts
+    }
+
+    /// Forget all contacts, without reporting them lifted off
+    pub fn reset(&mut self) {
+        self.contacts.clear();
+    }
+
+    fn distance(contact: TouchContact, point: TouchPoint) -> u16 {
+        contact.x.abs_diff(point.x).max(contact.y.abs_diff(point.y))
+    }
+}
+
+/// Multi-touch touchscreen
+///
+/// Answers the Contact Count Maximum feature report with the value supplied in
+/// [`MultiTouchConfig`].
+///
+/// Reports can be written directly with [`MultiTouch::write_report`], or built from raw touch
+/// points with [`MultiTouch::write_contacts`], which tracks the contacts with a
+/// [`ContactTracker`] and writes the frame in hybrid mode.
+pub struct MultiTouch<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes32, OutNone, ReportSingle>,
+    contact_count_maximum: u8,
+    tracker: ContactTracker,
+    pending: Deque<MultiTouchReport, MULTI_TOUCH_MAX_FRAME_REPORTS>,
+}
+
+impl<B: UsbBus> MultiTouch<'_, B> {
+    /// Write a frame of the points currently touching, tracked as contacts by the device's
+    /// [`ContactTracker`]
+    ///
+    /// Reports of the frame the endpoint is too busy for are sent from [`DeviceClass::tick`].
+    /// Returns [`UsbHidError::WouldBlock`], without tracking the points, while reports of the last
+    /// frame are still to be sent.
+    pub fn write_contacts(&mut self, points: &[TouchPoint]) -> Result<(), UsbHidError> {
+        self.write_pending()?;
+        let frame = self.tracker.update(points);
+        for report in MultiTouchReport::hybrid_reports(&frame) {
+            // The deque holds the reports of a full frame
+            let _ = self.pending.push_back(report);
+        }
+        match self.write_pending() {
+            Err(UsbHidError::WouldBlock) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Whether reports of the last frame written with [`MultiTouch::write_contacts`] are still to
+    /// be sent
+    #[must_use]
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    #[must_use]
+    pub fn tracker(&self) -> &ContactTracker {
+        &self.tracker
+    }
+
+    fn write_pending(&mut self) -> Result<(), UsbHidError> {
+        while let Some(report) = self.pending.front() {
+            let report = *report;
+            self.write_report(&report)?;
+            self.pending.pop_front();
+        }
+        Ok(())
+    }
+
+    pub fn write_report(&mut self, report: &MultiTouchReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|_| {
+            error!("Error packing MultiTouchReport");
+            UsbHidError::SerializationError
+        })?;
+
+        let mut buffer = [0u8; 32];
+        buffer[0] = MULTI_TOUCH_INPUT_REPORT_ID;
+        buffer[1..].copy_from_slice(&data);
+
+        self.interface
+            .write_report(&buffer)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+
+    #[must_use]
+    pub fn contact_count_maximum(&self) -> u8 {
+        self.contact_count_maximum
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for MultiTouch<'a, B> {
+    type I = Interface<'a, B, InBytes32, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.tracker.reset();
+        self.pending.clear();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        match self.write_pending() {
+            Err(UsbHidError::WouldBlock) => Ok(()),
+            result => result,
+        }
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        if report_id != MULTI_TOUCH_CONTACT_COUNT_MAXIMUM_REPORT_ID {
+            return Err(UsbError::Unsupported);
+        }
+        if data.len() < 2 {
+            return Err(UsbError::BufferOverflow);
+        }
+        data[0] = report_id;
+        data[1] = self.contact_count_maximum;
+        Ok(2)
+    }
+}
+
+pub struct MultiTouchConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes32, OutNone, ReportSingle>,
+    contact_count_maximum: u8,
+    max_contact_distance: u16,
+}
+
+impl Default for MultiTouchConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                MULTI_TOUCH_REPORT_DESCRIPTOR
+            ))
+            .description("Touch Screen")
+            .in_endpoint(5.millis()))
+            .without_out_endpoint()
+            .build(),
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                MULTI_TOUCH_CONTACTS_PER_REPORT as u8
+            },
+        )
+    }
+}
+
+impl<'a> MultiTouchConfig<'a> {
+    /// `contact_count_maximum` is the maximum number of simultaneous contacts reported to the host
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes32, OutNone, ReportSingle>,
+        contact_count_maximum: u8,
+    ) -> Self {
+        Self {
+            interface,
+            contact_count_maximum,
+            max_contact_distance: 2048,
+        }
+    }
+
+    /// Maximum distance a contact tracked by [`MultiTouch::write_contacts`] can move between
+    /// frames, in either axis, defaults to 2048
+    #[must_use]
+    pub fn with_max_contact_distance(mut self, max_contact_distance: u16) -> Self {
+        self.max_contact_distance = max_contact_distance;
+        self
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for MultiTouchConfig<'a> {
+    type Allocated = MultiTouch<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            contact_count_maximum: self.contact_count_maximum,
+            tracker: ContactTracker::new(self.contact_count_maximum, self.max_contact_distance),
+            pending: Deque::new(),
+        })
+    }
+}
+
+/// Pen digitizer report descriptor
+///
+/// A single stylus reporting tip, barrel, invert and eraser switches, in range, absolute X/Y in
+/// the range 0-32767, tip pressure in the range 0-4095 and X/Y tilt in degrees (-90 to 90).
+///
+/// Reference: <https://learn.microsoft.com/en-us/windows-hardware/design/component-guidelines/required-hid-top-level-collections>
+#[rustfmt::skip]
+pub const PEN_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0D,        // Usage Page (Digitizers),
+    0x09, 0x02,        // Usage (Pen),
+    0xA1, 0x01,        // Collection (Application),
+    0x09, 0x20,        //   Usage (Stylus),
+    0xA1, 0x00,        //   Collection (Physical),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x09, 0x44,        //     Usage (Barrel Switch),
+    0x09, 0x3C,        //     Usage (Invert),
+    0x09, 0x45,        //     Usage (Eraser),
+    0x09, 0x32,        //     Usage (In Range),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x05,        //     Report Count (5),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x03,        //     Report Count (3),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+
+    0x05, 0x0D,        //     Usage Page (Digitizers),
+    0x09, 0x30,        //     Usage (Tip Pressure),
+    0x26, 0xFF, 0x0F,  //     Logical Maximum (4095),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+
+    0x09, 0x3D,        //     Usage (X Tilt),
+    0x09, 0x3E,        //     Usage (Y Tilt),
+    0x15, 0xA6,        //     Logical Minimum (-90),
+    0x25, 0x5A,        //     Logical Maximum (90),
+    0x35, 0xA6,        //     Physical Minimum (-90),
+    0x45, 0x5A,        //     Physical Maximum (90),
+    0x65, 0x14,        //     Unit (Degrees),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "9")]
+pub struct PenReport {
+    #[packed_field(bits = "7")]
+    pub tip_switch: bool,
+    #[packed_field(bits = "6")]
+    pub barrel_switch: bool,
+    /// Set while the pen is inverted, i.e. the eraser end is in range
+    #[packed_field(bits = "5")]
+    pub invert: bool,
+    #[packed_field(bits = "4")]
+    pub eraser: bool,
+    #[packed_field(bits = "3")]
+    pub in_range: bool,
+    /// Absolute X position, 0-32767
+    #[packed_field(bytes = "1..=2")]
+    pub x: u16,
+    /// Absolute Y position, 0-32767
+    #[packed_field(bytes = "3..=4")]
+    pub y: u16,
+    /// Tip pressure, 0-4095
+    #[packed_field(bytes = "5..=6")]
+    pub tip_pressure: u16,
+    /// X tilt in degrees, -90 to 90
+    #[packed_field(bytes = "7")]
+    pub x_tilt: i8,
+    /// Y tilt in degrees, -90 to 90
+    #[packed_field(bytes = "8")]
+    pub y_tilt: i8,
+}
+
+/// Pen digitizer
+pub struct Pen<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes16, OutNone, ReportSingle>,
+}
+
+impl<B: UsbBus> Pen<'_, B> {
+    pub fn write_report(&mut self, report: &PenReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|_| {
+            error!("Error packing PenReport");
+            UsbHidError::SerializationError
+        })?;
+        self.interface
+            .write_report(&data)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for Pen<'a, B> {
+    type I = Interface<'a, B, InBytes16, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {}
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+}
+
+pub struct PenConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes16, OutNone, ReportSingle>,
+}
+
+impl Default for PenConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::new(PEN_REPORT_DESCRIPTOR))
+                .description("Pen")
+                .in_endpoint(5.millis()))
+            .without_out_endpoint()
+            .build(),
+        )
+    }
+}
+
+impl<'a> PenConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes16, OutNone, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for PenConfig<'a> {
+    type Allocated = Pen<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::digitizer::{
+        ContactTracker, MultiTouchReport, PenReport, TouchContact, TouchPoint,
+    };
+
+    #[test]
+    fn touch_contact_layout() {
+        let bytes = TouchContact {
+            tip_switch: true,
+            in_range: true,
+            contact_id: 7,
+            x: 0x1234,
+            y: 0x5678,
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(bytes, [0x03, 7, 0x34, 0x12, 0x78, 0x56]);
+    }
+
+    #[test]
+    fn multi_touch_report_layout() {
+        let mut report = MultiTouchReport {
+            contact_count: 2,
+            ..Default::default()
+        };
+        report.contacts[1] = TouchContact {
+            tip_switch: true,
+            in_range: false,
+            contact_id: 1,
+            x: 0x00FF,
+            y: 0x0100,
+        };
+
+        let bytes = report.pack().unwrap();
+
+        assert_eq!(bytes[6..12], [0x01, 1, 0xFF, 0x00, 0x00, 0x01]);
+        assert_eq!(bytes[30], 2);
+    }
+
+    #[test]
+    fn pen_report_layout() {
+        let bytes = PenReport {
+            tip_switch: true,
+            barrel_switch: false,
+            invert: false,
+            eraser: true,
+            in_range: true,
+            x: 0x0102,
+            y: 0x7FFF,
+            tip_pressure: 0x0FFF,
+            x_tilt: -90,
+            y_tilt: 45,
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(
+            bytes,
+            [0b0001_1001, 0x02, 0x01, 0xFF, 0x7F, 0xFF, 0x0F, 0xA6, 45]
+        );
+    }
+
+    #[test]
+    fn contacts_keep_their_identifiers_until_lifted() {
+        let mut tracker = ContactTracker::new(5, 100);
+        let ids = |frame: &[TouchContact]| {
+            frame
+                .iter()
+                .map(|c| (c.contact_id, c.tip_switch, c.x))
+                .collect::<std::vec::Vec<_>>()
+        };
+
+        let frame = tracker.update(&[
+            TouchPoint { x: 1000, y: 1000 },
+            TouchPoint { x: 5000, y: 1000 },
+        ]);
+        assert_eq!(ids(&frame), [(0, true, 1000), (1, true, 5000)]);
+
+        // Matched by distance regardless of order, the first contact is lifted off
+        let frame = tracker.update(&[
+            TouchPoint { x: 5050, y: 1050 },
+            TouchPoint { x: 9000, y: 1000 },
+        ]);
+        assert_eq!(
+            ids(&frame),
+            [(1, true, 5050), (2, true, 9000), (0, false, 1000)]
+        );
+
+        // Freed identifiers are reused once the lift-off has been reported
+        let frame = tracker.update(&[
+            TouchPoint { x: 5050, y: 1050 },
+            TouchPoint { x: 20000, y: 1000 },
+        ]);
+        assert_eq!(
+            ids(&frame),
+            [(1, true, 5050), (0, true, 20000), (2, false, 9000)]
+        );
+        assert_eq!(tracker.contacts().len(), 2);
+
+        let frame = tracker.update(&[]);
+        assert_eq!(ids(&frame), [(1, false, 5050), (0, false, 20000)]);
+        assert!(tracker.update(&[]).is_empty());
+    }
+
+    #[test]
+    fn hybrid_reports_count_contacts_in_first_report() {
+        let contacts = [TouchContact::default(); 7];
+        let reports: std::vec::Vec<_> = MultiTouchReport::hybrid_reports(&contacts).collect();
+
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].contact_count, 7);
+        assert_eq!(reports[1].contact_count, 0);
+        assert_eq!(MultiTouchReport::hybrid_reports(&[]).count(), 0);
+    }
+}
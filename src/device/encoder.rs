@@ -0,0 +1,285 @@
+//!HID rotary encoders - dials and knobs for macro pads
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Maximum number of encoders in an [`EncoderLayout`]
+pub const ENCODER_MAX_ENCODERS: usize = 8;
+
+/// Relative usage reported for an encoder
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EncoderUsage {
+    /// Generic Desktop Dial
+    Dial,
+    /// Generic Desktop Wheel
+    Wheel,
+    /// Consumer AC Pan, horizontal scrolling
+    Pan,
+    /// Consumer Volume
+    Volume,
+}
+
+impl EncoderUsage {
+    /// Usage page and usage ID
+    #[must_use]
+    pub const fn usage(self) -> (u16, u16) {
+        match self {
+            Self::Dial => (0x01, 0x37),
+            Self::Wheel => (0x01, 0x38),
+            Self::Pan => (0x0C, 0x238),
+            Self::Volume => (0x0C, 0xE0),
+        }
+    }
+}
+
+/// Report layout of a [`RotaryEncoders`] device, one signed byte of detents per encoder in the
+/// order given by `encoders`
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EncoderLayout {
+    pub encoders: &'static [EncoderUsage],
+}
+
+impl EncoderLayout {
+    /// Length of the report in bytes
+    #[must_use]
+    pub const fn report_len(&self) -> usize {
+        self.encoders.len()
+    }
+}
+
+/// Default [`EncoderLayout`] - a single volume knob
+pub const DEFAULT_ENCODER_LAYOUT: EncoderLayout = EncoderLayout {
+    encoders: &[EncoderUsage::Volume],
+};
+
+/// Report descriptor for an [`EncoderLayout`], generated at compile time
+///
+/// Encoders are reported in a Consumer Control application collection, each with its own
+/// extended usage
+///
+/// ```
+/// use usbd_human_interface_device::device::encoder::*;
+///
+/// static KNOBS: EncoderDescriptor = EncoderDescriptor::new(EncoderLayout {
+///     encoders: &[EncoderUsage::Volume, EncoderUsage::Pan, EncoderUsage::Wheel],
+/// });
+///
+/// let config = RotaryEncodersConfig::from_descriptor(&KNOBS);
+/// ```
+pub struct EncoderDescriptor {
+    layout: EncoderLayout,
+    descriptor: [u8; 64],
+    len: usize,
+}
+
+impl EncoderDescriptor {
+    /// Generate the report descriptor for `layout`
+    ///
+    /// Panics, at compile time if used in a const context, if `layout` has no encoders or
+    /// exceeds [`ENCODER_MAX_ENCODERS`]
+    #[must_use]
+    #[rustfmt::skip]
+    pub const fn new(layout: EncoderLayout) -> Self {
+        core::assert!(!layout.encoders.is_empty(), "no encoders");
+        core::assert!(layout.encoders.len() <= ENCODER_MAX_ENCODERS, "too many encoders");
+
+        let mut d = Self {
+            layout,
+            descriptor: [0; 64],
+            len: 0,
+        };
+
+        d.push(&[
+            0x05, 0x0C,                 // Usage Page (Consumer),
+            0x09, 0x01,                 // Usage (Consumer Control),
+            0xA1, 0x01,                 // Collection (Application),
+        ]);
+
+        let mut i = 0;
+        while i < layout.encoders.len() {
+            let (page, usage) = layout.encoders[i].usage();
+            let [page_lo, page_hi] = page.to_le_bytes();
+            let [usage_lo, usage_hi] = usage.to_le_bytes();
+            d.push(&[
+                0x0B, usage_lo, usage_hi, page_lo, page_hi, //   Usage (page:usage),
+            ]);
+            i += 1;
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        d.push(&[
+            0x15, 0x81,                 //   Logical Minimum (-127),
+            0x25, 0x7F,                 //   Logical Maximum (127),
+            0x75, 0x08,                 //   Report Size (8),
+            0x95, layout.encoders.len() as u8, //   Report Count (encoders),
+            0x81, 0x06,                 //   Input (Data, Variable, Relative),
+            0xC0,                       // End Collection
+        ]);
+
+        d
+    }
+
+    const fn push(&mut self, items: &[u8]) {
+        let mut i = 0;
+        while i < items.len() {
+            self.descriptor[self.len] = items[i];
+            self.len += 1;
+            i += 1;
+        }
+    }
+
+    #[must_use]
+    pub const fn layout(&self) -> EncoderLayout {
+        self.layout
+    }
+
+    #[must_use]
+    pub fn descriptor(&self) -> &[u8] {
+        &self.descriptor[..self.len]
+    }
+}
+
+/// Report descriptor for [`DEFAULT_ENCODER_LAYOUT`]
+pub static DEFAULT_ENCODER_DESCRIPTOR: EncoderDescriptor =
+    EncoderDescriptor::new(DEFAULT_ENCODER_LAYOUT);
+
+/// Rotary encoders
+///
+/// Detents added with [`RotaryEncoders::rotate`] are sent by [`DeviceClass::tick`], which must
+/// be called every 1ms, one detent per encoder per report so that the host steps once for each
+/// detent regardless of how quickly the knob is turned
+pub struct RotaryEncoders<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    layout: EncoderLayout,
+    pending: [i32; ENCODER_MAX_ENCODERS],
+}
+
+impl<B: UsbBus> RotaryEncoders<'_, B> {
+    /// Add `detents` to encoder `index`, positive values are clockwise
+    pub fn rotate(&mut self, index: usize, detents: i32) {
+        if let Some(pending) = self.pending[..self.layout.encoders.len()].get_mut(index) {
+            *pending = pending.saturating_add(detents);
+        } else {
+            warn!("Encoder {} not in layout", index);
+        }
+    }
+
+    /// Detents not yet sent to the host
+    #[must_use]
+    pub fn pending(&self, index: usize) -> i32 {
+        self.pending.get(index).copied().unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn layout(&self) -> &EncoderLayout {
+        &self.layout
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for RotaryEncoders<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.pending = [0; ENCODER_MAX_ENCODERS];
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        let len = self.layout.report_len();
+        if self.pending[..len].iter().all(|p| *p == 0) {
+            return Ok(());
+        }
+
+        let mut data = [0u8; ENCODER_MAX_ENCODERS];
+        for (d, p) in data.iter_mut().zip(&self.pending[..len]) {
+            *d = p.signum().to_le_bytes()[0];
+        }
+
+        match self.interface.write_report(&data[..len]) {
+            Ok(_) => {
+                for p in &mut self.pending[..len] {
+                    *p -= p.signum();
+                }
+                Ok(())
+            }
+            Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(UsbHidError::from(e)),
+        }
+    }
+}
+
+pub struct RotaryEncodersConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+    layout: EncoderLayout,
+}
+
+impl Default for RotaryEncodersConfig<'_> {
+    fn default() -> Self {
+        Self::from_descriptor(&DEFAULT_ENCODER_DESCRIPTOR)
+    }
+}
+
+impl<'a> RotaryEncodersConfig<'a> {
+    /// `interface` must use a report descriptor matching `layout`, see [`EncoderDescriptor`]
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+        layout: EncoderLayout,
+    ) -> Self {
+        Self { interface, layout }
+    }
+
+    /// Configuration with the default interface settings for a generated descriptor
+    #[must_use]
+    pub fn from_descriptor(descriptor: &'static EncoderDescriptor) -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                descriptor.descriptor()
+            ))
+            .description("Rotary Encoders")
+            .in_endpoint(10.millis()))
+            .without_out_endpoint()
+            .build(),
+            descriptor.layout(),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for RotaryEncodersConfig<'a> {
+    type Allocated = RotaryEncoders<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            layout: self.layout,
+            pending: [0; ENCODER_MAX_ENCODERS],
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::encoder::{EncoderDescriptor, EncoderLayout, EncoderUsage};
+
+    #[test]
+    fn encoder_descriptor() {
+        static DESCRIPTOR: EncoderDescriptor = EncoderDescriptor::new(EncoderLayout {
+            encoders: &[EncoderUsage::Pan, EncoderUsage::Dial],
+        });
+
+        assert_eq!(
+            DESCRIPTOR.descriptor(),
+            [
+                0x05, 0x0C, 0x09, 0x01, 0xA1, 0x01, 0x0B, 0x38, 0x02, 0x0C, 0x00, 0x0B, 0x37, 0x00,
+                0x01, 0x00, 0x15, 0x81, 0x25, 0x7F, 0x75, 0x08, 0x95, 0x02, 0x81, 0x06, 0xC0
+            ]
+        );
+    }
+}
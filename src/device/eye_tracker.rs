@@ -0,0 +1,502 @@
+//!HID eye tracker
+//!
+//! Uses the Eye and Head Trackers usage page (0x12) in the report model of the Windows eye
+//! tracker class driver. Positions are in micrometers, gaze points are relative to the top left
+//! corner of the calibrated screen.
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the [`GazeReport`] input report
+pub const EYE_TRACKER_GAZE_REPORT_ID: u8 = 0x01;
+/// Report ID of the [`EyeTrackerCapabilities`] feature report
+pub const EYE_TRACKER_CAPABILITIES_REPORT_ID: u8 = 0x02;
+/// Report ID of the [`EyeTrackerConfiguration`] feature report
+pub const EYE_TRACKER_CONFIGURATION_REPORT_ID: u8 = 0x03;
+/// Report ID of the [`EyeTrackerStatus`] feature report
+pub const EYE_TRACKER_STATUS_REPORT_ID: u8 = 0x04;
+/// Report ID of the [`EyeTrackerMode`] feature report
+pub const EYE_TRACKER_CONTROL_REPORT_ID: u8 = 0x05;
+
+///Eye tracker report descriptor
+///
+/// * Input report 1 - [`GazeReport`]
+/// * Feature report 2 - [`EyeTrackerCapabilities`], read only
+/// * Feature report 3 - [`EyeTrackerConfiguration`], set by the host
+/// * Feature report 4 - [`EyeTrackerStatus`], read only
+/// * Feature report 5 - [`EyeTrackerMode`], set by the host
+#[rustfmt::skip]
+pub const EYE_TRACKER_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x12,                     // Usage Page (Eye and Head Trackers),
+    0x09, 0x01,                     // Usage (Eye Tracker),
+    0xA1, 0x01,                     // Collection (Application),
+    0x85, 0x01,                     //   Report ID (1),
+    0x09, 0x10,                     //   Usage (Tracking Data),
+    0xA1, 0x00,                     //   Collection (Physical),
+    0x09, 0x20,                     //     Usage (Sensor Timestamp),
+    0x15, 0x00,                     //     Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F,   //     Logical Maximum (2147483647),
+    0x66, 0x01, 0x10,               //     Unit (Seconds),
+    0x55, 0x0A,                     //     Unit Exponent (-6),
+    0x75, 0x40,                     //     Report Size (64),
+    0x95, 0x01,                     //     Report Count (1),
+    0x81, 0x02,                     //     Input (Data, Variable, Absolute),
+    0x17, 0x01, 0x00, 0x00, 0x80,   //     Logical Minimum (-2147483647),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F,   //     Logical Maximum (2147483647),
+    0x65, 0x11,                     //     Unit (Centimeter),
+    0x55, 0x0C,                     //     Unit Exponent (-4),
+    0x75, 0x20,                     //     Report Size (32),
+    0x09, 0x24,                     //     Usage (Gaze Point),
+    0xA1, 0x00,                     //     Collection (Physical),
+    0x09, 0x21,                     //       Usage (Position X),
+    0x09, 0x22,                     //       Usage (Position Y),
+    0x95, 0x02,                     //       Report Count (2),
+    0x81, 0x02,                     //       Input (Data, Variable, Absolute),
+    0xC0,                           //     End Collection
+    0x09, 0x25,                     //     Usage (Left Eye Position),
+    0xA1, 0x00,                     //     Collection (Physical),
+    0x09, 0x21,                     //       Usage (Position X),
+    0x09, 0x22,                     //       Usage (Position Y),
+    0x09, 0x23,                     //       Usage (Position Z),
+    0x95, 0x03,                     //       Report Count (3),
+    0x81, 0x02,                     //       Input (Data, Variable, Absolute),
+    0xC0,                           //     End Collection
+    0x09, 0x26,                     //     Usage (Right Eye Position),
+    0xA1, 0x00,                     //     Collection (Physical),
+    0x09, 0x21,                     //       Usage (Position X),
+    0x09, 0x22,                     //       Usage (Position Y),
+    0x09, 0x23,                     //       Usage (Position Z),
+    0x95, 0x03,                     //       Report Count (3),
+    0x81, 0x02,                     //       Input (Data, Variable, Absolute),
+    0xC0,                           //     End Collection
+    0xC0,                           //   End Collection
+    0x85, 0x02,                     //   Report ID (2),
+    0x09, 0x11,                     //   Usage (Capabilities),
+    0xA1, 0x02,                     //   Collection (Logical),
+    0x0A, 0x00, 0x01,               //     Usage (Tracker Quality),
+    0x15, 0x00,                     //     Logical Minimum (0),
+    0x26, 0xFF, 0x00,               //     Logical Maximum (255),
+    0x65, 0x00,                     //     Unit (None),
+    0x55, 0x00,                     //     Unit Exponent (0),
+    0x75, 0x08,                     //     Report Size (8),
+    0x95, 0x01,                     //     Report Count (1),
+    0xB1, 0x02,                     //     Feature (Data, Variable, Absolute),
+    0x0A, 0x01, 0x01,               //     Usage (Minimum Tracking Distance),
+    0x0A, 0x02, 0x01,               //     Usage (Optimum Tracking Distance),
+    0x0A, 0x03, 0x01,               //     Usage (Maximum Tracking Distance),
+    0x0A, 0x04, 0x01,               //     Usage (Maximum Screen Plane Width),
+    0x0A, 0x05, 0x01,               //     Usage (Maximum Screen Plane Height),
+    0x17, 0x01, 0x00, 0x00, 0x80,   //     Logical Minimum (-2147483647),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F,   //     Logical Maximum (2147483647),
+    0x65, 0x11,                     //     Unit (Centimeter),
+    0x55, 0x0C,                     //     Unit Exponent (-4),
+    0x75, 0x20,                     //     Report Size (32),
+    0x95, 0x05,                     //     Report Count (5),
+    0xB1, 0x02,                     //     Feature (Data, Variable, Absolute),
+    0xC0,                           //   End Collection
+    0x85, 0x03,                     //   Report ID (3),
+    0x09, 0x12,                     //   Usage (Configuration),
+    0xA1, 0x02,                     //   Collection (Logical),
+    0x0A, 0x00, 0x02,               //     Usage (Display Manufacturer ID),
+    0x0A, 0x01, 0x02,               //     Usage (Display Product ID),
+    0x15, 0x00,                     //     Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0x00, 0x00,   //     Logical Maximum (65535),
+    0x65, 0x00,                     //     Unit (None),
+    0x55, 0x00,                     //     Unit Exponent (0),
+    0x75, 0x10,                     //     Report Size (16),
+    0x95, 0x02,                     //     Report Count (2),
+    0xB1, 0x02,                     //     Feature (Data, Variable, Absolute),
+    0x0A, 0x02, 0x02,               //     Usage (Display Serial Number),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F,   //     Logical Maximum (2147483647),
+    0x75, 0x20,                     //     Report Size (32),
+    0x95, 0x01,                     //     Report Count (1),
+    0xB1, 0x02,                     //     Feature (Data, Variable, Absolute),
+    0x0A, 0x03, 0x02,               //     Usage (Display Manufacturer Date),
+    0x27, 0xFF, 0xFF, 0x00, 0x00,   //     Logical Maximum (65535),
+    0x75, 0x10,                     //     Report Size (16),
+    0xB1, 0x02,                     //     Feature (Data, Variable, Absolute),
+    0x0A, 0x04, 0x02,               //     Usage (Calibrated Screen Width),
+    0x0A, 0x05, 0x02,               //     Usage (Calibrated Screen Height),
+    0x17, 0x01, 0x00, 0x00, 0x80,   //     Logical Minimum (-2147483647),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F,   //     Logical Maximum (2147483647),
+    0x65, 0x11,                     //     Unit (Centimeter),
+    0x55, 0x0C,                     //     Unit Exponent (-4),
+    0x75, 0x20,                     //     Report Size (32),
+    0x95, 0x02,                     //     Report Count (2),
+    0xB1, 0x02,                     //     Feature (Data, Variable, Absolute),
+    0xC0,                           //   End Collection
+    0x85, 0x04,                     //   Report ID (4),
+    0x09, 0x13,                     //   Usage (Status),
+    0xA1, 0x02,                     //   Collection (Logical),
+    0x0A, 0x01, 0x03,               //     Usage (Configuration Status),
+    0x15, 0x00,                     //     Logical Minimum (0),
+    0x26, 0xFF, 0x00,               //     Logical Maximum (255),
+    0x65, 0x00,                     //     Unit (None),
+    0x55, 0x00,                     //     Unit Exponent (0),
+    0x75, 0x08,                     //     Report Size (8),
+    0x95, 0x01,                     //     Report Count (1),
+    0xB1, 0x02,                     //     Feature (Data, Variable, Absolute),
+    0x0A, 0x00, 0x03,               //     Usage (Sampling Frequency),
+    0x27, 0xFF, 0xFF, 0x00, 0x00,   //     Logical Maximum (65535),
+    0x66, 0x01, 0xF0,               //     Unit (Hertz),
+    0x75, 0x10,                     //     Report Size (16),
+    0xB1, 0x02,                     //     Feature (Data, Variable, Absolute),
+    0xC0,                           //   End Collection
+    0x85, 0x05,                     //   Report ID (5),
+    0x09, 0x14,                     //   Usage (Control),
+    0xA1, 0x02,                     //   Collection (Logical),
+    0x0A, 0x00, 0x04,               //     Usage (Device Mode Request),
+    0x15, 0x00,                     //     Logical Minimum (0),
+    0x26, 0xFF, 0x00,               //     Logical Maximum (255),
+    0x65, 0x00,                     //     Unit (None),
+    0x75, 0x08,                     //     Report Size (8),
+    0x95, 0x01,                     //     Report Count (1),
+    0xB1, 0x02,                     //     Feature (Data, Variable, Absolute),
+    0xC0,                           //   End Collection
+    0xC0,                           // End Collection
+];
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "40")]
+pub struct GazeReport {
+    /// Time of the sample in microseconds
+    #[packed_field]
+    pub timestamp: u64,
+    #[packed_field]
+    pub gaze_x: i32,
+    #[packed_field]
+    pub gaze_y: i32,
+    #[packed_field]
+    pub left_eye_x: i32,
+    #[packed_field]
+    pub left_eye_y: i32,
+    #[packed_field]
+    pub left_eye_z: i32,
+    #[packed_field]
+    pub right_eye_x: i32,
+    #[packed_field]
+    pub right_eye_y: i32,
+    #[packed_field]
+    pub right_eye_z: i32,
+}
+
+/// Tracker capabilities, distances and sizes in micrometers
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "21")]
+pub struct EyeTrackerCapabilities {
+    /// 1 for fine gaze quality
+    #[packed_field]
+    pub tracker_quality: u8,
+    #[packed_field]
+    pub minimum_tracking_distance: i32,
+    #[packed_field]
+    pub optimum_tracking_distance: i32,
+    #[packed_field]
+    pub maximum_tracking_distance: i32,
+    #[packed_field]
+    pub maximum_screen_plane_width: i32,
+    #[packed_field]
+    pub maximum_screen_plane_height: i32,
+}
+
+/// Screen the tracker is calibrated for, set by the host
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "18")]
+pub struct EyeTrackerConfiguration {
+    #[packed_field]
+    pub display_manufacturer_id: u16,
+    #[packed_field]
+    pub display_product_id: u16,
+    #[packed_field]
+    pub display_serial_number: u32,
+    #[packed_field]
+    pub display_manufacturer_date: u16,
+    /// Calibrated screen width in micrometers
+    #[packed_field]
+    pub calibrated_screen_width: i32,
+    /// Calibrated screen height in micrometers
+    #[packed_field]
+    pub calibrated_screen_height: i32,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum ConfigurationStatus {
+    #[default]
+    Undefined = 0,
+    Ready = 1,
+    Configuring = 2,
+    ScreenSetupNeeded = 3,
+    UserCalibrationNeeded = 4,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "3")]
+pub struct EyeTrackerStatus {
+    #[packed_field(bytes = "0", ty = "enum")]
+    pub configuration_status: ConfigurationStatus,
+    /// Sampling frequency in Hertz
+    #[packed_field(bytes = "1..=2")]
+    pub sampling_frequency: u16,
+}
+
+/// Data requested by the host with a device mode request
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(bit_numbering = "lsb0", size_bytes = "1")]
+pub struct EyeTrackerMode {
+    #[packed_field(bits = "0")]
+    pub gaze_point: bool,
+    #[packed_field(bits = "1")]
+    pub eye_position: bool,
+    #[packed_field(bits = "2")]
+    pub head_position: bool,
+}
+
+/// Eye tracker
+///
+/// The host reads the tracker capabilities and status, sets the calibrated screen with the
+/// configuration report and requests data with the device mode. [`GazeReport`]s should be
+/// written at the sampling frequency while [`EyeTracker::mode`] requests gaze points or eye
+/// positions.
+pub struct EyeTracker<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes64, OutNone, ReportSingle>,
+    capabilities: EyeTrackerCapabilities,
+    configuration: EyeTrackerConfiguration,
+    status: EyeTrackerStatus,
+    mode: EyeTrackerMode,
+}
+
+impl<B: UsbBus> EyeTracker<'_, B> {
+    pub fn write_report(&mut self, report: &GazeReport) -> Result<(), UsbHidError> {
+        let mut data = [0u8; 41];
+        data[0] = EYE_TRACKER_GAZE_REPORT_ID;
+        report.pack_to_slice(&mut data[1..]).map_err(|_| {
+            error!("Error packing GazeReport");
+            UsbHidError::SerializationError
+        })?;
+        self.interface
+            .write_report(&data)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+
+    pub fn set_status(&mut self, status: EyeTrackerStatus) {
+        self.status = status;
+    }
+
+    #[must_use]
+    pub fn status(&self) -> EyeTrackerStatus {
+        self.status
+    }
+
+    #[must_use]
+    pub fn configuration(&self) -> EyeTrackerConfiguration {
+        self.configuration
+    }
+
+    #[must_use]
+    pub fn mode(&self) -> EyeTrackerMode {
+        self.mode
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for EyeTracker<'a, B> {
+    type I = Interface<'a, B, InBytes64, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.mode = EyeTrackerMode::default();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        let (len, result) = match report_id {
+            EYE_TRACKER_CAPABILITIES_REPORT_ID => (
+                22,
+                data.get_mut(1..22)
+                    .map(|d| self.capabilities.pack_to_slice(d)),
+            ),
+            EYE_TRACKER_CONFIGURATION_REPORT_ID => (
+                19,
+                data.get_mut(1..19)
+                    .map(|d| self.configuration.pack_to_slice(d)),
+            ),
+            EYE_TRACKER_STATUS_REPORT_ID => {
+                (4, data.get_mut(1..4).map(|d| self.status.pack_to_slice(d)))
+            }
+            EYE_TRACKER_CONTROL_REPORT_ID => {
+                (2, data.get_mut(1..2).map(|d| self.mode.pack_to_slice(d)))
+            }
+            _ => return Err(UsbError::Unsupported),
+        };
+        result
+            .ok_or(UsbError::BufferOverflow)?
+            .map_err(|_| UsbError::ParseError)?;
+        data[0] = report_id;
+        Ok(len)
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        if data.first() != Some(&report_id) {
+            return Err(UsbError::ParseError);
+        }
+        match report_id {
+            EYE_TRACKER_CONFIGURATION_REPORT_ID => {
+                self.configuration = data
+                    .get(1..19)
+                    .and_then(|d| EyeTrackerConfiguration::unpack_from_slice(d).ok())
+                    .ok_or(UsbError::ParseError)?;
+                info!(
+                    "Set eye tracker calibrated screen {}x{}um",
+                    self.configuration.calibrated_screen_width,
+                    self.configuration.calibrated_screen_height
+                );
+            }
+            EYE_TRACKER_CONTROL_REPORT_ID => {
+                self.mode = data
+                    .get(1..2)
+                    .and_then(|d| EyeTrackerMode::unpack_from_slice(d).ok())
+                    .ok_or(UsbError::ParseError)?;
+                info!(
+                    "Set eye tracker mode: gaze {}, eye position {}",
+                    self.mode.gaze_point, self.mode.eye_position
+                );
+            }
+            _ => return Err(UsbError::Unsupported),
+        }
+        Ok(())
+    }
+}
+
+pub struct EyeTrackerConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes64, OutNone, ReportSingle>,
+    capabilities: EyeTrackerCapabilities,
+}
+
+impl Default for EyeTrackerConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                EYE_TRACKER_REPORT_DESCRIPTOR
+            ))
+            .description("Eye Tracker")
+            .in_endpoint(4.millis()))
+            .without_out_endpoint()
+            .build(),
+            EyeTrackerCapabilities {
+                tracker_quality: 1,
+                minimum_tracking_distance: 450_000,
+                optimum_tracking_distance: 650_000,
+                maximum_tracking_distance: 850_000,
+                maximum_screen_plane_width: 600_000,
+                maximum_screen_plane_height: 340_000,
+            },
+        )
+    }
+}
+
+impl<'a> EyeTrackerConfig<'a> {
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes64, OutNone, ReportSingle>,
+        capabilities: EyeTrackerCapabilities,
+    ) -> Self {
+        Self {
+            interface,
+            capabilities,
+        }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for EyeTrackerConfig<'a> {
+    type Allocated = EyeTracker<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            capabilities: self.capabilities,
+            configuration: EyeTrackerConfiguration::default(),
+            status: EyeTrackerStatus {
+                configuration_status: ConfigurationStatus::ScreenSetupNeeded,
+                sampling_frequency: 0,
+            },
+            mode: EyeTrackerMode::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::eye_tracker::{
+        ConfigurationStatus, EyeTrackerConfiguration, EyeTrackerMode, EyeTrackerStatus, GazeReport,
+    };
+
+    #[test]
+    fn gaze_report_layout() {
+        let bytes = GazeReport {
+            timestamp: 0x0102_0304_0506_0708,
+            gaze_x: 1,
+            gaze_y: -1,
+            ..Default::default()
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(
+            bytes[..16],
+            [
+                0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0xFF, 0xFF,
+                0xFF, 0xFF
+            ]
+        );
+        assert!(bytes[16..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn configuration_layout() {
+        let configuration = EyeTrackerConfiguration::unpack(&[
+            0x34, 0x12, 0x78, 0x56, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0xE0, 0x93, 0x04, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+        ])
+        .unwrap();
+
+        assert_eq!(configuration.display_manufacturer_id, 0x1234);
+        assert_eq!(configuration.display_product_id, 0x5678);
+        assert_eq!(configuration.display_serial_number, 1);
+        assert_eq!(configuration.display_manufacturer_date, 2);
+        assert_eq!(configuration.calibrated_screen_width, 300_000);
+        assert_eq!(configuration.calibrated_screen_height, 0);
+    }
+
+    #[test]
+    fn status_and_mode_layout() {
+        let status = EyeTrackerStatus {
+            configuration_status: ConfigurationStatus::Ready,
+            sampling_frequency: 90,
+        };
+        assert_eq!(status.pack().unwrap(), [0x01, 0x5A, 0x00]);
+
+        let mode = EyeTrackerMode::unpack(&[0x03]).unwrap();
+        assert!(mode.gaze_point);
+        assert!(mode.eye_position);
+        assert!(!mode.head_position);
+    }
+}
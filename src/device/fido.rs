@@ -3,6 +3,7 @@ use crate::usb_class::prelude::*;
 use fugit::ExtU32;
 use usb_device::bus::UsbBus;
 use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
 
 /// Raw FIDO report descriptor.
 /// 
@@ -40,8 +41,22 @@ impl Default for RawFidoReport {
     }
 }
 
+impl OutputReport for RawFidoReport {
+    fn parse(data: &[u8]) -> usb_device::Result<Self> {
+        let mut report = Self::default();
+        report
+            .packet
+            .get_mut(..data.len())
+            .ok_or(UsbError::ParseError)?
+            .copy_from_slice(data);
+        Ok(report)
+    }
+}
+
+/// Raw FIDO device, packets are received one at a time as they're read, so none are dropped
 pub struct RawFido<'a, B: UsbBus> {
     interface: Interface<'a, B, InBytes64, OutBytes64, ReportSingle>,
+    received: ReceivedReport<RawFidoReport, 64>,
 }
 
 impl<B: UsbBus> RawFido<'_, B> {
@@ -51,12 +66,29 @@ impl<B: UsbBus> RawFido<'_, B> {
             .map(|_| ())
             .map_err(UsbHidError::from)
     }
+    /// See [`OutputReports::read_output_report`]
     pub fn read_report(&mut self) -> usb_device::Result<RawFidoReport> {
-        let mut report = RawFidoReport::default();
-        match self.interface.read_report(&mut report.packet) {
-            Err(e) => Err(e),
-            Ok(_) => Ok(report),
-        }
+        self.read_output_report()
+    }
+}
+
+impl<B: UsbBus> OutputReports for RawFido<'_, B> {
+    type Report = RawFidoReport;
+
+    fn read_output_report(&mut self) -> usb_device::Result<RawFidoReport> {
+        self.received
+            .receive_next(|data| self.interface.read_report(data))?;
+        self.received.read()
+    }
+
+    fn output_report(&self) -> RawFidoReport {
+        self.received.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<RawFidoReport>> {
+        self.received
+            .receive_next(|data| self.interface.read_report(data))?;
+        Ok(self.received.changed())
     }
 }
 
@@ -67,7 +99,9 @@ impl<'a, B: UsbBus> DeviceClass<'a> for RawFido<'a, B> {
         &mut self.interface
     }
 
-    fn reset(&mut self) {}
+    fn reset(&mut self) {
+        self.received = ReceivedReport::default();
+    }
 
     fn tick(&mut self) -> Result<(), UsbHidError> {
         Ok(())
@@ -79,7 +113,6 @@ pub struct RawFidoConfig<'a> {
 }
 
 impl Default for RawFidoConfig<'_> {
-    #[must_use]
     fn default() -> Self {
         Self::new(
             unwrap!(
@@ -103,9 +136,10 @@ impl<'a> RawFidoConfig<'a> {
 impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for RawFidoConfig<'a> {
     type Allocated = RawFido<'a, B>;
 
-    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        Self::Allocated {
-            interface: Interface::new(usb_alloc, self.interface),
-        }
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            received: ReceivedReport::default(),
+        })
     }
 }
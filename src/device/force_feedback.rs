@@ -0,0 +1,1214 @@
+//!HID force feedback joystick, using the Physical Interface Device (PID) usage page
+//!
+//! See [Device Class Definition for Physical Interface Devices (PID) Version 1.0](<https://www.usb.org/sites/default/files/documents/pid1_01.pdf>)
+use crate::descriptor::concat;
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Maximum number of effects that can be loaded on the device at one time
+pub const FORCE_FEEDBACK_MAX_EFFECTS: usize = 10;
+
+/// Report ID of the [`ForceFeedbackJoystickReport`] input report
+pub const JOYSTICK_INPUT_REPORT_ID: u8 = 0x01;
+/// Report ID of the [`PidStateReport`] input report
+pub const PID_STATE_REPORT_ID: u8 = 0x02;
+/// Report ID of the Set Effect output report
+pub const SET_EFFECT_REPORT_ID: u8 = 0x11;
+/// Report ID of the Set Condition output report
+pub const SET_CONDITION_REPORT_ID: u8 = 0x12;
+/// Report ID of the Set Periodic output report
+pub const SET_PERIODIC_REPORT_ID: u8 = 0x13;
+/// Report ID of the Set Constant Force output report
+pub const SET_CONSTANT_FORCE_REPORT_ID: u8 = 0x14;
+/// Report ID of the Effect Operation output report
+pub const EFFECT_OPERATION_REPORT_ID: u8 = 0x15;
+/// Report ID of the PID Block Free output report
+pub const BLOCK_FREE_REPORT_ID: u8 = 0x16;
+/// Report ID of the PID Device Control output report
+pub const DEVICE_CONTROL_REPORT_ID: u8 = 0x17;
+/// Report ID of the Device Gain output report
+pub const DEVICE_GAIN_REPORT_ID: u8 = 0x18;
+/// Report ID of the Create New Effect feature report
+pub const CREATE_NEW_EFFECT_REPORT_ID: u8 = 0x21;
+/// Report ID of the PID Block Load feature report
+pub const BLOCK_LOAD_REPORT_ID: u8 = 0x22;
+/// Report ID of the PID Pool feature report
+pub const POOL_REPORT_ID: u8 = 0x23;
+
+/// Two axis, eight button joystick with force feedback
+///
+/// * Input report 1 - [`ForceFeedbackJoystickReport`]
+/// * Input report 2 - [`PidStateReport`]
+/// * Output reports 0x11-0x18 - effect parameters, effect operations and device control
+/// * Feature reports 0x21-0x23 - effect allocation and pool information
+///
+/// Supports constant force, periodic (square, sine, triangle, sawtooth) and spring effects
+#[rustfmt::skip]
+pub const FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR: &[u8] = &concat::<
+    { JOYSTICK_INPUT_ITEMS.len() + PID_REPORT_ITEMS.len() + 1 }
+>(&[
+    JOYSTICK_INPUT_ITEMS,
+    PID_REPORT_ITEMS,
+    &[0xC0],           // End Collection
+]);
+
+#[rustfmt::skip]
+const JOYSTICK_INPUT_ITEMS: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x04,        // Usage (Joystick),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x05, 0x09,        //   Usage Page (Button),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x08,        //   Usage Maximum (8),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x08,        //   Report Count (8),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x05, 0x01,        //   Usage Page (Generic Desktop),
+    0x09, 0x01,        //   Usage (Pointer),
+    0xA1, 0x00,        //   Collection (Physical),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x16, 0x01, 0x80,  //     Logical Minimum (-32767),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+];
+
+/// PID state, effect, device control, allocation and pool reports
+///
+/// Placed inside the application collection of a force feedback device, after its input report
+/// with ID 1. Effects address the Generic Desktop X and Y axes.
+#[rustfmt::skip]
+pub(crate) const PID_REPORT_ITEMS: &[u8] = &[
+    0x05, 0x0F,        //   Usage Page (Physical Interface),
+    0x09, 0x92,        //   Usage (PID State Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x02,        //     Report ID (2),
+    0x09, 0x9F,        //     Usage (Device Paused),
+    0x09, 0xA0,        //     Usage (Actuators Enabled),
+    0x09, 0xA4,        //     Usage (Safety Switch),
+    0x09, 0xA5,        //     Usage (Actuator Override Switch),
+    0x09, 0xA6,        //     Usage (Actuator Power),
+    0x09, 0x94,        //     Usage (Effect Playing),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x06,        //     Report Count (6),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x75, 0x02,        //     Report Size (2),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x22,        //     Usage (Effect Block Index),
+    0x15, 0x01,        //     Logical Minimum (1),
+    0x25, 0x0A,        //     Logical Maximum (10),
+    0x75, 0x08,        //     Report Size (8),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0x21,        //   Usage (Set Effect Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x11,        //     Report ID (17),
+    0x09, 0x22,        //     Usage (Effect Block Index),
+    0x15, 0x01,        //     Logical Minimum (1),
+    0x25, 0x0A,        //     Logical Maximum (10),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x25,        //     Usage (Effect Type),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x09, 0x26,        //       Usage (ET Constant Force),
+    0x09, 0x30,        //       Usage (ET Square),
+    0x09, 0x31,        //       Usage (ET Sine),
+    0x09, 0x32,        //       Usage (ET Triangle),
+    0x09, 0x33,        //       Usage (ET Sawtooth Up),
+    0x09, 0x34,        //       Usage (ET Sawtooth Down),
+    0x09, 0x40,        //       Usage (ET Spring),
+    0x15, 0x01,        //       Logical Minimum (1),
+    0x25, 0x07,        //       Logical Maximum (7),
+    0x75, 0x08,        //       Report Size (8),
+    0x95, 0x01,        //       Report Count (1),
+    0x91, 0x00,        //       Output (Data, Array, Absolute),
+    0xC0,              //     End Collection
+    0x09, 0x50,        //     Usage (Duration),
+    0x09, 0x54,        //     Usage (Trigger Repeat Interval),
+    0x09, 0x51,        //     Usage (Sample Period),
+    0x09, 0xA7,        //     Usage (Start Delay),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535),
+    0x35, 0x00,        //     Physical Minimum (0),
+    0x47, 0xFF, 0xFF, 0x00, 0x00, //     Physical Maximum (65535),
+    0x66, 0x03, 0x10,  //     Unit (Seconds),
+    0x55, 0x0D,        //     Unit Exponent (-3),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x04,        //     Report Count (4),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x45, 0x00,        //     Physical Maximum (0),
+    0x65, 0x00,        //     Unit (None),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0x09, 0x52,        //     Usage (Gain),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x53,        //     Usage (Trigger Button),
+    0x25, 0x08,        //     Logical Maximum (8),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x55,        //     Usage (Axes Enable),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x05, 0x01,        //       Usage Page (Generic Desktop),
+    0x09, 0x30,        //       Usage (X),
+    0x09, 0x31,        //       Usage (Y),
+    0x25, 0x01,        //       Logical Maximum (1),
+    0x75, 0x01,        //       Report Size (1),
+    0x95, 0x02,        //       Report Count (2),
+    0x91, 0x02,        //       Output (Data, Variable, Absolute),
+    0xC0,              //     End Collection
+    0x05, 0x0F,        //     Usage Page (Physical Interface),
+    0x09, 0x56,        //     Usage (Direction Enable),
+    0x95, 0x01,        //     Report Count (1),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x75, 0x05,        //     Report Size (5),
+    0x91, 0x03,        //     Output (Constant, Variable, Absolute),
+    0x09, 0x57,        //     Usage (Direction),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x05, 0x0A,        //       Usage Page (Ordinal),
+    0x09, 0x01,        //       Usage (Instance 1),
+    0x09, 0x02,        //       Usage (Instance 2),
+    0x26, 0xFF, 0x00,  //       Logical Maximum (255),
+    0x46, 0x68, 0x01,  //       Physical Maximum (360),
+    0x65, 0x14,        //       Unit (Degrees),
+    0x75, 0x08,        //       Report Size (8),
+    0x95, 0x02,        //       Report Count (2),
+    0x91, 0x02,        //       Output (Data, Variable, Absolute),
+    0x45, 0x00,        //       Physical Maximum (0),
+    0x65, 0x00,        //       Unit (None),
+    0xC0,              //     End Collection
+    0x05, 0x0F,        //     Usage Page (Physical Interface),
+    0xC0,              //   End Collection
+    0x09, 0x5F,        //   Usage (Set Condition Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x12,        //     Report ID (18),
+    0x09, 0x22,        //     Usage (Effect Block Index),
+    0x15, 0x01,        //     Logical Minimum (1),
+    0x25, 0x0A,        //     Logical Maximum (10),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x23,        //     Usage (Parameter Block Offset),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x60,        //     Usage (CP Offset),
+    0x09, 0x61,        //     Usage (Positive Coefficient),
+    0x09, 0x62,        //     Usage (Negative Coefficient),
+    0x16, 0xF0, 0xD8,  //     Logical Minimum (-10000),
+    0x26, 0x10, 0x27,  //     Logical Maximum (10000),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x03,        //     Report Count (3),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x63,        //     Usage (Positive Saturation),
+    0x09, 0x64,        //     Usage (Negative Saturation),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x95, 0x02,        //     Report Count (2),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x65,        //     Usage (Dead Band),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0x6E,        //   Usage (Set Periodic Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x13,        //     Report ID (19),
+    0x09, 0x22,        //     Usage (Effect Block Index),
+    0x15, 0x01,        //     Logical Minimum (1),
+    0x25, 0x0A,        //     Logical Maximum (10),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x70,        //     Usage (Magnitude),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x26, 0x10, 0x27,  //     Logical Maximum (10000),
+    0x75, 0x10,        //     Report Size (16),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x6F,        //     Usage (Offset),
+    0x16, 0xF0, 0xD8,  //     Logical Minimum (-10000),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x71,        //     Usage (Phase),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x46, 0x68, 0x01,  //     Physical Maximum (360),
+    0x65, 0x14,        //     Unit (Degrees),
+    0x75, 0x08,        //     Report Size (8),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x72,        //     Usage (Period),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535),
+    0x47, 0xFF, 0xFF, 0x00, 0x00, //     Physical Maximum (65535),
+    0x66, 0x03, 0x10,  //     Unit (Seconds),
+    0x55, 0x0D,        //     Unit Exponent (-3),
+    0x75, 0x10,        //     Report Size (16),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x45, 0x00,        //     Physical Maximum (0),
+    0x65, 0x00,        //     Unit (None),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0xC0,              //   End Collection
+    0x09, 0x73,        //   Usage (Set Constant Force Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x14,        //     Report ID (20),
+    0x09, 0x22,        //     Usage (Effect Block Index),
+    0x15, 0x01,        //     Logical Minimum (1),
+    0x25, 0x0A,        //     Logical Maximum (10),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x70,        //     Usage (Magnitude),
+    0x16, 0xF0, 0xD8,  //     Logical Minimum (-10000),
+    0x26, 0x10, 0x27,  //     Logical Maximum (10000),
+    0x75, 0x10,        //     Report Size (16),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0x77,        //   Usage (Effect Operation Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x15,        //     Report ID (21),
+    0x09, 0x22,        //     Usage (Effect Block Index),
+    0x15, 0x01,        //     Logical Minimum (1),
+    0x25, 0x0A,        //     Logical Maximum (10),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x78,        //     Usage (Effect Operation),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x09, 0x79,        //       Usage (Op Effect Start),
+    0x09, 0x7A,        //       Usage (Op Effect Start Solo),
+    0x09, 0x7B,        //       Usage (Op Effect Stop),
+    0x15, 0x01,        //       Logical Minimum (1),
+    0x25, 0x03,        //       Logical Maximum (3),
+    0x91, 0x00,        //       Output (Data, Array, Absolute),
+    0xC0,              //     End Collection
+    0x09, 0x7C,        //     Usage (Loop Count),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0x90,        //   Usage (PID Block Free Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x16,        //     Report ID (22),
+    0x09, 0x22,        //     Usage (Effect Block Index),
+    0x15, 0x01,        //     Logical Minimum (1),
+    0x25, 0x0A,        //     Logical Maximum (10),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0x95,        //   Usage (PID Device Control Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x17,        //     Report ID (23),
+    0x09, 0x96,        //     Usage (PID Device Control),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x09, 0x97,        //       Usage (DC Enable Actuators),
+    0x09, 0x98,        //       Usage (DC Disable Actuators),
+    0x09, 0x99,        //       Usage (DC Stop All Effects),
+    0x09, 0x9A,        //       Usage (DC Device Reset),
+    0x09, 0x9B,        //       Usage (DC Device Pause),
+    0x09, 0x9C,        //       Usage (DC Device Continue),
+    0x15, 0x01,        //       Logical Minimum (1),
+    0x25, 0x06,        //       Logical Maximum (6),
+    0x91, 0x00,        //       Output (Data, Array, Absolute),
+    0xC0,              //     End Collection
+    0xC0,              //   End Collection
+    0x09, 0x7D,        //   Usage (Device Gain Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x18,        //     Report ID (24),
+    0x09, 0x7E,        //     Usage (Device Gain),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0xAB,        //   Usage (Create New Effect Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x21,        //     Report ID (33),
+    0x09, 0x25,        //     Usage (Effect Type),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x09, 0x26,        //       Usage (ET Constant Force),
+    0x09, 0x30,        //       Usage (ET Square),
+    0x09, 0x31,        //       Usage (ET Sine),
+    0x09, 0x32,        //       Usage (ET Triangle),
+    0x09, 0x33,        //       Usage (ET Sawtooth Up),
+    0x09, 0x34,        //       Usage (ET Sawtooth Down),
+    0x09, 0x40,        //       Usage (ET Spring),
+    0x15, 0x01,        //       Logical Minimum (1),
+    0x25, 0x07,        //       Logical Maximum (7),
+    0x75, 0x08,        //       Report Size (8),
+    0x95, 0x01,        //       Report Count (1),
+    0xB1, 0x00,        //       Feature (Data, Array, Absolute),
+    0xC0,              //     End Collection
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x3B,        //     Usage (Byte Count),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x26, 0xFF, 0x01,  //     Logical Maximum (511),
+    0x75, 0x10,        //     Report Size (16),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x05, 0x0F,        //     Usage Page (Physical Interface),
+    0xC0,              //   End Collection
+    0x09, 0x89,        //   Usage (PID Block Load Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x22,        //     Report ID (34),
+    0x09, 0x22,        //     Usage (Effect Block Index),
+    0x15, 0x01,        //     Logical Minimum (1),
+    0x25, 0x0A,        //     Logical Maximum (10),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x8B,        //     Usage (Block Load Status),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x09, 0x8C,        //       Usage (Block Load Success),
+    0x09, 0x8D,        //       Usage (Block Load Full),
+    0x09, 0x8E,        //       Usage (Block Load Error),
+    0x15, 0x01,        //       Logical Minimum (1),
+    0x25, 0x03,        //       Logical Maximum (3),
+    0xB1, 0x00,        //       Feature (Data, Array, Absolute),
+    0xC0,              //     End Collection
+    0x09, 0xAC,        //     Usage (RAM Pool Available),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0x7F,        //   Usage (PID Pool Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x23,        //     Report ID (35),
+    0x09, 0x80,        //     Usage (RAM Pool Size),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x83,        //     Usage (Simultaneous Effects Max),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0xA9,        //     Usage (Device Managed Pool),
+    0x09, 0xAA,        //     Usage (Shared Parameter Blocks),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x75, 0x06,        //     Report Size (6),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x03,        //     Feature (Constant, Variable, Absolute),
+    0xC0,              //   End Collection
+];
+
+/// Effect types, in the order of the Effect Type usages in
+/// [`FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR`]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum EffectType {
+    #[default]
+    ConstantForce = 1,
+    Square = 2,
+    Sine = 3,
+    Triangle = 4,
+    SawtoothUp = 5,
+    SawtoothDown = 6,
+    Spring = 7,
+}
+
+impl EffectType {
+    /// `true` for effect types using [`PeriodicParameters`]
+    #[must_use]
+    pub fn is_periodic(self) -> bool {
+        matches!(
+            self,
+            Self::Square | Self::Sine | Self::Triangle | Self::SawtoothUp | Self::SawtoothDown
+        )
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum EffectOperation {
+    #[default]
+    Start = 1,
+    /// Start the effect, stopping all other effects
+    StartSolo = 2,
+    Stop = 3,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum DeviceControl {
+    #[default]
+    EnableActuators = 1,
+    DisableActuators = 2,
+    StopAllEffects = 3,
+    /// Stop and free all effects
+    DeviceReset = 4,
+    DevicePause = 5,
+    DeviceContinue = 6,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum BlockLoadStatus {
+    #[default]
+    Success = 1,
+    Full = 2,
+    Error = 3,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "5")]
+pub struct ForceFeedbackJoystickReport {
+    #[packed_field]
+    pub buttons: u8,
+    /// X axis, -32767 to 32767
+    #[packed_field]
+    pub x: i16,
+    /// Y axis, -32767 to 32767
+    #[packed_field]
+    pub y: i16,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "2")]
+pub struct PidStateReport {
+    #[packed_field(bits = "7")]
+    pub device_paused: bool,
+    #[packed_field(bits = "6")]
+    pub actuators_enabled: bool,
+    #[packed_field(bits = "5")]
+    pub safety_switch: bool,
+    #[packed_field(bits = "4")]
+    pub actuator_override_switch: bool,
+    #[packed_field(bits = "3")]
+    pub actuator_power: bool,
+    /// `true` if the effect at `effect_block_index` is playing
+    #[packed_field(bits = "2")]
+    pub effect_playing: bool,
+    #[packed_field(bytes = "1")]
+    pub effect_block_index: u8,
+}
+
+/// Set Effect report - parameters common to all effect types
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "15")]
+pub struct EffectParameters {
+    #[packed_field(bytes = "0")]
+    pub effect_block_index: u8,
+    #[packed_field(bytes = "1", ty = "enum")]
+    pub effect_type: EffectType,
+    /// Duration in milliseconds, `0xFFFF` for infinite
+    #[packed_field(bytes = "2..=3")]
+    pub duration: u16,
+    /// Trigger repeat interval in milliseconds
+    #[packed_field(bytes = "4..=5")]
+    pub trigger_repeat_interval: u16,
+    /// Sample period in milliseconds, 0 for the device default
+    #[packed_field(bytes = "6..=7")]
+    pub sample_period: u16,
+    /// Start delay in milliseconds
+    #[packed_field(bytes = "8..=9")]
+    pub start_delay: u16,
+    /// Effect gain, 0 to 255
+    #[packed_field(bytes = "10")]
+    pub gain: u8,
+    /// Trigger button 1 to 8, 0 for none
+    #[packed_field(bytes = "11")]
+    pub trigger_button: u8,
+    #[packed_field(bits = "103")]
+    pub x_axis_enable: bool,
+    #[packed_field(bits = "102")]
+    pub y_axis_enable: bool,
+    /// If set the effect applies along `direction` rather than to the enabled axes
+    #[packed_field(bits = "101")]
+    pub direction_enable: bool,
+    /// Polar direction, 0 to 255 for 0 to 360 degrees
+    #[packed_field(bytes = "13..=14", element_size_bytes = "1")]
+    pub direction: [u8; 2],
+}
+
+/// Set Condition report - parameters for one axis of a spring effect
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "13")]
+pub struct ConditionParameters {
+    #[packed_field(bytes = "0")]
+    pub effect_block_index: u8,
+    /// Axis the condition applies to, 0 for X, 1 for Y
+    #[packed_field(bytes = "1")]
+    pub parameter_block_offset: u8,
+    /// Center point offset, -10000 to 10000
+    #[packed_field(bytes = "2..=3")]
+    pub cp_offset: i16,
+    /// -10000 to 10000
+    #[packed_field(bytes = "4..=5")]
+    pub positive_coefficient: i16,
+    /// -10000 to 10000
+    #[packed_field(bytes = "6..=7")]
+    pub negative_coefficient: i16,
+    /// 0 to 10000
+    #[packed_field(bytes = "8..=9")]
+    pub positive_saturation: u16,
+    /// 0 to 10000
+    #[packed_field(bytes = "10..=11")]
+    pub negative_saturation: u16,
+    #[packed_field(bytes = "12")]
+    pub dead_band: u8,
+}
+
+/// Set Periodic report - parameters for square, sine, triangle and sawtooth effects
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "8")]
+pub struct PeriodicParameters {
+    #[packed_field(bytes = "0")]
+    pub effect_block_index: u8,
+    /// 0 to 10000
+    #[packed_field(bytes = "1..=2")]
+    pub magnitude: u16,
+    /// -10000 to 10000
+    #[packed_field(bytes = "3..=4")]
+    pub offset: i16,
+    /// 0 to 255 for 0 to 360 degrees
+    #[packed_field(bytes = "5")]
+    pub phase: u8,
+    /// Period in milliseconds
+    #[packed_field(bytes = "6..=7")]
+    pub period: u16,
+}
+
+/// Set Constant Force report
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "3")]
+pub struct ConstantForceParameters {
+    #[packed_field(bytes = "0")]
+    pub effect_block_index: u8,
+    /// -10000 to 10000
+    #[packed_field(bytes = "1..=2")]
+    pub magnitude: i16,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "3")]
+struct EffectOperationReport {
+    #[packed_field(bytes = "0")]
+    effect_block_index: u8,
+    #[packed_field(bytes = "1", ty = "enum")]
+    operation: EffectOperation,
+    #[packed_field(bytes = "2")]
+    loop_count: u8,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "3")]
+struct CreateNewEffectReport {
+    #[packed_field(bytes = "0", ty = "enum")]
+    effect_type: EffectType,
+    #[packed_field(bytes = "1..=2")]
+    byte_count: u16,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "4")]
+struct BlockLoadReport {
+    #[packed_field(bytes = "0")]
+    effect_block_index: u8,
+    #[packed_field(bytes = "1", ty = "enum")]
+    status: BlockLoadStatus,
+    #[packed_field(bytes = "2..=3")]
+    ram_pool_available: u16,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "4")]
+struct PoolReport {
+    #[packed_field(bytes = "0..=1")]
+    ram_pool_size: u16,
+    #[packed_field(bytes = "2")]
+    simultaneous_effects_max: u8,
+    #[packed_field(bits = "31")]
+    device_managed_pool: bool,
+    #[packed_field(bits = "30")]
+    shared_parameter_blocks: bool,
+}
+
+/// An effect loaded on the device
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct Effect {
+    pub parameters: EffectParameters,
+    /// Spring effect conditions for the X and Y axes
+    pub conditions: [ConditionParameters; 2],
+    pub periodic: PeriodicParameters,
+    pub constant_force: ConstantForceParameters,
+    pub playing: bool,
+    /// Number of times to play the effect, 255 for infinite
+    pub loop_count: u8,
+}
+
+impl Effect {
+    #[must_use]
+    pub fn effect_type(&self) -> EffectType {
+        self.parameters.effect_type
+    }
+}
+
+/// Effect slots, indexed by effect block index `1..=FORCE_FEEDBACK_MAX_EFFECTS`
+#[derive(Clone, Debug, Default)]
+pub struct EffectSlots {
+    slots: [Option<Effect>; FORCE_FEEDBACK_MAX_EFFECTS],
+}
+
+impl EffectSlots {
+    /// Allocate a free slot for a new effect, returning its effect block index
+    pub fn allocate(&mut self, effect_type: EffectType) -> Option<u8> {
+        let (i, slot) = self
+            .slots
+            .iter_mut()
+            .enumerate()
+            .find(|(_, s)| s.is_none())?;
+        let index = u8::try_from(i + 1).ok()?;
+        *slot = Some(Effect {
+            parameters: EffectParameters {
+                effect_block_index: index,
+                effect_type,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        Some(index)
+    }
+
+    /// Free the effect at `index`, returning `true` if there was an effect to free
+    pub fn free(&mut self, index: u8) -> bool {
+        Self::slot_index(index)
+            .and_then(|i| self.slots[i].take())
+            .is_some()
+    }
+
+    /// Free all effects
+    pub fn clear(&mut self) {
+        self.slots = Default::default();
+    }
+
+    #[must_use]
+    pub fn get(&self, index: u8) -> Option<&Effect> {
+        Self::slot_index(index).and_then(|i| self.slots[i].as_ref())
+    }
+
+    pub fn get_mut(&mut self, index: u8) -> Option<&mut Effect> {
+        Self::slot_index(index).and_then(|i| self.slots[i].as_mut())
+    }
+
+    /// Loaded effects and their effect block indexes
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &Effect)> {
+        self.slots
+            .iter()
+            .zip(1..)
+            .filter_map(|(s, i)| s.as_ref().map(|e| (i, e)))
+    }
+
+    /// Effects that are currently playing and their effect block indexes
+    pub fn playing(&self) -> impl Iterator<Item = (u8, &Effect)> {
+        self.iter().filter(|(_, e)| e.playing)
+    }
+
+    /// Stop all effects
+    pub fn stop_all(&mut self) {
+        self.slots
+            .iter_mut()
+            .flatten()
+            .for_each(|e| e.playing = false);
+    }
+
+    #[must_use]
+    pub fn free_count(&self) -> usize {
+        self.slots.iter().filter(|s| s.is_none()).count()
+    }
+
+    fn slot_index(index: u8) -> Option<usize> {
+        usize::from(index)
+            .checked_sub(1)
+            .filter(|i| *i < FORCE_FEEDBACK_MAX_EFFECTS)
+    }
+}
+
+/// Force feedback event received from the host
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ForceFeedbackEvent {
+    /// Parameters of the effect at the effect block index were updated
+    EffectUpdated(u8),
+    /// Effect at the effect block index was started, stopped or started solo
+    EffectOperation(u8, EffectOperation),
+    /// Effect at the effect block index was freed
+    EffectFreed(u8),
+    DeviceControl(DeviceControl),
+    DeviceGain(u8),
+}
+
+/// PID output report received from the host, including its report ID
+///
+/// Applied to the [`ForceFeedbackState`] as it's read
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct PidOutputReport {
+    data: [u8; 32],
+    len: u8,
+}
+
+impl PidOutputReport {
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data[..usize::from(self.len)]
+    }
+}
+
+impl OutputReport for PidOutputReport {
+    fn parse(data: &[u8]) -> usb_device::Result<Self> {
+        let mut report = Self::default();
+        report
+            .data
+            .get_mut(..data.len())
+            .ok_or(UsbError::ParseError)?
+            .copy_from_slice(data);
+        report.len = u8::try_from(data.len()).map_err(|_| UsbError::ParseError)?;
+        Ok(report)
+    }
+}
+
+/// Effect and device state of a force feedback device
+///
+/// Updated from the PID output and feature reports received from the host. Shared by
+/// [`ForceFeedbackJoystick`] and [`ForceFeedbackRacingWheel`](crate::device::racing_wheel::ForceFeedbackRacingWheel).
+#[derive(Clone, Debug)]
+pub struct ForceFeedbackState {
+    effects: EffectSlots,
+    block_load: BlockLoadReport,
+    device_gain: u8,
+    actuators_enabled: bool,
+    paused: bool,
+}
+
+impl Default for ForceFeedbackState {
+    fn default() -> Self {
+        Self {
+            effects: EffectSlots::default(),
+            block_load: BlockLoadReport::default(),
+            device_gain: u8::MAX,
+            actuators_enabled: false,
+            paused: false,
+        }
+    }
+}
+
+impl ForceFeedbackState {
+    /// Apply a PID output report, `data` starts with the report ID
+    pub(crate) fn apply_output_report(
+        &mut self,
+        data: &[u8],
+    ) -> usb_device::Result<ForceFeedbackEvent> {
+        let (report_id, report) = data.split_first().ok_or(UsbError::ParseError)?;
+
+        match *report_id {
+            SET_EFFECT_REPORT_ID => {
+                let parameters = unpack::<EffectParameters>(report)?;
+                let effect = self.effect_mut(parameters.effect_block_index)?;
+                effect.parameters = parameters;
+                Ok(ForceFeedbackEvent::EffectUpdated(
+                    parameters.effect_block_index,
+                ))
+            }
+            SET_CONDITION_REPORT_ID => {
+                let condition = unpack::<ConditionParameters>(report)?;
+                let effect = self.effect_mut(condition.effect_block_index)?;
+                let axis = effect
+                    .conditions
+                    .get_mut(usize::from(condition.parameter_block_offset))
+                    .ok_or(UsbError::ParseError)?;
+                *axis = condition;
+                Ok(ForceFeedbackEvent::EffectUpdated(
+                    condition.effect_block_index,
+                ))
+            }
+            SET_PERIODIC_REPORT_ID => {
+                let periodic = unpack::<PeriodicParameters>(report)?;
+                self.effect_mut(periodic.effect_block_index)?.periodic = periodic;
+                Ok(ForceFeedbackEvent::EffectUpdated(
+                    periodic.effect_block_index,
+                ))
+            }
+            SET_CONSTANT_FORCE_REPORT_ID => {
+                let constant_force = unpack::<ConstantForceParameters>(report)?;
+                self.effect_mut(constant_force.effect_block_index)?
+                    .constant_force = constant_force;
+                Ok(ForceFeedbackEvent::EffectUpdated(
+                    constant_force.effect_block_index,
+                ))
+            }
+            EFFECT_OPERATION_REPORT_ID => {
+                let operation = unpack::<EffectOperationReport>(report)?;
+                self.effect_mut(operation.effect_block_index)?;
+                if operation.operation == EffectOperation::StartSolo {
+                    self.effects.stop_all();
+                }
+                let effect = self.effect_mut(operation.effect_block_index)?;
+                effect.playing = operation.operation != EffectOperation::Stop;
+                effect.loop_count = operation.loop_count;
+                Ok(ForceFeedbackEvent::EffectOperation(
+                    operation.effect_block_index,
+                    operation.operation,
+                ))
+            }
+            BLOCK_FREE_REPORT_ID => {
+                let index = *report.first().ok_or(UsbError::ParseError)?;
+                if self.effects.free(index) {
+                    Ok(ForceFeedbackEvent::EffectFreed(index))
+                } else {
+                    warn!("Free of unallocated effect block {:X}", index);
+                    Err(UsbError::ParseError)
+                }
+            }
+            DEVICE_CONTROL_REPORT_ID => {
+                let control = report
+                    .first()
+                    .and_then(|c| DeviceControl::from_primitive(*c))
+                    .ok_or(UsbError::ParseError)?;
+                match control {
+                    DeviceControl::EnableActuators => self.actuators_enabled = true,
+                    DeviceControl::DisableActuators => self.actuators_enabled = false,
+                    DeviceControl::StopAllEffects => self.effects.stop_all(),
+                    DeviceControl::DeviceReset => {
+                        self.effects.clear();
+                        self.paused = false;
+                    }
+                    DeviceControl::DevicePause => self.paused = true,
+                    DeviceControl::DeviceContinue => self.paused = false,
+                }
+                Ok(ForceFeedbackEvent::DeviceControl(control))
+            }
+            DEVICE_GAIN_REPORT_ID => {
+                self.device_gain = *report.first().ok_or(UsbError::ParseError)?;
+                Ok(ForceFeedbackEvent::DeviceGain(self.device_gain))
+            }
+            _ => {
+                warn!("Unsupported force feedback output report {:X}", report_id);
+                Err(UsbError::ParseError)
+            }
+        }
+    }
+
+    fn effect_mut(&mut self, index: u8) -> usb_device::Result<&mut Effect> {
+        self.effects.get_mut(index).ok_or_else(|| {
+            warn!("Unallocated effect block {:X}", index);
+            UsbError::ParseError
+        })
+    }
+
+    pub(crate) fn get_feature_report(
+        &self,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        let report = match report_id {
+            BLOCK_LOAD_REPORT_ID => self.block_load.pack(),
+            POOL_REPORT_ID => PoolReport {
+                ram_pool_size: u16::try_from(FORCE_FEEDBACK_MAX_EFFECTS).unwrap_or(u16::MAX),
+                simultaneous_effects_max: u8::try_from(FORCE_FEEDBACK_MAX_EFFECTS)
+                    .unwrap_or(u8::MAX),
+                device_managed_pool: true,
+                shared_parameter_blocks: false,
+            }
+            .pack(),
+            _ => return Err(UsbError::Unsupported),
+        }
+        .map_err(|_| UsbError::ParseError)?;
+
+        let out = data
+            .get_mut(..=report.len())
+            .ok_or(UsbError::BufferOverflow)?;
+        out[0] = report_id;
+        out[1..].copy_from_slice(&report);
+        Ok(out.len())
+    }
+
+    pub(crate) fn set_feature_report(
+        &mut self,
+        report_id: u8,
+        data: &[u8],
+    ) -> usb_device::Result<()> {
+        if report_id != CREATE_NEW_EFFECT_REPORT_ID || data.first() != Some(&report_id) {
+            return Err(UsbError::Unsupported);
+        }
+        let request = unpack::<CreateNewEffectReport>(&data[1..])?;
+
+        self.block_load = if let Some(index) = self.effects.allocate(request.effect_type) {
+            trace!("Allocated effect block {:X}", index);
+            BlockLoadReport {
+                effect_block_index: index,
+                status: BlockLoadStatus::Success,
+                ram_pool_available: u16::try_from(self.effects.free_count()).unwrap_or(u16::MAX),
+            }
+        } else {
+            warn!("No free effect blocks");
+            BlockLoadReport {
+                effect_block_index: 0,
+                status: BlockLoadStatus::Full,
+                ram_pool_available: 0,
+            }
+        };
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn effects(&self) -> &EffectSlots {
+        &self.effects
+    }
+
+    /// Overall device gain set by the host, 0 to 255
+    #[must_use]
+    pub fn device_gain(&self) -> u8 {
+        self.device_gain
+    }
+
+    #[must_use]
+    pub fn actuators_enabled(&self) -> bool {
+        self.actuators_enabled
+    }
+
+    #[must_use]
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+}
+
+fn unpack<T: PackedStruct>(data: &[u8]) -> usb_device::Result<T> {
+    T::unpack_from_slice(
+        data.get(..core::mem::size_of::<T::ByteArray>())
+            .ok_or(UsbError::ParseError)?,
+    )
+    .map_err(|_| UsbError::ParseError)
+}
+
+/// Two axis, eight button joystick with force feedback
+///
+/// Effects are allocated by the host via feature reports handled by the device. Effect
+/// parameters and operations are received as output reports and applied to the device's
+/// [`EffectSlots`] by [`ForceFeedbackJoystick::read_report`], which should be polled regularly,
+/// one at a time as they're read, so none are dropped.
+/// The application then renders the playing effects from [`ForceFeedbackJoystick::effects`].
+pub struct ForceFeedbackJoystick<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutBytes32, ReportSingle>,
+    state: ForceFeedbackState,
+    received: ReceivedReport<PidOutputReport, 32>,
+}
+
+impl<B: UsbBus> ForceFeedbackJoystick<'_, B> {
+    pub fn write_report(
+        &mut self,
+        report: &ForceFeedbackJoystickReport,
+    ) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|_| {
+            error!("Error packing ForceFeedbackJoystickReport");
+            UsbHidError::SerializationError
+        })?;
+        self.write_with_id(JOYSTICK_INPUT_REPORT_ID, &data)
+    }
+
+    pub fn write_pid_state(&mut self, report: &PidStateReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|_| {
+            error!("Error packing PidStateReport");
+            UsbHidError::SerializationError
+        })?;
+        self.write_with_id(PID_STATE_REPORT_ID, &data)
+    }
+
+    fn write_with_id(&mut self, report_id: u8, data: &[u8]) -> Result<(), UsbHidError> {
+        let mut buffer = [0u8; 8];
+        buffer[0] = report_id;
+        buffer[1..=data.len()].copy_from_slice(data);
+        self.interface
+            .write_report(&buffer[..=data.len()])
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+
+    /// Read and apply an output report from the host, see [`OutputReports::read_output_report`]
+    pub fn read_report(&mut self) -> usb_device::Result<ForceFeedbackEvent> {
+        let report = self.read_output_report()?;
+        self.state.apply_output_report(report.data())
+    }
+
+    #[must_use]
+    pub fn effects(&self) -> &EffectSlots {
+        self.state.effects()
+    }
+
+    /// Overall device gain set by the host, 0 to 255
+    #[must_use]
+    pub fn device_gain(&self) -> u8 {
+        self.state.device_gain()
+    }
+
+    #[must_use]
+    pub fn actuators_enabled(&self) -> bool {
+        self.state.actuators_enabled()
+    }
+
+    #[must_use]
+    pub fn paused(&self) -> bool {
+        self.state.paused()
+    }
+}
+
+impl<B: UsbBus> OutputReports for ForceFeedbackJoystick<'_, B> {
+    type Report = PidOutputReport;
+
+    fn read_output_report(&mut self) -> usb_device::Result<PidOutputReport> {
+        self.received
+            .receive_next(|data| self.interface.read_report(data))?;
+        self.received.read()
+    }
+
+    fn output_report(&self) -> PidOutputReport {
+        self.received.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<PidOutputReport>> {
+        self.received
+            .receive_next(|data| self.interface.read_report(data))?;
+        Ok(self.received.changed())
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for ForceFeedbackJoystick<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutBytes32, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.state = ForceFeedbackState::default();
+        self.received = ReceivedReport::default();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        self.state.get_feature_report(report_id, data)
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        self.state.set_feature_report(report_id, data)
+    }
+}
+
+pub struct ForceFeedbackJoystickConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutBytes32, ReportSingle>,
+}
+
+impl Default for ForceFeedbackJoystickConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR
+            ))
+            .description("Force Feedback Joystick")
+            .in_endpoint(1.millis()))
+            .with_out_endpoint(1.millis()))
+            .build(),
+        )
+    }
+}
+
+impl<'a> ForceFeedbackJoystickConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes8, OutBytes32, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for ForceFeedbackJoystickConfig<'a> {
+    type Allocated = ForceFeedbackJoystick<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            state: ForceFeedbackState::default(),
+            received: ReceivedReport::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::force_feedback::{
+        EffectParameters, EffectSlots, EffectType, FORCE_FEEDBACK_MAX_EFFECTS,
+    };
+
+    #[test]
+    fn effect_parameters_layout() {
+        let parameters = EffectParameters::unpack(&[
+            3, 7, 0xFF, 0xFF, 0x00, 0x00, 0x0A, 0x00, 0x34, 0x12, 0x80, 0x00, 0x05, 0x40, 0x00,
+        ])
+        .unwrap();
+
+        assert_eq!(
+            parameters,
+            EffectParameters {
+                effect_block_index: 3,
+                effect_type: EffectType::Spring,
+                duration: 0xFFFF,
+                trigger_repeat_interval: 0,
+                sample_period: 10,
+                start_delay: 0x1234,
+                gain: 0x80,
+                trigger_button: 0,
+                x_axis_enable: true,
+                y_axis_enable: false,
+                direction_enable: true,
+                direction: [0x40, 0x00],
+            }
+        );
+    }
+
+    #[test]
+    fn effect_slots_allocate_and_free() {
+        let mut slots = EffectSlots::default();
+
+        for i in 1..=FORCE_FEEDBACK_MAX_EFFECTS {
+            assert_eq!(
+                slots.allocate(EffectType::Sine),
+                Some(u8::try_from(i).unwrap())
+            );
+        }
+        assert_eq!(slots.allocate(EffectType::Sine), None);
+
+        assert!(slots.free(4));
+        assert!(!slots.free(4));
+        assert!(!slots.free(0));
+        assert_eq!(slots.allocate(EffectType::Spring), Some(4));
+        assert_eq!(slots.get(4).unwrap().effect_type(), EffectType::Spring);
+        assert_eq!(slots.free_count(), 0);
+    }
+}
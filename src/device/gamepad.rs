@@ -0,0 +1,439 @@
+//!HID gamepad
+use crate::descriptor::concat;
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+
+/// Gamepad controls
+///
+/// * 16 buttons
+/// * 8 way hat switch D-pad with a null (centered) state
+/// * Left stick X/Y and right stick Z/Rz, -127 to 127
+/// * Left trigger (Brake) and right trigger (Accelerator), 0 to 255
+#[rustfmt::skip]
+const GAMEPAD_INPUT_ITEMS: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x05,        // Usage (Game Pad),
+    0xA1, 0x01,        // Collection (Application),
+    0x05, 0x09,        //   Usage Page (Button),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x10,        //   Usage Maximum (16),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x10,        //   Report Count (16),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x05, 0x01,        //   Usage Page (Generic Desktop),
+    0x09, 0x39,        //   Usage (Hat Switch),
+    0x25, 0x07,        //   Logical Maximum (7),
+    0x46, 0x3B, 0x01,  //   Physical Maximum (315),
+    0x65, 0x14,        //   Unit (Degrees),
+    0x75, 0x04,        //   Report Size (4),
+    0x95, 0x01,        //   Report Count (1),
+    0x81, 0x42,        //   Input (Data, Variable, Absolute, Null State),
+    0x45, 0x00,        //   Physical Maximum (0),
+    0x65, 0x00,        //   Unit (None),
+    0x81, 0x03,        //   Input (Constant, Variable, Absolute),
+    0x09, 0x30,        //   Usage (X),
+    0x09, 0x31,        //   Usage (Y),
+    0x09, 0x32,        //   Usage (Z),
+    0x09, 0x35,        //   Usage (Rz),
+    0x15, 0x81,        //   Logical Minimum (-127),
+    0x25, 0x7F,        //   Logical Maximum (127),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x04,        //   Report Count (4),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x05, 0x02,        //   Usage Page (Simulation Controls),
+    0x09, 0xC5,        //   Usage (Brake),
+    0x09, 0xC4,        //   Usage (Accelerator),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x95, 0x02,        //   Report Count (2),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+];
+
+/// Accelerometer X/Y/Z in milli G and gyrometer X/Y/Z in tenths of a degree per second,
+/// -32767 to 32767, using the Sensors page usages
+#[rustfmt::skip]
+const GAMEPAD_MOTION_ITEMS: &[u8] = &[
+    0x05, 0x20,        //   Usage Page (Sensors),
+    0x16, 0x01, 0x80,  //   Logical Minimum (-32767),
+    0x26, 0xFF, 0x7F,  //   Logical Maximum (32767),
+    0x75, 0x10,        //   Report Size (16),
+    0x95, 0x03,        //   Report Count (3),
+    0x09, 0x73,        //   Usage (Accelerometer 3D),
+    0xA1, 0x00,        //   Collection (Physical),
+    0x0A, 0x53, 0x04,  //     Usage (Acceleration Axis X),
+    0x0A, 0x54, 0x04,  //     Usage (Acceleration Axis Y),
+    0x0A, 0x55, 0x04,  //     Usage (Acceleration Axis Z),
+    0x55, 0x0D,        //     Unit Exponent (-3),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0x76,        //   Usage (Gyrometer 3D),
+    0xA1, 0x00,        //   Collection (Physical),
+    0x0A, 0x57, 0x04,  //     Usage (Angular Velocity about X Axis),
+    0x0A, 0x58, 0x04,  //     Usage (Angular Velocity about Y Axis),
+    0x0A, 0x59, 0x04,  //     Usage (Angular Velocity about Z Axis),
+    0x55, 0x0F,        //     Unit Exponent (-1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+];
+
+/// Gamepad report descriptor - a single [`GamepadReport`] input report
+#[rustfmt::skip]
+pub const GAMEPAD_REPORT_DESCRIPTOR: &[u8] = &concat::<
+    { GAMEPAD_INPUT_ITEMS.len() + 1 }
+>(&[
+    GAMEPAD_INPUT_ITEMS,
+    &[0xC0],           // End Collection
+]);
+
+/// Gamepad with motion sensors report descriptor - a single [`MotionGamepadReport`] input
+/// report, the [`GamepadReport`] followed by accelerometer and gyrometer axes
+#[rustfmt::skip]
+pub const MOTION_GAMEPAD_REPORT_DESCRIPTOR: &[u8] = &concat::<
+    { GAMEPAD_INPUT_ITEMS.len() + GAMEPAD_MOTION_ITEMS.len() + 1 }
+>(&[
+    GAMEPAD_INPUT_ITEMS,
+    GAMEPAD_MOTION_ITEMS,
+    &[0xC0],           // End Collection
+]);
+
+/// Hat switch direction
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum HatSwitch {
+    Up = 0,
+    UpRight = 1,
+    Right = 2,
+    DownRight = 3,
+    Down = 4,
+    DownLeft = 5,
+    Left = 6,
+    UpLeft = 7,
+    /// Null state, reported when no direction is pressed
+    #[default]
+    Centered = 8,
+}
+
+impl HatSwitch {
+    /// Hat switch direction from the state of four D-pad buttons
+    ///
+    /// Opposing directions cancel each other out
+    #[must_use]
+    #[allow(clippy::fn_params_excessive_bools)]
+    pub fn from_buttons(up: bool, down: bool, left: bool, right: bool) -> Self {
+        match (up && !down, down && !up, left && !right, right && !left) {
+            (true, _, false, false) => Self::Up,
+            (true, _, false, true) => Self::UpRight,
+            (true, _, true, _) => Self::UpLeft,
+            (_, true, false, false) => Self::Down,
+            (_, true, false, true) => Self::DownRight,
+            (_, true, true, _) => Self::DownLeft,
+            (false, false, true, _) => Self::Left,
+            (false, false, false, true) => Self::Right,
+            (false, false, false, false) => Self::Centered,
+        }
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "9")]
+pub struct GamepadReport {
+    /// Button state, bit 0 is button 1
+    #[packed_field(bytes = "0..=1")]
+    pub buttons: u16,
+    #[packed_field(bits = "20..=23", ty = "enum")]
+    pub hat: HatSwitch,
+    #[packed_field(bytes = "3")]
+    pub left_x: i8,
+    #[packed_field(bytes = "4")]
+    pub left_y: i8,
+    #[packed_field(bytes = "5")]
+    pub right_x: i8,
+    #[packed_field(bytes = "6")]
+    pub right_y: i8,
+    #[packed_field(bytes = "7")]
+    pub left_trigger: u8,
+    #[packed_field(bytes = "8")]
+    pub right_trigger: u8,
+}
+
+/// [`GamepadReport`] with motion sensor readings, as sent by DS4 style controllers
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "21")]
+pub struct MotionGamepadReport {
+    #[packed_field(bytes = "0..=8")]
+    pub gamepad: GamepadReport,
+    /// Acceleration in milli G
+    #[packed_field(bytes = "9..=10")]
+    pub accel_x: i16,
+    #[packed_field(bytes = "11..=12")]
+    pub accel_y: i16,
+    #[packed_field(bytes = "13..=14")]
+    pub accel_z: i16,
+    /// Angular velocity in tenths of a degree per second
+    #[packed_field(bytes = "15..=16")]
+    pub gyro_x: i16,
+    #[packed_field(bytes = "17..=18")]
+    pub gyro_y: i16,
+    #[packed_field(bytes = "19..=20")]
+    pub gyro_z: i16,
+}
+
+/// Gamepad with two analog sticks, two analog triggers, a D-pad hat switch and 16 buttons
+///
+/// Duplicate reports are suppressed by [`Gamepad::write_report`], returning
+/// [`UsbHidError::Duplicate`], so the controller state can be written unconditionally from a
+/// polling loop. Use [`GamepadConfig::without_deduplication`] to send a report on every write.
+pub struct Gamepad<'a, B: UsbBus> {
+    interface: ManagedIdleInterface<'a, B, GamepadReport, InBytes16, OutNone>,
+}
+
+impl<B: UsbBus> Gamepad<'_, B> {
+    pub fn write_report(&mut self, report: &GamepadReport) -> Result<(), UsbHidError> {
+        self.interface.write_report(report)
+    }
+}
+
+pub struct GamepadConfig<'a> {
+    interface: ManagedIdleInterfaceConfig<'a, GamepadReport, InBytes16, OutNone>,
+}
+
+impl<'a> GamepadConfig<'a> {
+    #[must_use]
+    pub fn new(
+        interface: ManagedIdleInterfaceConfig<'a, GamepadReport, InBytes16, OutNone>,
+    ) -> Self {
+        Self { interface }
+    }
+
+    /// Send every report written, even if unchanged, see
+    /// [`ManagedIdleInterfaceConfig::without_deduplication`]
+    #[must_use]
+    pub fn without_deduplication(mut self) -> Self {
+        self.interface = self.interface.without_deduplication();
+        self
+    }
+}
+
+impl Default for GamepadConfig<'_> {
+    fn default() -> Self {
+        Self::new(ManagedIdleInterfaceConfig::new(
+            unwrap!(unwrap!(InterfaceBuilder::new(GAMEPAD_REPORT_DESCRIPTOR))
+                .description("Gamepad")
+                .in_endpoint(1.millis()))
+            .without_out_endpoint()
+            .build(),
+        ))
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for GamepadConfig<'a> {
+    type Allocated = Gamepad<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Gamepad {
+            interface: self.interface.try_allocate(usb_alloc)?,
+        })
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for Gamepad<'a, B> {
+    type I = Interface<'a, B, InBytes16, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        self.interface.interface()
+    }
+
+    fn reset(&mut self) {
+        self.interface.reset();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        self.interface.tick()
+    }
+
+    fn get_input_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        self.interface.get_input_report(report_id, data)
+    }
+
+    fn endpoint_halt_cleared(&mut self) {
+        self.interface.endpoint_halt_cleared();
+    }
+}
+
+/// [`Gamepad`] with an accelerometer and gyrometer for motion controls
+///
+/// Duplicate reports are suppressed by [`MotionGamepad::write_report`], returning
+/// [`UsbHidError::Duplicate`]. Motion readings rarely repeat exactly, so in practice a report
+/// is sent on every write.
+pub struct MotionGamepad<'a, B: UsbBus> {
+    interface: ManagedIdleInterface<'a, B, MotionGamepadReport, InBytes32, OutNone>,
+}
+
+impl<B: UsbBus> MotionGamepad<'_, B> {
+    pub fn write_report(&mut self, report: &MotionGamepadReport) -> Result<(), UsbHidError> {
+        self.interface.write_report(report)
+    }
+}
+
+pub struct MotionGamepadConfig<'a> {
+    interface: ManagedIdleInterfaceConfig<'a, MotionGamepadReport, InBytes32, OutNone>,
+}
+
+impl<'a> MotionGamepadConfig<'a> {
+    #[must_use]
+    pub fn new(
+        interface: ManagedIdleInterfaceConfig<'a, MotionGamepadReport, InBytes32, OutNone>,
+    ) -> Self {
+        Self { interface }
+    }
+
+    /// Send every report written, even if unchanged, see
+    /// [`ManagedIdleInterfaceConfig::without_deduplication`]
+    #[must_use]
+    pub fn without_deduplication(mut self) -> Self {
+        self.interface = self.interface.without_deduplication();
+        self
+    }
+}
+
+impl Default for MotionGamepadConfig<'_> {
+    fn default() -> Self {
+        Self::new(ManagedIdleInterfaceConfig::new(
+            unwrap!(
+                unwrap!(InterfaceBuilder::new(MOTION_GAMEPAD_REPORT_DESCRIPTOR))
+                    .description("Motion Gamepad")
+                    .in_endpoint(1.millis())
+            )
+            .without_out_endpoint()
+            .build(),
+        ))
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for MotionGamepadConfig<'a> {
+    type Allocated = MotionGamepad<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(MotionGamepad {
+            interface: self.interface.try_allocate(usb_alloc)?,
+        })
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for MotionGamepad<'a, B> {
+    type I = Interface<'a, B, InBytes32, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        self.interface.interface()
+    }
+
+    fn reset(&mut self) {
+        self.interface.reset();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        self.interface.tick()
+    }
+
+    fn get_input_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        self.interface.get_input_report(report_id, data)
+    }
+
+    fn endpoint_halt_cleared(&mut self) {
+        self.interface.endpoint_halt_cleared();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::gamepad::{
+        GamepadReport, HatSwitch, MotionGamepadReport, GAMEPAD_REPORT_DESCRIPTOR,
+        MOTION_GAMEPAD_REPORT_DESCRIPTOR,
+    };
+
+    #[test]
+    fn gamepad_report_layout() {
+        let bytes = GamepadReport {
+            buttons: 0x8001,
+            hat: HatSwitch::Centered,
+            left_x: -127,
+            left_y: 127,
+            right_x: 1,
+            right_y: -1,
+            left_trigger: 0x10,
+            right_trigger: 0xFF,
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(
+            bytes,
+            [0x01, 0x80, 0x08, 0x81, 0x7F, 0x01, 0xFF, 0x10, 0xFF]
+        );
+    }
+
+    #[test]
+    fn motion_gamepad_report_layout() {
+        let bytes = MotionGamepadReport {
+            gamepad: GamepadReport {
+                buttons: 0x0001,
+                ..Default::default()
+            },
+            accel_x: 1000,
+            accel_y: -1,
+            accel_z: 0,
+            gyro_x: 0x1234,
+            gyro_y: 0,
+            gyro_z: -32767,
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(bytes[..3], [0x01, 0x00, 0x08]);
+        assert_eq!(
+            bytes[9..],
+            [0xE8, 0x03, 0xFF, 0xFF, 0x00, 0x00, 0x34, 0x12, 0x00, 0x00, 0x01, 0x80]
+        );
+    }
+
+    #[test]
+    fn motion_descriptor_extends_gamepad_descriptor() {
+        let len = GAMEPAD_REPORT_DESCRIPTOR.len() - 1;
+        assert_eq!(
+            MOTION_GAMEPAD_REPORT_DESCRIPTOR[..len],
+            GAMEPAD_REPORT_DESCRIPTOR[..len]
+        );
+        assert_eq!(MOTION_GAMEPAD_REPORT_DESCRIPTOR.last(), Some(&0xC0));
+    }
+
+    #[test]
+    fn hat_switch_from_buttons() {
+        assert_eq!(
+            HatSwitch::from_buttons(false, false, false, false),
+            HatSwitch::Centered
+        );
+        assert_eq!(
+            HatSwitch::from_buttons(true, false, false, true),
+            HatSwitch::UpRight
+        );
+        assert_eq!(
+            HatSwitch::from_buttons(false, true, true, false),
+            HatSwitch::DownLeft
+        );
+        assert_eq!(
+            HatSwitch::from_buttons(true, true, true, false),
+            HatSwitch::Left
+        );
+    }
+}
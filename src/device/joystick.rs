@@ -1,10 +1,12 @@
 //!HID joystick
+use crate::device::gamepad::HatSwitch;
 use crate::usb_class::prelude::*;
 use core::default::Default;
 use fugit::ExtU32;
 use packed_struct::prelude::*;
 use usb_device::bus::UsbBus;
 use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
 
 #[rustfmt::skip]
 pub const JOYSTICK_DESCRIPTOR: &[u8] = &[
@@ -80,7 +82,6 @@ pub struct JoystickConfig<'a> {
 }
 
 impl Default for JoystickConfig<'_> {
-    #[must_use]
     fn default() -> Self {
         Self::new(
             unwrap!(unwrap!(InterfaceBuilder::new(JOYSTICK_DESCRIPTOR))
@@ -103,9 +104,675 @@ impl<'a> JoystickConfig<'a> {
 impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for JoystickConfig<'a> {
     type Allocated = Joystick<'a, B>;
 
-    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        Self::Allocated {
-            interface: Interface::new(usb_alloc, self.interface),
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+        })
+    }
+}
+
+/// Joystick axis, with the Generic Desktop usage reported for the axis
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum JoystickAxis {
+    X = 0x30,
+    Y = 0x31,
+    Z = 0x32,
+    Rx = 0x33,
+    Ry = 0x34,
+    Rz = 0x35,
+    Slider = 0x36,
+    Dial = 0x37,
+    Wheel = 0x38,
+}
+
+/// Maximum number of axes in a [`JoystickLayout`]
+pub const JOYSTICK_MAX_AXES: usize = 8;
+/// Maximum number of buttons in a [`JoystickLayout`]
+pub const JOYSTICK_MAX_BUTTONS: u8 = 32;
+/// Maximum number of hat switches in a [`JoystickLayout`]
+pub const JOYSTICK_MAX_HATS: u8 = 4;
+
+/// Report layout of a [`ConfigurableJoystick`]
+///
+/// The report contains, in order:
+/// * `buttons` one bit buttons, padded to a whole byte
+/// * `hats` four bit hat switches, padded to a whole byte
+/// * `axes` signed 16 bit axes, -32767 to 32767
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct JoystickLayout {
+    pub axes: &'static [JoystickAxis],
+    pub buttons: u8,
+    pub hats: u8,
+}
+
+impl JoystickLayout {
+    /// Length of the report in bytes
+    #[must_use]
+    pub const fn report_len(&self) -> usize {
+        (self.buttons as usize).div_ceil(8) + (self.hats as usize).div_ceil(2) + self.axes.len() * 2
+    }
+}
+
+/// Default [`JoystickLayout`] - six axes, 32 buttons and one hat switch
+pub const DEFAULT_JOYSTICK_LAYOUT: JoystickLayout = JoystickLayout {
+    axes: &[
+        JoystickAxis::X,
+        JoystickAxis::Y,
+        JoystickAxis::Z,
+        JoystickAxis::Rx,
+        JoystickAxis::Ry,
+        JoystickAxis::Rz,
+    ],
+    buttons: 32,
+    hats: 1,
+};
+
+/// Report descriptor for a [`JoystickLayout`], generated at compile time
+///
+/// ```
+/// use usbd_human_interface_device::device::joystick::*;
+///
+/// static BUTTON_BOX: JoystickDescriptor = JoystickDescriptor::new(JoystickLayout {
+///     axes: &[JoystickAxis::Slider],
+///     buttons: 24,
+///     hats: 2,
+/// });
+///
+/// let config = ConfigurableJoystickConfig::from_descriptor(&BUTTON_BOX);
+/// ```
+pub struct JoystickDescriptor {
+    layout: JoystickLayout,
+    calibration_report: bool,
+    descriptor: [u8; 160],
+    len: usize,
+}
+
+impl JoystickDescriptor {
+    /// Generate the report descriptor for `layout`
+    ///
+    /// Panics, at compile time if used in a const context, if `layout` exceeds
+    /// [`JOYSTICK_MAX_AXES`], [`JOYSTICK_MAX_BUTTONS`] or [`JOYSTICK_MAX_HATS`]
+    #[must_use]
+    pub const fn new(layout: JoystickLayout) -> Self {
+        Self::generate(layout, false)
+    }
+
+    /// Generate the report descriptor for `layout` with a vendor defined feature report of the
+    /// [`AxisCalibration`] of each axis, in the order of [`JoystickLayout::axes`], so the host can
+    /// read and adjust the calibration
+    ///
+    /// Panics as [`JoystickDescriptor::new`]
+    #[must_use]
+    pub const fn with_calibration_report(layout: JoystickLayout) -> Self {
+        Self::generate(layout, true)
+    }
+
+    #[rustfmt::skip]
+    const fn generate(layout: JoystickLayout, calibration_report: bool) -> Self {
+        core::assert!(layout.axes.len() <= JOYSTICK_MAX_AXES, "too many axes");
+        core::assert!(layout.buttons <= JOYSTICK_MAX_BUTTONS, "too many buttons");
+        core::assert!(layout.hats <= JOYSTICK_MAX_HATS, "too many hat switches");
+
+        let mut d = Self {
+            layout,
+            calibration_report,
+            descriptor: [0; 160],
+            len: 0,
+        };
+
+        d.push(&[
+            0x05, 0x01,                 // Usage Page (Generic Desktop),
+            0x09, 0x04,                 // Usage (Joystick),
+            0xA1, 0x01,                 // Collection (Application),
+        ]);
+
+        if layout.buttons > 0 {
+            d.push(&[
+                0x05, 0x09,             //   Usage Page (Button),
+                0x19, 0x01,             //   Usage Minimum (1),
+                0x29, layout.buttons,   //   Usage Maximum (buttons),
+                0x15, 0x00,             //   Logical Minimum (0),
+                0x25, 0x01,             //   Logical Maximum (1),
+                0x75, 0x01,             //   Report Size (1),
+                0x95, layout.buttons,   //   Report Count (buttons),
+                0x81, 0x02,             //   Input (Data, Variable, Absolute),
+            ]);
+            d.pad(layout.buttons, 1);
+        }
+
+        d.push(&[
+            0x05, 0x01,                 //   Usage Page (Generic Desktop),
+        ]);
+
+        if layout.hats > 0 {
+            let mut i = 0;
+            while i < layout.hats {
+                d.push(&[
+                    0x09, 0x39,         //   Usage (Hat Switch),
+                ]);
+                i += 1;
+            }
+            d.push(&[
+                0x15, 0x00,             //   Logical Minimum (0),
+                0x25, 0x07,             //   Logical Maximum (7),
+                0x35, 0x00,             //   Physical Minimum (0),
+                0x46, 0x3B, 0x01,       //   Physical Maximum (315),
+                0x65, 0x14,             //   Unit (Degrees),
+                0x75, 0x04,             //   Report Size (4),
+                0x95, layout.hats,      //   Report Count (hats),
+                0x81, 0x42,             //   Input (Data, Variable, Absolute, Null State),
+                0x45, 0x00,             //   Physical Maximum (0),
+                0x65, 0x00,             //   Unit (None),
+            ]);
+            d.pad(layout.hats, 4);
+        }
+
+        if !layout.axes.is_empty() {
+            let mut i = 0;
+            while i < layout.axes.len() {
+                d.push(&[
+                    0x09, layout.axes[i] as u8, //   Usage (axis),
+                ]);
+                i += 1;
+            }
+            #[allow(clippy::cast_possible_truncation)]
+            d.push(&[
+                0x16, 0x01, 0x80,       //   Logical Minimum (-32767),
+                0x26, 0xFF, 0x7F,       //   Logical Maximum (32767),
+                0x75, 0x10,             //   Report Size (16),
+                0x95, layout.axes.len() as u8, //   Report Count (axes),
+                0x81, 0x02,             //   Input (Data, Variable, Absolute),
+            ]);
+        }
+
+        if calibration_report && !layout.axes.is_empty() {
+            #[allow(clippy::cast_possible_truncation)]
+            d.push(&[
+                0x06, 0x00, 0xFF,       //   Usage Page (Vendor Defined 0xFF00),
+                0x09, 0x01,             //   Usage (0x01),
+                0x15, 0x00,             //   Logical Minimum (0),
+                0x26, 0xFF, 0x00,       //   Logical Maximum (255),
+                0x75, 0x08,             //   Report Size (8),
+                0x95, (layout.axes.len() * AXIS_CALIBRATION_LEN) as u8, //   Report Count (axes * 9),
+                0xB1, 0x02,             //   Feature (Data, Variable, Absolute),
+            ]);
+        }
+
+        d.push(&[
+            0xC0,                       // End Collection
+        ]);
+
+        d
+    }
+
+    /// Pad `count` fields of `size` bits to a whole byte
+    #[rustfmt::skip]
+    const fn pad(&mut self, count: u8, size: u8) {
+        let bits = (count * size) % 8;
+        if bits != 0 {
+            self.push(&[
+                0x75, 8 - bits,         //   Report Size (padding),
+                0x95, 0x01,             //   Report Count (1),
+                0x81, 0x03,             //   Input (Constant, Variable, Absolute),
+            ]);
+        }
+    }
+
+    const fn push(&mut self, items: &[u8]) {
+        let mut i = 0;
+        while i < items.len() {
+            self.descriptor[self.len] = items[i];
+            self.len += 1;
+            i += 1;
+        }
+    }
+
+    #[must_use]
+    pub const fn layout(&self) -> JoystickLayout {
+        self.layout
+    }
+
+    /// Whether the descriptor includes the calibration feature report, see
+    /// [`JoystickDescriptor::with_calibration_report`]
+    #[must_use]
+    pub const fn calibration_report(&self) -> bool {
+        self.calibration_report && !self.layout.axes.is_empty()
+    }
+
+    #[must_use]
+    pub fn descriptor(&self) -> &[u8] {
+        &self.descriptor[..self.len]
+    }
+}
+
+/// Report descriptor for [`DEFAULT_JOYSTICK_LAYOUT`]
+pub static DEFAULT_JOYSTICK_DESCRIPTOR: JoystickDescriptor =
+    JoystickDescriptor::new(DEFAULT_JOYSTICK_LAYOUT);
+
+/// Report for a [`ConfigurableJoystick`]
+///
+/// Only the buttons, hats and axes included in the device's [`JoystickLayout`] are sent. `axes`
+/// are in the order given by [`JoystickLayout::axes`]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct ConfigurableJoystickReport {
+    /// Button state, bit 0 is button 1
+    pub buttons: u32,
+    pub hats: [HatSwitch; 4],
+    pub axes: [i16; 8],
+}
+
+impl ConfigurableJoystickReport {
+    /// Serialize the report for `layout` into `buffer`, returning the length of the report
+    pub fn pack_into(
+        &self,
+        layout: &JoystickLayout,
+        buffer: &mut [u8],
+    ) -> Result<usize, UsbHidError> {
+        let len = layout.report_len();
+        if buffer.len() < len {
+            error!("Buffer too small for ConfigurableJoystickReport");
+            return Err(UsbHidError::SerializationError);
+        }
+
+        let buttons_len = usize::from(layout.buttons).div_ceil(8);
+        let hats_len = usize::from(layout.hats).div_ceil(2);
+
+        let buttons = if layout.buttons < 32 {
+            self.buttons & ((1 << layout.buttons) - 1)
+        } else {
+            self.buttons
+        };
+        buffer[..buttons_len].copy_from_slice(&buttons.to_le_bytes()[..buttons_len]);
+
+        let hats = &mut buffer[buttons_len..buttons_len + hats_len];
+        hats.fill(0);
+        for (i, hat) in self.hats[..usize::from(layout.hats)].iter().enumerate() {
+            hats[i / 2] |= (*hat as u8) << ((i % 2) * 4);
+        }
+
+        for (chunk, axis) in buffer[buttons_len + hats_len..len]
+            .chunks_exact_mut(2)
+            .zip(self.axes)
+        {
+            chunk.copy_from_slice(&axis.max(-i16::MAX).to_le_bytes());
+        }
+
+        Ok(len)
+    }
+}
+
+/// Length in bytes of an [`AxisCalibration`] in the calibration feature report
+pub const AXIS_CALIBRATION_LEN: usize = 9;
+
+/// Calibration of a [`ConfigurableJoystick`] axis, applied to the axis values written before
+/// they are sent
+///
+/// Values are limited to `min..=max` and scaled so that `min`, `center` and `max` are sent as
+/// -32767, 0 and 32767. Values within `deadzone` of `center` are sent as 0, and the rest of the
+/// range is scaled to start from 0 at the edge of the deadzone.
+///
+/// In the calibration feature report each axis is the little endian `min`, `center`, `max` and
+/// `deadzone` followed by a byte with `inverted` in bit 0.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PackedStruct)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb", size_bytes = "9")]
+pub struct AxisCalibration {
+    #[packed_field(bytes = "0..=1")]
+    pub min: i16,
+    #[packed_field(bytes = "2..=3")]
+    pub center: i16,
+    #[packed_field(bytes = "4..=5")]
+    pub max: i16,
+    #[packed_field(bytes = "6..=7")]
+    pub deadzone: u16,
+    /// Negate the calibrated value
+    #[packed_field(bits = "71")]
+    pub inverted: bool,
+}
+
+impl AxisCalibration {
+    /// Calibration for values from `min` to `max`, centered on `center`, with no deadzone
+    #[must_use]
+    pub const fn new(min: i16, center: i16, max: i16) -> Self {
+        Self {
+            min,
+            center,
+            max,
+            deadzone: 0,
+            inverted: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_deadzone(self, deadzone: u16) -> Self {
+        Self { deadzone, ..self }
+    }
+
+    #[must_use]
+    pub const fn with_inverted(self, inverted: bool) -> Self {
+        Self { inverted, ..self }
+    }
+
+    /// Whether `min <= center <= max`, calibrations set by the host must be valid
+    #[must_use]
+    pub const fn is_valid(&self) -> bool {
+        self.min <= self.center && self.center <= self.max
+    }
+
+    /// Calibrated value of `value`, from -32767 to 32767
+    #[must_use]
+    pub fn apply(&self, value: i16) -> i16 {
+        let min = i32::from(self.min);
+        let center = i32::from(self.center);
+        let max = i32::from(self.max);
+        let deadzone = i32::from(self.deadzone);
+
+        let offset = i32::from(value).max(min).min(max) - center;
+        let range = if offset < 0 {
+            center - min
+        } else {
+            max - center
+        };
+        let distance = offset.abs();
+        // At most 65535 * 32767, within i32
+        let scaled = if distance <= deadzone {
+            0
+        } else if range <= deadzone {
+            i32::from(i16::MAX)
+        } else {
+            ((distance - deadzone) * i32::from(i16::MAX) / (range - deadzone))
+                .min(i32::from(i16::MAX))
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        let scaled = scaled as i16;
+        if (offset < 0) == self.inverted {
+            scaled
+        } else {
+            -scaled
         }
     }
 }
+
+impl Default for AxisCalibration {
+    /// No calibration, values are sent as written
+    fn default() -> Self {
+        Self::new(-i16::MAX, 0, i16::MAX)
+    }
+}
+
+/// Joystick with a configurable number of axes, buttons and hat switches
+///
+/// See [`JoystickDescriptor`]
+///
+/// Each axis is calibrated with an [`AxisCalibration`] before the report is sent, set with
+/// [`ConfigurableJoystickConfig::with_calibrations`] or [`ConfigurableJoystick::calibrations_mut`],
+/// or by the host if the descriptor includes the calibration feature report, see
+/// [`JoystickDescriptor::with_calibration_report`].
+pub struct ConfigurableJoystick<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes32, OutNone, ReportSingle>,
+    layout: JoystickLayout,
+    calibrations: [AxisCalibration; JOYSTICK_MAX_AXES],
+    calibration_report: bool,
+}
+
+impl<B: UsbBus> ConfigurableJoystick<'_, B> {
+    pub fn write_report(&mut self, report: &ConfigurableJoystickReport) -> Result<(), UsbHidError> {
+        let mut report = *report;
+        for (axis, calibration) in report.axes.iter_mut().zip(self.calibrations()) {
+            *axis = calibration.apply(*axis);
+        }
+        let mut data = [0u8; 32];
+        let len = report.pack_into(&self.layout, &mut data)?;
+        self.interface
+            .write_report(&data[..len])
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+
+    #[must_use]
+    pub fn layout(&self) -> &JoystickLayout {
+        &self.layout
+    }
+
+    /// Calibration of each axis, in the order of [`JoystickLayout::axes`]
+    #[must_use]
+    pub fn calibrations(&self) -> &[AxisCalibration] {
+        &self.calibrations[..self.layout.axes.len()]
+    }
+
+    /// Change the calibration of the axes, in the order of [`JoystickLayout::axes`]
+    pub fn calibrations_mut(&mut self) -> &mut [AxisCalibration] {
+        &mut self.calibrations[..self.layout.axes.len()]
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for ConfigurableJoystick<'a, B> {
+    type I = Interface<'a, B, InBytes32, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {}
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        if !self.calibration_report || report_id != 0 {
+            return Err(UsbError::Unsupported);
+        }
+        let len = self.layout.axes.len() * AXIS_CALIBRATION_LEN;
+        let data = data.get_mut(..len).ok_or(UsbError::BufferOverflow)?;
+        for (chunk, calibration) in data
+            .chunks_exact_mut(AXIS_CALIBRATION_LEN)
+            .zip(self.calibrations())
+        {
+            calibration
+                .pack_to_slice(chunk)
+                .map_err(|_| UsbError::ParseError)?;
+        }
+        Ok(len)
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        if !self.calibration_report || report_id != 0 {
+            return Err(UsbError::Unsupported);
+        }
+        let data = data
+            .get(..self.layout.axes.len() * AXIS_CALIBRATION_LEN)
+            .ok_or(UsbError::ParseError)?;
+        let mut calibrations = self.calibrations;
+        for (calibration, chunk) in calibrations
+            .iter_mut()
+            .zip(data.chunks_exact(AXIS_CALIBRATION_LEN))
+        {
+            *calibration = AxisCalibration::unpack_from_slice(chunk)
+                .ok()
+                .filter(AxisCalibration::is_valid)
+                .ok_or(UsbError::ParseError)?;
+        }
+        info!("Set joystick calibration");
+        self.calibrations = calibrations;
+        Ok(())
+    }
+}
+
+pub struct ConfigurableJoystickConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes32, OutNone, ReportSingle>,
+    layout: JoystickLayout,
+    calibrations: [AxisCalibration; JOYSTICK_MAX_AXES],
+    calibration_report: bool,
+}
+
+impl Default for ConfigurableJoystickConfig<'_> {
+    fn default() -> Self {
+        Self::from_descriptor(&DEFAULT_JOYSTICK_DESCRIPTOR)
+    }
+}
+
+impl<'a> ConfigurableJoystickConfig<'a> {
+    /// `interface` must use a report descriptor matching `layout`, see [`JoystickDescriptor`]
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes32, OutNone, ReportSingle>,
+        layout: JoystickLayout,
+    ) -> Self {
+        Self {
+            interface,
+            layout,
+            calibrations: [AxisCalibration::default(); JOYSTICK_MAX_AXES],
+            calibration_report: false,
+        }
+    }
+
+    /// Configuration with the default interface settings for a generated descriptor
+    #[must_use]
+    pub fn from_descriptor(descriptor: &'static JoystickDescriptor) -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                descriptor.descriptor()
+            ))
+            .description("Joystick")
+            .in_endpoint(10.millis()))
+            .without_out_endpoint()
+            .build(),
+            descriptor.layout(),
+        )
+        .with_calibration_report(descriptor.calibration_report())
+    }
+
+    /// Calibration of each axis, in the order of [`JoystickLayout::axes`], axes without a
+    /// calibration are sent as written
+    #[must_use]
+    pub fn with_calibrations(mut self, calibrations: &[AxisCalibration]) -> Self {
+        for (calibration, c) in self.calibrations.iter_mut().zip(calibrations) {
+            *calibration = *c;
+        }
+        self
+    }
+
+    /// Whether the host can read and set the calibration with a feature report, the interface
+    /// must use a report descriptor from [`JoystickDescriptor::with_calibration_report`]
+    #[must_use]
+    pub fn with_calibration_report(mut self, calibration_report: bool) -> Self {
+        self.calibration_report = calibration_report;
+        self
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for ConfigurableJoystickConfig<'a> {
+    type Allocated = ConfigurableJoystick<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            layout: self.layout,
+            calibrations: self.calibrations,
+            calibration_report: self.calibration_report,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use crate::device::gamepad::HatSwitch;
+    use crate::device::joystick::{
+        AxisCalibration, ConfigurableJoystickReport, JoystickAxis, JoystickDescriptor,
+        JoystickLayout, DEFAULT_JOYSTICK_DESCRIPTOR, DEFAULT_JOYSTICK_LAYOUT,
+    };
+    use packed_struct::prelude::*;
+
+    #[test]
+    fn default_joystick_descriptor() {
+        let descriptor = DEFAULT_JOYSTICK_DESCRIPTOR.descriptor();
+        assert_eq!(descriptor[..6], [0x05, 0x01, 0x09, 0x04, 0xA1, 0x01]);
+        assert_eq!(descriptor.last(), Some(&0xC0));
+        assert_eq!(DEFAULT_JOYSTICK_LAYOUT.report_len(), 4 + 1 + 12);
+    }
+
+    #[test]
+    fn configurable_joystick_report_layout() {
+        static DESCRIPTOR: JoystickDescriptor = JoystickDescriptor::new(JoystickLayout {
+            axes: &[JoystickAxis::X, JoystickAxis::Slider],
+            buttons: 10,
+            hats: 3,
+        });
+
+        let report = ConfigurableJoystickReport {
+            buttons: 0xFFFF_FE01,
+            hats: [
+                HatSwitch::Up,
+                HatSwitch::Left,
+                HatSwitch::Centered,
+                HatSwitch::Down,
+            ],
+            axes: [i16::MIN, 0x1234, 1, 1, 1, 1, 1, 1],
+        };
+
+        let mut buffer = [0u8; 32];
+        let len = report.pack_into(&DESCRIPTOR.layout(), &mut buffer).unwrap();
+
+        assert_eq!(
+            buffer[..len],
+            [0x01, 0x02, 0x60, 0x08, 0x01, 0x80, 0x34, 0x12]
+        );
+    }
+
+    #[test]
+    fn axis_calibration_scales_around_center() {
+        assert_eq!(AxisCalibration::default().apply(i16::MIN), -i16::MAX);
+        assert_eq!(AxisCalibration::default().apply(1234), 1234);
+
+        let calibration = AxisCalibration::new(0, 1000, 4000).with_deadzone(100);
+        assert_eq!(calibration.apply(-50), -i16::MAX);
+        assert_eq!(calibration.apply(0), -i16::MAX);
+        assert_eq!(calibration.apply(950), 0);
+        assert_eq!(calibration.apply(1100), 0);
+        assert_eq!(calibration.apply(2550), i16::MAX / 2);
+        assert_eq!(calibration.apply(i16::MAX), i16::MAX);
+        assert_eq!(calibration.with_inverted(true).apply(2550), -(i16::MAX / 2));
+        assert_eq!(calibration.with_inverted(true).apply(0), i16::MAX);
+    }
+
+    #[test]
+    fn axis_calibration_feature_layout() {
+        let calibration = AxisCalibration::new(-100, 0x0102, 0x7FFF)
+            .with_deadzone(0x0304)
+            .with_inverted(true);
+        let bytes = calibration.pack().unwrap();
+        assert_eq!(
+            bytes,
+            [0x9C, 0xFF, 0x02, 0x01, 0xFF, 0x7F, 0x04, 0x03, 0x01]
+        );
+        assert_eq!(AxisCalibration::unpack(&bytes).unwrap(), calibration);
+        assert!(!AxisCalibration::new(1, 0, 2).is_valid());
+    }
+
+    #[test]
+    fn calibration_report_is_described() {
+        static DESCRIPTOR: JoystickDescriptor =
+            JoystickDescriptor::with_calibration_report(JoystickLayout {
+                axes: &[JoystickAxis::X, JoystickAxis::Y],
+                buttons: 0,
+                hats: 0,
+            });
+
+        assert!(DESCRIPTOR.calibration_report());
+        assert!(!DEFAULT_JOYSTICK_DESCRIPTOR.calibration_report());
+        assert_eq!(
+            DESCRIPTOR.descriptor()[DESCRIPTOR.descriptor().len() - 17..],
+            [
+                0x06, 0x00, 0xFF, 0x09, 0x01, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08, 0x95, 18,
+                0xB1, 0x02, 0xC0
+            ]
+        );
+    }
+}
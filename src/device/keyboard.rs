@@ -2,7 +2,9 @@
 
 use crate::page::Keyboard;
 use crate::usb_class::prelude::*;
-use fugit::ExtU32;
+use crate::usb_class::BuilderResult;
+use fugit::{ExtU32, MillisDurationU32};
+use heapless::Deque;
 use packed_struct::prelude::*;
 #[allow(clippy::wildcard_imports)]
 use usb_device::class_prelude::*;
@@ -13,6 +15,7 @@ use usb_device::UsbError;
 /// **Note:** This is a managed interfaces that support HID idle, [`UsbHidClass::tick()`] must be called every 1ms.
 pub struct BootKeyboard<'a, B: UsbBus> {
     interface: ManagedIdleInterface<'a, B, BootKeyboardReport, InBytes8, OutBytes8>,
+    leds: ReceivedReport<KeyboardLedsReport, 1>,
 }
 
 impl<B> BootKeyboard<'_, B>
@@ -26,15 +29,69 @@ where
         self.interface.write_report(&BootKeyboardReport::new(keys))
     }
 
+    /// Write the pressed keys, retrying while the IN endpoint is busy, see
+    /// [`ManagedIdleInterface::write_report_retrying`]
+    pub fn write_report_retrying<K: IntoIterator<Item = Keyboard>>(
+        &mut self,
+        keys: K,
+    ) -> Result<(), UsbHidError> {
+        self.interface
+            .write_report_retrying(&BootKeyboardReport::new(keys))
+    }
+
+    /// Write a report for each queued key event, see [`KeyEvents`]
+    pub fn write_events<const N: usize>(
+        &mut self,
+        events: &mut KeyEvents<N>,
+    ) -> Result<(), UsbHidError> {
+        events.write_reports(|keys| self.write_report(keys.iter()))
+    }
+
+    /// Write the phantom state report, see [`BootKeyboardReport::rollover_error`]
+    pub fn write_rollover_error<K: IntoIterator<Item = Keyboard>>(
+        &mut self,
+        keys: K,
+    ) -> Result<(), UsbHidError> {
+        self.interface
+            .write_report(&BootKeyboardReport::rollover_error(keys))
+    }
+
+    /// Read the LED report last received from the host, returns [`UsbError::WouldBlock`] if none
+    /// has been received since the last read, see [`OutputReports::read_output_report`]
     pub fn read_report(&mut self) -> usb_device::Result<KeyboardLedsReport> {
-        let data = &mut [0];
-        match self.interface.read_report(data) {
-            Err(e) => Err(e),
-            Ok(_) => match KeyboardLedsReport::unpack(data) {
-                Ok(r) => Ok(r),
-                Err(_) => Err(UsbError::ParseError),
-            },
-        }
+        self.read_output_report()
+    }
+
+    /// The LED state last set by the host
+    #[must_use]
+    pub fn leds(&self) -> KeyboardLedsReport {
+        self.output_report()
+    }
+
+    /// The LED state, if the host has changed it since the last call
+    ///
+    /// LED reports are received by [`UsbHidClass::tick`], so this only needs calling when the
+    /// LEDs are updated rather than on every poll of the USB device.
+    pub fn leds_changed(&mut self) -> usb_device::Result<Option<KeyboardLedsReport>> {
+        self.output_report_changed()
+    }
+}
+
+impl<B: UsbBus> OutputReports for BootKeyboard<'_, B> {
+    type Report = KeyboardLedsReport;
+
+    fn read_output_report(&mut self) -> usb_device::Result<KeyboardLedsReport> {
+        self.leds.receive(|data| self.interface.read_report(data))?;
+        self.leds.read()
+    }
+
+    fn output_report(&self) -> KeyboardLedsReport {
+        self.leds.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<KeyboardLedsReport>> {
+        self.leds.receive(|data| self.interface.read_report(data))?;
+        Ok(self.leds.changed())
     }
 }
 
@@ -50,11 +107,23 @@ where
 
     fn reset(&mut self) {
         self.interface.reset();
+        self.leds = ReceivedReport::default();
     }
 
     fn tick(&mut self) -> Result<(), UsbHidError> {
+        if let Err(e) = self.leds.receive(|data| self.interface.read_report(data)) {
+            warn!("Failed to receive LED report: {:?}", e);
+        }
         self.interface.tick()
     }
+
+    fn get_input_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        self.interface.get_input_report(report_id, data)
+    }
+
+    fn endpoint_halt_cleared(&mut self) {
+        self.interface.endpoint_halt_cleared();
+    }
 }
 
 pub struct BootKeyboardConfig<'a> {
@@ -62,7 +131,6 @@ pub struct BootKeyboardConfig<'a> {
 }
 
 impl Default for BootKeyboardConfig<'_> {
-    #[must_use]
     fn default() -> Self {
         Self::new(ManagedIdleInterfaceConfig::new(
             unwrap!(unwrap!(unwrap!(unwrap!(InterfaceBuilder::new(
@@ -87,15 +155,60 @@ impl<'a> BootKeyboardConfig<'a> {
     ) -> Self {
         Self { interface }
     }
+
+    /// Change the polling intervals of the key report IN endpoint and the LED report OUT
+    /// endpoint
+    pub fn with_poll_intervals(
+        mut self,
+        in_interval: MillisDurationU32,
+        out_interval: MillisDurationU32,
+    ) -> BuilderResult<Self> {
+        self.interface = self
+            .interface
+            .with_poll_intervals(in_interval, out_interval)?;
+        Ok(self)
+    }
+
+    /// Remove the LED report OUT endpoint, LED reports are received over the control pipe
+    ///
+    /// Some hosts are unreliable at setting the LEDs of a keyboard without an OUT endpoint.
+    #[must_use]
+    pub fn without_out_endpoint(mut self) -> Self {
+        self.interface = self.interface.without_out_endpoint();
+        self
+    }
+
+    /// Describe the endpoints for a high speed bus, see [`InterfaceBuilder::high_speed`]
+    #[must_use]
+    pub fn high_speed(mut self) -> Self {
+        self.interface = self.interface.high_speed();
+        self
+    }
+
+    /// Change the interface name, see [`InterfaceConfig::with_description`]
+    #[must_use]
+    pub fn with_description(mut self, description: &'a str) -> Self {
+        self.interface = self.interface.with_description(description);
+        self
+    }
+
+    /// Declare the keyboard's layout country, such as [`CountryCode::JapanKatakana`] for a JIS
+    /// keyboard
+    #[must_use]
+    pub fn with_country_code(mut self, country_code: CountryCode) -> Self {
+        self.interface = self.interface.with_country_code(country_code);
+        self
+    }
 }
 
 impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for BootKeyboardConfig<'a> {
     type Allocated = BootKeyboard<'a, B>;
 
-    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        Self::Allocated {
-            interface: self.interface.allocate(usb_alloc),
-        }
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            leds: ReceivedReport::default(),
+        })
     }
 }
 
@@ -116,6 +229,122 @@ pub struct KeyboardLedsReport {
     pub kana: bool,
 }
 
+impl OutputReport for KeyboardLedsReport {
+    fn parse(data: &[u8]) -> usb_device::Result<Self> {
+        Self::unpack_from_slice(data).map_err(|_| UsbError::ParseError)
+    }
+}
+
+/// Key press or release, such as from scanning a key matrix
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyEvent {
+    Pressed(Keyboard),
+    Released(Keyboard),
+}
+
+/// Set of held keys
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct HeldKeys([u8; 32]);
+
+impl HeldKeys {
+    /// Press or release a key
+    pub fn apply(&mut self, event: KeyEvent) {
+        match event {
+            KeyEvent::Pressed(k) => self.0[Self::byte(k)] |= Self::bit(k),
+            KeyEvent::Released(k) => self.0[Self::byte(k)] &= !Self::bit(k),
+        }
+    }
+
+    #[must_use]
+    pub fn is_held(&self, key: Keyboard) -> bool {
+        self.0[Self::byte(key)] & Self::bit(key) != 0
+    }
+
+    /// The held keys, in usage ID order
+    pub fn iter(&self) -> impl Iterator<Item = Keyboard> + '_ {
+        (0..=u8::MAX)
+            .filter(|&id| self.0[usize::from(id / 8)] & (1 << (id % 8)) != 0)
+            .map(Keyboard::from)
+    }
+
+    fn byte(key: Keyboard) -> usize {
+        usize::from(u8::from(key) / 8)
+    }
+
+    fn bit(key: Keyboard) -> u8 {
+        1 << (u8::from(key) % 8)
+    }
+}
+
+/// Queue of key events, reported one at a time, with the held keys maintained from them
+///
+/// For key matrix scanners that produce press and release events rather than the full set of
+/// held keys. Events are pushed as they're scanned and written with the keyboard's
+/// `write_events`, such as [`BootKeyboard::write_events`]. Each event is reported separately, so
+/// a key pressed and released between two reports isn't lost.
+#[derive(Default)]
+pub struct KeyEvents<const N: usize> {
+    queue: Deque<KeyEvent, N>,
+    held: HeldKeys,
+}
+
+impl<const N: usize> KeyEvents<N> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            queue: Deque::new(),
+            held: HeldKeys::default(),
+        }
+    }
+
+    /// Queue an event, returns it back if the queue is full
+    pub fn push(&mut self, event: KeyEvent) -> Result<(), KeyEvent> {
+        self.queue.push_back(event)
+    }
+
+    /// The held keys, after the events reported so far
+    #[must_use]
+    pub fn held(&self) -> &HeldKeys {
+        &self.held
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Report each queued event with `write_report`, stopping at the first event it fails to
+    /// report, such as with [`UsbHidError::WouldBlock`], to retry later
+    fn write_reports(
+        &mut self,
+        mut write_report: impl FnMut(&HeldKeys) -> Result<(), UsbHidError>,
+    ) -> Result<(), UsbHidError> {
+        while let Some(&event) = self.queue.front() {
+            let mut held = self.held;
+            held.apply(event);
+            match write_report(&held) {
+                Ok(()) | Err(UsbHidError::Duplicate) => {
+                    self.held = held;
+                    self.queue.pop_front();
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The modifiers in `keys` and [`Keyboard::ErrorRollOver`]
+fn rollover_error_keys<K: IntoIterator<Item = Keyboard>>(
+    keys: K,
+) -> impl Iterator<Item = Keyboard> {
+    keys.into_iter()
+        .filter(|k| (Keyboard::LeftControl..=Keyboard::RightGUI).contains(k))
+        .chain(core::iter::once(Keyboard::ErrorRollOver))
+}
+
 /// Report implementing the HID boot keyboard specification
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
@@ -142,6 +371,13 @@ pub struct BootKeyboardReport {
 }
 
 impl BootKeyboardReport {
+    /// Report of the phantom state, with [`Keyboard::ErrorRollOver`] in place of the keys, for
+    /// when the key matrix detects ghosting or more keys are pressed than can be reported. The
+    /// modifiers in `keys` are still reported.
+    pub fn rollover_error<K: IntoIterator<Item = Keyboard>>(keys: K) -> Self {
+        Self::new(rollover_error_keys(keys))
+    }
+
     pub fn new<K: IntoIterator<Item = Keyboard>>(keys: K) -> Self {
         let mut report = Self::default();
 
@@ -326,6 +562,13 @@ pub struct NKROBootKeyboardReport {
 }
 
 impl NKROBootKeyboardReport {
+    /// Report of the phantom state, with [`Keyboard::ErrorRollOver`] in place of the keys, for
+    /// when the key matrix detects ghosting or more keys are pressed than can be reported. The
+    /// modifiers in `keys` are still reported.
+    pub fn rollover_error<K: IntoIterator<Item = Keyboard>>(keys: K) -> Self {
+        Self::new(rollover_error_keys(keys))
+    }
+
     pub fn new<K: IntoIterator<Item = Keyboard>>(keys: K) -> Self {
         let mut report = Self::default();
 
@@ -398,6 +641,7 @@ impl NKROBootKeyboardReport {
 /// **Note:** This is a managed interfaces that support HID idle, [`UsbHidClass::tick()`] must be called every 1ms/ at 1kHz.
 pub struct NKROBootKeyboard<'a, B: UsbBus> {
     interface: ManagedIdleInterface<'a, B, NKROBootKeyboardReport, InBytes32, OutBytes8>,
+    leds: ReceivedReport<KeyboardLedsReport, 1>,
 }
 
 impl<B> NKROBootKeyboard<'_, B>
@@ -412,15 +656,51 @@ where
             .write_report(&NKROBootKeyboardReport::new(keys))
     }
 
+    /// Write the pressed keys, retrying while the IN endpoint is busy, see
+    /// [`ManagedIdleInterface::write_report_retrying`]
+    pub fn write_report_retrying<K: IntoIterator<Item = Keyboard>>(
+        &mut self,
+        keys: K,
+    ) -> Result<(), UsbHidError> {
+        self.interface
+            .write_report_retrying(&NKROBootKeyboardReport::new(keys))
+    }
+
+    /// Write a report for each queued key event, see [`KeyEvents`]
+    pub fn write_events<const N: usize>(
+        &mut self,
+        events: &mut KeyEvents<N>,
+    ) -> Result<(), UsbHidError> {
+        events.write_reports(|keys| self.write_report(keys.iter()))
+    }
+
+    /// Write the phantom state report, see [`NKROBootKeyboardReport::rollover_error`]
+    pub fn write_rollover_error<K: IntoIterator<Item = Keyboard>>(
+        &mut self,
+        keys: K,
+    ) -> Result<(), UsbHidError> {
+        self.interface
+            .write_report(&NKROBootKeyboardReport::rollover_error(keys))
+    }
+
+    /// Read the LED report last received from the host, returns [`UsbError::WouldBlock`] if none
+    /// has been received since the last read, see [`OutputReports::read_output_report`]
     pub fn read_report(&mut self) -> usb_device::Result<KeyboardLedsReport> {
-        let data = &mut [0];
-        match self.interface.read_report(data) {
-            Err(e) => Err(e),
-            Ok(_) => match KeyboardLedsReport::unpack(data) {
-                Ok(r) => Ok(r),
-                Err(_) => Err(UsbError::ParseError),
-            },
-        }
+        self.read_output_report()
+    }
+
+    /// The LED state last set by the host
+    #[must_use]
+    pub fn leds(&self) -> KeyboardLedsReport {
+        self.output_report()
+    }
+
+    /// The LED state, if the host has changed it since the last call
+    ///
+    /// LED reports are received by [`UsbHidClass::tick`], so this only needs calling when the
+    /// LEDs are updated rather than on every poll of the USB device.
+    pub fn leds_changed(&mut self) -> usb_device::Result<Option<KeyboardLedsReport>> {
+        self.output_report_changed()
     }
 }
 
@@ -429,14 +709,14 @@ pub struct NKROBootKeyboardConfig<'a> {
 }
 
 impl Default for NKROBootKeyboardConfig<'_> {
-    #[must_use]
     fn default() -> Self {
         Self::new(ManagedIdleInterfaceConfig::new(
-            unwrap!(unwrap!(unwrap!(unwrap!(InterfaceBuilder::new(
+            unwrap!(unwrap!(unwrap!(unwrap!(unwrap!(InterfaceBuilder::new(
                 NKRO_BOOT_KEYBOARD_REPORT_DESCRIPTOR
             ))
             .description("NKRO Keyboard")
             .boot_device(InterfaceProtocol::Keyboard)
+            .boot_report_descriptor(BOOT_KEYBOARD_REPORT_DESCRIPTOR))
             .idle_default(500.millis()))
             .in_endpoint(10.millis()))
             .with_out_endpoint(100.millis()))
@@ -452,15 +732,78 @@ impl<'a> NKROBootKeyboardConfig<'a> {
     ) -> Self {
         Self { interface }
     }
+
+    /// Change the polling intervals of the key report IN endpoint and the LED report OUT
+    /// endpoint
+    pub fn with_poll_intervals(
+        mut self,
+        in_interval: MillisDurationU32,
+        out_interval: MillisDurationU32,
+    ) -> BuilderResult<Self> {
+        self.interface = self
+            .interface
+            .with_poll_intervals(in_interval, out_interval)?;
+        Ok(self)
+    }
+
+    /// Remove the LED report OUT endpoint, LED reports are received over the control pipe
+    ///
+    /// Some hosts are unreliable at setting the LEDs of a keyboard without an OUT endpoint.
+    #[must_use]
+    pub fn without_out_endpoint(mut self) -> Self {
+        self.interface = self.interface.without_out_endpoint();
+        self
+    }
+
+    /// Describe the endpoints for a high speed bus, see [`InterfaceBuilder::high_speed`]
+    #[must_use]
+    pub fn high_speed(mut self) -> Self {
+        self.interface = self.interface.high_speed();
+        self
+    }
+
+    /// Change the interface name, see [`InterfaceConfig::with_description`]
+    #[must_use]
+    pub fn with_description(mut self, description: &'a str) -> Self {
+        self.interface = self.interface.with_description(description);
+        self
+    }
+
+    /// Declare the keyboard's layout country, such as [`CountryCode::JapanKatakana`] for a JIS
+    /// keyboard
+    #[must_use]
+    pub fn with_country_code(mut self, country_code: CountryCode) -> Self {
+        self.interface = self.interface.with_country_code(country_code);
+        self
+    }
 }
 
 impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for NKROBootKeyboardConfig<'a> {
     type Allocated = NKROBootKeyboard<'a, B>;
 
-    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        Self::Allocated {
-            interface: self.interface.allocate(usb_alloc),
-        }
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            leds: ReceivedReport::default(),
+        })
+    }
+}
+
+impl<B: UsbBus> OutputReports for NKROBootKeyboard<'_, B> {
+    type Report = KeyboardLedsReport;
+
+    fn read_output_report(&mut self) -> usb_device::Result<KeyboardLedsReport> {
+        self.leds.receive(|data| self.interface.read_report(data))?;
+        self.leds.read()
+    }
+
+    fn output_report(&self) -> KeyboardLedsReport {
+        self.leds.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<KeyboardLedsReport>> {
+        self.leds.receive(|data| self.interface.read_report(data))?;
+        Ok(self.leds.changed())
     }
 }
 
@@ -476,11 +819,23 @@ where
 
     fn reset(&mut self) {
         self.interface.reset();
+        self.leds = ReceivedReport::default();
     }
 
     fn tick(&mut self) -> core::result::Result<(), UsbHidError> {
+        if let Err(e) = self.leds.receive(|data| self.interface.read_report(data)) {
+            warn!("Failed to receive LED report: {:?}", e);
+        }
         self.interface.tick()
     }
+
+    fn get_input_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        self.interface.get_input_report(report_id, data)
+    }
+
+    fn endpoint_halt_cleared(&mut self) {
+        self.interface.endpoint_halt_cleared();
+    }
 }
 
 /// HID Keyboard report descriptor implementing an NKRO keyboard as a bitmap.
@@ -524,6 +879,325 @@ pub const NKRO_COMPACT_KEYBOARD_REPORT_DESCRIPTOR: &[u8] = &[
     0xc0                            // End Collection
 ];
 
+/// Report implementing an NKRO keyboard as a bitmap, see [`NKRO_COMPACT_KEYBOARD_REPORT_DESCRIPTOR`]
+///
+/// N.B. This is not compatible with the HID boot specification, see
+/// [`NKROKeyboardReport::boot_report`]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "18")]
+pub struct NKROKeyboardReport {
+    #[packed_field(bits = "0")]
+    pub right_gui: bool,
+    #[packed_field(bits = "1")]
+    pub right_alt: bool,
+    #[packed_field(bits = "2")]
+    pub right_shift: bool,
+    #[packed_field(bits = "3")]
+    pub right_ctrl: bool,
+    #[packed_field(bits = "4")]
+    pub left_gui: bool,
+    #[packed_field(bits = "5")]
+    pub left_alt: bool,
+    #[packed_field(bits = "6")]
+    pub left_shift: bool,
+    #[packed_field(bits = "7")]
+    pub left_ctrl: bool,
+    //The usb lsb/lsb0 expected ordering isn't compatible with pact structs
+    #[packed_field(bytes = "1..18", element_size_bits = "8")]
+    pub nkro_keys: [u8; 17],
+}
+
+impl NKROKeyboardReport {
+    /// Report of the phantom state, with [`Keyboard::ErrorRollOver`] in place of the keys, for
+    /// when the key matrix detects ghosting or more keys are pressed than can be reported. The
+    /// modifiers in `keys` are still reported.
+    pub fn rollover_error<K: IntoIterator<Item = Keyboard>>(keys: K) -> Self {
+        Self::new(rollover_error_keys(keys))
+    }
+
+    pub fn new<K: IntoIterator<Item = Keyboard>>(keys: K) -> Self {
+        let mut report = Self::default();
+
+        for k in keys {
+            match k {
+                Keyboard::LeftControl => {
+                    report.left_ctrl = true;
+                }
+                Keyboard::LeftShift => {
+                    report.left_shift = true;
+                }
+                Keyboard::LeftAlt => {
+                    report.left_alt = true;
+                }
+                Keyboard::LeftGUI => {
+                    report.left_gui = true;
+                }
+                Keyboard::RightControl => {
+                    report.right_ctrl = true;
+                }
+                Keyboard::RightShift => {
+                    report.right_shift = true;
+                }
+                Keyboard::RightAlt => {
+                    report.right_alt = true;
+                }
+                Keyboard::RightGUI => {
+                    report.right_gui = true;
+                }
+                Keyboard::NoEventIndicated => {}
+                _ => {
+                    if report.nkro_keys.len() * 8 > u8::from(k).into() {
+                        let byte = u8::from(k) / 8;
+                        let bit = u8::from(k) % 8;
+                        report.nkro_keys[usize::from(byte)] |= 1 << bit;
+                    }
+                }
+            }
+        }
+        report
+    }
+
+    /// The report in the boot keyboard format, with [`Keyboard::ErrorRollOver`] reported if more
+    /// than six keys are pressed
+    #[must_use]
+    pub fn boot_report(&self) -> BootKeyboardReport {
+        let keys = (0u8..).zip(self.nkro_keys).flat_map(|(byte, bits)| {
+            (0..8)
+                .filter(move |bit| bits & (1 << bit) != 0)
+                .map(move |bit| Keyboard::from(byte * 8 + bit))
+        });
+        BootKeyboardReport {
+            right_gui: self.right_gui,
+            right_alt: self.right_alt,
+            right_shift: self.right_shift,
+            right_ctrl: self.right_ctrl,
+            left_gui: self.left_gui,
+            left_alt: self.left_alt,
+            left_shift: self.left_shift,
+            left_ctrl: self.left_ctrl,
+            ..BootKeyboardReport::new(keys)
+        }
+    }
+}
+
+fn nkro_keyboard_boot_report(report: &NKROKeyboardReport) -> BootInputReport {
+    report
+        .boot_report()
+        .pack()
+        .ok()
+        .and_then(|data| BootInputReport::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Interface implementing an NKRO keyboard as a bitmap
+///
+/// While the host has selected the boot protocol, such as in a BIOS, the keys are sent as
+/// [`BootKeyboardReport`]s. Some reduced functionality USB hosts never select the boot protocol
+/// and need [`NKROBootKeyboard`], which sends its boot compatible report in either protocol.
+///
+/// **Note:** This is a managed interfaces that support HID idle, [`UsbHidClass::tick()`] must be called every 1ms/ at 1kHz.
+pub struct NKROKeyboard<'a, B: UsbBus> {
+    interface: ManagedIdleInterface<'a, B, NKROKeyboardReport, InBytes32, OutBytes8>,
+    leds: ReceivedReport<KeyboardLedsReport, 1>,
+}
+
+impl<B> NKROKeyboard<'_, B>
+where
+    B: UsbBus,
+{
+    pub fn write_report<K: IntoIterator<Item = Keyboard>>(
+        &mut self,
+        keys: K,
+    ) -> Result<(), UsbHidError> {
+        self.interface.write_report(&NKROKeyboardReport::new(keys))
+    }
+
+    /// Write the pressed keys, retrying while the IN endpoint is busy, see
+    /// [`ManagedIdleInterface::write_report_retrying`]
+    pub fn write_report_retrying<K: IntoIterator<Item = Keyboard>>(
+        &mut self,
+        keys: K,
+    ) -> Result<(), UsbHidError> {
+        self.interface
+            .write_report_retrying(&NKROKeyboardReport::new(keys))
+    }
+
+    /// Write a report for each queued key event, see [`KeyEvents`]
+    pub fn write_events<const N: usize>(
+        &mut self,
+        events: &mut KeyEvents<N>,
+    ) -> Result<(), UsbHidError> {
+        events.write_reports(|keys| self.write_report(keys.iter()))
+    }
+
+    /// Write the phantom state report, see [`NKROKeyboardReport::rollover_error`]
+    pub fn write_rollover_error<K: IntoIterator<Item = Keyboard>>(
+        &mut self,
+        keys: K,
+    ) -> Result<(), UsbHidError> {
+        self.interface
+            .write_report(&NKROKeyboardReport::rollover_error(keys))
+    }
+
+    /// Read the LED report last received from the host, returns [`UsbError::WouldBlock`] if none
+    /// has been received since the last read, see [`OutputReports::read_output_report`]
+    pub fn read_report(&mut self) -> usb_device::Result<KeyboardLedsReport> {
+        self.read_output_report()
+    }
+
+    /// The LED state last set by the host
+    #[must_use]
+    pub fn leds(&self) -> KeyboardLedsReport {
+        self.output_report()
+    }
+
+    /// The LED state, if the host has changed it since the last call
+    ///
+    /// LED reports are received by [`UsbHidClass::tick`], so this only needs calling when the
+    /// LEDs are updated rather than on every poll of the USB device.
+    pub fn leds_changed(&mut self) -> usb_device::Result<Option<KeyboardLedsReport>> {
+        self.output_report_changed()
+    }
+}
+
+pub struct NKROKeyboardConfig<'a> {
+    interface: ManagedIdleInterfaceConfig<'a, NKROKeyboardReport, InBytes32, OutBytes8>,
+}
+
+impl Default for NKROKeyboardConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            ManagedIdleInterfaceConfig::new(
+                unwrap!(unwrap!(unwrap!(unwrap!(unwrap!(InterfaceBuilder::new(
+                    NKRO_COMPACT_KEYBOARD_REPORT_DESCRIPTOR
+                ))
+                .description("NKRO Keyboard")
+                .boot_device(InterfaceProtocol::Keyboard)
+                .boot_report_descriptor(BOOT_KEYBOARD_REPORT_DESCRIPTOR))
+                .idle_default(500.millis()))
+                .in_endpoint(10.millis()))
+                .with_out_endpoint(100.millis()))
+                .build(),
+            )
+            .with_boot_report(nkro_keyboard_boot_report),
+        )
+    }
+}
+
+impl<'a> NKROKeyboardConfig<'a> {
+    #[must_use]
+    pub fn new(
+        interface: ManagedIdleInterfaceConfig<'a, NKROKeyboardReport, InBytes32, OutBytes8>,
+    ) -> Self {
+        Self { interface }
+    }
+
+    /// Change the polling intervals of the key report IN endpoint and the LED report OUT
+    /// endpoint
+    pub fn with_poll_intervals(
+        mut self,
+        in_interval: MillisDurationU32,
+        out_interval: MillisDurationU32,
+    ) -> BuilderResult<Self> {
+        self.interface = self
+            .interface
+            .with_poll_intervals(in_interval, out_interval)?;
+        Ok(self)
+    }
+
+    /// Remove the LED report OUT endpoint, LED reports are received over the control pipe
+    ///
+    /// Some hosts are unreliable at setting the LEDs of a keyboard without an OUT endpoint.
+    #[must_use]
+    pub fn without_out_endpoint(mut self) -> Self {
+        self.interface = self.interface.without_out_endpoint();
+        self
+    }
+
+    /// Describe the endpoints for a high speed bus, see [`InterfaceBuilder::high_speed`]
+    #[must_use]
+    pub fn high_speed(mut self) -> Self {
+        self.interface = self.interface.high_speed();
+        self
+    }
+
+    /// Change the interface name, see [`InterfaceConfig::with_description`]
+    #[must_use]
+    pub fn with_description(mut self, description: &'a str) -> Self {
+        self.interface = self.interface.with_description(description);
+        self
+    }
+
+    /// Declare the keyboard's layout country, such as [`CountryCode::JapanKatakana`] for a JIS
+    /// keyboard
+    #[must_use]
+    pub fn with_country_code(mut self, country_code: CountryCode) -> Self {
+        self.interface = self.interface.with_country_code(country_code);
+        self
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for NKROKeyboardConfig<'a> {
+    type Allocated = NKROKeyboard<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            leds: ReceivedReport::default(),
+        })
+    }
+}
+
+impl<B: UsbBus> OutputReports for NKROKeyboard<'_, B> {
+    type Report = KeyboardLedsReport;
+
+    fn read_output_report(&mut self) -> usb_device::Result<KeyboardLedsReport> {
+        self.leds.receive(|data| self.interface.read_report(data))?;
+        self.leds.read()
+    }
+
+    fn output_report(&self) -> KeyboardLedsReport {
+        self.leds.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<KeyboardLedsReport>> {
+        self.leds.receive(|data| self.interface.read_report(data))?;
+        Ok(self.leds.changed())
+    }
+}
+
+impl<'a, B> DeviceClass<'a> for NKROKeyboard<'a, B>
+where
+    B: UsbBus,
+{
+    type I = Interface<'a, B, InBytes32, OutBytes8, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        self.interface.interface()
+    }
+
+    fn reset(&mut self) {
+        self.interface.reset();
+        self.leds = ReceivedReport::default();
+    }
+
+    fn tick(&mut self) -> core::result::Result<(), UsbHidError> {
+        if let Err(e) = self.leds.receive(|data| self.interface.read_report(data)) {
+            warn!("Failed to receive LED report: {:?}", e);
+        }
+        self.interface.tick()
+    }
+
+    fn get_input_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        self.interface.get_input_report(report_id, data)
+    }
+
+    fn endpoint_halt_cleared(&mut self) {
+        self.interface.endpoint_halt_cleared();
+    }
+}
+
 #[cfg(test)]
 mod test {
     #![allow(clippy::unwrap_used)]
@@ -531,8 +1205,12 @@ mod test {
 
     use packed_struct::prelude::*;
 
-    use crate::device::keyboard::{BootKeyboardReport, KeyboardLedsReport};
+    use crate::device::keyboard::{
+        BootKeyboardReport, KeyEvent, KeyEvents, KeyboardLedsReport, NKROBootKeyboardReport,
+        NKROKeyboardReport,
+    };
     use crate::page::Keyboard;
+    use crate::UsbHidError;
 
     #[test]
     fn leds_num_lock() {
@@ -664,4 +1342,106 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn nkro_keyboard_report_bitmap() {
+        let bytes = NKROKeyboardReport::new([
+            Keyboard::LeftShift,
+            Keyboard::A,
+            Keyboard::Z,
+            Keyboard::F24,
+            Keyboard::RightAlt,
+        ])
+        .pack()
+        .unwrap();
+
+        let mut expected = [0u8; 18];
+        expected[0] = 0x1_u8 << (u8::from(Keyboard::LeftShift) - u8::from(Keyboard::LeftControl))
+            | 0x1_u8 << (u8::from(Keyboard::RightAlt) - u8::from(Keyboard::LeftControl));
+        for k in [Keyboard::A, Keyboard::Z, Keyboard::F24] {
+            expected[1 + usize::from(u8::from(k) / 8)] |= 1 << (u8::from(k) % 8);
+        }
+
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn nkro_keyboard_report_boot_report() {
+        let report = NKROKeyboardReport::new([Keyboard::LeftShift, Keyboard::Z, Keyboard::A]);
+        assert_eq!(
+            report.boot_report(),
+            BootKeyboardReport::new([Keyboard::LeftShift, Keyboard::A, Keyboard::Z])
+        );
+
+        let report = NKROKeyboardReport::new([
+            Keyboard::A,
+            Keyboard::B,
+            Keyboard::C,
+            Keyboard::D,
+            Keyboard::E,
+            Keyboard::F,
+            Keyboard::G,
+        ]);
+        assert_eq!(report.boot_report().keys, [Keyboard::ErrorRollOver; 6]);
+    }
+
+    #[test]
+    fn rollover_error_reports_keep_modifiers() {
+        let keys = [Keyboard::LeftShift, Keyboard::A, Keyboard::B];
+
+        let report = BootKeyboardReport::rollover_error(keys);
+        assert!(report.left_shift);
+        assert_eq!(report.keys, [Keyboard::ErrorRollOver; 6]);
+
+        let report = NKROBootKeyboardReport::rollover_error(keys);
+        assert!(report.left_shift);
+        assert_eq!(report.boot_keys, [Keyboard::ErrorRollOver; 6]);
+        assert_eq!(report.nkro_keys[0], 1 << u8::from(Keyboard::ErrorRollOver));
+        assert!(report.nkro_keys[1..].iter().all(|&b| b == 0));
+
+        let report = NKROKeyboardReport::rollover_error(keys);
+        assert!(report.left_shift);
+        assert_eq!(report.nkro_keys[0], 1 << u8::from(Keyboard::ErrorRollOver));
+        assert!(report.nkro_keys[1..].iter().all(|&b| b == 0));
+        assert_eq!(
+            report.boot_report(),
+            BootKeyboardReport::rollover_error(keys)
+        );
+    }
+
+    #[test]
+    fn key_events_are_reported_one_at_a_time() {
+        let mut events = KeyEvents::<4>::new();
+        events.push(KeyEvent::Pressed(Keyboard::A)).unwrap();
+        events.push(KeyEvent::Pressed(Keyboard::LeftShift)).unwrap();
+        events.push(KeyEvent::Released(Keyboard::A)).unwrap();
+
+        // The second report would block, so it and the events after it stay queued
+        let mut reports = std::vec::Vec::new();
+        let result = events.write_reports(|keys| {
+            if reports.len() == 1 {
+                return Err(UsbHidError::WouldBlock);
+            }
+            reports.push(keys.iter().collect::<std::vec::Vec<_>>());
+            Ok(())
+        });
+        assert!(matches!(result, Err(UsbHidError::WouldBlock)));
+        assert!(events.held().is_held(Keyboard::A));
+
+        events
+            .write_reports(|keys| {
+                reports.push(keys.iter().collect());
+                Ok(())
+            })
+            .unwrap();
+        assert!(events.is_empty());
+        assert_eq!(
+            reports,
+            [
+                std::vec![Keyboard::A],
+                std::vec![Keyboard::A, Keyboard::LeftShift],
+                std::vec![Keyboard::LeftShift],
+            ]
+        );
+    }
 }
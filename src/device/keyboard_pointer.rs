@@ -0,0 +1,257 @@
+//!HID keyboard with pointing stick - boot keyboard and wheel mouse in a single interface
+//!
+//! For keyboards with an integrated pointing stick or trackpad on microcontrollers without the
+//! endpoints for separate keyboard and mouse interfaces
+use crate::device::keyboard::{BootKeyboardReport, KeyboardLedsReport};
+use crate::device::mouse::WheelMouseReport;
+use crate::interface::IdleManager;
+use crate::page::Keyboard;
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the keyboard input and LED output reports
+pub const KEYBOARD_POINTER_KEYBOARD_REPORT_ID: u8 = 0x01;
+/// Report ID of the pointer input report
+pub const KEYBOARD_POINTER_POINTER_REPORT_ID: u8 = 0x02;
+
+/// Keyboard with pointer report descriptor - a [`BootKeyboardReport`] and [`KeyboardLedsReport`]
+/// and a [`WheelMouseReport`], under separate report IDs
+#[rustfmt::skip]
+pub const KEYBOARD_POINTER_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x06,        // Usage (Keyboard),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x08,        //   Report Count (8),
+    0x05, 0x07,        //   Usage Page (Key Codes),
+    0x19, 0xE0,        //   Usage Minimum (224),
+    0x29, 0xE7,        //   Usage Maximum (231),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute), ;Modifier byte
+    0x95, 0x01,        //   Report Count (1),
+    0x75, 0x08,        //   Report Size (8),
+    0x81, 0x01,        //   Input (Constant), ;Reserved byte
+    0x95, 0x05,        //   Report Count (5),
+    0x75, 0x01,        //   Report Size (1),
+    0x05, 0x08,        //   Usage Page (LEDs),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x05,        //   Usage Maximum (5),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute), ;LED report
+    0x95, 0x01,        //   Report Count (1),
+    0x75, 0x03,        //   Report Size (3),
+    0x91, 0x01,        //   Output (Constant), ;LED report padding
+    0x95, 0x06,        //   Report Count (6),
+    0x75, 0x08,        //   Report Size (8),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x05, 0x07,        //   Usage Page (Key Codes),
+    0x19, 0x00,        //   Usage Minimum (0),
+    0x2A, 0xFF, 0x00,  //   Usage Maximum (255),
+    0x81, 0x00,        //   Input (Data, Array),
+    0xC0,              // End Collection
+
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x02,        // Usage (Mouse),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x02,        //   Report ID (2),
+    0x09, 0x01,        //   Usage (Pointer),
+    0xA1, 0x00,        //   Collection (Physical),
+    0x95, 0x08,        //     Report Count (8),
+    0x75, 0x01,        //     Report Size (1),
+    0x05, 0x09,        //     Usage Page (Buttons),
+    0x19, 0x01,        //     Usage Minimum (1),
+    0x29, 0x08,        //     Usage Maximum (8),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x03,        //     Report Count (3),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x09, 0x38,        //     Usage (Wheel),
+    0x15, 0x81,        //     Logical Minimum (-127),
+    0x25, 0x7F,        //     Logical Maximum (127),
+    0x81, 0x06,        //     Input (Data, Variable, Relative),
+    0x05, 0x0C,        //     Usage Page (Consumer),
+    0x0A, 0x38, 0x02,  //     Usage (AC Pan),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x06,        //     Input (Data, Variable, Relative),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+/// Keyboard with pointing stick
+///
+/// The interface is a boot keyboard. While the host has selected the boot protocol, such as in
+/// a BIOS, keyboard reports are sent without a report ID and pointer reports are dropped.
+///
+/// The keyboard report is repeated at the idle rate the host set for the keyboard report ID, or
+/// else for all reports, which requires [`DeviceClass::tick`] calling every 1ms.
+pub struct KeyboardPointer<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes16, OutBytes8, Reports8>,
+    keyboard: IdleManager<BootKeyboardReport>,
+    leds: ReceivedReport<KeyboardLedsReport, 1>,
+}
+
+impl<B: UsbBus> KeyboardPointer<'_, B> {
+    /// Write the pressed keys, returns [`UsbHidError::Duplicate`] if they haven't changed
+    pub fn write_keyboard<K: IntoIterator<Item = Keyboard>>(
+        &mut self,
+        keys: K,
+    ) -> Result<(), UsbHidError> {
+        let report = BootKeyboardReport::new(keys);
+        if self.keyboard.is_duplicate(&report) {
+            return Err(UsbHidError::Duplicate);
+        }
+        self.send_keyboard(report)
+    }
+
+    fn send_keyboard(&mut self, report: BootKeyboardReport) -> Result<(), UsbHidError> {
+        let mut data = [0; 9];
+        data[0] = KEYBOARD_POINTER_KEYBOARD_REPORT_ID;
+        report.pack_to_slice(&mut data[1..]).map_err(|_| {
+            error!("Error packing BootKeyboardReport");
+            UsbHidError::SerializationError
+        })?;
+        let data = match self.interface.protocol() {
+            HidProtocol::Boot => &data[1..],
+            HidProtocol::Report => &data[..],
+        };
+
+        self.interface
+            .write_report(data)
+            .map_err(UsbHidError::from)?;
+        self.keyboard.report_written(report);
+        Ok(())
+    }
+
+    /// Write a pointer report, dropped while the host has selected the boot protocol
+    pub fn write_pointer(&mut self, report: &WheelMouseReport) -> Result<(), UsbHidError> {
+        if self.interface.protocol() == HidProtocol::Boot {
+            return Ok(());
+        }
+
+        let mut data = [0; 6];
+        data[0] = KEYBOARD_POINTER_POINTER_REPORT_ID;
+        report.pack_to_slice(&mut data[1..]).map_err(|_| {
+            error!("Error packing WheelMouseReport");
+            UsbHidError::SerializationError
+        })?;
+        self.interface
+            .write_report(&data)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+
+    /// Read the LED report last received from the host, returns [`UsbError::WouldBlock`] if none
+    /// has been received since the last read, see [`OutputReports::read_output_report`]
+    pub fn read_leds(&mut self) -> usb_device::Result<KeyboardLedsReport> {
+        self.read_output_report()
+    }
+
+    /// Receive the LED reports, with the keyboard report ID in report protocol
+    fn receive_leds(&mut self) -> usb_device::Result<()> {
+        let interface = &mut self.interface;
+        self.leds.receive(|leds| {
+            let mut data = [0; 2];
+            let len = interface.read_report(&mut data)?;
+            leds[0] = match (interface.protocol(), &data[..len]) {
+                (HidProtocol::Boot, [leds] | [_, leds])
+                | (HidProtocol::Report, [KEYBOARD_POINTER_KEYBOARD_REPORT_ID, leds]) => *leds,
+                _ => return Err(UsbError::ParseError),
+            };
+            Ok(1)
+        })
+    }
+}
+
+impl<B: UsbBus> OutputReports for KeyboardPointer<'_, B> {
+    type Report = KeyboardLedsReport;
+
+    fn read_output_report(&mut self) -> usb_device::Result<KeyboardLedsReport> {
+        self.receive_leds()?;
+        self.leds.read()
+    }
+
+    fn output_report(&self) -> KeyboardLedsReport {
+        self.leds.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<KeyboardLedsReport>> {
+        self.receive_leds()?;
+        Ok(self.leds.changed())
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for KeyboardPointer<'a, B> {
+    type I = Interface<'a, B, InBytes16, OutBytes8, Reports8>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.keyboard = IdleManager::default();
+        self.leds = ReceivedReport::default();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        if let Err(e) = self.receive_leds() {
+            warn!("Failed to receive LED report: {:?}", e);
+        }
+        let idle = match self.interface.protocol() {
+            HidProtocol::Boot => self.interface.global_idle(),
+            HidProtocol::Report => self.interface.idle(KEYBOARD_POINTER_KEYBOARD_REPORT_ID),
+        };
+        let due = self.keyboard.tick(idle);
+        match self.keyboard.last_report() {
+            Some(report) if due => self.send_keyboard(report),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub struct KeyboardPointerConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes16, OutBytes8, Reports8>,
+}
+
+impl Default for KeyboardPointerConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                KEYBOARD_POINTER_REPORT_DESCRIPTOR
+            ))
+            .boot_device(InterfaceProtocol::Keyboard)
+            .description("Keyboard")
+            .in_endpoint(10.millis()))
+            .with_out_endpoint(100.millis()))
+            .build(),
+        )
+    }
+}
+
+impl<'a> KeyboardPointerConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes16, OutBytes8, Reports8>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for KeyboardPointerConfig<'a> {
+    type Allocated = KeyboardPointer<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            keyboard: IdleManager::default(),
+            leds: ReceivedReport::default(),
+        })
+    }
+}
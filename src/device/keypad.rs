@@ -0,0 +1,237 @@
+//!HID numeric keypad - boot compatible keypad with a compact bitmap report
+use crate::device::keyboard::{BootKeyboardReport, KeyboardLedsReport};
+use crate::page::Keyboard;
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+
+/// Keys of a [`NumericKeypad`], in report bit order
+pub const KEYPAD_KEYS: [Keyboard; 20] = [
+    Keyboard::KeypadNumLockAndClear,
+    Keyboard::KeypadDivide,
+    Keyboard::KeypadMultiply,
+    Keyboard::KeypadSubtract,
+    Keyboard::KeypadAdd,
+    Keyboard::KeypadEnter,
+    Keyboard::Keypad1,
+    Keyboard::Keypad2,
+    Keyboard::Keypad3,
+    Keyboard::Keypad4,
+    Keyboard::Keypad5,
+    Keyboard::Keypad6,
+    Keyboard::Keypad7,
+    Keyboard::Keypad8,
+    Keyboard::Keypad9,
+    Keyboard::Keypad0,
+    Keyboard::KeypadDot,
+    Keyboard::KeypadEqual,
+    Keyboard::KeypadComma,
+    Keyboard::DeleteBackspace,
+];
+
+/// Numeric keypad report descriptor - a three byte bitmap of [`KEYPAD_KEYS`] and a Num Lock LED
+/// output
+#[rustfmt::skip]
+pub const NUMERIC_KEYPAD_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x07,        // Usage (Keypad),
+    0xA1, 0x01,        // Collection (Application),
+    0x05, 0x07,        //   Usage Page (Key Codes),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x19, 0x53,        //   Usage Minimum (Keypad Num Lock and Clear),
+    0x29, 0x63,        //   Usage Maximum (Keypad . and Delete),
+    0x95, 0x11,        //   Report Count (17),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x09, 0x67,        //   Usage (Keypad =),
+    0x09, 0x85,        //   Usage (Keypad Comma),
+    0x09, 0x2A,        //   Usage (Delete Backspace),
+    0x95, 0x03,        //   Report Count (3),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x75, 0x04,        //   Report Size (4),
+    0x95, 0x01,        //   Report Count (1),
+    0x81, 0x03,        //   Input (Constant, Variable, Absolute),
+    0x05, 0x08,        //   Usage Page (LEDs),
+    0x09, 0x01,        //   Usage (Num Lock),
+    0x75, 0x01,        //   Report Size (1),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x75, 0x07,        //   Report Size (7),
+    0x91, 0x03,        //   Output (Constant, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Numeric keypad report, bit `n` of `keys` is [`KEYPAD_KEYS`]`[n]`
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct NumericKeypadReport {
+    pub keys: u32,
+}
+
+impl NumericKeypadReport {
+    /// Report of the pressed `keys`, keys not in [`KEYPAD_KEYS`] are ignored
+    pub fn new<K: IntoIterator<Item = Keyboard>>(keys: K) -> Self {
+        let keys = keys
+            .into_iter()
+            .filter_map(|k| KEYPAD_KEYS.iter().position(|p| *p == k))
+            .fold(0, |keys, i| keys | (1 << i));
+        Self { keys }
+    }
+
+    /// Pressed keys, in [`KEYPAD_KEYS`] order
+    pub fn pressed(&self) -> impl Iterator<Item = Keyboard> + '_ {
+        KEYPAD_KEYS
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.keys & (1 << i) != 0)
+            .map(|(_, k)| *k)
+    }
+}
+
+/// Numeric keypad
+///
+/// The interface is a boot keyboard. While the host has selected the boot protocol, such as in
+/// a BIOS, the pressed keys are sent as a [`BootKeyboardReport`] instead of the compact report.
+/// Duplicate reports are suppressed, returning [`UsbHidError::Duplicate`].
+pub struct NumericKeypad<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutBytes8, ReportSingle>,
+    report: NumericKeypadReport,
+    leds: ReceivedReport<KeyboardLedsReport, 1>,
+}
+
+impl<B: UsbBus> NumericKeypad<'_, B> {
+    pub fn write_report<K: IntoIterator<Item = Keyboard>>(
+        &mut self,
+        keys: K,
+    ) -> Result<(), UsbHidError> {
+        let report = NumericKeypadReport::new(keys);
+        if report == self.report {
+            return Err(UsbHidError::Duplicate);
+        }
+
+        match self.interface.protocol() {
+            HidProtocol::Boot => {
+                let data = BootKeyboardReport::new(report.pressed())
+                    .pack()
+                    .map_err(|_| {
+                        error!("Error packing BootKeyboardReport");
+                        UsbHidError::SerializationError
+                    })?;
+                self.interface.write_report(&data)
+            }
+            HidProtocol::Report => self.interface.write_report(&report.keys.to_le_bytes()[..3]),
+        }
+        .map_err(UsbHidError::from)?;
+
+        self.report = report;
+        Ok(())
+    }
+
+    /// Read the LED report last received from the host, returns
+    /// [`UsbError::WouldBlock`](usb_device::UsbError::WouldBlock) if none has been received since
+    /// the last read, see [`OutputReports::read_output_report`]
+    pub fn read_report(&mut self) -> usb_device::Result<KeyboardLedsReport> {
+        self.read_output_report()
+    }
+}
+
+impl<B: UsbBus> OutputReports for NumericKeypad<'_, B> {
+    type Report = KeyboardLedsReport;
+
+    fn read_output_report(&mut self) -> usb_device::Result<KeyboardLedsReport> {
+        self.leds.receive(|data| self.interface.read_report(data))?;
+        self.leds.read()
+    }
+
+    fn output_report(&self) -> KeyboardLedsReport {
+        self.leds.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<KeyboardLedsReport>> {
+        self.leds.receive(|data| self.interface.read_report(data))?;
+        Ok(self.leds.changed())
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for NumericKeypad<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutBytes8, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.report = NumericKeypadReport::default();
+        self.leds = ReceivedReport::default();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        if let Err(e) = self.leds.receive(|data| self.interface.read_report(data)) {
+            warn!("Failed to receive LED report: {:?}", e);
+        }
+        Ok(())
+    }
+}
+
+pub struct NumericKeypadConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutBytes8, ReportSingle>,
+}
+
+impl Default for NumericKeypadConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(
+                unwrap!(InterfaceBuilder::new(NUMERIC_KEYPAD_REPORT_DESCRIPTOR))
+                    .boot_device(InterfaceProtocol::Keyboard)
+                    .description("Keypad")
+                    .in_endpoint(10.millis())
+            )
+            .with_out_endpoint(100.millis()))
+            .build(),
+        )
+    }
+}
+
+impl<'a> NumericKeypadConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes8, OutBytes8, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for NumericKeypadConfig<'a> {
+    type Allocated = NumericKeypad<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            report: NumericKeypadReport::default(),
+            leds: ReceivedReport::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::keypad::NumericKeypadReport;
+    use crate::page::Keyboard;
+
+    #[test]
+    fn keypad_report_bitmap() {
+        let report = NumericKeypadReport::new([
+            Keyboard::KeypadNumLockAndClear,
+            Keyboard::KeypadDot,
+            Keyboard::A,
+            Keyboard::DeleteBackspace,
+        ]);
+
+        assert_eq!(report.keys.to_le_bytes(), [0x01, 0x00, 0x09, 0x00]);
+        assert!(report.pressed().eq([
+            Keyboard::KeypadNumLockAndClear,
+            Keyboard::KeypadDot,
+            Keyboard::DeleteBackspace
+        ]));
+    }
+}
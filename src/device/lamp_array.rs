@@ -0,0 +1,620 @@
+//!HID `LampArray`, Lighting and Illumination usage page (0x59)
+//!
+//! Supports Windows Dynamic Lighting. See
+//! [Dynamic Lighting devices](<https://learn.microsoft.com/en-us/windows-hardware/design/component-guidelines/dynamic-lighting-devices>)
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use heapless::Deque;
+use num_enum::IntoPrimitive;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the `LampArrayAttributesReport` feature report
+pub const LAMP_ARRAY_ATTRIBUTES_REPORT_ID: u8 = 0x01;
+/// Report ID of the `LampAttributesRequestReport` feature report
+pub const LAMP_ATTRIBUTES_REQUEST_REPORT_ID: u8 = 0x02;
+/// Report ID of the `LampAttributesResponseReport` feature report
+pub const LAMP_ATTRIBUTES_RESPONSE_REPORT_ID: u8 = 0x03;
+/// Report ID of the `LampMultiUpdateReport` feature report
+pub const LAMP_MULTI_UPDATE_REPORT_ID: u8 = 0x04;
+/// Report ID of the `LampRangeUpdateReport` feature report
+pub const LAMP_RANGE_UPDATE_REPORT_ID: u8 = 0x05;
+/// Report ID of the `LampArrayControlReport` feature report
+pub const LAMP_ARRAY_CONTROL_REPORT_ID: u8 = 0x06;
+
+/// Maximum number of lamps in a `LampMultiUpdateReport`
+pub const LAMP_MULTI_UPDATE_LAMP_COUNT: usize = 8;
+
+/// `LampArray` report descriptor, all reports are feature reports
+#[rustfmt::skip]
+pub const LAMP_ARRAY_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x59,        // Usage Page (Lighting And Illumination),
+    0x09, 0x01,        // Usage (LampArray),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x09, 0x02,        //   Usage (LampArrayAttributesReport),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x03,        //     Usage (LampCount),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x03,        //     Feature (Constant, Variable, Absolute),
+    0x09, 0x04,        //     Usage (BoundingBoxWidthInMicrometers),
+    0x09, 0x05,        //     Usage (BoundingBoxHeightInMicrometers),
+    0x09, 0x06,        //     Usage (BoundingBoxDepthInMicrometers),
+    0x09, 0x07,        //     Usage (LampArrayKind),
+    0x09, 0x08,        //     Usage (MinUpdateIntervalInMicroseconds),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F, //     Logical Maximum (2147483647),
+    0x75, 0x20,        //     Report Size (32),
+    0x95, 0x05,        //     Report Count (5),
+    0xB1, 0x03,        //     Feature (Constant, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x85, 0x02,        //   Report ID (2),
+    0x09, 0x20,        //   Usage (LampAttributesRequestReport),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x21,        //     Usage (LampId),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x85, 0x03,        //   Report ID (3),
+    0x09, 0x22,        //   Usage (LampAttributesResponseReport),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x21,        //     Usage (LampId),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x23,        //     Usage (PositionXInMicrometers),
+    0x09, 0x24,        //     Usage (PositionYInMicrometers),
+    0x09, 0x25,        //     Usage (PositionZInMicrometers),
+    0x09, 0x27,        //     Usage (UpdateLatencyInMicroseconds),
+    0x09, 0x26,        //     Usage (LampPurposes),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F, //     Logical Maximum (2147483647),
+    0x75, 0x20,        //     Report Size (32),
+    0x95, 0x05,        //     Report Count (5),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x28,        //     Usage (RedLevelCount),
+    0x09, 0x29,        //     Usage (GreenLevelCount),
+    0x09, 0x2A,        //     Usage (BlueLevelCount),
+    0x09, 0x2B,        //     Usage (IntensityLevelCount),
+    0x09, 0x2C,        //     Usage (IsProgrammable),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x05,        //     Report Count (5),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x2D,        //     Usage (InputBinding),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x85, 0x04,        //   Report ID (4),
+    0x09, 0x50,        //   Usage (LampMultiUpdateReport),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x03,        //     Usage (LampCount),
+    0x09, 0x55,        //     Usage (LampUpdateFlags),
+    0x25, 0x08,        //     Logical Maximum (8),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x02,        //     Report Count (2),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x21,        //     Usage (LampId),
+    0x09, 0x21,        //     Usage (LampId),
+    0x09, 0x21,        //     Usage (LampId),
+    0x09, 0x21,        //     Usage (LampId),
+    0x09, 0x21,        //     Usage (LampId),
+    0x09, 0x21,        //     Usage (LampId),
+    0x09, 0x21,        //     Usage (LampId),
+    0x09, 0x21,        //     Usage (LampId),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x08,        //     Report Count (8),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x51,        //     Usage (RedUpdateChannel),
+    0x09, 0x52,        //     Usage (GreenUpdateChannel),
+    0x09, 0x53,        //     Usage (BlueUpdateChannel),
+    0x09, 0x54,        //     Usage (IntensityUpdateChannel),
+    0x09, 0x51,        //     Usage (RedUpdateChannel),
+    0x09, 0x52,        //     Usage (GreenUpdateChannel),
+    0x09, 0x53,        //     Usage (BlueUpdateChannel),
+    0x09, 0x54,        //     Usage (IntensityUpdateChannel),
+    0x09, 0x51,        //     Usage (RedUpdateChannel),
+    0x09, 0x52,        //     Usage (GreenUpdateChannel),
+    0x09, 0x53,        //     Usage (BlueUpdateChannel),
+    0x09, 0x54,        //     Usage (IntensityUpdateChannel),
+    0x09, 0x51,        //     Usage (RedUpdateChannel),
+    0x09, 0x52,        //     Usage (GreenUpdateChannel),
+    0x09, 0x53,        //     Usage (BlueUpdateChannel),
+    0x09, 0x54,        //     Usage (IntensityUpdateChannel),
+    0x09, 0x51,        //     Usage (RedUpdateChannel),
+    0x09, 0x52,        //     Usage (GreenUpdateChannel),
+    0x09, 0x53,        //     Usage (BlueUpdateChannel),
+    0x09, 0x54,        //     Usage (IntensityUpdateChannel),
+    0x09, 0x51,        //     Usage (RedUpdateChannel),
+    0x09, 0x52,        //     Usage (GreenUpdateChannel),
+    0x09, 0x53,        //     Usage (BlueUpdateChannel),
+    0x09, 0x54,        //     Usage (IntensityUpdateChannel),
+    0x09, 0x51,        //     Usage (RedUpdateChannel),
+    0x09, 0x52,        //     Usage (GreenUpdateChannel),
+    0x09, 0x53,        //     Usage (BlueUpdateChannel),
+    0x09, 0x54,        //     Usage (IntensityUpdateChannel),
+    0x09, 0x51,        //     Usage (RedUpdateChannel),
+    0x09, 0x52,        //     Usage (GreenUpdateChannel),
+    0x09, 0x53,        //     Usage (BlueUpdateChannel),
+    0x09, 0x54,        //     Usage (IntensityUpdateChannel),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x20,        //     Report Count (32),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x85, 0x05,        //   Report ID (5),
+    0x09, 0x60,        //   Usage (LampRangeUpdateReport),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x55,        //     Usage (LampUpdateFlags),
+    0x25, 0x08,        //     Logical Maximum (8),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x61,        //     Usage (LampIdStart),
+    0x09, 0x62,        //     Usage (LampIdEnd),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x51,        //     Usage (RedUpdateChannel),
+    0x09, 0x52,        //     Usage (GreenUpdateChannel),
+    0x09, 0x53,        //     Usage (BlueUpdateChannel),
+    0x09, 0x54,        //     Usage (IntensityUpdateChannel),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x04,        //     Report Count (4),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x85, 0x06,        //   Report ID (6),
+    0x09, 0x70,        //   Usage (LampArrayControlReport),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x71,        //     Usage (AutonomousMode),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, IntoPrimitive)]
+#[repr(u32)]
+pub enum LampArrayKind {
+    #[default]
+    Undefined = 0x00,
+    Keyboard = 0x01,
+    Mouse = 0x02,
+    GameController = 0x03,
+    Peripheral = 0x04,
+    Scene = 0x05,
+    Notification = 0x06,
+    Chassis = 0x07,
+    Wearable = 0x08,
+    Furniture = 0x09,
+    Art = 0x0A,
+}
+
+/// Attributes of the whole `LampArray`
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct LampArrayAttributes {
+    pub bounding_box_width: u32,
+    pub bounding_box_height: u32,
+    pub bounding_box_depth: u32,
+    pub kind: LampArrayKind,
+    pub min_update_interval: u32,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "4")]
+pub struct LampPurposes {
+    #[packed_field(bits = "7")]
+    pub control: bool,
+    #[packed_field(bits = "6")]
+    pub accent: bool,
+    #[packed_field(bits = "5")]
+    pub branding: bool,
+    #[packed_field(bits = "4")]
+    pub status: bool,
+    #[packed_field(bits = "3")]
+    pub illumination: bool,
+    #[packed_field(bits = "2")]
+    pub presentation: bool,
+}
+
+/// Attributes of a single lamp, positions are in micrometers from the top left corner of the
+/// bounding box
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct LampAttributes {
+    pub position_x: u32,
+    pub position_y: u32,
+    pub position_z: u32,
+    pub update_latency: u32,
+    pub purposes: LampPurposes,
+    pub red_level_count: u8,
+    pub green_level_count: u8,
+    pub blue_level_count: u8,
+    pub intensity_level_count: u8,
+    pub is_programmable: bool,
+    /// Keyboard usage of the key the lamp is associated with, 0 for none
+    pub input_binding: u16,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct LampColor {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub intensity: u8,
+}
+
+/// Colors for up to [`LAMP_MULTI_UPDATE_LAMP_COUNT`] individual lamps
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct LampMultiUpdate {
+    lamp_count: u8,
+    lamp_ids: [u16; LAMP_MULTI_UPDATE_LAMP_COUNT],
+    colors: [LampColor; LAMP_MULTI_UPDATE_LAMP_COUNT],
+    /// Set on the last update of a frame, lamps may be updated once this is received
+    pub update_complete: bool,
+}
+
+impl LampMultiUpdate {
+    /// Lamp IDs and their new colors
+    pub fn iter(&self) -> impl Iterator<Item = (u16, LampColor)> + '_ {
+        self.lamp_ids
+            .iter()
+            .copied()
+            .zip(self.colors.iter().copied())
+            .take(usize::from(self.lamp_count))
+    }
+}
+
+/// Color for a contiguous range of lamps
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct LampRangeUpdate {
+    pub lamp_id_start: u16,
+    /// Inclusive
+    pub lamp_id_end: u16,
+    pub color: LampColor,
+    /// Set on the last update of a frame, lamps may be updated once this is received
+    pub update_complete: bool,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LampArrayUpdate {
+    Multi(LampMultiUpdate),
+    Range(LampRangeUpdate),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "22")]
+struct LampArrayAttributesReport {
+    #[packed_field(bytes = "0..=1")]
+    lamp_count: u16,
+    #[packed_field(bytes = "2..=5")]
+    bounding_box_width: u32,
+    #[packed_field(bytes = "6..=9")]
+    bounding_box_height: u32,
+    #[packed_field(bytes = "10..=13")]
+    bounding_box_depth: u32,
+    #[packed_field(bytes = "14..=17")]
+    kind: u32,
+    #[packed_field(bytes = "18..=21")]
+    min_update_interval: u32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "29")]
+struct LampAttributesResponseReport {
+    #[packed_field(bytes = "0..=1")]
+    lamp_id: u16,
+    #[packed_field(bytes = "2..=5")]
+    position_x: u32,
+    #[packed_field(bytes = "6..=9")]
+    position_y: u32,
+    #[packed_field(bytes = "10..=13")]
+    position_z: u32,
+    #[packed_field(bytes = "14..=17")]
+    update_latency: u32,
+    #[packed_field(bytes = "18..=21")]
+    purposes: LampPurposes,
+    #[packed_field(bytes = "22")]
+    red_level_count: u8,
+    #[packed_field(bytes = "23")]
+    green_level_count: u8,
+    #[packed_field(bytes = "24")]
+    blue_level_count: u8,
+    #[packed_field(bytes = "25")]
+    intensity_level_count: u8,
+    #[packed_field(bytes = "26")]
+    is_programmable: u8,
+    #[packed_field(bytes = "27..=28")]
+    input_binding: u16,
+}
+
+fn color(data: &[u8]) -> LampColor {
+    LampColor {
+        red: data[0],
+        green: data[1],
+        blue: data[2],
+        intensity: data[3],
+    }
+}
+
+/// `LampArray` device
+///
+/// The host reads the array and lamp attributes, supplied in [`LampArrayConfig`], via feature
+/// reports. Lamp color updates received from the host are queued and read with
+/// [`LampArray::read_report`]. While [`LampArray::autonomous_mode`] is set the device should
+/// control its own lighting and ignore updates.
+pub struct LampArray<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    attributes: LampArrayAttributes,
+    lamps: &'a [LampAttributes],
+    lamp_id: u16,
+    autonomous_mode: bool,
+    updates: Deque<LampArrayUpdate, 8>,
+}
+
+impl<B: UsbBus> LampArray<'_, B> {
+    /// Read a lamp color update received from the host
+    pub fn read_report(&mut self) -> usb_device::Result<LampArrayUpdate> {
+        self.updates.pop_front().ok_or(UsbError::WouldBlock)
+    }
+
+    #[must_use]
+    pub fn autonomous_mode(&self) -> bool {
+        self.autonomous_mode
+    }
+
+    fn lamp_count(&self) -> u16 {
+        u16::try_from(self.lamps.len()).unwrap_or(u16::MAX)
+    }
+
+    fn queue(&mut self, update: LampArrayUpdate) -> usb_device::Result<()> {
+        self.updates.push_back(update).map_err(|_| {
+            warn!("LampArray update queue full");
+            UsbError::WouldBlock
+        })
+    }
+
+    fn multi_update(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        if data.len() < 2 + LAMP_MULTI_UPDATE_LAMP_COUNT * 6 {
+            return Err(UsbError::ParseError);
+        }
+        let lamp_count = data[0];
+        if usize::from(lamp_count) > LAMP_MULTI_UPDATE_LAMP_COUNT {
+            return Err(UsbError::ParseError);
+        }
+
+        let mut update = LampMultiUpdate {
+            lamp_count,
+            update_complete: data[1] & 0x01 != 0,
+            ..Default::default()
+        };
+        let (ids, colors) = data[2..].split_at(LAMP_MULTI_UPDATE_LAMP_COUNT * 2);
+        for (i, (id, c)) in ids.chunks_exact(2).zip(colors.chunks_exact(4)).enumerate() {
+            update.lamp_ids[i] = u16::from_le_bytes([id[0], id[1]]);
+            update.colors[i] = color(c);
+        }
+
+        if update.iter().any(|(id, _)| id >= self.lamp_count()) {
+            return Err(UsbError::ParseError);
+        }
+        self.queue(LampArrayUpdate::Multi(update))
+    }
+
+    fn range_update(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        if data.len() < 9 {
+            return Err(UsbError::ParseError);
+        }
+        let update = LampRangeUpdate {
+            update_complete: data[0] & 0x01 != 0,
+            lamp_id_start: u16::from_le_bytes([data[1], data[2]]),
+            lamp_id_end: u16::from_le_bytes([data[3], data[4]]),
+            color: color(&data[5..9]),
+        };
+
+        if update.lamp_id_start > update.lamp_id_end || update.lamp_id_end >= self.lamp_count() {
+            return Err(UsbError::ParseError);
+        }
+        self.queue(LampArrayUpdate::Range(update))
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for LampArray<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.lamp_id = 0;
+        self.autonomous_mode = true;
+        self.updates.clear();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        let len = match report_id {
+            LAMP_ARRAY_ATTRIBUTES_REPORT_ID => {
+                let report = LampArrayAttributesReport {
+                    lamp_count: self.lamp_count(),
+                    bounding_box_width: self.attributes.bounding_box_width,
+                    bounding_box_height: self.attributes.bounding_box_height,
+                    bounding_box_depth: self.attributes.bounding_box_depth,
+                    kind: self.attributes.kind.into(),
+                    min_update_interval: self.attributes.min_update_interval,
+                };
+                let out = data.get_mut(1..=22).ok_or(UsbError::BufferOverflow)?;
+                report
+                    .pack_to_slice(out)
+                    .map_err(|_| UsbError::ParseError)?;
+                22
+            }
+            LAMP_ATTRIBUTES_RESPONSE_REPORT_ID => {
+                let lamp = self
+                    .lamps
+                    .get(usize::from(self.lamp_id))
+                    .ok_or(UsbError::InvalidState)?;
+                let report = LampAttributesResponseReport {
+                    lamp_id: self.lamp_id,
+                    position_x: lamp.position_x,
+                    position_y: lamp.position_y,
+                    position_z: lamp.position_z,
+                    update_latency: lamp.update_latency,
+                    purposes: lamp.purposes,
+                    red_level_count: lamp.red_level_count,
+                    green_level_count: lamp.green_level_count,
+                    blue_level_count: lamp.blue_level_count,
+                    intensity_level_count: lamp.intensity_level_count,
+                    is_programmable: lamp.is_programmable.into(),
+                    input_binding: lamp.input_binding,
+                };
+                let out = data.get_mut(1..=29).ok_or(UsbError::BufferOverflow)?;
+                report
+                    .pack_to_slice(out)
+                    .map_err(|_| UsbError::ParseError)?;
+
+                // Successive responses return successive lamps
+                self.lamp_id = (self.lamp_id + 1) % self.lamp_count().max(1);
+                29
+            }
+            _ => return Err(UsbError::Unsupported),
+        };
+        data[0] = report_id;
+        Ok(len + 1)
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        let Some((&id, report)) = data.split_first() else {
+            return Err(UsbError::ParseError);
+        };
+        if id != report_id {
+            return Err(UsbError::ParseError);
+        }
+
+        match report_id {
+            LAMP_ATTRIBUTES_REQUEST_REPORT_ID => {
+                let lamp_id = report
+                    .get(..2)
+                    .map(|r| u16::from_le_bytes([r[0], r[1]]))
+                    .ok_or(UsbError::ParseError)?;
+                if lamp_id >= self.lamp_count() {
+                    return Err(UsbError::ParseError);
+                }
+                self.lamp_id = lamp_id;
+                Ok(())
+            }
+            LAMP_MULTI_UPDATE_REPORT_ID => self.multi_update(report),
+            LAMP_RANGE_UPDATE_REPORT_ID => self.range_update(report),
+            LAMP_ARRAY_CONTROL_REPORT_ID => {
+                self.autonomous_mode = *report.first().ok_or(UsbError::ParseError)? != 0;
+                info!("Set LampArray autonomous mode {}", self.autonomous_mode);
+                Ok(())
+            }
+            _ => Err(UsbError::Unsupported),
+        }
+    }
+}
+
+pub struct LampArrayConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+    attributes: LampArrayAttributes,
+    lamps: &'a [LampAttributes],
+}
+
+impl<'a> LampArrayConfig<'a> {
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+        attributes: LampArrayAttributes,
+        lamps: &'a [LampAttributes],
+    ) -> Self {
+        Self {
+            interface,
+            attributes,
+            lamps,
+        }
+    }
+
+    /// Configuration with the default interface settings
+    #[must_use]
+    pub fn from_lamps(attributes: LampArrayAttributes, lamps: &'a [LampAttributes]) -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                LAMP_ARRAY_REPORT_DESCRIPTOR
+            ))
+            .description("LampArray")
+            .in_endpoint(100.millis()))
+            .without_out_endpoint()
+            .build(),
+            attributes,
+            lamps,
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for LampArrayConfig<'a> {
+    type Allocated = LampArray<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            attributes: self.attributes,
+            lamps: self.lamps,
+            lamp_id: 0,
+            autonomous_mode: true,
+            updates: Deque::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::lamp_array::{LampAttributesResponseReport, LampPurposes};
+
+    #[test]
+    fn lamp_attributes_response_layout() {
+        let bytes = LampAttributesResponseReport {
+            lamp_id: 0x0102,
+            position_x: 0x0A0B_0C0D,
+            purposes: LampPurposes {
+                control: true,
+                status: true,
+                ..Default::default()
+            },
+            red_level_count: 0xFF,
+            is_programmable: 1,
+            input_binding: 0x0004,
+            ..Default::default()
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(bytes[0..6], [0x02, 0x01, 0x0D, 0x0C, 0x0B, 0x0A]);
+        assert_eq!(bytes[18..23], [0x09, 0x00, 0x00, 0x00, 0xFF]);
+        assert_eq!(bytes[26..29], [0x01, 0x04, 0x00]);
+    }
+}
@@ -0,0 +1,181 @@
+//!HID light gun - absolute aiming pointer
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+
+/// Light gun report descriptor
+///
+/// An absolute pointer, as [`ABSOLUTE_MOUSE_REPORT_DESCRIPTOR`](crate::device::mouse::ABSOLUTE_MOUSE_REPORT_DESCRIPTOR),
+/// with the trigger as button 1, seven auxiliary buttons and a Digitizer In Range flag that is
+/// cleared while the gun is aimed off screen
+#[rustfmt::skip]
+pub const LIGHT_GUN_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x02,        // Usage (Mouse),
+    0xA1, 0x01,        // Collection (Application),
+    0x09, 0x01,        //   Usage (Pointer),
+    0xA1, 0x00,        //   Collection (Physical),
+
+    0x05, 0x09,        //     Usage Page (Buttons),
+    0x19, 0x01,        //     Usage Minimum (1),
+    0x29, 0x08,        //     Usage Maximum (8),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x95, 0x08,        //     Report Count (8),
+    0x75, 0x01,        //     Report Size (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+
+    0x05, 0x0D,        //     Usage Page (Digitizer),
+    0x09, 0x32,        //     Usage (In Range),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x07,        //     Report Count (7),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x35, 0x00,        //     Physical Minimum (0),
+    0x46, 0xFF, 0x7F,  //     Physical Maximum (32767),
+    0x95, 0x02,        //     Report Count (2),
+    0x75, 0x10,        //     Report Size (16),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "6")]
+pub struct LightGunReport {
+    #[packed_field(bits = "7")]
+    pub trigger: bool,
+    /// Auxiliary buttons, bit 0 is button 2
+    #[packed_field(bits = "0..=6")]
+    pub buttons: u8,
+    /// The gun is aimed at the screen
+    #[packed_field(bits = "15")]
+    pub in_range: bool,
+    /// Absolute X position, 0-32767
+    #[packed_field(bytes = "2..=3")]
+    pub x: u16,
+    /// Absolute Y position, 0-32767
+    #[packed_field(bytes = "4..=5")]
+    pub y: u16,
+}
+
+/// Light gun
+///
+/// While a report is out of range [`LightGun::write_report`] holds the pointer at the last
+/// position aimed at on screen, rather than the position in the report, so that off screen
+/// shots such as reloads don't move the cursor. Duplicate reports are suppressed, returning
+/// [`UsbHidError::Duplicate`].
+pub struct LightGun<'a, B: UsbBus> {
+    interface: ManagedIdleInterface<'a, B, LightGunReport, InBytes8, OutNone>,
+    last_position: (u16, u16),
+}
+
+impl<B: UsbBus> LightGun<'_, B> {
+    pub fn write_report(&mut self, report: &LightGunReport) -> Result<(), UsbHidError> {
+        let report = if report.in_range {
+            self.last_position = (report.x, report.y);
+            *report
+        } else {
+            LightGunReport {
+                x: self.last_position.0,
+                y: self.last_position.1,
+                ..*report
+            }
+        };
+        self.interface.write_report(&report)
+    }
+}
+
+pub struct LightGunConfig<'a> {
+    interface: ManagedIdleInterfaceConfig<'a, LightGunReport, InBytes8, OutNone>,
+}
+
+impl<'a> LightGunConfig<'a> {
+    #[must_use]
+    pub fn new(
+        interface: ManagedIdleInterfaceConfig<'a, LightGunReport, InBytes8, OutNone>,
+    ) -> Self {
+        Self { interface }
+    }
+}
+
+impl Default for LightGunConfig<'_> {
+    fn default() -> Self {
+        Self::new(ManagedIdleInterfaceConfig::new(
+            unwrap!(unwrap!(InterfaceBuilder::new(LIGHT_GUN_REPORT_DESCRIPTOR))
+                .description("Light Gun")
+                .in_endpoint(1.millis()))
+            .without_out_endpoint()
+            .build(),
+        ))
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for LightGunConfig<'a> {
+    type Allocated = LightGun<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(LightGun {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            last_position: (0, 0),
+        })
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for LightGun<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        self.interface.interface()
+    }
+
+    fn reset(&mut self) {
+        self.interface.reset();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        self.interface.tick()
+    }
+
+    fn get_input_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        self.interface.get_input_report(report_id, data)
+    }
+
+    fn endpoint_halt_cleared(&mut self) {
+        self.interface.endpoint_halt_cleared();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::light_gun::LightGunReport;
+
+    #[test]
+    fn light_gun_report_layout() {
+        let bytes = LightGunReport {
+            trigger: true,
+            buttons: 0x41,
+            in_range: true,
+            x: 0x1234,
+            y: 0x7FFF,
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(bytes, [0x83, 0x01, 0x34, 0x12, 0xFF, 0x7F]);
+    }
+}
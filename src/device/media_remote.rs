@@ -0,0 +1,171 @@
+//!HID media remote control - consumer control and system control in a single interface
+use crate::interface::{AutoRelease, PendingReports};
+use crate::page::{Consumer, Desktop};
+use crate::usb_class::prelude::*;
+use fugit::{ExtU32, MillisDurationU32};
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the consumer control input report
+pub const MEDIA_REMOTE_CONSUMER_REPORT_ID: u8 = 0x01;
+/// Report ID of the system control input report
+pub const MEDIA_REMOTE_SYSTEM_REPORT_ID: u8 = 0x02;
+
+///Media remote report descriptor - a `u16` Consumer usage code and a `u8` Generic Desktop system
+/// control usage code, under separate report IDs
+#[rustfmt::skip]
+pub const MEDIA_REMOTE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0C,        // Usage Page (Consumer),
+    0x09, 0x01,        // Usage (Consumer Control),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x75, 0x10,        //   Report Size (16),
+    0x95, 0x01,        //   Report Count (1),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0x14, 0x05,  //   Logical Maximum (0x0514),
+    0x19, 0x00,        //   Usage Minimum (0),
+    0x2A, 0x14, 0x05,  //   Usage Maximum (0x0514),
+    0x81, 0x00,        //   Input (Data, Array, Absolute),
+    0xC0,              // End Collection
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x80,        // Usage (System Control),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x02,        //   Report ID (2),
+    0x19, 0x81,        //   Usage Minimum (System Power Down),
+    0x29, 0x83,        //   Usage Maximum (System Wake Up),
+    0x15, 0x81,        //   Logical Minimum (0x81),
+    0x25, 0x83,        //   Logical Maximum (0x83),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x01,        //   Report Count (1),
+    0x81, 0x00,        //   Input (Data, Array, Absolute),
+    0xC0,              // End Collection
+];
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct MediaRemoteReport {
+    /// [`Consumer::Unassigned`] for no usage
+    pub consumer: Consumer,
+    /// [`Desktop::SystemPowerDown`], [`Desktop::SystemSleep`], [`Desktop::SystemWakeUp`] or
+    /// [`Desktop::Undefined`] for no usage
+    pub system: Desktop,
+}
+
+/// Media remote control
+///
+/// Usages written with [`MediaRemote::write_report`] are held until the same report has not
+/// been written for the configured hold time, then released automatically by
+/// [`DeviceClass::tick`], which must be called every 1ms. Repeated writes of a held usage, such
+/// as IR repeat codes, extend the hold without sending duplicate reports.
+pub struct MediaRemote<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    report: MediaRemoteReport,
+    pending: PendingReports<2>,
+    release: AutoRelease,
+}
+
+impl<B: UsbBus> MediaRemote<'_, B> {
+    pub fn write_report(&mut self, report: &MediaRemoteReport) -> Result<(), UsbHidError> {
+        self.set_report(*report);
+        self.release
+            .report_written(*report != MediaRemoteReport::default());
+
+        match self.flush() {
+            Ok(()) | Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(UsbHidError::from(e)),
+        }
+    }
+
+    fn set_report(&mut self, report: MediaRemoteReport) {
+        self.pending.set(0, report.consumer != self.report.consumer);
+        self.pending.set(1, report.system != self.report.system);
+        self.report = report;
+    }
+
+    fn flush(&mut self) -> usb_device::Result<()> {
+        self.pending.flush(|index| {
+            if index == 0 {
+                let [lo, hi] = u16::from(self.report.consumer).to_le_bytes();
+                self.interface
+                    .write_report(&[MEDIA_REMOTE_CONSUMER_REPORT_ID, lo, hi])
+            } else {
+                self.interface
+                    .write_report(&[MEDIA_REMOTE_SYSTEM_REPORT_ID, self.report.system.into()])
+            }
+            .map(|_| ())
+        })
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for MediaRemote<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.report = MediaRemoteReport::default();
+        self.pending.clear();
+        self.release.released();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        // The release is sent by flush, now or once the endpoint is free
+        if self.release.tick() {
+            self.set_report(MediaRemoteReport::default());
+            self.release.released();
+        }
+
+        match self.flush() {
+            Ok(()) | Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(UsbHidError::from(e)),
+        }
+    }
+}
+
+pub struct MediaRemoteConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+    hold: MillisDurationU32,
+}
+
+impl Default for MediaRemoteConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(
+                unwrap!(InterfaceBuilder::new(MEDIA_REMOTE_REPORT_DESCRIPTOR))
+                    .description("Media Remote")
+                    .in_endpoint(10.millis())
+            )
+            .without_out_endpoint()
+            .build(),
+            150.millis(),
+        )
+    }
+}
+
+impl<'a> MediaRemoteConfig<'a> {
+    /// `hold` is the time a usage is held after the last write, it should be longer than the
+    /// repeat interval of the remote
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+        hold: MillisDurationU32,
+    ) -> Self {
+        Self { interface, hold }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for MediaRemoteConfig<'a> {
+    type Allocated = MediaRemote<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            report: MediaRemoteReport::default(),
+            pending: PendingReports::default(),
+            release: AutoRelease::new(self.hold.max(1.millis())),
+        })
+    }
+}
@@ -1,33 +1,228 @@
 //! Concrete implementation of Human Interface Devices
 
-use crate::interface::InterfaceClass;
+use crate::interface::{InterfaceClass, InterfaceInfo};
 use crate::UsbHidError;
-use frunk::{HCons, HNil, ToMut};
+use frunk::{HCons, HNil};
 #[allow(clippy::wildcard_imports)]
 use usb_device::class_prelude::*;
 use usb_device::descriptor::lang_id::LangID;
+use usb_device::UsbError;
 
+pub mod ambient_light;
+pub mod barcode;
+pub mod braille;
 pub mod consumer;
+pub mod ctaphid;
+pub mod dfu;
+pub mod digitizer;
+pub mod encoder;
+pub mod eye_tracker;
 pub mod fido;
+pub mod force_feedback;
+pub mod gamepad;
 pub mod joystick;
 pub mod keyboard;
+pub mod keyboard_pointer;
+pub mod keypad;
+pub mod lamp_array;
+pub mod light_gun;
+pub mod media_remote;
 pub mod mouse;
+pub mod msr;
+pub mod power;
+pub mod presence;
+pub mod racing_wheel;
+pub mod radial_controller;
+pub mod raw;
+pub mod scale;
+pub mod sensor;
+pub mod space_mouse;
+pub mod switch;
+pub mod system_control;
+pub mod telephony;
+pub mod throttle;
+pub mod touchpad;
+pub mod wireless_radio;
 
 pub trait DeviceClass<'a> {
     type I: InterfaceClass<'a>;
     fn interface(&mut self) -> &mut Self::I;
     /// Called if the USB Device is reset
     fn reset(&mut self);
-    /// Called every 1ms
+    /// Called once for each millisecond elapsed, see [`crate::usb_class::UsbHidClass::tick`]
     fn tick(&mut self) -> Result<(), UsbHidError>;
+    /// Called on a `GET_REPORT(Input)` request from the host
+    ///
+    /// Write the current input report, including the report ID prefix if the descriptor uses
+    /// report IDs, to `data` and return the number of bytes written. Returning
+    /// [`UsbError::WouldBlock`] answers the request with the next report written to the interface.
+    fn get_input_report(&mut self, _report_id: u8, _data: &mut [u8]) -> usb_device::Result<usize> {
+        Err(UsbError::WouldBlock)
+    }
+    /// Called on a `GET_REPORT(Feature)` request from the host
+    ///
+    /// Write the feature report, including the report ID prefix if the descriptor uses report
+    /// IDs, to `data` and return the number of bytes written
+    fn get_feature_report(
+        &mut self,
+        _report_id: u8,
+        _data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        Err(UsbError::Unsupported)
+    }
+    /// Called on a `GET_REPORT(Feature)` request from the host, before
+    /// [`DeviceClass::get_feature_report`], for feature reports held in static memory
+    ///
+    /// Static feature reports are not limited by the size of the control transfer buffer
+    fn get_static_feature_report(&mut self, _report_id: u8) -> Option<&'static [u8]> {
+        None
+    }
+    /// Called on a `SET_REPORT(Feature)` request from the host
+    fn set_feature_report(&mut self, _report_id: u8, _data: &[u8]) -> usb_device::Result<()> {
+        Err(UsbError::Unsupported)
+    }
+    /// Called when the host selects an alternate setting of the interface with `SET_INTERFACE`
+    fn set_alternate_setting(&mut self, _alternate_setting: u8) {}
+    /// Called when the host clears a halt on one of the interface's endpoints, after the
+    /// interface has dropped the reports queued on the endpoint, such as to resend the current
+    /// state
+    fn endpoint_halt_cleared(&mut self) {}
+    /// Called when the host suspends the bus, such as to stop scanning a key matrix or turn off
+    /// LEDs
+    fn suspend(&mut self) {}
+    /// Called when the host resumes the bus
+    fn resume(&mut self) {}
+}
+
+/// Output report parsed from the data received from the host, such as LED states or vendor
+/// command frames
+pub trait OutputReport: Copy + PartialEq + Default {
+    /// Parse a report from `data`, including the report ID prefix if the descriptor uses report
+    /// IDs
+    fn parse(data: &[u8]) -> usb_device::Result<Self>;
+}
+
+/// Device exposing the output reports received from the host to the application
+///
+/// Reports are received by the device from [`DeviceClass::tick`] and by these methods, so the
+/// application only needs to call them when it acts on the reports rather than on every poll.
+pub trait OutputReports {
+    type Report: OutputReport;
+    /// The report last received, returns [`UsbError::WouldBlock`] if none has been received
+    /// since the last read
+    fn read_output_report(&mut self) -> usb_device::Result<Self::Report>;
+    /// The report last received, or the default report if none has been received
+    fn output_report(&self) -> Self::Report;
+    /// The report, if the host has changed it since the last call
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<Self::Report>>;
+}
+
+/// Last output report received from the host, for implementing [`OutputReports`]
+///
+/// Reports of up to `LEN` bytes are received with [`ReceivedReport::receive`], keeping the last
+/// report and whether it's unread or changed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReceivedReport<R, const LEN: usize> {
+    report: R,
+    /// A report has been received since the last [`ReceivedReport::read`]
+    unread: bool,
+    /// The report has changed since the last [`ReceivedReport::changed`]
+    changed: bool,
 }
 
-pub trait DeviceHList<'a>: ToMut<'a> {
+impl<R: OutputReport, const LEN: usize> ReceivedReport<R, LEN> {
+    /// Receive all pending reports with `read_report`, keeping the last
+    pub fn receive(
+        &mut self,
+        mut read_report: impl FnMut(&mut [u8]) -> usb_device::Result<usize>,
+    ) -> usb_device::Result<()> {
+        while self.receive_next(&mut read_report)? {}
+        Ok(())
+    }
+
+    /// Receive the next pending report with `read_report`, returns whether one was received
+    ///
+    /// For reports that are each acted on, such as data frames or effect commands, which
+    /// [`ReceivedReport::receive`] would drop all but the last of. Later reports are left for
+    /// the next call.
+    pub fn receive_next(
+        &mut self,
+        read_report: impl FnOnce(&mut [u8]) -> usb_device::Result<usize>,
+    ) -> usb_device::Result<bool> {
+        let mut data = [0; LEN];
+        let len = match read_report(&mut data) {
+            Ok(len) => len,
+            Err(UsbError::WouldBlock) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        let report = R::parse(&data[..len])?;
+        self.changed |= report != self.report;
+        self.unread = true;
+        self.report = report;
+        Ok(true)
+    }
+
+    /// See [`OutputReports::read_output_report`]
+    pub fn read(&mut self) -> usb_device::Result<R> {
+        if core::mem::take(&mut self.unread) {
+            Ok(self.report)
+        } else {
+            Err(UsbError::WouldBlock)
+        }
+    }
+
+    /// See [`OutputReports::output_report`]
+    #[must_use]
+    pub fn last(&self) -> R {
+        self.report
+    }
+
+    /// See [`OutputReports::output_report_changed`]
+    pub fn changed(&mut self) -> Option<R> {
+        core::mem::take(&mut self.changed).then_some(self.report)
+    }
+
+    /// Return to the default report, such as on a USB reset, which is a change if the last report
+    /// wasn't the default
+    pub fn reset(&mut self) {
+        self.changed |= self.report != R::default();
+        self.report = R::default();
+        self.unread = false;
+    }
+}
+
+/// List of the devices of a [`UsbHidClass`](crate::usb_class::UsbHidClass), either a frunk
+/// [`HList`](frunk::hlist::HList) built by
+/// [`UsbHidClassBuilder::add_device`](crate::usb_class::UsbHidClassBuilder::add_device) or a
+/// tuple of one to four devices, or an array or [`heapless::Vec`] of devices of the same type,
+/// built by [`UsbHidClassBuilder::devices`](crate::usb_class::UsbHidClassBuilder::devices)
+pub trait DeviceHList<'a> {
     fn get(&mut self, id: u8) -> Option<&mut dyn InterfaceClass<'a>>;
     fn reset(&mut self);
-    fn write_descriptors(&mut self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+    fn endpoint_in_complete(&mut self, addr: EndpointAddress);
+    fn endpoint_out(&mut self, addr: EndpointAddress);
+    fn endpoint_halt_cleared(&mut self, addr: EndpointAddress);
     fn get_string(&mut self, index: StringIndex, lang_id: LangID) -> Option<&'a str>;
     fn tick(&mut self) -> Result<(), UsbHidError>;
+    fn get_input_report(
+        &mut self,
+        id: u8,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize>;
+    fn get_feature_report(
+        &mut self,
+        id: u8,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize>;
+    fn get_static_feature_report(&mut self, id: u8, report_id: u8) -> Option<&'static [u8]>;
+    fn set_feature_report(&mut self, id: u8, report_id: u8, data: &[u8]) -> usb_device::Result<()>;
+    fn set_alt_setting(&mut self, id: u8, alternate_setting: u8) -> bool;
+    fn for_each_info(&mut self, f: &mut dyn FnMut(InterfaceInfo));
+    fn for_each_interface(&mut self, f: &mut dyn FnMut(&mut dyn InterfaceClass<'a>));
+    fn suspend(&mut self);
+    fn resume(&mut self);
 }
 
 impl<'a> DeviceHList<'a> for HNil {
@@ -37,9 +232,11 @@ impl<'a> DeviceHList<'a> for HNil {
 
     fn reset(&mut self) {}
 
-    fn write_descriptors(&mut self, _: &mut DescriptorWriter) -> usb_device::Result<()> {
-        Ok(())
-    }
+    fn endpoint_in_complete(&mut self, _: EndpointAddress) {}
+
+    fn endpoint_out(&mut self, _: EndpointAddress) {}
+
+    fn endpoint_halt_cleared(&mut self, _: EndpointAddress) {}
 
     fn get_string(&mut self, _: StringIndex, _: LangID) -> Option<&'a str> {
         None
@@ -48,6 +245,34 @@ impl<'a> DeviceHList<'a> for HNil {
     fn tick(&mut self) -> Result<(), UsbHidError> {
         Ok(())
     }
+
+    fn get_input_report(&mut self, _: u8, _: u8, _: &mut [u8]) -> usb_device::Result<usize> {
+        Err(UsbError::InvalidEndpoint)
+    }
+
+    fn get_feature_report(&mut self, _: u8, _: u8, _: &mut [u8]) -> usb_device::Result<usize> {
+        Err(UsbError::InvalidEndpoint)
+    }
+
+    fn get_static_feature_report(&mut self, _: u8, _: u8) -> Option<&'static [u8]> {
+        None
+    }
+
+    fn set_feature_report(&mut self, _: u8, _: u8, _: &[u8]) -> usb_device::Result<()> {
+        Err(UsbError::InvalidEndpoint)
+    }
+
+    fn set_alt_setting(&mut self, _: u8, _: u8) -> bool {
+        false
+    }
+
+    fn for_each_info(&mut self, _: &mut dyn FnMut(InterfaceInfo)) {}
+
+    fn for_each_interface(&mut self, _: &mut dyn FnMut(&mut dyn InterfaceClass<'a>)) {}
+
+    fn suspend(&mut self) {}
+
+    fn resume(&mut self) {}
 }
 
 impl<'a, Head: DeviceClass<'a> + 'a, Tail: DeviceHList<'a>> DeviceHList<'a> for HCons<Head, Tail> {
@@ -65,9 +290,21 @@ impl<'a, Head: DeviceClass<'a> + 'a, Tail: DeviceHList<'a>> DeviceHList<'a> for
         self.tail.reset();
     }
 
-    fn write_descriptors(&mut self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
-        self.head.interface().write_descriptors(writer)?;
-        self.tail.write_descriptors(writer)
+    fn endpoint_in_complete(&mut self, addr: EndpointAddress) {
+        self.head.interface().endpoint_in_complete(addr);
+        self.tail.endpoint_in_complete(addr);
+    }
+
+    fn endpoint_out(&mut self, addr: EndpointAddress) {
+        self.head.interface().endpoint_out(addr);
+        self.tail.endpoint_out(addr);
+    }
+
+    fn endpoint_halt_cleared(&mut self, addr: EndpointAddress) {
+        if self.head.interface().endpoint_halt_cleared(addr) {
+            self.head.endpoint_halt_cleared();
+        }
+        self.tail.endpoint_halt_cleared(addr);
     }
 
     fn get_string(&mut self, index: StringIndex, lang_id: LangID) -> Option<&'a str> {
@@ -80,7 +317,375 @@ impl<'a, Head: DeviceClass<'a> + 'a, Tail: DeviceHList<'a>> DeviceHList<'a> for
     }
 
     fn tick(&mut self) -> Result<(), UsbHidError> {
+        self.head.interface().elapse_millisecond();
         self.head.tick()?;
         self.tail.tick()
     }
+
+    fn get_input_report(
+        &mut self,
+        id: u8,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        if id == u8::from(self.head.interface().id()) {
+            self.head.get_input_report(report_id, data)
+        } else {
+            self.tail.get_input_report(id, report_id, data)
+        }
+    }
+
+    fn get_feature_report(
+        &mut self,
+        id: u8,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        if id == u8::from(self.head.interface().id()) {
+            self.head.get_feature_report(report_id, data)
+        } else {
+            self.tail.get_feature_report(id, report_id, data)
+        }
+    }
+
+    fn get_static_feature_report(&mut self, id: u8, report_id: u8) -> Option<&'static [u8]> {
+        if id == u8::from(self.head.interface().id()) {
+            self.head.get_static_feature_report(report_id)
+        } else {
+            self.tail.get_static_feature_report(id, report_id)
+        }
+    }
+
+    fn set_feature_report(&mut self, id: u8, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        if id == u8::from(self.head.interface().id()) {
+            self.head.set_feature_report(report_id, data)
+        } else {
+            self.tail.set_feature_report(id, report_id, data)
+        }
+    }
+
+    fn set_alt_setting(&mut self, id: u8, alternate_setting: u8) -> bool {
+        if id == u8::from(self.head.interface().id()) {
+            let selected = self.head.interface().set_alt_setting(alternate_setting);
+            if selected {
+                self.head.set_alternate_setting(alternate_setting);
+            }
+            selected
+        } else {
+            self.tail.set_alt_setting(id, alternate_setting)
+        }
+    }
+
+    fn for_each_info(&mut self, f: &mut dyn FnMut(InterfaceInfo)) {
+        f(self.head.interface().info());
+        self.tail.for_each_info(f);
+    }
+
+    fn for_each_interface(&mut self, f: &mut dyn FnMut(&mut dyn InterfaceClass<'a>)) {
+        f(self.head.interface());
+        self.tail.for_each_interface(f);
+    }
+
+    fn suspend(&mut self) {
+        self.head.interface().suspend();
+        self.head.suspend();
+        self.tail.suspend();
+    }
+
+    fn resume(&mut self) {
+        self.head.interface().resume();
+        self.head.resume();
+        self.tail.resume();
+    }
+}
+
+/// Implement [`DeviceHList`] for a tuple of devices, in interface order
+macro_rules! tuple_device_list {
+    ($($D:ident $idx:tt),+) => {
+        impl<'a, $($D: DeviceClass<'a> + 'a),+> DeviceHList<'a> for ($($D,)+) {
+            fn get(&mut self, id: u8) -> Option<&mut dyn InterfaceClass<'a>> {
+                $(
+                    if id == u8::from(self.$idx.interface().id()) {
+                        return Some(self.$idx.interface());
+                    }
+                )+
+                None
+            }
+
+            fn reset(&mut self) {
+                $(
+                    self.$idx.interface().reset();
+                    self.$idx.reset();
+                )+
+            }
+
+            fn endpoint_in_complete(&mut self, addr: EndpointAddress) {
+                $(self.$idx.interface().endpoint_in_complete(addr);)+
+            }
+
+            fn endpoint_out(&mut self, addr: EndpointAddress) {
+                $(self.$idx.interface().endpoint_out(addr);)+
+            }
+
+            fn endpoint_halt_cleared(&mut self, addr: EndpointAddress) {
+                $(
+                    if self.$idx.interface().endpoint_halt_cleared(addr) {
+                        self.$idx.endpoint_halt_cleared();
+                    }
+                )+
+            }
+
+            fn get_string(&mut self, index: StringIndex, lang_id: LangID) -> Option<&'a str> {
+                $(
+                    if let Some(s) = self.$idx.interface().get_string(index, lang_id) {
+                        return Some(s);
+                    }
+                )+
+                None
+            }
+
+            fn tick(&mut self) -> Result<(), UsbHidError> {
+                $(
+                    self.$idx.interface().elapse_millisecond();
+                    self.$idx.tick()?;
+                )+
+                Ok(())
+            }
+
+            fn get_input_report(
+                &mut self,
+                id: u8,
+                report_id: u8,
+                data: &mut [u8],
+            ) -> usb_device::Result<usize> {
+                $(
+                    if id == u8::from(self.$idx.interface().id()) {
+                        return self.$idx.get_input_report(report_id, data);
+                    }
+                )+
+                Err(UsbError::InvalidEndpoint)
+            }
+
+            fn get_feature_report(
+                &mut self,
+                id: u8,
+                report_id: u8,
+                data: &mut [u8],
+            ) -> usb_device::Result<usize> {
+                $(
+                    if id == u8::from(self.$idx.interface().id()) {
+                        return self.$idx.get_feature_report(report_id, data);
+                    }
+                )+
+                Err(UsbError::InvalidEndpoint)
+            }
+
+            fn get_static_feature_report(
+                &mut self,
+                id: u8,
+                report_id: u8,
+            ) -> Option<&'static [u8]> {
+                $(
+                    if id == u8::from(self.$idx.interface().id()) {
+                        return self.$idx.get_static_feature_report(report_id);
+                    }
+                )+
+                None
+            }
+
+            fn set_feature_report(
+                &mut self,
+                id: u8,
+                report_id: u8,
+                data: &[u8],
+            ) -> usb_device::Result<()> {
+                $(
+                    if id == u8::from(self.$idx.interface().id()) {
+                        return self.$idx.set_feature_report(report_id, data);
+                    }
+                )+
+                Err(UsbError::InvalidEndpoint)
+            }
+
+            fn set_alt_setting(&mut self, id: u8, alternate_setting: u8) -> bool {
+                $(
+                    if id == u8::from(self.$idx.interface().id()) {
+                        let selected = self.$idx.interface().set_alt_setting(alternate_setting);
+                        if selected {
+                            self.$idx.set_alternate_setting(alternate_setting);
+                        }
+                        return selected;
+                    }
+                )+
+                false
+            }
+
+            fn for_each_info(&mut self, f: &mut dyn FnMut(InterfaceInfo)) {
+                $(f(self.$idx.interface().info());)+
+            }
+
+            fn for_each_interface(&mut self, f: &mut dyn FnMut(&mut dyn InterfaceClass<'a>)) {
+                $(f(self.$idx.interface());)+
+            }
+
+            fn suspend(&mut self) {
+                $(
+                    self.$idx.interface().suspend();
+                    self.$idx.suspend();
+                )+
+            }
+
+            fn resume(&mut self) {
+                $(
+                    self.$idx.interface().resume();
+                    self.$idx.resume();
+                )+
+            }
+        }
+    };
+}
+
+tuple_device_list!(D0 0);
+tuple_device_list!(D0 0, D1 1);
+tuple_device_list!(D0 0, D1 1, D2 2);
+tuple_device_list!(D0 0, D1 1, D2 2, D3 3);
+
+/// Implement [`DeviceHList`] for a collection of devices of the same type, in interface order
+macro_rules! collection_device_list {
+    ($collection:ty) => {
+        impl<'a, D: DeviceClass<'a> + 'a, const N: usize> DeviceHList<'a> for $collection {
+            fn get(&mut self, id: u8) -> Option<&mut dyn InterfaceClass<'a>> {
+                self.iter_mut()
+                    .map(DeviceClass::interface)
+                    .find(|interface| u8::from(interface.id()) == id)
+                    .map(|interface| interface as &mut dyn InterfaceClass<'a>)
+            }
+
+            fn reset(&mut self) {
+                for device in self.iter_mut() {
+                    device.interface().reset();
+                    device.reset();
+                }
+            }
+
+            fn endpoint_in_complete(&mut self, addr: EndpointAddress) {
+                for device in self.iter_mut() {
+                    device.interface().endpoint_in_complete(addr);
+                }
+            }
+
+            fn endpoint_out(&mut self, addr: EndpointAddress) {
+                for device in self.iter_mut() {
+                    device.interface().endpoint_out(addr);
+                }
+            }
+
+            fn endpoint_halt_cleared(&mut self, addr: EndpointAddress) {
+                for device in self.iter_mut() {
+                    if device.interface().endpoint_halt_cleared(addr) {
+                        device.endpoint_halt_cleared();
+                    }
+                }
+            }
+
+            fn get_string(&mut self, index: StringIndex, lang_id: LangID) -> Option<&'a str> {
+                self.iter_mut()
+                    .find_map(|device| device.interface().get_string(index, lang_id))
+            }
+
+            fn tick(&mut self) -> Result<(), UsbHidError> {
+                self.iter_mut().try_for_each(|device| {
+                    device.interface().elapse_millisecond();
+                    device.tick()
+                })
+            }
+
+            fn get_input_report(
+                &mut self,
+                id: u8,
+                report_id: u8,
+                data: &mut [u8],
+            ) -> usb_device::Result<usize> {
+                device_with_id(self, id)
+                    .ok_or(UsbError::InvalidEndpoint)?
+                    .get_input_report(report_id, data)
+            }
+
+            fn get_feature_report(
+                &mut self,
+                id: u8,
+                report_id: u8,
+                data: &mut [u8],
+            ) -> usb_device::Result<usize> {
+                device_with_id(self, id)
+                    .ok_or(UsbError::InvalidEndpoint)?
+                    .get_feature_report(report_id, data)
+            }
+
+            fn get_static_feature_report(
+                &mut self,
+                id: u8,
+                report_id: u8,
+            ) -> Option<&'static [u8]> {
+                device_with_id(self, id)?.get_static_feature_report(report_id)
+            }
+
+            fn set_feature_report(
+                &mut self,
+                id: u8,
+                report_id: u8,
+                data: &[u8],
+            ) -> usb_device::Result<()> {
+                device_with_id(self, id)
+                    .ok_or(UsbError::InvalidEndpoint)?
+                    .set_feature_report(report_id, data)
+            }
+
+            fn set_alt_setting(&mut self, id: u8, alternate_setting: u8) -> bool {
+                device_with_id(self, id).is_some_and(|device| {
+                    let selected = device.interface().set_alt_setting(alternate_setting);
+                    if selected {
+                        device.set_alternate_setting(alternate_setting);
+                    }
+                    selected
+                })
+            }
+
+            fn for_each_info(&mut self, f: &mut dyn FnMut(InterfaceInfo)) {
+                for device in self.iter_mut() {
+                    f(device.interface().info());
+                }
+            }
+
+            fn for_each_interface(&mut self, f: &mut dyn FnMut(&mut dyn InterfaceClass<'a>)) {
+                for device in self.iter_mut() {
+                    f(device.interface());
+                }
+            }
+
+            fn suspend(&mut self) {
+                for device in self.iter_mut() {
+                    device.interface().suspend();
+                    device.suspend();
+                }
+            }
+
+            fn resume(&mut self) {
+                for device in self.iter_mut() {
+                    device.interface().resume();
+                    device.resume();
+                }
+            }
+        }
+    };
+}
+
+collection_device_list!([D; N]);
+collection_device_list!(heapless::Vec<D, N>);
+
+/// The device of a collection with the interface number `id`
+fn device_with_id<'a, D: DeviceClass<'a>>(devices: &mut [D], id: u8) -> Option<&mut D> {
+    devices
+        .iter_mut()
+        .find_map(|device| (u8::from(device.interface().id()) == id).then_some(device))
 }
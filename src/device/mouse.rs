@@ -5,6 +5,7 @@ use fugit::ExtU32;
 use packed_struct::prelude::*;
 use usb_device::bus::UsbBus;
 use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
 
 /// HID Mouse report descriptor conforming to the Boot specification
 ///
@@ -96,6 +97,7 @@ pub const WHEEL_MOUSE_REPORT_DESCRIPTOR: &[u8] = &[
     0xC0,              // End Collection
 ];
 
+/// Wheel mouse report, the horizontal wheel is reported as Consumer AC Pan
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
 #[packed_struct(endian = "lsb")]
 pub struct WheelMouseReport {
@@ -111,6 +113,243 @@ pub struct WheelMouseReport {
     pub horizontal_wheel: i8,
 }
 
+impl WheelMouseReport {
+    /// Report with pointer movement and buttons, and no scrolling
+    #[must_use]
+    pub const fn new(buttons: u8, x: i8, y: i8) -> Self {
+        Self {
+            buttons,
+            x,
+            y,
+            vertical_wheel: 0,
+            horizontal_wheel: 0,
+        }
+    }
+
+    /// Set both scroll axes, positive `horizontal` values pan right
+    #[must_use]
+    pub const fn with_scroll(self, vertical: i8, horizontal: i8) -> Self {
+        Self {
+            vertical_wheel: vertical,
+            horizontal_wheel: horizontal,
+            ..self
+        }
+    }
+}
+
+/// High resolution wheel mouse with eight buttons
+///
+/// As [`WHEEL_MOUSE_REPORT_DESCRIPTOR`] with a Resolution Multiplier feature for each wheel. A
+/// host that supports smooth scrolling sets the multipliers, after which each wheel unit is
+/// `1 / HIGH_RESOLUTION_WHEEL_MULTIPLIER` of a detent.
+///
+/// Reference: <https://learn.microsoft.com/en-us/previous-versions/windows/hardware/design/dn613912(v=vs.85)>
+#[rustfmt::skip]
+pub const HIGH_RESOLUTION_WHEEL_MOUSE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x02,        // Usage (Mouse),
+    0xA1, 0x01,        // Collection (Application),
+    0x09, 0x01,        //   Usage (Pointer),
+    0xA1, 0x00,        //   Collection (Physical),
+    0x95, 0x08,        //     Report Count (8),
+    0x75, 0x01,        //     Report Size (1),
+    0x05, 0x09,        //     Usage Page (Buttons),
+    0x19, 0x01,        //     Usage Minimum (1),
+    0x29, 0x08,        //     Usage Maximum (8),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x02,        //     Report Count (2),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x15, 0x81,        //     Logical Minimum (-127),
+    0x25, 0x7F,        //     Logical Maximum (127),
+    0x81, 0x06,        //     Input (Data, Variable, Relative),
+
+    0xA1, 0x02,        //     Collection (Logical),
+    0x09, 0x48,        //       Usage (Resolution Multiplier),
+    0x15, 0x00,        //       Logical Minimum (0),
+    0x25, 0x01,        //       Logical Maximum (1),
+    0x35, 0x01,        //       Physical Minimum (1),
+    0x45, 0x08,        //       Physical Maximum (8),
+    0x75, 0x02,        //       Report Size (2),
+    0x95, 0x01,        //       Report Count (1),
+    0xB1, 0x02,        //       Feature (Data, Variable, Absolute),
+    0x35, 0x00,        //       Physical Minimum (0),
+    0x45, 0x00,        //       Physical Maximum (0),
+    0x09, 0x38,        //       Usage (Wheel),
+    0x15, 0x81,        //       Logical Minimum (-127),
+    0x25, 0x7F,        //       Logical Maximum (127),
+    0x75, 0x08,        //       Report Size (8),
+    0x81, 0x06,        //       Input (Data, Variable, Relative),
+    0xC0,              //     End Collection
+
+    0xA1, 0x02,        //     Collection (Logical),
+    0x09, 0x48,        //       Usage (Resolution Multiplier),
+    0x15, 0x00,        //       Logical Minimum (0),
+    0x25, 0x01,        //       Logical Maximum (1),
+    0x35, 0x01,        //       Physical Minimum (1),
+    0x45, 0x08,        //       Physical Maximum (8),
+    0x75, 0x02,        //       Report Size (2),
+    0xB1, 0x02,        //       Feature (Data, Variable, Absolute),
+    0x35, 0x00,        //       Physical Minimum (0),
+    0x45, 0x00,        //       Physical Maximum (0),
+    0x05, 0x0C,        //       Usage Page (Consumer),
+    0x0A, 0x38, 0x02,  //       Usage (AC Pan),
+    0x15, 0x81,        //       Logical Minimum (-127),
+    0x25, 0x7F,        //       Logical Maximum (127),
+    0x75, 0x08,        //       Report Size (8),
+    0x81, 0x06,        //       Input (Data, Variable, Relative),
+    0xC0,              //     End Collection
+
+    0x75, 0x04,        //     Report Size (4),
+    0xB1, 0x03,        //     Feature (Constant, Variable, Absolute),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+/// Wheel units per detent once the host has enabled the resolution multiplier
+pub const HIGH_RESOLUTION_WHEEL_MULTIPLIER: i8 = 8;
+
+/// Resolution multiplier feature report, set by the host to enable high resolution scrolling
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(bit_numbering = "lsb0", size_bytes = "1")]
+pub struct ResolutionMultiplierReport {
+    #[packed_field(bits = "0")]
+    pub vertical: bool,
+    #[packed_field(bits = "2")]
+    pub horizontal: bool,
+}
+
+/// Maximum number of buttons of a [`PrecisionMouse`]
+pub const PRECISION_MOUSE_MAX_BUTTONS: u8 = 16;
+
+/// Report descriptor for a [`PrecisionMouse`] with `buttons` buttons, generated at compile time
+///
+/// Buttons are padded to 16 bits and followed by 16 bit X and Y deltas, the wheel and AC Pan,
+/// matching [`PrecisionMouseReport`]
+pub struct PrecisionMouseDescriptor {
+    descriptor: [u8; 80],
+    len: usize,
+}
+
+impl PrecisionMouseDescriptor {
+    /// Generate the report descriptor for `buttons` buttons
+    ///
+    /// Panics, at compile time if used in a const context, if `buttons` is zero or exceeds
+    /// [`PRECISION_MOUSE_MAX_BUTTONS`]
+    #[must_use]
+    #[rustfmt::skip]
+    pub const fn new(buttons: u8) -> Self {
+        core::assert!(buttons > 0, "no buttons");
+        core::assert!(buttons <= PRECISION_MOUSE_MAX_BUTTONS, "too many buttons");
+
+        let mut d = Self {
+            descriptor: [0; 80],
+            len: 0,
+        };
+
+        d.push(&[
+            0x05, 0x01,                 // Usage Page (Generic Desktop),
+            0x09, 0x02,                 // Usage (Mouse),
+            0xA1, 0x01,                 // Collection (Application),
+            0x09, 0x01,                 //   Usage (Pointer),
+            0xA1, 0x00,                 //   Collection (Physical),
+            0x05, 0x09,                 //     Usage Page (Buttons),
+            0x19, 0x01,                 //     Usage Minimum (1),
+            0x29, buttons,              //     Usage Maximum (buttons),
+            0x15, 0x00,                 //     Logical Minimum (0),
+            0x25, 0x01,                 //     Logical Maximum (1),
+            0x75, 0x01,                 //     Report Size (1),
+            0x95, buttons,              //     Report Count (buttons),
+            0x81, 0x02,                 //     Input (Data, Variable, Absolute),
+        ]);
+
+        if buttons < 16 {
+            d.push(&[
+                0x75, 16 - buttons,     //     Report Size (padding),
+                0x95, 0x01,             //     Report Count (1),
+                0x81, 0x03,             //     Input (Constant, Variable, Absolute),
+            ]);
+        }
+
+        d.push(&[
+            0x05, 0x01,                 //     Usage Page (Generic Desktop),
+            0x09, 0x30,                 //     Usage (X),
+            0x09, 0x31,                 //     Usage (Y),
+            0x16, 0x01, 0x80,           //     Logical Minimum (-32767),
+            0x26, 0xFF, 0x7F,           //     Logical Maximum (32767),
+            0x75, 0x10,                 //     Report Size (16),
+            0x95, 0x02,                 //     Report Count (2),
+            0x81, 0x06,                 //     Input (Data, Variable, Relative),
+            0x09, 0x38,                 //     Usage (Wheel),
+            0x15, 0x81,                 //     Logical Minimum (-127),
+            0x25, 0x7F,                 //     Logical Maximum (127),
+            0x75, 0x08,                 //     Report Size (8),
+            0x95, 0x01,                 //     Report Count (1),
+            0x81, 0x06,                 //     Input (Data, Variable, Relative),
+            0x05, 0x0C,                 //     Usage Page (Consumer),
+            0x0A, 0x38, 0x02,           //     Usage (AC Pan),
+            0x81, 0x06,                 //     Input (Data, Variable, Relative),
+            0xC0,                       //   End Collection
+            0xC0,                       // End Collection
+        ]);
+
+        d
+    }
+
+    const fn push(&mut self, items: &[u8]) {
+        let mut i = 0;
+        while i < items.len() {
+            self.descriptor[self.len] = items[i];
+            self.len += 1;
+            i += 1;
+        }
+    }
+
+    #[must_use]
+    pub fn descriptor(&self) -> &[u8] {
+        &self.descriptor[..self.len]
+    }
+}
+
+/// Precision mouse report, buttons not supported by the device are ignored
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "8")]
+pub struct PrecisionMouseReport {
+    /// Button state, bit 0 is button 1
+    #[packed_field]
+    pub buttons: u16,
+    #[packed_field]
+    pub x: i16,
+    #[packed_field]
+    pub y: i16,
+    #[packed_field]
+    pub vertical_wheel: i8,
+    #[packed_field]
+    pub horizontal_wheel: i8,
+}
+
+impl PrecisionMouseReport {
+    /// The report in the boot mouse format, with the first three buttons and the deltas
+    /// saturated to 8 bits
+    #[must_use]
+    pub fn boot_report(&self) -> BootMouseReport {
+        let saturate =
+            |delta: i16| i8::try_from(delta).unwrap_or(if delta < 0 { i8::MIN } else { i8::MAX });
+        BootMouseReport {
+            buttons: (self.buttons & 0x07) as u8,
+            x: saturate(self.x),
+            y: saturate(self.y),
+        }
+    }
+}
+
 /// Absolute mouse with wheel and eight buttons
 ///
 /// Note - absolute pointer support is relatively uncommon. This has been tested on Windows 11
@@ -173,20 +412,165 @@ pub struct AbsoluteWheelMouseReport {
     pub wheel: i8,
 }
 
+/// Absolute pointer with eight buttons, suitable for touchscreen and tablet style devices
+///
+/// X and Y are reported as absolute positions in the range 0-32767, scaled by the host to
+/// cover the display.
+///
+/// Note - as with [`ABSOLUTE_WHEEL_MOUSE_REPORT_DESCRIPTOR`], Windows only natively supports
+/// absolute pointer devices on the primary display.
+#[rustfmt::skip]
+pub const ABSOLUTE_MOUSE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x02,        // Usage (Mouse),
+    0xA1, 0x01,        // Collection (Application),
+    0x09, 0x01,        //   Usage (Pointer),
+    0xA1, 0x00,        //   Collection (Physical),
+
+    0x05, 0x09,        //     Usage Page (Buttons),
+    0x19, 0x01,        //     Usage Minimum (1),
+    0x29, 0x08,        //     Usage Maximum (8),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x95, 0x08,        //     Report Count (8),
+    0x75, 0x01,        //     Report Size (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x35, 0x00,        //     Physical Minimum (0),
+    0x46, 0xFF, 0x7F,  //     Physical Maximum (32767),
+    0x95, 0x02,        //     Report Count (2),
+    0x75, 0x10,        //     Report Size (16),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "5")]
+pub struct AbsoluteMouseReport {
+    #[packed_field]
+    pub buttons: u8,
+    /// Absolute X position, 0-32767
+    #[packed_field]
+    pub x: u16,
+    /// Absolute Y position, 0-32767
+    #[packed_field]
+    pub y: u16,
+}
+
+/// Buttons and movement written to a relative mouse but not yet sent to the host
+///
+/// Movement is accumulated, saturating, until it's sent, so movement written while the endpoint
+/// is busy, or beyond the range of a single report, is sent with a later report.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct MotionAccumulator {
+    buttons: u16,
+    x: i32,
+    y: i32,
+    vertical_wheel: i32,
+    horizontal_wheel: i32,
+    buttons_pending: bool,
+}
+
+impl MotionAccumulator {
+    fn add(&mut self, buttons: u16, x: i32, y: i32, vertical_wheel: i32, horizontal_wheel: i32) {
+        self.buttons_pending |= buttons != self.buttons;
+        self.buttons = buttons;
+        self.x = self.x.saturating_add(x);
+        self.y = self.y.saturating_add(y);
+        self.vertical_wheel = self.vertical_wheel.saturating_add(vertical_wheel);
+        self.horizontal_wheel = self.horizontal_wheel.saturating_add(horizontal_wheel);
+    }
+
+    /// Whether there are button changes or movement still to send
+    fn is_pending(&self) -> bool {
+        self.buttons_pending
+            || self.x != 0
+            || self.y != 0
+            || self.vertical_wheel != 0
+            || self.horizontal_wheel != 0
+    }
+
+    /// X, Y, vertical and horizontal wheel movement to send, saturated to `min..=max`
+    fn next<T: TryFrom<i32> + Copy>(&self, min: T, max: T) -> [T; 4] {
+        [self.x, self.y, self.vertical_wheel, self.horizontal_wheel]
+            .map(|delta| T::try_from(delta).unwrap_or(if delta < 0 { min } else { max }))
+    }
+
+    /// Remove movement sent to the host with [`MotionAccumulator::next`]
+    fn sent<T: Into<i32>>(&mut self, [x, y, vertical_wheel, horizontal_wheel]: [T; 4]) {
+        self.x -= x.into();
+        self.y -= y.into();
+        self.vertical_wheel -= vertical_wheel.into();
+        self.horizontal_wheel -= horizontal_wheel.into();
+        self.buttons_pending = false;
+    }
+
+    /// Handle the result of writing the report built from [`MotionAccumulator::next`], blocked
+    /// reports are kept to send later
+    fn written<T: Into<i32>>(
+        &mut self,
+        result: usb_device::Result<usize>,
+        sent: [T; 4],
+    ) -> Result<(), UsbHidError> {
+        match result {
+            Ok(_) => {
+                self.sent(sent);
+                Ok(())
+            }
+            Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Boot mouse
+///
+/// Movement written while the endpoint is busy is accumulated and sent with the next report, or
+/// from [`DeviceClass::tick`], see [`BootMouse::write_report`].
 pub struct BootMouse<'a, B: UsbBus> {
     interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    motion: MotionAccumulator,
 }
 
 impl<B: UsbBus> BootMouse<'_, B> {
+    /// Write the buttons and add the movement of `report` to any movement not yet sent
+    ///
+    /// If the endpoint is busy the report is kept and sent later, returning `Ok`, so the
+    /// movement written is never lost or sent twice.
     pub fn write_report(&mut self, report: &BootMouseReport) -> Result<(), UsbHidError> {
-        let data = report.pack().map_err(|_| {
+        self.motion.add(
+            report.buttons.into(),
+            report.x.into(),
+            report.y.into(),
+            0,
+            0,
+        );
+        self.write_motion()
+    }
+
+    fn write_motion(&mut self) -> Result<(), UsbHidError> {
+        let next = self.motion.next(i8::MIN, i8::MAX);
+        #[allow(clippy::cast_possible_truncation)]
+        let data = BootMouseReport {
+            buttons: self.motion.buttons as u8,
+            x: next[0],
+            y: next[1],
+        }
+        .pack()
+        .map_err(|_| {
             error!("Error packing BootMouseReport");
             UsbHidError::SerializationError
         })?;
-        self.interface
-            .write_report(&data)
-            .map(|_| ())
-            .map_err(UsbHidError::from)
+        let result = self.interface.write_report(&data);
+        self.motion.written(result, next)
     }
 }
 
@@ -202,7 +586,6 @@ impl<'a> BootMouseConfig<'a> {
 }
 
 impl Default for BootMouseConfig<'_> {
-    #[must_use]
     fn default() -> Self {
         Self::new(
             unwrap!(unwrap!(InterfaceBuilder::new(BOOT_MOUSE_REPORT_DESCRIPTOR))
@@ -218,10 +601,11 @@ impl Default for BootMouseConfig<'_> {
 impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for BootMouseConfig<'a> {
     type Allocated = BootMouse<'a, B>;
 
-    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        BootMouse {
-            interface: self.interface.allocate(usb_alloc),
-        }
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(BootMouse {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            motion: MotionAccumulator::default(),
+        })
     }
 }
 
@@ -232,27 +616,56 @@ impl<'a, B: UsbBus> DeviceClass<'a> for BootMouse<'a, B> {
         &mut self.interface
     }
 
-    fn reset(&mut self) {}
+    fn reset(&mut self) {
+        self.motion = MotionAccumulator::default();
+    }
 
     fn tick(&mut self) -> Result<(), UsbHidError> {
-        Ok(())
+        if self.motion.is_pending() {
+            self.write_motion()
+        } else {
+            Ok(())
+        }
     }
 }
 
+/// Boot compatible mouse with wheel and pan
+///
+/// Movement written while the endpoint is busy is accumulated and sent with the next report, or
+/// from [`DeviceClass::tick`], see [`WheelMouse::write_report`].
 pub struct WheelMouse<'a, B: UsbBus> {
     interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    motion: MotionAccumulator,
 }
 
 impl<B: UsbBus> WheelMouse<'_, B> {
+    /// Write the buttons and add the movement of `report` to any movement not yet sent
+    ///
+    /// If the endpoint is busy the report is kept and sent later, returning `Ok`, so the
+    /// movement written is never lost or sent twice.
     pub fn write_report(&mut self, report: &WheelMouseReport) -> Result<(), UsbHidError> {
-        let data = report.pack().map_err(|_| {
-            error!("Error packing WheelMouseReport");
-            UsbHidError::SerializationError
-        })?;
-        self.interface
-            .write_report(&data)
-            .map(|_| ())
-            .map_err(UsbHidError::from)
+        self.motion.add(
+            report.buttons.into(),
+            report.x.into(),
+            report.y.into(),
+            report.vertical_wheel.into(),
+            report.horizontal_wheel.into(),
+        );
+        self.write_motion()
+    }
+
+    fn write_motion(&mut self) -> Result<(), UsbHidError> {
+        let next = self.motion.next(i8::MIN, i8::MAX);
+        #[allow(clippy::cast_possible_truncation)]
+        let data = WheelMouseReport::new(self.motion.buttons as u8, next[0], next[1])
+            .with_scroll(next[2], next[3])
+            .pack()
+            .map_err(|_| {
+                error!("Error packing WheelMouseReport");
+                UsbHidError::SerializationError
+            })?;
+        let result = self.interface.write_report(&data);
+        self.motion.written(result, next)
     }
 }
 pub struct WheelMouseConfig<'a> {
@@ -267,7 +680,6 @@ impl<'a> WheelMouseConfig<'a> {
 }
 
 impl Default for WheelMouseConfig<'_> {
-    #[must_use]
     fn default() -> Self {
         WheelMouseConfig::new(
             unwrap!(
@@ -285,10 +697,11 @@ impl Default for WheelMouseConfig<'_> {
 impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for WheelMouseConfig<'a> {
     type Allocated = WheelMouse<'a, B>;
 
-    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        WheelMouse {
-            interface: self.interface.allocate(usb_alloc),
-        }
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(WheelMouse {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            motion: MotionAccumulator::default(),
+        })
     }
 }
 
@@ -299,10 +712,16 @@ impl<'a, B: UsbBus> DeviceClass<'a> for WheelMouse<'a, B> {
         &mut self.interface
     }
 
-    fn reset(&mut self) {}
+    fn reset(&mut self) {
+        self.motion = MotionAccumulator::default();
+    }
 
     fn tick(&mut self) -> Result<(), UsbHidError> {
-        Ok(())
+        if self.motion.is_pending() {
+            self.write_motion()
+        } else {
+            Ok(())
+        }
     }
 }
 
@@ -335,7 +754,6 @@ impl<'a> AbsoluteWheelMouseConfig<'a> {
 }
 
 impl Default for AbsoluteWheelMouseConfig<'_> {
-    #[must_use]
     fn default() -> Self {
         AbsoluteWheelMouseConfig::new(
             unwrap!(unwrap!(InterfaceBuilder::new(
@@ -352,10 +770,10 @@ impl Default for AbsoluteWheelMouseConfig<'_> {
 impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for AbsoluteWheelMouseConfig<'a> {
     type Allocated = AbsoluteWheelMouse<'a, B>;
 
-    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        AbsoluteWheelMouse {
-            interface: self.interface.allocate(usb_alloc),
-        }
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(AbsoluteWheelMouse {
+            interface: self.interface.try_allocate(usb_alloc)?,
+        })
     }
 }
 
@@ -372,3 +790,433 @@ impl<'a, B: UsbBus> DeviceClass<'a> for AbsoluteWheelMouse<'a, B> {
         Ok(())
     }
 }
+
+/// Absolute pointer device
+///
+/// Duplicate reports are suppressed by [`AbsoluteMouse::write_report`], returning
+/// [`UsbHidError::Duplicate`], so the pointer position can be written unconditionally from a
+/// polling loop.
+pub struct AbsoluteMouse<'a, B: UsbBus> {
+    interface: ManagedIdleInterface<'a, B, AbsoluteMouseReport, InBytes8, OutNone>,
+}
+
+impl<B: UsbBus> AbsoluteMouse<'_, B> {
+    pub fn write_report(&mut self, report: &AbsoluteMouseReport) -> Result<(), UsbHidError> {
+        self.interface.write_report(report)
+    }
+}
+
+pub struct AbsoluteMouseConfig<'a> {
+    interface: ManagedIdleInterfaceConfig<'a, AbsoluteMouseReport, InBytes8, OutNone>,
+}
+
+impl<'a> AbsoluteMouseConfig<'a> {
+    #[must_use]
+    pub fn new(
+        interface: ManagedIdleInterfaceConfig<'a, AbsoluteMouseReport, InBytes8, OutNone>,
+    ) -> Self {
+        Self { interface }
+    }
+}
+
+impl Default for AbsoluteMouseConfig<'_> {
+    fn default() -> Self {
+        Self::new(ManagedIdleInterfaceConfig::new(
+            unwrap!(
+                unwrap!(InterfaceBuilder::new(ABSOLUTE_MOUSE_REPORT_DESCRIPTOR))
+                    .description("Absolute Mouse")
+                    .in_endpoint(10.millis())
+            )
+            .without_out_endpoint()
+            .build(),
+        ))
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for AbsoluteMouseConfig<'a> {
+    type Allocated = AbsoluteMouse<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(AbsoluteMouse {
+            interface: self.interface.try_allocate(usb_alloc)?,
+        })
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for AbsoluteMouse<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        self.interface.interface()
+    }
+
+    fn reset(&mut self) {
+        self.interface.reset();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        self.interface.tick()
+    }
+
+    fn get_input_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        self.interface.get_input_report(report_id, data)
+    }
+
+    fn endpoint_halt_cleared(&mut self) {
+        self.interface.endpoint_halt_cleared();
+    }
+}
+
+/// High resolution wheel mouse
+///
+/// Wheel values written with [`HighResolutionWheelMouse::write_report`] are always in high
+/// resolution units, [`HIGH_RESOLUTION_WHEEL_MULTIPLIER`] per detent. Until the host enables a
+/// wheel's resolution multiplier the units are accumulated and only whole detents are sent, so
+/// scrolling speed is the same for hosts without smooth scrolling support.
+pub struct HighResolutionWheelMouse<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    multiplier: ResolutionMultiplierReport,
+    remainder: (i16, i16),
+}
+
+impl<B: UsbBus> HighResolutionWheelMouse<'_, B> {
+    pub fn write_report(&mut self, report: &WheelMouseReport) -> Result<(), UsbHidError> {
+        let (vertical, vertical_remainder) = Self::scale(
+            self.multiplier.vertical,
+            self.remainder.0,
+            report.vertical_wheel,
+        );
+        let (horizontal, horizontal_remainder) = Self::scale(
+            self.multiplier.horizontal,
+            self.remainder.1,
+            report.horizontal_wheel,
+        );
+
+        let data = WheelMouseReport {
+            vertical_wheel: vertical,
+            horizontal_wheel: horizontal,
+            ..*report
+        }
+        .pack()
+        .map_err(|_| {
+            error!("Error packing WheelMouseReport");
+            UsbHidError::SerializationError
+        })?;
+        self.interface
+            .write_report(&data)
+            .map_err(UsbHidError::from)?;
+        self.remainder = (vertical_remainder, horizontal_remainder);
+        Ok(())
+    }
+
+    /// Wheel value to send and units left over, whole detents only if the multiplier is disabled
+    fn scale(enabled: bool, remainder: i16, units: i8) -> (i8, i16) {
+        if enabled {
+            (units, 0)
+        } else {
+            let total = remainder + i16::from(units);
+            let multiplier = i16::from(HIGH_RESOLUTION_WHEEL_MULTIPLIER);
+            #[allow(clippy::cast_possible_truncation)]
+            ((total / multiplier) as i8, total % multiplier)
+        }
+    }
+
+    /// Resolution multipliers currently enabled by the host
+    #[must_use]
+    pub fn multiplier(&self) -> ResolutionMultiplierReport {
+        self.multiplier
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for HighResolutionWheelMouse<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.multiplier = ResolutionMultiplierReport::default();
+        self.remainder = (0, 0);
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        if report_id != 0 {
+            return Err(UsbError::Unsupported);
+        }
+        let out = data.get_mut(..1).ok_or(UsbError::BufferOverflow)?;
+        self.multiplier
+            .pack_to_slice(out)
+            .map_err(|_| UsbError::ParseError)?;
+        Ok(1)
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        if report_id != 0 {
+            return Err(UsbError::Unsupported);
+        }
+        let multiplier = data
+            .get(..1)
+            .and_then(|d| ResolutionMultiplierReport::unpack_from_slice(d).ok())
+            .ok_or(UsbError::ParseError)?;
+        info!(
+            "Set resolution multiplier: vertical {}, horizontal {}",
+            multiplier.vertical, multiplier.horizontal
+        );
+        self.multiplier = multiplier;
+        Ok(())
+    }
+}
+
+pub struct HighResolutionWheelMouseConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+}
+
+impl<'a> HighResolutionWheelMouseConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl Default for HighResolutionWheelMouseConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::new(
+                HIGH_RESOLUTION_WHEEL_MOUSE_REPORT_DESCRIPTOR
+            ))
+            .boot_device(InterfaceProtocol::Mouse)
+            .description("Wheel Mouse")
+            .in_endpoint(10.millis()))
+            .without_out_endpoint()
+            .build(),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for HighResolutionWheelMouseConfig<'a> {
+    type Allocated = HighResolutionWheelMouse<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(HighResolutionWheelMouse {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            multiplier: ResolutionMultiplierReport::default(),
+            remainder: (0, 0),
+        })
+    }
+}
+
+/// Mouse with 16 bit deltas for high DPI sensors and `BUTTONS` buttons, up to
+/// [`PRECISION_MOUSE_MAX_BUTTONS`]
+///
+/// While the host has selected the boot protocol, such as in a BIOS, reports are sent as
+/// [`BootMouseReport`]s, see [`PrecisionMouseReport::boot_report`].
+///
+/// ```
+/// use usbd_human_interface_device::device::mouse::*;
+///
+/// let config = PrecisionMouseConfig::<12>::default();
+/// ```
+pub struct PrecisionMouse<'a, B: UsbBus, const BUTTONS: u8> {
+    interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    motion: MotionAccumulator,
+}
+
+impl<B: UsbBus, const BUTTONS: u8> PrecisionMouse<'_, B, BUTTONS> {
+    /// Write the buttons and add the movement of `report` to any movement not yet sent
+    ///
+    /// If the endpoint is busy the report is kept and sent later, with the next report or from
+    /// [`DeviceClass::tick`], returning `Ok`, so the movement written is never lost or sent twice.
+    pub fn write_report(&mut self, report: &PrecisionMouseReport) -> Result<(), UsbHidError> {
+        self.motion.add(
+            report.buttons & (u16::MAX >> (16 - u32::from(BUTTONS))),
+            report.x.into(),
+            report.y.into(),
+            report.vertical_wheel.into(),
+            report.horizontal_wheel.into(),
+        );
+        self.write_motion()
+    }
+
+    fn write_motion(&mut self) -> Result<(), UsbHidError> {
+        if self.interface.protocol() == HidProtocol::Boot {
+            // Boot reports have no wheels
+            self.motion.vertical_wheel = 0;
+            self.motion.horizontal_wheel = 0;
+        }
+        let [x, y, _, _] = self.motion.next(i16::MIN, i16::MAX);
+        let [_, _, vertical_wheel, horizontal_wheel] = self.motion.next(i8::MIN, i8::MAX);
+        let report = PrecisionMouseReport {
+            buttons: self.motion.buttons,
+            x,
+            y,
+            vertical_wheel,
+            horizontal_wheel,
+        };
+        let (packed, sent) = match self.interface.protocol() {
+            HidProtocol::Boot => {
+                let boot_report = report.boot_report();
+                (
+                    boot_report
+                        .pack()
+                        .map(|data| self.interface.write_report(&data)),
+                    [boot_report.x.into(), boot_report.y.into(), 0, 0],
+                )
+            }
+            HidProtocol::Report => (
+                report.pack().map(|data| self.interface.write_report(&data)),
+                [x, y, vertical_wheel.into(), horizontal_wheel.into()],
+            ),
+        };
+        let result = packed.map_err(|_| {
+            error!("Error packing PrecisionMouseReport");
+            UsbHidError::SerializationError
+        })?;
+        self.motion.written(result, sent)
+    }
+}
+
+impl<'a, B: UsbBus, const BUTTONS: u8> DeviceClass<'a> for PrecisionMouse<'a, B, BUTTONS> {
+    type I = Interface<'a, B, InBytes8, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.motion = MotionAccumulator::default();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        if self.motion.is_pending() {
+            self.write_motion()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub struct PrecisionMouseConfig<'a, const BUTTONS: u8> {
+    interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+}
+
+impl<'a, const BUTTONS: u8> PrecisionMouseConfig<'a, BUTTONS> {
+    /// Report descriptor for `BUTTONS` buttons
+    pub const DESCRIPTOR: &'static PrecisionMouseDescriptor =
+        &PrecisionMouseDescriptor::new(BUTTONS);
+
+    /// `interface` must use [`PrecisionMouseConfig::DESCRIPTOR`]
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<const BUTTONS: u8> Default for PrecisionMouseConfig<'_, BUTTONS> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                Self::DESCRIPTOR.descriptor()
+            ))
+            .description("Mouse")
+            .boot_device(InterfaceProtocol::Mouse)
+            .boot_report_descriptor(BOOT_MOUSE_REPORT_DESCRIPTOR))
+            .in_endpoint(1.millis()))
+            .without_out_endpoint()
+            .build(),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a, const BUTTONS: u8> UsbAllocatable<'a, B>
+    for PrecisionMouseConfig<'a, BUTTONS>
+{
+    type Allocated = PrecisionMouse<'a, B, BUTTONS>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(PrecisionMouse {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            motion: MotionAccumulator::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::mouse::{
+        AbsoluteMouseReport, BootMouseReport, PrecisionMouseDescriptor, PrecisionMouseReport,
+        ResolutionMultiplierReport, WheelMouseReport,
+    };
+
+    #[test]
+    fn absolute_mouse_report_is_little_endian() {
+        let bytes = AbsoluteMouseReport {
+            buttons: 0x05,
+            x: 0x1234,
+            y: 0x7FFF,
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(bytes, [0x05, 0x34, 0x12, 0xFF, 0x7F]);
+    }
+
+    #[test]
+    fn resolution_multiplier_layout() {
+        let report = ResolutionMultiplierReport::unpack(&[0x05]).unwrap();
+        assert!(report.vertical);
+        assert!(report.horizontal);
+    }
+
+    #[test]
+    fn wheel_mouse_report_scroll_axes() {
+        let bytes = WheelMouseReport::new(0x01, -2, 3)
+            .with_scroll(1, -1)
+            .pack()
+            .unwrap();
+
+        assert_eq!(bytes, [0x01, 0xFE, 0x03, 0x01, 0xFF]);
+    }
+
+    #[test]
+    fn precision_mouse_descriptor_pads_buttons() {
+        static DESCRIPTOR: PrecisionMouseDescriptor = PrecisionMouseDescriptor::new(5);
+
+        assert_eq!(
+            DESCRIPTOR.descriptor()[..30],
+            [
+                0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x09, 0x01, 0xA1, 0x00, 0x05, 0x09, 0x19, 0x01,
+                0x29, 0x05, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x05, 0x81, 0x02, 0x75, 0x0B,
+                0x95, 0x01
+            ]
+        );
+        assert_eq!(DESCRIPTOR.descriptor().len(), 71);
+        assert_eq!(PrecisionMouseDescriptor::new(16).descriptor().len(), 65);
+    }
+
+    #[test]
+    fn precision_mouse_report_boot_report_saturates() {
+        let report = PrecisionMouseReport {
+            buttons: 0xFFFF,
+            x: 1000,
+            y: -1000,
+            ..Default::default()
+        };
+        assert_eq!(
+            report.boot_report(),
+            BootMouseReport {
+                buttons: 0x07,
+                x: i8::MAX,
+                y: i8::MIN,
+            }
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//!HID magnetic stripe reader
+//!
+//! Reports ISO 7811 track data using the Magnetic Stripe Reader usage page (0x8E)
+use crate::interface::InBytes256;
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the track data input report
+pub const MSR_TRACK_DATA_REPORT_ID: u8 = 0x01;
+/// Report ID of the track length feature report
+pub const MSR_TRACK_LENGTH_REPORT_ID: u8 = 0x02;
+
+/// Maximum number of characters on track 1, 2 and 3
+pub const MSR_TRACK_LENGTHS: [u8; 3] = [79, 40, 107];
+
+const MSR_REPORT_LEN: usize = 1 + 3 + 79 + 40 + 107;
+
+/// Track data is ASCII, without start and end sentinels
+#[rustfmt::skip]
+pub const MSR_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x8E,        // Usage Page (Magnetic Stripe Reader),
+    0x09, 0x01,        // Usage (MSR Device Read-Only),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x01,        //   Report Count (1),
+    0x09, 0x11,        //   Usage (Track 1 Length),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x09, 0x12,        //   Usage (Track 2 Length),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x09, 0x13,        //   Usage (Track 3 Length),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x09, 0x21,        //   Usage (Track 1 Data),
+    0x95, 0x4F,        //   Report Count (79),
+    0x82, 0x02, 0x01,  //   Input (Data, Variable, Absolute, Buffered Bytes),
+    0x09, 0x22,        //   Usage (Track 2 Data),
+    0x95, 0x28,        //   Report Count (40),
+    0x82, 0x02, 0x01,  //   Input (Data, Variable, Absolute, Buffered Bytes),
+    0x09, 0x23,        //   Usage (Track 3 Data),
+    0x95, 0x6B,        //   Report Count (107),
+    0x82, 0x02, 0x01,  //   Input (Data, Variable, Absolute, Buffered Bytes),
+    0x85, 0x02,        //   Report ID (2),
+    0x95, 0x01,        //   Report Count (1),
+    0x09, 0x11,        //   Usage (Track 1 Length),
+    0xB1, 0x03,        //   Feature (Constant, Variable, Absolute),
+    0x09, 0x12,        //   Usage (Track 2 Length),
+    0xB1, 0x03,        //   Feature (Constant, Variable, Absolute),
+    0x09, 0x13,        //   Usage (Track 3 Length),
+    0xB1, 0x03,        //   Feature (Constant, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Decoded track data from a card swipe, empty tracks were not present or failed to decode
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct MsrTracks<'b> {
+    pub track_1: &'b [u8],
+    pub track_2: &'b [u8],
+    pub track_3: &'b [u8],
+}
+
+impl MsrTracks<'_> {
+    fn pack(&self) -> Result<[u8; MSR_REPORT_LEN], UsbHidError> {
+        let mut report = [0; MSR_REPORT_LEN];
+        report[0] = MSR_TRACK_DATA_REPORT_ID;
+
+        let mut offset = 4;
+        for (i, track) in [self.track_1, self.track_2, self.track_3]
+            .iter()
+            .enumerate()
+        {
+            let max = MSR_TRACK_LENGTHS[i];
+            let len = u8::try_from(track.len())
+                .ok()
+                .filter(|len| *len <= max)
+                .ok_or_else(|| {
+                    error!("MSR track {} too long", i + 1);
+                    UsbHidError::SerializationError
+                })?;
+            report[1 + i] = len;
+            report[offset..offset + track.len()].copy_from_slice(track);
+            offset += usize::from(max);
+        }
+
+        Ok(report)
+    }
+}
+
+/// Magnetic stripe reader
+///
+/// The track data report is longer than the endpoint packet size and is split across packets
+/// by the interface.
+pub struct MagneticStripeReader<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes256, OutNone, ReportSingle>,
+}
+
+impl<B: UsbBus> MagneticStripeReader<'_, B> {
+    /// Send a card swipe, returns [`UsbHidError::WouldBlock`] if the previous swipe is still
+    /// being sent
+    pub fn write_report(&mut self, tracks: &MsrTracks) -> Result<(), UsbHidError> {
+        self.interface
+            .write_report(&tracks.pack()?)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for MagneticStripeReader<'a, B> {
+    type I = Interface<'a, B, InBytes256, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {}
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        if report_id != MSR_TRACK_LENGTH_REPORT_ID {
+            return Err(UsbError::Unsupported);
+        }
+        let out = data.get_mut(..4).ok_or(UsbError::BufferOverflow)?;
+        out[0] = report_id;
+        out[1..].copy_from_slice(&MSR_TRACK_LENGTHS);
+        Ok(4)
+    }
+}
+
+pub struct MagneticStripeReaderConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes256, OutNone, ReportSingle>,
+}
+
+impl Default for MagneticStripeReaderConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::new(MSR_REPORT_DESCRIPTOR))
+                .description("Magnetic Stripe Reader")
+                .in_endpoint(1.millis()))
+            .without_out_endpoint()
+            .build(),
+        )
+    }
+}
+
+impl<'a> MagneticStripeReaderConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes256, OutNone, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for MagneticStripeReaderConfig<'a> {
+    type Allocated = MagneticStripeReader<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use crate::device::msr::MsrTracks;
+
+    #[test]
+    fn tracks_are_packed_at_fixed_offsets() {
+        let report = MsrTracks {
+            track_1: b"B4111111111111111^DOE/JOHN^2512",
+            track_2: b"4111111111111111=2512",
+            track_3: &[],
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(report[..4], [0x01, 31, 21, 0]);
+        assert_eq!(report[4..35], *b"B4111111111111111^DOE/JOHN^2512");
+        assert_eq!(report[83..104], *b"4111111111111111=2512");
+        assert!(report[104..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn long_track_is_rejected() {
+        let track_2 = [b'1'; 41];
+
+        assert!(MsrTracks {
+            track_2: &track_2,
+            ..Default::default()
+        }
+        .pack()
+        .is_err());
+    }
+}
@@ -0,0 +1,358 @@
+//!HID power device, battery or UPS status
+//!
+//! Appears as a battery in the host operating system power settings. See
+//! [Usage Tables for HID Power Devices](<https://www.usb.org/sites/default/files/pdcv11.pdf>)
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the present status input and feature reports
+pub const PRESENT_STATUS_REPORT_ID: u8 = 0x01;
+/// Report ID of the remaining capacity input and feature reports
+pub const REMAINING_CAPACITY_REPORT_ID: u8 = 0x02;
+/// Report ID of the run time to empty input and feature reports
+pub const RUN_TIME_TO_EMPTY_REPORT_ID: u8 = 0x03;
+/// Report ID of the capacity feature report
+pub const CAPACITY_REPORT_ID: u8 = 0x04;
+
+/// Capacities are reported in percent, run time to empty in seconds
+#[rustfmt::skip]
+pub const POWER_DEVICE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x84,        // Usage Page (Power Device),
+    0x09, 0x04,        // Usage (UPS),
+    0xA1, 0x01,        // Collection (Application),
+    0x09, 0x24,        //   Usage (Power Summary),
+    0xA1, 0x00,        //   Collection (Physical),
+    0x85, 0x01,        //     Report ID (1),
+    0x09, 0x02,        //     Usage (Present Status),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x05, 0x85,        //       Usage Page (Battery System),
+    0x15, 0x00,        //       Logical Minimum (0),
+    0x25, 0x01,        //       Logical Maximum (1),
+    0x75, 0x01,        //       Report Size (1),
+    0x95, 0x08,        //       Report Count (8),
+    0x09, 0x44,        //       Usage (Charging),
+    0x09, 0x45,        //       Usage (Discharging),
+    0x09, 0xD0,        //       Usage (AC Present),
+    0x09, 0xD1,        //       Usage (Battery Present),
+    0x09, 0x42,        //       Usage (Below Remaining Capacity Limit),
+    0x09, 0x43,        //       Usage (Remaining Time Limit Expired),
+    0x09, 0x4B,        //       Usage (Need Replacement),
+    0x09, 0x46,        //       Usage (Fully Charged),
+    0x81, 0x02,        //       Input (Data, Variable, Absolute),
+    0x09, 0x44,        //       Usage (Charging),
+    0x09, 0x45,        //       Usage (Discharging),
+    0x09, 0xD0,        //       Usage (AC Present),
+    0x09, 0xD1,        //       Usage (Battery Present),
+    0x09, 0x42,        //       Usage (Below Remaining Capacity Limit),
+    0x09, 0x43,        //       Usage (Remaining Time Limit Expired),
+    0x09, 0x4B,        //       Usage (Need Replacement),
+    0x09, 0x46,        //       Usage (Fully Charged),
+    0xB1, 0x02,        //       Feature (Data, Variable, Absolute),
+    0xC0,              //     End Collection
+    0x85, 0x02,        //     Report ID (2),
+    0x09, 0x66,        //     Usage (Remaining Capacity),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x64,        //     Logical Maximum (100),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x09, 0x66,        //     Usage (Remaining Capacity),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x85, 0x03,        //     Report ID (3),
+    0x09, 0x68,        //     Usage (Run Time To Empty),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F, //     Logical Maximum (2147483647),
+    0x75, 0x20,        //     Report Size (32),
+    0x66, 0x01, 0x10,  //     Unit (Seconds),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x09, 0x68,        //     Usage (Run Time To Empty),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x65, 0x00,        //     Unit (None),
+    0x85, 0x04,        //     Report ID (4),
+    0x09, 0x2C,        //     Usage (Capacity Mode),
+    0x09, 0x83,        //     Usage (Design Capacity),
+    0x09, 0x67,        //     Usage (Full Charge Capacity),
+    0x09, 0x8C,        //     Usage (Warning Capacity Limit),
+    0x09, 0x29,        //     Usage (Remaining Capacity Limit),
+    0x09, 0x8D,        //     Usage (Capacity Granularity 1),
+    0x09, 0x8E,        //     Usage (Capacity Granularity 2),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x64,        //     Logical Maximum (100),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x07,        //     Report Count (7),
+    0xB1, 0x03,        //     Feature (Constant, Variable, Absolute),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+const CAPACITY_MODE_PERCENT: u8 = 2;
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "1")]
+pub struct PresentStatus {
+    #[packed_field(bits = "7")]
+    pub charging: bool,
+    #[packed_field(bits = "6")]
+    pub discharging: bool,
+    #[packed_field(bits = "5")]
+    pub ac_present: bool,
+    #[packed_field(bits = "4")]
+    pub battery_present: bool,
+    #[packed_field(bits = "3")]
+    pub below_remaining_capacity_limit: bool,
+    #[packed_field(bits = "2")]
+    pub remaining_time_limit_expired: bool,
+    #[packed_field(bits = "1")]
+    pub need_replacement: bool,
+    #[packed_field(bits = "0")]
+    pub fully_charged: bool,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct BatteryStatus {
+    pub present_status: PresentStatus,
+    /// Percent, 0 to 100
+    pub remaining_capacity: u8,
+    /// Seconds
+    pub run_time_to_empty: u32,
+}
+
+/// Battery capacity limits in percent
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BatteryCapacity {
+    /// Capacity below which the host warns the user
+    pub warning_capacity_limit: u8,
+    /// Capacity below which the host shuts down
+    pub remaining_capacity_limit: u8,
+}
+
+impl Default for BatteryCapacity {
+    fn default() -> Self {
+        Self {
+            warning_capacity_limit: 20,
+            remaining_capacity_limit: 5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "7")]
+struct CapacityReport {
+    #[packed_field(bytes = "0")]
+    capacity_mode: u8,
+    #[packed_field(bytes = "1")]
+    design_capacity: u8,
+    #[packed_field(bytes = "2")]
+    full_charge_capacity: u8,
+    #[packed_field(bytes = "3")]
+    warning_capacity_limit: u8,
+    #[packed_field(bytes = "4")]
+    remaining_capacity_limit: u8,
+    #[packed_field(bytes = "5")]
+    capacity_granularity_1: u8,
+    #[packed_field(bytes = "6")]
+    capacity_granularity_2: u8,
+}
+
+impl From<BatteryCapacity> for CapacityReport {
+    fn from(capacity: BatteryCapacity) -> Self {
+        Self {
+            capacity_mode: CAPACITY_MODE_PERCENT,
+            design_capacity: 100,
+            full_charge_capacity: 100,
+            warning_capacity_limit: capacity.warning_capacity_limit,
+            remaining_capacity_limit: capacity.remaining_capacity_limit,
+            capacity_granularity_1: 1,
+            capacity_granularity_2: 1,
+        }
+    }
+}
+
+/// Battery or UPS power device
+///
+/// Status passed to [`PowerDevice::update`] is sent to the host as input reports from
+/// [`DeviceClass::tick`] and is also available to the host as feature reports.
+pub struct PowerDevice<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    capacity: BatteryCapacity,
+    status: BatteryStatus,
+    pending: [bool; 3],
+}
+
+impl<B: UsbBus> PowerDevice<'_, B> {
+    /// Set the battery status, changes are sent to the host on the next tick
+    pub fn update(&mut self, status: BatteryStatus) {
+        let status = BatteryStatus {
+            remaining_capacity: status.remaining_capacity.min(100),
+            run_time_to_empty: status.run_time_to_empty.min(i32::MAX as u32),
+            ..status
+        };
+
+        self.pending[0] |= status.present_status != self.status.present_status;
+        self.pending[1] |= status.remaining_capacity != self.status.remaining_capacity;
+        self.pending[2] |= status.run_time_to_empty != self.status.run_time_to_empty;
+        self.status = status;
+    }
+
+    #[must_use]
+    pub fn status(&self) -> BatteryStatus {
+        self.status
+    }
+
+    fn report(&self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        let len = match report_id {
+            PRESENT_STATUS_REPORT_ID => {
+                let out = data.get_mut(1..2).ok_or(UsbError::BufferOverflow)?;
+                self.status
+                    .present_status
+                    .pack_to_slice(out)
+                    .map_err(|_| UsbError::ParseError)?;
+                1
+            }
+            REMAINING_CAPACITY_REPORT_ID => {
+                *data.get_mut(1).ok_or(UsbError::BufferOverflow)? = self.status.remaining_capacity;
+                1
+            }
+            RUN_TIME_TO_EMPTY_REPORT_ID => {
+                data.get_mut(1..5)
+                    .ok_or(UsbError::BufferOverflow)?
+                    .copy_from_slice(&self.status.run_time_to_empty.to_le_bytes());
+                4
+            }
+            CAPACITY_REPORT_ID => {
+                let out = data.get_mut(1..8).ok_or(UsbError::BufferOverflow)?;
+                CapacityReport::from(self.capacity)
+                    .pack_to_slice(out)
+                    .map_err(|_| UsbError::ParseError)?;
+                7
+            }
+            _ => return Err(UsbError::Unsupported),
+        };
+        data[0] = report_id;
+        Ok(len + 1)
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for PowerDevice<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.pending = [true; 3];
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        let report_ids = [
+            PRESENT_STATUS_REPORT_ID,
+            REMAINING_CAPACITY_REPORT_ID,
+            RUN_TIME_TO_EMPTY_REPORT_ID,
+        ];
+        for (i, report_id) in report_ids.into_iter().enumerate() {
+            if !self.pending[i] {
+                continue;
+            }
+
+            let mut data = [0; 8];
+            let len = self.report(report_id, &mut data)?;
+            match self.interface.write_report(&data[..len]) {
+                Ok(_) => self.pending[i] = false,
+                Err(UsbError::WouldBlock) => return Ok(()),
+                Err(e) => return Err(UsbHidError::from(e)),
+            }
+        }
+        Ok(())
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        self.report(report_id, data)
+    }
+}
+
+pub struct PowerDeviceConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+    capacity: BatteryCapacity,
+}
+
+impl Default for PowerDeviceConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                POWER_DEVICE_REPORT_DESCRIPTOR
+            ))
+            .description("UPS")
+            .in_endpoint(100.millis()))
+            .without_out_endpoint()
+            .build(),
+            BatteryCapacity::default(),
+        )
+    }
+}
+
+impl<'a> PowerDeviceConfig<'a> {
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+        capacity: BatteryCapacity,
+    ) -> Self {
+        Self {
+            interface,
+            capacity,
+        }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for PowerDeviceConfig<'a> {
+    type Allocated = PowerDevice<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            capacity: self.capacity,
+            status: BatteryStatus::default(),
+            pending: [true; 3],
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::power::{BatteryCapacity, CapacityReport, PresentStatus};
+
+    #[test]
+    fn present_status_bit_order() {
+        let bytes = PresentStatus {
+            charging: true,
+            ac_present: true,
+            battery_present: true,
+            ..Default::default()
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(bytes, [0b0000_1101]);
+    }
+
+    #[test]
+    fn capacity_report_is_percent() {
+        let bytes = CapacityReport::from(BatteryCapacity::default())
+            .pack()
+            .unwrap();
+
+        assert_eq!(bytes, [2, 100, 100, 20, 5, 1, 1]);
+    }
+}
@@ -0,0 +1,294 @@
+//!HID human presence sensor
+//!
+//! Reports whether a person is in front of the device as a HID Sensors Usages human presence
+//! sensor, so the host can wake on approach and lock on leave. See
+//! [`sensor`](crate::device::sensor) for the host controlled sensor properties.
+use crate::descriptor::concat;
+use crate::device::sensor::{
+    Sensor, SensorEvent, SensorProperties, SensorState, SENSOR_PROPERTY_ITEMS,
+};
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the human presence sensor input and feature reports
+pub const HUMAN_PRESENCE_REPORT_ID: u8 = 0x01;
+
+/// Detection thresholds are in millimetres
+#[rustfmt::skip]
+pub const HUMAN_PRESENCE_REPORT_DESCRIPTOR: &[u8] = &concat::<
+    { SENSOR_PROPERTY_ITEMS.len() + 48 }
+>(&[
+    &[
+        0x05, 0x20,        // Usage Page (Sensors),
+        0x09, 0x01,        // Usage (Sensor),
+        0xA1, 0x01,        // Collection (Application),
+        0x85, 0x01,        //   Report ID (1),
+        0x09, 0x11,        //   Usage (Human Presence),
+        0xA1, 0x00,        //   Collection (Physical),
+    ],
+    SENSOR_PROPERTY_ITEMS,
+    &[
+        0x0A, 0xB2, 0x74,  //     Usage (Human Proximity Range Threshold Low),
+        0x0A, 0xB2, 0x64,  //     Usage (Human Proximity Range Threshold High),
+        0x15, 0x00,        //     Logical Minimum (0),
+        0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535),
+        0x75, 0x10,        //     Report Size (16),
+        0x95, 0x02,        //     Report Count (2),
+        0x55, 0x0D,        //     Unit Exponent (-3),
+        0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+        0x0A, 0xB1, 0x04,  //     Usage (Human Presence),
+        0x15, 0x00,        //     Logical Minimum (0),
+        0x25, 0x01,        //     Logical Maximum (1),
+        0x75, 0x08,        //     Report Size (8),
+        0x95, 0x01,        //     Report Count (1),
+        0x81, 0x02,        //     Input (Data, Variable, Absolute),
+        0xC0,              //   End Collection
+        0xC0,              // End Collection
+    ],
+]);
+
+/// Detection thresholds, set by the host with the sensor properties
+///
+/// A person should be reported present once they come within `detect_range` and absent once
+/// they move beyond `release_range`. The device firmware is responsible for applying these to
+/// its own measurements before calling [`HumanPresenceSensor::set_present`].
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "4")]
+pub struct PresenceThresholds {
+    /// Range in millimetres within which a person is detected
+    #[packed_field(bytes = "0..=1")]
+    pub detect_range: u16,
+    /// Range in millimetres beyond which a person is no longer detected
+    #[packed_field(bytes = "2..=3")]
+    pub release_range: u16,
+}
+
+impl Default for PresenceThresholds {
+    fn default() -> Self {
+        Self {
+            detect_range: 600,
+            release_range: 900,
+        }
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "3")]
+pub struct HumanPresenceReport {
+    #[packed_field(bytes = "0", ty = "enum")]
+    pub sensor_state: SensorState,
+    #[packed_field(bytes = "1", ty = "enum")]
+    pub event: SensorEvent,
+    #[packed_field(bits = "23")]
+    pub present: bool,
+}
+
+/// Human presence sensor
+///
+/// Changes passed to [`HumanPresenceSensor::set_present`] are sent to the host immediately from
+/// [`DeviceClass::tick`], which must be called every 1ms, and the current presence is repeated at
+/// the report interval requested by the host, while the reporting state allows it and the sensor
+/// is at full power.
+pub struct HumanPresenceSensor<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    sensor: Sensor<bool>,
+    thresholds: PresenceThresholds,
+    default_thresholds: PresenceThresholds,
+    default_report_interval: u32,
+}
+
+impl<B: UsbBus> HumanPresenceSensor<'_, B> {
+    /// Set whether a person is present
+    pub fn set_present(&mut self, present: bool) {
+        if self.sensor.sample != Some(present) {
+            self.sensor.sample = Some(present);
+            self.sensor.notify(SensorEvent::DataUpdated);
+        }
+    }
+
+    /// Set the sensor state, e.g. to report a sensor error to the host
+    pub fn set_sensor_state(&mut self, state: SensorState) {
+        self.sensor.set_sensor_state(state);
+    }
+
+    #[must_use]
+    pub fn properties(&self) -> SensorProperties {
+        self.sensor.properties
+    }
+
+    /// Detection thresholds currently requested by the host
+    #[must_use]
+    pub fn thresholds(&self) -> PresenceThresholds {
+        self.thresholds
+    }
+
+    fn write(&mut self, report: HumanPresenceReport) -> Result<(), UsbHidError> {
+        let mut data = [0; 4];
+        data[0] = HUMAN_PRESENCE_REPORT_ID;
+        report.pack_to_slice(&mut data[1..]).map_err(|_| {
+            error!("Error packing HumanPresenceReport");
+            UsbHidError::SerializationError
+        })?;
+        self.interface
+            .write_report(&data)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for HumanPresenceSensor<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.sensor.reset();
+        self.thresholds = self.default_thresholds;
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        let Some((event, present)) = self.sensor.tick() else {
+            return Ok(());
+        };
+
+        let report = HumanPresenceReport {
+            sensor_state: self.sensor.properties.sensor_state,
+            event,
+            present: present.unwrap_or_default(),
+        };
+
+        match self.write(report) {
+            Ok(()) => {
+                self.sensor.report_written(self.default_report_interval);
+                Ok(())
+            }
+            Err(UsbHidError::WouldBlock) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        if report_id != HUMAN_PRESENCE_REPORT_ID {
+            return Err(UsbError::Unsupported);
+        }
+        let out = data.get_mut(..12).ok_or(UsbError::BufferOverflow)?;
+        let len = self.sensor.get_properties_report(report_id, out)?;
+        self.thresholds
+            .pack_to_slice(&mut out[len..])
+            .map_err(|_| UsbError::ParseError)?;
+        Ok(12)
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        if report_id != HUMAN_PRESENCE_REPORT_ID {
+            return Err(UsbError::Unsupported);
+        }
+        let thresholds = data
+            .get(8..12)
+            .and_then(|d| PresenceThresholds::unpack_from_slice(d).ok())
+            .ok_or(UsbError::ParseError)?;
+        self.sensor.set_properties_report(report_id, data)?;
+
+        if self.thresholds != thresholds {
+            info!(
+                "Set presence thresholds: detect {}mm, release {}mm",
+                thresholds.detect_range, thresholds.release_range
+            );
+            self.thresholds = thresholds;
+            self.sensor.notify(SensorEvent::PropertyChanged);
+        }
+        Ok(())
+    }
+}
+
+pub struct HumanPresenceSensorConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+    thresholds: PresenceThresholds,
+    default_report_interval: u32,
+}
+
+impl Default for HumanPresenceSensorConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                HUMAN_PRESENCE_REPORT_DESCRIPTOR
+            ))
+            .description("Human Presence Sensor")
+            .in_endpoint(10.millis()))
+            .without_out_endpoint()
+            .build(),
+            PresenceThresholds::default(),
+            1000,
+        )
+    }
+}
+
+impl<'a> HumanPresenceSensorConfig<'a> {
+    /// `thresholds` are used until the host sets its own, `default_report_interval` in
+    /// milliseconds is used until the host sets a report interval
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+        thresholds: PresenceThresholds,
+        default_report_interval: u32,
+    ) -> Self {
+        Self {
+            interface,
+            thresholds,
+            default_report_interval,
+        }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for HumanPresenceSensorConfig<'a> {
+    type Allocated = HumanPresenceSensor<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            sensor: Sensor::default(),
+            thresholds: self.thresholds,
+            default_thresholds: self.thresholds,
+            default_report_interval: self.default_report_interval.max(1),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::presence::{HumanPresenceReport, PresenceThresholds};
+    use crate::device::sensor::{SensorEvent, SensorState};
+
+    #[test]
+    fn human_presence_report_layout() {
+        let bytes = HumanPresenceReport {
+            sensor_state: SensorState::Ready,
+            event: SensorEvent::DataUpdated,
+            present: true,
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(bytes, [0x01, 0x03, 0x01]);
+    }
+
+    #[test]
+    fn presence_thresholds_layout() {
+        let thresholds = PresenceThresholds::unpack(&[0x58, 0x02, 0x84, 0x03]).unwrap();
+
+        assert_eq!(thresholds.detect_range, 600);
+        assert_eq!(thresholds.release_range, 900);
+    }
+}
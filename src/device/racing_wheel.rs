@@ -0,0 +1,342 @@
+//!HID racing wheel with pedals, with optional force feedback
+use crate::descriptor::concat;
+use crate::device::force_feedback::{
+    ForceFeedbackEvent, ForceFeedbackState, PidOutputReport, PidStateReport, PID_REPORT_ITEMS,
+    PID_STATE_REPORT_ID,
+};
+use crate::interface::OutSize;
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+
+/// Report ID of the [`RacingWheelReport`] input report
+pub const RACING_WHEEL_INPUT_REPORT_ID: u8 = 0x01;
+
+/// Sixteen buttons, steering on the Generic Desktop X axis so that force feedback effects apply
+/// to it, and Simulation Controls accelerator, brake and clutch axes
+#[rustfmt::skip]
+const RACING_WHEEL_INPUT_ITEMS: &[u8] = &[
+    0x05, 0x01,                     // Usage Page (Generic Desktop),
+    0x09, 0x04,                     // Usage (Joystick),
+    0xA1, 0x01,                     // Collection (Application),
+    0x85, 0x01,                     //   Report ID (1),
+    0x05, 0x09,                     //   Usage Page (Button),
+    0x19, 0x01,                     //   Usage Minimum (1),
+    0x29, 0x10,                     //   Usage Maximum (16),
+    0x15, 0x00,                     //   Logical Minimum (0),
+    0x25, 0x01,                     //   Logical Maximum (1),
+    0x75, 0x01,                     //   Report Size (1),
+    0x95, 0x10,                     //   Report Count (16),
+    0x81, 0x02,                     //   Input (Data, Variable, Absolute),
+    0x05, 0x01,                     //   Usage Page (Generic Desktop),
+    0x09, 0x30,                     //   Usage (X),
+    0x16, 0x01, 0x80,               //   Logical Minimum (-32767),
+    0x26, 0xFF, 0x7F,               //   Logical Maximum (32767),
+    0x75, 0x10,                     //   Report Size (16),
+    0x95, 0x01,                     //   Report Count (1),
+    0x81, 0x02,                     //   Input (Data, Variable, Absolute),
+    0x05, 0x02,                     //   Usage Page (Simulation Controls),
+    0x09, 0xC4,                     //   Usage (Accelerator),
+    0x09, 0xC5,                     //   Usage (Brake),
+    0x09, 0xC6,                     //   Usage (Clutch),
+    0x15, 0x00,                     //   Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0x00, 0x00,   //   Logical Maximum (65535),
+    0x95, 0x03,                     //   Report Count (3),
+    0x81, 0x02,                     //   Input (Data, Variable, Absolute),
+];
+
+///Racing wheel report descriptor - a single [`RacingWheelReport`] input report
+#[rustfmt::skip]
+pub const RACING_WHEEL_REPORT_DESCRIPTOR: &[u8] = &concat::<
+    { RACING_WHEEL_INPUT_ITEMS.len() + 1 }
+>(&[
+    RACING_WHEEL_INPUT_ITEMS,
+    &[0xC0],           // End Collection
+]);
+
+/// Racing wheel with force feedback
+///
+/// * Input report 1 - [`RacingWheelReport`]
+/// * Input report 2 - [`PidStateReport`]
+/// * Output reports 0x11-0x18 - effect parameters, effect operations and device control
+/// * Feature reports 0x21-0x23 - effect allocation and pool information
+///
+/// The Physical Interface Device reports are those of
+/// [`FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR`](crate::device::force_feedback::FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR),
+/// effects on the X axis apply to the steering
+#[rustfmt::skip]
+pub const FORCE_FEEDBACK_RACING_WHEEL_REPORT_DESCRIPTOR: &[u8] = &concat::<
+    { RACING_WHEEL_INPUT_ITEMS.len() + PID_REPORT_ITEMS.len() + 1 }
+>(&[
+    RACING_WHEEL_INPUT_ITEMS,
+    PID_REPORT_ITEMS,
+    &[0xC0],           // End Collection
+]);
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "10")]
+pub struct RacingWheelReport {
+    /// Buttons 1 to 16, button 1 in the least significant bit
+    #[packed_field]
+    pub buttons: u16,
+    /// Steering, -32767 (full left) to 32767 (full right)
+    #[packed_field]
+    pub steering: i16,
+    #[packed_field]
+    pub accelerator: u16,
+    #[packed_field]
+    pub brake: u16,
+    #[packed_field]
+    pub clutch: u16,
+}
+
+fn write_with_id<B: UsbBus, O: OutSize>(
+    interface: &mut Interface<'_, B, InBytes16, O, ReportSingle>,
+    report_id: u8,
+    data: &[u8],
+) -> Result<(), UsbHidError> {
+    let mut buffer = [0u8; 16];
+    buffer[0] = report_id;
+    buffer[1..=data.len()].copy_from_slice(data);
+    interface
+        .write_report(&buffer[..=data.len()])
+        .map(|_| ())
+        .map_err(UsbHidError::from)
+}
+
+fn pack_report(report: &RacingWheelReport) -> Result<[u8; 10], UsbHidError> {
+    report.pack().map_err(|_| {
+        error!("Error packing RacingWheelReport");
+        UsbHidError::SerializationError
+    })
+}
+
+/// Racing wheel with pedals
+pub struct RacingWheel<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes16, OutNone, ReportSingle>,
+}
+
+impl<B: UsbBus> RacingWheel<'_, B> {
+    pub fn write_report(&mut self, report: &RacingWheelReport) -> Result<(), UsbHidError> {
+        let data = pack_report(report)?;
+        write_with_id(&mut self.interface, RACING_WHEEL_INPUT_REPORT_ID, &data)
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for RacingWheel<'a, B> {
+    type I = Interface<'a, B, InBytes16, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {}
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+}
+
+pub struct RacingWheelConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes16, OutNone, ReportSingle>,
+}
+
+impl Default for RacingWheelConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(
+                unwrap!(InterfaceBuilder::new(RACING_WHEEL_REPORT_DESCRIPTOR))
+                    .description("Racing Wheel")
+                    .in_endpoint(1.millis())
+            )
+            .without_out_endpoint()
+            .build(),
+        )
+    }
+}
+
+impl<'a> RacingWheelConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes16, OutNone, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for RacingWheelConfig<'a> {
+    type Allocated = RacingWheel<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+        })
+    }
+}
+
+/// Racing wheel with pedals and force feedback
+///
+/// Effects are allocated and updated by the host as for
+/// [`ForceFeedbackJoystick`](crate::device::force_feedback::ForceFeedbackJoystick).
+/// [`ForceFeedbackRacingWheel::read_report`] should be polled regularly, it receives output
+/// reports one at a time so none are dropped. The application then
+/// renders the playing effects on the steering from [`ForceFeedbackRacingWheel::force_feedback`].
+pub struct ForceFeedbackRacingWheel<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes16, OutBytes32, ReportSingle>,
+    state: ForceFeedbackState,
+    received: ReceivedReport<PidOutputReport, 32>,
+}
+
+impl<B: UsbBus> ForceFeedbackRacingWheel<'_, B> {
+    pub fn write_report(&mut self, report: &RacingWheelReport) -> Result<(), UsbHidError> {
+        let data = pack_report(report)?;
+        write_with_id(&mut self.interface, RACING_WHEEL_INPUT_REPORT_ID, &data)
+    }
+
+    pub fn write_pid_state(&mut self, report: &PidStateReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|_| {
+            error!("Error packing PidStateReport");
+            UsbHidError::SerializationError
+        })?;
+        write_with_id(&mut self.interface, PID_STATE_REPORT_ID, &data)
+    }
+
+    /// Read and apply an output report from the host, see [`OutputReports::read_output_report`]
+    pub fn read_report(&mut self) -> usb_device::Result<ForceFeedbackEvent> {
+        let report = self.read_output_report()?;
+        self.state.apply_output_report(report.data())
+    }
+
+    #[must_use]
+    pub fn force_feedback(&self) -> &ForceFeedbackState {
+        &self.state
+    }
+}
+
+impl<B: UsbBus> OutputReports for ForceFeedbackRacingWheel<'_, B> {
+    type Report = PidOutputReport;
+
+    fn read_output_report(&mut self) -> usb_device::Result<PidOutputReport> {
+        self.received
+            .receive_next(|data| self.interface.read_report(data))?;
+        self.received.read()
+    }
+
+    fn output_report(&self) -> PidOutputReport {
+        self.received.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<PidOutputReport>> {
+        self.received
+            .receive_next(|data| self.interface.read_report(data))?;
+        Ok(self.received.changed())
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for ForceFeedbackRacingWheel<'a, B> {
+    type I = Interface<'a, B, InBytes16, OutBytes32, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.state = ForceFeedbackState::default();
+        self.received = ReceivedReport::default();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        self.state.get_feature_report(report_id, data)
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        self.state.set_feature_report(report_id, data)
+    }
+}
+
+pub struct ForceFeedbackRacingWheelConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes16, OutBytes32, ReportSingle>,
+}
+
+impl Default for ForceFeedbackRacingWheelConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                FORCE_FEEDBACK_RACING_WHEEL_REPORT_DESCRIPTOR
+            ))
+            .description("Force Feedback Racing Wheel")
+            .in_endpoint(1.millis()))
+            .with_out_endpoint(1.millis()))
+            .build(),
+        )
+    }
+}
+
+impl<'a> ForceFeedbackRacingWheelConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes16, OutBytes32, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for ForceFeedbackRacingWheelConfig<'a> {
+    type Allocated = ForceFeedbackRacingWheel<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            state: ForceFeedbackState::default(),
+            received: ReceivedReport::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::force_feedback::FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR;
+    use crate::device::racing_wheel::{
+        RacingWheelReport, FORCE_FEEDBACK_RACING_WHEEL_REPORT_DESCRIPTOR,
+        RACING_WHEEL_REPORT_DESCRIPTOR,
+    };
+
+    #[test]
+    fn report_layout() {
+        let report = RacingWheelReport {
+            buttons: 0x8001,
+            steering: -2,
+            accelerator: 0x1234,
+            brake: 0,
+            clutch: u16::MAX,
+        };
+
+        assert_eq!(
+            report.pack().unwrap(),
+            [0x01, 0x80, 0xFE, 0xFF, 0x34, 0x12, 0x00, 0x00, 0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn force_feedback_descriptor_shares_pid_reports() {
+        let wheel_input_len = RACING_WHEEL_REPORT_DESCRIPTOR.len() - 1;
+        let joystick_input_len = FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR.len()
+            - (FORCE_FEEDBACK_RACING_WHEEL_REPORT_DESCRIPTOR.len() - wheel_input_len);
+
+        assert_eq!(
+            FORCE_FEEDBACK_RACING_WHEEL_REPORT_DESCRIPTOR[..wheel_input_len],
+            RACING_WHEEL_REPORT_DESCRIPTOR[..wheel_input_len]
+        );
+        assert_eq!(
+            FORCE_FEEDBACK_RACING_WHEEL_REPORT_DESCRIPTOR[wheel_input_len..],
+            FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR[joystick_input_len..]
+        );
+    }
+}
@@ -0,0 +1,450 @@
+//!Windows radial controller, such as the Surface Dial, with haptic feedback
+//!
+//! See [Radial controller protocol implementation](<https://learn.microsoft.com/en-us/windows-hardware/design/component-guidelines/radial-controller-protocol-implementation>)
+//! and [Haptic Pen implementation](<https://learn.microsoft.com/en-us/windows-hardware/design/component-guidelines/haptic-pen-implementation>)
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the [`RadialControllerReport`] input report
+pub const RADIAL_CONTROLLER_INPUT_REPORT_ID: u8 = 0x01;
+/// Report ID of the haptic manual trigger output report
+pub const RADIAL_CONTROLLER_HAPTIC_OUTPUT_REPORT_ID: u8 = 0x02;
+/// Report ID of the haptic configuration feature report
+pub const RADIAL_CONTROLLER_HAPTIC_FEATURE_REPORT_ID: u8 = 0x03;
+
+/// Radial controller report descriptor
+///
+/// * Input report 1 - button and dial rotation, see [`RadialControllerReport`]
+/// * Output report 2 - haptic manual trigger, see [`HapticTrigger`]
+/// * Feature report 3 - supported waveforms and durations, auto trigger waveform and intensity
+///
+/// Supported waveforms are [`HapticWaveform::Click`] and [`HapticWaveform::BuzzContinuous`]
+#[rustfmt::skip]
+pub const RADIAL_CONTROLLER_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x0E,        // Usage (System Multi-Axis Controller),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x05, 0x0D,        //   Usage Page (Digitizers),
+    0x09, 0x21,        //   Usage (Puck),
+    0xA1, 0x00,        //   Collection (Physical),
+    0x05, 0x09,        //     Usage Page (Button),
+    0x09, 0x01,        //     Usage (Button 1),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x75, 0x07,        //     Report Size (7),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x37,        //     Usage (Dial),
+    0x16, 0xF0, 0xF1,  //     Logical Minimum (-3600),
+    0x26, 0x10, 0x0E,  //     Logical Maximum (3600),
+    0x36, 0xF0, 0xF1,  //     Physical Minimum (-3600),
+    0x46, 0x10, 0x0E,  //     Physical Maximum (3600),
+    0x65, 0x14,        //     Unit (Degrees),
+    0x55, 0x0F,        //     Unit Exponent (-1),
+    0x75, 0x10,        //     Report Size (16),
+    0x81, 0x06,        //     Input (Data, Variable, Relative),
+    0x35, 0x00,        //     Physical Minimum (0),
+    0x45, 0x00,        //     Physical Maximum (0),
+    0x65, 0x00,        //     Unit (None),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0xC0,              //   End Collection
+    0x05, 0x0E,        //   Usage Page (Haptics),
+    0x09, 0x01,        //   Usage (Simple Haptic Controller),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x02,        //     Report ID (2),
+    0x09, 0x21,        //     Usage (Manual Trigger),
+    0x16, 0x00, 0x10,  //     Logical Minimum (4096),
+    0x26, 0x07, 0x10,  //     Logical Maximum (4103),
+    0x75, 0x10,        //     Report Size (16),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x23,        //     Usage (Intensity),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x64,        //     Logical Maximum (100),
+    0x75, 0x08,        //     Report Size (8),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x24,        //     Usage (Repeat Count),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x09, 0x25,        //     Usage (Retrigger Period),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535),
+    0x66, 0x03, 0x10,  //     Unit (Seconds),
+    0x55, 0x0D,        //     Unit Exponent (-3),
+    0x75, 0x10,        //     Report Size (16),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x65, 0x00,        //     Unit (None),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0x85, 0x03,        //     Report ID (3),
+    0x09, 0x10,        //     Usage (Waveform List),
+    0xA1, 0x04,        //     Collection (Named Array),
+    0x05, 0x0A,        //       Usage Page (Ordinal),
+    0x09, 0x03,        //       Usage (Instance 3),
+    0x09, 0x04,        //       Usage (Instance 4),
+    0x16, 0x03, 0x10,  //       Logical Minimum (4099),
+    0x26, 0x04, 0x10,  //       Logical Maximum (4100),
+    0x95, 0x02,        //       Report Count (2),
+    0xB1, 0x03,        //       Feature (Constant, Variable, Absolute),
+    0xC0,              //     End Collection
+    0x05, 0x0E,        //     Usage Page (Haptics),
+    0x09, 0x11,        //     Usage (Duration List),
+    0xA1, 0x04,        //     Collection (Named Array),
+    0x05, 0x0A,        //       Usage Page (Ordinal),
+    0x09, 0x03,        //       Usage (Instance 3),
+    0x09, 0x04,        //       Usage (Instance 4),
+    0x15, 0x00,        //       Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //       Logical Maximum (65535),
+    0x66, 0x03, 0x10,  //       Unit (Seconds),
+    0x55, 0x0D,        //       Unit Exponent (-3),
+    0xB1, 0x03,        //       Feature (Constant, Variable, Absolute),
+    0x65, 0x00,        //       Unit (None),
+    0x55, 0x00,        //       Unit Exponent (0),
+    0xC0,              //     End Collection
+    0x05, 0x0E,        //     Usage Page (Haptics),
+    0x09, 0x20,        //     Usage (Auto Trigger),
+    0x16, 0x00, 0x10,  //     Logical Minimum (4096),
+    0x26, 0x07, 0x10,  //     Logical Maximum (4103),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x22,        //     Usage (Auto Trigger Associated Control),
+    0x17, 0x37, 0x00, 0x01, 0x00, //     Logical Minimum (65591),
+    0x27, 0x37, 0x00, 0x01, 0x00, //     Logical Maximum (65591),
+    0x75, 0x20,        //     Report Size (32),
+    0xB1, 0x03,        //     Feature (Constant, Variable, Absolute),
+    0x09, 0x23,        //     Usage (Intensity),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x64,        //     Logical Maximum (100),
+    0x75, 0x08,        //     Report Size (8),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x28,        //     Usage (Waveform Cutoff Time),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535),
+    0x66, 0x03, 0x10,  //     Unit (Seconds),
+    0x55, 0x0D,        //     Unit Exponent (-3),
+    0x75, 0x10,        //     Report Size (16),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x65, 0x00,        //     Unit (None),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+/// Simple haptic controller waveforms, Haptics usage page (0x0E)
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, TryFromPrimitive, IntoPrimitive)]
+#[repr(u16)]
+pub enum HapticWaveform {
+    #[default]
+    None = 0x1001,
+    Stop = 0x1002,
+    Click = 0x1003,
+    BuzzContinuous = 0x1004,
+    RumbleContinuous = 0x1005,
+    Press = 0x1006,
+    Release = 0x1007,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "3")]
+pub struct RadialControllerReport {
+    #[packed_field(bits = "7")]
+    pub button: bool,
+    /// Relative rotation in tenths of a degree, -3600 to 3600, clockwise positive
+    #[packed_field(bytes = "1..=2")]
+    pub dial: i16,
+}
+
+/// Haptic feedback requested by the host
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct HapticTrigger {
+    pub waveform: HapticWaveform,
+    /// Intensity, 0 to 100 percent
+    pub intensity: u8,
+    /// Number of times to repeat the waveform after the first
+    pub repeat_count: u8,
+    /// Time between repeats in milliseconds
+    pub retrigger_period: u16,
+}
+
+impl OutputReport for HapticTrigger {
+    /// Parse the haptic output report, including its report ID
+    fn parse(data: &[u8]) -> usb_device::Result<Self> {
+        match data.split_first() {
+            Some((&RADIAL_CONTROLLER_HAPTIC_OUTPUT_REPORT_ID, report)) => {
+                let report = report
+                    .get(..6)
+                    .and_then(|r| HapticOutputReport::unpack_from_slice(r).ok())
+                    .ok_or(UsbError::ParseError)?;
+                Ok(Self {
+                    waveform: HapticWaveform::try_from(report.manual_trigger)
+                        .map_err(|_| UsbError::ParseError)?,
+                    intensity: report.intensity,
+                    repeat_count: report.repeat_count,
+                    retrigger_period: report.retrigger_period,
+                })
+            }
+            _ => Err(UsbError::ParseError),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "6")]
+struct HapticOutputReport {
+    #[packed_field(bytes = "0..=1")]
+    manual_trigger: u16,
+    #[packed_field(bytes = "2")]
+    intensity: u8,
+    #[packed_field(bytes = "3")]
+    repeat_count: u8,
+    #[packed_field(bytes = "4..=5")]
+    retrigger_period: u16,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "17")]
+struct HapticFeatureReport {
+    #[packed_field(bytes = "0..=1")]
+    click_waveform: u16,
+    #[packed_field(bytes = "2..=3")]
+    buzz_waveform: u16,
+    #[packed_field(bytes = "4..=5")]
+    click_duration: u16,
+    #[packed_field(bytes = "6..=7")]
+    buzz_duration: u16,
+    #[packed_field(bytes = "8..=9")]
+    auto_trigger: u16,
+    #[packed_field(bytes = "10..=13")]
+    auto_trigger_associated_control: u32,
+    #[packed_field(bytes = "14")]
+    intensity: u8,
+    #[packed_field(bytes = "15..=16")]
+    waveform_cutoff_time: u16,
+}
+
+/// Generic Desktop Dial usage, the control associated with the auto trigger waveform
+const AUTO_TRIGGER_ASSOCIATED_CONTROL: u32 = 0x0001_0037;
+
+/// Radial controller with haptic feedback
+///
+/// Haptic feedback is signalled in two ways:
+/// * Manually triggered waveforms are received as output reports via
+///   [`RadialController::read_report`], one at a time as they're read, so none are dropped
+/// * The host configures a waveform to be played by the device on each detent of the dial,
+///   [`RadialController::auto_trigger`]
+pub struct RadialController<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutBytes8, ReportSingle>,
+    click_duration: u16,
+    auto_trigger: HapticWaveform,
+    auto_trigger_intensity: u8,
+    waveform_cutoff_time: u16,
+    haptic: ReceivedReport<HapticTrigger, 8>,
+}
+
+impl<B: UsbBus> RadialController<'_, B> {
+    pub fn write_report(&mut self, report: &RadialControllerReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|_| {
+            error!("Error packing RadialControllerReport");
+            UsbHidError::SerializationError
+        })?;
+
+        let mut buffer = [0u8; 4];
+        buffer[0] = RADIAL_CONTROLLER_INPUT_REPORT_ID;
+        buffer[1..].copy_from_slice(&data);
+
+        self.interface
+            .write_report(&buffer)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+
+    /// Read a manually triggered haptic waveform from the host, see
+    /// [`OutputReports::read_output_report`]
+    pub fn read_report(&mut self) -> usb_device::Result<HapticTrigger> {
+        self.read_output_report()
+    }
+
+    /// Waveform to play on each detent of the dial, as configured by the host
+    #[must_use]
+    pub fn auto_trigger(&self) -> HapticWaveform {
+        self.auto_trigger
+    }
+
+    /// Intensity of the auto trigger waveform, 0 to 100 percent
+    #[must_use]
+    pub fn auto_trigger_intensity(&self) -> u8 {
+        self.auto_trigger_intensity
+    }
+
+    /// Maximum time in milliseconds to play a continuous waveform
+    #[must_use]
+    pub fn waveform_cutoff_time(&self) -> u16 {
+        self.waveform_cutoff_time
+    }
+
+    fn haptic_feature_report(&self) -> HapticFeatureReport {
+        HapticFeatureReport {
+            click_waveform: HapticWaveform::Click.into(),
+            buzz_waveform: HapticWaveform::BuzzContinuous.into(),
+            click_duration: self.click_duration,
+            buzz_duration: 0,
+            auto_trigger: self.auto_trigger.into(),
+            auto_trigger_associated_control: AUTO_TRIGGER_ASSOCIATED_CONTROL,
+            intensity: self.auto_trigger_intensity,
+            waveform_cutoff_time: self.waveform_cutoff_time,
+        }
+    }
+}
+
+impl<B: UsbBus> OutputReports for RadialController<'_, B> {
+    type Report = HapticTrigger;
+
+    fn read_output_report(&mut self) -> usb_device::Result<HapticTrigger> {
+        self.haptic
+            .receive_next(|data| self.interface.read_report(data))?;
+        self.haptic.read()
+    }
+
+    fn output_report(&self) -> HapticTrigger {
+        self.haptic.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<HapticTrigger>> {
+        self.haptic
+            .receive_next(|data| self.interface.read_report(data))?;
+        Ok(self.haptic.changed())
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for RadialController<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutBytes8, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.auto_trigger = HapticWaveform::Click;
+        self.auto_trigger_intensity = 100;
+        self.haptic = ReceivedReport::default();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        if report_id != RADIAL_CONTROLLER_HAPTIC_FEATURE_REPORT_ID {
+            return Err(UsbError::Unsupported);
+        }
+        let report = self
+            .haptic_feature_report()
+            .pack()
+            .map_err(|_| UsbError::ParseError)?;
+
+        let out = data
+            .get_mut(..=report.len())
+            .ok_or(UsbError::BufferOverflow)?;
+        out[0] = report_id;
+        out[1..].copy_from_slice(&report);
+        Ok(out.len())
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        if report_id != RADIAL_CONTROLLER_HAPTIC_FEATURE_REPORT_ID {
+            return Err(UsbError::Unsupported);
+        }
+        let report = data
+            .get(1..=17)
+            .and_then(|r| HapticFeatureReport::unpack_from_slice(r).ok())
+            .ok_or(UsbError::ParseError)?;
+
+        self.auto_trigger =
+            HapticWaveform::try_from(report.auto_trigger).map_err(|_| UsbError::ParseError)?;
+        self.auto_trigger_intensity = report.intensity.min(100);
+        self.waveform_cutoff_time = report.waveform_cutoff_time;
+        info!(
+            "Set haptic auto trigger {:?}, intensity {}",
+            self.auto_trigger, self.auto_trigger_intensity
+        );
+        Ok(())
+    }
+}
+
+pub struct RadialControllerConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutBytes8, ReportSingle>,
+    click_duration: u16,
+}
+
+impl Default for RadialControllerConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                RADIAL_CONTROLLER_REPORT_DESCRIPTOR
+            ))
+            .description("Radial Controller")
+            .in_endpoint(10.millis()))
+            .with_out_endpoint(10.millis()))
+            .build(),
+            10,
+        )
+    }
+}
+
+impl<'a> RadialControllerConfig<'a> {
+    /// * `click_duration` - duration of the [`HapticWaveform::Click`] waveform in milliseconds
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes8, OutBytes8, ReportSingle>,
+        click_duration: u16,
+    ) -> Self {
+        Self {
+            interface,
+            click_duration,
+        }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for RadialControllerConfig<'a> {
+    type Allocated = RadialController<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            click_duration: self.click_duration,
+            auto_trigger: HapticWaveform::Click,
+            auto_trigger_intensity: 100,
+            waveform_cutoff_time: 0,
+            haptic: ReceivedReport::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::radial_controller::RadialControllerReport;
+
+    #[test]
+    fn radial_controller_report_layout() {
+        let bytes = RadialControllerReport {
+            button: true,
+            dial: -10,
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(bytes, [0x01, 0xF6, 0xFF]);
+    }
+}
@@ -0,0 +1,342 @@
+//! Vendor defined raw HID device with a generated report descriptor
+use crate::usb_class::prelude::*;
+use fugit::{ExtU32, MillisDurationU32};
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Largest input or output report of a raw HID device
+pub const RAW_HID_MAX_REPORT_LEN: u8 = 64;
+
+/// Usage page, usage and report lengths of a raw HID device
+///
+/// Reports have no report ID. An `out_len` of zero omits the output report.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RawHidLayout {
+    /// Vendor defined usage page, 0xFF00 to 0xFFFF
+    pub usage_page: u16,
+    pub usage: u16,
+    pub in_len: u8,
+    pub out_len: u8,
+}
+
+/// Default [`RawHidLayout`] - usage page 0xFF00, usage 0x01, 64 byte input and output reports
+pub const DEFAULT_RAW_HID_LAYOUT: RawHidLayout = RawHidLayout {
+    usage_page: 0xFF00,
+    usage: 0x01,
+    in_len: RAW_HID_MAX_REPORT_LEN,
+    out_len: RAW_HID_MAX_REPORT_LEN,
+};
+
+/// Report descriptor for a [`RawHidLayout`], generated at compile time
+///
+/// ```
+/// use usbd_human_interface_device::device::raw::*;
+///
+/// static CHANNEL: RawHidDescriptor = RawHidDescriptor::new(RawHidLayout {
+///     usage_page: 0xFF60,
+///     usage: 0x61,
+///     in_len: 32,
+///     out_len: 32,
+/// });
+///
+/// let config = RawHidConfig::from_descriptor(&CHANNEL);
+/// ```
+pub struct RawHidDescriptor {
+    layout: RawHidLayout,
+    descriptor: [u8; 40],
+    len: usize,
+}
+
+impl RawHidDescriptor {
+    /// Generate the report descriptor for `layout`
+    ///
+    /// Panics, at compile time if used in a const context, if `layout` has a usage page outside
+    /// of the vendor defined range or report lengths greater than [`RAW_HID_MAX_REPORT_LEN`]
+    #[must_use]
+    #[rustfmt::skip]
+    pub const fn new(layout: RawHidLayout) -> Self {
+        core::assert!(layout.usage_page >= 0xFF00, "usage page must be vendor defined");
+        core::assert!(layout.in_len > 0, "input report must not be empty");
+        core::assert!(layout.in_len <= RAW_HID_MAX_REPORT_LEN, "input report too long");
+        core::assert!(layout.out_len <= RAW_HID_MAX_REPORT_LEN, "output report too long");
+
+        let mut d = Self {
+            layout,
+            descriptor: [0; 40],
+            len: 0,
+        };
+
+        let [page_lo, page_hi] = layout.usage_page.to_le_bytes();
+        let [usage_lo, usage_hi] = layout.usage.to_le_bytes();
+        d.push(&[
+            0x06, page_lo, page_hi,     // Usage Page (usage_page),
+            0x0A, usage_lo, usage_hi,   // Usage (usage),
+            0xA1, 0x01,                 // Collection (Application),
+            0x09, 0x01,                 //   Usage (1),
+            0x15, 0x00,                 //   Logical Minimum (0),
+            0x26, 0xFF, 0x00,           //   Logical Maximum (255),
+            0x75, 0x08,                 //   Report Size (8),
+            0x95, layout.in_len,        //   Report Count (in_len),
+            0x81, 0x02,                 //   Input (Data, Variable, Absolute),
+        ]);
+
+        if layout.out_len > 0 {
+            d.push(&[
+                0x09, 0x02,             //   Usage (2),
+                0x95, layout.out_len,   //   Report Count (out_len),
+                0x91, 0x02,             //   Output (Data, Variable, Absolute),
+            ]);
+        }
+
+        d.push(&[
+            0xC0,                       // End Collection
+        ]);
+
+        d
+    }
+
+    const fn push(&mut self, items: &[u8]) {
+        let mut i = 0;
+        while i < items.len() {
+            self.descriptor[self.len] = items[i];
+            self.len += 1;
+            i += 1;
+        }
+    }
+
+    #[must_use]
+    pub const fn layout(&self) -> RawHidLayout {
+        self.layout
+    }
+
+    #[must_use]
+    pub fn descriptor(&self) -> &[u8] {
+        &self.descriptor[..self.len]
+    }
+}
+
+/// Report descriptor for [`DEFAULT_RAW_HID_LAYOUT`]
+pub static DEFAULT_RAW_HID_DESCRIPTOR: RawHidDescriptor =
+    RawHidDescriptor::new(DEFAULT_RAW_HID_LAYOUT);
+
+/// Output report received by a [`RawHid`]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RawHidReport {
+    data: [u8; RAW_HID_MAX_REPORT_LEN as usize],
+    len: u8,
+}
+
+impl RawHidReport {
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data[..usize::from(self.len)]
+    }
+}
+
+impl Default for RawHidReport {
+    fn default() -> Self {
+        Self {
+            data: [0; RAW_HID_MAX_REPORT_LEN as usize],
+            len: 0,
+        }
+    }
+}
+
+impl OutputReport for RawHidReport {
+    fn parse(data: &[u8]) -> usb_device::Result<Self> {
+        let mut report = Self::default();
+        report
+            .data
+            .get_mut(..data.len())
+            .ok_or(UsbError::ParseError)?
+            .copy_from_slice(data);
+        report.len = u8::try_from(data.len()).map_err(|_| UsbError::ParseError)?;
+        Ok(report)
+    }
+}
+
+/// Raw HID device for vendor defined data exchange with the host
+///
+/// Output reports are received one at a time as they're read, so none are dropped. See
+/// [`RawHidDescriptor`]
+pub struct RawHid<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes64, OutBytes64, ReportSingle>,
+    layout: RawHidLayout,
+    received: ReceivedReport<RawHidReport, { RAW_HID_MAX_REPORT_LEN as usize }>,
+}
+
+impl<B: UsbBus> RawHid<'_, B> {
+    /// Write an input report, `data` shorter than [`RawHidLayout::in_len`] is padded with zeros
+    pub fn write_report(&mut self, data: &[u8]) -> Result<(), UsbHidError> {
+        let len = usize::from(self.layout.in_len);
+        if data.len() > len {
+            error!("Raw HID report too long");
+            return Err(UsbHidError::SerializationError);
+        }
+
+        let mut report = [0; RAW_HID_MAX_REPORT_LEN as usize];
+        report[..data.len()].copy_from_slice(data);
+        self.interface
+            .write_report(&report[..len])
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+
+    /// Read an output report into `data`, returning the number of bytes read, see
+    /// [`OutputReports::read_output_report`]
+    pub fn read_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize> {
+        let report = self.read_output_report()?;
+        let out = data
+            .get_mut(..report.data().len())
+            .ok_or(UsbError::BufferOverflow)?;
+        out.copy_from_slice(report.data());
+        Ok(out.len())
+    }
+
+    #[must_use]
+    pub fn layout(&self) -> RawHidLayout {
+        self.layout
+    }
+}
+
+impl<B: UsbBus> OutputReports for RawHid<'_, B> {
+    type Report = RawHidReport;
+
+    fn read_output_report(&mut self) -> usb_device::Result<RawHidReport> {
+        self.received
+            .receive_next(|data| self.interface.read_report(data))?;
+        self.received.read()
+    }
+
+    fn output_report(&self) -> RawHidReport {
+        self.received.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<RawHidReport>> {
+        self.received
+            .receive_next(|data| self.interface.read_report(data))?;
+        Ok(self.received.changed())
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for RawHid<'a, B> {
+    type I = Interface<'a, B, InBytes64, OutBytes64, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.received = ReceivedReport::default();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+}
+
+pub struct RawHidConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes64, OutBytes64, ReportSingle>,
+    layout: RawHidLayout,
+}
+
+impl Default for RawHidConfig<'_> {
+    fn default() -> Self {
+        Self::from_descriptor(&DEFAULT_RAW_HID_DESCRIPTOR)
+    }
+}
+
+impl<'a> RawHidConfig<'a> {
+    /// `interface` must use a report descriptor matching `layout`, see [`RawHidDescriptor`]
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes64, OutBytes64, ReportSingle>,
+        layout: RawHidLayout,
+    ) -> Self {
+        Self { interface, layout }
+    }
+
+    /// Configuration with the default interface settings and 1ms polling intervals for a
+    /// generated descriptor
+    #[must_use]
+    pub fn from_descriptor(descriptor: &'static RawHidDescriptor) -> Self {
+        Self::with_intervals(descriptor, 1.millis(), 1.millis())
+    }
+
+    /// Configuration with the given polling intervals for a generated descriptor
+    ///
+    /// An OUT endpoint is only allocated if the layout has an output report
+    #[must_use]
+    pub fn with_intervals(
+        descriptor: &'static RawHidDescriptor,
+        in_interval: MillisDurationU32,
+        out_interval: MillisDurationU32,
+    ) -> Self {
+        let builder = unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+            descriptor.descriptor()
+        ))
+        .description("Raw HID")
+        .in_endpoint(in_interval));
+
+        let builder = if descriptor.layout().out_len > 0 {
+            unwrap!(builder.with_out_endpoint(out_interval))
+        } else {
+            builder.without_out_endpoint()
+        };
+
+        Self::new(builder.build(), descriptor.layout())
+    }
+
+    /// Describe the interface to Windows, such as to bind WinUSB to it, see
+    /// [`InterfaceConfig::with_ms_os_20`]
+    #[must_use]
+    pub fn with_ms_os_20(mut self, function: MsOs20Function<'a>) -> Self {
+        self.interface = self.interface.with_ms_os_20(function);
+        self
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for RawHidConfig<'a> {
+    type Allocated = RawHid<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            layout: self.layout,
+            received: ReceivedReport::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::raw::{RawHidDescriptor, RawHidLayout, DEFAULT_RAW_HID_DESCRIPTOR};
+
+    #[test]
+    fn default_descriptor() {
+        assert_eq!(
+            DEFAULT_RAW_HID_DESCRIPTOR.descriptor(),
+            [
+                0x06, 0x00, 0xFF, 0x0A, 0x01, 0x00, 0xA1, 0x01, 0x09, 0x01, 0x15, 0x00, 0x26, 0xFF,
+                0x00, 0x75, 0x08, 0x95, 0x40, 0x81, 0x02, 0x09, 0x02, 0x95, 0x40, 0x91, 0x02, 0xC0,
+            ]
+        );
+    }
+
+    #[test]
+    fn input_only_descriptor() {
+        static DESCRIPTOR: RawHidDescriptor = RawHidDescriptor::new(RawHidLayout {
+            usage_page: 0xFF60,
+            usage: 0x61,
+            in_len: 8,
+            out_len: 0,
+        });
+
+        let descriptor = DESCRIPTOR.descriptor();
+        assert_eq!(descriptor[..6], [0x06, 0x60, 0xFF, 0x0A, 0x61, 0x00]);
+        assert_eq!(descriptor[17..], [0x95, 0x08, 0x81, 0x02, 0xC0]);
+    }
+}
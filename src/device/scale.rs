@@ -0,0 +1,261 @@
+//!HID point of sale weighing scale
+//!
+//! See [HID Point of Sale Usage Tables](<https://www.usb.org/sites/default/files/pos1_02.pdf>).
+//! The scale data report uses the same layout as common USB postal scales, so existing scale
+//! drivers and point of sale software can read it.
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the scale control feature report
+pub const SCALE_CONTROL_REPORT_ID: u8 = 0x02;
+/// Report ID of the [`ScaleReport`] input report
+pub const SCALE_DATA_REPORT_ID: u8 = 0x03;
+
+#[rustfmt::skip]
+pub const SCALE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x8D,        // Usage Page (Scales),
+    0x09, 0x01,        // Usage (Scales),
+    0xA1, 0x01,        // Collection (Application),
+    0x09, 0x32,        //   Usage (Scale Data Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x03,        //     Report ID (3),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x09, 0x70,        //     Usage (Scale Status),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x19, 0x71,        //       Usage Minimum (Fault),
+    0x29, 0x78,        //       Usage Maximum (Requires Rezeroing),
+    0x15, 0x01,        //       Logical Minimum (1),
+    0x25, 0x08,        //       Logical Maximum (8),
+    0x81, 0x00,        //       Input (Data, Array, Absolute),
+    0xC0,              //     End Collection
+    0x09, 0x50,        //     Usage (Weight Unit),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x19, 0x51,        //       Usage Minimum (Milligram),
+    0x29, 0x5C,        //       Usage Maximum (Pound),
+    0x15, 0x01,        //       Logical Minimum (1),
+    0x25, 0x0C,        //       Logical Maximum (12),
+    0x81, 0x00,        //       Input (Data, Array, Absolute),
+    0xC0,              //     End Collection
+    0x09, 0x41,        //     Usage (Data Scaling),
+    0x15, 0x80,        //     Logical Minimum (-128),
+    0x25, 0x7F,        //     Logical Maximum (127),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x09, 0x40,        //     Usage (Data Weight),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0x31,        //   Usage (Scale Control Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x02,        //     Report ID (2),
+    0x09, 0x80,        //     Usage (Zero Scale),
+    0x09, 0x81,        //     Usage (Enforced Zero Return),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0xB1, 0x03,        //     Feature (Constant, Variable, Absolute),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum ScaleStatus {
+    Fault = 1,
+    StableAtZero = 2,
+    InMotion = 3,
+    #[default]
+    WeightStable = 4,
+    UnderZero = 5,
+    OverWeightLimit = 6,
+    RequiresCalibration = 7,
+    RequiresRezeroing = 8,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum WeightUnit {
+    Milligram = 1,
+    #[default]
+    Gram = 2,
+    Kilogram = 3,
+    Carat = 4,
+    Tael = 5,
+    Grain = 6,
+    Pennyweight = 7,
+    MetricTon = 8,
+    AvoirTon = 9,
+    TroyOunce = 10,
+    Ounce = 11,
+    Pound = 12,
+}
+
+/// Weight reading, the weight is `weight * 10^scaling` in `unit`s
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "5")]
+pub struct ScaleReport {
+    #[packed_field(bytes = "0", ty = "enum")]
+    pub status: ScaleStatus,
+    #[packed_field(bytes = "1", ty = "enum")]
+    pub unit: WeightUnit,
+    #[packed_field(bytes = "2")]
+    pub scaling: i8,
+    #[packed_field(bytes = "3..=4")]
+    pub weight: u16,
+}
+
+/// Scale control request from the host
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(bit_numbering = "lsb0", size_bytes = "1")]
+pub struct ScaleControl {
+    /// Zero (tare) the scale at the current weight
+    #[packed_field(bits = "0")]
+    pub zero: bool,
+    /// Only report weight once the scale has returned to zero
+    #[packed_field(bits = "1")]
+    pub enforced_zero_return: bool,
+}
+
+/// Weighing scale
+///
+/// Requests from the host to zero the scale are returned by [`WeighingScale::read_control`]
+pub struct WeighingScale<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    control: Option<ScaleControl>,
+}
+
+impl<B: UsbBus> WeighingScale<'_, B> {
+    pub fn write_report(&mut self, report: &ScaleReport) -> Result<(), UsbHidError> {
+        let mut data = [0u8; 6];
+        data[0] = SCALE_DATA_REPORT_ID;
+        report.pack_to_slice(&mut data[1..]).map_err(|_| {
+            error!("Error packing ScaleReport");
+            UsbHidError::SerializationError
+        })?;
+        self.interface
+            .write_report(&data)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+
+    /// Take the last control request from the host, if any
+    pub fn read_control(&mut self) -> Option<ScaleControl> {
+        self.control.take()
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for WeighingScale<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.control = None;
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        if report_id != SCALE_CONTROL_REPORT_ID {
+            return Err(UsbError::Unsupported);
+        }
+        let out = data.get_mut(..2).ok_or(UsbError::BufferOverflow)?;
+        out.copy_from_slice(&[report_id, 0]);
+        Ok(2)
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        if report_id != SCALE_CONTROL_REPORT_ID || data.first() != Some(&report_id) {
+            return Err(UsbError::Unsupported);
+        }
+        let control = data
+            .get(1..2)
+            .and_then(|d| ScaleControl::unpack_from_slice(d).ok())
+            .ok_or(UsbError::ParseError)?;
+        info!(
+            "Scale control: zero {}, enforced zero return {}",
+            control.zero, control.enforced_zero_return
+        );
+        self.control = Some(control);
+        Ok(())
+    }
+}
+
+pub struct WeighingScaleConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+}
+
+impl Default for WeighingScaleConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::new(SCALE_REPORT_DESCRIPTOR))
+                .description("Weighing Scale")
+                .in_endpoint(10.millis()))
+            .without_out_endpoint()
+            .build(),
+        )
+    }
+}
+
+impl<'a> WeighingScaleConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for WeighingScaleConfig<'a> {
+    type Allocated = WeighingScale<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            control: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::scale::{ScaleControl, ScaleReport, ScaleStatus, WeightUnit};
+
+    #[test]
+    fn scale_report_layout() {
+        let bytes = ScaleReport {
+            status: ScaleStatus::WeightStable,
+            unit: WeightUnit::Ounce,
+            scaling: -1,
+            weight: 0x0123,
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(bytes, [0x04, 0x0B, 0xFF, 0x23, 0x01]);
+    }
+
+    #[test]
+    fn scale_control_layout() {
+        let control = ScaleControl::unpack(&[0x01]).unwrap();
+        assert!(control.zero);
+        assert!(!control.enforced_zero_return);
+    }
+}
@@ -0,0 +1,579 @@
+//!HID sensors, accelerometer and gyrometer
+//!
+//! Follows the HID Sensor Usages specification as used by the Windows sensor class driver. See
+//! [HID Sensors Usages](<https://www.usb.org/sites/default/files/hutrr39b_0.pdf>)
+use crate::descriptor::concat;
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the accelerometer input and feature reports
+pub const ACCELEROMETER_REPORT_ID: u8 = 0x01;
+/// Report ID of the gyrometer input and feature reports
+pub const GYROMETER_REPORT_ID: u8 = 0x02;
+
+/// Sensor property feature items and sensor state and event input items, common to all sensors
+///
+/// Matches [`SensorProperties`] followed by the sensor state and event bytes of the input report
+#[rustfmt::skip]
+pub(crate) const SENSOR_PROPERTY_ITEMS: &[u8] = &[
+    0x0A, 0x16, 0x03,  //     Usage (Reporting State),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x05,        //     Logical Maximum (5),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x0A, 0x40, 0x08,  //       Usage (No Events),
+    0x0A, 0x41, 0x08,  //       Usage (All Events),
+    0x0A, 0x42, 0x08,  //       Usage (Threshold Events),
+    0x0A, 0x43, 0x08,  //       Usage (Wake No Events),
+    0x0A, 0x44, 0x08,  //       Usage (Wake All Events),
+    0x0A, 0x45, 0x08,  //       Usage (Wake Threshold Events),
+    0xB1, 0x00,        //       Feature (Data, Array, Absolute),
+    0xC0,              //     End Collection
+    0x0A, 0x19, 0x03,  //     Usage (Power State),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x05,        //     Logical Maximum (5),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x0A, 0x50, 0x08,  //       Usage (Undefined),
+    0x0A, 0x51, 0x08,  //       Usage (D0 Full Power),
+    0x0A, 0x52, 0x08,  //       Usage (D1 Low Power),
+    0x0A, 0x53, 0x08,  //       Usage (D2 Standby with Wake),
+    0x0A, 0x54, 0x08,  //       Usage (D3 Sleep with Wake),
+    0x0A, 0x55, 0x08,  //       Usage (D4 Power Off),
+    0xB1, 0x00,        //       Feature (Data, Array, Absolute),
+    0xC0,              //     End Collection
+    0x0A, 0x01, 0x02,  //     Usage (Sensor State),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x06,        //     Logical Maximum (6),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x0A, 0x00, 0x08,  //       Usage (Undefined),
+    0x0A, 0x01, 0x08,  //       Usage (Ready),
+    0x0A, 0x02, 0x08,  //       Usage (Not Available),
+    0x0A, 0x03, 0x08,  //       Usage (No Data),
+    0x0A, 0x04, 0x08,  //       Usage (Initializing),
+    0x0A, 0x05, 0x08,  //       Usage (Access Denied),
+    0x0A, 0x06, 0x08,  //       Usage (Error),
+    0xB1, 0x00,        //       Feature (Data, Array, Absolute),
+    0xC0,              //     End Collection
+    0x0A, 0x0E, 0x03,  //     Usage (Report Interval),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0xFF, 0xFF, //     Logical Maximum (4294967295),
+    0x75, 0x20,        //     Report Size (32),
+    0x95, 0x01,        //     Report Count (1),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x0A, 0x01, 0x02,  //     Usage (Sensor State),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x06,        //     Logical Maximum (6),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x0A, 0x00, 0x08,  //       Usage (Undefined),
+    0x0A, 0x01, 0x08,  //       Usage (Ready),
+    0x0A, 0x02, 0x08,  //       Usage (Not Available),
+    0x0A, 0x03, 0x08,  //       Usage (No Data),
+    0x0A, 0x04, 0x08,  //       Usage (Initializing),
+    0x0A, 0x05, 0x08,  //       Usage (Access Denied),
+    0x0A, 0x06, 0x08,  //       Usage (Error),
+    0x81, 0x00,        //       Input (Data, Array, Absolute),
+    0xC0,              //     End Collection
+    0x0A, 0x02, 0x02,  //     Usage (Sensor Event),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x05,        //     Logical Maximum (5),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x0A, 0x10, 0x08,  //       Usage (Unknown),
+    0x0A, 0x11, 0x08,  //       Usage (State Changed),
+    0x0A, 0x12, 0x08,  //       Usage (Property Changed),
+    0x0A, 0x13, 0x08,  //       Usage (Data Updated),
+    0x0A, 0x14, 0x08,  //       Usage (Poll Response),
+    0x0A, 0x15, 0x08,  //       Usage (Change Sensitivity),
+    0x81, 0x00,        //       Input (Data, Array, Absolute),
+    0xC0,              //     End Collection
+];
+
+#[rustfmt::skip]
+const ACCELEROMETER_ITEMS: &[u8] = &[
+    0x16, 0x01, 0x80,  //     Logical Minimum (-32767),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x01,        //     Report Count (1),
+    0x55, 0x0D,        //     Unit Exponent (-3),
+    0x0A, 0x53, 0x04,  //     Usage (Acceleration Axis X),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x0A, 0x54, 0x04,  //     Usage (Acceleration Axis Y),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x0A, 0x55, 0x04,  //     Usage (Acceleration Axis Z),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+];
+
+#[rustfmt::skip]
+const GYROMETER_ITEMS: &[u8] = &[
+    0x16, 0x01, 0x80,  //     Logical Minimum (-32767),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x01,        //     Report Count (1),
+    0x55, 0x0F,        //     Unit Exponent (-1),
+    0x0A, 0x57, 0x04,  //     Usage (Angular Velocity about X Axis),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x0A, 0x58, 0x04,  //     Usage (Angular Velocity about Y Axis),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x0A, 0x59, 0x04,  //     Usage (Angular Velocity about Z Axis),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+];
+
+/// Accelerometer axes are in milli G, gyrometer axes in tenths of a degree per second
+#[rustfmt::skip]
+pub const SENSORS_REPORT_DESCRIPTOR: &[u8] = &concat::<
+    { 2 * SENSOR_PROPERTY_ITEMS.len() + ACCELEROMETER_ITEMS.len() + GYROMETER_ITEMS.len() + 21 }
+>(&[
+    &[
+        0x05, 0x20,    // Usage Page (Sensors),
+        0x09, 0x01,    // Usage (Sensor),
+        0xA1, 0x01,    // Collection (Application),
+        0x85, 0x01,    //   Report ID (1),
+        0x09, 0x73,    //   Usage (Accelerometer 3D),
+        0xA1, 0x00,    //   Collection (Physical),
+    ],
+    SENSOR_PROPERTY_ITEMS,
+    ACCELEROMETER_ITEMS,
+    &[
+        0xC0,          //   End Collection
+        0x85, 0x02,    //   Report ID (2),
+        0x09, 0x76,    //   Usage (Gyrometer 3D),
+        0xA1, 0x00,    //   Collection (Physical),
+    ],
+    SENSOR_PROPERTY_ITEMS,
+    GYROMETER_ITEMS,
+    &[
+        0xC0,          //   End Collection
+        0xC0,          // End Collection
+    ],
+]);
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum ReportingState {
+    #[default]
+    NoEvents = 0,
+    AllEvents = 1,
+    ThresholdEvents = 2,
+    WakeNoEvents = 3,
+    WakeAllEvents = 4,
+    WakeThresholdEvents = 5,
+}
+
+impl ReportingState {
+    /// Input reports should be sent in this state
+    #[must_use]
+    pub fn is_reporting(self) -> bool {
+        !matches!(self, Self::NoEvents | Self::WakeNoEvents)
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum PowerState {
+    #[default]
+    Undefined = 0,
+    FullPower = 1,
+    LowPower = 2,
+    StandbyWithWake = 3,
+    SleepWithWake = 4,
+    PowerOff = 5,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum SensorState {
+    Undefined = 0,
+    #[default]
+    Ready = 1,
+    NotAvailable = 2,
+    NoData = 3,
+    Initializing = 4,
+    AccessDenied = 5,
+    Error = 6,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u8)]
+pub enum SensorEvent {
+    #[default]
+    Unknown = 0,
+    StateChanged = 1,
+    PropertyChanged = 2,
+    DataUpdated = 3,
+    PollResponse = 4,
+    ChangeSensitivity = 5,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SensorKind {
+    Accelerometer,
+    Gyrometer,
+}
+
+impl SensorKind {
+    fn report_id(self) -> u8 {
+        match self {
+            Self::Accelerometer => ACCELEROMETER_REPORT_ID,
+            Self::Gyrometer => GYROMETER_REPORT_ID,
+        }
+    }
+
+    fn from_report_id(report_id: u8) -> Option<Self> {
+        match report_id {
+            ACCELEROMETER_REPORT_ID => Some(Self::Accelerometer),
+            GYROMETER_REPORT_ID => Some(Self::Gyrometer),
+            _ => None,
+        }
+    }
+}
+
+/// Sensor properties, reporting and power state are controlled by the host
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "7")]
+pub struct SensorProperties {
+    #[packed_field(bytes = "0", ty = "enum")]
+    pub reporting_state: ReportingState,
+    #[packed_field(bytes = "1", ty = "enum")]
+    pub power_state: PowerState,
+    #[packed_field(bytes = "2", ty = "enum")]
+    pub sensor_state: SensorState,
+    /// Report interval in milliseconds, 0 for the sensor default
+    #[packed_field(bytes = "3..=6")]
+    pub report_interval: u32,
+}
+
+/// Three axis sample
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct AxisSample {
+    pub x: i16,
+    pub y: i16,
+    pub z: i16,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "8")]
+pub struct SensorReport {
+    #[packed_field(bytes = "0", ty = "enum")]
+    pub sensor_state: SensorState,
+    #[packed_field(bytes = "1", ty = "enum")]
+    pub event: SensorEvent,
+    #[packed_field(bytes = "2..=3")]
+    pub x: i16,
+    #[packed_field(bytes = "4..=5")]
+    pub y: i16,
+    #[packed_field(bytes = "6..=7")]
+    pub z: i16,
+}
+
+/// Host controlled properties and reporting of a single sensor
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Sensor<S> {
+    pub(crate) properties: SensorProperties,
+    pub(crate) sample: Option<S>,
+    pending_event: Option<SensorEvent>,
+    interval_remaining_ms: u32,
+}
+
+impl<S: Copy> Sensor<S> {
+    pub(crate) fn set_sensor_state(&mut self, state: SensorState) {
+        if self.properties.sensor_state != state {
+            self.properties.sensor_state = state;
+            self.pending_event = Some(SensorEvent::StateChanged);
+        }
+    }
+
+    /// Report `event` to the host on the next tick, rather than waiting for the report interval
+    ///
+    /// A pending state or property change is not replaced by a data update
+    pub(crate) fn notify(&mut self, event: SensorEvent) {
+        if event != SensorEvent::DataUpdated || self.pending_event.is_none() {
+            self.pending_event = Some(event);
+        }
+    }
+
+    /// Call every 1ms, returns the event to report, if a report is due, and the latest sample
+    pub(crate) fn tick(&mut self) -> Option<(SensorEvent, Option<S>)> {
+        self.interval_remaining_ms = self.interval_remaining_ms.saturating_sub(1);
+
+        if !self.properties.reporting_state.is_reporting()
+            || self.properties.power_state != PowerState::FullPower
+        {
+            return None;
+        }
+
+        match self.pending_event {
+            Some(event) => Some((event, self.sample)),
+            None if self.interval_remaining_ms == 0 && self.sample.is_some() => {
+                Some((SensorEvent::DataUpdated, self.sample))
+            }
+            None => None,
+        }
+    }
+
+    /// Call once the report returned by [`Sensor::tick`] has been written
+    pub(crate) fn report_written(&mut self, default_report_interval: u32) {
+        self.pending_event = None;
+        self.interval_remaining_ms = match self.properties.report_interval {
+            0 => default_report_interval,
+            interval => interval,
+        };
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.properties.reporting_state = ReportingState::default();
+        self.properties.power_state = PowerState::default();
+        self.properties.report_interval = 0;
+        self.pending_event = None;
+        self.interval_remaining_ms = 0;
+    }
+
+    /// Write the properties feature report, with the report ID prefix, to `data`
+    pub(crate) fn get_properties_report(
+        &self,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        let out = data.get_mut(..8).ok_or(UsbError::BufferOverflow)?;
+        out[0] = report_id;
+        self.properties
+            .pack_to_slice(&mut out[1..])
+            .map_err(|_| UsbError::ParseError)?;
+        Ok(8)
+    }
+
+    /// Apply a properties feature report, with the report ID prefix, from the host
+    pub(crate) fn set_properties_report(
+        &mut self,
+        report_id: u8,
+        data: &[u8],
+    ) -> usb_device::Result<()> {
+        if data.first() != Some(&report_id) {
+            return Err(UsbError::ParseError);
+        }
+        let properties = data
+            .get(1..8)
+            .and_then(|d| SensorProperties::unpack_from_slice(d).ok())
+            .ok_or(UsbError::ParseError)?;
+
+        // Sensor state is owned by the device, any value written by the host is ignored
+        let properties = SensorProperties {
+            sensor_state: self.properties.sensor_state,
+            ..properties
+        };
+        if self.properties != properties {
+            info!(
+                "Set sensor {} properties: reporting {}, power {}, interval {}",
+                report_id,
+                properties.reporting_state as u8,
+                properties.power_state as u8,
+                properties.report_interval
+            );
+            self.properties = properties;
+            self.pending_event = Some(SensorEvent::PropertyChanged);
+            self.interval_remaining_ms = 0;
+        }
+        Ok(())
+    }
+}
+
+/// Accelerometer and gyrometer sensor collection
+///
+/// The host configures each sensor with feature reports. Samples passed to
+/// [`Sensors::update`] are sent at the report interval requested by the host from
+/// [`DeviceClass::tick`], which must be called every 1ms, while the reporting state allows it
+/// and the sensor is at full power. State and property changes are reported to the host
+/// immediately.
+pub struct Sensors<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes16, OutNone, ReportSingle>,
+    state: [Sensor<AxisSample>; 2],
+    default_report_interval: u32,
+}
+
+impl<B: UsbBus> Sensors<'_, B> {
+    /// Set the latest sample for a sensor
+    pub fn update(&mut self, kind: SensorKind, sample: AxisSample) {
+        self.sensor_mut(kind).sample = Some(sample);
+    }
+
+    /// Set the sensor state, e.g. to report a sensor error to the host
+    pub fn set_sensor_state(&mut self, kind: SensorKind, state: SensorState) {
+        self.sensor_mut(kind).set_sensor_state(state);
+    }
+
+    #[must_use]
+    pub fn properties(&self, kind: SensorKind) -> SensorProperties {
+        self.state[kind as usize].properties
+    }
+
+    fn sensor_mut(&mut self, kind: SensorKind) -> &mut Sensor<AxisSample> {
+        &mut self.state[kind as usize]
+    }
+
+    fn tick_sensor(&mut self, kind: SensorKind) -> Result<(), UsbHidError> {
+        let sensor = self.sensor_mut(kind);
+        let Some((event, sample)) = sensor.tick() else {
+            return Ok(());
+        };
+
+        let sample = sample.unwrap_or_default();
+        let report = SensorReport {
+            sensor_state: sensor.properties.sensor_state,
+            event,
+            x: sample.x,
+            y: sample.y,
+            z: sample.z,
+        };
+
+        match self.write(kind, report) {
+            Ok(()) => {
+                let interval = self.default_report_interval;
+                self.sensor_mut(kind).report_written(interval);
+                Ok(())
+            }
+            Err(UsbHidError::WouldBlock) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write(&mut self, kind: SensorKind, report: SensorReport) -> Result<(), UsbHidError> {
+        let mut data = [0; 9];
+        data[0] = kind.report_id();
+        report.pack_to_slice(&mut data[1..]).map_err(|_| {
+            error!("Error packing SensorReport");
+            UsbHidError::SerializationError
+        })?;
+        self.interface
+            .write_report(&data)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for Sensors<'a, B> {
+    type I = Interface<'a, B, InBytes16, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        for sensor in &mut self.state {
+            sensor.reset();
+        }
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        self.tick_sensor(SensorKind::Accelerometer)?;
+        self.tick_sensor(SensorKind::Gyrometer)
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        let kind = SensorKind::from_report_id(report_id).ok_or(UsbError::Unsupported)?;
+        self.state[kind as usize].get_properties_report(report_id, data)
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        let kind = SensorKind::from_report_id(report_id).ok_or(UsbError::Unsupported)?;
+        self.sensor_mut(kind).set_properties_report(report_id, data)
+    }
+}
+
+pub struct SensorsConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes16, OutNone, ReportSingle>,
+    default_report_interval: u32,
+}
+
+impl Default for SensorsConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                SENSORS_REPORT_DESCRIPTOR
+            ))
+            .description("Sensors")
+            .in_endpoint(1.millis()))
+            .without_out_endpoint()
+            .build(),
+            100,
+        )
+    }
+}
+
+impl<'a> SensorsConfig<'a> {
+    /// `default_report_interval` in milliseconds is used until the host sets a report interval
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes16, OutNone, ReportSingle>,
+        default_report_interval: u32,
+    ) -> Self {
+        Self {
+            interface,
+            default_report_interval,
+        }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for SensorsConfig<'a> {
+    type Allocated = Sensors<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            state: [Sensor::default(); 2],
+            default_report_interval: self.default_report_interval.max(1),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::sensor::{
+        PowerState, ReportingState, SensorEvent, SensorProperties, SensorReport, SensorState,
+    };
+
+    #[test]
+    fn sensor_properties_layout() {
+        let properties =
+            SensorProperties::unpack(&[0x01, 0x01, 0x01, 0xE8, 0x03, 0x00, 0x00]).unwrap();
+
+        assert_eq!(properties.reporting_state, ReportingState::AllEvents);
+        assert_eq!(properties.power_state, PowerState::FullPower);
+        assert_eq!(properties.sensor_state, SensorState::Ready);
+        assert_eq!(properties.report_interval, 1000);
+    }
+
+    #[test]
+    fn sensor_report_layout() {
+        let bytes = SensorReport {
+            sensor_state: SensorState::Ready,
+            event: SensorEvent::DataUpdated,
+            x: -1000,
+            y: 0,
+            z: 1000,
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(bytes, [0x01, 0x03, 0x18, 0xFC, 0x00, 0x00, 0xE8, 0x03]);
+    }
+}
@@ -0,0 +1,210 @@
+//!HID 6-DOF 3D mouse for CAD applications
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+
+/// Report ID of the [`SpaceMouseMotionReport`] input report
+pub const SPACE_MOUSE_MOTION_REPORT_ID: u8 = 0x01;
+/// Report ID of the button input report
+pub const SPACE_MOUSE_BUTTONS_REPORT_ID: u8 = 0x03;
+
+/// Minimum axis value of a [`SpaceMouseMotionReport`]
+pub const SPACE_MOUSE_AXIS_MIN: i16 = -350;
+/// Maximum axis value of a [`SpaceMouseMotionReport`]
+pub const SPACE_MOUSE_AXIS_MAX: i16 = 350;
+
+/// Multi-axis controller report descriptor using the report model of 3Dconnexion devices
+///
+/// * Input report 1 - [`SpaceMouseMotionReport`], X/Y/Z translation then Rx/Ry/Rz rotation
+/// * Input report 3 - 32 buttons, button 1 in the least significant bit
+#[rustfmt::skip]
+pub const SPACE_MOUSE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,         // Usage Page (Generic Desktop),
+    0x09, 0x08,         // Usage (Multi-axis Controller),
+    0xA1, 0x01,         // Collection (Application),
+    0xA1, 0x00,         //   Collection (Physical),
+    0x85, 0x01,         //     Report ID (1),
+    0x16, 0xA2, 0xFE,   //     Logical Minimum (-350),
+    0x26, 0x5E, 0x01,   //     Logical Maximum (350),
+    0x36, 0x88, 0xFA,   //     Physical Minimum (-1400),
+    0x46, 0x78, 0x05,   //     Physical Maximum (1400),
+    0x09, 0x30,         //     Usage (X),
+    0x09, 0x31,         //     Usage (Y),
+    0x09, 0x32,         //     Usage (Z),
+    0x09, 0x33,         //     Usage (Rx),
+    0x09, 0x34,         //     Usage (Ry),
+    0x09, 0x35,         //     Usage (Rz),
+    0x75, 0x10,         //     Report Size (16),
+    0x95, 0x06,         //     Report Count (6),
+    0x81, 0x02,         //     Input (Data, Variable, Absolute),
+    0xC0,               //   End Collection,
+    0xA1, 0x02,         //   Collection (Logical),
+    0x85, 0x03,         //     Report ID (3),
+    0x05, 0x09,         //     Usage Page (Button),
+    0x19, 0x01,         //     Usage Minimum (1),
+    0x29, 0x20,         //     Usage Maximum (32),
+    0x15, 0x00,         //     Logical Minimum (0),
+    0x25, 0x01,         //     Logical Maximum (1),
+    0x35, 0x00,         //     Physical Minimum (0),
+    0x45, 0x01,         //     Physical Maximum (1),
+    0x75, 0x01,         //     Report Size (1),
+    0x95, 0x20,         //     Report Count (32),
+    0x81, 0x02,         //     Input (Data, Variable, Absolute),
+    0xC0,               //   End Collection,
+    0xC0,               // End Collection
+];
+
+/// Cap displacement, [`SPACE_MOUSE_AXIS_MIN`] to [`SPACE_MOUSE_AXIS_MAX`] on each axis
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "12")]
+pub struct SpaceMouseMotionReport {
+    /// Translation, positive to the right
+    #[packed_field]
+    pub x: i16,
+    /// Translation, positive towards the user
+    #[packed_field]
+    pub y: i16,
+    /// Translation, positive downwards
+    #[packed_field]
+    pub z: i16,
+    /// Rotation about the X axis
+    #[packed_field]
+    pub rx: i16,
+    /// Rotation about the Y axis
+    #[packed_field]
+    pub ry: i16,
+    /// Rotation about the Z axis
+    #[packed_field]
+    pub rz: i16,
+}
+
+impl SpaceMouseMotionReport {
+    /// Report with every axis clamped to [`SPACE_MOUSE_AXIS_MIN`] to [`SPACE_MOUSE_AXIS_MAX`]
+    #[must_use]
+    pub fn clamped(&self) -> Self {
+        let clamp = |v: i16| v.clamp(SPACE_MOUSE_AXIS_MIN, SPACE_MOUSE_AXIS_MAX);
+        Self {
+            x: clamp(self.x),
+            y: clamp(self.y),
+            z: clamp(self.z),
+            rx: clamp(self.rx),
+            ry: clamp(self.ry),
+            rz: clamp(self.rz),
+        }
+    }
+}
+
+/// 6-DOF 3D mouse
+///
+/// Motion and buttons are sent as separate reports, [`SpaceMouse::write_motion`] should be
+/// called at the polling rate while the cap is displaced and once more with a zero report when
+/// it is released
+pub struct SpaceMouse<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes16, OutNone, ReportSingle>,
+}
+
+impl<B: UsbBus> SpaceMouse<'_, B> {
+    pub fn write_motion(&mut self, report: &SpaceMouseMotionReport) -> Result<(), UsbHidError> {
+        let data = report.clamped().pack().map_err(|_| {
+            error!("Error packing SpaceMouseMotionReport");
+            UsbHidError::SerializationError
+        })?;
+        let mut buffer = [0u8; 13];
+        buffer[0] = SPACE_MOUSE_MOTION_REPORT_ID;
+        buffer[1..].copy_from_slice(&data);
+        self.interface
+            .write_report(&buffer)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+
+    /// Write the button state, bit 0 is button 1
+    pub fn write_buttons(&mut self, buttons: u32) -> Result<(), UsbHidError> {
+        let mut buffer = [0u8; 5];
+        buffer[0] = SPACE_MOUSE_BUTTONS_REPORT_ID;
+        buffer[1..].copy_from_slice(&buttons.to_le_bytes());
+        self.interface
+            .write_report(&buffer)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for SpaceMouse<'a, B> {
+    type I = Interface<'a, B, InBytes16, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {}
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+}
+
+pub struct SpaceMouseConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes16, OutNone, ReportSingle>,
+}
+
+impl Default for SpaceMouseConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(
+                unwrap!(InterfaceBuilder::new(SPACE_MOUSE_REPORT_DESCRIPTOR))
+                    .description("3D Mouse")
+                    .in_endpoint(8.millis())
+            )
+            .without_out_endpoint()
+            .build(),
+        )
+    }
+}
+
+impl<'a> SpaceMouseConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes16, OutNone, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for SpaceMouseConfig<'a> {
+    type Allocated = SpaceMouse<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::space_mouse::SpaceMouseMotionReport;
+
+    #[test]
+    fn motion_report_layout() {
+        let report = SpaceMouseMotionReport {
+            x: 1,
+            y: -1,
+            z: 350,
+            rx: -350,
+            ry: 1000,
+            rz: i16::MIN,
+        }
+        .clamped();
+
+        assert_eq!(
+            report.pack().unwrap(),
+            [0x01, 0x00, 0xFF, 0xFF, 0x5E, 0x01, 0xA2, 0xFE, 0x5E, 0x01, 0xA2, 0xFE]
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//!HID switch input - foot pedals and assistive switches
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Maximum number of switches in a [`SwitchLayout`]
+pub const SWITCH_MAX_BUTTONS: u8 = 32;
+
+/// Application collection the switches are reported in
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SwitchCollection {
+    /// Generic Desktop Joystick, seen as game controller buttons by the host
+    Joystick,
+    /// Consumer Control, not picked up by game controller APIs
+    Consumer,
+}
+
+/// Report layout of a [`Switches`] device, one bit per switch padded to a whole byte
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SwitchLayout {
+    pub buttons: u8,
+    pub collection: SwitchCollection,
+}
+
+impl SwitchLayout {
+    /// Length of the report in bytes
+    #[must_use]
+    pub const fn report_len(&self) -> usize {
+        (self.buttons as usize).div_ceil(8)
+    }
+}
+
+/// Default [`SwitchLayout`] - three pedals, as found on transcription foot pedals
+pub const DEFAULT_SWITCH_LAYOUT: SwitchLayout = SwitchLayout {
+    buttons: 3,
+    collection: SwitchCollection::Joystick,
+};
+
+/// Report descriptor for a [`SwitchLayout`], generated at compile time
+///
+/// ```
+/// use usbd_human_interface_device::device::switch::*;
+///
+/// static SINGLE_SWITCH: SwitchDescriptor = SwitchDescriptor::new(SwitchLayout {
+///     buttons: 1,
+///     collection: SwitchCollection::Consumer,
+/// });
+///
+/// let config = SwitchesConfig::from_descriptor(&SINGLE_SWITCH);
+/// ```
+pub struct SwitchDescriptor {
+    layout: SwitchLayout,
+    descriptor: [u8; 32],
+    len: usize,
+}
+
+impl SwitchDescriptor {
+    /// Generate the report descriptor for `layout`
+    ///
+    /// Panics, at compile time if used in a const context, if `layout` has no switches or
+    /// exceeds [`SWITCH_MAX_BUTTONS`]
+    #[must_use]
+    #[rustfmt::skip]
+    pub const fn new(layout: SwitchLayout) -> Self {
+        core::assert!(layout.buttons > 0, "no buttons");
+        core::assert!(layout.buttons <= SWITCH_MAX_BUTTONS, "too many buttons");
+
+        let mut d = Self {
+            layout,
+            descriptor: [0; 32],
+            len: 0,
+        };
+
+        match layout.collection {
+            SwitchCollection::Joystick => d.push(&[
+                0x05, 0x01,             // Usage Page (Generic Desktop),
+                0x09, 0x04,             // Usage (Joystick),
+            ]),
+            SwitchCollection::Consumer => d.push(&[
+                0x05, 0x0C,             // Usage Page (Consumer),
+                0x09, 0x01,             // Usage (Consumer Control),
+            ]),
+        }
+
+        d.push(&[
+            0xA1, 0x01,                 // Collection (Application),
+            0x05, 0x09,                 //   Usage Page (Button),
+            0x19, 0x01,                 //   Usage Minimum (1),
+            0x29, layout.buttons,       //   Usage Maximum (buttons),
+            0x15, 0x00,                 //   Logical Minimum (0),
+            0x25, 0x01,                 //   Logical Maximum (1),
+            0x75, 0x01,                 //   Report Size (1),
+            0x95, layout.buttons,       //   Report Count (buttons),
+            0x81, 0x02,                 //   Input (Data, Variable, Absolute),
+        ]);
+
+        let bits = layout.buttons % 8;
+        if bits != 0 {
+            d.push(&[
+                0x75, 8 - bits,         //   Report Size (padding),
+                0x95, 0x01,             //   Report Count (1),
+                0x81, 0x03,             //   Input (Constant, Variable, Absolute),
+            ]);
+        }
+
+        d.push(&[
+            0xC0,                       // End Collection
+        ]);
+
+        d
+    }
+
+    const fn push(&mut self, items: &[u8]) {
+        let mut i = 0;
+        while i < items.len() {
+            self.descriptor[self.len] = items[i];
+            self.len += 1;
+            i += 1;
+        }
+    }
+
+    #[must_use]
+    pub const fn layout(&self) -> SwitchLayout {
+        self.layout
+    }
+
+    #[must_use]
+    pub fn descriptor(&self) -> &[u8] {
+        &self.descriptor[..self.len]
+    }
+}
+
+/// Report descriptor for [`DEFAULT_SWITCH_LAYOUT`]
+pub static DEFAULT_SWITCH_DESCRIPTOR: SwitchDescriptor =
+    SwitchDescriptor::new(DEFAULT_SWITCH_LAYOUT);
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct SwitchReport {
+    /// Switch state, bit 0 is switch 1. Switches not in the device's [`SwitchLayout`] are ignored
+    pub buttons: u32,
+}
+
+/// Switch input device
+///
+/// Reports are only sent when the switch state changes, so the raw switch state can be written
+/// on every scan of the inputs. If the endpoint is busy the latest state is sent by a later
+/// [`DeviceClass::tick`], which must be called every 1ms, so the host always ends up with the
+/// final state of a bouncing switch.
+pub struct Switches<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    layout: SwitchLayout,
+    buttons: u32,
+    pending: bool,
+}
+
+impl<B: UsbBus> Switches<'_, B> {
+    pub fn write_report(&mut self, report: &SwitchReport) -> Result<(), UsbHidError> {
+        let buttons = if self.layout.buttons < 32 {
+            report.buttons & ((1 << self.layout.buttons) - 1)
+        } else {
+            report.buttons
+        };
+
+        if buttons == self.buttons && !self.pending {
+            return Err(UsbHidError::Duplicate);
+        }
+
+        self.pending |= buttons != self.buttons;
+        self.buttons = buttons;
+
+        match self.flush() {
+            Ok(()) | Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(UsbHidError::from(e)),
+        }
+    }
+
+    fn flush(&mut self) -> usb_device::Result<()> {
+        if self.pending {
+            let len = self.layout.report_len();
+            self.interface
+                .write_report(&self.buttons.to_le_bytes()[..len])?;
+            self.pending = false;
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn layout(&self) -> &SwitchLayout {
+        &self.layout
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for Switches<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.buttons = 0;
+        self.pending = false;
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        match self.flush() {
+            Ok(()) | Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(UsbHidError::from(e)),
+        }
+    }
+}
+
+pub struct SwitchesConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+    layout: SwitchLayout,
+}
+
+impl Default for SwitchesConfig<'_> {
+    fn default() -> Self {
+        Self::from_descriptor(&DEFAULT_SWITCH_DESCRIPTOR)
+    }
+}
+
+impl<'a> SwitchesConfig<'a> {
+    /// `interface` must use a report descriptor matching `layout`, see [`SwitchDescriptor`]
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+        layout: SwitchLayout,
+    ) -> Self {
+        Self { interface, layout }
+    }
+
+    /// Configuration with the default interface settings for a generated descriptor
+    #[must_use]
+    pub fn from_descriptor(descriptor: &'static SwitchDescriptor) -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                descriptor.descriptor()
+            ))
+            .description("Switches")
+            .in_endpoint(10.millis()))
+            .without_out_endpoint()
+            .build(),
+            descriptor.layout(),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for SwitchesConfig<'a> {
+    type Allocated = Switches<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            layout: self.layout,
+            buttons: 0,
+            pending: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::switch::{SwitchCollection, SwitchDescriptor, SwitchLayout};
+
+    #[test]
+    fn consumer_switch_descriptor() {
+        static DESCRIPTOR: SwitchDescriptor = SwitchDescriptor::new(SwitchLayout {
+            buttons: 9,
+            collection: SwitchCollection::Consumer,
+        });
+
+        assert_eq!(
+            DESCRIPTOR.descriptor(),
+            [
+                0x05, 0x0C, 0x09, 0x01, 0xA1, 0x01, 0x05, 0x09, 0x19, 0x01, 0x29, 0x09, 0x15, 0x00,
+                0x25, 0x01, 0x75, 0x01, 0x95, 0x09, 0x81, 0x02, 0x75, 0x07, 0x95, 0x01, 0x81, 0x03,
+                0xC0
+            ]
+        );
+        assert_eq!(DESCRIPTOR.layout().report_len(), 2);
+    }
+}
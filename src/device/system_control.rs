@@ -0,0 +1,159 @@
+//!HID system control - power down, sleep and wake up
+use crate::interface::AutoRelease;
+use crate::page::Desktop;
+use crate::usb_class::prelude::*;
+use fugit::{ExtU32, MillisDurationU32};
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+
+///System control report descriptor - a single `u8` Generic Desktop usage code as an array
+#[rustfmt::skip]
+pub const SYSTEM_CONTROL_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01, // Usage Page (Generic Desktop),
+    0x09, 0x80, // Usage (System Control),
+    0xA1, 0x01, // Collection (Application),
+    0x19, 0x81, //   Usage Minimum (System Power Down),
+    0x29, 0x83, //   Usage Maximum (System Wake Up),
+    0x15, 0x81, //   Logical Minimum (0x81),
+    0x25, 0x83, //   Logical Maximum (0x83),
+    0x75, 0x08, //   Report Size (8),
+    0x95, 0x01, //   Report Count (1),
+    0x81, 0x00, //   Input (Data, Array, Absolute),
+    0xC0,       // End Collection
+];
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "1")]
+pub struct SystemControlReport {
+    /// [`Desktop::SystemPowerDown`], [`Desktop::SystemSleep`], [`Desktop::SystemWakeUp`] or
+    /// [`Desktop::Undefined`] for no usage
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub usage: Desktop,
+}
+
+/// System control device
+///
+/// Usages written with [`SystemControl::write_report`] are automatically released on a later
+/// [`DeviceClass::tick`], once the host has accepted the report and any delay set with
+/// [`SystemControlConfig::with_release_delay`] has passed
+pub struct SystemControl<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutNone, ReportSingle>,
+    release: AutoRelease,
+}
+
+impl<B: UsbBus> SystemControl<'_, B> {
+    pub fn write_report(&mut self, report: &SystemControlReport) -> Result<(), UsbHidError> {
+        self.write(*report)?;
+        self.release
+            .report_written(report.usage != Desktop::Undefined);
+        Ok(())
+    }
+
+    fn write(&mut self, report: SystemControlReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|_| {
+            error!("Error packing SystemControlReport");
+            UsbHidError::SerializationError
+        })?;
+        self.interface
+            .write_report(&data)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for SystemControl<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.release.released();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        if !self.release.tick() {
+            return Ok(());
+        }
+
+        match self.write(SystemControlReport::default()) {
+            Ok(()) => {
+                self.release.released();
+                Ok(())
+            }
+            Err(UsbHidError::WouldBlock) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+pub struct SystemControlConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>,
+    release_delay: MillisDurationU32,
+}
+
+impl Default for SystemControlConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(
+                unwrap!(InterfaceBuilder::new(SYSTEM_CONTROL_REPORT_DESCRIPTOR))
+                    .description("System Control")
+                    .in_endpoint(50.millis())
+            )
+            .without_out_endpoint()
+            .build(),
+        )
+    }
+}
+
+impl<'a> SystemControlConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes8, OutNone, ReportSingle>) -> Self {
+        Self {
+            interface,
+            release_delay: 0.millis(),
+        }
+    }
+
+    /// Hold usages for at least `delay` before releasing them, rather than releasing them as soon
+    /// as the host has accepted the report, for hosts that ignore short presses
+    #[must_use]
+    pub fn with_release_delay(mut self, delay: MillisDurationU32) -> Self {
+        self.release_delay = delay;
+        self
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for SystemControlConfig<'a> {
+    type Allocated = SystemControl<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            release: AutoRelease::new(self.release_delay),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::system_control::SystemControlReport;
+    use crate::page::Desktop;
+
+    #[test]
+    fn system_control_report_is_usage_code() {
+        let report = SystemControlReport {
+            usage: Desktop::SystemSleep,
+        };
+
+        assert_eq!(report.pack().unwrap(), [0x82]);
+        assert_eq!(SystemControlReport::default().pack().unwrap(), [0x00]);
+    }
+}
@@ -0,0 +1,495 @@
+//!HID telephony headset and speakerphone controls
+use crate::interface::PendingReports;
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the [`HeadsetReport`] input report
+pub const HEADSET_INPUT_REPORT_ID: u8 = 0x01;
+/// Report ID of the [`HeadsetLedsReport`] output report
+pub const HEADSET_LEDS_REPORT_ID: u8 = 0x02;
+
+/// Report ID of the speakerphone consumer control input report
+pub const SPEAKERPHONE_CONSUMER_REPORT_ID: u8 = 0x01;
+/// Report ID of the speakerphone telephony input report
+pub const SPEAKERPHONE_TELEPHONY_REPORT_ID: u8 = 0x02;
+/// Report ID of the speakerphone [`HeadsetLedsReport`] output report
+pub const SPEAKERPHONE_LEDS_REPORT_ID: u8 = 0x03;
+
+/// Headset report descriptor, compatible with Microsoft Teams and Zoom call control
+#[rustfmt::skip]
+pub const HEADSET_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0B,        // Usage Page (Telephony),
+    0x09, 0x05,        // Usage (Headset),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x01,        //   Report Count (1),
+    0x09, 0x20,        //   Usage (Hook Switch),
+    0x81, 0x22,        //   Input (Data, Variable, Absolute, No Preferred),
+    0x09, 0x2F,        //   Usage (Phone Mute),
+    0x09, 0x21,        //   Usage (Flash),
+    0x95, 0x02,        //   Report Count (2),
+    0x81, 0x06,        //   Input (Data, Variable, Relative),
+    0x95, 0x05,        //   Report Count (5),
+    0x81, 0x03,        //   Input (Constant, Variable, Absolute),
+    0x85, 0x02,        //   Report ID (2),
+    0x05, 0x08,        //   Usage Page (LED),
+    0x09, 0x17,        //   Usage (Off-Hook),
+    0x09, 0x09,        //   Usage (Mute),
+    0x09, 0x18,        //   Usage (Ring),
+    0x09, 0x20,        //   Usage (Hold),
+    0x09, 0x21,        //   Usage (Microphone),
+    0x95, 0x05,        //   Report Count (5),
+    0x91, 0x22,        //   Output (Data, Variable, Absolute, No Preferred),
+    0x95, 0x03,        //   Report Count (3),
+    0x91, 0x03,        //   Output (Constant, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Speakerphone report descriptor - consumer volume and mute controls, telephony hook switch
+/// and phone mute, and the call state LEDs of [`HEADSET_REPORT_DESCRIPTOR`], under separate
+/// report IDs
+#[rustfmt::skip]
+pub const SPEAKERPHONE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0C,        // Usage Page (Consumer),
+    0x09, 0x01,        // Usage (Consumer Control),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x09, 0xE9,        //   Usage (Volume Increment),
+    0x09, 0xEA,        //   Usage (Volume Decrement),
+    0x95, 0x02,        //   Report Count (2),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x09, 0xE2,        //   Usage (Mute),
+    0x95, 0x01,        //   Report Count (1),
+    0x81, 0x06,        //   Input (Data, Variable, Relative),
+    0x95, 0x05,        //   Report Count (5),
+    0x81, 0x03,        //   Input (Constant, Variable, Absolute),
+    0xC0,              // End Collection
+    0x05, 0x0B,        // Usage Page (Telephony),
+    0x09, 0x01,        // Usage (Phone),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x02,        //   Report ID (2),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x01,        //   Report Count (1),
+    0x09, 0x20,        //   Usage (Hook Switch),
+    0x81, 0x22,        //   Input (Data, Variable, Absolute, No Preferred),
+    0x09, 0x2F,        //   Usage (Phone Mute),
+    0x81, 0x06,        //   Input (Data, Variable, Relative),
+    0x95, 0x06,        //   Report Count (6),
+    0x81, 0x03,        //   Input (Constant, Variable, Absolute),
+    0x85, 0x03,        //   Report ID (3),
+    0x05, 0x08,        //   Usage Page (LED),
+    0x09, 0x17,        //   Usage (Off-Hook),
+    0x09, 0x09,        //   Usage (Mute),
+    0x09, 0x18,        //   Usage (Ring),
+    0x09, 0x20,        //   Usage (Hold),
+    0x09, 0x21,        //   Usage (Microphone),
+    0x95, 0x05,        //   Report Count (5),
+    0x91, 0x22,        //   Output (Data, Variable, Absolute, No Preferred),
+    0x95, 0x03,        //   Report Count (3),
+    0x91, 0x03,        //   Output (Constant, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Headset call control input report
+///
+/// `hook_switch` is the off-hook state of the headset. `phone_mute` and `flash` are one-shot
+/// controls that toggle the host state and are released automatically by [`Headset`].
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "lsb0", size_bytes = "1")]
+pub struct HeadsetReport {
+    #[packed_field(bits = "0")]
+    pub hook_switch: bool,
+    #[packed_field(bits = "1")]
+    pub phone_mute: bool,
+    #[packed_field(bits = "2")]
+    pub flash: bool,
+}
+
+/// Call state indicated by the host
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "lsb0", size_bytes = "1")]
+pub struct HeadsetLedsReport {
+    #[packed_field(bits = "0")]
+    pub off_hook: bool,
+    #[packed_field(bits = "1")]
+    pub mute: bool,
+    #[packed_field(bits = "2")]
+    pub ring: bool,
+    #[packed_field(bits = "3")]
+    pub hold: bool,
+    #[packed_field(bits = "4")]
+    pub microphone: bool,
+}
+
+impl OutputReport for HeadsetLedsReport {
+    fn parse(data: &[u8]) -> usb_device::Result<Self> {
+        Self::unpack_from_slice(data).map_err(|_| UsbError::ParseError)
+    }
+}
+
+/// Read an LED output report with `report_id` into `leds`, without the report ID, for
+/// [`ReceivedReport::receive`]
+fn read_leds_report<B: UsbBus>(
+    interface: &mut Interface<'_, B, InBytes8, OutBytes8, ReportSingle>,
+    report_id: u8,
+    leds: &mut [u8],
+) -> usb_device::Result<usize> {
+    let mut data = [0u8; 8];
+    let len = interface.read_report(&mut data)?;
+
+    match data[..len] {
+        [id, report, ..] if id == report_id => {
+            leds[0] = report;
+            Ok(1)
+        }
+        _ => Err(UsbError::ParseError),
+    }
+}
+
+/// Speakerphone input report
+///
+/// `volume_up`, `volume_down` and `mute` control the host audio output, `hook_switch` and
+/// `phone_mute` the call
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct SpeakerphoneReport {
+    pub volume_up: bool,
+    pub volume_down: bool,
+    pub mute: bool,
+    pub hook_switch: bool,
+    pub phone_mute: bool,
+}
+
+/// Telephony headset with hook switch, phone mute and flash controls
+pub struct Headset<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutBytes8, ReportSingle>,
+    hook_switch: bool,
+    release_pending: bool,
+    leds: ReceivedReport<HeadsetLedsReport, 1>,
+}
+
+impl<B: UsbBus> Headset<'_, B> {
+    pub fn write_report(&mut self, report: &HeadsetReport) -> Result<(), UsbHidError> {
+        self.write(*report)?;
+        self.hook_switch = report.hook_switch;
+        self.release_pending = report.phone_mute || report.flash;
+        Ok(())
+    }
+
+    /// Read the LED report last received from the host, returns [`UsbError::WouldBlock`] if none
+    /// has been received since the last read, see [`OutputReports::read_output_report`]
+    pub fn read_report(&mut self) -> usb_device::Result<HeadsetLedsReport> {
+        self.read_output_report()
+    }
+
+    fn receive_leds(&mut self) -> usb_device::Result<()> {
+        let interface = &mut self.interface;
+        self.leds
+            .receive(|leds| read_leds_report(interface, HEADSET_LEDS_REPORT_ID, leds))
+    }
+
+    fn write(&mut self, report: HeadsetReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|_| {
+            error!("Error packing HeadsetReport");
+            UsbHidError::SerializationError
+        })?;
+        self.interface
+            .write_report(&[HEADSET_INPUT_REPORT_ID, data[0]])
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+}
+
+impl<B: UsbBus> OutputReports for Headset<'_, B> {
+    type Report = HeadsetLedsReport;
+
+    fn read_output_report(&mut self) -> usb_device::Result<HeadsetLedsReport> {
+        self.receive_leds()?;
+        self.leds.read()
+    }
+
+    fn output_report(&self) -> HeadsetLedsReport {
+        self.leds.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<HeadsetLedsReport>> {
+        self.receive_leds()?;
+        Ok(self.leds.changed())
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for Headset<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutBytes8, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.hook_switch = false;
+        self.release_pending = false;
+        self.leds = ReceivedReport::default();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        if let Err(e) = self.receive_leds() {
+            warn!("Failed to receive LED report: {:?}", e);
+        }
+        if !self.release_pending {
+            return Ok(());
+        }
+
+        let report = HeadsetReport {
+            hook_switch: self.hook_switch,
+            ..Default::default()
+        };
+        match self.write(report) {
+            Ok(()) => {
+                self.release_pending = false;
+                Ok(())
+            }
+            Err(UsbHidError::WouldBlock) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+pub struct HeadsetConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutBytes8, ReportSingle>,
+}
+
+impl Default for HeadsetConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(
+                unwrap!(unwrap!(InterfaceBuilder::new(HEADSET_REPORT_DESCRIPTOR))
+                    .description("Headset")
+                    .in_endpoint(10.millis()))
+                .with_out_endpoint(10.millis())
+            )
+            .build(),
+        )
+    }
+}
+
+impl<'a> HeadsetConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes8, OutBytes8, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for HeadsetConfig<'a> {
+    type Allocated = Headset<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            hook_switch: false,
+            release_pending: false,
+            leds: ReceivedReport::default(),
+        })
+    }
+}
+
+/// Speakerphone with volume and call controls
+///
+/// Output reports are read by [`DeviceClass::tick`], which must be called every 1ms, and changes
+/// to the call state LEDs are returned by [`Speakerphone::read_leds`]. Input reports are only
+/// sent for the controls that changed, if the endpoint is busy they are sent by a later tick.
+pub struct Speakerphone<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes8, OutBytes8, ReportSingle>,
+    report: SpeakerphoneReport,
+    pending: PendingReports<2>,
+    leds: ReceivedReport<HeadsetLedsReport, 1>,
+}
+
+impl<B: UsbBus> Speakerphone<'_, B> {
+    pub fn write_report(&mut self, report: &SpeakerphoneReport) -> Result<(), UsbHidError> {
+        self.pending.set(
+            0,
+            (report.volume_up, report.volume_down, report.mute)
+                != (
+                    self.report.volume_up,
+                    self.report.volume_down,
+                    self.report.mute,
+                ),
+        );
+        self.pending.set(
+            1,
+            (report.hook_switch, report.phone_mute)
+                != (self.report.hook_switch, self.report.phone_mute),
+        );
+        self.report = *report;
+
+        match self.flush() {
+            Ok(()) | Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(UsbHidError::from(e)),
+        }
+    }
+
+    /// Call state LEDs, if they have changed since the last call
+    pub fn read_leds(&mut self) -> Option<HeadsetLedsReport> {
+        self.output_report_changed().ok().flatten()
+    }
+
+    /// Current call state LEDs
+    #[must_use]
+    pub fn leds(&self) -> HeadsetLedsReport {
+        self.output_report()
+    }
+
+    fn flush(&mut self) -> usb_device::Result<()> {
+        self.pending.flush(|index| {
+            let report = if index == 0 {
+                let consumer = u8::from(self.report.volume_up)
+                    | u8::from(self.report.volume_down) << 1
+                    | u8::from(self.report.mute) << 2;
+                [SPEAKERPHONE_CONSUMER_REPORT_ID, consumer]
+            } else {
+                let telephony =
+                    u8::from(self.report.hook_switch) | u8::from(self.report.phone_mute) << 1;
+                [SPEAKERPHONE_TELEPHONY_REPORT_ID, telephony]
+            };
+            self.interface.write_report(&report).map(|_| ())
+        })
+    }
+
+    fn receive_leds(&mut self) -> usb_device::Result<()> {
+        let interface = &mut self.interface;
+        self.leds
+            .receive(|leds| read_leds_report(interface, SPEAKERPHONE_LEDS_REPORT_ID, leds))
+    }
+}
+
+impl<B: UsbBus> OutputReports for Speakerphone<'_, B> {
+    type Report = HeadsetLedsReport;
+
+    fn read_output_report(&mut self) -> usb_device::Result<HeadsetLedsReport> {
+        self.receive_leds()?;
+        self.leds.read()
+    }
+
+    fn output_report(&self) -> HeadsetLedsReport {
+        self.leds.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<HeadsetLedsReport>> {
+        self.receive_leds()?;
+        Ok(self.leds.changed())
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for Speakerphone<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutBytes8, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.report = SpeakerphoneReport::default();
+        self.pending.clear();
+        self.leds.reset();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        if let Err(e) = self.receive_leds() {
+            warn!("Failed to receive LED report: {:?}", e);
+        }
+
+        match self.flush() {
+            Ok(()) | Err(UsbError::WouldBlock) => Ok(()),
+            Err(e) => Err(UsbHidError::from(e)),
+        }
+    }
+}
+
+pub struct SpeakerphoneConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes8, OutBytes8, ReportSingle>,
+}
+
+impl Default for SpeakerphoneConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(
+                unwrap!(InterfaceBuilder::new(SPEAKERPHONE_REPORT_DESCRIPTOR))
+                    .description("Speakerphone")
+                    .in_endpoint(10.millis())
+            )
+            .with_out_endpoint(10.millis()))
+            .build(),
+        )
+    }
+}
+
+impl<'a> SpeakerphoneConfig<'a> {
+    #[must_use]
+    pub fn new(interface: InterfaceConfig<'a, InBytes8, OutBytes8, ReportSingle>) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for SpeakerphoneConfig<'a> {
+    type Allocated = Speakerphone<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            report: SpeakerphoneReport::default(),
+            pending: PendingReports::default(),
+            leds: ReceivedReport::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::telephony::{HeadsetLedsReport, HeadsetReport};
+
+    #[test]
+    fn headset_report_bit_order() {
+        let bytes = HeadsetReport {
+            hook_switch: true,
+            flash: true,
+            ..Default::default()
+        }
+        .pack()
+        .unwrap();
+
+        assert_eq!(bytes, [0b101]);
+    }
+
+    #[test]
+    fn headset_leds_report_bit_order() {
+        let leds = HeadsetLedsReport::unpack(&[0b1_0011]).unwrap();
+
+        assert_eq!(
+            leds,
+            HeadsetLedsReport {
+                off_hook: true,
+                mute: true,
+                microphone: true,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//!HID throttle quadrant for flight simulation (HOTAS)
+use crate::page::Simulation;
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+
+/// Maximum number of levers in a [`ThrottleLayout`]
+pub const THROTTLE_MAX_LEVERS: usize = 6;
+/// Maximum number of buttons in a [`ThrottleLayout`]
+pub const THROTTLE_MAX_BUTTONS: u8 = 32;
+
+/// Resolution of the lever axes of a [`ThrottleQuadrant`]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ThrottleResolution {
+    /// 0 to 4095
+    Bits12,
+    /// 0 to 65535
+    Bits16,
+}
+
+impl ThrottleResolution {
+    /// Size of a lever axis in bits
+    #[must_use]
+    pub const fn bits(self) -> u8 {
+        match self {
+            Self::Bits12 => 12,
+            Self::Bits16 => 16,
+        }
+    }
+
+    /// Maximum lever value
+    #[must_use]
+    pub const fn max(self) -> u16 {
+        match self {
+            Self::Bits12 => 0x0FFF,
+            Self::Bits16 => 0xFFFF,
+        }
+    }
+}
+
+/// Report layout of a [`ThrottleQuadrant`]
+///
+/// Each lever is reported with its Simulation Controls usage, such as [`Simulation::Throttle`],
+/// [`Simulation::WingFlaps`] or [`Simulation::DiveBrake`] for spoilers and speed brakes. The
+/// same usage may be given more than once, for example one throttle per engine.
+///
+/// The report contains, in order:
+/// * `buttons` one bit buttons, padded to a whole byte
+/// * `levers` unsigned axes of `resolution` bits, padded to a whole byte
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ThrottleLayout {
+    pub levers: &'static [Simulation],
+    pub buttons: u8,
+    pub resolution: ThrottleResolution,
+}
+
+impl ThrottleLayout {
+    /// Length of the report in bytes
+    #[must_use]
+    pub const fn report_len(&self) -> usize {
+        self.buttons_len() + self.levers_len()
+    }
+
+    const fn buttons_len(&self) -> usize {
+        (self.buttons as usize).div_ceil(8)
+    }
+
+    const fn levers_len(&self) -> usize {
+        (self.levers.len() * self.resolution.bits() as usize).div_ceil(8)
+    }
+}
+
+/// Default [`ThrottleLayout`] - throttle, flaps and spoiler 16 bit levers with 32 buttons
+pub const DEFAULT_THROTTLE_LAYOUT: ThrottleLayout = ThrottleLayout {
+    levers: &[
+        Simulation::Throttle,
+        Simulation::WingFlaps,
+        Simulation::DiveBrake,
+    ],
+    buttons: 32,
+    resolution: ThrottleResolution::Bits16,
+};
+
+/// Report descriptor for a [`ThrottleLayout`], generated at compile time
+///
+/// ```
+/// use usbd_human_interface_device::device::throttle::*;
+/// use usbd_human_interface_device::page::Simulation;
+///
+/// static TWIN_ENGINE: ThrottleDescriptor = ThrottleDescriptor::new(ThrottleLayout {
+///     levers: &[Simulation::Throttle, Simulation::Throttle, Simulation::WingFlaps],
+///     buttons: 12,
+///     resolution: ThrottleResolution::Bits12,
+/// });
+///
+/// let config = ThrottleQuadrantConfig::from_descriptor(&TWIN_ENGINE);
+/// ```
+pub struct ThrottleDescriptor {
+    layout: ThrottleLayout,
+    descriptor: [u8; 80],
+    len: usize,
+}
+
+impl ThrottleDescriptor {
+    /// Generate the report descriptor for `layout`
+    ///
+    /// Panics, at compile time if used in a const context, if `layout` has no levers or exceeds
+    /// [`THROTTLE_MAX_LEVERS`] or [`THROTTLE_MAX_BUTTONS`]
+    #[must_use]
+    #[rustfmt::skip]
+    pub const fn new(layout: ThrottleLayout) -> Self {
+        core::assert!(!layout.levers.is_empty(), "no levers");
+        core::assert!(layout.levers.len() <= THROTTLE_MAX_LEVERS, "too many levers");
+        core::assert!(layout.buttons <= THROTTLE_MAX_BUTTONS, "too many buttons");
+
+        let mut d = Self {
+            layout,
+            descriptor: [0; 80],
+            len: 0,
+        };
+
+        d.push(&[
+            0x05, 0x01,                 // Usage Page (Generic Desktop),
+            0x09, 0x04,                 // Usage (Joystick),
+            0xA1, 0x01,                 // Collection (Application),
+        ]);
+
+        if layout.buttons > 0 {
+            d.push(&[
+                0x05, 0x09,             //   Usage Page (Button),
+                0x19, 0x01,             //   Usage Minimum (1),
+                0x29, layout.buttons,   //   Usage Maximum (buttons),
+                0x15, 0x00,             //   Logical Minimum (0),
+                0x25, 0x01,             //   Logical Maximum (1),
+                0x75, 0x01,             //   Report Size (1),
+                0x95, layout.buttons,   //   Report Count (buttons),
+                0x81, 0x02,             //   Input (Data, Variable, Absolute),
+            ]);
+            d.pad(layout.buttons, 1);
+        }
+
+        d.push(&[
+            0x05, 0x02,                 //   Usage Page (Simulation Controls),
+        ]);
+
+        let mut i = 0;
+        while i < layout.levers.len() {
+            d.push(&[
+                0x09, layout.levers[i] as u8, //   Usage (lever),
+            ]);
+            i += 1;
+        }
+
+        match layout.resolution {
+            ThrottleResolution::Bits12 => d.push(&[
+                0x15, 0x00,             //   Logical Minimum (0),
+                0x26, 0xFF, 0x0F,       //   Logical Maximum (4095),
+            ]),
+            ThrottleResolution::Bits16 => d.push(&[
+                0x15, 0x00,             //   Logical Minimum (0),
+                0x27, 0xFF, 0xFF, 0x00, 0x00, //   Logical Maximum (65535),
+            ]),
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let levers = layout.levers.len() as u8;
+        d.push(&[
+            0x75, layout.resolution.bits(), //   Report Size (resolution),
+            0x95, levers,               //   Report Count (levers),
+            0x81, 0x02,                 //   Input (Data, Variable, Absolute),
+        ]);
+        d.pad(levers, layout.resolution.bits());
+
+        d.push(&[
+            0xC0,                       // End Collection
+        ]);
+
+        d
+    }
+
+    /// Pad `count` fields of `size` bits to a whole byte
+    #[rustfmt::skip]
+    const fn pad(&mut self, count: u8, size: u8) {
+        let bits = (count * size) % 8;
+        if bits != 0 {
+            self.push(&[
+                0x75, 8 - bits,         //   Report Size (padding),
+                0x95, 0x01,             //   Report Count (1),
+                0x81, 0x03,             //   Input (Constant, Variable, Absolute),
+            ]);
+        }
+    }
+
+    const fn push(&mut self, items: &[u8]) {
+        let mut i = 0;
+        while i < items.len() {
+            self.descriptor[self.len] = items[i];
+            self.len += 1;
+            i += 1;
+        }
+    }
+
+    #[must_use]
+    pub const fn layout(&self) -> ThrottleLayout {
+        self.layout
+    }
+
+    #[must_use]
+    pub fn descriptor(&self) -> &[u8] {
+        &self.descriptor[..self.len]
+    }
+}
+
+/// Report descriptor for [`DEFAULT_THROTTLE_LAYOUT`]
+pub static DEFAULT_THROTTLE_DESCRIPTOR: ThrottleDescriptor =
+    ThrottleDescriptor::new(DEFAULT_THROTTLE_LAYOUT);
+
+/// Report for a [`ThrottleQuadrant`]
+///
+/// Only the buttons and levers included in the device's [`ThrottleLayout`] are sent. `levers`
+/// are in the order given by [`ThrottleLayout::levers`] and are clamped to
+/// [`ThrottleResolution::max`]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct ThrottleReport {
+    /// Button state, bit 0 is button 1
+    pub buttons: u32,
+    pub levers: [u16; THROTTLE_MAX_LEVERS],
+}
+
+impl ThrottleReport {
+    /// Serialize the report for `layout` into `buffer`, returning the length of the report
+    pub fn pack_into(
+        &self,
+        layout: &ThrottleLayout,
+        buffer: &mut [u8],
+    ) -> Result<usize, UsbHidError> {
+        let len = layout.report_len();
+        if buffer.len() < len {
+            error!("Buffer too small for ThrottleReport");
+            return Err(UsbHidError::SerializationError);
+        }
+
+        let buttons_len = layout.buttons_len();
+        let buttons = if layout.buttons < 32 {
+            self.buttons & ((1 << layout.buttons) - 1)
+        } else {
+            self.buttons
+        };
+        buffer[..buttons_len].copy_from_slice(&buttons.to_le_bytes()[..buttons_len]);
+
+        let bits = usize::from(layout.resolution.bits());
+        let levers = self.levers[..layout.levers.len()]
+            .iter()
+            .enumerate()
+            .fold(0u128, |acc, (i, lever)| {
+                acc | u128::from((*lever).min(layout.resolution.max())) << (i * bits)
+            });
+        buffer[buttons_len..len].copy_from_slice(&levers.to_le_bytes()[..layout.levers_len()]);
+
+        Ok(len)
+    }
+}
+
+/// Throttle quadrant with a configurable number of levers and buttons
+///
+/// See [`ThrottleDescriptor`]
+pub struct ThrottleQuadrant<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes16, OutNone, ReportSingle>,
+    layout: ThrottleLayout,
+}
+
+impl<B: UsbBus> ThrottleQuadrant<'_, B> {
+    pub fn write_report(&mut self, report: &ThrottleReport) -> Result<(), UsbHidError> {
+        let mut data = [0u8; 16];
+        let len = report.pack_into(&self.layout, &mut data)?;
+        self.interface
+            .write_report(&data[..len])
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+
+    #[must_use]
+    pub fn layout(&self) -> &ThrottleLayout {
+        &self.layout
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for ThrottleQuadrant<'a, B> {
+    type I = Interface<'a, B, InBytes16, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {}
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+}
+
+pub struct ThrottleQuadrantConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes16, OutNone, ReportSingle>,
+    layout: ThrottleLayout,
+}
+
+impl Default for ThrottleQuadrantConfig<'_> {
+    fn default() -> Self {
+        Self::from_descriptor(&DEFAULT_THROTTLE_DESCRIPTOR)
+    }
+}
+
+impl<'a> ThrottleQuadrantConfig<'a> {
+    /// `interface` must use a report descriptor matching `layout`, see [`ThrottleDescriptor`]
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes16, OutNone, ReportSingle>,
+        layout: ThrottleLayout,
+    ) -> Self {
+        Self { interface, layout }
+    }
+
+    /// Configuration with the default interface settings for a generated descriptor
+    #[must_use]
+    pub fn from_descriptor(descriptor: &'static ThrottleDescriptor) -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                descriptor.descriptor()
+            ))
+            .description("Throttle Quadrant")
+            .in_endpoint(10.millis()))
+            .without_out_endpoint()
+            .build(),
+            descriptor.layout(),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for ThrottleQuadrantConfig<'a> {
+    type Allocated = ThrottleQuadrant<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            layout: self.layout,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use crate::device::throttle::{
+        ThrottleDescriptor, ThrottleLayout, ThrottleReport, ThrottleResolution,
+        DEFAULT_THROTTLE_DESCRIPTOR, DEFAULT_THROTTLE_LAYOUT,
+    };
+    use crate::page::Simulation;
+
+    #[test]
+    fn default_throttle_descriptor() {
+        let descriptor = DEFAULT_THROTTLE_DESCRIPTOR.descriptor();
+        assert_eq!(descriptor[..6], [0x05, 0x01, 0x09, 0x04, 0xA1, 0x01]);
+        assert_eq!(descriptor.last(), Some(&0xC0));
+        assert_eq!(DEFAULT_THROTTLE_LAYOUT.report_len(), 4 + 6);
+    }
+
+    #[test]
+    fn twelve_bit_report_layout() {
+        static DESCRIPTOR: ThrottleDescriptor = ThrottleDescriptor::new(ThrottleLayout {
+            levers: &[
+                Simulation::Throttle,
+                Simulation::Throttle,
+                Simulation::WingFlaps,
+            ],
+            buttons: 4,
+            resolution: ThrottleResolution::Bits12,
+        });
+
+        let report = ThrottleReport {
+            buttons: 0xFF,
+            levers: [0x0123, 0x0ABC, u16::MAX, 1, 1, 1],
+        };
+
+        let mut buffer = [0u8; 16];
+        let len = report.pack_into(&DESCRIPTOR.layout(), &mut buffer).unwrap();
+
+        assert_eq!(buffer[..len], [0x0F, 0x23, 0xC1, 0xAB, 0xFF, 0x0F]);
+    }
+}
@@ -0,0 +1,609 @@
+//!Windows Precision Touchpad
+use crate::device::mouse::BootMouseReport;
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Report ID of the [`PrecisionTouchpadReport`] input report
+pub const TOUCHPAD_INPUT_REPORT_ID: u8 = 0x01;
+/// Report ID of the device capabilities feature report
+pub const TOUCHPAD_CAPABILITIES_REPORT_ID: u8 = 0x02;
+/// Report ID of the certification status feature report
+pub const TOUCHPAD_CERTIFICATION_REPORT_ID: u8 = 0x03;
+/// Report ID of the input mode feature report
+pub const TOUCHPAD_INPUT_MODE_REPORT_ID: u8 = 0x04;
+/// Report ID of the selective reporting feature report
+pub const TOUCHPAD_FUNCTION_SWITCH_REPORT_ID: u8 = 0x05;
+/// Report ID of the legacy mouse input report
+pub const TOUCHPAD_MOUSE_REPORT_ID: u8 = 0x06;
+
+/// Certification status feature report for devices without a Microsoft issued certification
+/// blob - report ID followed by 256 zero bytes
+pub static TOUCHPAD_UNCERTIFIED_STATUS: [u8; 257] = {
+    let mut report = [0u8; 257];
+    report[0] = TOUCHPAD_CERTIFICATION_REPORT_ID;
+    report
+};
+
+/// Windows Precision Touchpad report descriptor
+///
+/// Contains three top level collections:
+/// * Touch Pad - input report 1 with five contacts, scan time, contact count and button, feature
+///   report 2 with the contact count maximum and pad type and feature report 3 with the
+///   certification status blob
+/// * Device Configuration - feature report 4 selecting the input mode and feature report 5
+///   selecting which of the surface and button are reported
+/// * Mouse - input report 6, used until the host selects touchpad input mode
+///
+/// Contact X/Y are in the range 0-4095 over a 10cm x 6cm surface. Devices with other dimensions
+/// should supply their own descriptor via [`PrecisionTouchpadConfig::new`].
+///
+/// Reference: <https://learn.microsoft.com/en-us/windows-hardware/design/component-guidelines/touchpad-required-hid-top-level-collections>
+#[rustfmt::skip]
+pub const PRECISION_TOUCHPAD_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0D,        // Usage Page (Digitizers),
+    0x09, 0x05,        // Usage (Touch Pad),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+
+    // contact 1
+    0x05, 0x0D,        //   Usage Page (Digitizers),
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x09, 0x47,        //     Usage (Confidence),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Contact Identifier),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x26, 0xFF, 0x0F,  //     Logical Maximum (4095),
+    0x75, 0x10,        //     Report Size (16),
+    0x55, 0x0E,        //     Unit Exponent (-2),
+    0x65, 0x11,        //     Unit (Centimeter),
+    0x35, 0x00,        //     Physical Minimum (0),
+    0x46, 0xE8, 0x03,  //     Physical Maximum (1000),
+    0x09, 0x30,        //     Usage (X),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x46, 0x58, 0x02,  //     Physical Maximum (600),
+    0x09, 0x31,        //     Usage (Y),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x45, 0x00,        //     Physical Maximum (0),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0x65, 0x00,        //     Unit (None),
+    0xC0,              //   End Collection
+
+    // contact 2
+    0x05, 0x0D,        //   Usage Page (Digitizers),
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x09, 0x47,        //     Usage (Confidence),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Contact Identifier),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x26, 0xFF, 0x0F,  //     Logical Maximum (4095),
+    0x75, 0x10,        //     Report Size (16),
+    0x55, 0x0E,        //     Unit Exponent (-2),
+    0x65, 0x11,        //     Unit (Centimeter),
+    0x35, 0x00,        //     Physical Minimum (0),
+    0x46, 0xE8, 0x03,  //     Physical Maximum (1000),
+    0x09, 0x30,        //     Usage (X),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x46, 0x58, 0x02,  //     Physical Maximum (600),
+    0x09, 0x31,        //     Usage (Y),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x45, 0x00,        //     Physical Maximum (0),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0x65, 0x00,        //     Unit (None),
+    0xC0,              //   End Collection
+
+    // contact 3
+    0x05, 0x0D,        //   Usage Page (Digitizers),
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x09, 0x47,        //     Usage (Confidence),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Contact Identifier),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x26, 0xFF, 0x0F,  //     Logical Maximum (4095),
+    0x75, 0x10,        //     Report Size (16),
+    0x55, 0x0E,        //     Unit Exponent (-2),
+    0x65, 0x11,        //     Unit (Centimeter),
+    0x35, 0x00,        //     Physical Minimum (0),
+    0x46, 0xE8, 0x03,  //     Physical Maximum (1000),
+    0x09, 0x30,        //     Usage (X),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x46, 0x58, 0x02,  //     Physical Maximum (600),
+    0x09, 0x31,        //     Usage (Y),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x45, 0x00,        //     Physical Maximum (0),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0x65, 0x00,        //     Unit (None),
+    0xC0,              //   End Collection
+
+    // contact 4
+    0x05, 0x0D,        //   Usage Page (Digitizers),
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x09, 0x47,        //     Usage (Confidence),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Contact Identifier),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x26, 0xFF, 0x0F,  //     Logical Maximum (4095),
+    0x75, 0x10,        //     Report Size (16),
+    0x55, 0x0E,        //     Unit Exponent (-2),
+    0x65, 0x11,        //     Unit (Centimeter),
+    0x35, 0x00,        //     Physical Minimum (0),
+    0x46, 0xE8, 0x03,  //     Physical Maximum (1000),
+    0x09, 0x30,        //     Usage (X),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x46, 0x58, 0x02,  //     Physical Maximum (600),
+    0x09, 0x31,        //     Usage (Y),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x45, 0x00,        //     Physical Maximum (0),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0x65, 0x00,        //     Unit (None),
+    0xC0,              //   End Collection
+
+    // contact 5
+    0x05, 0x0D,        //   Usage Page (Digitizers),
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x09, 0x47,        //     Usage (Confidence),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Contact Identifier),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x26, 0xFF, 0x0F,  //     Logical Maximum (4095),
+    0x75, 0x10,        //     Report Size (16),
+    0x55, 0x0E,        //     Unit Exponent (-2),
+    0x65, 0x11,        //     Unit (Centimeter),
+    0x35, 0x00,        //     Physical Minimum (0),
+    0x46, 0xE8, 0x03,  //     Physical Maximum (1000),
+    0x09, 0x30,        //     Usage (X),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x46, 0x58, 0x02,  //     Physical Maximum (600),
+    0x09, 0x31,        //     Usage (Y),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x45, 0x00,        //     Physical Maximum (0),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0x65, 0x00,        //     Unit (None),
+    0xC0,              //   End Collection
+
+    0x05, 0x0D,        //   Usage Page (Digitizers),
+    0x55, 0x0C,        //   Unit Exponent (-4),
+    0x66, 0x01, 0x10,  //   Unit (Seconds),
+    0x47, 0xFF, 0xFF, 0x00, 0x00,//   Physical Maximum (65535),
+    0x27, 0xFF, 0xFF, 0x00, 0x00,//   Logical Maximum (65535),
+    0x75, 0x10,        //   Report Size (16),
+    0x95, 0x01,        //   Report Count (1),
+    0x09, 0x56,        //   Usage (Scan Time),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x45, 0x00,        //   Physical Maximum (0),
+    0x55, 0x00,        //   Unit Exponent (0),
+    0x65, 0x00,        //   Unit (None),
+    0x09, 0x54,        //   Usage (Contact Count),
+    0x25, 0x7F,        //   Logical Maximum (127),
+    0x75, 0x08,        //   Report Size (8),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x05, 0x09,        //   Usage Page (Buttons),
+    0x09, 0x01,        //   Usage (Button 1),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x95, 0x07,        //   Report Count (7),
+    0x81, 0x03,        //   Input (Constant, Variable, Absolute),
+
+    0x85, 0x02,        //   Report ID (2),
+    0x05, 0x0D,        //   Usage Page (Digitizers),
+    0x09, 0x55,        //   Usage (Contact Count Maximum),
+    0x09, 0x59,        //   Usage (Pad Type),
+    0x25, 0x0F,        //   Logical Maximum (15),
+    0x75, 0x04,        //   Report Size (4),
+    0x95, 0x02,        //   Report Count (2),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+
+    0x06, 0x00, 0xFF,  //   Usage Page (Vendor Defined 0xFF00),
+    0x85, 0x03,        //   Report ID (3),
+    0x09, 0xC5,        //   Usage (Certification Status),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x75, 0x08,        //   Report Size (8),
+    0x96, 0x00, 0x01,  //   Report Count (256),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0xC0,              // End Collection
+
+    0x05, 0x0D,        // Usage Page (Digitizers),
+    0x09, 0x0E,        // Usage (Device Configuration),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x04,        //   Report ID (4),
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x52,        //     Usage (Input Mode),
+    0x25, 0x0A,        //     Logical Maximum (10),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x00,        //   Collection (Physical),
+    0x85, 0x05,        //     Report ID (5),
+    0x09, 0x57,        //     Usage (Surface Switch),
+    0x09, 0x58,        //     Usage (Button Switch),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0xB1, 0x03,        //     Feature (Constant, Variable, Absolute),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x02,        // Usage (Mouse),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x06,        //   Report ID (6),
+    0x09, 0x01,        //   Usage (Pointer),
+    0xA1, 0x00,        //   Collection (Physical),
+    0x05, 0x09,        //     Usage Page (Buttons),
+    0x19, 0x01,        //     Usage Minimum (1),
+    0x29, 0x02,        //     Usage Maximum (2),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x15, 0x81,        //     Logical Minimum (-127),
+    0x25, 0x7F,        //     Logical Maximum (127),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x06,        //     Input (Data, Variable, Relative),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+/// Touchpad pad type, reported to the host in the device capabilities feature report
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum PadType {
+    /// Click-pad, the whole surface is depressible
+    #[default]
+    Depressible = 0x00,
+    /// Pressure-pad, clicks are detected by pressure
+    Pressure = 0x01,
+    /// Surface is not depressible, separate buttons are reported
+    NonDepressible = 0x02,
+}
+
+/// Input mode selected by the host via the input mode feature report
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum InputMode {
+    /// Legacy mouse reports, the default until configured by the host
+    #[default]
+    Mouse = 0x00,
+    /// Precision touchpad reports
+    Touchpad = 0x03,
+}
+
+/// A single touchpad contact
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "6")]
+pub struct TouchpadContact {
+    /// Cleared if the contact is not an intended finger, e.g. a palm
+    #[packed_field(bits = "7")]
+    pub confidence: bool,
+    #[packed_field(bits = "6")]
+    pub tip_switch: bool,
+    #[packed_field(bytes = "1")]
+    pub contact_id: u8,
+    /// Absolute X position, 0-4095
+    #[packed_field(bytes = "2..=3")]
+    pub x: u16,
+    /// Absolute Y position, 0-4095
+    #[packed_field(bytes = "4..=5")]
+    pub y: u16,
+}
+
+/// Precision touchpad input report, excluding the report ID
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "34")]
+pub struct PrecisionTouchpadReport {
+    #[packed_field(bytes = "0..30", element_size_bytes = "6")]
+    pub contacts: [TouchpadContact; 5],
+    /// Relative scan time in units of 100us
+    #[packed_field(bytes = "30..=31")]
+    pub scan_time: u16,
+    #[packed_field(bytes = "32")]
+    pub contact_count: u8,
+    #[packed_field(bits = "271")]
+    pub button: bool,
+}
+
+/// Windows Precision Touchpad
+///
+/// Handles the capabilities, certification, input mode and selective reporting feature reports.
+/// Until the host selects [`InputMode::Touchpad`] the device should report via
+/// [`PrecisionTouchpad::write_mouse_report`].
+pub struct PrecisionTouchpad<'a, B: UsbBus> {
+    interface: Interface<'a, B, InBytes64, OutNone, ReportSingle>,
+    contact_count_maximum: u8,
+    pad_type: PadType,
+    certification_status: &'static [u8],
+    input_mode: InputMode,
+    surface_switch: bool,
+    button_switch: bool,
+}
+
+impl<B: UsbBus> PrecisionTouchpad<'_, B> {
+    pub fn write_report(&mut self, report: &PrecisionTouchpadReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|_| {
+            error!("Error packing PrecisionTouchpadReport");
+            UsbHidError::SerializationError
+        })?;
+
+        let mut buffer = [0u8; 35];
+        buffer[0] = TOUCHPAD_INPUT_REPORT_ID;
+        buffer[1..].copy_from_slice(&data);
+
+        self.interface
+            .write_report(&buffer)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+
+    pub fn write_mouse_report(&mut self, report: &BootMouseReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|_| {
+            error!("Error packing BootMouseReport");
+            UsbHidError::SerializationError
+        })?;
+
+        let mut buffer = [0u8; 4];
+        buffer[0] = TOUCHPAD_MOUSE_REPORT_ID;
+        buffer[1..].copy_from_slice(&data);
+
+        self.interface
+            .write_report(&buffer)
+            .map(|_| ())
+            .map_err(UsbHidError::from)
+    }
+
+    /// Input mode selected by the host
+    #[must_use]
+    pub fn input_mode(&self) -> InputMode {
+        self.input_mode
+    }
+
+    /// `false` if the host has requested that surface contacts are not reported
+    #[must_use]
+    pub fn surface_reporting(&self) -> bool {
+        self.surface_switch
+    }
+
+    /// `false` if the host has requested that button state is not reported
+    #[must_use]
+    pub fn button_reporting(&self) -> bool {
+        self.button_switch
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for PrecisionTouchpad<'a, B> {
+    type I = Interface<'a, B, InBytes64, OutNone, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        &mut self.interface
+    }
+
+    fn reset(&mut self) {
+        self.input_mode = InputMode::Mouse;
+        self.surface_switch = true;
+        self.button_switch = true;
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        Ok(())
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        let value = match report_id {
+            TOUCHPAD_CAPABILITIES_REPORT_ID => {
+                (u8::from(self.pad_type) << 4) | (self.contact_count_maximum & 0x0F)
+            }
+            TOUCHPAD_INPUT_MODE_REPORT_ID => self.input_mode.into(),
+            TOUCHPAD_FUNCTION_SWITCH_REPORT_ID => {
+                u8::from(self.surface_switch) | (u8::from(self.button_switch) << 1)
+            }
+            _ => return Err(UsbError::Unsupported),
+        };
+        if data.len() < 2 {
+            return Err(UsbError::BufferOverflow);
+        }
+        data[0] = report_id;
+        data[1] = value;
+        Ok(2)
+    }
+
+    fn get_static_feature_report(&mut self, report_id: u8) -> Option<&'static [u8]> {
+        (report_id == TOUCHPAD_CERTIFICATION_REPORT_ID).then_some(self.certification_status)
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        let [id, value, ..] = *data else {
+            return Err(UsbError::ParseError);
+        };
+        if id != report_id {
+            return Err(UsbError::ParseError);
+        }
+
+        match report_id {
+            TOUCHPAD_INPUT_MODE_REPORT_ID => {
+                self.input_mode = InputMode::try_from(value).map_err(|_| UsbError::ParseError)?;
+                info!("Set touchpad input mode {:?}", self.input_mode);
+                Ok(())
+            }
+            TOUCHPAD_FUNCTION_SWITCH_REPORT_ID => {
+                self.surface_switch = value & 0x01 != 0;
+                self.button_switch = value & 0x02 != 0;
+                info!(
+                    "Set touchpad surface reporting {}, button reporting {}",
+                    self.surface_switch, self.button_switch
+                );
+                Ok(())
+            }
+            _ => Err(UsbError::Unsupported),
+        }
+    }
+}
+
+pub struct PrecisionTouchpadConfig<'a> {
+    interface: InterfaceConfig<'a, InBytes64, OutNone, ReportSingle>,
+    contact_count_maximum: u8,
+    pad_type: PadType,
+    certification_status: &'static [u8],
+}
+
+impl Default for PrecisionTouchpadConfig<'_> {
+    fn default() -> Self {
+        Self::new(
+            unwrap!(unwrap!(InterfaceBuilder::with_static_descriptor(
+                PRECISION_TOUCHPAD_REPORT_DESCRIPTOR
+            ))
+            .description("Touchpad")
+            .in_endpoint(5.millis()))
+            .without_out_endpoint()
+            .build(),
+            5,
+            PadType::Depressible,
+            &TOUCHPAD_UNCERTIFIED_STATUS,
+        )
+    }
+}
+
+impl<'a> PrecisionTouchpadConfig<'a> {
+    /// * `contact_count_maximum` - maximum number of simultaneous contacts, at most 15
+    /// * `certification_status` - certification status feature report, the report ID
+    ///   [`TOUCHPAD_CERTIFICATION_REPORT_ID`] followed by the 256 byte blob
+    #[must_use]
+    pub fn new(
+        interface: InterfaceConfig<'a, InBytes64, OutNone, ReportSingle>,
+        contact_count_maximum: u8,
+        pad_type: PadType,
+        certification_status: &'static [u8],
+    ) -> Self {
+        Self {
+            interface,
+            contact_count_maximum,
+            pad_type,
+            certification_status,
+        }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for PrecisionTouchpadConfig<'a> {
+    type Allocated = PrecisionTouchpad<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            contact_count_maximum: self.contact_count_maximum,
+            pad_type: self.pad_type,
+            certification_status: self.certification_status,
+            input_mode: InputMode::Mouse,
+            surface_switch: true,
+            button_switch: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::device::touchpad::{PrecisionTouchpadReport, TouchpadContact};
+
+    #[test]
+    fn precision_touchpad_report_layout() {
+        let mut report = PrecisionTouchpadReport {
+            scan_time: 0x1234,
+            contact_count: 1,
+            button: true,
+            ..Default::default()
+        };
+        report.contacts[0] = TouchpadContact {
+            confidence: true,
+            tip_switch: true,
+            contact_id: 3,
+            x: 0x0FFF,
+            y: 0x0001,
+        };
+
+        let bytes = report.pack().unwrap();
+
+        assert_eq!(bytes[0..6], [0x03, 3, 0xFF, 0x0F, 0x01, 0x00]);
+        assert_eq!(bytes[30..34], [0x34, 0x12, 1, 0x01]);
+    }
+}
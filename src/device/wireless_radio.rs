@@ -0,0 +1,193 @@
+//!HID wireless radio controls - airplane mode button with radio LED
+use crate::usb_class::prelude::*;
+use fugit::ExtU32;
+use packed_struct::prelude::*;
+use usb_device::bus::UsbBus;
+use usb_device::class_prelude::UsbBusAllocator;
+use usb_device::UsbError;
+
+/// Wireless radio controls report descriptor - a radio button input and a radio LED output
+///
+/// Recognised by the Windows radio management (airplane mode) driver
+#[rustfmt::skip]
+pub const WIRELESS_RADIO_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x0C,        // Usage (Wireless Radio Controls),
+    0xA1, 0x01,        // Collection (Application),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x09, 0xC6,        //   Usage (Wireless Radio Button),
+    0x95, 0x01,        //   Report Count (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x81, 0x06,        //   Input (Data, Variable, Relative),
+    0x75, 0x07,        //   Report Size (7),
+    0x81, 0x03,        //   Input (Constant, Variable, Absolute),
+    0x09, 0xC7,        //   Usage (Wireless Radio LED),
+    0x75, 0x01,        //   Report Size (1),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x75, 0x07,        //   Report Size (7),
+    0x91, 0x03,        //   Output (Constant, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(bit_numbering = "lsb0", size_bytes = "1")]
+pub struct WirelessRadioReport {
+    /// Radio button pressed, the host toggles airplane mode on each press
+    #[packed_field(bits = "0")]
+    pub button: bool,
+}
+
+/// Radio LED state set by the host, lit while the radios are on
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(bit_numbering = "lsb0", size_bytes = "1")]
+pub struct WirelessRadioLedReport {
+    #[packed_field(bits = "0")]
+    pub led: bool,
+}
+
+impl OutputReport for WirelessRadioLedReport {
+    fn parse(data: &[u8]) -> usb_device::Result<Self> {
+        Self::unpack_from_slice(data).map_err(|_| UsbError::ParseError)
+    }
+}
+
+/// Wireless radio controls
+///
+/// Duplicate reports are suppressed by [`WirelessRadio::write_report`], returning
+/// [`UsbHidError::Duplicate`], so the button state can be written on every scan of the input
+pub struct WirelessRadio<'a, B: UsbBus> {
+    interface: ManagedIdleInterface<'a, B, WirelessRadioReport, InBytes8, OutBytes8>,
+    led: ReceivedReport<WirelessRadioLedReport, 1>,
+}
+
+impl<B: UsbBus> WirelessRadio<'_, B> {
+    pub fn write_report(&mut self, report: &WirelessRadioReport) -> Result<(), UsbHidError> {
+        self.interface.write_report(report)
+    }
+
+    /// Read the LED report last received from the host, returns [`UsbError::WouldBlock`] if none
+    /// has been received since the last read, see [`OutputReports::read_output_report`]
+    pub fn read_report(&mut self) -> usb_device::Result<WirelessRadioLedReport> {
+        self.read_output_report()
+    }
+}
+
+impl<B: UsbBus> OutputReports for WirelessRadio<'_, B> {
+    type Report = WirelessRadioLedReport;
+
+    fn read_output_report(&mut self) -> usb_device::Result<WirelessRadioLedReport> {
+        self.led.receive(|data| self.interface.read_report(data))?;
+        self.led.read()
+    }
+
+    fn output_report(&self) -> WirelessRadioLedReport {
+        self.led.last()
+    }
+
+    fn output_report_changed(&mut self) -> usb_device::Result<Option<WirelessRadioLedReport>> {
+        self.led.receive(|data| self.interface.read_report(data))?;
+        Ok(self.led.changed())
+    }
+}
+
+impl<'a, B: UsbBus> DeviceClass<'a> for WirelessRadio<'a, B> {
+    type I = Interface<'a, B, InBytes8, OutBytes8, ReportSingle>;
+
+    fn interface(&mut self) -> &mut Self::I {
+        self.interface.interface()
+    }
+
+    fn reset(&mut self) {
+        self.interface.reset();
+        self.led = ReceivedReport::default();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        if let Err(e) = self.led.receive(|data| self.interface.read_report(data)) {
+            warn!("Failed to receive LED report: {:?}", e);
+        }
+        self.interface.tick()
+    }
+
+    fn get_input_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        self.interface.get_input_report(report_id, data)
+    }
+
+    fn endpoint_halt_cleared(&mut self) {
+        self.interface.endpoint_halt_cleared();
+    }
+}
+
+pub struct WirelessRadioConfig<'a> {
+    interface: ManagedIdleInterfaceConfig<'a, WirelessRadioReport, InBytes8, OutBytes8>,
+}
+
+impl Default for WirelessRadioConfig<'_> {
+    fn default() -> Self {
+        Self::new(ManagedIdleInterfaceConfig::new(
+            unwrap!(
+                unwrap!(InterfaceBuilder::new(WIRELESS_RADIO_REPORT_DESCRIPTOR))
+                    .description("Wireless Radio Controls")
+                    .in_endpoint(10.millis())
+            )
+            .without_out_endpoint()
+            .build(),
+        ))
+    }
+}
+
+impl<'a> WirelessRadioConfig<'a> {
+    #[must_use]
+    pub fn new(
+        interface: ManagedIdleInterfaceConfig<'a, WirelessRadioReport, InBytes8, OutBytes8>,
+    ) -> Self {
+        Self { interface }
+    }
+}
+
+impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for WirelessRadioConfig<'a> {
+    type Allocated = WirelessRadio<'a, B>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(Self::Allocated {
+            interface: self.interface.try_allocate(usb_alloc)?,
+            led: ReceivedReport::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use packed_struct::prelude::*;
+
+    use crate::descriptor::parse::{parse, ReportSizes};
+    use crate::device::wireless_radio::{
+        WirelessRadioLedReport, WirelessRadioReport, WIRELESS_RADIO_REPORT_DESCRIPTOR,
+    };
+
+    #[test]
+    fn wireless_radio_reports_match_descriptor() {
+        let reports = parse(WIRELESS_RADIO_REPORT_DESCRIPTOR).unwrap();
+
+        assert!(!reports.uses_report_ids());
+        assert_eq!(
+            reports.report(0),
+            Some(&ReportSizes {
+                report_id: 0,
+                input_bits: 8,
+                output_bits: 8,
+                feature_bits: 0,
+            })
+        );
+        assert_eq!(WirelessRadioReport { button: true }.pack().unwrap(), [0x01]);
+        assert_eq!(
+            WirelessRadioLedReport::unpack(&[0x01]).unwrap(),
+            WirelessRadioLedReport { led: true }
+        );
+    }
+}
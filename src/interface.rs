@@ -1,6 +1,8 @@
 //! Human Interface Device Interfaces
+use crate::descriptor::ms_os::MsOs20Function;
+use crate::descriptor::parse::{parse, PARSE_MAX_REPORTS};
 use crate::descriptor::{
-    DescriptorType, HidProtocol, InterfaceProtocol, InterfaceSubClass, COUNTRY_CODE_NOT_SUPPORTED,
+    CountryCode, DescriptorType, HidProtocol, InterfaceProtocol, InterfaceSubClass,
     SPEC_VERSION_1_11, USB_CLASS_HID,
 };
 use crate::device::DeviceClass;
@@ -19,6 +21,7 @@ use usb_device::bus::{StringIndex, UsbBus, UsbBusAllocator};
 use usb_device::class_prelude::*;
 use usb_device::class_prelude::{DescriptorWriter, InterfaceNumber};
 use usb_device::descriptor::lang_id::LangID;
+use usb_device::endpoint::{Endpoint, EndpointDirection};
 use usb_device::UsbError;
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -35,14 +38,32 @@ struct HidDescriptorBody {
 
 pub trait UsbAllocatable<'a, B: UsbBus> {
     type Allocated;
-    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated;
+
+    /// Allocate the interfaces, endpoints and strings of the device
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bus can't allocate an endpoint, see [`UsbAllocatable::try_allocate`]
+    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated
+    where
+        Self: Sized,
+    {
+        unwrap!(self.try_allocate(usb_alloc))
+    }
+
+    /// Allocate the interfaces, endpoints and strings of the device, failing with
+    /// [`UsbHidBuilderError::EndpointAllocation`] if the bus can't allocate an endpoint, such as
+    /// when it has run out of endpoints or doesn't support a max packet size
+    ///
+    /// Anything allocated before the failure stays allocated.
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated>;
 }
 
 impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for HNil {
     type Allocated = Self;
 
-    fn allocate(self, _: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        self
+    fn try_allocate(self, _: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(self)
     }
 }
 
@@ -54,21 +75,209 @@ where
 {
     type Allocated = HCons<C::Allocated, Tail::Allocated>;
 
-    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        HCons {
-            head: self.head.allocate(usb_alloc),
-            tail: self.tail.allocate(usb_alloc),
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(HCons {
+            head: self.head.try_allocate(usb_alloc)?,
+            tail: self.tail.try_allocate(usb_alloc)?,
+        })
+    }
+}
+
+/// Implement [`UsbAllocatable`] for a tuple of configs, allocated in order
+macro_rules! tuple_allocatable {
+    ($($C:ident $idx:tt),+) => {
+        impl<'a, B, $($C),+> UsbAllocatable<'a, B> for ($($C,)+)
+        where
+            B: UsbBus + 'a,
+            $($C: UsbAllocatable<'a, B>,)+
+        {
+            type Allocated = ($($C::Allocated,)+);
+
+            fn try_allocate(
+                self,
+                usb_alloc: &'a UsbBusAllocator<B>,
+            ) -> BuilderResult<Self::Allocated> {
+                Ok(($(self.$idx.try_allocate(usb_alloc)?,)+))
+            }
+        }
+    };
+}
+
+tuple_allocatable!(C0 0);
+tuple_allocatable!(C0 0, C1 1);
+tuple_allocatable!(C0 0, C1 1, C2 2);
+tuple_allocatable!(C0 0, C1 1, C2 2, C3 3);
+
+impl<'a, B, C, const N: usize> UsbAllocatable<'a, B> for Vec<C, N>
+where
+    B: UsbBus + 'a,
+    C: UsbAllocatable<'a, B>,
+{
+    type Allocated = Vec<C::Allocated, N>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        let mut allocated = Vec::new();
+        for config in self {
+            // Can't overflow, the configs and devices have the same capacity
+            unwrap!(allocated.push(config.try_allocate(usb_alloc)?).ok());
+        }
+        Ok(allocated)
+    }
+}
+
+impl<'a, B, C, const N: usize> UsbAllocatable<'a, B> for [C; N]
+where
+    B: UsbBus + 'a,
+    C: UsbAllocatable<'a, B>,
+{
+    type Allocated = [C::Allocated; N];
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        let allocated = Vec::<_, N>::from_iter(self).try_allocate(usb_alloc)?;
+        Ok(unwrap!(allocated.into_array().ok()))
+    }
+}
+
+/// Length of the body of a HID descriptor, after its length and type, with a report and a physical
+/// descriptor
+pub const MAX_HID_DESCRIPTOR_BODY_LEN: usize = 10;
+
+/// Endpoint allocated to an interface
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndpointInfo {
+    pub address: EndpointAddress,
+    pub max_packet_size: u16,
+    /// bInterval of the endpoint descriptor, in frames or, at high speed, as a power of two of
+    /// microframes
+    pub interval: u8,
+}
+
+impl EndpointInfo {
+    fn new<B: UsbBus, D: EndpointDirection>(ep: &Endpoint<'_, B, D>) -> Self {
+        Self {
+            address: ep.address(),
+            max_packet_size: ep.max_packet_size(),
+            interval: ep.interval(),
         }
     }
 }
 
+/// Interface number and endpoints allocated to an interface, for logging and debugging
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceInfo {
+    pub interface_number: u8,
+    pub in_endpoint: Option<EndpointInfo>,
+    pub out_endpoint: Option<EndpointInfo>,
+    pub diagnostics: InterfaceDiagnostics,
+}
+
+/// State of an interface selected by the host, see
+/// [`UsbHidClass::interface_state`](crate::usb_class::UsbHidClass::interface_state)
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceState {
+    pub protocol: HidProtocol,
+    /// Idle rate set with `SET_IDLE` for all reports, zero for indefinite, see
+    /// [`UsbHidClass::report_idle`](crate::usb_class::UsbHidClass::report_idle) for the rate of a
+    /// single report
+    pub idle: MillisDurationU32,
+    pub alternate_setting: u8,
+    pub suspended: bool,
+    /// Time since the last output report was received, counted by
+    /// [`UsbHidClass::tick`](crate::usb_class::UsbHidClass::tick), or `None` if none has been
+    /// received since the interface was reset, such as by a bus reset
+    pub since_output_report: Option<MillisDurationU32>,
+}
+
+/// Counts of an interface's reports and requests since it was allocated, for debugging in the
+/// field, such as a keyboard occasionally dropping keys. The counts wrap on overflow.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InterfaceDiagnostics {
+    /// Input reports written, to the IN endpoint, its queue or the control pipe
+    pub input_reports_sent: u32,
+    /// Input report writes failing with [`UsbError::WouldBlock`] as the IN endpoint was busy
+    pub input_reports_blocked: u32,
+    /// Input reports a managed interface gave up retrying, see
+    /// [`ManagedIdleInterface::write_report_retrying`], or queued on the IN endpoint when the host
+    /// cleared its halt
+    pub input_reports_dropped: u32,
+    /// Output reports received, on the OUT endpoint or with `SET_REPORT`
+    pub output_reports_received: u32,
+    /// `SET_PROTOCOL` requests changing the protocol
+    pub protocol_changes: u32,
+    /// `SET_IDLE` requests
+    pub set_idle_requests: u32,
+}
+
+/// Increment a diagnostics count
+fn count(count: &mut u32) {
+    *count = count.wrapping_add(1);
+}
+
+/// A HID interface of a [`UsbHidClass`](crate::usb_class::UsbHidClass)
+///
+/// Methods for optional features have default bodies for interfaces without them, such as
+/// without endpoints, alternate settings or Microsoft OS descriptors.
 pub trait InterfaceClass<'a> {
-    fn hid_descriptor_body(&self) -> [u8; 7];
+    fn hid_descriptor_body(&self) -> Vec<u8, MAX_HID_DESCRIPTOR_BODY_LEN>;
     fn report_descriptor(&self) -> ReportDescriptor<'_>;
+    /// Physical descriptor sets 1 and up, set 0 describing them is generated from their lengths
+    fn physical_descriptor_sets(&self) -> &[&[u8]] {
+        &[]
+    }
     fn id(&self) -> InterfaceNumber;
+    /// Swap in another interface number allocated to the class, see
+    /// [`UsbHidClassBuilder::interface_order`](crate::usb_class::UsbHidClassBuilder::interface_order)
+    fn set_id(&mut self, _id: InterfaceNumber) {}
+    fn info(&self) -> InterfaceInfo {
+        InterfaceInfo {
+            interface_number: self.id().into(),
+            in_endpoint: None,
+            out_endpoint: None,
+            diagnostics: InterfaceDiagnostics::default(),
+        }
+    }
+    fn state(&self) -> InterfaceState {
+        InterfaceState {
+            protocol: self.get_protocol(),
+            idle: (u32::from(self.get_idle(0)) * 4).millis(),
+            alternate_setting: self.get_alt_setting(),
+            suspended: false,
+            since_output_report: None,
+        }
+    }
     fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+    /// Write an Interface Association Descriptor for the interface as a function of its own, see
+    /// [`InterfaceAssociation::PerInterface`](crate::usb_class::InterfaceAssociation::PerInterface)
+    fn write_association(&self, _writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+        Ok(())
+    }
     fn get_string(&self, index: StringIndex, _lang_id: LangID) -> Option<&'a str>;
+    /// String descriptor index of the first report string, added to the string indices of the
+    /// report descriptor
+    fn report_string_index(&self) -> Option<StringIndex> {
+        None
+    }
+    /// Microsoft OS 2.0 descriptors of the interface, see [`InterfaceConfig::with_ms_os_20`]
+    fn ms_os_20(&self) -> Option<MsOs20Function<'a>> {
+        None
+    }
     fn reset(&mut self);
+    /// Called once for each millisecond elapsed, see
+    /// [`UsbHidClass::tick`](crate::usb_class::UsbHidClass::tick)
+    fn elapse_millisecond(&mut self) {}
+    /// Called when the host has read from an IN endpoint
+    fn endpoint_in_complete(&mut self, _addr: EndpointAddress) {}
+    /// Called when the host has written to an OUT endpoint
+    fn endpoint_out(&mut self, _addr: EndpointAddress) {}
+    /// Called when the host clears a halt on an endpoint with `CLEAR_FEATURE(ENDPOINT_HALT)`,
+    /// returning whether it's one of the interface's endpoints
+    fn endpoint_halt_cleared(&mut self, _addr: EndpointAddress) -> bool {
+        false
+    }
     fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
     fn get_report(&self, data: &mut [u8]) -> usb_device::Result<usize>;
     fn get_report_ack(&mut self) -> usb_device::Result<()>;
@@ -76,6 +285,17 @@ pub trait InterfaceClass<'a> {
     fn get_idle(&self, report_id: u8) -> u8;
     fn set_protocol(&mut self, protocol: HidProtocol);
     fn get_protocol(&self) -> HidProtocol;
+    fn get_alt_setting(&self) -> u8 {
+        0
+    }
+    /// Select an alternate setting, returning `false` if the interface doesn't have it
+    fn set_alt_setting(&mut self, _alternate_setting: u8) -> bool {
+        false
+    }
+    /// Called when the host suspends the bus
+    fn suspend(&mut self) {}
+    /// Called when the host resumes the bus
+    fn resume(&mut self) {}
 }
 
 pub trait ReportBuffer: Default {
@@ -86,6 +306,11 @@ pub trait ReportBuffer: Default {
     #[allow(clippy::result_unit_err)]
     fn extend_from_slice(&mut self, other: &[u8]) -> Result<(), ()>;
     fn as_ref(&self) -> &[u8];
+    /// Append the bytes `read` writes to the buffer's spare capacity, returning their number
+    fn append_with(
+        &mut self,
+        read: impl FnOnce(&mut [u8]) -> usb_device::Result<usize>,
+    ) -> usb_device::Result<usize>;
 }
 
 impl ReportBuffer for () {
@@ -108,6 +333,13 @@ impl ReportBuffer for () {
     fn as_ref(&self) -> &[u8] {
         &[]
     }
+
+    fn append_with(
+        &mut self,
+        read: impl FnOnce(&mut [u8]) -> usb_device::Result<usize>,
+    ) -> usb_device::Result<usize> {
+        read(&mut [])
+    }
 }
 
 impl<const N: usize> ReportBuffer for Vec<u8, N> {
@@ -135,6 +367,18 @@ impl<const N: usize> ReportBuffer for Vec<u8, N> {
     fn as_ref(&self) -> &[u8] {
         self
     }
+
+    fn append_with(
+        &mut self,
+        read: impl FnOnce(&mut [u8]) -> usb_device::Result<usize>,
+    ) -> usb_device::Result<usize> {
+        let len = <[u8]>::len(self);
+        // Can't fail as the buffer is resized to its capacity
+        self.resize_default(N).ok();
+        let result = read(&mut self[len..]);
+        self.truncate(len + *result.as_ref().unwrap_or(&0));
+        result
+    }
 }
 
 pub trait InSize: Sealed {
@@ -160,6 +404,10 @@ vec_in_bytes!(InBytes8, 8);
 vec_in_bytes!(InBytes16, 16);
 vec_in_bytes!(InBytes32, 32);
 vec_in_bytes!(InBytes64, 64);
+vec_in_bytes!(InBytes128, 128);
+vec_in_bytes!(InBytes256, 256);
+vec_in_bytes!(InBytes512, 512);
+vec_in_bytes!(InBytes1024, 1024);
 
 pub trait OutSize: Sealed {
     type Buffer: ReportBuffer;
@@ -184,6 +432,10 @@ vec_out_bytes!(OutBytes8, 8);
 vec_out_bytes!(OutBytes16, 16);
 vec_out_bytes!(OutBytes32, 32);
 vec_out_bytes!(OutBytes64, 64);
+vec_out_bytes!(OutBytes128, 128);
+vec_out_bytes!(OutBytes256, 256);
+vec_out_bytes!(OutBytes512, 512);
+vec_out_bytes!(OutBytes1024, 1024);
 
 pub trait IdleStorage: Default {
     const CAPACITY: u32;
@@ -241,15 +493,113 @@ option_block_idle_storage!(Reports32, Block32);
 option_block_idle_storage!(Reports64, Block64);
 option_block_idle_storage!(Reports128, Block128);
 
+/// Input report in the boot protocol format, 8 bytes for a keyboard and 3 for a mouse
+pub type BootInputReport = Vec<u8, 8>;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ReportDescriptor<'a> {
     StaticDescriptor(&'static [u8]),
     DynamicDescriptor(&'a [u8]),
 }
 
+/// Input and output lengths of a report, excluding its report ID, 0 if it has none
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ReportLength {
+    report_id: u8,
+    input: u16,
+    output: u16,
+    feature: u16,
+}
+
+/// Report lengths described by a report descriptor, parsed once when the interface is allocated
+/// rather than for every report
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReportLengths {
+    uses_report_ids: bool,
+    reports: Vec<ReportLength, PARSE_MAX_REPORTS>,
+}
+
+impl ReportLengths {
+    /// `None` if the descriptor can't be parsed so the lengths can't be known
+    fn parse(report_descriptor: ReportDescriptor<'_>) -> Option<Self> {
+        let (ReportDescriptor::StaticDescriptor(descriptor)
+        | ReportDescriptor::DynamicDescriptor(descriptor)) = report_descriptor;
+        let reports = parse(descriptor).ok()?;
+        let len = |len: usize| u16::try_from(len).unwrap_or(u16::MAX);
+        Some(Self {
+            uses_report_ids: reports.uses_report_ids(),
+            reports: reports
+                .iter()
+                .map(|r| ReportLength {
+                    report_id: r.report_id,
+                    input: len(r.input_len()),
+                    output: len(r.output_len()),
+                    feature: len(r.feature_len()),
+                })
+                .collect(),
+        })
+    }
+
+    /// Length of `report`, including its report ID, `None` if the descriptor doesn't describe
+    /// the report
+    fn len(&self, report: &[u8], len: fn(&ReportLength) -> u16) -> Option<usize> {
+        let report_id = if self.uses_report_ids {
+            *report.first()?
+        } else {
+            0
+        };
+        self.reports
+            .iter()
+            .find(|r| r.report_id == report_id)
+            .map(len)
+            .filter(|len| *len > 0)
+            .map(|len| usize::from(len) + usize::from(self.uses_report_ids))
+    }
+
+    fn input_len(&self, report: &[u8]) -> Option<usize> {
+        self.len(report, |r| r.input)
+    }
+
+    fn output_len(&self, report: &[u8]) -> Option<usize> {
+        self.len(report, |r| r.output)
+    }
+
+    fn feature_len(&self, report: &[u8]) -> Option<usize> {
+        self.len(report, |r| r.feature)
+    }
+}
+
+/// Largest max packet size of an interrupt endpoint, reports longer than an endpoint's max packet
+/// size are written and read a packet at a time
+const fn max_packet_size(high_speed: bool) -> u16 {
+    if high_speed {
+        1024
+    } else {
+        64
+    }
+}
+
+/// Length of physical descriptor set 0, the number of sets and their length
+const PHYSICAL_DESCRIPTOR_SET_0_LEN: u16 = 3;
+
+/// Maximum number of alternate report descriptors of an interface
+pub const MAX_ALTERNATE_REPORT_DESCRIPTORS: usize = 3;
+
+/// Maximum number of languages an interface has localized strings for, in addition to its
+/// default strings
+pub const MAX_LOCALIZED_STRINGS: usize = 3;
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LocalizedStrings<'a> {
+    lang_id: LangID,
+    description: &'a str,
+    report_strings: &'a [&'a str],
+}
+
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct InterfaceConfig<'a, I, O, R>
+pub struct InterfaceConfig<'a, I, O, R, const OUT_QUEUE: usize = 0, const IN_QUEUE: usize = 0>
 where
     I: InSize,
     O: OutSize,
@@ -258,14 +608,152 @@ where
     marker: PhantomData<(I, O, R)>,
     report_descriptor: ReportDescriptor<'a>,
     report_descriptor_length: u16,
+    alternate_report_descriptors:
+        [Option<(ReportDescriptor<'a>, u16)>; MAX_ALTERNATE_REPORT_DESCRIPTORS],
+    boot_report_descriptor: Option<(ReportDescriptor<'a>, u16)>,
     description: Option<&'a str>,
+    report_strings: &'a [&'a str],
+    localized_strings: [Option<LocalizedStrings<'a>>; MAX_LOCALIZED_STRINGS],
+    physical_descriptor_sets: &'a [&'a [u8]],
     protocol: InterfaceProtocol,
+    country_code: CountryCode,
+    hid_version: u16,
     idle_default: u8,
     out_endpoint: Option<EndpointConfig>,
-    in_endpoint: EndpointConfig,
+    in_endpoint: Option<EndpointConfig>,
+    in_max_packet_size: Option<u16>,
+    out_max_packet_size: Option<u16>,
+    high_speed: bool,
+    endpointless_alternate_setting: bool,
+    ms_os_20: Option<MsOs20Function<'a>>,
+}
+
+impl<'a, I, O, R, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    InterfaceConfig<'a, I, O, R, OUT_QUEUE, IN_QUEUE>
+where
+    I: InSize,
+    O: OutSize,
+    R: ReportCount,
+{
+    /// Change the polling interval of the IN endpoint and, if the interface has one, the OUT
+    /// endpoint
+    pub fn with_poll_intervals(
+        mut self,
+        in_interval: MillisDurationU32,
+        out_interval: MillisDurationU32,
+    ) -> BuilderResult<Self> {
+        if self.in_endpoint.is_some() {
+            self.in_endpoint = Some(EndpointConfig::new(in_interval)?);
+        }
+        if self.out_endpoint.is_some() {
+            self.out_endpoint = Some(EndpointConfig::new(out_interval)?);
+        }
+        Ok(self)
+    }
+
+    /// Add an interrupt OUT endpoint, output reports are otherwise only received over the
+    /// control pipe
+    pub fn with_out_endpoint(mut self, poll_interval: MillisDurationU32) -> BuilderResult<Self> {
+        self.out_endpoint = Some(EndpointConfig::new(poll_interval)?);
+        Ok(self)
+    }
+
+    /// Remove the interrupt OUT endpoint, output reports are received over the control pipe
+    #[must_use]
+    pub fn without_out_endpoint(mut self) -> Self {
+        self.out_endpoint = None;
+        self
+    }
+
+    /// Remove the interrupt endpoints, see [`InterfaceBuilder::without_endpoints`]
+    #[must_use]
+    pub fn without_endpoints(mut self) -> Self {
+        self.in_endpoint = None;
+        self.out_endpoint = None;
+        self
+    }
+
+    /// Describe the endpoints for a high speed bus, see [`InterfaceBuilder::high_speed`]
+    #[must_use]
+    pub fn high_speed(mut self) -> Self {
+        self.high_speed = true;
+        self
+    }
+
+    /// Add an alternate setting without endpoints, see
+    /// [`InterfaceBuilder::endpointless_alternate_setting`]
+    #[must_use]
+    pub fn endpointless_alternate_setting(mut self) -> Self {
+        self.endpointless_alternate_setting = true;
+        self
+    }
+
+    /// Change the interface string descriptor, the name hosts show for the interface, such as
+    /// "Lighting" or "Vendor Channel" for the interfaces of a composite device
+    #[must_use]
+    pub fn with_description(mut self, description: &'a str) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    /// Change the country of the localized hardware, see [`InterfaceBuilder::country_code`]
+    #[must_use]
+    pub fn with_country_code(mut self, country_code: CountryCode) -> Self {
+        self.country_code = country_code;
+        self
+    }
+
+    /// Describe the interface to Windows with Microsoft OS 2.0 descriptors, such as to bind
+    /// WinUSB to a vendor configuration channel without an INF file, see
+    /// [`UsbHidClassBuilder::ms_os_20_descriptors`](crate::usb_class::UsbHidClassBuilder::ms_os_20_descriptors)
+    #[must_use]
+    pub fn with_ms_os_20(mut self, function: MsOs20Function<'a>) -> Self {
+        self.ms_os_20 = Some(function);
+        self
+    }
+
+    /// Change the HID specification release in the HID descriptor, see
+    /// [`InterfaceBuilder::hid_version`]
+    #[must_use]
+    pub fn with_hid_version(mut self, bcd_hid: u16) -> Self {
+        self.hid_version = bcd_hid;
+        self
+    }
+
+    /// Add an interface name and report strings for a language, returned in place of the default
+    /// description and report strings when the host requests strings in that language
+    ///
+    /// Report strings are matched by position, any missing from `report_strings` fall back to
+    /// the default report strings. The language must also be listed in the device's string
+    /// descriptors for hosts to request it.
+    pub fn with_localized_strings(
+        mut self,
+        lang_id: LangID,
+        description: &'a str,
+        report_strings: &'a [&'a str],
+    ) -> BuilderResult<Self> {
+        let slot = self
+            .localized_strings
+            .iter_mut()
+            .find(|s| s.is_none_or(|s| s.lang_id == lang_id))
+            .ok_or(UsbHidBuilderError::TooManyLocalizedStrings)?;
+        *slot = Some(LocalizedStrings {
+            lang_id,
+            description,
+            report_strings,
+        });
+        Ok(self)
+    }
+
+    fn localized_strings(&self, lang_id: LangID) -> Option<&LocalizedStrings<'a>> {
+        self.localized_strings
+            .iter()
+            .flatten()
+            .find(|s| s.lang_id == lang_id)
+    }
 }
 
-pub struct Interface<'a, B, I, O, R>
+pub struct Interface<'a, B, I, O, R, const OUT_QUEUE: usize = 0, const IN_QUEUE: usize = 0>
 where
     B: UsbBus,
     I: InSize,
@@ -273,32 +761,49 @@ where
     R: ReportCount,
 {
     id: InterfaceNumber,
-    config: InterfaceConfig<'a, I, O, R>,
+    config: InterfaceConfig<'a, I, O, R, OUT_QUEUE, IN_QUEUE>,
     out_endpoint: Option<EndpointOut<'a, B>>,
-    in_endpoint: EndpointIn<'a, B>,
+    in_endpoint: Option<EndpointIn<'a, B>>,
     description_index: Option<StringIndex>,
+    report_string_index: Option<StringIndex>,
+    report_descriptor_index: usize,
+    /// Report lengths of the report descriptor and its alternates, in selection order
+    report_lengths: [Option<ReportLengths>; 1 + MAX_ALTERNATE_REPORT_DESCRIPTORS],
+    boot_report_lengths: Option<ReportLengths>,
+    alternate_setting: u8,
+    suspended: bool,
     protocol: HidProtocol,
+    diagnostics: InterfaceDiagnostics,
+    /// Time since the last output report was received
+    since_output_report: Option<MillisDurationU32>,
     report_idle: R::IdleStorage,
     global_idle: u8,
     control_in_report_buffer: I::Buffer,
     control_out_report_buffer: O::Buffer,
+    in_transfer: I::Buffer,
+    in_transfer_offset: usize,
+    out_transfer: O::Buffer,
+    out_report_queue: Vec<O::Buffer, OUT_QUEUE>,
+    in_report_queue: Vec<I::Buffer, IN_QUEUE>,
 }
 
-impl<'a, B: UsbBus + 'a, I, O, R> UsbAllocatable<'a, B> for InterfaceConfig<'a, I, O, R>
+impl<'a, B: UsbBus + 'a, I, O, R, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    UsbAllocatable<'a, B> for InterfaceConfig<'a, I, O, R, OUT_QUEUE, IN_QUEUE>
 where
     B: UsbBus,
     I: InSize,
     O: OutSize,
     R: ReportCount,
 {
-    type Allocated = Interface<'a, B, I, O, R>;
+    type Allocated = Interface<'a, B, I, O, R, OUT_QUEUE, IN_QUEUE>;
 
-    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        Interface::new(usb_alloc, self)
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Interface::try_new(usb_alloc, self)
     }
 }
 
-impl<'a, B, I, O, R> DeviceClass<'a> for Interface<'a, B, I, O, R>
+impl<'a, B, I, O, R, const OUT_QUEUE: usize, const IN_QUEUE: usize> DeviceClass<'a>
+    for Interface<'a, B, I, O, R, OUT_QUEUE, IN_QUEUE>
 where
     B: UsbBus,
     I: InSize,
@@ -320,31 +825,190 @@ where
     }
 }
 
-impl<'a, B: UsbBus, I, O, R> Interface<'a, B, I, O, R>
+impl<'a, B: UsbBus, I, O, R, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    Interface<'a, B, I, O, R, OUT_QUEUE, IN_QUEUE>
 where
     B: UsbBus,
     I: InSize,
     O: OutSize,
     R: ReportCount,
 {
-    pub fn new(usb_alloc: &'a UsbBusAllocator<B>, config: InterfaceConfig<'a, I, O, R>) -> Self {
-        Interface {
+    /// # Panics
+    ///
+    /// Panics if the bus can't allocate an endpoint, see [`Interface::try_new`]
+    pub fn new(
+        usb_alloc: &'a UsbBusAllocator<B>,
+        config: InterfaceConfig<'a, I, O, R, OUT_QUEUE, IN_QUEUE>,
+    ) -> Self {
+        unwrap!(Self::try_new(usb_alloc, config))
+    }
+
+    /// Allocate the interface, failing with [`UsbHidBuilderError::EndpointAllocation`] if the
+    /// bus can't allocate one of its endpoints
+    pub fn try_new(
+        usb_alloc: &'a UsbBusAllocator<B>,
+        config: InterfaceConfig<'a, I, O, R, OUT_QUEUE, IN_QUEUE>,
+    ) -> BuilderResult<Self> {
+        // Checked here as the bus speed may be set after the max packet sizes
+        if !config.high_speed
+            && [config.in_max_packet_size, config.out_max_packet_size]
+                .into_iter()
+                .flatten()
+                .any(|size| !matches!(size, 8 | 16 | 32 | 64))
+        {
+            return Err(UsbHidBuilderError::InvalidPacketSize);
+        }
+        Ok(Interface {
             id: usb_alloc.interface(),
-            in_endpoint: usb_alloc.interrupt(I::Buffer::CAPACITY, config.in_endpoint.poll_interval),
+            in_endpoint: config
+                .in_endpoint
+                .map(|c| {
+                    c.allocate(
+                        usb_alloc,
+                        config
+                            .in_max_packet_size
+                            .unwrap_or(I::Buffer::CAPACITY.min(max_packet_size(config.high_speed))),
+                        config.high_speed,
+                    )
+                })
+                .transpose()?,
             out_endpoint: config
                 .out_endpoint
-                .map(|c| usb_alloc.interrupt(O::Buffer::CAPACITY, c.poll_interval)),
-            description_index: config.description.map(|_| usb_alloc.string()),
+                .map(|c| {
+                    c.allocate(
+                        usb_alloc,
+                        config
+                            .out_max_packet_size
+                            .unwrap_or(O::Buffer::CAPACITY.min(max_packet_size(config.high_speed))),
+                        config.high_speed,
+                    )
+                })
+                .transpose()?,
+            description_index: (config.description.is_some()
+                || config.localized_strings.iter().any(Option::is_some))
+            .then(|| usb_alloc.string()),
+            // Report strings are allocated consecutive indices, enough for the longest language
+            report_string_index: (0..config
+                .localized_strings
+                .iter()
+                .flatten()
+                .map(|s| s.report_strings.len())
+                .fold(config.report_strings.len(), usize::max))
+                .map(|_| usb_alloc.string())
+                .reduce(|first, _| first),
+            report_descriptor_index: 0,
+            report_lengths: core::array::from_fn(|i| {
+                i.checked_sub(1)
+                    .map_or(Some(config.report_descriptor), |i| {
+                        config.alternate_report_descriptors[i].map(|(d, _)| d)
+                    })
+                    .and_then(ReportLengths::parse)
+            }),
+            boot_report_lengths: config
+                .boot_report_descriptor
+                .and_then(|(d, _)| ReportLengths::parse(d)),
+            alternate_setting: 0,
+            suspended: false,
             //When initialized, all devices default to report protocol - Hid spec 7.2.6 Set_Protocol Request
             protocol: HidProtocol::Report,
+            diagnostics: InterfaceDiagnostics::default(),
+            since_output_report: None,
             report_idle: R::IdleStorage::default(),
             global_idle: config.idle_default,
             control_in_report_buffer: I::Buffer::default(),
             control_out_report_buffer: O::Buffer::default(),
+            in_transfer: I::Buffer::default(),
+            in_transfer_offset: 0,
+            out_transfer: O::Buffer::default(),
+            out_report_queue: Vec::new(),
+            in_report_queue: Vec::new(),
             config,
-        }
+        })
     }
 
+    /// Report descriptor exposed to the host and its length
+    fn selected_report_descriptor(&self) -> (ReportDescriptor<'a>, u16) {
+        if let Some(descriptor) = self
+            .config
+            .boot_report_descriptor
+            .filter(|_| self.protocol == HidProtocol::Boot)
+        {
+            return descriptor;
+        }
+        self.report_descriptor_index
+            .checked_sub(1)
+            .and_then(|i| self.config.alternate_report_descriptors[i])
+            .unwrap_or((
+                self.config.report_descriptor,
+                self.config.report_descriptor_length,
+            ))
+    }
+    /// Report lengths of the report descriptor exposed to the host, `None` if it can't be parsed
+    fn selected_report_lengths(&self) -> Option<&ReportLengths> {
+        if self.protocol == HidProtocol::Boot && self.config.boot_report_descriptor.is_some() {
+            return self.boot_report_lengths.as_ref();
+        }
+        self.report_lengths[self.report_descriptor_index].as_ref()
+    }
+    /// Select the report descriptor exposed to the host, 0 for the interface's report descriptor
+    /// and 1 onwards for its alternate report descriptors, in the order they were added
+    ///
+    /// The host reads the report descriptor when it enumerates the device, so select it before
+    /// connecting the device or force the host to enumerate the device again. The selection is
+    /// kept through a USB reset.
+    pub fn select_report_descriptor(&mut self, index: usize) -> usb_device::Result<()> {
+        let available = index == 0
+            || index
+                .checked_sub(1)
+                .and_then(|i| self.config.alternate_report_descriptors.get(i))
+                .is_some_and(Option::is_some);
+        if available {
+            self.report_descriptor_index = index;
+            info!("Selected report descriptor {}", index);
+            Ok(())
+        } else {
+            Err(UsbError::Unsupported)
+        }
+    }
+    #[must_use]
+    pub fn report_descriptor_index(&self) -> usize {
+        self.report_descriptor_index
+    }
+    /// Interface number and endpoints allocated to the interface
+    #[must_use]
+    pub fn info(&self) -> InterfaceInfo {
+        InterfaceInfo {
+            interface_number: self.id.into(),
+            in_endpoint: self.in_endpoint.as_ref().map(EndpointInfo::new),
+            out_endpoint: self.out_endpoint.as_ref().map(EndpointInfo::new),
+            diagnostics: self.diagnostics,
+        }
+    }
+    #[must_use]
+    pub fn diagnostics(&self) -> InterfaceDiagnostics {
+        self.diagnostics
+    }
+    pub fn clear_diagnostics(&mut self) {
+        self.diagnostics = InterfaceDiagnostics::default();
+    }
+    /// Alternate setting selected by the host, see [`InterfaceBuilder::endpointless_alternate_setting`]
+    #[must_use]
+    pub fn alternate_setting(&self) -> u8 {
+        self.alternate_setting
+    }
+    /// Whether the selected alternate setting has the interrupt endpoints
+    fn endpoints_enabled(&self) -> bool {
+        !self.config.endpointless_alternate_setting || self.alternate_setting == 1
+    }
+    /// Drop queued reports and the rest of a report being written to the IN endpoint
+    fn drop_in_reports(&mut self) {
+        for _ in 0..self.in_report_queue.len() {
+            count(&mut self.diagnostics.input_reports_dropped);
+        }
+        self.in_report_queue.clear();
+        self.in_transfer.clear();
+        self.in_transfer_offset = 0;
+    }
     fn clear_report_idle(&mut self) {
         self.report_idle = R::IdleStorage::default();
     }
@@ -359,20 +1023,65 @@ where
     pub fn protocol(&self) -> HidProtocol {
         self.protocol
     }
+    /// Whether the host has suspended the bus, see [`crate::usb_class::UsbHidClass::update_suspend`]
+    #[must_use]
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
     #[must_use]
     pub fn global_idle(&self) -> MillisDurationU32 {
         (u32::from(self.global_idle) * 4).millis()
     }
+    /// Idle rate the host set for `report_id` with `SET_IDLE`, if it set one for that ID alone
     #[must_use]
     pub fn report_idle(&self, report_id: u8) -> Option<MillisDurationU32> {
         if report_id == 0 {
             None
         } else {
-            self.get_report_idle(report_id)
+            self.get_report_idle(report_id - 1)
                 .map(|i| (u32::from(i) * 4).millis())
         }
     }
+    /// Idle rate to repeat the input report with `report_id` at, the rate set for that ID or
+    /// else the global idle rate
+    #[must_use]
+    pub fn idle(&self, report_id: u8) -> MillisDurationU32 {
+        self.report_idle(report_id)
+            .unwrap_or_else(|| self.global_idle())
+    }
+    /// Length of `report` in boot protocol, if the interface has a boot protocol report
+    /// descriptor
+    fn boot_input_report_len(&self, report: &[u8]) -> Option<usize> {
+        self.boot_report_lengths
+            .as_ref()
+            .filter(|_| self.protocol == HidProtocol::Boot)?
+            .input_len(report)
+    }
+    /// Whether `data` has the length of the input report the selected report descriptor describes
+    ///
+    /// Boot protocol reports, reports longer than a packet that are written in chunks and reports
+    /// of descriptors that can't be parsed aren't checked.
+    fn input_report_len_matches(&self, data: &[u8]) -> bool {
+        self.protocol == HidProtocol::Boot
+            || self.selected_report_lengths().is_none_or(|lengths| {
+                lengths.input_len(data).is_some_and(|len| {
+                    len == data.len()
+                        || self
+                            .in_endpoint
+                            .as_ref()
+                            .is_some_and(|ep| len > usize::from(ep.max_packet_size()))
+                })
+            })
+    }
     pub fn write_report(&mut self, data: &[u8]) -> usb_device::Result<usize> {
+        debug_assert!(self.input_report_len_matches(data));
+
+        // In boot protocol reports are truncated to the boot format they start with
+        let data = match self.boot_input_report_len(data) {
+            Some(len) if len < data.len() => &data[..len],
+            _ => data,
+        };
+
         //Try to write report to the report buffer for the config endpoint
         let control_result = if self.control_in_report_buffer.is_empty() {
             match self.control_in_report_buffer.extend_from_slice(data) {
@@ -383,22 +1092,172 @@ where
             Err(UsbError::WouldBlock)
         };
 
-        //Also try to write report to the in endpoint
-        let endpoint_result = self.in_endpoint.write(data);
+        //Also try to write report to the in endpoint, after any queued reports
+        let endpoint_result = if self.in_report_queue.is_empty() {
+            self.write_in_transfer(data)
+        } else {
+            Err(UsbError::WouldBlock)
+        };
+        //Queue the report if the in endpoint is busy
+        let endpoint_result = match endpoint_result {
+            Err(UsbError::WouldBlock) if IN_QUEUE > 0 && self.in_endpoint.is_some() => {
+                self.queue_in_report(data)
+            }
+            _ => endpoint_result,
+        };
 
-        match (control_result, endpoint_result) {
+        let result = match (control_result, endpoint_result) {
             //OK if either succeeded
             (_, Ok(n)) | (Ok(n), _) => Ok(n),
             //non-WouldBlock errors take preference
             (Err(e), Err(UsbError::WouldBlock)) | (_, Err(e)) => Err(e),
+        };
+        match result {
+            Ok(_) => count(&mut self.diagnostics.input_reports_sent),
+            Err(UsbError::WouldBlock) => count(&mut self.diagnostics.input_reports_blocked),
+            Err(_) => {}
+        }
+        result
+    }
+    fn queue_in_report(&mut self, data: &[u8]) -> usb_device::Result<usize> {
+        let mut report = I::Buffer::default();
+        report
+            .extend_from_slice(data)
+            .map_err(|()| UsbError::BufferOverflow)?;
+        self.in_report_queue
+            .push(report)
+            .map_err(|_| UsbError::WouldBlock)?;
+        trace!("Queued input report, {:X} bytes", data.len());
+        Ok(data.len())
+    }
+    /// Write the first packet of `data` to the IN endpoint, the rest of a report longer than the
+    /// max packet size is written a packet at a time as the host reads each packet
+    fn write_in_transfer(&mut self, data: &[u8]) -> usb_device::Result<usize> {
+        let Some(ep) = self
+            .in_endpoint
+            .as_ref()
+            .filter(|_| self.in_transfer.is_empty() && self.endpoints_enabled())
+        else {
+            return Err(UsbError::WouldBlock);
+        };
+        let packet_size = usize::from(ep.max_packet_size());
+        if data.len() > packet_size {
+            if data.len() > usize::from(I::Buffer::CAPACITY) {
+                return Err(UsbError::BufferOverflow);
+            }
+            ep.write(&data[..packet_size])?;
+            // Can't fail as the report fits in the buffer
+            self.in_transfer.extend_from_slice(data).ok();
+            self.in_transfer_offset = packet_size;
+            trace!("Started input report, {:X} bytes", data.len());
+            Ok(data.len())
+        } else {
+            ep.write(data)
+        }
+    }
+    /// Write the next packet of the report being written to the IN endpoint, then queued reports
+    fn flush_in_report_queue(&mut self) {
+        let Some(ep) = &self.in_endpoint else {
+            return;
+        };
+        if !self.in_transfer.is_empty() {
+            let packet_size = usize::from(ep.max_packet_size());
+            let end = (self.in_transfer_offset + packet_size).min(self.in_transfer.len());
+            let packet = &self.in_transfer.as_ref()[self.in_transfer_offset..end];
+            if ep.write(packet).is_err() {
+                return;
+            }
+            if end == self.in_transfer.len() {
+                self.in_transfer.clear();
+            } else {
+                self.in_transfer_offset = end;
+            }
+            return;
+        }
+        while !self.in_report_queue.is_empty() {
+            let report = self.in_report_queue.remove(0);
+            if self.write_in_transfer(report.as_ref()).is_err() {
+                // Can't fail as the report was just removed
+                self.in_report_queue.insert(0, report).ok();
+                return;
+            }
+        }
+    }
+    /// Read a packet from the OUT endpoint, returning whether it completes the report being read
+    ///
+    /// A report ends with a packet shorter than the max packet size or when it has the length the
+    /// report descriptor describes.
+    fn read_out_transfer(&mut self) -> usb_device::Result<bool> {
+        let Some(ep) = self
+            .out_endpoint
+            .as_ref()
+            .filter(|_| self.endpoints_enabled())
+        else {
+            return Err(UsbError::WouldBlock);
+        };
+        let packet_size = usize::from(ep.max_packet_size());
+        let len = match self.out_transfer.append_with(|buffer| ep.read(buffer)) {
+            Err(UsbError::BufferOverflow) => {
+                error!("Dropped output report, too large for buffer");
+                self.out_transfer.clear();
+                return Err(UsbError::BufferOverflow);
+            }
+            result => result?,
+        };
+        let complete = len < packet_size
+            || self
+                .selected_report_lengths()
+                .and_then(|lengths| lengths.output_len(self.out_transfer.as_ref()))
+                .is_none_or(|len| self.out_transfer.len() >= len);
+        if complete {
+            count(&mut self.diagnostics.output_reports_received);
+            self.since_output_report = Some(0.millis());
+        }
+        Ok(complete)
+    }
+    /// Move reports from the OUT endpoint to the queue until it's full
+    fn fill_out_report_queue(&mut self) {
+        while !self.out_report_queue.is_full() {
+            match self.read_out_transfer() {
+                Ok(true) => {
+                    let report = core::mem::take(&mut self.out_transfer);
+                    trace!("Queued output report, {:X} bytes", report.len());
+                    // Can't fail as the queue isn't full
+                    self.out_report_queue.push(report).ok();
+                }
+                Ok(false) | Err(UsbError::BufferOverflow) => {}
+                Err(_) => return,
+            }
         }
     }
     pub fn read_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize> {
+        //Queued reports were received before any report in the out endpoint
+        if let Some(report) = self.out_report_queue.first() {
+            let len = report.len();
+            if data.len() < len {
+                return Err(UsbError::BufferOverflow);
+            }
+            data[..len].copy_from_slice(report.as_ref());
+            self.out_report_queue.remove(0);
+            self.fill_out_report_queue();
+            return Ok(len);
+        }
+
         //If there is an out endpoint, try to read from it first
-        let ep_result = if let Some(ep) = &self.out_endpoint {
-            ep.read(data)
-        } else {
-            Err(UsbError::WouldBlock)
+        let ep_result = loop {
+            match self.read_out_transfer() {
+                Ok(true) => {
+                    let report = core::mem::take(&mut self.out_transfer);
+                    let len = report.len();
+                    if data.len() < len {
+                        break Err(UsbError::BufferOverflow);
+                    }
+                    data[..len].copy_from_slice(report.as_ref());
+                    break Ok(len);
+                }
+                Ok(false) => {}
+                Err(e) => break Err(e),
+            }
         };
 
         match ep_result {
@@ -419,70 +1278,213 @@ where
             _ => ep_result,
         }
     }
+    /// Read an output report and pass it to the handler `router` has for its report ID
+    pub fn dispatch_report<const N: usize>(
+        &mut self,
+        router: &mut ReportRouter<'_, N>,
+    ) -> Result<(), UsbHidError> {
+        let mut report = O::Buffer::default();
+        report.append_with(|data| self.read_report(data))?;
+        router.dispatch(report.as_ref())
+    }
 }
-impl<'a, B: UsbBus, I, O, R> InterfaceClass<'a> for Interface<'a, B, I, O, R>
+impl<'a, B: UsbBus, I, O, R, const OUT_QUEUE: usize, const IN_QUEUE: usize> InterfaceClass<'a>
+    for Interface<'a, B, I, O, R, OUT_QUEUE, IN_QUEUE>
 where
     B: UsbBus,
     I: InSize,
     O: OutSize,
     R: ReportCount,
 {
-    fn hid_descriptor_body(&self) -> [u8; 7] {
-        match (HidDescriptorBody {
-            bcd_hid: SPEC_VERSION_1_11,
-            country_code: COUNTRY_CODE_NOT_SUPPORTED,
-            num_descriptors: 1,
+    fn hid_descriptor_body(&self) -> Vec<u8, MAX_HID_DESCRIPTOR_BODY_LEN> {
+        let has_physical_descriptor = !self.config.physical_descriptor_sets.is_empty();
+        let Ok(body) = HidDescriptorBody {
+            bcd_hid: self.config.hid_version,
+            country_code: self.config.country_code.into(),
+            num_descriptors: if has_physical_descriptor { 2 } else { 1 },
             descriptor_type: DescriptorType::Report,
-            descriptor_length: self.config.report_descriptor_length,
+            descriptor_length: self.selected_report_descriptor().1,
         }
-        .pack())
-        {
-            Ok(d) => d,
-            Err(_) => panic!("Failed to pack HidDescriptor"),
+        .pack() else {
+            panic!("Failed to pack HidDescriptor")
+        };
+
+        let mut descriptor = Vec::new();
+        unwrap!(descriptor.extend_from_slice(&body));
+        if has_physical_descriptor {
+            // The length of physical descriptor set 0, which describes the other sets
+            let [low, high] = PHYSICAL_DESCRIPTOR_SET_0_LEN.to_le_bytes();
+            unwrap!(descriptor.extend_from_slice(&[DescriptorType::Physical.into(), low, high]));
         }
+        descriptor
     }
 
     fn report_descriptor(&self) -> ReportDescriptor<'_> {
-        self.config.report_descriptor
+        self.selected_report_descriptor().0
+    }
+
+    fn physical_descriptor_sets(&self) -> &[&[u8]] {
+        self.config.physical_descriptor_sets
     }
 
     fn id(&self) -> InterfaceNumber {
         self.id
     }
+    fn set_id(&mut self, id: InterfaceNumber) {
+        self.id = id;
+    }
+    fn info(&self) -> InterfaceInfo {
+        Interface::info(self)
+    }
+    fn state(&self) -> InterfaceState {
+        InterfaceState {
+            protocol: self.protocol,
+            idle: self.global_idle(),
+            alternate_setting: self.alternate_setting,
+            suspended: self.suspended,
+            since_output_report: self.since_output_report,
+        }
+    }
     fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
-        writer.interface_alt(
-            self.id,
-            usb_device::device::DEFAULT_ALTERNATE_SETTING,
-            USB_CLASS_HID,
-            InterfaceSubClass::from(self.config.protocol).into(),
-            self.config.protocol.into(),
-            self.description_index,
-        )?;
+        let mut write_interface = |alternate_setting| {
+            writer.interface_alt(
+                self.id,
+                alternate_setting,
+                USB_CLASS_HID,
+                InterfaceSubClass::from(self.config.protocol).into(),
+                self.config.protocol.into(),
+                self.description_index,
+            )?;
+
+            //Hid descriptor
+            writer.write(DescriptorType::Hid.into(), &self.hid_descriptor_body())
+        };
 
-        //Hid descriptor
-        writer.write(DescriptorType::Hid.into(), &self.hid_descriptor_body())?;
+        write_interface(usb_device::device::DEFAULT_ALTERNATE_SETTING)?;
+        if self.config.endpointless_alternate_setting {
+            write_interface(1)?;
+        }
 
         //Endpoint descriptors
-        writer.endpoint(&self.in_endpoint)?;
+        if let Some(e) = &self.in_endpoint {
+            writer.endpoint(e)?;
+        }
         if let Some(e) = &self.out_endpoint {
             writer.endpoint(e)?;
         }
 
         Ok(())
     }
-    fn get_string(&self, index: StringIndex, _lang_id: LangID) -> Option<&'a str> {
-        self.description_index
-            .filter(|&i| i == index)
-            .and(self.config.description)
-    }
+    fn write_association(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+        writer.iad(
+            self.id,
+            1,
+            USB_CLASS_HID,
+            InterfaceSubClass::from(self.config.protocol).into(),
+            self.config.protocol.into(),
+            self.description_index,
+        )
+    }
+    fn get_string(&self, index: StringIndex, lang_id: LangID) -> Option<&'a str> {
+        let localized = self.config.localized_strings(lang_id);
+        if self.description_index == Some(index) {
+            return localized.map(|s| s.description).or(self.config.description);
+        }
+        let first = u8::from(self.report_string_index?);
+        let i = usize::from(u8::from(index).checked_sub(first)?);
+        localized
+            .and_then(|s| s.report_strings.get(i))
+            .or_else(|| self.config.report_strings.get(i))
+            .copied()
+    }
+    fn report_string_index(&self) -> Option<StringIndex> {
+        self.report_string_index
+    }
+    fn ms_os_20(&self) -> Option<MsOs20Function<'a>> {
+        self.config.ms_os_20
+    }
     fn reset(&mut self) {
+        self.alternate_setting = 0;
+        self.suspended = false;
         self.protocol = HidProtocol::Report;
         self.global_idle = self.config.idle_default;
         self.clear_report_idle();
         self.control_in_report_buffer = I::Buffer::default();
         self.control_out_report_buffer = O::Buffer::default();
+        self.in_transfer = I::Buffer::default();
+        self.out_transfer = O::Buffer::default();
+        self.out_report_queue.clear();
+        self.in_report_queue.clear();
+        self.since_output_report = None;
+    }
+    fn elapse_millisecond(&mut self) {
+        if let Some(since) = &mut self.since_output_report {
+            *since = since
+                .checked_add(MillisDurationU32::millis(1))
+                .unwrap_or(*since);
+        }
+    }
+    fn endpoint_in_complete(&mut self, addr: EndpointAddress) {
+        if self
+            .in_endpoint
+            .as_ref()
+            .is_some_and(|ep| ep.address() == addr)
+        {
+            self.flush_in_report_queue();
+        }
+    }
+    fn endpoint_out(&mut self, addr: EndpointAddress) {
+        if self
+            .out_endpoint
+            .as_ref()
+            .is_some_and(|ep| ep.address() == addr)
+        {
+            self.fill_out_report_queue();
+        }
+    }
+    fn endpoint_halt_cleared(&mut self, addr: EndpointAddress) -> bool {
+        if self
+            .in_endpoint
+            .as_ref()
+            .is_some_and(|ep| ep.address() == addr)
+        {
+            // The host restarts at the next report, drop the rest of a report being written and
+            // reports queued before the halt. The bus resets the data toggle as it clears the halt.
+            info!(
+                "IN endpoint halt cleared, dropped {} queued reports",
+                self.in_report_queue.len()
+            );
+            self.drop_in_reports();
+            true
+        } else if self
+            .out_endpoint
+            .as_ref()
+            .is_some_and(|ep| ep.address() == addr)
+        {
+            // Reports received before the halt are kept, only a partial report is dropped
+            info!("OUT endpoint halt cleared");
+            self.out_transfer = O::Buffer::default();
+            true
+        } else {
+            false
+        }
     }
     fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        // Longer reports are truncated as some hosts pad reports, unknown and short reports are
+        // rejected. Feature reports the device doesn't handle are also set here.
+        let data = match self.selected_report_lengths().map(|lengths| {
+            lengths
+                .output_len(data)
+                .or_else(|| lengths.feature_len(data))
+        }) {
+            Some(Some(len)) if data.len() >= len => &data[..len],
+            Some(_) => {
+                warn!("Rejected output report of {:X} bytes", data.len());
+                return Err(UsbError::ParseError);
+            }
+            None => data,
+        };
+
         if self.control_out_report_buffer.is_empty() {
             if self
                 .control_out_report_buffer
@@ -493,6 +1495,8 @@ where
                     "Set report, {:X} bytes",
                     &self.control_out_report_buffer.len()
                 );
+                count(&mut self.diagnostics.output_reports_received);
+                self.since_output_report = Some(0.millis());
                 Ok(())
             } else {
                 error!(
@@ -533,6 +1537,7 @@ where
     }
 
     fn set_idle(&mut self, report_id: u8, value: u8) {
+        count(&mut self.diagnostics.set_idle_requests);
         if report_id == 0 {
             self.global_idle = value;
             //"If the lower byte of value is zero, then the idle rate applies to all
@@ -563,6 +1568,13 @@ where
         }
     }
     fn set_protocol(&mut self, protocol: HidProtocol) {
+        if protocol != self.protocol {
+            count(&mut self.diagnostics.protocol_changes);
+            // Idle rates set for one protocol's reports don't apply to the other's, such as the
+            // 500ms boot keyboard default after the host disabled idle reports in report protocol
+            self.global_idle = self.config.idle_default;
+            self.clear_report_idle();
+        }
         self.protocol = protocol;
         info!("Set protocol to {:?}", protocol);
     }
@@ -570,6 +1582,34 @@ where
     fn get_protocol(&self) -> HidProtocol {
         self.protocol
     }
+    fn get_alt_setting(&self) -> u8 {
+        self.alternate_setting
+    }
+    fn set_alt_setting(&mut self, alternate_setting: u8) -> bool {
+        let alternate_settings = if self.config.endpointless_alternate_setting {
+            2
+        } else {
+            1
+        };
+        if alternate_setting < alternate_settings {
+            info!("Selected alternate setting {}", alternate_setting);
+            if alternate_setting != self.alternate_setting {
+                // Selecting a setting resets its endpoints, transfers in progress are abandoned
+                self.drop_in_reports();
+                self.out_transfer = O::Buffer::default();
+            }
+            self.alternate_setting = alternate_setting;
+            true
+        } else {
+            false
+        }
+    }
+    fn suspend(&mut self) {
+        self.suspended = true;
+    }
+    fn resume(&mut self) {
+        self.suspended = false;
+    }
 }
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -578,18 +1618,57 @@ struct EndpointConfig {
     pub poll_interval: u8,
 }
 
+impl EndpointConfig {
+    fn new(poll_interval: MillisDurationU32) -> BuilderResult<Self> {
+        Ok(Self {
+            poll_interval: u8::try_from(poll_interval.to_millis())
+                .map_err(|_| UsbHidBuilderError::ValueOverflow)?,
+        })
+    }
+
+    /// `bInterval` of the endpoint, in frames at full speed, and at high speed the exponent of a
+    /// power of two number of microframes, rounded down to poll at least as often as configured
+    #[allow(clippy::cast_possible_truncation)]
+    fn interval(self, high_speed: bool) -> u8 {
+        if high_speed {
+            let microframes = u32::from(self.poll_interval.max(1)) * 8;
+            (microframes.ilog2() + 1).min(16) as u8
+        } else {
+            self.poll_interval
+        }
+    }
+
+    /// Allocate an interrupt endpoint polled at this interval
+    fn allocate<B: UsbBus, D: EndpointDirection>(
+        self,
+        usb_alloc: &UsbBusAllocator<B>,
+        max_packet_size: u16,
+        high_speed: bool,
+    ) -> BuilderResult<Endpoint<'_, B, D>> {
+        usb_alloc
+            .alloc(
+                None,
+                EndpointType::Interrupt,
+                max_packet_size,
+                self.interval(high_speed),
+            )
+            .map_err(UsbHidBuilderError::EndpointAllocation)
+    }
+}
+
 #[must_use = "this `UsbHidInterfaceBuilder` must be assigned or consumed by `::build_interface()`"]
 #[derive(Copy, Clone, Debug)]
-pub struct InterfaceBuilder<'a, I, O, R>
+pub struct InterfaceBuilder<'a, I, O, R, const OUT_QUEUE: usize = 0, const IN_QUEUE: usize = 0>
 where
     I: InSize,
     O: OutSize,
     R: ReportCount,
 {
-    config: InterfaceConfig<'a, I, O, R>,
+    config: InterfaceConfig<'a, I, O, R, OUT_QUEUE, IN_QUEUE>,
 }
 
-impl<'a, I, O, R> InterfaceBuilder<'a, I, O, R>
+impl<'a, I, O, R, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    InterfaceBuilder<'a, I, O, R, OUT_QUEUE, IN_QUEUE>
 where
     I: InSize,
     O: OutSize,
@@ -606,11 +1685,23 @@ where
                 report_descriptor: ReportDescriptor::DynamicDescriptor(report_descriptor),
                 report_descriptor_length: u16::try_from(report_descriptor.len())
                     .map_err(|_| UsbHidBuilderError::SliceLengthOverflow)?,
+                alternate_report_descriptors: [None; MAX_ALTERNATE_REPORT_DESCRIPTORS],
+                boot_report_descriptor: None,
                 description: None,
+                report_strings: &[],
+                localized_strings: [None; MAX_LOCALIZED_STRINGS],
+                physical_descriptor_sets: &[],
                 protocol: InterfaceProtocol::None,
+                country_code: CountryCode::NotSupported,
+                hid_version: SPEC_VERSION_1_11,
                 idle_default: 0,
                 out_endpoint: None,
-                in_endpoint: EndpointConfig { poll_interval: 20 },
+                in_endpoint: Some(EndpointConfig { poll_interval: 20 }),
+                in_max_packet_size: None,
+                out_max_packet_size: None,
+                high_speed: false,
+                endpointless_alternate_setting: false,
+                ms_os_20: None,
             },
         })
     }
@@ -622,20 +1713,203 @@ where
                 report_descriptor: ReportDescriptor::StaticDescriptor(report_descriptor),
                 report_descriptor_length: u16::try_from(report_descriptor.len())
                     .map_err(|_| UsbHidBuilderError::SliceLengthOverflow)?,
+                alternate_report_descriptors: [None; MAX_ALTERNATE_REPORT_DESCRIPTORS],
+                boot_report_descriptor: None,
                 description: None,
+                report_strings: &[],
+                localized_strings: [None; MAX_LOCALIZED_STRINGS],
+                physical_descriptor_sets: &[],
                 protocol: InterfaceProtocol::None,
+                country_code: CountryCode::NotSupported,
+                hid_version: SPEC_VERSION_1_11,
                 idle_default: 0,
                 out_endpoint: None,
-                in_endpoint: EndpointConfig { poll_interval: 20 },
+                in_endpoint: Some(EndpointConfig { poll_interval: 20 }),
+                in_max_packet_size: None,
+                out_max_packet_size: None,
+                high_speed: false,
+                endpointless_alternate_setting: false,
+                ms_os_20: None,
             },
         })
     }
 
+    /// Add an alternate report descriptor, exposed in place of the interface's report descriptor
+    /// once selected with [`Interface::select_report_descriptor`], such as a simpler descriptor
+    /// for BIOSes or older hosts
+    pub fn alternate_report_descriptor(self, report_descriptor: &'a [u8]) -> BuilderResult<Self> {
+        if report_descriptor.len() > 128 {
+            return Err(UsbHidBuilderError::SliceLengthOverflow);
+        }
+        self.add_alternate_report_descriptor(ReportDescriptor::DynamicDescriptor(report_descriptor))
+    }
+
+    /// Add an alternate report descriptor, see [`InterfaceBuilder::alternate_report_descriptor`]
+    pub fn alternate_static_report_descriptor(
+        self,
+        report_descriptor: &'static [u8],
+    ) -> BuilderResult<Self> {
+        self.add_alternate_report_descriptor(ReportDescriptor::StaticDescriptor(report_descriptor))
+    }
+
+    /// Expose `report_descriptor` while the host has selected boot protocol with `SET_PROTOCOL`,
+    /// such as [`BOOT_KEYBOARD_REPORT_DESCRIPTOR`](crate::device::keyboard::BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+    /// for an NKRO keyboard
+    ///
+    /// Input reports written in boot protocol are truncated to the length of the boot descriptor's
+    /// input report, so reports must start with their boot format, as
+    /// [`NKROBootKeyboardReport`](crate::device::keyboard::NKROBootKeyboardReport) does. Output
+    /// reports received in boot protocol are checked against the boot descriptor.
+    pub fn boot_report_descriptor(
+        mut self,
+        report_descriptor: &'static [u8],
+    ) -> BuilderResult<Self> {
+        let length = u16::try_from(report_descriptor.len())
+            .map_err(|_| UsbHidBuilderError::SliceLengthOverflow)?;
+        let report_descriptor = ReportDescriptor::StaticDescriptor(report_descriptor);
+        self.validate_packet_sizes(report_descriptor)?;
+        self.config.boot_report_descriptor = Some((report_descriptor, length));
+        Ok(self)
+    }
+
+    fn add_alternate_report_descriptor(
+        mut self,
+        report_descriptor: ReportDescriptor<'a>,
+    ) -> BuilderResult<Self> {
+        let (ReportDescriptor::StaticDescriptor(descriptor)
+        | ReportDescriptor::DynamicDescriptor(descriptor)) = report_descriptor;
+        let length =
+            u16::try_from(descriptor.len()).map_err(|_| UsbHidBuilderError::SliceLengthOverflow)?;
+        self.validate_packet_sizes(report_descriptor)?;
+        let slot = self
+            .config
+            .alternate_report_descriptors
+            .iter_mut()
+            .find(|d| d.is_none())
+            .ok_or(UsbHidBuilderError::TooManyReportDescriptors)?;
+        *slot = Some((report_descriptor, length));
+        Ok(self)
+    }
+
+    /// Set the max packet size of the IN endpoint, 8, 16, 32 or 64 bytes, or up to 1024 bytes
+    /// [at high speed](InterfaceBuilder::high_speed), and no larger than the report buffer,
+    /// defaults to the size of the report buffer up to the largest packet size
+    ///
+    /// Input reports longer than a packet are written a packet at a time, and must fit in the
+    /// report buffer including their report ID. Sizes valid only at high speed are rejected when
+    /// the interface is allocated, so [`InterfaceBuilder::high_speed`] can be set before or after.
+    pub fn in_max_packet_size(mut self, size: u16) -> BuilderResult<Self> {
+        self.config.in_max_packet_size = Some(Self::packet_size(size, I::Buffer::CAPACITY)?);
+        self.validate_all_packet_sizes()?;
+        Ok(self)
+    }
+
+    /// Set the max packet size of the OUT endpoint, see [`InterfaceBuilder::in_max_packet_size`]
+    pub fn out_max_packet_size(mut self, size: u16) -> BuilderResult<Self> {
+        self.config.out_max_packet_size = Some(Self::packet_size(size, O::Buffer::CAPACITY)?);
+        self.validate_all_packet_sizes()?;
+        Ok(self)
+    }
+
+    fn packet_size(size: u16, capacity: u16) -> BuilderResult<u16> {
+        if (1..=max_packet_size(true)).contains(&size) && size <= capacity {
+            Ok(size)
+        } else {
+            Err(UsbHidBuilderError::InvalidPacketSize)
+        }
+    }
+
+    fn validate_all_packet_sizes(&self) -> BuilderResult<()> {
+        self.validate_packet_sizes(self.config.report_descriptor)?;
+        for (descriptor, _) in self
+            .config
+            .alternate_report_descriptors
+            .iter()
+            .chain(core::iter::once(&self.config.boot_report_descriptor))
+            .flatten()
+        {
+            self.validate_packet_sizes(*descriptor)?;
+        }
+        Ok(())
+    }
+
+    /// Check the reports of `report_descriptor` fit in the report buffers of the endpoints with a
+    /// configured max packet size
+    fn validate_packet_sizes(&self, report_descriptor: ReportDescriptor<'a>) -> BuilderResult<()> {
+        if self.config.in_max_packet_size.is_none() && self.config.out_max_packet_size.is_none() {
+            return Ok(());
+        }
+        let (ReportDescriptor::StaticDescriptor(descriptor)
+        | ReportDescriptor::DynamicDescriptor(descriptor)) = report_descriptor;
+        let reports = parse(descriptor).map_err(UsbHidBuilderError::InvalidReportDescriptor)?;
+        let report_id_len = usize::from(reports.uses_report_ids());
+
+        for report in reports.iter() {
+            let fits = |len: usize, packet_size: Option<u16>, capacity: u16| {
+                len == 0 || packet_size.is_none() || len + report_id_len <= usize::from(capacity)
+            };
+            if !fits(
+                report.input_len(),
+                self.config.in_max_packet_size,
+                I::Buffer::CAPACITY,
+            ) || !fits(
+                report.output_len(),
+                self.config.out_max_packet_size,
+                O::Buffer::CAPACITY,
+            ) {
+                return Err(UsbHidBuilderError::ReportLengthOverflow);
+            }
+        }
+        Ok(())
+    }
+
+    /// Describe the endpoints for a high speed bus, such as the high speed PHYs of STM32F7 and H7
+    /// parts: poll intervals are encoded in microframes and max packet sizes can be up to 1024
+    /// bytes
+    ///
+    /// `usb-device` doesn't report the speed the bus enumerated at, so set this when the bus is
+    /// configured for high speed.
+    #[must_use = "the setting only applies to the returned builder"]
+    pub fn high_speed(mut self) -> Self {
+        self.config.high_speed = true;
+        self
+    }
+
+    /// Describe the interface without endpoints in alternate setting 0 and with its endpoints in
+    /// alternate setting 1, so the host only reserves bandwidth for the endpoints while it has
+    /// selected alternate setting 1
+    ///
+    /// Reports are only written to and read from the endpoints while alternate setting 1 is
+    /// selected, the control pipe is used in either. The selected setting is available from
+    /// [`Interface::alternate_setting`] and [`DeviceClass::set_alternate_setting`] is called when
+    /// the host selects a setting.
+    #[must_use = "the setting only applies to the returned builder"]
+    pub fn endpointless_alternate_setting(mut self) -> Self {
+        self.config.endpointless_alternate_setting = true;
+        self
+    }
+
     pub fn boot_device(mut self, protocol: InterfaceProtocol) -> Self {
         self.config.protocol = protocol;
         self
     }
 
+    /// Declare the country of localized hardware in the HID descriptor, such as the layout of an
+    /// ISO or JIS keyboard, otherwise [`CountryCode::NotSupported`]
+    pub fn country_code(mut self, country_code: CountryCode) -> Self {
+        self.config.country_code = country_code;
+        self
+    }
+
+    /// Change the HID specification release, in BCD, advertised by the HID descriptor's `bcdHID`
+    /// from [`SPEC_VERSION_1_11`], such as
+    /// [`SPEC_VERSION_1_10`](crate::descriptor::SPEC_VERSION_1_10) for legacy hosts that reject
+    /// newer releases
+    pub fn hid_version(mut self, bcd_hid: u16) -> Self {
+        self.config.hid_version = bcd_hid;
+        self
+    }
+
     pub fn idle_default(mut self, duration: MillisDurationU32) -> BuilderResult<Self> {
         if duration.ticks() == 0 {
             self.config.idle_default = 0;
@@ -658,11 +1932,56 @@ where
         self
     }
 
+    /// See [`InterfaceConfig::with_ms_os_20`]
+    pub fn ms_os_20(mut self, function: MsOs20Function<'a>) -> Self {
+        self.config.ms_os_20 = Some(function);
+        self
+    }
+
+    /// Names of the report descriptor's controls, referenced by position from its String Index,
+    /// String Minimum and String Maximum items
+    ///
+    /// The report descriptor is copied to the control buffer to rewrite its string indices, so
+    /// must be 128 bytes or less even if it is `'static`.
+    pub fn report_strings(mut self, strings: &'a [&'a str]) -> Self {
+        self.config.report_strings = strings;
+        self
+    }
+
+    /// Physical descriptor sets describing the body parts used to operate the device's controls,
+    /// referenced by the Designator Index items of the report descriptor
+    ///
+    /// `sets` are sets 1 and up, each starting with its bPreference byte, and must all be the
+    /// same length. Physical descriptor set 0, giving the number of sets and their length, is
+    /// generated and its length advertised in the HID descriptor.
+    pub fn physical_descriptor_sets(mut self, sets: &'a [&'a [u8]]) -> BuilderResult<Self> {
+        let len = sets.first().map_or(0, |set| set.len());
+        if u8::try_from(sets.len()).is_err() || u16::try_from(len).is_err() {
+            return Err(UsbHidBuilderError::SliceLengthOverflow);
+        }
+        if sets.iter().any(|set| set.len() != len) {
+            return Err(UsbHidBuilderError::InvalidPhysicalDescriptor);
+        }
+        self.config.physical_descriptor_sets = sets;
+        Ok(self)
+    }
+
+    /// Add an interface name and report strings for a language, see
+    /// [`InterfaceConfig::with_localized_strings`]
+    pub fn localized_strings(
+        mut self,
+        lang_id: LangID,
+        description: &'a str,
+        report_strings: &'a [&'a str],
+    ) -> BuilderResult<Self> {
+        self.config = self
+            .config
+            .with_localized_strings(lang_id, description, report_strings)?;
+        Ok(self)
+    }
+
     pub fn with_out_endpoint(mut self, poll_interval: MillisDurationU32) -> BuilderResult<Self> {
-        self.config.out_endpoint = Some(EndpointConfig {
-            poll_interval: u8::try_from(poll_interval.to_millis())
-                .map_err(|_| UsbHidBuilderError::ValueOverflow)?,
-        });
+        self.config.out_endpoint = Some(EndpointConfig::new(poll_interval)?);
         Ok(self)
     }
 
@@ -672,22 +1991,161 @@ where
     }
 
     pub fn in_endpoint(mut self, poll_interval: MillisDurationU32) -> BuilderResult<Self> {
-        self.config.in_endpoint = EndpointConfig {
-            poll_interval: u8::try_from(poll_interval.to_millis())
-                .map_err(|_| UsbHidBuilderError::ValueOverflow)?,
-        };
+        self.config.in_endpoint = Some(EndpointConfig::new(poll_interval)?);
         Ok(self)
     }
 
+    /// Remove the interrupt endpoints, input and output reports are only exchanged over the
+    /// control pipe with `GET_REPORT` and `SET_REPORT`
+    ///
+    /// Saves endpoints on parts with few of them for configuration interfaces that don't need
+    /// the latency of interrupt endpoints. [`Interface::write_report`] holds the report for the
+    /// next `GET_REPORT`.
+    pub fn without_endpoints(mut self) -> Self {
+        self.config = self.config.without_endpoints();
+        self
+    }
+
+    /// Queue up to `DEPTH` output reports received on the OUT endpoint until they're read by
+    /// [`Interface::read_report`], so reports sent back-to-back aren't held up by a slow main loop
+    ///
+    /// Without a queue the OUT endpoint holds a single report, and the host can't send another
+    /// until it's read.
+    pub fn out_report_queue<const DEPTH: usize>(
+        self,
+    ) -> InterfaceBuilder<'a, I, O, R, DEPTH, IN_QUEUE> {
+        self.with_queues()
+    }
+
+    /// Queue up to `DEPTH` input reports written by [`Interface::write_report`] while the IN
+    /// endpoint is busy, they're written to the endpoint as the host reads the reports before them
+    ///
+    /// Without a queue [`Interface::write_report`] returns [`UsbError::WouldBlock`] while the IN
+    /// endpoint is busy.
+    pub fn in_report_queue<const DEPTH: usize>(
+        self,
+    ) -> InterfaceBuilder<'a, I, O, R, OUT_QUEUE, DEPTH> {
+        self.with_queues()
+    }
+
+    fn with_queues<const OUT_DEPTH: usize, const IN_DEPTH: usize>(
+        self,
+    ) -> InterfaceBuilder<'a, I, O, R, OUT_DEPTH, IN_DEPTH> {
+        let config = self.config;
+        InterfaceBuilder {
+            config: InterfaceConfig {
+                marker: PhantomData,
+                report_descriptor: config.report_descriptor,
+                report_descriptor_length: config.report_descriptor_length,
+                alternate_report_descriptors: config.alternate_report_descriptors,
+                boot_report_descriptor: config.boot_report_descriptor,
+                description: config.description,
+                report_strings: config.report_strings,
+                localized_strings: config.localized_strings,
+                physical_descriptor_sets: config.physical_descriptor_sets,
+                protocol: config.protocol,
+                country_code: config.country_code,
+                hid_version: config.hid_version,
+                idle_default: config.idle_default,
+                out_endpoint: config.out_endpoint,
+                in_endpoint: config.in_endpoint,
+                in_max_packet_size: config.in_max_packet_size,
+                out_max_packet_size: config.out_max_packet_size,
+                high_speed: config.high_speed,
+                endpointless_alternate_setting: config.endpointless_alternate_setting,
+                ms_os_20: config.ms_os_20,
+            },
+        }
+    }
+
     #[must_use]
-    pub fn build(self) -> InterfaceConfig<'a, I, O, R> {
+    pub fn build(self) -> InterfaceConfig<'a, I, O, R, OUT_QUEUE, IN_QUEUE> {
         self.config
     }
 }
 
-struct IdleManager<R> {
+/// Timing of the release report of a device releasing its usages itself, such as a consumer
+/// control, so that applications only write the pressed usages
+pub(crate) struct AutoRelease {
+    delay: MillisDurationU32,
+    /// Time until the release report is due, while a usage is pressed
+    remaining: Option<MillisDurationU32>,
+}
+
+impl AutoRelease {
+    pub fn new(delay: MillisDurationU32) -> Self {
+        Self {
+            delay,
+            remaining: None,
+        }
+    }
+
+    /// Call when a report is written, with whether it presses any usage
+    pub fn report_written(&mut self, pressed: bool) {
+        self.remaining = pressed.then_some(self.delay);
+    }
+
+    /// Call every 1ms, returns whether the release report is due, until it's written
+    pub fn tick(&mut self) -> bool {
+        let Some(remaining) = &mut self.remaining else {
+            return false;
+        };
+        if remaining.ticks() > 0 {
+            *remaining -= 1.millis();
+        }
+        remaining.ticks() == 0
+    }
+
+    pub fn released(&mut self) {
+        self.remaining = None;
+    }
+}
+
+/// Input reports of a device with several report IDs, each written only when it changes and by a
+/// later tick if the IN endpoint is busy, such as a media remote
+pub(crate) struct PendingReports<const N: usize> {
+    pending: [bool; N],
+}
+
+impl<const N: usize> Default for PendingReports<N> {
+    fn default() -> Self {
+        Self {
+            pending: [false; N],
+        }
+    }
+}
+
+impl<const N: usize> PendingReports<N> {
+    /// Mark report `index` to be written if it has `changed`
+    pub fn set(&mut self, index: usize, changed: bool) {
+        self.pending[index] |= changed;
+    }
+
+    /// Write the pending reports in order with `write`, stopping at the first error, such as
+    /// [`UsbError::WouldBlock`], and leaving the rest pending
+    pub fn flush(
+        &mut self,
+        mut write: impl FnMut(usize) -> usb_device::Result<()>,
+    ) -> usb_device::Result<()> {
+        for (index, pending) in self.pending.iter_mut().enumerate() {
+            if *pending {
+                write(index)?;
+                *pending = false;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.pending = [false; N];
+    }
+}
+
+pub(crate) struct IdleManager<R> {
     last_report: Option<R>,
     since_last_report: MillisDurationU32,
+    /// Idle rate of the current period, 0 for indefinite
+    idle: MillisDurationU32,
 }
 
 impl<R> Default for IdleManager<R> {
@@ -695,6 +2153,7 @@ impl<R> Default for IdleManager<R> {
         Self {
             last_report: Option::None,
             since_last_report: 0.millis(),
+            idle: 0.millis(),
         }
     }
 }
@@ -712,20 +2171,31 @@ where
         self.last_report.as_ref() == Some(report)
     }
 
-    /// Call every 1ms
-    pub fn tick(&mut self, timeout: MillisDurationU32) -> bool {
-        if timeout.ticks() == 0 {
+    /// Call every 1ms with the idle rate set by the host, returns whether the last report is due
+    /// to be repeated, until it's written
+    pub fn tick(&mut self, idle: MillisDurationU32) -> bool {
+        //"A new request will be executed as if it were issued immediately after the last report, if
+        //the new request is received at least 4 milliseconds before the end of the currently
+        //executing period. If the new request is received within 4 milliseconds of the end of the
+        //current period, then the new request will have no effect until after the report."
+        // - HID spec 7.2.4
+        if idle != self.idle
+            && (self.last_report.is_none()
+                || self.idle.ticks() == 0
+                || self.since_last_report + 4.millis() <= self.idle)
+        {
+            self.idle = idle;
+        }
+
+        if self.idle.ticks() == 0 {
             self.since_last_report = 0.millis();
             return false;
         }
 
-        if self.since_last_report >= timeout {
-            self.since_last_report = 0.millis();
-            true
-        } else {
+        if self.since_last_report < self.idle {
             self.since_last_report += 1.millis();
-            false
         }
+        self.last_report.is_some() && self.since_last_report >= self.idle
     }
 
     pub fn last_report(&self) -> Option<R> {
@@ -741,6 +2211,12 @@ where
 {
     interface: Interface<'a, B, I, O, ReportSingle>,
     idle_manager: IdleManager<Report>,
+    deduplicate: bool,
+    boot_report: Option<fn(&Report) -> BootInputReport>,
+    /// Report written with [`ManagedIdleInterface::write_report_retrying`] that's yet to be sent,
+    /// and the time since it was written
+    pending: Option<(Report, MillisDurationU32)>,
+    max_retry_age: MillisDurationU32,
 }
 
 #[allow(clippy::inline_always)]
@@ -750,10 +2226,19 @@ where
     I: InSize,
     O: OutSize,
 {
-    fn new(interface: Interface<'a, B, I, O, ReportSingle>) -> Self {
+    fn new(
+        interface: Interface<'a, B, I, O, ReportSingle>,
+        deduplicate: bool,
+        boot_report: Option<fn(&Report) -> BootInputReport>,
+        max_retry_age: MillisDurationU32,
+    ) -> Self {
         Self {
             interface,
             idle_manager: IdleManager::default(),
+            deduplicate,
+            boot_report,
+            pending: None,
+            max_retry_age,
         }
     }
 }
@@ -766,22 +2251,98 @@ where
     I: InSize,
     O: OutSize,
 {
+    /// Write a report, returns [`UsbHidError::Duplicate`] if it's the same as the last report
+    /// written, unless deduplication is disabled with
+    /// [`ManagedIdleInterfaceConfig::without_deduplication`]
     pub fn write_report(&mut self, report: &Report) -> Result<(), UsbHidError> {
-        if self.idle_manager.is_duplicate(report) {
+        // The caller retries this report, a pending report would overwrite it
+        self.pending = None;
+        if self.deduplicate && self.idle_manager.is_duplicate(report) {
             Err(UsbHidError::Duplicate)
+        } else {
+            self.write(report)
+        }
+    }
+
+    /// Write a report, retrying from [`DeviceClass::tick`] while the IN endpoint is busy rather
+    /// than returning [`UsbHidError::WouldBlock`]
+    ///
+    /// The report replaces any report still being retried. It's dropped if it can't be sent within
+    /// [`ManagedIdleInterfaceConfig::with_max_retry_age`], such as while the host isn't reading
+    /// reports, returns [`UsbHidError::Duplicate`] like [`ManagedIdleInterface::write_report`].
+    pub fn write_report_retrying(&mut self, report: &Report) -> Result<(), UsbHidError> {
+        let duplicate = match &self.pending {
+            Some((pending, _)) => pending == report,
+            None => self.idle_manager.is_duplicate(report),
+        };
+        if self.deduplicate && duplicate {
+            return Err(UsbHidError::Duplicate);
+        }
+        self.pending = None;
+        match self.write(report) {
+            Err(UsbHidError::WouldBlock) => {
+                trace!("Retrying report");
+                self.pending = Some((*report, 0.millis()));
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Whether a report written with [`ManagedIdleInterface::write_report_retrying`] is yet to be
+    /// sent
+    #[must_use]
+    pub fn is_retrying(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Retry the pending report, if any, returns whether it's still pending
+    fn retry(&mut self) -> Result<bool, UsbHidError> {
+        let Some((report, age)) = self.pending else {
+            return Ok(false);
+        };
+        // A suspended host can't read reports, so they don't age until it resumes
+        if self.interface.is_suspended() {
+            return Ok(true);
+        }
+        let age = age + 1.millis();
+        if age > self.max_retry_age {
+            warn!("Dropped report older than the max retry age");
+            count(&mut self.interface.diagnostics.input_reports_dropped);
+            self.pending = None;
+            return Ok(false);
+        }
+        match self.write(&report) {
+            Err(UsbHidError::WouldBlock) => {
+                self.pending = Some((report, age));
+                Ok(true)
+            }
+            result => {
+                self.pending = None;
+                result.map(|()| false)
+            }
+        }
+    }
+
+    /// Write `report`, translated to the boot protocol format while the host has selected the
+    /// boot protocol if the interface has a boot format
+    fn write(&mut self, report: &Report) -> Result<(), UsbHidError> {
+        let boot_report = self
+            .boot_report
+            .filter(|_| self.interface.protocol() == HidProtocol::Boot);
+        let result = if let Some(boot_report) = boot_report {
+            self.interface.write_report(&boot_report(report))
         } else {
             let data = report.pack().map_err(|_| {
                 error!("Error packing report");
                 UsbHidError::SerializationError
             })?;
+            self.interface.write_report(&data)
+        };
 
-            self.interface
-                .write_report(&data)
-                .map_err(UsbHidError::from)
-                .map(|_| {
-                    self.idle_manager.report_written(*report);
-                })
-        }
+        result.map_err(UsbHidError::from).map(|_| {
+            self.idle_manager.report_written(*report);
+        })
     }
 
     pub fn read_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize> {
@@ -805,29 +2366,71 @@ where
 
     fn reset(&mut self) {
         self.idle_manager = IdleManager::default();
+        self.pending = None;
+    }
+
+    /// The last report may have been lost, so it's resent from [`DeviceClass::tick`], even if
+    /// deduplicated, for the host to have the current state
+    fn endpoint_halt_cleared(&mut self) {
+        if let Some(report) = self
+            .pending
+            .map(|(report, _)| report)
+            .or_else(|| self.idle_manager.last_report())
+        {
+            self.pending = Some((report, 0.millis()));
+        }
     }
 
     fn tick(&mut self) -> Result<(), UsbHidError> {
-        if !(self.idle_manager.tick(self.interface.global_idle())) {
+        // The last report isn't repeated while a newer report is pending
+        let pending = self.retry()?;
+        // Reports aren't repeated while suspended, the host would miss them
+        if pending
+            || self.interface.is_suspended()
+            || !(self.idle_manager.tick(self.interface.global_idle()))
+        {
             Ok(())
         } else if let Some(r) = self.idle_manager.last_report() {
-            let data = r.pack().map_err(|_| {
-                error!("Error packing report");
-                UsbHidError::SerializationError
-            })?;
-            match self.interface.write_report(&data) {
-                Ok(n) => {
-                    self.idle_manager.report_written(r);
-                    Ok(n)
-                }
-                Err(UsbError::WouldBlock) => Err(UsbHidError::WouldBlock),
-                Err(e) => Err(UsbHidError::UsbError(e)),
-            }
-            .map(|_| ())
+            self.write(&r)
         } else {
             Ok(())
         }
     }
+
+    /// Answered with the last report written, as it was sent to the host
+    fn get_input_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        let report = self
+            .idle_manager
+            .last_report()
+            .ok_or(UsbError::WouldBlock)?;
+        let boot_report = self
+            .boot_report
+            .filter(|_| self.interface.protocol() == HidProtocol::Boot)
+            .map(|boot_report| boot_report(&report));
+        let packed;
+        let report = if let Some(boot_report) = &boot_report {
+            boot_report.as_slice()
+        } else {
+            packed = report.pack().map_err(|_| {
+                error!("Error packing report");
+                UsbError::ParseError
+            })?;
+            &packed[..]
+        };
+        let report = match self.interface.boot_input_report_len(report) {
+            Some(len) if len < report.len() => &report[..len],
+            _ => report,
+        };
+
+        // Reports with a report ID start with it
+        if report_id != 0 && report.first() != Some(&report_id) {
+            return Err(UsbError::Unsupported);
+        }
+        data.get_mut(..report.len())
+            .ok_or(UsbError::BufferOverflow)?
+            .copy_from_slice(report);
+        Ok(report.len())
+    }
 }
 
 pub struct ManagedIdleInterfaceConfig<'a, Report, I, O>
@@ -837,6 +2440,9 @@ where
 {
     report: PhantomData<Report>,
     interface_config: InterfaceConfig<'a, I, O, ReportSingle>,
+    deduplicate: bool,
+    boot_report: Option<fn(&Report) -> BootInputReport>,
+    max_retry_age: MillisDurationU32,
 }
 
 impl<'a, Report, I, O> ManagedIdleInterfaceConfig<'a, Report, I, O>
@@ -849,8 +2455,110 @@ where
         Self {
             interface_config,
             report: PhantomData,
+            deduplicate: true,
+            boot_report: None,
+            max_retry_age: 100.millis(),
         }
     }
+
+    /// Translate reports to the boot protocol format with `boot_report` while the host has
+    /// selected the boot protocol, such as a bitmap NKRO keyboard report to a boot keyboard
+    /// report. The interface needs a [`InterfaceBuilder::boot_report_descriptor`] describing
+    /// the boot format.
+    #[must_use]
+    pub fn with_boot_report(mut self, boot_report: fn(&Report) -> BootInputReport) -> Self {
+        self.boot_report = Some(boot_report);
+        self
+    }
+
+    /// Transmit every report written, rather than suppressing reports identical to the last one
+    /// written, such as for games sampling raw input that expect a report each poll
+    #[must_use]
+    pub fn without_deduplication(mut self) -> Self {
+        self.deduplicate = false;
+        self
+    }
+
+    /// Drop reports written with [`ManagedIdleInterface::write_report_retrying`] that can't be
+    /// sent within `max_retry_age`, 100ms by default
+    #[must_use]
+    pub fn with_max_retry_age(mut self, max_retry_age: MillisDurationU32) -> Self {
+        self.max_retry_age = max_retry_age;
+        self
+    }
+
+    /// See [`InterfaceConfig::with_poll_intervals`]
+    pub fn with_poll_intervals(
+        mut self,
+        in_interval: MillisDurationU32,
+        out_interval: MillisDurationU32,
+    ) -> BuilderResult<Self> {
+        self.interface_config = self
+            .interface_config
+            .with_poll_intervals(in_interval, out_interval)?;
+        Ok(self)
+    }
+
+    /// See [`InterfaceConfig::with_out_endpoint`]
+    pub fn with_out_endpoint(mut self, poll_interval: MillisDurationU32) -> BuilderResult<Self> {
+        self.interface_config = self.interface_config.with_out_endpoint(poll_interval)?;
+        Ok(self)
+    }
+
+    /// See [`InterfaceConfig::without_out_endpoint`]
+    #[must_use]
+    pub fn without_out_endpoint(mut self) -> Self {
+        self.interface_config = self.interface_config.without_out_endpoint();
+        self
+    }
+
+    /// See [`InterfaceConfig::high_speed`]
+    #[must_use]
+    pub fn high_speed(mut self) -> Self {
+        self.interface_config = self.interface_config.high_speed();
+        self
+    }
+
+    /// See [`InterfaceConfig::endpointless_alternate_setting`]
+    #[must_use]
+    pub fn endpointless_alternate_setting(mut self) -> Self {
+        self.interface_config = self.interface_config.endpointless_alternate_setting();
+        self
+    }
+
+    /// See [`InterfaceConfig::with_description`]
+    #[must_use]
+    pub fn with_description(mut self, description: &'a str) -> Self {
+        self.interface_config = self.interface_config.with_description(description);
+        self
+    }
+
+    /// See [`InterfaceConfig::with_country_code`]
+    #[must_use]
+    pub fn with_country_code(mut self, country_code: CountryCode) -> Self {
+        self.interface_config = self.interface_config.with_country_code(country_code);
+        self
+    }
+
+    /// See [`InterfaceConfig::with_hid_version`]
+    #[must_use]
+    pub fn with_hid_version(mut self, bcd_hid: u16) -> Self {
+        self.interface_config = self.interface_config.with_hid_version(bcd_hid);
+        self
+    }
+
+    /// See [`InterfaceConfig::with_localized_strings`]
+    pub fn with_localized_strings(
+        mut self,
+        lang_id: LangID,
+        description: &'a str,
+        report_strings: &'a [&'a str],
+    ) -> BuilderResult<Self> {
+        self.interface_config =
+            self.interface_config
+                .with_localized_strings(lang_id, description, report_strings)?;
+        Ok(self)
+    }
 }
 
 impl<'a, B, Report, I, O> UsbAllocatable<'a, B> for ManagedIdleInterfaceConfig<'a, Report, I, O>
@@ -861,7 +2569,395 @@ where
 {
     type Allocated = ManagedIdleInterface<'a, B, Report, I, O>;
 
-    fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        ManagedIdleInterface::new(self.interface_config.allocate(usb_alloc))
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(ManagedIdleInterface::new(
+            self.interface_config.try_allocate(usb_alloc)?,
+            self.deduplicate,
+            self.boot_report,
+            self.max_retry_age,
+        ))
+    }
+}
+
+/// Feature report held by [`FeatureReports`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FeatureReport<const LEN: usize> {
+    report_id: u8,
+    data: Vec<u8, LEN>,
+    changed: bool,
+}
+
+/// Device with feature reports held in buffers, registered by report ID with
+/// [`FeatureReportsConfig::report`]
+///
+/// `GET_REPORT(Feature)` and `SET_REPORT(Feature)` requests for registered report IDs are handled
+/// by reading and writing the buffers, requests for other report IDs are passed to the device.
+/// Holds up to `N` feature reports of up to `LEN` bytes, including their report ID prefix if the
+/// report descriptor uses report IDs.
+pub struct FeatureReports<D, const N: usize, const LEN: usize> {
+    device: D,
+    reports: Vec<FeatureReport<LEN>, N>,
+}
+
+impl<D, const N: usize, const LEN: usize> FeatureReports<D, N, LEN> {
+    pub fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+
+    /// Current value of a feature report
+    #[must_use]
+    pub fn feature_report(&self, report_id: u8) -> Option<&[u8]> {
+        self.reports
+            .iter()
+            .find(|r| r.report_id == report_id)
+            .map(|r| r.data.as_slice())
+    }
+
+    /// Value of a feature report, if the host has set it since it was last read
+    pub fn read_feature_report(&mut self, report_id: u8) -> Option<&[u8]> {
+        self.reports
+            .iter_mut()
+            .find(|r| r.report_id == report_id && r.changed)
+            .map(|r| {
+                r.changed = false;
+                r.data.as_slice()
+            })
+    }
+
+    /// Update the value of a feature report read by the host, `data` must be the same length as
+    /// the registered report
+    pub fn write_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        let report = self
+            .reports
+            .iter_mut()
+            .find(|r| r.report_id == report_id)
+            .ok_or(UsbError::InvalidState)?;
+        if data.len() == report.data.len() {
+            report.data.copy_from_slice(data);
+            Ok(())
+        } else {
+            Err(UsbError::BufferOverflow)
+        }
+    }
+}
+
+impl<'a, D, const N: usize, const LEN: usize> DeviceClass<'a> for FeatureReports<D, N, LEN>
+where
+    D: DeviceClass<'a>,
+{
+    type I = D::I;
+
+    fn interface(&mut self) -> &mut Self::I {
+        self.device.interface()
+    }
+
+    fn reset(&mut self) {
+        self.device.reset();
+    }
+
+    fn tick(&mut self) -> Result<(), UsbHidError> {
+        self.device.tick()
+    }
+
+    fn get_input_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        self.device.get_input_report(report_id, data)
+    }
+
+    fn get_feature_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        match self.feature_report(report_id) {
+            Some(report) => {
+                let buffer = data
+                    .get_mut(..report.len())
+                    .ok_or(UsbError::BufferOverflow)?;
+                buffer.copy_from_slice(report);
+                Ok(report.len())
+            }
+            None => self.device.get_feature_report(report_id, data),
+        }
+    }
+
+    fn get_static_feature_report(&mut self, report_id: u8) -> Option<&'static [u8]> {
+        if self.feature_report(report_id).is_some() {
+            None
+        } else {
+            self.device.get_static_feature_report(report_id)
+        }
+    }
+
+    fn set_feature_report(&mut self, report_id: u8, data: &[u8]) -> usb_device::Result<()> {
+        match self.reports.iter_mut().find(|r| r.report_id == report_id) {
+            Some(report) if data.len() == report.data.len() => {
+                report.data.copy_from_slice(data);
+                report.changed = true;
+                Ok(())
+            }
+            Some(_) => Err(UsbError::ParseError),
+            None => self.device.set_feature_report(report_id, data),
+        }
+    }
+
+    fn set_alternate_setting(&mut self, alternate_setting: u8) {
+        self.device.set_alternate_setting(alternate_setting);
+    }
+
+    fn endpoint_halt_cleared(&mut self) {
+        self.device.endpoint_halt_cleared();
+    }
+
+    fn suspend(&mut self) {
+        self.device.suspend();
+    }
+
+    fn resume(&mut self) {
+        self.device.resume();
+    }
+}
+
+/// Configuration of [`FeatureReports`], wrapping the configuration of a device
+pub struct FeatureReportsConfig<C, const N: usize, const LEN: usize> {
+    config: C,
+    reports: Vec<FeatureReport<LEN>, N>,
+}
+
+impl<C, const N: usize, const LEN: usize> FeatureReportsConfig<C, N, LEN> {
+    #[must_use]
+    pub fn new(config: C) -> Self {
+        Self {
+            config,
+            reports: Vec::new(),
+        }
+    }
+
+    /// Register a feature report with its initial value, including the report ID prefix if the
+    /// report descriptor uses report IDs, use report ID 0 if it doesn't
+    pub fn report(mut self, report_id: u8, initial: &[u8]) -> BuilderResult<Self> {
+        if self.reports.iter().any(|r| r.report_id == report_id) {
+            return Err(UsbHidBuilderError::DuplicateReportId);
+        }
+        let data =
+            Vec::from_slice(initial).map_err(|()| UsbHidBuilderError::SliceLengthOverflow)?;
+        self.reports
+            .push(FeatureReport {
+                report_id,
+                data,
+                changed: false,
+            })
+            .map_err(|_| UsbHidBuilderError::TooManyFeatureReports)?;
+        Ok(self)
+    }
+}
+
+impl<'a, B, C, const N: usize, const LEN: usize> UsbAllocatable<'a, B>
+    for FeatureReportsConfig<C, N, LEN>
+where
+    B: UsbBus + 'a,
+    C: UsbAllocatable<'a, B>,
+{
+    type Allocated = FeatureReports<C::Allocated, N, LEN>;
+
+    fn try_allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> BuilderResult<Self::Allocated> {
+        Ok(FeatureReports {
+            device: self.config.try_allocate(usb_alloc)?,
+            reports: self.reports,
+        })
+    }
+}
+
+/// Handler of output reports passed on by a [`ReportRouter`]
+///
+/// Implemented for closures taking the report data, and by [`PackedSink`] for reports unpacked
+/// to a [`PackedStruct`].
+pub trait ReportSink {
+    /// Receive the data of a report, without its report ID
+    fn receive(&mut self, data: &[u8]) -> Result<(), UsbHidError>;
+}
+
+impl<F: FnMut(&[u8])> ReportSink for F {
+    fn receive(&mut self, data: &[u8]) -> Result<(), UsbHidError> {
+        self(data);
+        Ok(())
+    }
+}
+
+/// [`ReportSink`] unpacking reports to `R` before passing them to a closure
+pub struct PackedSink<R, F> {
+    handler: F,
+    report: PhantomData<R>,
+}
+
+impl<R, F: FnMut(R)> PackedSink<R, F> {
+    #[must_use]
+    pub fn new(handler: F) -> Self {
+        Self {
+            handler,
+            report: PhantomData,
+        }
+    }
+}
+
+impl<R, F, const LEN: usize> ReportSink for PackedSink<R, F>
+where
+    R: PackedStruct<ByteArray = [u8; LEN]>,
+    F: FnMut(R),
+{
+    fn receive(&mut self, data: &[u8]) -> Result<(), UsbHidError> {
+        let report = R::unpack_from_slice(data).map_err(|_| {
+            error!("Error unpacking report");
+            UsbHidError::SerializationError
+        })?;
+        (self.handler)(report);
+        Ok(())
+    }
+}
+
+/// Dispatches output reports to up to `N` [`ReportSink`]s by report ID
+///
+/// A sink routed from report ID 0 receives every report whole, for report descriptors without
+/// report IDs. Otherwise the first byte of a report is its report ID, and the sink routed from
+/// that ID receives the rest of the report.
+pub struct ReportRouter<'h, const N: usize> {
+    routes: Vec<(u8, &'h mut dyn ReportSink), N>,
+}
+
+impl<const N: usize> Default for ReportRouter<'_, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'h, const N: usize> ReportRouter<'h, N> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    /// Route reports with `report_id` to `sink`
+    pub fn route(mut self, report_id: u8, sink: &'h mut dyn ReportSink) -> BuilderResult<Self> {
+        if self.routes.iter().any(|(id, _)| *id == report_id) {
+            return Err(UsbHidBuilderError::DuplicateReportId);
+        }
+        self.routes
+            .push((report_id, sink))
+            .map_err(|_| UsbHidBuilderError::TooManyReportHandlers)?;
+        Ok(self)
+    }
+
+    /// Pass `report` to the sink routed from its report ID
+    pub fn dispatch(&mut self, report: &[u8]) -> Result<(), UsbHidError> {
+        let (report_id, data) = if self.routes.iter().any(|(id, _)| *id == 0) {
+            (0, report)
+        } else {
+            report
+                .split_first()
+                .map(|(id, data)| (*id, data))
+                .ok_or(UsbHidError::UsbError(UsbError::ParseError))?
+        };
+
+        let Some((_, sink)) = self.routes.iter_mut().find(|(id, _)| *id == report_id) else {
+            warn!("No handler for report ID{}", report_id);
+            return Err(UsbHidError::UsbError(UsbError::Unsupported));
+        };
+        sink.receive(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use crate::device::keyboard::KeyboardLedsReport;
+    use crate::interface::{IdleManager, PackedSink, ReportRouter};
+    use crate::usb_class::UsbHidBuilderError;
+    use crate::UsbHidError;
+    use fugit::ExtU32;
+    use usb_device::UsbError;
+
+    #[test]
+    fn idle_repeats_last_report_until_written() {
+        let mut idle_manager = IdleManager::default();
+        idle_manager.report_written(1);
+
+        assert!((0..3).all(|_| !idle_manager.tick(4.millis())));
+        assert!(idle_manager.tick(4.millis()));
+        // Still due while the endpoint is busy
+        assert!(idle_manager.tick(4.millis()));
+        idle_manager.report_written(1);
+        assert!(!idle_manager.tick(4.millis()));
+        assert!(!idle_manager.tick(0.millis()));
+    }
+
+    #[test]
+    fn new_idle_rate_applies_from_last_report() {
+        let mut idle_manager = IdleManager::default();
+        idle_manager.tick(20.millis());
+        idle_manager.report_written(1);
+
+        // More than 4ms before the end of the period, the new rate applies from the last report
+        assert!((0..9).all(|_| !idle_manager.tick(20.millis())));
+        assert!(idle_manager.tick(8.millis()));
+        idle_manager.report_written(1);
+
+        // Within 4ms of the end of the period, the new rate applies after the next report
+        assert!((0..5).all(|_| !idle_manager.tick(8.millis())));
+        assert!(!idle_manager.tick(40.millis()));
+        assert!(!idle_manager.tick(40.millis()));
+        assert!(idle_manager.tick(40.millis()));
+        idle_manager.report_written(1);
+        assert!((0..39).all(|_| !idle_manager.tick(40.millis())));
+        assert!(idle_manager.tick(40.millis()));
+    }
+
+    #[test]
+    fn reports_are_dispatched_by_report_id() {
+        let mut leds = None;
+        let mut raw = std::vec::Vec::new();
+        {
+            let mut led_sink = PackedSink::new(|r: KeyboardLedsReport| leds = Some(r));
+            let mut raw_sink = |data: &[u8]| raw.extend_from_slice(data);
+            let mut router = ReportRouter::<2>::new()
+                .route(1, &mut led_sink)
+                .unwrap()
+                .route(2, &mut raw_sink)
+                .unwrap();
+
+            router.dispatch(&[1, 0x02]).unwrap();
+            router.dispatch(&[2, 0xAA, 0xBB]).unwrap();
+            assert!(matches!(
+                router.dispatch(&[3, 0x00]),
+                Err(UsbHidError::UsbError(UsbError::Unsupported))
+            ));
+            assert!(matches!(
+                router.dispatch(&[1, 0x00, 0x00]),
+                Err(UsbHidError::SerializationError)
+            ));
+        }
+
+        assert_eq!(
+            leds,
+            Some(KeyboardLedsReport {
+                caps_lock: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(raw, [0xAA, 0xBB]);
+    }
+
+    #[test]
+    fn report_id_zero_receives_whole_reports() {
+        let mut received = std::vec::Vec::new();
+        let mut sink = |data: &[u8]| received.extend_from_slice(data);
+        let mut router = ReportRouter::<1>::new().route(0, &mut sink).unwrap();
+
+        router.dispatch(&[1, 2, 3]).unwrap();
+        assert!(matches!(
+            ReportRouter::<1>::new()
+                .route(1, &mut |_: &[u8]| {})
+                .unwrap()
+                .route(2, &mut |_: &[u8]| {}),
+            Err(UsbHidBuilderError::TooManyReportHandlers)
+        ));
+
+        drop(router);
+        assert_eq!(received, [1, 2, 3]);
     }
 }
@@ -127,15 +127,10 @@
 //!         keyboard.tick().unwrap();
 //!     }
 //!
-//!     if usb_dev.poll(&mut [&mut keyboard]) {
-//!         match keyboard.device().read_report() {
+//!     usb_dev.poll(&mut [&mut keyboard]);
 //!
-//!             Ok(l) => {
-//!                 update_leds(l);
-//!             }
-//!             _ => {}
-//!
-//!         }
+//!     if let Ok(Some(leds)) = keyboard.device().leds_changed() {
+//!         update_leds(leds);
 //!     }
 //! }
 //! ```
@@ -148,8 +143,13 @@ pub(crate) mod fmt;
 #[cfg(test)]
 extern crate std;
 
+//Allow the derive macros to refer to this crate by name within its own tests
+#[cfg(test)]
+extern crate self as usbd_human_interface_device;
+
 use usb_device::UsbError;
 
+pub mod channel;
 pub mod descriptor;
 pub mod device;
 pub mod interface;
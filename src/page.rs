@@ -31,10 +31,11 @@ use packed_struct::prelude::*;
     Hash,
     IntoPrimitive,
     FromPrimitive,
+    Default,
 )]
 #[repr(u8)]
 pub enum Leds {
-    #[num_enum(default)]
+    #[default]
     Undefined = 0x00,
     NumLock = 0x01,
     CapsLock = 0x02,
@@ -114,9 +115,32 @@ pub enum Leds {
     //0x4C-0xFFFF Reserved
 }
 
-impl Default for Leds {
-    fn default() -> Self {
-        Self::Undefined
+/// Button usage page
+///
+/// See [Universal Serial Bus (USB) HID Usage Tables Version 1.12](<https://www.usb.org/sites/default/files/documents/hut1_12v2.pdf>):
+/// Section 12 Button Page (0x09)
+///
+/// Button usages are the button number, from 1, with 0 meaning no button pressed
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct Button(pub u16);
+
+impl Button {
+    pub const NO_BUTTON: Self = Self(0x00);
+    pub const PRIMARY: Self = Self(0x01);
+    pub const SECONDARY: Self = Self(0x02);
+    pub const TERTIARY: Self = Self(0x03);
+}
+
+impl From<Button> for u16 {
+    fn from(button: Button) -> Self {
+        button.0
+    }
+}
+
+impl From<u16> for Button {
+    fn from(usage: u16) -> Self {
+        Self(usage)
     }
 }
 
@@ -124,6 +148,9 @@ impl Default for Leds {
 ///
 /// See [Universal Serial Bus (USB) HID Usage Tables Version 1.12](<https://www.usb.org/sites/default/files/documents/hut1_12v2.pdf>):
 /// Section 15 Consumer Page (0x0C)
+///
+/// Includes usages added in [HID Usage Tables Version 1.4](<https://usb.org/sites/default/files/hut1_4.pdf>):
+/// Section 15 Consumer Page (0x0C)
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(
     Debug,
@@ -137,10 +164,11 @@ impl Default for Leds {
     Hash,
     IntoPrimitive,
     FromPrimitive,
+    Default,
 )]
 #[repr(u16)]
 pub enum Consumer {
-    #[num_enum(default)]
+    #[default]
     Unassigned = 0x00,
     ConsumerControl = 0x01,
     NumericKeyPad = 0x02,
@@ -178,7 +206,31 @@ pub enum Consumer {
     BroadcastMode = 0x64,
     Snapshot = 0x65,
     Still = 0x66,
-    //0x67-0x7F Reserved
+    PictureInPictureToggle = 0x67,
+    PictureInPictureSwap = 0x68,
+    RedMenuButton = 0x69,
+    GreenMenuButton = 0x6A,
+    BlueMenuButton = 0x6B,
+    YellowMenuButton = 0x6C,
+    Aspect = 0x6D,
+    ThreeDModeSelect = 0x6E,
+    DisplayBrightnessIncrement = 0x6F,
+    DisplayBrightnessDecrement = 0x70,
+    DisplayBrightness = 0x71,
+    DisplayBacklightToggle = 0x72,
+    DisplaySetBrightnessToMinimum = 0x73,
+    DisplaySetBrightnessToMaximum = 0x74,
+    DisplaySetAutoBrightness = 0x75,
+    CameraAccessEnabled = 0x76,
+    CameraAccessDisabled = 0x77,
+    CameraAccessToggle = 0x78,
+    KeyboardBrightnessIncrement = 0x79,
+    KeyboardBrightnessDecrement = 0x7A,
+    KeyboardBacklightSetLevel = 0x7B,
+    KeyboardBacklightOOC = 0x7C,
+    KeyboardBacklightSetMinimum = 0x7D,
+    KeyboardBacklightSetMaximum = 0x7E,
+    KeyboardBacklightAuto = 0x7F,
     Selection = 0x80,
     AssignSelection = 0x81,
     ModeStep = 0x82,
@@ -248,7 +300,18 @@ pub enum Consumer {
     StopEject = 0xCC,
     PlayPause = 0xCD,
     PlaySkip = 0xCE,
-    //0xCF-0xDF Reserved
+    VoiceCommand = 0xCF,
+    InvokeCaptureInterface = 0xD0,
+    StartOrStopGameRecording = 0xD1,
+    HistoricalGameCapture = 0xD2,
+    CaptureGameScreenshot = 0xD3,
+    ShowOrHideRecordingIndicator = 0xD4,
+    StartOrStopMicrophoneCapture = 0xD5,
+    StartOrStopCameraCapture = 0xD6,
+    StartOrStopGameBroadcast = 0xD7,
+    StartOrStopVoiceDictationSession = 0xD8,
+    InvokeDismissEmojiPicker = 0xD9,
+    //0xDA-0xDF Reserved
     Volume = 0xE0,
     Balance = 0xE1,
     Mute = 0xE2,
@@ -380,7 +443,11 @@ pub enum Consumer {
     ALOnlineActivityBrowser = 0x1C5,
     ALResearchSearchBrowser = 0x1C6,
     ALAudioPlayer = 0x1C7,
-    //0x1C8-0x1FF Reserved
+    ALMessageStatus = 0x1C8,
+    ALContactSync = 0x1C9,
+    ALNavigation = 0x1CA,
+    ALContextAwareDesktopAssistant = 0x1CB,
+    //0x1CC-0x1FF Reserved
     GenericGUIApplicationControls = 0x200,
     ACNew = 0x201,
     ACOpen = 0x202,
@@ -522,13 +589,57 @@ pub enum Consumer {
     ACSplit = 0x29A,
     ACDistributeHorizontally = 0x29B,
     ACDistributeVertically = 0x29C,
-    //0x29D-0xFFFF Reserved
-}
-
-impl Default for Consumer {
-    fn default() -> Self {
-        Self::Unassigned
-    }
+    ACKeyboardLayoutSelect = 0x29D,
+    ACNavigationGuidance = 0x29E,
+    ACDesktopShowAllWindows = 0x29F,
+    ACSoftKeyLeft = 0x2A0,
+    ACSoftKeyRight = 0x2A1,
+    ACDesktopShowAllApplications = 0x2A2,
+    //0x2A3-0x2AF Reserved
+    ACIdleKeepAlive = 0x2B0,
+    //0x2B1-0x2BF Reserved
+    ExtendedKeyboardAttributesCollection = 0x2C0,
+    KeyboardFormFactor = 0x2C1,
+    KeyboardKeyType = 0x2C2,
+    KeyboardPhysicalLayout = 0x2C3,
+    VendorSpecificKeyboardPhysicalLayout = 0x2C4,
+    KeyboardIETFLanguageTagIndex = 0x2C5,
+    ImplementedKeyboardInputAssistControls = 0x2C6,
+    KeyboardInputAssistPrevious = 0x2C7,
+    KeyboardInputAssistNext = 0x2C8,
+    KeyboardInputAssistPreviousGroup = 0x2C9,
+    KeyboardInputAssistNextGroup = 0x2CA,
+    KeyboardInputAssistAccept = 0x2CB,
+    KeyboardInputAssistCancel = 0x2CC,
+    //0x2CD-0x2CF Reserved
+    PrivacyScreenToggle = 0x2D0,
+    PrivacyScreenLevelDecrement = 0x2D1,
+    PrivacyScreenLevelIncrement = 0x2D2,
+    PrivacyScreenLevelMinimum = 0x2D3,
+    PrivacyScreenLevelMaximum = 0x2D4,
+    //0x2D5-0x4FF Reserved
+    ContactEdited = 0x500,
+    ContactAdded = 0x501,
+    ContactRecordActive = 0x502,
+    ContactIndex = 0x503,
+    ContactNickname = 0x504,
+    ContactFirstName = 0x505,
+    ContactLastName = 0x506,
+    ContactFullName = 0x507,
+    ContactPhoneNumberPersonal = 0x508,
+    ContactPhoneNumberBusiness = 0x509,
+    ContactPhoneNumberMobile = 0x50A,
+    ContactPhoneNumberPager = 0x50B,
+    ContactPhoneNumberFax = 0x50C,
+    ContactPhoneNumberOther = 0x50D,
+    ContactEmailPersonal = 0x50E,
+    ContactEmailBusiness = 0x50F,
+    ContactEmailOther = 0x510,
+    ContactEmailMain = 0x511,
+    ContactSpeedDialNumber = 0x512,
+    ContactStatusFlag = 0x513,
+    ContactMisc = 0x514,
+    //0x515-0xFFFF Reserved
 }
 
 /// Generic Desktop usage page
@@ -548,10 +659,11 @@ impl Default for Consumer {
     PrimitiveEnum,
     IntoPrimitive,
     FromPrimitive,
+    Default,
 )]
 #[repr(u8)]
 pub enum Desktop {
-    #[num_enum(default)]
+    #[default]
     Undefined = 0x00,
     Pointer = 0x01,
     Mouse = 0x02,
@@ -612,10 +724,119 @@ pub enum Desktop {
     //0x94-0xFFFF Reserved
 }
 
-impl Default for Desktop {
-    fn default() -> Self {
-        Self::Undefined
-    }
+/// Digitizers usage page
+///
+/// See [HID Usage Tables Version 1.4](<https://usb.org/sites/default/files/hut1_4.pdf>):
+/// Section 16 Digitizers Page (0x0D)
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    PrimitiveEnum,
+    IntoPrimitive,
+    FromPrimitive,
+    Default,
+)]
+#[repr(u8)]
+pub enum Digitizer {
+    #[default]
+    Undefined = 0x00,
+    Digitizer = 0x01,
+    Pen = 0x02,
+    LightPen = 0x03,
+    TouchScreen = 0x04,
+    TouchPad = 0x05,
+    Whiteboard = 0x06,
+    CoordinateMeasuringMachine = 0x07,
+    Digitizer3D = 0x08,
+    StereoPlotter = 0x09,
+    ArticulatedArm = 0x0A,
+    Armature = 0x0B,
+    MultiplePointDigitizer = 0x0C,
+    FreeSpaceWand = 0x0D,
+    DeviceConfiguration = 0x0E,
+    CapacitiveHeatMapDigitizer = 0x0F,
+    //0x10-0x1F Reserved
+    Stylus = 0x20,
+    Puck = 0x21,
+    Finger = 0x22,
+    DeviceSettings = 0x23,
+    CharacterGesture = 0x24,
+    //0x25-0x2F Reserved
+    TipPressure = 0x30,
+    BarrelPressure = 0x31,
+    InRange = 0x32,
+    Touch = 0x33,
+    Untouch = 0x34,
+    Tap = 0x35,
+    Quality = 0x36,
+    DataValid = 0x37,
+    TransducerIndex = 0x38,
+    TabletFunctionKeys = 0x39,
+    ProgramChangeKeys = 0x3A,
+    BatteryStrength = 0x3B,
+    Invert = 0x3C,
+    XTilt = 0x3D,
+    YTilt = 0x3E,
+    Azimuth = 0x3F,
+    Altitude = 0x40,
+    Twist = 0x41,
+    TipSwitch = 0x42,
+    SecondaryTipSwitch = 0x43,
+    BarrelSwitch = 0x44,
+    Eraser = 0x45,
+    TabletPick = 0x46,
+    TouchValid = 0x47,
+    Width = 0x48,
+    Height = 0x49,
+    //0x4A-0x50 Reserved
+    ContactIdentifier = 0x51,
+    DeviceMode = 0x52,
+    DeviceIdentifier = 0x53,
+    ContactCount = 0x54,
+    ContactCountMaximum = 0x55,
+    ScanTime = 0x56,
+    SurfaceSwitch = 0x57,
+    ButtonSwitch = 0x58,
+    PadType = 0x59,
+    SecondaryBarrelSwitch = 0x5A,
+    TransducerSerialNumber = 0x5B,
+    PreferredColor = 0x5C,
+    PreferredColorIsLocked = 0x5D,
+    PreferredLineWidth = 0x5E,
+    PreferredLineWidthIsLocked = 0x5F,
+    LatencyMode = 0x60,
+    GestureCharacterQuality = 0x61,
+    CharacterGestureDataLength = 0x62,
+    CharacterGestureData = 0x63,
+    GestureCharacterEncoding = 0x64,
+    Utf8CharacterGestureEncoding = 0x65,
+    Utf16LittleEndianCharacterGestureEncoding = 0x66,
+    Utf16BigEndianCharacterGestureEncoding = 0x67,
+    Utf32LittleEndianCharacterGestureEncoding = 0x68,
+    Utf32BigEndianCharacterGestureEncoding = 0x69,
+    CapacitiveHeatMapProtocolVendorId = 0x6A,
+    CapacitiveHeatMapProtocolVersion = 0x6B,
+    CapacitiveHeatMapFrameData = 0x6C,
+    GestureCharacterEnable = 0x6D,
+    TransducerSerialNumberPart2 = 0x6E,
+    NoPreferredColor = 0x6F,
+    PreferredLineStyle = 0x70,
+    PreferredLineStyleIsLocked = 0x71,
+    Ink = 0x72,
+    Pencil = 0x73,
+    Highlighter = 0x74,
+    ChiselMarker = 0x75,
+    Brush = 0x76,
+    NoPreference = 0x77,
+    //0x78-0x7F Reserved
+    //0x80-0xFF Digitizer diagnostics and transducer information, not listed
 }
 
 /// Game Controls usage page
@@ -635,10 +856,11 @@ impl Default for Desktop {
     PrimitiveEnum,
     IntoPrimitive,
     FromPrimitive,
+    Default,
 )]
 #[repr(u8)]
 pub enum Game {
-    #[num_enum(default)]
+    #[default]
     Undefined = 0x00,
     Game3DController = 0x01,
     PinballDevice = 0x02,
@@ -672,12 +894,6 @@ pub enum Game {
     //0x3A-0xFFFF Reserved
 }
 
-impl Default for Game {
-    fn default() -> Self {
-        Self::Undefined
-    }
-}
-
 /// Keyboard usage page
 ///
 /// See [Universal Serial Bus (USB) HID Usage Tables Version 1.12](<https://www.usb.org/sites/default/files/documents/hut1_12v2.pdf>):
@@ -698,10 +914,11 @@ impl Default for Game {
     PrimitiveEnum,
     IntoPrimitive,
     FromPrimitive,
+    Default,
 )]
 #[repr(u8)]
 pub enum Keyboard {
-    #[num_enum(default)]
+    #[default]
     NoEventIndicated = 0x00,
     ErrorRollOver = 0x01,
     POSTFail = 0x02,
@@ -879,10 +1096,218 @@ pub enum Keyboard {
     //0xE8-0xFFFF Reserved
 }
 
-impl Default for Keyboard {
-    fn default() -> Self {
-        Self::NoEventIndicated
-    }
+/// Sensors usage page
+///
+/// See [HID Usage Tables Version 1.4](<https://usb.org/sites/default/files/hut1_4.pdf>):
+/// Section 22 Sensors Page (0x20)
+///
+/// Includes the sensor categories and types, the common properties, events and data fields, and
+/// the sensor state, event, reporting state and power state selectors. Data field modifiers, such
+/// as Threshold Low (0x7000), are combined with a data field usage by OR-ing them into its upper
+/// four bits.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    PrimitiveEnum,
+    IntoPrimitive,
+    FromPrimitive,
+    Default,
+)]
+#[repr(u16)]
+pub enum Sensor {
+    #[default]
+    Undefined = 0x00,
+    Sensor = 0x01,
+    //0x02-0x0F Reserved
+    Biometric = 0x10,
+    BiometricHumanPresence = 0x11,
+    BiometricHumanProximity = 0x12,
+    BiometricHumanTouch = 0x13,
+    //0x14-0x1F Not listed
+    Electrical = 0x20,
+    ElectricalCapacitance = 0x21,
+    ElectricalCurrent = 0x22,
+    ElectricalPower = 0x23,
+    ElectricalInductance = 0x24,
+    ElectricalResistance = 0x25,
+    ElectricalVoltage = 0x26,
+    ElectricalPotentiometer = 0x27,
+    ElectricalFrequency = 0x28,
+    ElectricalPeriod = 0x29,
+    //0x2A-0x2F Reserved
+    Environmental = 0x30,
+    EnvironmentalAtmosphericPressure = 0x31,
+    EnvironmentalHumidity = 0x32,
+    EnvironmentalTemperature = 0x33,
+    EnvironmentalWindDirection = 0x34,
+    EnvironmentalWindSpeed = 0x35,
+    //0x36-0x3F Not listed
+    Light = 0x40,
+    LightAmbientLight = 0x41,
+    LightConsumerInfrared = 0x42,
+    //0x43-0x4F Not listed
+    Location = 0x50,
+    LocationBroadcast = 0x51,
+    LocationDeadReckoning = 0x52,
+    LocationGps = 0x53,
+    LocationLookup = 0x54,
+    LocationOther = 0x55,
+    LocationStatic = 0x56,
+    LocationTriangulation = 0x57,
+    //0x58-0x5F Reserved
+    Mechanical = 0x60,
+    MechanicalBooleanSwitch = 0x61,
+    MechanicalBooleanSwitchArray = 0x62,
+    MechanicalMultivalueSwitch = 0x63,
+    MechanicalForce = 0x64,
+    MechanicalPressure = 0x65,
+    MechanicalStrain = 0x66,
+    MechanicalWeight = 0x67,
+    MechanicalHapticVibrator = 0x68,
+    MechanicalHallEffectSwitch = 0x69,
+    //0x6A-0x6F Reserved
+    Motion = 0x70,
+    MotionAccelerometer1D = 0x71,
+    MotionAccelerometer2D = 0x72,
+    MotionAccelerometer3D = 0x73,
+    MotionGyrometer1D = 0x74,
+    MotionGyrometer2D = 0x75,
+    MotionGyrometer3D = 0x76,
+    MotionMotionDetector = 0x77,
+    MotionSpeedometer = 0x78,
+    MotionAccelerometer = 0x79,
+    MotionGyrometer = 0x7A,
+    //0x7B-0x7F Not listed
+    Orientation = 0x80,
+    OrientationCompass1D = 0x81,
+    OrientationCompass2D = 0x82,
+    OrientationCompass3D = 0x83,
+    OrientationInclinometer1D = 0x84,
+    OrientationInclinometer2D = 0x85,
+    OrientationInclinometer3D = 0x86,
+    OrientationDistance1D = 0x87,
+    OrientationDistance2D = 0x88,
+    OrientationDistance3D = 0x89,
+    OrientationDeviceOrientation = 0x8A,
+    OrientationCompass = 0x8B,
+    OrientationInclinometer = 0x8C,
+    OrientationDistance = 0x8D,
+    //0x8E-0x8F Not listed
+    Scanner = 0x90,
+    ScannerBarcode = 0x91,
+    ScannerRfid = 0x92,
+    ScannerNfc = 0x93,
+    //0x94-0x9F Reserved
+    Time = 0xA0,
+    TimeAlarmTimer = 0xA1,
+    TimeRealTimeClock = 0xA2,
+    //0xA3-0xDF Not listed
+    Other = 0xE0,
+    OtherCustom = 0xE1,
+    OtherGeneric = 0xE2,
+    //0xE3-0x01FF Not listed
+    Event = 0x0200,
+    EventSensorState = 0x0201,
+    EventSensorEvent = 0x0202,
+    //0x0203-0x02FF Not listed
+    Property = 0x0300,
+    PropertyFriendlyName = 0x0301,
+    PropertyPersistentUniqueId = 0x0302,
+    PropertySensorStatus = 0x0303,
+    PropertyMinimumReportInterval = 0x0304,
+    PropertySensorManufacturer = 0x0305,
+    PropertySensorModel = 0x0306,
+    PropertySensorSerialNumber = 0x0307,
+    PropertySensorDescription = 0x0308,
+    PropertySensorConnectionType = 0x0309,
+    PropertySensorDevicePath = 0x030A,
+    PropertyHardwareRevision = 0x030B,
+    PropertyFirmwareVersion = 0x030C,
+    PropertyReleaseDate = 0x030D,
+    PropertyReportInterval = 0x030E,
+    PropertyChangeSensitivityAbsolute = 0x030F,
+    PropertyChangeSensitivityPercentOfRange = 0x0310,
+    PropertyChangeSensitivityPercentRelative = 0x0311,
+    PropertyAccuracy = 0x0312,
+    PropertyResolution = 0x0313,
+    PropertyMaximum = 0x0314,
+    PropertyMinimum = 0x0315,
+    PropertyReportingState = 0x0316,
+    PropertySamplingRate = 0x0317,
+    PropertyResponseCurve = 0x0318,
+    PropertyPowerState = 0x0319,
+    //0x031A-0x044F Not listed
+    DataFieldMotion = 0x0450,
+    DataFieldMotionState = 0x0451,
+    DataFieldAcceleration = 0x0452,
+    DataFieldAccelerationAxisX = 0x0453,
+    DataFieldAccelerationAxisY = 0x0454,
+    DataFieldAccelerationAxisZ = 0x0455,
+    DataFieldAngularVelocity = 0x0456,
+    DataFieldAngularVelocityAboutXAxis = 0x0457,
+    DataFieldAngularVelocityAboutYAxis = 0x0458,
+    DataFieldAngularVelocityAboutZAxis = 0x0459,
+    //0x045A-0x04AF Not listed
+    DataFieldBiometric = 0x04B0,
+    DataFieldHumanPresence = 0x04B1,
+    DataFieldHumanProximityRange = 0x04B2,
+    DataFieldHumanProximityOutOfRange = 0x04B3,
+    DataFieldHumanTouchState = 0x04B4,
+    //0x04B5-0x04CF Not listed
+    DataFieldLight = 0x04D0,
+    DataFieldIlluminance = 0x04D1,
+    DataFieldColorTemperature = 0x04D2,
+    DataFieldChromaticity = 0x04D3,
+    DataFieldChromaticityX = 0x04D4,
+    DataFieldChromaticityY = 0x04D5,
+    //0x04D6-0x07FF Not listed
+    SensorStateUndefined = 0x0800,
+    SensorStateReady = 0x0801,
+    SensorStateNotAvailable = 0x0802,
+    SensorStateNoData = 0x0803,
+    SensorStateInitializing = 0x0804,
+    SensorStateAccessDenied = 0x0805,
+    SensorStateError = 0x0806,
+    //0x0807-0x080F Reserved
+    SensorEventUnknown = 0x0810,
+    SensorEventStateChanged = 0x0811,
+    SensorEventPropertyChanged = 0x0812,
+    SensorEventDataUpdated = 0x0813,
+    SensorEventPollResponse = 0x0814,
+    SensorEventChangeSensitivity = 0x0815,
+    SensorEventRangeMaximumReached = 0x0816,
+    SensorEventRangeMinimumReached = 0x0817,
+    SensorEventHighThresholdCrossUpward = 0x0818,
+    SensorEventHighThresholdCrossDownward = 0x0819,
+    SensorEventLowThresholdCrossUpward = 0x081A,
+    SensorEventLowThresholdCrossDownward = 0x081B,
+    SensorEventZeroThresholdCrossUpward = 0x081C,
+    SensorEventZeroThresholdCrossDownward = 0x081D,
+    SensorEventPeriodExceeded = 0x081E,
+    SensorEventFrequencyExceeded = 0x081F,
+    SensorEventComplexTrigger = 0x0820,
+    //0x0821-0x083F Not listed
+    ReportingStateReportNoEvents = 0x0840,
+    ReportingStateReportAllEvents = 0x0841,
+    ReportingStateReportThresholdEvents = 0x0842,
+    ReportingStateWakeOnNoEvents = 0x0843,
+    ReportingStateWakeOnAllEvents = 0x0844,
+    ReportingStateWakeOnThresholdEvents = 0x0845,
+    //0x0846-0x084F Reserved
+    PowerStateUndefined = 0x0850,
+    PowerStateD0FullPower = 0x0851,
+    PowerStateD1LowPower = 0x0852,
+    PowerStateD2StandbyWithWake = 0x0853,
+    PowerStateD3SleepWithWake = 0x0854,
+    PowerStateD4PowerOff = 0x0855,
+    //0x0856-0xFFFF Not listed
 }
 
 /// Simulation Controls usage page
@@ -902,10 +1327,11 @@ impl Default for Keyboard {
     PrimitiveEnum,
     IntoPrimitive,
     FromPrimitive,
+    Default,
 )]
 #[repr(u8)]
 pub enum Simulation {
-    #[num_enum(default)]
+    #[default]
     Undefined = 0x00,
     FlightSimulationDevice = 0x01,
     AutomobileSimulationDevice = 0x02,
@@ -964,12 +1390,6 @@ pub enum Simulation {
     //0xD1-0xFFFF Reserved
 }
 
-impl Default for Simulation {
-    fn default() -> Self {
-        Self::Undefined
-    }
-}
-
 /// Telephony Device usage page
 ///
 /// See [Universal Serial Bus (USB) HID Usage Tables Version 1.12](<https://www.usb.org/sites/default/files/documents/hut1_12v2.pdf>):
@@ -987,10 +1407,11 @@ impl Default for Simulation {
     PrimitiveEnum,
     IntoPrimitive,
     FromPrimitive,
+    Default,
 )]
 #[repr(u8)]
 pub enum Telephony {
-    #[num_enum(default)]
+    #[default]
     Unassigned = 0x00,
     Phone = 0x01,
     AnsweringMachine = 0x02,
@@ -1064,8 +1485,75 @@ pub enum Telephony {
     PhoneKeyD = 0xBF,
     //0xC0-0xFFFF Reserved
 }
-impl Default for Telephony {
-    fn default() -> Self {
-        Self::Unassigned
-    }
+
+/// Usage page IDs
+///
+/// See [HID Usage Tables Version 1.4](<https://usb.org/sites/default/files/hut1_4.pdf>):
+/// Section 3 Usage Pages
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    PrimitiveEnum,
+    IntoPrimitive,
+    FromPrimitive,
+    Default,
+)]
+#[repr(u16)]
+pub enum UsagePage {
+    #[default]
+    Undefined = 0x00,
+    GenericDesktop = 0x01,
+    SimulationControls = 0x02,
+    VrControls = 0x03,
+    SportControls = 0x04,
+    GameControls = 0x05,
+    GenericDeviceControls = 0x06,
+    Keyboard = 0x07,
+    Led = 0x08,
+    Button = 0x09,
+    Ordinal = 0x0A,
+    TelephonyDevice = 0x0B,
+    Consumer = 0x0C,
+    Digitizers = 0x0D,
+    Haptics = 0x0E,
+    PhysicalInputDevice = 0x0F,
+    Unicode = 0x10,
+    Soc = 0x11,
+    EyeAndHeadTrackers = 0x12,
+    //0x13 Reserved
+    AuxiliaryDisplay = 0x14,
+    //0x15-0x1F Reserved
+    Sensors = 0x20,
+    //0x21-0x3F Reserved
+    MedicalInstrument = 0x40,
+    BrailleDisplay = 0x41,
+    //0x42-0x58 Reserved
+    LightingAndIllumination = 0x59,
+    //0x5A-0x7F Reserved
+    Monitor = 0x80,
+    MonitorEnumerated = 0x81,
+    VesaVirtualControls = 0x82,
+    //0x83 Reserved
+    PowerDevice = 0x84,
+    BatterySystem = 0x85,
+    //0x86-0x8B Reserved
+    BarcodeScanner = 0x8C,
+    Scales = 0x8D,
+    MagneticStripeReader = 0x8E,
+    //0x8F Reserved
+    CameraControl = 0x90,
+    Arcade = 0x91,
+    GamingDevice = 0x92,
+    //0x93-0xF1CF Reserved
+    Fido = 0xF1D0,
+    //0xF1D1-0xFEFF Reserved
+    VendorDefined = 0xFF00,
+    //0xFF01-0xFFFF Vendor defined
 }
@@ -8,5 +8,6 @@
 //! use usbd_human_interface_device::prelude::*;
 //! ```
 
-pub use crate::usb_class::{UsbHidClass, UsbHidClassBuilder};
+pub use crate::device::OutputReports;
+pub use crate::usb_class::{Clock, UsbHidClass, UsbHidClassBuilder};
 pub use crate::UsbHidError;
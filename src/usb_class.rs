@@ -1,18 +1,25 @@
 //! USB Class for implementing Human Interface Devices
 
-use crate::descriptor::{DescriptorType, HidProtocol, HidRequest};
+use crate::descriptor::ms_os::{self, DescriptorSetWriter, MS_OS_20_DESCRIPTOR_INDEX};
+use crate::descriptor::parse::offset_string_indices;
+use crate::descriptor::{DescriptorType, HidProtocol, HidRequest, ReportType, USB_CLASS_HID};
 use crate::device::{DeviceClass, DeviceHList};
-use crate::interface::{InterfaceClass, ReportDescriptor, UsbAllocatable};
+use crate::interface::{
+    InterfaceClass, InterfaceInfo, InterfaceState, ReportDescriptor, UsbAllocatable,
+};
 use crate::UsbHidError;
 use core::cell::RefCell;
 use core::default::Default;
 use core::marker::PhantomData;
 use frunk::hlist::{HList, Selector};
 use frunk::{HCons, HNil, ToMut};
+use fugit::{ExtU32, MillisDurationU32};
+use heapless::Vec;
 #[allow(clippy::wildcard_imports)]
 use usb_device::class_prelude::*;
 use usb_device::control::{Recipient, Request};
 use usb_device::descriptor::lang_id::LangID;
+use usb_device::device::{UsbDevice, UsbDeviceState, CONFIGURATION_NONE, CONFIGURATION_VALUE};
 use usb_device::{control::RequestType, Result};
 
 pub mod prelude {
@@ -26,15 +33,22 @@ pub mod prelude {
     //! use usbd_human_interface_device::usb_class::prelude::*;
     //! ```
 
-    pub use crate::descriptor::{HidProtocol, InterfaceProtocol};
-    pub use crate::device::DeviceClass;
+    pub use crate::descriptor::ms_os::MsOs20Function;
+    pub use crate::descriptor::{CountryCode, HidProtocol, InterfaceProtocol};
+    pub use crate::device::{DeviceClass, OutputReport, OutputReports, ReceivedReport};
+    pub use crate::interface::{BootInputReport, ManagedIdleInterface, ManagedIdleInterfaceConfig};
+    pub use crate::interface::{FeatureReports, FeatureReportsConfig};
     pub use crate::interface::{
-        InBytes16, InBytes32, InBytes64, InBytes8, InNone, Interface, InterfaceBuilder,
-        InterfaceConfig, OutBytes16, OutBytes32, OutBytes64, OutBytes8, OutNone, ReportSingle,
-        Reports128, Reports16, Reports32, Reports64, Reports8, UsbAllocatable,
+        InBytes1024, InBytes128, InBytes16, InBytes256, InBytes32, InBytes512, InBytes64, InBytes8,
+        InNone, Interface, InterfaceBuilder, InterfaceConfig, OutBytes1024, OutBytes128,
+        OutBytes16, OutBytes256, OutBytes32, OutBytes512, OutBytes64, OutBytes8, OutNone,
+        ReportSingle, Reports128, Reports16, Reports32, Reports64, Reports8, UsbAllocatable,
+    };
+    pub use crate::interface::{PackedSink, ReportRouter, ReportSink};
+    pub use crate::usb_class::{
+        BuilderResult, InterfaceAssociation, InterfaceSet, PollEvents, UsbHidBuilderError,
+        UsbHidClass, UsbHidClassBuilder,
     };
-    pub use crate::interface::{ManagedIdleInterface, ManagedIdleInterfaceConfig};
-    pub use crate::usb_class::{UsbHidClass, UsbHidClassBuilder};
     pub use crate::UsbHidError;
 }
 
@@ -46,6 +60,113 @@ pub enum UsbHidBuilderError {
     ValueOverflow,
     /// A slice of data is longer than permitted
     SliceLengthOverflow,
+    /// More than [`MAX_ALTERNATE_REPORT_DESCRIPTORS`](crate::interface::MAX_ALTERNATE_REPORT_DESCRIPTORS)
+    /// alternate report descriptors
+    TooManyReportDescriptors,
+    /// An endpoint max packet size other than 8, 16, 32 or 64 bytes, or up to 1024 bytes at high
+    /// speed, or larger than its report buffer
+    InvalidPacketSize,
+    /// A report is longer than its endpoint's report buffer
+    ReportLengthOverflow,
+    /// The report descriptor isn't well formed, so its report lengths are unknown
+    InvalidReportDescriptor(crate::descriptor::parse::ParseError),
+    /// More feature reports than a [`FeatureReportsConfig`](crate::interface::FeatureReportsConfig)
+    /// holds
+    TooManyFeatureReports,
+    /// A report ID is registered more than once
+    DuplicateReportId,
+    /// More report handlers than a [`ReportRouter`](crate::interface::ReportRouter) holds
+    TooManyReportHandlers,
+    /// Physical descriptor sets of differing lengths
+    InvalidPhysicalDescriptor,
+    /// The bus couldn't allocate an endpoint, such as when it has run out of endpoints or doesn't
+    /// support its max packet size
+    EndpointAllocation(UsbError),
+    /// Localized strings for more than
+    /// [`MAX_LOCALIZED_STRINGS`](crate::interface::MAX_LOCALIZED_STRINGS) languages
+    TooManyLocalizedStrings,
+    /// An interface order with a position repeated or not less than the number of interfaces
+    InvalidInterfaceOrder,
+}
+
+/// Interface Association Descriptors written by a [`UsbHidClass`], see
+/// [`UsbHidClassBuilder::interface_association`]
+///
+/// IADs are only written if the [`UsbDevice`] is built with
+/// [`composite_with_iads`](usb_device::device::UsbDeviceBuilder::composite_with_iads), as it must
+/// be to mix HID with classes whose functions have several interfaces, such as CDC or audio.
+/// Hosts, including the Windows composite driver, enumerate interfaces outside an IAD as
+/// functions of their own, and each HID interface is a function of its own, so HID interfaces
+/// don't require IADs.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum InterfaceAssociation {
+    /// No IADs, each interface is a function of its own
+    #[default]
+    None,
+    /// An IAD before each interface, as a function of its own, for hosts expecting every function
+    /// of a device with IADs to be associated
+    PerInterface,
+    /// An IAD grouping all of the class's interfaces into a single HID function, for a driver
+    /// binding to the group. Only written if the class has more than one interface, as a single
+    /// interface needs no IAD.
+    ///
+    /// The Windows HID driver binds to a single interface, so don't group interfaces for it.
+    Grouped,
+}
+
+/// Set of interface numbers, see [`PollEvents`]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InterfaceSet([u32; 8]);
+
+impl InterfaceSet {
+    #[must_use]
+    pub fn contains(&self, interface_number: u8) -> bool {
+        self.0[usize::from(interface_number / 32)] & (1 << (interface_number % 32)) != 0
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&bits| bits == 0)
+    }
+
+    /// Interface numbers in the set, in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=u8::MAX).filter(|&interface_number| self.contains(interface_number))
+    }
+
+    fn insert(&mut self, interface_number: u8) {
+        self.0[usize::from(interface_number / 32)] |= 1 << (interface_number % 32);
+    }
+}
+
+/// What happened on the bus since the last [`UsbHidClass::take_poll_events`], such as to only
+/// read the output reports of the interfaces that received them after a USB interrupt
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PollEvents {
+    /// Interfaces that received data on their interrupt OUT endpoint or an output report with
+    /// `SET_REPORT`
+    pub out_received: InterfaceSet,
+    /// Interfaces whose interrupt IN endpoint completed a transfer
+    pub in_complete: InterfaceSet,
+    /// Interfaces whose protocol the host changed with `SET_PROTOCOL`
+    pub protocol_changed: InterfaceSet,
+    /// Interfaces an idle rate of which the host changed with `SET_IDLE`
+    pub idle_changed: InterfaceSet,
+    /// Interfaces whose alternate setting the host selected with `SET_INTERFACE`
+    pub alternate_setting_selected: InterfaceSet,
+    /// The host reset the bus, resetting all interfaces
+    pub bus_reset: bool,
+}
+
+impl PollEvents {
+    /// Whether nothing happened
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 /// Builder for [`UsbHidClass`]
@@ -54,6 +175,9 @@ pub enum UsbHidBuilderError {
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct UsbHidClassBuilder<'a, B, Devices> {
     devices: Devices,
+    interface_association: InterfaceAssociation,
+    ms_os_20_vendor_code: Option<u8>,
+    interface_order: &'a [u8],
     marker: PhantomData<&'a B>,
 }
 
@@ -61,6 +185,32 @@ impl<B> UsbHidClassBuilder<'_, B, HNil> {
     pub fn new() -> Self {
         Self {
             devices: HNil,
+            interface_association: InterfaceAssociation::None,
+            ms_os_20_vendor_code: None,
+            interface_order: &[],
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, B: UsbBus> UsbHidClassBuilder<'a, B, HNil> {
+    /// Use a tuple of one to four device configs, such as `(keyboard, mouse)`, or an array or
+    /// [`heapless::Vec`] of configs of the same type, such as several identical vendor interfaces,
+    /// allocated with interface numbers in order
+    ///
+    /// The class is then typed by a plain tuple or collection of devices, borrowed with
+    /// [`UsbHidClass::devices_mut`], rather than the frunk [`HList`] built by
+    /// [`UsbHidClassBuilder::add_device`].
+    pub fn devices<Devices>(self, devices: Devices) -> UsbHidClassBuilder<'a, B, Devices>
+    where
+        Devices: UsbAllocatable<'a, B>,
+        Devices::Allocated: DeviceHList<'a>,
+    {
+        UsbHidClassBuilder {
+            devices,
+            interface_association: self.interface_association,
+            ms_os_20_vendor_code: self.ms_os_20_vendor_code,
+            interface_order: self.interface_order,
             marker: PhantomData,
         }
     }
@@ -83,36 +233,161 @@ impl<'a, B: UsbBus, Tail: HList> UsbHidClassBuilder<'a, B, Tail> {
     {
         UsbHidClassBuilder {
             devices: self.devices.prepend(config),
+            interface_association: self.interface_association,
+            ms_os_20_vendor_code: self.ms_os_20_vendor_code,
+            interface_order: self.interface_order,
             marker: PhantomData,
         }
     }
 }
 
-impl<'a, B, Config, Tail> UsbHidClassBuilder<'a, B, HCons<Config, Tail>>
+impl<'a, B, Devices> UsbHidClassBuilder<'a, B, Devices> {
+    /// Write Interface Association Descriptors for the class's interfaces, defaults to
+    /// [`InterfaceAssociation::None`]
+    pub fn interface_association(mut self, interface_association: InterfaceAssociation) -> Self {
+        self.interface_association = interface_association;
+        self
+    }
+
+    /// Serve Microsoft OS 2.0 descriptors for the interfaces configured with
+    /// [`InterfaceConfig::with_ms_os_20`](crate::interface::InterfaceConfig::with_ms_os_20),
+    /// announced in the BOS descriptor and read by Windows with the vendor request `vendor_code`
+    ///
+    /// Windows only reads the BOS descriptor of devices built with
+    /// `UsbDeviceBuilder::usb_rev(UsbRev::Usb210)`. The descriptor set is written to the control
+    /// transfer buffer, device interface GUIDs need the 256 byte buffer of the `usb-device`
+    /// `control-buffer-256` feature.
+    pub fn ms_os_20_descriptors(mut self, vendor_code: u8) -> Self {
+        self.ms_os_20_vendor_code = Some(vendor_code);
+        self
+    }
+
+    /// Number the interfaces listed in `order`, by their position in allocation order, from the
+    /// class's first interface number, followed by the unlisted interfaces in allocation order
+    ///
+    /// Some BIOSes only look for a boot keyboard at interface 0, `&[1]` numbers the second device
+    /// allocated first. Devices added with [`UsbHidClassBuilder::add_device`] are allocated in
+    /// reverse order of addition, and tuples and collections in order. Descriptors are written in
+    /// interface number order, while endpoints keep their allocation order.
+    pub fn interface_order(mut self, order: &'a [u8]) -> Self {
+        self.interface_order = order;
+        self
+    }
+}
+
+impl<'a, B, Devices> UsbHidClassBuilder<'a, B, Devices>
 where
     B: UsbBus,
-    Tail: UsbAllocatable<'a, B>,
-    Config: UsbAllocatable<'a, B>,
+    Devices: UsbAllocatable<'a, B>,
+    Devices::Allocated: DeviceHList<'a>,
 {
+    /// # Panics
+    ///
+    /// Panics if the bus can't allocate an endpoint or the interface order set by
+    /// [`UsbHidClassBuilder::interface_order`] is invalid, see [`UsbHidClassBuilder::try_build`]
     pub fn build(
         self,
         usb_alloc: &'a UsbBusAllocator<B>,
-    ) -> UsbHidClass<'a, B, HCons<Config::Allocated, Tail::Allocated>> {
-        UsbHidClass {
-            devices: RefCell::new(self.devices.allocate(usb_alloc)),
+    ) -> UsbHidClass<'a, B, Devices::Allocated> {
+        unwrap!(self.try_build(usb_alloc))
+    }
+
+    /// Allocate the devices, failing with [`UsbHidBuilderError::EndpointAllocation`] if the bus
+    /// can't allocate an endpoint, such as when it has run out of endpoints, or
+    /// [`UsbHidBuilderError::InvalidInterfaceOrder`] if the interface order is invalid
+    pub fn try_build(
+        self,
+        usb_alloc: &'a UsbBusAllocator<B>,
+    ) -> BuilderResult<UsbHidClass<'a, B, Devices::Allocated>> {
+        let mut devices = self.devices.try_allocate(usb_alloc)?;
+        Self::order_interfaces(&mut devices, self.interface_order)?;
+        Ok(UsbHidClass {
+            devices: RefCell::new(devices),
+            interface_association: self.interface_association,
+            ms_os_20_vendor_code: self.ms_os_20_vendor_code,
+            suspended: false,
+            remote_wakeup_pending: false,
+            bus_reset: false,
+            poll_events: PollEvents::default(),
+            clock_micros: None,
+            tick_remainder_micros: 0,
             _marker: PhantomData,
+        })
+    }
+
+    /// Swap the interface numbers allocated to the devices into `order`, see
+    /// [`UsbHidClassBuilder::interface_order`]
+    fn order_interfaces(devices: &mut Devices::Allocated, order: &[u8]) -> BuilderResult<()> {
+        if order.is_empty() {
+            return Ok(());
+        }
+
+        // Interface numbers are a u8, so a class can't have more than 256 interfaces
+        let mut ids = Vec::<InterfaceNumber, 256>::new();
+        devices.for_each_interface(&mut |interface| {
+            ids.push(interface.id()).ok();
+        });
+        for (i, &position) in order.iter().enumerate() {
+            if usize::from(position) >= ids.len() || order[..i].contains(&position) {
+                return Err(UsbHidBuilderError::InvalidInterfaceOrder);
+            }
         }
+
+        let listed = |allocated: usize| order.iter().any(|&p| usize::from(p) == allocated);
+        let mut allocated = 0;
+        devices.for_each_interface(&mut |interface| {
+            let position = order
+                .iter()
+                .position(|&p| usize::from(p) == allocated)
+                .unwrap_or_else(|| order.len() + (0..allocated).filter(|&p| !listed(p)).count());
+            interface.set_id(ids[position]);
+            allocated += 1;
+        });
+        Ok(())
     }
 }
 
 pub type BuilderResult<B> = core::result::Result<B, UsbHidBuilderError>;
 
+/// Most ticks run by one call of [`UsbHidClass::tick_elapsed`] or [`UsbHidClass::tick_clock`],
+/// the longest idle period a host can set
+pub const MAX_CATCH_UP_TICKS: u32 = 1020;
+
+/// Monotonic timer counting at `TIMER_HZ`, such as an RTIC or embassy monotonic, driving
+/// [`UsbHidClass::tick_clock`]
+pub trait Clock<const TIMER_HZ: u32> {
+    fn now(&self) -> fugit::TimerInstantU64<TIMER_HZ>;
+}
+
+impl<F, const TIMER_HZ: u32> Clock<TIMER_HZ> for F
+where
+    F: Fn() -> fugit::TimerInstantU64<TIMER_HZ>,
+{
+    fn now(&self) -> fugit::TimerInstantU64<TIMER_HZ> {
+        self()
+    }
+}
+
 /// USB Human Interface Device class
 pub struct UsbHidClass<'a, B, Devices> {
     // Using a RefCell makes it simpler to implement devices as all calls to interfaces are mut
     // this could be removed, but then each usb device would need to implement a non mut borrow
     // of its `RawInterface`.
     devices: RefCell<Devices>,
+    interface_association: InterfaceAssociation,
+    /// Vendor request reading the MS OS 2.0 descriptor set
+    ms_os_20_vendor_code: Option<u8>,
+    suspended: bool,
+    /// Resume signalled with [`UsbHidClass::remote_wakeup`] and the host yet to resume the bus
+    remote_wakeup_pending: bool,
+    /// The host has reset the bus since the last [`UsbHidClass::take_bus_reset`]
+    bus_reset: bool,
+    /// Events since the last [`UsbHidClass::take_poll_events`]
+    poll_events: PollEvents,
+    /// Time of the last [`UsbHidClass::tick_clock`] call
+    clock_micros: Option<u64>,
+    /// Time elapsed since the last device tick
+    tick_remainder_micros: u64,
     _marker: PhantomData<&'a B>,
 }
 
@@ -126,24 +401,326 @@ impl<'a, B, Devices: DeviceHList<'a>> UsbHidClass<'a, B, Devices> {
     }
 
     /// Borrow an [`HList`] of all devices
-    pub fn devices(&'a mut self) -> <Devices as ToMut<'a>>::Output {
+    pub fn devices(&'a mut self) -> <Devices as ToMut<'a>>::Output
+    where
+        Devices: ToMut<'a>,
+    {
         self.devices.get_mut().to_mut()
     }
 
-    /// Provide a clock tick to allow the tracking of time. Call this every 1ms / at 1KHz
+    /// Borrow all devices, such as a tuple built by [`UsbHidClassBuilder::devices`] with
+    /// `let (keyboard, mouse) = hid.devices_mut();` or an array indexed by `hid.devices_mut()[i]`
+    pub fn devices_mut(&mut self) -> &mut Devices {
+        self.devices.get_mut()
+    }
+
+    /// Provide a clock tick to allow the tracking of time. Call this every 1ms / at 1KHz, or use
+    /// [`UsbHidClass::tick_elapsed`] or [`UsbHidClass::tick_clock`] with timers of other
+    /// resolutions
     pub fn tick(&mut self) -> core::result::Result<(), UsbHidError> {
         self.devices.get_mut().tick()
     }
+
+    /// Advance time by `elapsed`, such as the period of a timer of any resolution, ticking the
+    /// devices once for each whole millisecond elapsed. Time short of a millisecond is carried
+    /// over to the next call.
+    ///
+    /// All the ticks are run, returning the first error. At most [`MAX_CATCH_UP_TICKS`] ticks are
+    /// run per call, so a long gap, such as after the device sleeps, doesn't stall the caller.
+    pub fn tick_elapsed<const NOM: u32, const DENOM: u32>(
+        &mut self,
+        elapsed: fugit::Duration<u32, NOM, DENOM>,
+    ) -> core::result::Result<(), UsbHidError> {
+        // Converted in u64 as u32 microseconds overflow after about 71 minutes
+        let micros = u64::from(elapsed.ticks())
+            .saturating_mul(u64::from(NOM))
+            .saturating_mul(1_000_000)
+            / u64::from(DENOM);
+        self.tick_micros(micros)
+    }
+
+    /// Advance time to the current time of `clock`, such as from a task scheduled by an RTIC or
+    /// embassy monotonic rather than a dedicated 1kHz interrupt, ticking the devices once for
+    /// each whole millisecond elapsed since the last call. The first call only starts the clock.
+    ///
+    /// Reports due while the clock isn't ticked are sent late, so call it at least as often as
+    /// reports need to be sent, such as every few milliseconds. Like
+    /// [`UsbHidClass::tick_elapsed`], at most [`MAX_CATCH_UP_TICKS`] ticks are run per call.
+    pub fn tick_clock<const TIMER_HZ: u32>(
+        &mut self,
+        clock: &impl Clock<TIMER_HZ>,
+    ) -> core::result::Result<(), UsbHidError> {
+        let now = clock.now().duration_since_epoch().to_micros();
+        let elapsed = self
+            .clock_micros
+            .replace(now)
+            .map_or(0, |last| now.saturating_sub(last));
+        self.tick_micros(elapsed)
+    }
+
+    fn tick_micros(&mut self, elapsed: u64) -> core::result::Result<(), UsbHidError> {
+        let elapsed = self.tick_remainder_micros + elapsed;
+        self.tick_remainder_micros = elapsed % 1000;
+        let mut result = Ok(());
+        for _ in 0..(elapsed / 1000).min(u64::from(MAX_CATCH_UP_TICKS)) {
+            let tick = self.devices.get_mut().tick();
+            result = result.and(tick);
+        }
+        result
+    }
+
+    /// Call `f` with the interface number and endpoints allocated to each interface, such as to
+    /// log them when debugging a HAL
+    pub fn for_each_interface_info(&mut self, mut f: impl FnMut(InterfaceInfo)) {
+        self.devices.get_mut().for_each_info(&mut f);
+    }
+
+    /// State the host has selected for the interface `interface_number`, such as its protocol and
+    /// idle rate, or `None` if the class doesn't have that interface
+    pub fn interface_state(&mut self, interface_number: u8) -> Option<InterfaceState> {
+        self.devices
+            .get_mut()
+            .get(interface_number)
+            .map(|interface| interface.state())
+    }
+
+    /// Idle rate the host has set for `report_id` of the interface `interface_number`, zero for
+    /// indefinite, or `None` if the class doesn't have that interface
+    pub fn report_idle(
+        &mut self,
+        interface_number: u8,
+        report_id: u8,
+    ) -> Option<MillisDurationU32> {
+        self.devices
+            .get_mut()
+            .get(interface_number)
+            .map(|interface| (u32::from(interface.get_idle(report_id)) * 4).millis())
+    }
+
+    /// Whether the host has reset the bus since the last call, such as to clear pending key
+    /// state, re-send LED state or restart timers once the host has enumerated the device again
+    ///
+    /// The devices are reset with [`DeviceClass::reset`] as the bus is reset, this only notifies
+    /// the application.
+    pub fn take_bus_reset(&mut self) -> bool {
+        core::mem::take(&mut self.bus_reset)
+    }
+
+    /// What happened on the bus since the last call, such as which interfaces received output
+    /// reports, call after [`UsbDevice::poll`] returns `true` rather than checking every
+    /// interface for changes
+    ///
+    /// A bus reset is also notified to [`UsbHidClass::take_bus_reset`].
+    pub fn take_poll_events(&mut self) -> PollEvents {
+        core::mem::take(&mut self.poll_events)
+    }
+
+    /// Interface number of the interface with the interrupt endpoint `addr`
+    fn endpoint_interface(&mut self, addr: EndpointAddress) -> Option<u8> {
+        let mut interface_number = None;
+        self.devices.get_mut().for_each_info(&mut |info| {
+            if [info.in_endpoint, info.out_endpoint]
+                .iter()
+                .flatten()
+                .any(|endpoint| endpoint.address == addr)
+            {
+                interface_number = Some(info.interface_number);
+            }
+        });
+        interface_number
+    }
 }
 
-impl<'a, B: UsbBus + 'a, Devices> UsbHidClass<'a, B, Devices> {
+impl<'a, B: UsbBus + 'a, Devices: DeviceHList<'a>> UsbHidClass<'a, B, Devices> {
+    /// Follow the suspend state of `usb_dev`, call after each [`UsbDevice::poll`]
+    ///
+    /// `usb-device` doesn't notify classes when the host suspends or resumes the bus. Devices are
+    /// notified with [`DeviceClass::suspend`] and [`DeviceClass::resume`] when the state changes.
+    pub fn update_suspend(&mut self, usb_dev: &UsbDevice<'a, B>) {
+        let suspended = usb_dev.state() == UsbDeviceState::Suspend;
+        if suspended == self.suspended {
+            return;
+        }
+        self.suspended = suspended;
+        if suspended {
+            info!("Suspended");
+            self.devices.get_mut().suspend();
+        } else {
+            info!("Resumed");
+            self.remote_wakeup_pending = false;
+            self.devices.get_mut().resume();
+        }
+    }
+
+    /// Whether the host has suspended the bus, as of the last [`UsbHidClass::update_suspend`]
+    #[must_use]
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// Wake the host from suspend, such as on a key press
+    ///
+    /// The host must have armed remote wakeup, which it only does for devices built with
+    /// `UsbDeviceBuilder::supports_remote_wakeup(true)`, see
+    /// [`UsbDevice::remote_wakeup_enabled`] and [`UsbDevice::state`]. `signal_resume` drives
+    /// resume signalling on the bus, which `usb-device` leaves to the HAL, such as
+    /// `rp2040_hal::usb::UsbBus::remote_wakeup`. It's called once per suspend, further calls
+    /// before the host resumes the bus return `Ok` without signalling again.
+    ///
+    /// Returns [`UsbError::InvalidState`] if the bus isn't suspended or the host hasn't armed
+    /// remote wakeup.
+    pub fn remote_wakeup(
+        &mut self,
+        usb_dev: &UsbDevice<'a, B>,
+        signal_resume: impl FnOnce(),
+    ) -> core::result::Result<(), UsbHidError> {
+        if usb_dev.state() != UsbDeviceState::Suspend || !usb_dev.remote_wakeup_enabled() {
+            return Err(UsbHidError::UsbError(UsbError::InvalidState));
+        }
+        if !self.remote_wakeup_pending {
+            info!("Signalling remote wakeup");
+            signal_resume();
+            self.remote_wakeup_pending = true;
+        }
+        Ok(())
+    }
+
+    /// Whether resume has been signalled with [`UsbHidClass::remote_wakeup`] and the host is yet
+    /// to resume the bus
+    #[must_use]
+    pub fn remote_wakeup_pending(&self) -> bool {
+        self.remote_wakeup_pending
+    }
+
+    /// Length of the MS OS 2.0 descriptor set and whether the class has several interfaces, each
+    /// described by a function subset, or `None` if no interface has MS OS 2.0 descriptors
+    fn ms_os_20_descriptor_set_len(devices: &mut Devices) -> Option<(u16, bool)> {
+        let mut interfaces = 0;
+        let mut functions = 0;
+        devices.for_each_interface(&mut |interface| {
+            interfaces += 1;
+            functions += usize::from(interface.ms_os_20().is_some());
+        });
+        if functions == 0 {
+            return None;
+        }
+
+        let composite = interfaces > 1;
+        let mut functions_len = 0;
+        devices.for_each_interface(&mut |interface| {
+            functions_len += interface
+                .ms_os_20()
+                .map_or(0, |function| function.len(composite));
+        });
+        let set_len = u16::try_from(ms_os::descriptor_set_len(functions_len, composite)).ok()?;
+        Some((set_len, composite))
+    }
+
+    fn get_ms_os_20_descriptor_set(transfer: ControlIn<B>, devices: &mut Devices) {
+        let Some((set_len, composite)) = Self::ms_os_20_descriptor_set_len(devices) else {
+            warn!("No interfaces with MS OS 2.0 descriptors");
+            transfer.reject().ok();
+            return;
+        };
+        let result = transfer.accept(|buffer| {
+            let mut writer = DescriptorSetWriter::new(buffer, set_len, composite)?;
+            let mut result = Ok(());
+            devices.for_each_interface(&mut |interface| {
+                if let Some(function) = interface.ms_os_20() {
+                    result =
+                        result.and_then(|()| writer.function(interface.id().into(), &function));
+                }
+            });
+            result.map(|()| writer.position())
+        });
+        if let Err(e) = result {
+            error!("Failed to send MS OS 2.0 descriptor set - {:?}", e);
+        } else {
+            info!("Sent MS OS 2.0 descriptor set");
+        }
+    }
+
+    fn get_feature_report(transfer: ControlIn<B>, devices: &mut Devices, interface_id: u8) {
+        let report_id = (transfer.request().value & 0xFF) as u8;
+        let mut next_report = false;
+        let result =
+            if let Some(report) = devices.get_static_feature_report(interface_id, report_id) {
+                transfer.accept_with_static(report)
+            } else {
+                // Feature reports the device doesn't handle are answered as by `get_report`
+                transfer.accept(|buffer| {
+                    match devices.get_feature_report(interface_id, report_id, buffer) {
+                        Err(UsbError::Unsupported) => {
+                            next_report = true;
+                            devices
+                                .get(interface_id)
+                                .ok_or(UsbError::InvalidEndpoint)?
+                                .get_report(buffer)
+                        }
+                        result => result,
+                    }
+                })
+            };
+        if let Err(e) = result {
+            warn!("Failed to send feature report ID{} - {:?}", report_id, e);
+        } else {
+            trace!("Sent feature report ID{}", report_id);
+            if let Some(interface) = devices.get(interface_id).filter(|_| next_report) {
+                unwrap!(interface.get_report_ack());
+            }
+        }
+    }
+
+    fn get_report(transfer: ControlIn<B>, devices: &mut Devices, interface_id: u8) {
+        let report_id = (transfer.request().value & 0xFF) as u8;
+        let requested_n = transfer.request().length.into();
+        // Devices answer with their current report, otherwise the next report written is sent
+        let mut next_report = false;
+        if let Err(e) = transfer.accept(|buffer| {
+            match devices.get_input_report(interface_id, report_id, buffer) {
+                Err(UsbError::WouldBlock) => {
+                    next_report = true;
+                    devices
+                        .get(interface_id)
+                        .ok_or(UsbError::InvalidEndpoint)?
+                        .get_report(buffer)
+                }
+                result => result,
+            }
+            .inspect(|&n| {
+                if n != requested_n {
+                    warn!("GetReport requested {} bytes, got {} bytes", requested_n, n);
+                }
+            })
+        }) {
+            error!("Failed to send report ID{} - {:?}", report_id, e);
+        } else {
+            trace!("Sent report ID{}", report_id);
+            if let Some(interface) = devices.get(interface_id).filter(|_| next_report) {
+                unwrap!(interface.get_report_ack());
+            }
+        }
+    }
+
     fn get_descriptor(transfer: ControlIn<B>, interface: &mut dyn InterfaceClass<'a>) {
         let request: &Request = transfer.request();
         match DescriptorType::try_from((request.value >> 8) as u8) {
             Ok(DescriptorType::Report) => {
-                let result = match interface.report_descriptor() {
-                    ReportDescriptor::DynamicDescriptor(desc) => transfer.accept_with(desc),
-                    ReportDescriptor::StaticDescriptor(desc) => transfer.accept_with_static(desc),
+                let result = match (
+                    interface.report_descriptor(),
+                    interface.report_string_index(),
+                ) {
+                    // String indices are rewritten, so the descriptor must fit the control buffer
+                    (
+                        ReportDescriptor::DynamicDescriptor(desc)
+                        | ReportDescriptor::StaticDescriptor(desc),
+                        Some(index),
+                    ) => transfer
+                        .accept(|buffer| offset_string_indices(desc, u8::from(index), buffer)),
+                    (ReportDescriptor::DynamicDescriptor(desc), None) => transfer.accept_with(desc),
+                    (ReportDescriptor::StaticDescriptor(desc), None) => {
+                        transfer.accept_with_static(desc)
+                    }
                 };
 
                 match result {
@@ -155,14 +732,16 @@ impl<'a, B: UsbBus + 'a, Devices> UsbHidClass<'a, B, Devices> {
             }
             Ok(DescriptorType::Hid) => {
                 let transfer_result = transfer.accept(|buffer| {
-                    if buffer.len() < 9 {
+                    let body = interface.hid_descriptor_body();
+                    let len = body.len() + 2;
+                    if buffer.len() < len {
                         return Err(UsbError::BufferOverflow);
                     }
 
-                    buffer[0] = 9;
+                    buffer[0] = u8::try_from(len).map_err(|_| UsbError::BufferOverflow)?;
                     buffer[1] = u8::from(DescriptorType::Hid);
-                    (buffer[2..9]).copy_from_slice(&interface.hid_descriptor_body());
-                    Ok(9)
+                    (buffer[2..len]).copy_from_slice(&body);
+                    Ok(len)
                 });
                 match transfer_result {
                     Err(e) => {
@@ -173,6 +752,35 @@ impl<'a, B: UsbBus + 'a, Devices> UsbHidClass<'a, B, Devices> {
                     }
                 }
             }
+            Ok(DescriptorType::Physical) if !interface.physical_descriptor_sets().is_empty() => {
+                let sets = interface.physical_descriptor_sets();
+                let transfer_result = match usize::from((request.value & 0xFF) as u8) {
+                    // Set 0 gives the number of sets and their length
+                    0 => transfer.accept(|buffer| {
+                        if buffer.len() < 3 {
+                            return Err(UsbError::BufferOverflow);
+                        }
+                        buffer[0] =
+                            u8::try_from(sets.len()).map_err(|_| UsbError::BufferOverflow)?;
+                        let len =
+                            u16::try_from(sets[0].len()).map_err(|_| UsbError::BufferOverflow)?;
+                        buffer[1..3].copy_from_slice(&len.to_le_bytes());
+                        Ok(3)
+                    }),
+                    index => match sets.get(index - 1) {
+                        Some(set) => transfer.accept_with(set),
+                        None => transfer.reject(),
+                    },
+                };
+                match transfer_result {
+                    Err(e) => {
+                        error!("Failed to send physical descriptor - {:?}", e);
+                    }
+                    Ok(()) => {
+                        trace!("Sent physical descriptor");
+                    }
+                }
+            }
             _ => {
                 warn!(
                     "Unsupported descriptor type, request type:{:?}, request:{}, value:{}",
@@ -183,29 +791,140 @@ impl<'a, B: UsbBus + 'a, Devices> UsbHidClass<'a, B, Devices> {
     }
 }
 
+impl<'a, B: UsbBus + 'a, Devices: DeviceHList<'a>> UsbHidClass<'a, B, Devices> {
+    /// Follow the standard requests accepted by `usb-device` that change the interfaces' state
+    fn standard_control_out(&mut self, request: &Request) {
+        // `usb-device` supports a single configuration and accepts `SET_CONFIGURATION` itself, the
+        // interfaces are reset to their initial state when the host selects or deselects it
+        if request.recipient == Recipient::Device
+            && request.request == Request::SET_CONFIGURATION
+            && (request.value == u16::from(CONFIGURATION_VALUE)
+                || request.value == u16::from(CONFIGURATION_NONE))
+        {
+            info!("Set configuration {}", request.value);
+            self.devices.get_mut().reset();
+        }
+
+        // Also accepted by `usb-device`, which clears the halt on the bus
+        if request.recipient == Recipient::Endpoint
+            && request.request == Request::CLEAR_FEATURE
+            && request.value == Request::FEATURE_ENDPOINT_HALT
+        {
+            #[allow(clippy::cast_possible_truncation)]
+            let addr = EndpointAddress::from((request.index as u8) & 0x8F);
+            self.devices.get_mut().endpoint_halt_cleared(addr);
+        }
+    }
+}
+
 impl<'a, B, Devices> UsbClass<B> for UsbHidClass<'a, B, Devices>
 where
     B: UsbBus + 'a,
     Devices: DeviceHList<'a>,
 {
     fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> Result<()> {
-        self.devices.borrow_mut().write_descriptors(writer)?;
+        let mut devices = self.devices.borrow_mut();
+        let mut first = u8::MAX;
+        let mut count = 0;
+        devices.for_each_info(&mut |info| {
+            first = first.min(info.interface_number);
+            count += 1;
+        });
+        if self.interface_association == InterfaceAssociation::Grouped && count > 1 {
+            if let Some(interface) = devices.get(first) {
+                writer.iad(interface.id(), count, USB_CLASS_HID, 0, 0, None)?;
+            }
+        }
+        // The class's interface numbers are allocated together, write them in ascending order
+        // whatever the order of the devices
+        for id in (0..count).map(|offset| first + offset) {
+            if let Some(interface) = devices.get(id) {
+                if self.interface_association == InterfaceAssociation::PerInterface {
+                    interface.write_association(writer)?;
+                }
+                interface.write_descriptors(writer)?;
+            }
+        }
         info!("wrote class config descriptor");
         Ok(())
     }
 
+    fn get_bos_descriptors(&self, writer: &mut BosWriter) -> Result<()> {
+        let Some(vendor_code) = self.ms_os_20_vendor_code else {
+            return Ok(());
+        };
+        if let Some((set_len, _)) =
+            Self::ms_os_20_descriptor_set_len(&mut self.devices.borrow_mut())
+        {
+            writer.capability(
+                ms_os::PLATFORM_CAPABILITY,
+                &ms_os::platform_capability(set_len, vendor_code),
+            )?;
+            info!("wrote MS OS 2.0 platform capability");
+        }
+        Ok(())
+    }
+
     fn get_string(&self, index: StringIndex, lang_id: LangID) -> Option<&str> {
         self.devices.borrow_mut().get_string(index, lang_id)
     }
 
     fn reset(&mut self) {
         info!("Reset");
+        self.suspended = false;
+        self.remote_wakeup_pending = false;
+        self.bus_reset = true;
+        self.poll_events.bus_reset = true;
         self.devices.get_mut().reset();
     }
 
+    fn poll(&mut self) {
+        // The bus has resumed, as the device is only polled while it isn't suspended
+        self.remote_wakeup_pending = false;
+    }
+
+    fn endpoint_in_complete(&mut self, addr: EndpointAddress) {
+        if let Some(interface_number) = self.endpoint_interface(addr) {
+            self.poll_events.in_complete.insert(interface_number);
+        }
+        self.devices.get_mut().endpoint_in_complete(addr);
+    }
+
+    fn get_alt_setting(&mut self, interface: InterfaceNumber) -> Option<u8> {
+        self.devices
+            .get_mut()
+            .get(u8::from(interface))
+            .map(|i| i.get_alt_setting())
+    }
+
+    fn set_alt_setting(&mut self, interface: InterfaceNumber, alternative: u8) -> bool {
+        let selected = self
+            .devices
+            .get_mut()
+            .set_alt_setting(u8::from(interface), alternative);
+        if selected {
+            self.poll_events
+                .alternate_setting_selected
+                .insert(u8::from(interface));
+        }
+        selected
+    }
+
+    fn endpoint_out(&mut self, addr: EndpointAddress) {
+        if let Some(interface_number) = self.endpoint_interface(addr) {
+            self.poll_events.out_received.insert(interface_number);
+        }
+        self.devices.get_mut().endpoint_out(addr);
+    }
+
     fn control_out(&mut self, transfer: ControlOut<B>) {
         let request: &Request = transfer.request();
 
+        if request.request_type == RequestType::Standard {
+            self.standard_control_out(request);
+            return;
+        }
+
         //only respond to Class requests for this interface
         if !(request.request_type == RequestType::Class
             && request.recipient == Recipient::Interface)
@@ -213,10 +932,10 @@ where
             return;
         }
 
-        let Some(interface) = u8::try_from(request.index)
-            .ok()
-            .and_then(|id| self.devices.get_mut().get(id))
-        else {
+        let Ok(interface_id) = u8::try_from(request.index) else {
+            return;
+        };
+        let Some(interface) = self.devices.get_mut().get(interface_id) else {
             return;
         };
 
@@ -228,10 +947,42 @@ where
         );
 
         match HidRequest::try_from(request.request) {
-            Ok(HidRequest::SetReport) => {
-                interface.set_report(transfer.data()).ok();
-                transfer.accept().ok();
+            Ok(HidRequest::SetReport)
+                if ReportType::try_from((request.value >> 8) as u8) == Ok(ReportType::Feature) =>
+            {
+                let report_id = (request.value & 0xFF) as u8;
+                let devices = self.devices.get_mut();
+                // Feature reports the device doesn't handle are read with `read_report`
+                let result =
+                    match devices.set_feature_report(interface_id, report_id, transfer.data()) {
+                        Err(UsbError::Unsupported) => devices
+                            .get(interface_id)
+                            .map_or(Err(UsbError::InvalidEndpoint), |interface| {
+                                interface.set_report(transfer.data())
+                            }),
+                        result => result,
+                    };
+                match result {
+                    Ok(()) => {
+                        trace!("Set feature report ID{}", report_id);
+                        transfer.accept().ok();
+                    }
+                    Err(e) => {
+                        warn!("Failed to set feature report ID{} - {:?}", report_id, e);
+                        transfer.reject().ok();
+                    }
+                }
             }
+            Ok(HidRequest::SetReport) => match interface.set_report(transfer.data()) {
+                Ok(()) => {
+                    self.poll_events.out_received.insert(interface_id);
+                    transfer.accept().ok();
+                }
+                Err(e) => {
+                    warn!("Failed to set report - {:?}", e);
+                    transfer.reject().ok();
+                }
+            },
             Ok(HidRequest::SetIdle) => {
                 if request.length != 0 {
                     warn!(
@@ -240,7 +991,12 @@ where
                     );
                 }
 
-                interface.set_idle((request.value & 0xFF) as u8, (request.value >> 8) as u8);
+                let report_id = (request.value & 0xFF) as u8;
+                let idle = (request.value >> 8) as u8;
+                if interface.get_idle(report_id) != idle {
+                    self.poll_events.idle_changed.insert(interface_id);
+                }
+                interface.set_idle(report_id, idle);
                 transfer.accept().ok();
             }
             Ok(HidRequest::SetProtocol) => {
@@ -251,6 +1007,9 @@ where
                     );
                 }
                 if let Ok(protocol) = HidProtocol::try_from((request.value & 0xFF) as u8) {
+                    if interface.get_protocol() != protocol {
+                        self.poll_events.protocol_changed.insert(interface_id);
+                    }
                     interface.set_protocol(protocol);
                     transfer.accept().ok();
                 } else {
@@ -271,6 +1030,16 @@ where
 
     fn control_in(&mut self, transfer: ControlIn<B>) {
         let request: &Request = transfer.request();
+        if request.request_type == RequestType::Vendor
+            && request.recipient == Recipient::Device
+            && Some(request.request) == self.ms_os_20_vendor_code
+            && request.index == MS_OS_20_DESCRIPTOR_INDEX
+        {
+            info!("Get MS OS 2.0 descriptor set");
+            Self::get_ms_os_20_descriptor_set(transfer, self.devices.get_mut());
+            return;
+        }
+
         //only respond to requests for this interface
         if !(request.recipient == Recipient::Interface) {
             return;
@@ -303,23 +1072,14 @@ where
                 };
 
                 match HidRequest::try_from(request.request) {
+                    Ok(HidRequest::GetReport)
+                        if ReportType::try_from((request.value >> 8) as u8)
+                            == Ok(ReportType::Feature) =>
+                    {
+                        Self::get_feature_report(transfer, self.devices.get_mut(), interface_id);
+                    }
                     Ok(HidRequest::GetReport) => {
-                        let requested_n = transfer.request().length.into();
-                        if let Err(e) = transfer.accept(|buffer| {
-                            interface.get_report(buffer).inspect(|&n| {
-                                if n != requested_n {
-                                    warn!(
-                                        "GetReport requested {} bytes, got {} bytes",
-                                        requested_n, n
-                                    );
-                                }
-                            })
-                        }) {
-                            error!("Failed to send report - {:?}", e);
-                        } else {
-                            trace!("Sent report");
-                            unwrap!(interface.get_report_ack());
-                        }
+                        Self::get_report(transfer, self.devices.get_mut(), interface_id);
                     }
                     Ok(HidRequest::GetIdle) => {
                         if request.length != 1 {
@@ -382,17 +1142,62 @@ mod test {
     #![allow(clippy::unwrap_used)]
     #![allow(clippy::expect_used)]
 
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
     use std::sync::Mutex;
     use std::vec::Vec;
 
-    use crate::descriptor::USB_CLASS_HID;
-    use crate::interface::{InBytes64, InterfaceBuilder, OutBytes64, ReportSingle, Reports8};
+    use crate::descriptor::builder::{Collection, DescriptorBuilder, ItemFlags};
+    use crate::descriptor::ms_os::MsOs20Function;
+    use crate::descriptor::{CountryCode, SPEC_VERSION_1_10, USB_CLASS_HID};
+    use crate::device::consumer::{
+        ConsumerControl, ConsumerControlConfig, ConsumerControlFixed, ConsumerControlFixedConfig,
+        FixedFunctionReport, MultipleConsumerReport,
+    };
+    use crate::device::digitizer::{
+        MultiTouch, MultiTouchConfig, TouchPoint, MULTI_TOUCH_CONTACT_COUNT_MAXIMUM_REPORT_ID,
+        MULTI_TOUCH_INPUT_REPORT_ID,
+    };
+    use crate::device::gamepad::{Gamepad, GamepadConfig, GamepadReport};
+    use crate::device::joystick::{
+        AxisCalibration, ConfigurableJoystick, ConfigurableJoystickConfig,
+        ConfigurableJoystickReport, JoystickAxis, JoystickDescriptor, JoystickLayout,
+    };
+    use crate::device::keyboard::{
+        BootKeyboard, BootKeyboardConfig, KeyboardLedsReport, NKROBootKeyboard,
+        NKROBootKeyboardConfig, NKROKeyboard, NKROKeyboardConfig, BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+        NKRO_BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+    };
+    use crate::device::keyboard_pointer::{
+        KeyboardPointer, KeyboardPointerConfig, KEYBOARD_POINTER_KEYBOARD_REPORT_ID,
+        KEYBOARD_POINTER_POINTER_REPORT_ID,
+    };
+    use crate::device::media_remote::{MediaRemote, MediaRemoteConfig, MediaRemoteReport};
+    use crate::device::mouse::{WheelMouse, WheelMouseConfig, WheelMouseReport};
+    use crate::device::msr::{
+        MagneticStripeReader, MagneticStripeReaderConfig, MsrTracks, MSR_TRACK_DATA_REPORT_ID,
+    };
+    use crate::device::raw::{RawHid, RawHidConfig};
+    use crate::device::telephony::{
+        HeadsetLedsReport, Speakerphone, SpeakerphoneConfig, SpeakerphoneReport,
+        SPEAKERPHONE_CONSUMER_REPORT_ID, SPEAKERPHONE_LEDS_REPORT_ID,
+        SPEAKERPHONE_TELEPHONY_REPORT_ID,
+    };
+    use crate::device::wireless_radio::{
+        WirelessRadio, WirelessRadioConfig, WirelessRadioLedReport, WirelessRadioReport,
+    };
+    use crate::device::OutputReports;
+    use crate::interface::{
+        EndpointInfo, FeatureReports, FeatureReportsConfig, InBytes16, InBytes32, InBytes512,
+        InBytes64, InBytes8, Interface, InterfaceBuilder, InterfaceDiagnostics, InterfaceState,
+        OutBytes32, OutBytes64, OutBytes8, ReportSingle, Reports8,
+    };
+    use crate::page::{Consumer, Desktop, Keyboard};
     use env_logger::Env;
-    use fugit::MillisDurationU32;
+    use fugit::{MicrosDurationU32, MicrosDurationU64, MillisDurationU32};
     use log::SetLoggerError;
     use packed_struct::prelude::*;
     use usb_device::bus::PollResult;
+    use usb_device::device::UsbRev;
     use usb_device::prelude::*;
     use usb_device::UsbDirection;
 
@@ -406,12 +1211,38 @@ mod test {
     }
 
     #[derive(Default)]
+    #[allow(clippy::struct_field_names)]
     struct UsbTestManager {
         in_buf: Mutex<RefCell<Vec<u8>>>,
         setup_buf: Mutex<RefCell<Vec<u8>>>,
+        control_out_buf: Mutex<RefCell<Vec<u8>>>,
+        out_buf: Mutex<RefCell<Vec<u8>>>,
+        suspended: Mutex<RefCell<bool>>,
+        reset: Mutex<RefCell<bool>>,
+        control_stalled: Mutex<RefCell<bool>>,
     }
 
     impl UsbTestManager {
+        fn host_set_suspended(&self, suspended: bool) {
+            self.suspended.lock().unwrap().replace(suspended);
+        }
+
+        fn is_suspended(&self) -> bool {
+            *self.suspended.lock().unwrap().borrow()
+        }
+
+        fn host_reset(&self) {
+            self.reset.lock().unwrap().replace(true);
+        }
+
+        fn take_reset(&self) -> bool {
+            self.reset.lock().unwrap().replace(false)
+        }
+
+        fn take_control_stalled(&self) -> bool {
+            self.control_stalled.lock().unwrap().replace(false)
+        }
+
         fn host_write_setup(&self, data: &[u8]) -> Result<()> {
             let buf = self.setup_buf.lock().unwrap();
             if buf.borrow().is_empty() {
@@ -422,6 +1253,47 @@ mod test {
             }
         }
 
+        /// Write the data stage of a control OUT transfer, after its setup packet is polled
+        fn host_write_control_out(&self, data: &[u8]) {
+            self.control_out_buf
+                .lock()
+                .unwrap()
+                .borrow_mut()
+                .extend_from_slice(data);
+        }
+
+        fn has_control_out_data(&self) -> bool {
+            !self.control_out_buf.lock().unwrap().borrow().is_empty()
+        }
+
+        fn host_write_out(&self, data: &[u8]) -> Result<()> {
+            let buf = self.out_buf.lock().unwrap();
+            if buf.borrow().is_empty() {
+                buf.borrow_mut().extend_from_slice(data);
+                Ok(())
+            } else {
+                Err(UsbError::WouldBlock)
+            }
+        }
+
+        fn has_out_data(&self) -> bool {
+            !self.out_buf.lock().unwrap().borrow().is_empty()
+        }
+
+        fn device_read_out(&self, data: &mut [u8]) -> Result<usize> {
+            let tmp = self.out_buf.lock().unwrap().take();
+            if tmp.is_empty() {
+                Err(UsbError::WouldBlock)
+            } else {
+                data[..tmp.len()].copy_from_slice(&tmp);
+                Ok(tmp.len())
+            }
+        }
+
+        fn has_in_data(&self) -> bool {
+            !self.in_buf.lock().unwrap().borrow().is_empty()
+        }
+
         fn host_read_in(&self) -> Vec<u8> {
             self.in_buf.lock().unwrap().take()
         }
@@ -454,13 +1326,17 @@ mod test {
 
     struct TestUsbBus<'a> {
         next_ep_index: usize,
+        in_ep_index: Option<usize>,
+        out_ep_index: Option<usize>,
         manager: &'a UsbTestManager,
     }
 
     impl<'a> TestUsbBus<'a> {
         fn new(manager: &'a UsbTestManager) -> Self {
             TestUsbBus {
-                next_ep_index: 0,
+                next_ep_index: 1,
+                in_ep_index: None,
+                out_ep_index: None,
                 manager,
             }
         }
@@ -471,42 +1347,75 @@ mod test {
             &mut self,
             ep_dir: UsbDirection,
             _ep_addr: Option<EndpointAddress>,
-            _ep_type: EndpointType,
+            ep_type: EndpointType,
             _max_packet_size: u16,
             _interval: u8,
         ) -> Result<EndpointAddress> {
+            // Endpoint 0 is reserved for the control pipe
+            if ep_type == EndpointType::Control {
+                return Ok(EndpointAddress::from_parts(0, ep_dir));
+            }
+            // Endpoints 1-7, as on parts with 8 endpoints
+            if self.next_ep_index >= 8 {
+                return Err(UsbError::EndpointOverflow);
+            }
             let ep = EndpointAddress::from_parts(self.next_ep_index, ep_dir);
+            // Only the first interrupt IN and OUT endpoints report transfers to the host
+            match ep_dir {
+                UsbDirection::In => self.in_ep_index.get_or_insert(self.next_ep_index),
+                UsbDirection::Out => self.out_ep_index.get_or_insert(self.next_ep_index),
+            };
             self.next_ep_index += 1;
             Ok(ep)
         }
 
         fn enable(&mut self) {}
-        fn reset(&self) {
-            todo!()
-        }
+        fn reset(&self) {}
         fn set_device_address(&self, _addr: u8) {
             todo!()
         }
         fn write(&self, _ep_addr: EndpointAddress, buf: &[u8]) -> Result<usize> {
             self.manager.device_write(buf)
         }
-        fn read(&self, _ep_addr: EndpointAddress, buf: &mut [u8]) -> Result<usize> {
-            self.manager.device_read_setup(buf)
-        }
-        fn set_stalled(&self, _ep_addr: EndpointAddress, _stalled: bool) {}
-        fn is_stalled(&self, _ep_addr: EndpointAddress) -> bool {
-            todo!()
+        fn read(&self, ep_addr: EndpointAddress, buf: &mut [u8]) -> Result<usize> {
+            if Some(ep_addr.index()) == self.out_ep_index {
+                self.manager.device_read_out(buf)
+            } else if !self.manager.has_setup_data() && self.manager.has_control_out_data() {
+                let data = self.manager.control_out_buf.lock().unwrap().take();
+                buf[..data.len()].copy_from_slice(&data);
+                Ok(data.len())
+            } else {
+                self.manager.device_read_setup(buf)
+            }
         }
-        fn suspend(&self) {
-            todo!()
+        fn set_stalled(&self, ep_addr: EndpointAddress, stalled: bool) {
+            if ep_addr.index() == 0 && stalled {
+                self.manager.control_stalled.lock().unwrap().replace(true);
+            }
         }
-        fn resume(&self) {
+        fn is_stalled(&self, _ep_addr: EndpointAddress) -> bool {
             todo!()
         }
+        fn suspend(&self) {}
+        fn resume(&self) {}
         fn poll(&self) -> PollResult {
+            if self.manager.take_reset() {
+                return PollResult::Reset;
+            }
+            if self.manager.is_suspended() {
+                return PollResult::Suspend;
+            }
             PollResult::Data {
-                ep_out: 0,
-                ep_in_complete: 1,
+                ep_out: u16::from(
+                    !self.manager.has_setup_data() && self.manager.has_control_out_data(),
+                ) | self
+                    .out_ep_index
+                    .filter(|_| self.manager.has_out_data())
+                    .map_or(0, |i| 1 << i),
+                ep_in_complete: 1 | self
+                    .in_ep_index
+                    .filter(|_| !self.manager.has_in_data())
+                    .map_or(0, |i| 1 << i),
                 ep_setup: u16::from(self.manager.has_setup_data()),
             }
         }
@@ -528,11 +1437,107 @@ mod test {
         length: u16,
     }
 
+    /// Direction and bInterval of each endpoint descriptor of a configuration descriptor
+    fn endpoints(mut descriptor: &[u8]) -> Vec<(u8, u8)> {
+        let mut endpoints = Vec::new();
+        while let [len, descriptor_type, ..] = *descriptor {
+            if descriptor_type == 0x05 {
+                endpoints.push((descriptor[2] & 0x80, descriptor[6]));
+            }
+            descriptor = &descriptor[usize::from(len)..];
+        }
+        endpoints
+    }
+
     #[test]
-    fn descriptor_ordering_satisfies_boot_spec() {
-        init_logging();
+    fn interface_associations_are_written_for_composite_devices() {
+        /// Configuration descriptor of a class of a keyboard and a mouse
+        fn configuration(association: InterfaceAssociation, composite: bool) -> Vec<u8> {
+            let manager = UsbTestManager::default();
+            let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+            let mut hid = UsbHidClassBuilder::new()
+                .add_device(BootKeyboardConfig::default())
+                .add_device(WheelMouseConfig::default())
+                .interface_association(association)
+                .build(&usb_alloc);
+
+            let builder = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001));
+            let mut usb_dev = if composite {
+                builder.composite_with_iads().build()
+            } else {
+                builder.build()
+            };
+
+            manager
+                .host_write_setup(
+                    &UsbRequest {
+                        direction: UsbDirection::In != UsbDirection::Out,
+                        request_type: RequestType::Standard as u8,
+                        recipient: Recipient::Device as u8,
+                        request: Request::GET_DESCRIPTOR,
+                        value: u16::from(usb_device::descriptor::descriptor_type::CONFIGURATION)
+                            << 8,
+                        index: 0,
+                        length: 0xFFFF,
+                    }
+                    .pack()
+                    .unwrap(),
+                )
+                .unwrap();
+            assert!(usb_dev.poll(&mut [&mut hid]));
 
-        let manager = UsbTestManager::default();
+            let mut data = Vec::new();
+            loop {
+                let read = manager.host_read_in();
+                if read.is_empty() {
+                    break;
+                }
+                data.extend_from_slice(&read);
+                assert!(usb_dev.poll(&mut [&mut hid]));
+            }
+            data
+        }
+
+        /// Interface Association Descriptors of a configuration descriptor
+        fn iads(mut descriptor: &[u8]) -> Vec<Vec<u8>> {
+            let mut iads = Vec::new();
+            while let [len, descriptor_type, ..] = *descriptor {
+                if descriptor_type == 0x0B {
+                    iads.push(descriptor[2..usize::from(len)].to_vec());
+                }
+                descriptor = &descriptor[usize::from(len)..];
+            }
+            iads
+        }
+
+        init_logging();
+
+        assert!(iads(&configuration(InterfaceAssociation::None, true)).is_empty());
+        assert!(iads(&configuration(InterfaceAssociation::Grouped, false)).is_empty());
+        assert_eq!(
+            iads(&configuration(InterfaceAssociation::Grouped, true)),
+            [[0, 2, USB_CLASS_HID, 0, 0, 0]]
+        );
+
+        // Each IAD precedes its interface, with the interface's boot subclass and protocol
+        let descriptor = configuration(InterfaceAssociation::PerInterface, true);
+        assert_eq!(
+            iads(&descriptor),
+            [
+                [0, 1, USB_CLASS_HID, 1, 2, 4],
+                [1, 1, USB_CLASS_HID, 1, 1, 5]
+            ]
+        );
+        assert_eq!(descriptor[9..11], [8, 0x0B]);
+        assert_eq!(descriptor[17..19], [9, 0x04]);
+    }
+
+    #[test]
+    fn descriptor_ordering_satisfies_boot_spec() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
 
         let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
 
@@ -1061,6 +2066,43 @@ mod test {
         );
     }
 
+    #[test]
+    fn short_output_reports_are_rejected() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // The boot keyboard's LED output report is one byte
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::In,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::SetReport.into(),
+                    value: u16::from(u8::from(ReportType::Output)) << 8,
+                    index: 0x0,
+                    length: 0x0,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        assert!(manager.take_control_stalled());
+        assert!(hid.take_poll_events().out_received.is_empty());
+    }
+
     #[test]
     fn set_report_idle() {
         const IDLE_DEFAULT: MillisDurationU32 = MillisDurationU32::millis(40);
@@ -1163,10 +2205,10 @@ mod test {
     }
 
     #[test]
-    fn set_report_idle_no_reports() {
+    fn report_idle_falls_back_to_global_idle() {
         const IDLE_DEFAULT: MillisDurationU32 = MillisDurationU32::millis(40);
         const IDLE_NEW: MillisDurationU32 = MillisDurationU32::millis(88);
-        const REPORT_ID: u16 = 0x4;
+        const REPORT_ID: u8 = 0x4;
 
         init_logging();
 
@@ -1176,7 +2218,7 @@ mod test {
 
         let mut hid = UsbHidClassBuilder::new()
             .add_device(
-                InterfaceBuilder::<InBytes64, OutBytes64, ReportSingle>::new(&[])
+                InterfaceBuilder::<InBytes64, OutBytes64, Reports8>::new(&[])
                     .unwrap()
                     .idle_default(IDLE_DEFAULT)
                     .unwrap()
@@ -1188,15 +2230,15 @@ mod test {
             .device_class(USB_CLASS_HID)
             .build();
 
-        // Set report idle - this is a nop due to lack of report storage in the device (`SingleReport`)
         manager
             .host_write_setup(
                 &UsbRequest {
                     direction: UsbDirection::In != UsbDirection::In,
                     request_type: RequestType::Class as u8,
                     recipient: Recipient::Interface as u8,
-                    request: HidRequest::SetIdle as u8,
-                    value: (u16::try_from(IDLE_NEW.to_millis()).unwrap() / 4) << 8 | REPORT_ID,
+                    request: HidRequest::SetIdle.into(),
+                    value: (u16::try_from(IDLE_NEW.to_millis()).unwrap() / 4) << 8
+                        | u16::from(REPORT_ID),
                     index: 0x0,
                     length: 0x0,
                 }
@@ -1207,15 +2249,123 @@ mod test {
 
         assert!(usb_dev.poll(&mut [&mut hid]));
 
-        // Get report idle
+        let device = hid.device::<Interface<_, InBytes64, OutBytes64, Reports8>, _>();
+        assert_eq!(device.report_idle(REPORT_ID), Some(IDLE_NEW));
+        assert_eq!(device.idle(REPORT_ID), IDLE_NEW);
+        assert_eq!(device.report_idle(REPORT_ID + 1), None);
+        assert_eq!(device.idle(REPORT_ID + 1), IDLE_DEFAULT);
+    }
+
+    #[test]
+    fn keyboard_leds_report_changes() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let caps_lock = KeyboardLedsReport {
+            caps_lock: true,
+            ..Default::default()
+        };
+
+        // Unchanged LEDs aren't reported as changed
+        manager.host_write_out(&[0]).unwrap();
+        usb_dev.poll(&mut [&mut hid]);
+        hid.tick().unwrap();
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        assert_eq!(keyboard.leds_changed(), Ok(None));
+
+        // Reports received by tick are still returned by read_report
+        manager.host_write_out(&[0x02]).unwrap();
+        usb_dev.poll(&mut [&mut hid]);
+        hid.tick().unwrap();
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        assert_eq!(keyboard.leds(), caps_lock);
+        assert_eq!(keyboard.leds_changed(), Ok(Some(caps_lock)));
+        assert_eq!(keyboard.leds_changed(), Ok(None));
+        assert_eq!(keyboard.read_report(), Ok(caps_lock));
+        assert_eq!(keyboard.read_report(), Err(UsbError::WouldBlock));
+    }
+
+    #[test]
+    fn output_reports_are_exposed_by_devices() {
+        fn changed<D: OutputReports>(device: &mut D) -> Option<D::Report> {
+            device.output_report_changed().unwrap()
+        }
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .add_device(WirelessRadioConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        manager.host_write_out(&[0x01]).unwrap();
+        usb_dev.poll(&mut [&mut hid]);
+        hid.tick().unwrap();
+
+        let num_lock = KeyboardLedsReport {
+            num_lock: true,
+            ..Default::default()
+        };
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        assert_eq!(keyboard.output_report(), num_lock);
+        assert_eq!(changed(keyboard), Some(num_lock));
+        assert_eq!(changed(keyboard), None);
+        assert_eq!(keyboard.read_output_report(), Ok(num_lock));
+
+        let radio = hid.device::<WirelessRadio<_>, _>();
+        assert_eq!(changed(radio), None);
+        assert_eq!(radio.output_report(), WirelessRadioLedReport::default());
+        assert_eq!(radio.read_report(), Err(UsbError::WouldBlock));
+    }
+
+    #[test]
+    fn wireless_radio_button_is_sent_and_led_set_by_the_host() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(WirelessRadioConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let radio = hid.device::<WirelessRadio<_>, _>();
+        radio
+            .write_report(&WirelessRadioReport { button: true })
+            .unwrap();
+        assert_eq!(manager.host_read_in(), [0x01]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // No OUT endpoint, the LED is set on the control pipe
         manager
             .host_write_setup(
                 &UsbRequest {
-                    direction: UsbDirection::In != UsbDirection::Out,
+                    direction: UsbDirection::In != UsbDirection::In,
                     request_type: RequestType::Class as u8,
                     recipient: Recipient::Interface as u8,
-                    request: HidRequest::GetIdle as u8,
-                    value: REPORT_ID,
+                    request: HidRequest::SetReport.into(),
+                    value: u16::from(u8::from(ReportType::Output)) << 8,
                     index: 0x0,
                     length: 0x1,
                 }
@@ -1223,118 +2373,3995 @@ mod test {
                 .unwrap(),
             )
             .unwrap();
+        usb_dev.poll(&mut [&mut hid]);
+        manager.host_write_control_out(&[0x01]);
+        usb_dev.poll(&mut [&mut hid]);
+        assert!(!manager.take_control_stalled());
+
+        let led = WirelessRadioLedReport { led: true };
+        let radio = hid.device::<WirelessRadio<_>, _>();
+        assert_eq!(radio.read_report(), Ok(led));
+        assert_eq!(radio.read_report(), Err(UsbError::WouldBlock));
+        assert_eq!(radio.output_report(), led);
+    }
 
-        assert!(usb_dev.poll(&mut [&mut hid]));
+    #[test]
+    fn stream_output_reports_are_received_one_at_a_time() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(RawHidConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Reports aren't drained by tick, the host is NAKed until each is read
+        manager.host_write_out(&[1, 2, 3]).unwrap();
+        usb_dev.poll(&mut [&mut hid]);
+        hid.tick().unwrap();
+        assert_eq!(manager.host_write_out(&[4]), Err(UsbError::WouldBlock));
+
+        let raw = hid.device::<RawHid<_>, _>();
+        let mut data = [0; 64];
+        assert_eq!(raw.read_report(&mut data), Ok(3));
+        assert_eq!(data[..3], [1, 2, 3]);
+        assert_eq!(raw.output_report().data(), [1, 2, 3]);
+        assert_eq!(raw.read_report(&mut data), Err(UsbError::WouldBlock));
+
+        manager.host_write_out(&[4]).unwrap();
+        usb_dev.poll(&mut [&mut hid]);
+        let raw = hid.device::<RawHid<_>, _>();
+        assert_eq!(raw.read_output_report().map(|r| r.data()[0]), Ok(4));
+    }
+
+    #[test]
+    fn interface_state_is_queried_from_the_class() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
 
-        // read and validate hid_protocol report
-        let data = manager.host_read_in();
         assert_eq!(
-            data,
-            [u8::try_from(IDLE_DEFAULT.ticks()).unwrap() / 4],
-            "Unexpected report idle value"
+            hid.interface_state(0),
+            Some(InterfaceState {
+                protocol: HidProtocol::Report,
+                idle: MillisDurationU32::millis(500),
+                alternate_setting: 0,
+                suspended: false,
+                since_output_report: None,
+            })
         );
+        assert_eq!(hid.interface_state(1), None);
 
-        // Get global idle
         manager
             .host_write_setup(
                 &UsbRequest {
-                    direction: UsbDirection::In != UsbDirection::Out,
+                    direction: UsbDirection::In != UsbDirection::In,
                     request_type: RequestType::Class as u8,
                     recipient: Recipient::Interface as u8,
-                    request: HidRequest::GetIdle as u8,
-                    value: 0x0,
-                    index: 0x0,
-                    length: 0x1,
+                    request: HidRequest::SetIdle as u8,
+                    value: 0x0A00,
+                    index: 0,
+                    length: 0,
                 }
                 .pack()
                 .unwrap(),
             )
             .unwrap();
-
         assert!(usb_dev.poll(&mut [&mut hid]));
+        assert_eq!(hid.report_idle(0, 0), Some(MillisDurationU32::millis(40)));
+        assert_eq!(hid.report_idle(1, 0), None);
 
-        // read and validate hid_protocol report
-        let data = manager.host_read_in();
+        manager.host_write_out(&[0x01]).unwrap();
+        usb_dev.poll(&mut [&mut hid]);
+        for _ in 0..5 {
+            hid.tick().unwrap();
+        }
+        let state = hid.interface_state(0).unwrap();
+        assert_eq!(state.idle, MillisDurationU32::millis(40));
         assert_eq!(
-            data,
-            [u8::try_from(IDLE_DEFAULT.ticks()).unwrap() / 4],
-            "Unexpected global idle value"
+            state.since_output_report,
+            Some(MillisDurationU32::millis(4))
         );
     }
 
     #[test]
-    fn get_report_idle_default_post_reset() {
-        const REPORT_ID: u8 = 0x4;
-        const IDLE_DEFAULT: MillisDurationU32 = MillisDurationU32::millis(40);
-        const IDLE_NEW: MillisDurationU32 = MillisDurationU32::millis(88);
-
+    fn indefinite_idle_is_reset_on_protocol_change_and_bus_reset() {
         init_logging();
 
         let manager = UsbTestManager::default();
-
         let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
 
         let mut hid = UsbHidClassBuilder::new()
-            .add_device(
-                InterfaceBuilder::<InBytes64, OutBytes64, ReportSingle>::new(&[])
-                    .unwrap()
-                    .idle_default(IDLE_DEFAULT)
-                    .unwrap()
-                    .build(),
-            )
+            .add_device(BootKeyboardConfig::default())
             .build(&usb_alloc);
 
         let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
             .device_class(USB_CLASS_HID)
             .build();
 
-        // Set report idle
-        manager
-            .host_write_setup(
-                &UsbRequest {
-                    direction: UsbDirection::In != UsbDirection::In,
-                    request_type: RequestType::Class as u8,
-                    recipient: Recipient::Interface as u8,
-                    request: HidRequest::SetIdle.into(),
-                    value: (u16::try_from(IDLE_NEW.to_millis()).unwrap() / 4) << 8
-                        | u16::from(REPORT_ID),
-                    index: 0x0,
-                    length: 0x0,
+        // Send a class request and poll the device
+        macro_rules! request {
+            ($request:expr, $value:expr) => {{
+                let get = $request == HidRequest::GetIdle;
+                manager
+                    .host_write_setup(
+                        &UsbRequest {
+                            direction: get,
+                            request_type: RequestType::Class as u8,
+                            recipient: Recipient::Interface as u8,
+                            request: $request.into(),
+                            value: $value,
+                            index: 0x0,
+                            length: u16::from(get),
+                        }
+                        .pack()
+                        .unwrap(),
+                    )
+                    .unwrap();
+                assert!(usb_dev.poll(&mut [&mut hid]));
+            }};
+        }
+        // Whether the report is repeated within the ticks
+        macro_rules! repeats_within {
+            ($ticks:expr) => {{
+                let ticks: u32 = $ticks;
+                for _ in 0..ticks {
+                    hid.tick().unwrap();
                 }
-                .pack()
-                .unwrap(),
-            )
-            .unwrap();
+                let repeated = !manager.host_read_in().is_empty();
+                assert!(usb_dev.poll(&mut [&mut hid]));
+                repeated
+            }};
+        }
 
-        assert!(usb_dev.poll(&mut [&mut hid]));
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        keyboard.write_report([Keyboard::A]).unwrap();
+        assert!(repeats_within!(0));
+
+        // Indefinite idle in report protocol only reports changes
+        request!(HidRequest::SetIdle, 0);
+        assert!(!repeats_within!(2000));
+
+        // Switching to boot protocol restores the 500ms boot keyboard default
+        request!(HidRequest::SetProtocol, HidProtocol::Boot as u16);
+        request!(HidRequest::GetIdle, 0);
+        assert_eq!(manager.host_read_in(), [125]);
+        assert!(repeats_within!(500));
+
+        // Indefinite idle in boot protocol, kept when the protocol is set again unchanged
+        request!(HidRequest::SetIdle, 0);
+        request!(HidRequest::SetProtocol, HidProtocol::Boot as u16);
+        assert!(!repeats_within!(2000));
+
+        // Switching back to report protocol restores the default too
+        request!(HidRequest::SetProtocol, HidProtocol::Report as u16);
+        assert!(repeats_within!(500));
+
+        // As does a bus reset, in report protocol
+        request!(HidRequest::SetIdle, 0);
+        request!(HidRequest::SetProtocol, HidProtocol::Boot as u16);
+        request!(HidRequest::SetIdle, 0);
+        UsbClass::reset(&mut hid);
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        assert_eq!(keyboard.interface().protocol(), HidProtocol::Report);
+        assert_eq!(
+            keyboard.interface().global_idle(),
+            MillisDurationU32::millis(500)
+        );
+        keyboard.write_report([Keyboard::B]).unwrap();
+        assert!(repeats_within!(0));
+        assert!(!repeats_within!(499));
+        assert!(repeats_within!(1));
+    }
 
-        hid.reset();
+    #[test]
+    fn suspend_pauses_idle_repeats() {
+        init_logging();
 
-        // Get report idle
-        manager
-            .host_write_setup(
-                &UsbRequest {
-                    direction: UsbDirection::In != UsbDirection::Out,
-                    request_type: RequestType::Class as u8,
-                    recipient: Recipient::Interface as u8,
-                    request: HidRequest::GetIdle.into(),
-                    value: u16::from(REPORT_ID),
-                    index: 0x0,
-                    length: 0x1,
-                }
-                .pack()
-                .unwrap(),
-            )
-            .unwrap();
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        keyboard.write_report([Keyboard::A]).unwrap();
+        assert_eq!(manager.host_read_in(), [0, 0, 4, 0, 0, 0, 0, 0]);
+
+        manager.host_set_suspended(true);
+        usb_dev.poll(&mut [&mut hid]);
+        hid.update_suspend(&usb_dev);
+        assert!(hid.is_suspended());
+        assert!(hid
+            .device::<BootKeyboard<_>, _>()
+            .interface()
+            .is_suspended());
+
+        // The 500ms default idle rate doesn't repeat the report while suspended
+        for _ in 0..600 {
+            hid.tick().unwrap();
+        }
+        assert!(manager.host_read_in().is_empty());
+
+        manager.host_set_suspended(false);
+        usb_dev.poll(&mut [&mut hid]);
+        hid.update_suspend(&usb_dev);
+        assert!(!hid.is_suspended());
+        for _ in 0..500 {
+            hid.tick().unwrap();
+        }
+        assert_eq!(manager.host_read_in(), [0, 0, 4, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn ticks_are_counted_from_elapsed_time() {
+        init_logging();
 
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        keyboard.write_report([Keyboard::A]).unwrap();
+        assert_eq!(manager.host_read_in(), [0, 0, 4, 0, 0, 0, 0, 0]);
         assert!(usb_dev.poll(&mut [&mut hid]));
 
-        // read and validate hid_protocol report
-        let data = manager.host_read_in();
-        assert_eq!(
-            data,
-            [u8::try_from(IDLE_DEFAULT.ticks()).unwrap() / 4],
-            "Unexpected report idle value"
+        // The 500ms default idle rate from a 4kHz timer
+        for _ in 0..1999 {
+            hid.tick_elapsed(MicrosDurationU32::micros(250)).unwrap();
+        }
+        assert!(manager.host_read_in().is_empty());
+        hid.tick_elapsed(MicrosDurationU32::micros(250)).unwrap();
+        assert_eq!(manager.host_read_in(), [0, 0, 4, 0, 0, 0, 0, 0]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // From a monotonic clock
+        let now = Cell::new(fugit::TimerInstantU64::<1_000_000>::from_ticks(1_000_000));
+        let clock = || now.get();
+        hid.tick_clock(&clock).unwrap();
+        now.set(now.get() + MicrosDurationU64::micros(499_900));
+        hid.tick_clock(&clock).unwrap();
+        assert!(manager.host_read_in().is_empty());
+        now.set(now.get() + MicrosDurationU64::micros(100));
+        hid.tick_clock(&clock).unwrap();
+        assert_eq!(manager.host_read_in(), [0, 0, 4, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn ticks_catching_up_on_elapsed_time_are_capped() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        manager.host_write_out(&[0x01]).unwrap();
+        usb_dev.poll(&mut [&mut hid]);
+        hid.tick().unwrap();
+        assert_eq!(
+            hid.interface_state(0).unwrap().since_output_report,
+            Some(MillisDurationU32::millis(0))
+        );
+
+        // An hour without ticks, such as while the device slept
+        hid.tick_elapsed(MicrosDurationU32::minutes(60)).unwrap();
+        assert_eq!(
+            hid.interface_state(0).unwrap().since_output_report,
+            Some(MillisDurationU32::millis(MAX_CATCH_UP_TICKS))
+        );
+
+        // Longer than a u32 of microseconds
+        hid.tick_elapsed(MillisDurationU32::minutes(120)).unwrap();
+        assert_eq!(
+            hid.interface_state(0).unwrap().since_output_report,
+            Some(MillisDurationU32::millis(2 * MAX_CATCH_UP_TICKS))
+        );
+    }
+
+    #[test]
+    fn blocked_reports_are_retried_until_max_age() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        keyboard.write_report([Keyboard::A]).unwrap();
+        assert!(matches!(
+            keyboard.write_report([Keyboard::B]),
+            Err(UsbHidError::WouldBlock)
+        ));
+        keyboard.write_report_retrying([Keyboard::B]).unwrap();
+        assert!(matches!(
+            keyboard.write_report_retrying([Keyboard::B]),
+            Err(UsbHidError::Duplicate)
+        ));
+        hid.tick().unwrap();
+
+        // Retried once the host has read the last report
+        assert_eq!(manager.host_read_in(), [0, 0, 4, 0, 0, 0, 0, 0]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        hid.tick().unwrap();
+        assert_eq!(manager.host_read_in(), [0, 0, 5, 0, 0, 0, 0, 0]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // Dropped after the 100ms default max age
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        keyboard.write_report([Keyboard::C]).unwrap();
+        keyboard.write_report_retrying([Keyboard::D]).unwrap();
+        for _ in 0..101 {
+            hid.tick().unwrap();
+        }
+        assert_eq!(manager.host_read_in(), [0, 0, 6, 0, 0, 0, 0, 0]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        hid.tick().unwrap();
+        assert!(manager.host_read_in().is_empty());
+        assert_eq!(
+            hid.device::<BootKeyboard<_>, _>()
+                .interface()
+                .diagnostics()
+                .input_reports_dropped,
+            1
+        );
+    }
+
+    #[test]
+    fn pending_reports_are_not_aged_while_suspended() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        keyboard.write_report([Keyboard::A]).unwrap();
+        keyboard.write_report_retrying([]).unwrap();
+
+        manager.host_set_suspended(true);
+        usb_dev.poll(&mut [&mut hid]);
+        hid.update_suspend(&usb_dev);
+
+        // Suspended for longer than the 100ms default max age
+        for _ in 0..200 {
+            hid.tick().unwrap();
+        }
+
+        // The key release is sent once the host resumes
+        manager.host_set_suspended(false);
+        usb_dev.poll(&mut [&mut hid]);
+        hid.update_suspend(&usb_dev);
+        assert_eq!(manager.host_read_in(), [0, 0, 4, 0, 0, 0, 0, 0]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        hid.tick().unwrap();
+        assert_eq!(manager.host_read_in(), [0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            hid.device::<BootKeyboard<_>, _>()
+                .interface()
+                .diagnostics()
+                .input_reports_dropped,
+            0
+        );
+    }
+
+    #[test]
+    fn mouse_motion_is_accumulated_while_blocked() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(WheelMouseConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let mouse = hid.device::<WheelMouse<_>, _>();
+        mouse
+            .write_report(&WheelMouseReport::new(1, 10, -5))
+            .unwrap();
+        mouse
+            .write_report(&WheelMouseReport::new(1, 20, 0))
+            .unwrap();
+        mouse
+            .write_report(&WheelMouseReport::new(1, 120, 3).with_scroll(1, 0))
+            .unwrap();
+        assert_eq!(manager.host_read_in(), [1, 10, 0xFB, 0, 0]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // Sent from the tick, saturated until all the movement is sent
+        hid.tick().unwrap();
+        assert_eq!(manager.host_read_in(), [1, 127, 3, 1, 0]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        hid.tick().unwrap();
+        assert_eq!(manager.host_read_in(), [1, 13, 0, 0, 0]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        hid.tick().unwrap();
+        assert!(manager.host_read_in().is_empty());
+    }
+
+    #[test]
+    fn consumer_usages_are_released_automatically() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                ConsumerControlConfig::default().with_auto_release(MillisDurationU32::millis(20)),
+            )
+            .add_device(
+                ConsumerControlFixedConfig::default()
+                    .with_auto_release(MillisDurationU32::millis(0)),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Released after the next poll
+        hid.device::<ConsumerControlFixed<_>, _>()
+            .write_report(&FixedFunctionReport {
+                mute: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(manager.host_read_in(), [0x10]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        hid.tick().unwrap();
+        assert_eq!(manager.host_read_in(), [0x00]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // Released after the delay
+        let report = MultipleConsumerReport {
+            codes: [
+                Consumer::VolumeIncrement,
+                Consumer::Unassigned,
+                Consumer::Unassigned,
+                Consumer::Unassigned,
+            ],
+        };
+        hid.device::<ConsumerControl<_>, _>()
+            .write_report(&report)
+            .unwrap();
+        assert_eq!(manager.host_read_in(), [0xE9, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        for _ in 0..19 {
+            hid.tick().unwrap();
+        }
+        assert!(manager.host_read_in().is_empty());
+        hid.tick().unwrap();
+        assert_eq!(manager.host_read_in(), [0; 8]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        hid.tick().unwrap();
+        assert!(manager.host_read_in().is_empty());
+    }
+
+    #[test]
+    fn media_remote_hold_is_extended_by_repeated_writes() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(MediaRemoteConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let report = MediaRemoteReport {
+            consumer: Consumer::PlayPause,
+            ..Default::default()
+        };
+        hid.device::<MediaRemote<_>, _>()
+            .write_report(&report)
+            .unwrap();
+        assert_eq!(manager.host_read_in(), [0x01, 0xCD, 0x00]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // A repeat code within the hold time isn't sent again, and restarts the hold
+        for _ in 0..100 {
+            hid.tick().unwrap();
+        }
+        hid.device::<MediaRemote<_>, _>()
+            .write_report(&report)
+            .unwrap();
+        for _ in 0..149 {
+            hid.tick().unwrap();
+        }
+        assert!(manager.host_read_in().is_empty());
+
+        hid.tick().unwrap();
+        assert_eq!(manager.host_read_in(), [0x01, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn media_remote_usages_are_released_after_the_hold_time() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(MediaRemoteConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        hid.device::<MediaRemote<_>, _>()
+            .write_report(&MediaRemoteReport {
+                system: Desktop::SystemSleep,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(manager.host_read_in(), [0x02, 0x82]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        for _ in 0..149 {
+            hid.tick().unwrap();
+        }
+        assert!(manager.host_read_in().is_empty());
+
+        // Only the released system usage is reported
+        hid.tick().unwrap();
+        assert_eq!(manager.host_read_in(), [0x02, 0x00]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        hid.tick().unwrap();
+        assert!(manager.host_read_in().is_empty());
+    }
+
+    #[test]
+    fn media_remote_reports_blocked_by_a_busy_endpoint_are_sent_by_tick() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(MediaRemoteConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Both usages change, the system report waits for the consumer report to be read
+        hid.device::<MediaRemote<_>, _>()
+            .write_report(&MediaRemoteReport {
+                consumer: Consumer::PlayPause,
+                system: Desktop::SystemSleep,
+            })
+            .unwrap();
+        hid.tick().unwrap();
+        assert_eq!(manager.host_read_in(), [0x01, 0xCD, 0x00]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        hid.tick().unwrap();
+        assert_eq!(manager.host_read_in(), [0x02, 0x82]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        hid.tick().unwrap();
+        assert!(manager.host_read_in().is_empty());
+    }
+
+    #[test]
+    fn speakerphone_leds_are_read_once_from_out_and_set_report() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(SpeakerphoneConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Off-hook and mute on the interrupt OUT endpoint
+        manager
+            .host_write_out(&[SPEAKERPHONE_LEDS_REPORT_ID, 0b11])
+            .unwrap();
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let leds = HeadsetLedsReport {
+            off_hook: true,
+            mute: true,
+            ..HeadsetLedsReport::default()
+        };
+        let speakerphone = hid.device::<Speakerphone<_>, _>();
+        assert_eq!(speakerphone.read_leds(), Some(leds));
+        assert_eq!(speakerphone.read_leds(), None);
+        assert_eq!(speakerphone.leds(), leds);
+
+        // Ring on the control pipe
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::In,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::SetReport.into(),
+                    value: u16::from(u8::from(ReportType::Output)) << 8
+                        | u16::from(SPEAKERPHONE_LEDS_REPORT_ID),
+                    index: 0x0,
+                    length: 0x2,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+        usb_dev.poll(&mut [&mut hid]);
+        manager.host_write_control_out(&[SPEAKERPHONE_LEDS_REPORT_ID, 0b100]);
+        usb_dev.poll(&mut [&mut hid]);
+        assert!(!manager.take_control_stalled());
+
+        let leds = HeadsetLedsReport {
+            ring: true,
+            ..HeadsetLedsReport::default()
+        };
+        let speakerphone = hid.device::<Speakerphone<_>, _>();
+        assert_eq!(speakerphone.read_leds(), Some(leds));
+        assert_eq!(speakerphone.read_leds(), None);
+    }
+
+    #[test]
+    fn speakerphone_reports_blocked_by_a_busy_endpoint_are_sent_by_tick() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(SpeakerphoneConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Both collections change, the telephony report waits for the consumer report to be read
+        hid.device::<Speakerphone<_>, _>()
+            .write_report(&SpeakerphoneReport {
+                volume_up: true,
+                hook_switch: true,
+                ..SpeakerphoneReport::default()
+            })
+            .unwrap();
+        hid.tick().unwrap();
+        assert_eq!(
+            manager.host_read_in(),
+            [SPEAKERPHONE_CONSUMER_REPORT_ID, 0b1]
+        );
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        hid.tick().unwrap();
+        assert_eq!(
+            manager.host_read_in(),
+            [SPEAKERPHONE_TELEPHONY_REPORT_ID, 0b1]
+        );
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        hid.tick().unwrap();
+        assert!(manager.host_read_in().is_empty());
+    }
+
+    #[test]
+    fn remote_wakeup_requires_armed_suspended_bus() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .supports_remote_wakeup(true)
+            .build();
+
+        let mut signalled = 0;
+
+        // Not suspended
+        assert!(matches!(
+            hid.remote_wakeup(&usb_dev, || signalled += 1),
+            Err(UsbHidError::UsbError(UsbError::InvalidState))
+        ));
+
+        // Suspended, but not armed
+        manager.host_set_suspended(true);
+        assert!(!usb_dev.poll(&mut [&mut hid]));
+        assert!(matches!(
+            hid.remote_wakeup(&usb_dev, || signalled += 1),
+            Err(UsbHidError::UsbError(UsbError::InvalidState))
+        ));
+        manager.host_set_suspended(false);
+        usb_dev.poll(&mut [&mut hid]);
+
+        // Arm remote wakeup
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::In,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Device as u8,
+                    request: Request::SET_FEATURE,
+                    value: Request::FEATURE_DEVICE_REMOTE_WAKEUP,
+                    index: 0x0,
+                    length: 0x0,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+        usb_dev.poll(&mut [&mut hid]);
+
+        // Resume is signalled once per suspend
+        manager.host_set_suspended(true);
+        assert!(!usb_dev.poll(&mut [&mut hid]));
+        hid.remote_wakeup(&usb_dev, || signalled += 1).unwrap();
+        hid.remote_wakeup(&usb_dev, || signalled += 1).unwrap();
+        assert_eq!(signalled, 1);
+        assert!(hid.remote_wakeup_pending());
+
+        manager.host_set_suspended(false);
+        usb_dev.poll(&mut [&mut hid]);
+        assert!(!hid.remote_wakeup_pending());
+    }
+
+    #[test]
+    fn reports_are_translated_in_boot_protocol() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(NKROKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let keyboard = hid.device::<NKROKeyboard<_>, _>();
+        keyboard.write_report([Keyboard::A]).unwrap();
+        assert_eq!(manager.host_read_in().len(), 18);
+
+        // Set protocol to boot
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::In,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::SetProtocol.into(),
+                    value: HidProtocol::Boot as u16,
+                    index: 0x0,
+                    length: 0x0,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let keyboard = hid.device::<NKROKeyboard<_>, _>();
+        keyboard.write_report([Keyboard::B]).unwrap();
+        assert_eq!(manager.host_read_in(), [0, 0, 5, 0, 0, 0, 0, 0]);
+
+        // Idle repeats are also in the boot format
+        for _ in 0..500 {
+            hid.tick().unwrap();
+        }
+        assert_eq!(manager.host_read_in(), [0, 0, 5, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn get_input_report_is_answered_with_last_report() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let get_input_report = UsbRequest {
+            direction: UsbDirection::In != UsbDirection::Out,
+            request_type: RequestType::Class as u8,
+            recipient: Recipient::Interface as u8,
+            request: HidRequest::GetReport.into(),
+            value: u16::from(u8::from(ReportType::Input)) << 8,
+            index: 0x0,
+            length: 0x8,
+        }
+        .pack()
+        .unwrap();
+
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        keyboard.write_report([Keyboard::A]).unwrap();
+        assert_eq!(manager.host_read_in(), [0, 0, 4, 0, 0, 0, 0, 0]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        keyboard.write_report([Keyboard::B]).unwrap();
+        assert_eq!(manager.host_read_in(), [0, 0, 5, 0, 0, 0, 0, 0]);
+
+        // Every request is answered with the last report written
+        for _ in 0..2 {
+            manager.host_write_setup(&get_input_report).unwrap();
+            assert!(usb_dev.poll(&mut [&mut hid]));
+            assert_eq!(manager.host_read_in(), [0, 0, 5, 0, 0, 0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn managed_interface_deduplication_is_optional() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(GamepadConfig::default())
+            .add_device(GamepadConfig::default().without_deduplication())
+            .build(&usb_alloc);
+
+        let _usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let report = GamepadReport::default();
+        // The last device added is at the head of the list
+        let devices = hid.devices();
+        let (raw, deduplicated) = (devices.head, devices.tail.head);
+
+        deduplicated.write_report(&report).unwrap();
+        assert!(!manager.host_read_in().is_empty());
+        assert!(matches!(
+            deduplicated.write_report(&report),
+            Err(UsbHidError::Duplicate)
+        ));
+        assert!(manager.host_read_in().is_empty());
+
+        for _ in 0..2 {
+            raw.write_report(&report).unwrap();
+            assert!(!manager.host_read_in().is_empty());
+        }
+    }
+
+    #[test]
+    fn keyboard_pointer_repeats_keyboard_report_at_report_idle() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(KeyboardPointerConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Set an 8ms idle for the keyboard report ID only
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::In,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::SetIdle.into(),
+                    value: 2 << 8 | u16::from(KEYBOARD_POINTER_KEYBOARD_REPORT_ID),
+                    index: 0x0,
+                    length: 0x0,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let keyboard = hid.device::<KeyboardPointer<_>, _>();
+        keyboard.write_keyboard([Keyboard::A]).unwrap();
+        assert_eq!(manager.host_read_in(), [1, 0, 0, 4, 0, 0, 0, 0, 0]);
+
+        // Pointer reports aren't repeated, with no global idle
+        keyboard
+            .write_pointer(&WheelMouseReport::default())
+            .unwrap();
+        assert_eq!(manager.host_read_in(), [2, 0, 0, 0, 0, 0]);
+
+        for _ in 0..7 {
+            hid.tick().unwrap();
+            assert!(manager.host_read_in().is_empty());
+        }
+        hid.tick().unwrap();
+        assert_eq!(manager.host_read_in(), [1, 0, 0, 4, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn set_report_idle_no_reports() {
+        const IDLE_DEFAULT: MillisDurationU32 = MillisDurationU32::millis(40);
+        const IDLE_NEW: MillisDurationU32 = MillisDurationU32::millis(88);
+        const REPORT_ID: u16 = 0x4;
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes64, OutBytes64, ReportSingle>::new(&[])
+                    .unwrap()
+                    .idle_default(IDLE_DEFAULT)
+                    .unwrap()
+                    .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Set report idle - this is a nop due to lack of report storage in the device (`SingleReport`)
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::In,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::SetIdle as u8,
+                    value: (u16::try_from(IDLE_NEW.to_millis()).unwrap() / 4) << 8 | REPORT_ID,
+                    index: 0x0,
+                    length: 0x0,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // Get report idle
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::GetIdle as u8,
+                    value: REPORT_ID,
+                    index: 0x0,
+                    length: 0x1,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // read and validate hid_protocol report
+        let data = manager.host_read_in();
+        assert_eq!(
+            data,
+            [u8::try_from(IDLE_DEFAULT.ticks()).unwrap() / 4],
+            "Unexpected report idle value"
+        );
+
+        // Get global idle
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::GetIdle as u8,
+                    value: 0x0,
+                    index: 0x0,
+                    length: 0x1,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // read and validate hid_protocol report
+        let data = manager.host_read_in();
+        assert_eq!(
+            data,
+            [u8::try_from(IDLE_DEFAULT.ticks()).unwrap() / 4],
+            "Unexpected global idle value"
+        );
+    }
+
+    #[test]
+    fn get_report_idle_default_post_reset() {
+        const REPORT_ID: u8 = 0x4;
+        const IDLE_DEFAULT: MillisDurationU32 = MillisDurationU32::millis(40);
+        const IDLE_NEW: MillisDurationU32 = MillisDurationU32::millis(88);
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes64, OutBytes64, ReportSingle>::new(&[])
+                    .unwrap()
+                    .idle_default(IDLE_DEFAULT)
+                    .unwrap()
+                    .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Set report idle
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::In,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::SetIdle.into(),
+                    value: (u16::try_from(IDLE_NEW.to_millis()).unwrap() / 4) << 8
+                        | u16::from(REPORT_ID),
+                    index: 0x0,
+                    length: 0x0,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        hid.reset();
+
+        // Get report idle
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::GetIdle.into(),
+                    value: u16::from(REPORT_ID),
+                    index: 0x0,
+                    length: 0x1,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // read and validate hid_protocol report
+        let data = manager.host_read_in();
+        assert_eq!(
+            data,
+            [u8::try_from(IDLE_DEFAULT.ticks()).unwrap() / 4],
+            "Unexpected report idle value"
+        );
+    }
+
+    #[test]
+    fn keyboard_pointer_writes_reports_with_ids() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(KeyboardPointerConfig::default())
+            .build(&usb_alloc);
+
+        let _usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let keyboard_pointer = hid.device::<KeyboardPointer<_>, _>();
+        keyboard_pointer.write_keyboard([Keyboard::A]).unwrap();
+        assert_eq!(
+            manager.host_read_in(),
+            [
+                KEYBOARD_POINTER_KEYBOARD_REPORT_ID,
+                0,
+                0,
+                0x04,
+                0,
+                0,
+                0,
+                0,
+                0
+            ]
+        );
+
+        keyboard_pointer
+            .write_pointer(&WheelMouseReport {
+                buttons: 0x01,
+                x: 5,
+                y: -3,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            manager.host_read_in(),
+            [KEYBOARD_POINTER_POINTER_REPORT_ID, 0x01, 5, 0xFD, 0, 0]
+        );
+    }
+
+    #[test]
+    fn get_feature_report() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(MultiTouchConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Get feature report
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::GetReport.into(),
+                    value: u16::from(u8::from(ReportType::Feature)) << 8
+                        | u16::from(MULTI_TOUCH_CONTACT_COUNT_MAXIMUM_REPORT_ID),
+                    index: 0x0,
+                    length: 0x2,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let data = manager.host_read_in();
+        assert_eq!(
+            data,
+            [MULTI_TOUCH_CONTACT_COUNT_MAXIMUM_REPORT_ID, 5],
+            "Unexpected feature report"
+        );
+    }
+
+    #[test]
+    fn feature_reports_fall_back_to_the_interface() {
+        #[rustfmt::skip]
+        const DESCRIPTOR: &[u8] = &[
+            0x06, 0x00, 0xFF, // Usage Page (Vendor Defined 0xFF00)
+            0x09, 0x01,       // Usage (0x01)
+            0xA1, 0x01,       // Collection (Application)
+            0x15, 0x00,       //   Logical Minimum (0)
+            0x26, 0xFF, 0x00, //   Logical Maximum (255)
+            0x75, 0x08,       //   Report Size (8)
+            0x95, 0x04,       //   Report Count (4)
+            0x09, 0x02,       //   Usage (0x02)
+            0x81, 0x02,       //   Input (Data, Variable, Absolute)
+            0x09, 0x03,       //   Usage (0x03)
+            0xB1, 0x02,       //   Feature (Data, Variable, Absolute)
+            0xC0,             // End Collection
+        ];
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes64, OutBytes64, ReportSingle>::new(DESCRIPTOR)
+                    .unwrap()
+                    .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Set feature report, read by the application with read_report
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::In,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::SetReport.into(),
+                    value: u16::from(u8::from(ReportType::Feature)) << 8,
+                    index: 0x0,
+                    length: 0x4,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+        usb_dev.poll(&mut [&mut hid]);
+        manager.host_write_control_out(&[1, 2, 3, 4]);
+        usb_dev.poll(&mut [&mut hid]);
+        assert!(!manager.take_control_stalled());
+
+        let interface = hid.device::<Interface<_, InBytes64, OutBytes64, ReportSingle>, _>();
+        let mut data = [0; 64];
+        assert_eq!(interface.read_report(&mut data), Ok(4));
+        assert_eq!(data[..4], [1, 2, 3, 4]);
+
+        // Get feature report, answered with the report written by the application
+        interface.write_report(&[5, 6, 7, 8]).unwrap();
+        assert_eq!(manager.host_read_in(), [5, 6, 7, 8]);
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::GetReport.into(),
+                    value: u16::from(u8::from(ReportType::Feature)) << 8,
+                    index: 0x0,
+                    length: 0x4,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        assert!(!manager.take_control_stalled());
+        assert_eq!(manager.host_read_in(), [5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn joystick_calibration_is_set_by_host() {
+        static DESCRIPTOR: JoystickDescriptor =
+            JoystickDescriptor::with_calibration_report(JoystickLayout {
+                axes: &[JoystickAxis::X, JoystickAxis::Y],
+                buttons: 8,
+                hats: 0,
+            });
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                ConfigurableJoystickConfig::from_descriptor(&DESCRIPTOR)
+                    .with_calibrations(&[AxisCalibration::new(0, 100, 200).with_deadzone(10)]),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let joystick = hid.device::<ConfigurableJoystick<_>, _>();
+        assert_eq!(
+            joystick.set_feature_report(0, &[0; 9]),
+            Err(UsbError::ParseError)
+        );
+        let mut report = [0; 18];
+        AxisCalibration::default()
+            .with_inverted(true)
+            .pack_to_slice(&mut report[9..])
+            .unwrap();
+        report[..9].copy_from_slice(&joystick.calibrations()[0].pack().unwrap());
+        joystick.set_feature_report(0, &report).unwrap();
+        assert_eq!(
+            joystick.calibrations()[1],
+            AxisCalibration::default().with_inverted(true)
+        );
+
+        joystick
+            .write_report(&ConfigurableJoystickReport {
+                buttons: 1,
+                axes: [200, 0x100, 0, 0, 0, 0, 0, 0],
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(manager.host_read_in(), [0x01, 0xFF, 0x7F, 0x00, 0xFF]);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // Get feature report
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::GetReport.into(),
+                    value: u16::from(u8::from(ReportType::Feature)) << 8,
+                    index: 0x0,
+                    length: 18,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let mut data = Vec::new();
+        loop {
+            let read = manager.host_read_in();
+            if read.is_empty() {
+                break;
+            }
+            data.extend_from_slice(&read);
+            assert!(usb_dev.poll(&mut [&mut hid]));
+        }
+        assert_eq!(data, report);
+    }
+
+    #[test]
+    fn touch_frames_are_sent_from_tick_when_blocked() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(MultiTouchConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let touch = hid.device::<MultiTouch<_>, _>();
+        touch
+            .write_contacts(&[TouchPoint { x: 0x100, y: 0x200 }])
+            .unwrap();
+        touch.write_contacts(&[]).unwrap();
+        assert!(touch.is_pending());
+        assert!(matches!(
+            touch.write_contacts(&[]),
+            Err(UsbHidError::WouldBlock)
+        ));
+
+        let mut report = manager.host_read_in();
+        assert_eq!(report.len(), 32);
+        assert_eq!(
+            report[..8],
+            [
+                MULTI_TOUCH_INPUT_REPORT_ID,
+                0x03,
+                0,
+                0x00,
+                0x01,
+                0x00,
+                0x02,
+                0
+            ]
+        );
+        assert_eq!(report[31], 1);
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // Lift-off sent from the tick
+        hid.tick().unwrap();
+        report = manager.host_read_in();
+        assert_eq!(
+            report[..8],
+            [
+                MULTI_TOUCH_INPUT_REPORT_ID,
+                0x00,
+                0,
+                0x00,
+                0x01,
+                0x00,
+                0x02,
+                0
+            ]
+        );
+        assert_eq!(report[31], 1);
+        assert!(!hid.device::<MultiTouch<_>, _>().is_pending());
+    }
+
+    #[test]
+    fn report_strings_are_indexed_from_report_descriptor() {
+        const BUILDER: DescriptorBuilder = DescriptorBuilder::new()
+            .usage_page(0x01)
+            .usage(0x05)
+            .collection(Collection::Application)
+            .usage_page(0x09)
+            .logical_minimum(0)
+            .logical_maximum(1)
+            .report_size(1)
+            .report_count(1)
+            .string_index(0)
+            .usage(0x01)
+            .input(ItemFlags::DATA_VARIABLE_ABSOLUTE)
+            .string_index(1)
+            .usage(0x02)
+            .input(ItemFlags::DATA_VARIABLE_ABSOLUTE)
+            .report_size(6)
+            .input(ItemFlags::PADDING)
+            .end_collection();
+        const DESCRIPTOR: &[u8] = &BUILDER.build::<{ BUILDER.len() }>();
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes64, OutBytes64, ReportSingle>::new(DESCRIPTOR)
+                    .unwrap()
+                    .description("Button box")
+                    .report_strings(&["Start", "Stop"])
+                    .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Get Report descriptor
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: Request::GET_DESCRIPTOR,
+                    value: u16::from(u8::from(DescriptorType::Report)) << 8,
+                    index: 0,
+                    length: 0xFF,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let mut data = Vec::new();
+        loop {
+            let read = manager.host_read_in();
+            if read.is_empty() {
+                break;
+            }
+            data.extend_from_slice(&read);
+            assert!(usb_dev.poll(&mut [&mut hid]));
+        }
+
+        // String indices 1-3 are the device strings and 4 the interface description
+        let mut expected = DESCRIPTOR.to_vec();
+        expected[17] = 5;
+        expected[23] = 6;
+        assert_eq!(data, expected);
+
+        // Get String descriptor of the second report string
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Device as u8,
+                    request: Request::GET_DESCRIPTOR,
+                    value: u16::from(usb_device::descriptor::descriptor_type::STRING) << 8 | 6,
+                    index: 0x0409,
+                    length: 0xFF,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let mut data = Vec::new();
+        loop {
+            let read = manager.host_read_in();
+            if read.is_empty() {
+                break;
+            }
+            data.extend_from_slice(&read);
+            assert!(usb_dev.poll(&mut [&mut hid]));
+        }
+        assert_eq!(data, [10, 3, b'S', 0, b't', 0, b'o', 0, b'p', 0]);
+    }
+
+    #[test]
+    fn country_code_is_in_hid_descriptor() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default().with_country_code(CountryCode::JapanKatakana))
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: Request::GET_DESCRIPTOR,
+                    value: u16::from(u8::from(DescriptorType::Hid)) << 8,
+                    index: 0,
+                    length: 0xFF,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let data = manager.host_read_in();
+        assert_eq!(data[..6], [9, 0x21, 0x11, 0x01, 15, 1]);
+    }
+
+    #[test]
+    fn hid_version_is_in_hid_descriptor() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes8, OutBytes8, ReportSingle>::new(
+                    BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+                )
+                .unwrap()
+                .hid_version(SPEC_VERSION_1_10)
+                .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: Request::GET_DESCRIPTOR,
+                    value: u16::from(u8::from(DescriptorType::Hid)) << 8,
+                    index: 0,
+                    length: 0xFF,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let data = manager.host_read_in();
+        assert_eq!(data[..5], [9, 0x21, 0x10, 0x01, 0]);
+    }
+
+    #[test]
+    fn physical_descriptors_are_served() {
+        const SETS: &[&[u8]] = &[
+            &[0x00, 0x01, 0x00, 0x02, 0x00],
+            &[0x20, 0x02, 0x00, 0x01, 0x00],
+        ];
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes8, OutBytes8, ReportSingle>::new(
+                    BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+                )
+                .unwrap()
+                .physical_descriptor_sets(SETS)
+                .unwrap()
+                .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let mut get_descriptor = |descriptor_type: DescriptorType, index: u8| {
+            manager
+                .host_write_setup(
+                    &UsbRequest {
+                        direction: UsbDirection::In != UsbDirection::Out,
+                        request_type: RequestType::Standard as u8,
+                        recipient: Recipient::Interface as u8,
+                        request: Request::GET_DESCRIPTOR,
+                        value: u16::from(u8::from(descriptor_type)) << 8 | u16::from(index),
+                        index: 0,
+                        length: 0xFF,
+                    }
+                    .pack()
+                    .unwrap(),
+                )
+                .unwrap();
+
+            assert!(usb_dev.poll(&mut [&mut hid]));
+
+            let mut data = Vec::new();
+            loop {
+                let read = manager.host_read_in();
+                if read.is_empty() {
+                    break;
+                }
+                data.extend_from_slice(&read);
+                assert!(usb_dev.poll(&mut [&mut hid]));
+            }
+            data
+        };
+
+        let hid_descriptor = get_descriptor(DescriptorType::Hid, 0);
+        assert_eq!(hid_descriptor.len(), 12);
+        assert_eq!(hid_descriptor[..6], [12, 0x21, 0x11, 0x01, 0, 2]);
+        assert_eq!(hid_descriptor[9..], [0x23, 3, 0]);
+
+        assert_eq!(get_descriptor(DescriptorType::Physical, 0), [2, 5, 0]);
+        assert_eq!(get_descriptor(DescriptorType::Physical, 2), SETS[1]);
+        assert!(get_descriptor(DescriptorType::Physical, 3).is_empty());
+    }
+
+    #[test]
+    fn physical_descriptor_sets_must_be_the_same_length() {
+        let result = InterfaceBuilder::<InBytes8, OutBytes8, ReportSingle>::new(
+            BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+        )
+        .unwrap()
+        .physical_descriptor_sets(&[&[0x00, 0x01, 0x00], &[0x20]]);
+
+        assert!(matches!(
+            result,
+            Err(UsbHidBuilderError::InvalidPhysicalDescriptor)
+        ));
+    }
+
+    #[test]
+    fn interface_descriptions_are_served_as_strings() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default().with_description("Main Keys"))
+            .add_device(
+                InterfaceBuilder::<InBytes64, OutBytes64, ReportSingle>::new(
+                    BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+                )
+                .unwrap()
+                .build()
+                .with_description("Lighting"),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let mut get_string = |index: u16| {
+            manager
+                .host_write_setup(
+                    &UsbRequest {
+                        direction: UsbDirection::In != UsbDirection::Out,
+                        request_type: RequestType::Standard as u8,
+                        recipient: Recipient::Device as u8,
+                        request: Request::GET_DESCRIPTOR,
+                        value: u16::from(usb_device::descriptor::descriptor_type::STRING) << 8
+                            | index,
+                        index: 0x0409,
+                        length: 0xFF,
+                    }
+                    .pack()
+                    .unwrap(),
+                )
+                .unwrap();
+
+            assert!(usb_dev.poll(&mut [&mut hid]));
+
+            let mut data = Vec::new();
+            loop {
+                let read = manager.host_read_in();
+                if read.is_empty() {
+                    break;
+                }
+                data.extend_from_slice(&read);
+                assert!(usb_dev.poll(&mut [&mut hid]));
+            }
+            data
+        };
+
+        // String indices 1-3 are the device strings, followed by one per interface in reverse
+        // order of addition
+        assert_eq!(
+            get_string(5),
+            [
+                20, 3, b'M', 0, b'a', 0, b'i', 0, b'n', 0, b' ', 0, b'K', 0, b'e', 0, b'y', 0,
+                b's', 0
+            ]
+        );
+        assert_eq!(
+            get_string(4),
+            [18, 3, b'L', 0, b'i', 0, b'g', 0, b'h', 0, b't', 0, b'i', 0, b'n', 0, b'g', 0]
+        );
+    }
+
+    #[test]
+    fn strings_are_localized() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes64, OutBytes64, ReportSingle>::new(
+                    BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+                )
+                .unwrap()
+                .description("Light")
+                .report_strings(&["On", "Off"])
+                .localized_strings(LangID::DE_DE, "Licht", &["An"])
+                .unwrap()
+                .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let mut get_string = |index: u16, lang_id: LangID| {
+            manager
+                .host_write_setup(
+                    &UsbRequest {
+                        direction: UsbDirection::In != UsbDirection::Out,
+                        request_type: RequestType::Standard as u8,
+                        recipient: Recipient::Device as u8,
+                        request: Request::GET_DESCRIPTOR,
+                        value: u16::from(usb_device::descriptor::descriptor_type::STRING) << 8
+                            | index,
+                        index: lang_id.into(),
+                        length: 0xFF,
+                    }
+                    .pack()
+                    .unwrap(),
+                )
+                .unwrap();
+
+            assert!(usb_dev.poll(&mut [&mut hid]));
+
+            let mut data = Vec::new();
+            loop {
+                let read = manager.host_read_in();
+                if read.is_empty() {
+                    break;
+                }
+                data.extend_from_slice(&read);
+                assert!(usb_dev.poll(&mut [&mut hid]));
+            }
+            data
+        };
+
+        assert_eq!(
+            get_string(4, LangID::EN_US),
+            [12, 3, b'L', 0, b'i', 0, b'g', 0, b'h', 0, b't', 0]
+        );
+        assert_eq!(
+            get_string(4, LangID::DE_DE),
+            [12, 3, b'L', 0, b'i', 0, b'c', 0, b'h', 0, b't', 0]
+        );
+        assert_eq!(get_string(5, LangID::DE_DE), [6, 3, b'A', 0, b'n', 0]);
+        // Missing localized report strings fall back to the default
+        assert_eq!(
+            get_string(6, LangID::DE_DE),
+            [8, 3, b'O', 0, b'f', 0, b'f', 0]
+        );
+        assert_eq!(get_string(5, LangID::EN_US), [6, 3, b'O', 0, b'n', 0]);
+    }
+
+    #[test]
+    fn localized_strings_are_limited() {
+        let builder = InterfaceBuilder::<InBytes64, OutBytes64, ReportSingle>::new(
+            BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+        )
+        .unwrap()
+        .localized_strings(LangID::DE_DE, "Licht", &[])
+        .unwrap()
+        .localized_strings(LangID::FR_FR, "Lumi\u{e8}re", &[])
+        .unwrap()
+        .localized_strings(LangID::ES_ES_TRADNL, "Luz", &[])
+        .unwrap()
+        // Replacing a language doesn't take another slot
+        .localized_strings(LangID::DE_DE, "Beleuchtung", &[])
+        .unwrap();
+
+        assert!(matches!(
+            builder.localized_strings(LangID::IT_IT, "Luce", &[]),
+            Err(UsbHidBuilderError::TooManyLocalizedStrings)
+        ));
+    }
+
+    #[test]
+    fn selected_report_descriptor_is_exposed() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes64, OutBytes64, ReportSingle>::with_static_descriptor(
+                    NKRO_BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+                )
+                .unwrap()
+                .alternate_report_descriptor(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                .unwrap()
+                .build(),
+            )
+            .build(&usb_alloc);
+
+        let interface = hid.device::<Interface<_, InBytes64, OutBytes64, ReportSingle>, _>();
+        assert_eq!(
+            interface.select_report_descriptor(2),
+            Err(UsbError::Unsupported)
+        );
+        interface.select_report_descriptor(1).unwrap();
+        assert_eq!(interface.report_descriptor_index(), 1);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let mut get_descriptor = |descriptor_type: DescriptorType| {
+            manager
+                .host_write_setup(
+                    &UsbRequest {
+                        direction: UsbDirection::In != UsbDirection::Out,
+                        request_type: RequestType::Standard as u8,
+                        recipient: Recipient::Interface as u8,
+                        request: Request::GET_DESCRIPTOR,
+                        value: u16::from(u8::from(descriptor_type)) << 8,
+                        index: 0,
+                        length: 0xFF,
+                    }
+                    .pack()
+                    .unwrap(),
+                )
+                .unwrap();
+
+            assert!(usb_dev.poll(&mut [&mut hid]));
+
+            let mut data = Vec::new();
+            loop {
+                let read = manager.host_read_in();
+                if read.is_empty() {
+                    break;
+                }
+                data.extend_from_slice(&read);
+                assert!(usb_dev.poll(&mut [&mut hid]));
+            }
+            data
+        };
+
+        let hid_descriptor = get_descriptor(DescriptorType::Hid);
+        assert_eq!(
+            u16::from_le_bytes([hid_descriptor[7], hid_descriptor[8]]),
+            u16::try_from(BOOT_KEYBOARD_REPORT_DESCRIPTOR.len()).unwrap()
+        );
+        assert_eq!(
+            get_descriptor(DescriptorType::Report),
+            BOOT_KEYBOARD_REPORT_DESCRIPTOR
+        );
+    }
+
+    #[test]
+    fn in_and_out_endpoints_have_independent_poll_intervals() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                BootKeyboardConfig::default()
+                    .with_poll_intervals(
+                        MillisDurationU32::millis(1),
+                        MillisDurationU32::millis(10),
+                    )
+                    .unwrap(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Get Configuration
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Device as u8,
+                    request: Request::GET_DESCRIPTOR,
+                    value: u16::from(usb_device::descriptor::descriptor_type::CONFIGURATION) << 8,
+                    index: 0,
+                    length: 0xFFFF,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let mut data = Vec::new();
+        loop {
+            let read = manager.host_read_in();
+            if read.is_empty() {
+                break;
+            }
+            data.extend_from_slice(&read);
+            assert!(usb_dev.poll(&mut [&mut hid]));
+        }
+
+        assert_eq!(endpoints(&data), [(0x80, 1), (0x00, 10)]);
+    }
+
+    #[test]
+    fn high_speed_endpoints_are_described_in_microframes() {
+        type Builder<'a> = InterfaceBuilder<'a, InBytes512, OutBytes8, ReportSingle>;
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                BootKeyboardConfig::default()
+                    .with_poll_intervals(
+                        MillisDurationU32::millis(1),
+                        MillisDurationU32::millis(10),
+                    )
+                    .unwrap()
+                    .high_speed(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Get Configuration
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Device as u8,
+                    request: Request::GET_DESCRIPTOR,
+                    value: u16::from(usb_device::descriptor::descriptor_type::CONFIGURATION) << 8,
+                    index: 0,
+                    length: 0xFFFF,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let mut data = Vec::new();
+        loop {
+            let read = manager.host_read_in();
+            if read.is_empty() {
+                break;
+            }
+            data.extend_from_slice(&read);
+            assert!(usb_dev.poll(&mut [&mut hid]));
+        }
+
+        // 8 microframes is 2^(4-1), 80 microframes is rounded down to 2^(7-1)
+        assert_eq!(endpoints(&data), [(0x80, 4), (0x00, 7)]);
+
+        // The bus speed is checked on allocation, so can be set after the max packet sizes
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+        let result = UsbHidClassBuilder::new()
+            .add_device(
+                Builder::new(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                    .unwrap()
+                    .in_max_packet_size(512)
+                    .unwrap()
+                    .build(),
+            )
+            .try_build(&usb_alloc);
+        assert!(matches!(result, Err(UsbHidBuilderError::InvalidPacketSize)));
+        assert!(UsbHidClassBuilder::new()
+            .add_device(
+                Builder::new(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                    .unwrap()
+                    .in_max_packet_size(512)
+                    .unwrap()
+                    .high_speed()
+                    .build(),
+            )
+            .try_build(&usb_alloc)
+            .is_ok());
+    }
+
+    #[test]
+    fn max_packet_sizes_are_validated_against_reports() {
+        type Builder<'a> = InterfaceBuilder<'a, InBytes64, OutBytes8, ReportSingle>;
+        type SmallBuilder<'a> = InterfaceBuilder<'a, InBytes16, OutBytes8, ReportSingle>;
+
+        assert!(Builder::new(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+            .unwrap()
+            .in_max_packet_size(8)
+            .and_then(|b| b.out_max_packet_size(8))
+            .is_ok());
+        assert_eq!(
+            Builder::new(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                .unwrap()
+                .in_max_packet_size(0)
+                .err(),
+            Some(UsbHidBuilderError::InvalidPacketSize)
+        );
+        assert_eq!(
+            Builder::new(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                .unwrap()
+                .out_max_packet_size(16)
+                .err(),
+            Some(UsbHidBuilderError::InvalidPacketSize)
+        );
+        // Reports longer than a packet are written a packet at a time
+        assert!(
+            Builder::with_static_descriptor(NKRO_BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                .unwrap()
+                .in_max_packet_size(16)
+                .is_ok()
+        );
+        assert_eq!(
+            SmallBuilder::with_static_descriptor(NKRO_BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                .unwrap()
+                .in_max_packet_size(16)
+                .err(),
+            Some(UsbHidBuilderError::ReportLengthOverflow)
+        );
+        assert_eq!(
+            SmallBuilder::new(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                .unwrap()
+                .in_max_packet_size(8)
+                .and_then(
+                    |b| b.alternate_static_report_descriptor(NKRO_BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                )
+                .err(),
+            Some(UsbHidBuilderError::ReportLengthOverflow)
+        );
+    }
+
+    #[test]
+    fn endpoints_are_in_alternate_setting_one() {
+        type Device<'a> = Interface<'a, TestUsbBus<'a>, InBytes8, OutBytes8, ReportSingle>;
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes8, OutBytes8, ReportSingle>::new(
+                    BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+                )
+                .unwrap()
+                .endpointless_alternate_setting()
+                .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Get Configuration
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Device as u8,
+                    request: Request::GET_DESCRIPTOR,
+                    value: u16::from(usb_device::descriptor::descriptor_type::CONFIGURATION) << 8,
+                    index: 0,
+                    length: 0xFFFF,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let mut data = Vec::new();
+        loop {
+            let read = manager.host_read_in();
+            if read.is_empty() {
+                break;
+            }
+            data.extend_from_slice(&read);
+            assert!(usb_dev.poll(&mut [&mut hid]));
+        }
+
+        // Interface and endpoint descriptors, by descriptor type and alternate setting
+        let mut descriptors = Vec::new();
+        let mut descriptor = data.as_slice();
+        while let [len, descriptor_type, ..] = *descriptor {
+            match descriptor_type {
+                0x04 => descriptors.push((descriptor_type, descriptor[3])),
+                0x05 => descriptors.push((descriptor_type, 0)),
+                _ => {}
+            }
+            descriptor = &descriptor[usize::from(len)..];
+        }
+        assert_eq!(descriptors, [(0x04, 0), (0x04, 1), (0x05, 0)]);
+
+        // Only the control pipe is used in alternate setting 0
+        let device = hid.device::<Device, _>();
+        assert_eq!(device.write_report(&[0; 8]), Ok(8));
+        assert!(manager.host_read_in().is_empty());
+
+        // Set Interface
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: false,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: Request::SET_INTERFACE,
+                    value: 1,
+                    index: 0,
+                    length: 0,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let device = hid.device::<Device, _>();
+        assert_eq!(device.alternate_setting(), 1);
+        assert_eq!(device.write_report(&[0, 0, 4, 0, 0, 0, 0, 0]), Ok(8));
+        assert_eq!(manager.host_read_in(), [0, 0, 4, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn reports_are_exchanged_over_the_control_pipe_without_endpoints() {
+        type Device<'a> = Interface<'a, TestUsbBus<'a>, InBytes8, OutBytes8, ReportSingle>;
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes8, OutBytes8, ReportSingle>::new(
+                    BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+                )
+                .unwrap()
+                .without_endpoints()
+                .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Get Configuration
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Device as u8,
+                    request: Request::GET_DESCRIPTOR,
+                    value: u16::from(usb_device::descriptor::descriptor_type::CONFIGURATION) << 8,
+                    index: 0,
+                    length: 0xFFFF,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let mut data = Vec::new();
+        loop {
+            let read = manager.host_read_in();
+            if read.is_empty() {
+                break;
+            }
+            data.extend_from_slice(&read);
+            assert!(usb_dev.poll(&mut [&mut hid]));
+        }
+        assert!(endpoints(&data).is_empty());
+
+        let device = hid.device::<Device, _>();
+        assert_eq!(device.write_report(&[0, 0, 4, 0, 0, 0, 0, 0]), Ok(8));
+
+        // Get input report
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::GetReport.into(),
+                    value: u16::from(u8::from(ReportType::Input)) << 8,
+                    index: 0x0,
+                    length: 0x8,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        assert_eq!(manager.host_read_in(), [0, 0, 4, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn allocated_interfaces_are_listed() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default().without_out_endpoint())
+            .add_device(
+                InterfaceBuilder::<InBytes8, OutBytes8, ReportSingle>::new(
+                    BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+                )
+                .unwrap()
+                .without_endpoints()
+                .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut interfaces = Vec::new();
+        hid.for_each_interface_info(|info| interfaces.push(info));
+
+        // Devices are allocated in reverse order of addition
+        assert_eq!(
+            interfaces,
+            [
+                InterfaceInfo {
+                    interface_number: 0,
+                    in_endpoint: None,
+                    out_endpoint: None,
+                    diagnostics: InterfaceDiagnostics::default(),
+                },
+                InterfaceInfo {
+                    interface_number: 1,
+                    in_endpoint: Some(EndpointInfo {
+                        address: EndpointAddress::from_parts(1, UsbDirection::In),
+                        max_packet_size: 8,
+                        interval: 10,
+                    }),
+                    out_endpoint: None,
+                    diagnostics: InterfaceDiagnostics::default(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn interfaces_are_numbered_in_order() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        // The keyboard is allocated second, but numbered first
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .add_device(WheelMouseConfig::default())
+            .add_device(ConsumerControlConfig::default())
+            .interface_order(&[2])
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001)).build();
+
+        let mut interfaces = Vec::new();
+        hid.for_each_interface_info(|info| interfaces.push(info.interface_number));
+        assert_eq!(interfaces, [1, 2, 0]);
+
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Device as u8,
+                    request: Request::GET_DESCRIPTOR,
+                    value: u16::from(usb_device::descriptor::descriptor_type::CONFIGURATION) << 8,
+                    index: 0,
+                    length: 0xFFFF,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let mut data = Vec::new();
+        loop {
+            let read = manager.host_read_in();
+            if read.is_empty() {
+                break;
+            }
+            data.extend_from_slice(&read);
+            assert!(usb_dev.poll(&mut [&mut hid]));
+        }
+
+        // Interface number, subclass and protocol of each interface descriptor, in order
+        let mut descriptor = data.as_slice();
+        let mut interfaces = Vec::new();
+        while let [len, descriptor_type, ..] = *descriptor {
+            if descriptor_type == 0x04 {
+                interfaces.push([descriptor[2], descriptor[6], descriptor[7]]);
+            }
+            descriptor = &descriptor[usize::from(len)..];
+        }
+        assert_eq!(interfaces, [[0, 1, 1], [1, 0, 0], [2, 1, 2]]);
+    }
+
+    #[test]
+    fn invalid_interface_orders_are_rejected() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        for order in [&[2][..], &[1, 1]] {
+            let result = UsbHidClassBuilder::new()
+                .add_device(BootKeyboardConfig::default().without_out_endpoint())
+                .add_device(WheelMouseConfig::default())
+                .interface_order(order)
+                .try_build(&usb_alloc);
+            assert!(matches!(
+                result,
+                Err(UsbHidBuilderError::InvalidInterfaceOrder)
+            ));
+        }
+    }
+
+    #[test]
+    fn devices_are_built_from_tuples() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid: UsbHidClass<_, (BootKeyboard<_>, WheelMouse<_>, ConsumerControl<_>)> =
+            UsbHidClassBuilder::new()
+                .devices((
+                    BootKeyboardConfig::default(),
+                    WheelMouseConfig::default(),
+                    ConsumerControlConfig::default(),
+                ))
+                .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Devices are allocated in tuple order
+        let mut interfaces = Vec::new();
+        hid.for_each_interface_info(|info| interfaces.push(info.interface_number));
+        assert_eq!(interfaces, [0, 1, 2]);
+
+        let (keyboard, mouse, _) = hid.devices_mut();
+        keyboard.write_report([Keyboard::A]).unwrap();
+        assert_eq!(manager.host_read_in(), [0, 0, 0x04, 0, 0, 0, 0, 0]);
+        mouse
+            .write_report(&WheelMouseReport {
+                x: 1,
+                ..WheelMouseReport::default()
+            })
+            .unwrap();
+        assert_eq!(manager.host_read_in(), [0, 1, 0, 0, 0]);
+
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::GetProtocol as u8,
+                    value: 0,
+                    index: 1,
+                    length: 1,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        assert_eq!(manager.host_read_in(), [HidProtocol::Report as u8]);
+    }
+
+    #[test]
+    fn devices_are_built_from_collections() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid: UsbHidClass<_, [ConsumerControl<_>; 3]> = UsbHidClassBuilder::new()
+            .devices(core::array::from_fn(|_| ConsumerControlConfig::default()))
+            .build(&usb_alloc);
+        let mut vec_hid: UsbHidClass<_, heapless::Vec<ConsumerControl<_>, 4>> =
+            UsbHidClassBuilder::new()
+                .devices(
+                    [
+                        ConsumerControlConfig::default(),
+                        ConsumerControlConfig::default(),
+                    ]
+                    .into_iter()
+                    .collect::<heapless::Vec<_, 4>>(),
+                )
+                .build(&usb_alloc);
+
+        let _usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Devices are allocated in collection order
+        let mut interfaces = Vec::new();
+        hid.for_each_interface_info(|info| interfaces.push(info.interface_number));
+        vec_hid.for_each_interface_info(|info| interfaces.push(info.interface_number));
+        assert_eq!(interfaces, [0, 1, 2, 3, 4]);
+
+        let report = MultipleConsumerReport {
+            codes: [Consumer::Mute; 4],
+        };
+        hid.devices_mut()[2].write_report(&report).unwrap();
+        let mut sent = Vec::new();
+        hid.for_each_interface_info(|info| sent.push(info.diagnostics.input_reports_sent));
+        assert_eq!(sent, [0, 0, 1]);
+        assert_eq!(vec_hid.devices_mut().len(), 2);
+    }
+
+    #[test]
+    fn ms_os_20_descriptors_are_served() {
+        /// Read the response to an IN control request
+        fn control_read<'m>(
+            manager: &UsbTestManager,
+            usb_dev: &mut UsbDevice<'_, TestUsbBus<'m>>,
+            hid: &mut dyn UsbClass<TestUsbBus<'m>>,
+            request: UsbRequest,
+        ) -> Vec<u8> {
+            manager.host_write_setup(&request.pack().unwrap()).unwrap();
+            assert!(usb_dev.poll(&mut [hid]));
+
+            let mut data = Vec::new();
+            loop {
+                let read = manager.host_read_in();
+                if read.is_empty() {
+                    break;
+                }
+                data.extend_from_slice(&read);
+                assert!(usb_dev.poll(&mut [hid]));
+            }
+            data
+        }
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .devices((
+                BootKeyboardConfig::default(),
+                RawHidConfig::default().with_ms_os_20(MsOs20Function::new().with_winusb()),
+            ))
+            .ms_os_20_descriptors(0x21)
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .usb_rev(UsbRev::Usb210)
+            .build();
+
+        let bos = control_read(
+            &manager,
+            &mut usb_dev,
+            &mut hid,
+            UsbRequest {
+                direction: UsbDirection::In != UsbDirection::Out,
+                request_type: RequestType::Standard as u8,
+                recipient: Recipient::Device as u8,
+                request: Request::GET_DESCRIPTOR,
+                value: u16::from(usb_device::descriptor::descriptor_type::BOS) << 8,
+                index: 0,
+                length: 0xFF,
+            },
+        );
+        // BOS descriptor with the USB 2.0 extension written by `usb-device`
+        assert_eq!(bos[..5], [0x05, 0x0F, 0x28, 0x00, 0x02]);
+        // Platform capability, with the length of the descriptor set and the vendor code
+        assert_eq!(bos[12..16], [0x1C, 0x10, 0x05, 0x00]);
+        assert_eq!(bos[36..], [0x2E, 0x00, 0x21, 0x00]);
+
+        let set = control_read(
+            &manager,
+            &mut usb_dev,
+            &mut hid,
+            UsbRequest {
+                direction: UsbDirection::In != UsbDirection::Out,
+                request_type: RequestType::Vendor as u8,
+                recipient: Recipient::Device as u8,
+                request: 0x21,
+                value: 0,
+                index: MS_OS_20_DESCRIPTOR_INDEX,
+                length: 0x2E,
+            },
+        );
+        assert_eq!(set.len(), 0x2E);
+        // Function subset of the raw HID interface, binding WinUSB to it
+        assert_eq!(set[18..24], [0x08, 0x00, 0x02, 0x00, 0x01, 0x00]);
+        assert_eq!(set[30..38], *b"WINUSB\0\0");
+    }
+
+    #[test]
+    fn interfaces_are_reset_by_set_configuration() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::In,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::SetProtocol as u8,
+                    value: HidProtocol::Boot as u16,
+                    index: 0,
+                    length: 0,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        assert_eq!(
+            hid.device::<BootKeyboard<_>, _>()
+                .interface()
+                .get_protocol(),
+            HidProtocol::Boot
+        );
+
+        for configuration in [CONFIGURATION_NONE, CONFIGURATION_VALUE] {
+            hid.device::<BootKeyboard<_>, _>()
+                .interface()
+                .set_protocol(HidProtocol::Boot);
+
+            manager
+                .host_write_setup(
+                    &UsbRequest {
+                        direction: UsbDirection::In != UsbDirection::In,
+                        request_type: RequestType::Standard as u8,
+                        recipient: Recipient::Device as u8,
+                        request: Request::SET_CONFIGURATION,
+                        value: u16::from(configuration),
+                        index: 0,
+                        length: 0,
+                    }
+                    .pack()
+                    .unwrap(),
+                )
+                .unwrap();
+            assert!(usb_dev.poll(&mut [&mut hid]));
+            assert_eq!(
+                hid.device::<BootKeyboard<_>, _>()
+                    .interface()
+                    .get_protocol(),
+                HidProtocol::Report
+            );
+        }
+    }
+
+    #[test]
+    fn bus_reset_is_notified() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        assert!(!hid.take_bus_reset());
+
+        manager.host_reset();
+        usb_dev.poll(&mut [&mut hid]);
+        assert_eq!(usb_dev.state(), UsbDeviceState::Default);
+        assert!(hid.take_bus_reset());
+        assert!(!hid.take_bus_reset());
+
+        usb_dev.poll(&mut [&mut hid]);
+        assert!(!hid.take_bus_reset());
+    }
+
+    #[test]
+    fn poll_events_are_summarised() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let set_request = |request: HidRequest, value: u16| {
+            manager
+                .host_write_setup(
+                    &UsbRequest {
+                        direction: UsbDirection::In != UsbDirection::In,
+                        request_type: RequestType::Class as u8,
+                        recipient: Recipient::Interface as u8,
+                        request: request as u8,
+                        value,
+                        index: 0,
+                        length: 0,
+                    }
+                    .pack()
+                    .unwrap(),
+                )
+                .unwrap();
+        };
+
+        // Only changes of protocol and idle rate are notified
+        for (changed, value) in [(true, 0), (false, 0)] {
+            set_request(HidRequest::SetProtocol, value);
+            assert!(usb_dev.poll(&mut [&mut hid]));
+            let events = hid.take_poll_events();
+            assert_eq!(events.protocol_changed.contains(0), changed);
+            assert!(events.idle_changed.is_empty());
+        }
+        for (changed, value) in [(true, 0x0A00), (false, 0x0A00)] {
+            set_request(HidRequest::SetIdle, value);
+            assert!(usb_dev.poll(&mut [&mut hid]));
+            let events = hid.take_poll_events();
+            assert_eq!(events.idle_changed.contains(0), changed);
+            assert!(events.protocol_changed.is_empty());
+        }
+
+        manager.host_write_out(&[0x01]).unwrap();
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        let events = hid.take_poll_events();
+        assert_eq!(events.out_received.iter().collect::<Vec<_>>(), [0]);
+        assert!(!events.bus_reset);
+        assert!(hid.take_poll_events().out_received.is_empty());
+
+        hid.device::<BootKeyboard<_>, _>()
+            .write_report([Keyboard::A])
+            .unwrap();
+        assert_eq!(manager.host_read_in(), [0, 0, 4, 0, 0, 0, 0, 0]);
+        usb_dev.poll(&mut [&mut hid]);
+        assert!(hid.take_poll_events().in_complete.contains(0));
+
+        manager.host_reset();
+        usb_dev.poll(&mut [&mut hid]);
+        assert!(hid.take_poll_events().bus_reset);
+        assert!(hid.take_poll_events().is_empty());
+    }
+
+    #[test]
+    fn interface_diagnostics_are_counted() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        keyboard.write_report([Keyboard::A]).unwrap();
+        assert!(keyboard.write_report([Keyboard::B]).is_err());
+
+        for (request, value) in [
+            (HidRequest::SetIdle, 0),
+            (HidRequest::SetProtocol, HidProtocol::Boot as u16),
+            (HidRequest::SetProtocol, HidProtocol::Boot as u16),
+        ] {
+            manager
+                .host_write_setup(
+                    &UsbRequest {
+                        direction: UsbDirection::In != UsbDirection::In,
+                        request_type: RequestType::Class as u8,
+                        recipient: Recipient::Interface as u8,
+                        request: request.into(),
+                        value,
+                        index: 0x0,
+                        length: 0x0,
+                    }
+                    .pack()
+                    .unwrap(),
+                )
+                .unwrap();
+            assert!(usb_dev.poll(&mut [&mut hid]));
+        }
+
+        manager.host_write_out(&[0x01]).unwrap();
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        let keyboard = hid.device::<BootKeyboard<_>, _>();
+        keyboard.read_report().unwrap();
+
+        assert_eq!(
+            keyboard.interface().diagnostics(),
+            InterfaceDiagnostics {
+                input_reports_sent: 1,
+                input_reports_blocked: 1,
+                input_reports_dropped: 0,
+                output_reports_received: 1,
+                protocol_changes: 1,
+                set_idle_requests: 1,
+            }
+        );
+        keyboard.interface().clear_diagnostics();
+        assert_eq!(
+            keyboard.interface().diagnostics(),
+            InterfaceDiagnostics::default()
+        );
+    }
+
+    #[test]
+    fn endpoint_allocation_failure_is_reported() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        // Each keyboard has an IN and an OUT endpoint, the bus has 7
+        let result = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default())
+            .add_device(BootKeyboardConfig::default())
+            .add_device(BootKeyboardConfig::default())
+            .add_device(BootKeyboardConfig::default())
+            .try_build(&usb_alloc);
+
+        assert!(matches!(
+            result,
+            Err(UsbHidBuilderError::EndpointAllocation(
+                UsbError::EndpointOverflow
+            ))
+        ));
+    }
+
+    #[test]
+    fn boot_report_descriptor_is_exposed_in_boot_protocol() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(NKROBootKeyboardConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Set protocol to boot
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::In,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::SetProtocol.into(),
+                    value: HidProtocol::Boot as u16,
+                    index: 0x0,
+                    length: 0x0,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // Get Report descriptor
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: Request::GET_DESCRIPTOR,
+                    value: u16::from(u8::from(DescriptorType::Report)) << 8,
+                    index: 0,
+                    length: 0xFF,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let mut data = Vec::new();
+        loop {
+            let read = manager.host_read_in();
+            if read.is_empty() {
+                break;
+            }
+            data.extend_from_slice(&read);
+            assert!(usb_dev.poll(&mut [&mut hid]));
+        }
+        assert_eq!(data, BOOT_KEYBOARD_REPORT_DESCRIPTOR);
+
+        // Reports are sent in the boot format
+        let keyboard = hid.device::<NKROBootKeyboard<_>, _>();
+        keyboard.write_report([Keyboard::A]).unwrap();
+        assert_eq!(manager.host_read_in(), [0, 0, 4, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn out_endpoint_is_optional() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(BootKeyboardConfig::default().without_out_endpoint())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // Get Configuration
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Device as u8,
+                    request: Request::GET_DESCRIPTOR,
+                    value: u16::from(usb_device::descriptor::descriptor_type::CONFIGURATION) << 8,
+                    index: 0,
+                    length: 0xFFFF,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let mut data = Vec::new();
+        loop {
+            let read = manager.host_read_in();
+            if read.is_empty() {
+                break;
+            }
+            data.extend_from_slice(&read);
+            assert!(usb_dev.poll(&mut [&mut hid]));
+        }
+
+        assert_eq!(endpoints(&data), [(0x80, 10)]);
+    }
+
+    #[test]
+    fn in_reports_are_queued_while_endpoint_is_busy() {
+        type Device<'a> = Interface<'a, TestUsbBus<'a>, InBytes8, OutBytes8, ReportSingle, 0, 2>;
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes8, OutBytes8, ReportSingle>::new(
+                    BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+                )
+                .unwrap()
+                .in_report_queue::<2>()
+                .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let device = hid.device::<Device, _>();
+        for key in 1..=3 {
+            assert_eq!(device.write_report(&[0, 0, key, 0, 0, 0, 0, 0]), Ok(8));
+        }
+        assert_eq!(
+            device.write_report(&[0, 0, 4, 0, 0, 0, 0, 0]),
+            Err(UsbError::WouldBlock)
+        );
+
+        // Queued reports are written as the host reads the reports before them
+        for key in 1..=3 {
+            assert_eq!(manager.host_read_in(), [0, 0, key, 0, 0, 0, 0, 0]);
+            usb_dev.poll(&mut [&mut hid]);
+        }
+        assert!(manager.host_read_in().is_empty());
+    }
+
+    #[test]
+    fn queued_reports_are_dropped_when_endpoint_halt_is_cleared() {
+        type Device<'a> = Interface<'a, TestUsbBus<'a>, InBytes8, OutBytes8, ReportSingle, 0, 2>;
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes8, OutBytes8, ReportSingle>::new(
+                    BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+                )
+                .unwrap()
+                .in_report_queue::<2>()
+                .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let device = hid.device::<Device, _>();
+        for key in 1..=3 {
+            assert_eq!(device.write_report(&[0, 0, key, 0, 0, 0, 0, 0]), Ok(8));
+        }
+        let in_endpoint = device.info().in_endpoint.unwrap().address;
+
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::In,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Endpoint as u8,
+                    request: Request::CLEAR_FEATURE,
+                    value: Request::FEATURE_ENDPOINT_HALT,
+                    index: u16::from(u8::from(in_endpoint)),
+                    length: 0,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // Only the report already written to the endpoint is read
+        assert_eq!(manager.host_read_in(), [0, 0, 1, 0, 0, 0, 0, 0]);
+        usb_dev.poll(&mut [&mut hid]);
+        assert!(manager.host_read_in().is_empty());
+        assert_eq!(
+            hid.device::<Device, _>()
+                .info()
+                .diagnostics
+                .input_reports_dropped,
+            2
+        );
+    }
+
+    #[test]
+    fn queued_reports_are_dropped_when_alternate_setting_changes() {
+        type Device<'a> = Interface<'a, TestUsbBus<'a>, InBytes8, OutBytes8, ReportSingle, 0, 2>;
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes8, OutBytes8, ReportSingle>::new(
+                    BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+                )
+                .unwrap()
+                .endpointless_alternate_setting()
+                .in_report_queue::<2>()
+                .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let set_interface = |alternate_setting| {
+            UsbRequest {
+                direction: false,
+                request_type: RequestType::Standard as u8,
+                recipient: Recipient::Interface as u8,
+                request: Request::SET_INTERFACE,
+                value: alternate_setting,
+                index: 0,
+                length: 0,
+            }
+            .pack()
+            .unwrap()
+        };
+
+        manager.host_write_setup(&set_interface(1)).unwrap();
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        let device = hid.device::<Device, _>();
+        for key in 1..=3 {
+            assert_eq!(device.write_report(&[0, 0, key, 0, 0, 0, 0, 0]), Ok(8));
+        }
+
+        // Reselecting the selected setting keeps the queue
+        manager.host_write_setup(&set_interface(1)).unwrap();
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        assert_eq!(
+            hid.device::<Device, _>()
+                .info()
+                .diagnostics
+                .input_reports_dropped,
+            0
+        );
+
+        manager.host_write_setup(&set_interface(0)).unwrap();
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // Only the report already written to the endpoint is read
+        assert_eq!(manager.host_read_in(), [0, 0, 1, 0, 0, 0, 0, 0]);
+        usb_dev.poll(&mut [&mut hid]);
+        assert!(manager.host_read_in().is_empty());
+        assert_eq!(
+            hid.device::<Device, _>()
+                .info()
+                .diagnostics
+                .input_reports_dropped,
+            2
+        );
+    }
+
+    #[test]
+    fn managed_reports_are_resent_when_endpoint_halt_is_cleared() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(GamepadConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let report = GamepadReport::default();
+        let gamepad = hid.device::<Gamepad<_>, _>();
+        gamepad.write_report(&report).unwrap();
+        let in_endpoint = gamepad.interface().info().in_endpoint.unwrap().address;
+        let sent = manager.host_read_in();
+        assert!(!sent.is_empty());
+
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::In,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Endpoint as u8,
+                    request: Request::CLEAR_FEATURE,
+                    value: Request::FEATURE_ENDPOINT_HALT,
+                    index: u16::from(u8::from(in_endpoint)),
+                    length: 0,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        // The last report is resent although it's a duplicate
+        hid.tick().unwrap();
+        assert_eq!(manager.host_read_in(), sent);
+        hid.tick().unwrap();
+        assert!(manager.host_read_in().is_empty());
+    }
+
+    #[test]
+    fn wrapped_managed_reports_are_resent_when_endpoint_halt_is_cleared() {
+        type Device<'a> = FeatureReports<Gamepad<'a, TestUsbBus<'a>>, 1, 8>;
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(FeatureReportsConfig::<_, 1, 8>::new(
+                GamepadConfig::default(),
+            ))
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let device = hid.device::<Device, _>();
+        device
+            .device()
+            .write_report(&GamepadReport::default())
+            .unwrap();
+        let in_endpoint = device.interface().info().in_endpoint.unwrap().address;
+        let sent = manager.host_read_in();
+        assert!(!sent.is_empty());
+
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::In,
+                    request_type: RequestType::Standard as u8,
+                    recipient: Recipient::Endpoint as u8,
+                    request: Request::CLEAR_FEATURE,
+                    value: Request::FEATURE_ENDPOINT_HALT,
+                    index: u16::from(u8::from(in_endpoint)),
+                    length: 0,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+        assert!(usb_dev.poll(&mut [&mut hid]));
+
+        hid.tick().unwrap();
+        assert_eq!(manager.host_read_in(), sent);
+    }
+
+    #[test]
+    fn reports_longer_than_a_packet_are_split_into_packets() {
+        type Device<'a> = Interface<'a, TestUsbBus<'a>, InBytes32, OutBytes32, ReportSingle>;
+
+        const BUILDER: DescriptorBuilder = DescriptorBuilder::new()
+            .usage_page(0xFF00)
+            .usage(0x01)
+            .collection(Collection::Application)
+            .logical_minimum(0)
+            .logical_maximum(255)
+            .report_size(8)
+            .report_count(20)
+            .usage(0x02)
+            .input(ItemFlags::DATA_VARIABLE_ABSOLUTE)
+            .report_count(16)
+            .usage(0x03)
+            .output(ItemFlags::DATA_VARIABLE_ABSOLUTE)
+            .end_collection();
+        const DESCRIPTOR: &[u8] = &BUILDER.build::<{ BUILDER.len() }>();
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes32, OutBytes32, ReportSingle>::new(DESCRIPTOR)
+                    .unwrap()
+                    .in_max_packet_size(8)
+                    .unwrap()
+                    .out_max_packet_size(8)
+                    .unwrap()
+                    .with_out_endpoint(MillisDurationU32::millis(10))
+                    .unwrap()
+                    .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let report: Vec<u8> = (0..20).collect();
+        let device = hid.device::<Device, _>();
+        assert_eq!(device.write_report(&report), Ok(20));
+        assert_eq!(device.write_report(&report), Err(UsbError::WouldBlock));
+
+        let mut data = manager.host_read_in();
+        for _ in 0..2 {
+            usb_dev.poll(&mut [&mut hid]);
+            data.extend(manager.host_read_in());
+        }
+        assert_eq!(data, report);
+
+        // The report is complete when it has the length the descriptor describes, although
+        // the last packet isn't short
+        let device = hid.device::<Device, _>();
+        let mut data = [0; 32];
+        manager.host_write_out(&[1; 8]).unwrap();
+        assert_eq!(device.read_report(&mut data), Err(UsbError::WouldBlock));
+        manager.host_write_out(&[2; 8]).unwrap();
+        assert_eq!(device.read_report(&mut data), Ok(16));
+        assert_eq!(data[..16], [[1; 8], [2; 8]].concat());
+    }
+
+    #[test]
+    fn magnetic_stripe_reader_swipe_is_split_into_packets() {
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(MagneticStripeReaderConfig::default())
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let tracks = MsrTracks {
+            track_1: b"B4111111111111111^DOE/JOHN^2512",
+            track_2: b"4111111111111111=2512",
+            track_3: &[],
+        };
+        let msr = hid.device::<MagneticStripeReader<_>, _>();
+        msr.write_report(&tracks).unwrap();
+        assert!(matches!(
+            msr.write_report(&tracks),
+            Err(UsbHidError::WouldBlock)
+        ));
+
+        let mut packets = Vec::new();
+        loop {
+            let packet = manager.host_read_in();
+            if packet.is_empty() {
+                break;
+            }
+            packets.push(packet);
+            usb_dev.poll(&mut [&mut hid]);
+        }
+        assert_eq!(
+            packets.iter().map(Vec::len).collect::<Vec<_>>(),
+            [64, 64, 64, 38]
+        );
+
+        let report = packets.concat();
+        assert_eq!(report[..4], [MSR_TRACK_DATA_REPORT_ID, 31, 21, 0]);
+        assert_eq!(report[4..35], *b"B4111111111111111^DOE/JOHN^2512");
+        assert_eq!(report[83..104], *b"4111111111111111=2512");
+
+        // The next swipe is written once the first is sent
+        hid.device::<MagneticStripeReader<_>, _>()
+            .write_report(&tracks)
+            .unwrap();
+    }
+
+    /// Write a report from a device through the class and check it was sent, debug builds also
+    /// check the report against the device's report descriptor
+    macro_rules! write_report {
+        ($config:expr, $device:ty, |$d:ident| $write:expr) => {{
+            let manager = UsbTestManager::default();
+            let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+            let mut hid = UsbHidClassBuilder::new()
+                .add_device($config)
+                .build(&usb_alloc);
+            let _usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001)).build();
+
+            let $d = hid.device::<$device, _>();
+            $write;
+            hid.tick().unwrap();
+            assert!(
+                !manager.host_read_in().is_empty(),
+                "{} wrote no report",
+                stringify!($device)
+            );
+        }};
+    }
+
+    #[test]
+    fn keyboard_devices_write_reports_matching_their_descriptors() {
+        use crate::device::{
+            consumer, keyboard, keyboard_pointer, keypad, media_remote, mouse, system_control,
+            telephony, wireless_radio,
+        };
+
+        init_logging();
+
+        write_report!(
+            keyboard::BootKeyboardConfig::default(),
+            keyboard::BootKeyboard<_>,
+            |d| d.write_report([Keyboard::A]).unwrap()
+        );
+        write_report!(
+            keyboard::NKROBootKeyboardConfig::default(),
+            keyboard::NKROBootKeyboard<_>,
+            |d| d.write_report([Keyboard::A]).unwrap()
+        );
+        write_report!(
+            keyboard::NKROKeyboardConfig::default(),
+            keyboard::NKROKeyboard<_>,
+            |d| d.write_report([Keyboard::A]).unwrap()
+        );
+        write_report!(
+            keyboard_pointer::KeyboardPointerConfig::default(),
+            keyboard_pointer::KeyboardPointer<_>,
+            |d| d.write_keyboard([Keyboard::A]).unwrap()
+        );
+        write_report!(
+            keyboard_pointer::KeyboardPointerConfig::default(),
+            keyboard_pointer::KeyboardPointer<_>,
+            |d| d
+                .write_pointer(&mouse::WheelMouseReport::default())
+                .unwrap()
+        );
+        write_report!(
+            keypad::NumericKeypadConfig::default(),
+            keypad::NumericKeypad<_>,
+            |d| d.write_report([Keyboard::Keypad1]).unwrap()
+        );
+        write_report!(
+            consumer::ConsumerControlConfig::default(),
+            consumer::ConsumerControl<_>,
+            |d| d
+                .write_report(&consumer::MultipleConsumerReport::default())
+                .unwrap()
+        );
+        write_report!(
+            consumer::ConsumerControlFixedConfig::default(),
+            consumer::ConsumerControlFixed<_>,
+            |d| d
+                .write_report(&consumer::FixedFunctionReport::default())
+                .unwrap()
+        );
+        write_report!(
+            media_remote::MediaRemoteConfig::default(),
+            media_remote::MediaRemote<_>,
+            |d| d
+                .write_report(&media_remote::MediaRemoteReport {
+                    consumer: Consumer::PlayPause,
+                    ..Default::default()
+                })
+                .unwrap()
+        );
+        write_report!(
+            system_control::SystemControlConfig::default(),
+            system_control::SystemControl<_>,
+            |d| d
+                .write_report(&system_control::SystemControlReport::default())
+                .unwrap()
+        );
+        write_report!(
+            telephony::HeadsetConfig::default(),
+            telephony::Headset<_>,
+            |d| d
+                .write_report(&telephony::HeadsetReport::default())
+                .unwrap()
+        );
+        write_report!(
+            telephony::SpeakerphoneConfig::default(),
+            telephony::Speakerphone<_>,
+            |d| d
+                .write_report(&telephony::SpeakerphoneReport {
+                    mute: true,
+                    ..Default::default()
+                })
+                .unwrap()
+        );
+        write_report!(
+            wireless_radio::WirelessRadioConfig::default(),
+            wireless_radio::WirelessRadio<_>,
+            |d| d
+                .write_report(&wireless_radio::WirelessRadioReport::default())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn pointing_devices_write_reports_matching_their_descriptors() {
+        use crate::device::{
+            digitizer, eye_tracker, light_gun, mouse, radial_controller, space_mouse, touchpad,
+        };
+
+        init_logging();
+
+        write_report!(
+            mouse::BootMouseConfig::default(),
+            mouse::BootMouse<_>,
+            |d| d.write_report(&mouse::BootMouseReport::default()).unwrap()
+        );
+        write_report!(
+            mouse::WheelMouseConfig::default(),
+            mouse::WheelMouse<_>,
+            |d| d.write_report(&mouse::WheelMouseReport::default()).unwrap()
+        );
+        write_report!(
+            mouse::AbsoluteWheelMouseConfig::default(),
+            mouse::AbsoluteWheelMouse<_>,
+            |d| d
+                .write_report(&mouse::AbsoluteWheelMouseReport::default())
+                .unwrap()
+        );
+        write_report!(
+            mouse::AbsoluteMouseConfig::default(),
+            mouse::AbsoluteMouse<_>,
+            |d| d
+                .write_report(&mouse::AbsoluteMouseReport::default())
+                .unwrap()
+        );
+        write_report!(
+            mouse::HighResolutionWheelMouseConfig::default(),
+            mouse::HighResolutionWheelMouse<_>,
+            |d| d.write_report(&mouse::WheelMouseReport::default()).unwrap()
+        );
+        write_report!(
+            mouse::PrecisionMouseConfig::<12>::default(),
+            mouse::PrecisionMouse<_, 12>,
+            |d| d
+                .write_report(&mouse::PrecisionMouseReport::default())
+                .unwrap()
+        );
+        write_report!(
+            digitizer::MultiTouchConfig::default(),
+            digitizer::MultiTouch<_>,
+            |d| d
+                .write_report(&digitizer::MultiTouchReport::default())
+                .unwrap()
+        );
+        write_report!(digitizer::PenConfig::default(), digitizer::Pen<_>, |d| d
+            .write_report(&digitizer::PenReport::default())
+            .unwrap());
+        write_report!(
+            touchpad::PrecisionTouchpadConfig::default(),
+            touchpad::PrecisionTouchpad<_>,
+            |d| d
+                .write_report(&touchpad::PrecisionTouchpadReport::default())
+                .unwrap()
+        );
+        write_report!(
+            touchpad::PrecisionTouchpadConfig::default(),
+            touchpad::PrecisionTouchpad<_>,
+            |d| d
+                .write_mouse_report(&mouse::BootMouseReport::default())
+                .unwrap()
+        );
+        write_report!(
+            space_mouse::SpaceMouseConfig::default(),
+            space_mouse::SpaceMouse<_>,
+            |d| d
+                .write_motion(&space_mouse::SpaceMouseMotionReport::default())
+                .unwrap()
+        );
+        write_report!(
+            space_mouse::SpaceMouseConfig::default(),
+            space_mouse::SpaceMouse<_>,
+            |d| d.write_buttons(1).unwrap()
+        );
+        write_report!(
+            radial_controller::RadialControllerConfig::default(),
+            radial_controller::RadialController<_>,
+            |d| d
+                .write_report(&radial_controller::RadialControllerReport::default())
+                .unwrap()
+        );
+        write_report!(
+            eye_tracker::EyeTrackerConfig::default(),
+            eye_tracker::EyeTracker<_>,
+            |d| d.write_report(&eye_tracker::GazeReport::default()).unwrap()
+        );
+        write_report!(
+            light_gun::LightGunConfig::default(),
+            light_gun::LightGun<_>,
+            |d| d
+                .write_report(&light_gun::LightGunReport::default())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn game_controllers_write_reports_matching_their_descriptors() {
+        use crate::device::{
+            encoder, force_feedback, gamepad, joystick, racing_wheel, switch, throttle,
+        };
+
+        init_logging();
+
+        write_report!(
+            joystick::JoystickConfig::default(),
+            joystick::Joystick<_>,
+            |d| d
+                .write_report(&joystick::JoystickReport::default())
+                .unwrap()
+        );
+        write_report!(
+            joystick::ConfigurableJoystickConfig::default(),
+            joystick::ConfigurableJoystick<_>,
+            |d| d
+                .write_report(&joystick::ConfigurableJoystickReport::default())
+                .unwrap()
+        );
+        write_report!(
+            gamepad::GamepadConfig::default(),
+            gamepad::Gamepad<_>,
+            |d| d.write_report(&gamepad::GamepadReport::default()).unwrap()
+        );
+        write_report!(
+            gamepad::MotionGamepadConfig::default(),
+            gamepad::MotionGamepad<_>,
+            |d| d
+                .write_report(&gamepad::MotionGamepadReport::default())
+                .unwrap()
+        );
+        write_report!(
+            force_feedback::ForceFeedbackJoystickConfig::default(),
+            force_feedback::ForceFeedbackJoystick<_>,
+            |d| d
+                .write_report(&force_feedback::ForceFeedbackJoystickReport::default())
+                .unwrap()
+        );
+        write_report!(
+            racing_wheel::RacingWheelConfig::default(),
+            racing_wheel::RacingWheel<_>,
+            |d| d
+                .write_report(&racing_wheel::RacingWheelReport::default())
+                .unwrap()
+        );
+        write_report!(
+            racing_wheel::ForceFeedbackRacingWheelConfig::default(),
+            racing_wheel::ForceFeedbackRacingWheel<_>,
+            |d| d
+                .write_report(&racing_wheel::RacingWheelReport::default())
+                .unwrap()
+        );
+        write_report!(
+            throttle::ThrottleQuadrantConfig::default(),
+            throttle::ThrottleQuadrant<_>,
+            |d| d
+                .write_report(&throttle::ThrottleReport::default())
+                .unwrap()
+        );
+        write_report!(
+            switch::SwitchesConfig::default(),
+            switch::Switches<_>,
+            |d| d
+                .write_report(&switch::SwitchReport { buttons: 1 })
+                .unwrap()
+        );
+        write_report!(
+            encoder::RotaryEncodersConfig::default(),
+            encoder::RotaryEncoders<_>,
+            |d| d.rotate(0, 1)
+        );
+    }
+
+    #[test]
+    fn sensors_write_reports_matching_their_descriptors() {
+        use crate::device::{ambient_light, power, presence, scale, sensor};
+
+        init_logging();
+
+        write_report!(
+            ambient_light::AmbientLightSensorConfig::default(),
+            ambient_light::AmbientLightSensor<_>,
+            |d| d
+                .interface()
+                .write_report(
+                    &[
+                        [ambient_light::AMBIENT_LIGHT_REPORT_ID].as_slice(),
+                        &ambient_light::AmbientLightReport::default().pack().unwrap()
+                    ]
+                    .concat()
+                )
+                .unwrap()
+        );
+        write_report!(
+            presence::HumanPresenceSensorConfig::default(),
+            presence::HumanPresenceSensor<_>,
+            |d| d
+                .interface()
+                .write_report(
+                    &[
+                        [presence::HUMAN_PRESENCE_REPORT_ID].as_slice(),
+                        &presence::HumanPresenceReport::default().pack().unwrap()
+                    ]
+                    .concat()
+                )
+                .unwrap()
+        );
+        write_report!(sensor::SensorsConfig::default(), sensor::Sensors<_>, |d| d
+            .interface()
+            .write_report(
+                &[
+                    [sensor::ACCELEROMETER_REPORT_ID].as_slice(),
+                    &sensor::SensorReport::default().pack().unwrap()
+                ]
+                .concat()
+            )
+            .unwrap());
+        write_report!(
+            power::PowerDeviceConfig::default(),
+            power::PowerDevice<_>,
+            |d| d.update(power::BatteryStatus::default())
+        );
+        write_report!(
+            scale::WeighingScaleConfig::default(),
+            scale::WeighingScale<_>,
+            |d| d.write_report(&scale::ScaleReport::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn vendor_devices_write_reports_matching_their_descriptors() {
+        use crate::device::{barcode, braille, ctaphid, fido, msr, raw};
+
+        init_logging();
+
+        write_report!(
+            msr::MagneticStripeReaderConfig::default(),
+            msr::MagneticStripeReader<_>,
+            |d| d.write_report(&msr::MsrTracks::default()).unwrap()
+        );
+        write_report!(
+            barcode::BarcodeScannerConfig::<64>::default(),
+            barcode::BarcodeScanner<_, 64>,
+            |d| d.write_barcode(*b"]A0", &[]).unwrap()
+        );
+        write_report!(
+            braille::BrailleDisplayConfig::default(),
+            braille::BrailleDisplay<_>,
+            |d| d.write_report(&braille::BrailleReport::default()).unwrap()
+        );
+        write_report!(
+            ctaphid::CtapHidConfig::<1024>::default(),
+            ctaphid::CtapHid<_, 1024>,
+            |d| d
+                .write_message(1, ctaphid::CtapHidCommand::Ping, &[])
+                .unwrap()
+        );
+        write_report!(fido::RawFidoConfig::default(), fido::RawFido<_>, |d| d
+            .write_report(&fido::RawFidoReport::default())
+            .unwrap());
+        write_report!(raw::RawHidConfig::default(), raw::RawHid<_>, |d| d
+            .write_report(&[0; 64])
+            .unwrap());
+    }
+
+    #[test]
+    fn out_reports_are_queued() {
+        type Device<'a> = Interface<'a, TestUsbBus<'a>, InBytes8, OutBytes8, ReportSingle, 3>;
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes8, OutBytes8, ReportSingle>::new(
+                    BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+                )
+                .unwrap()
+                .with_out_endpoint(MillisDurationU32::millis(10))
+                .unwrap()
+                .out_report_queue::<3>()
+                .build(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        // The fourth report is left in the endpoint when the queue is full
+        for leds in 1..=4 {
+            manager.host_write_out(&[leds]).unwrap();
+            usb_dev.poll(&mut [&mut hid]);
+        }
+        assert!(manager.has_out_data());
+
+        let device = hid.device::<Device, _>();
+        let mut data = [0; 8];
+        for leds in 1..=4 {
+            assert_eq!(device.read_report(&mut data), Ok(1));
+            assert_eq!(data[0], leds);
+        }
+        assert_eq!(device.read_report(&mut data), Err(UsbError::WouldBlock));
+    }
+
+    #[test]
+    fn registered_feature_reports_are_served_from_buffers() {
+        type Device<'a> = FeatureReports<
+            Interface<'a, TestUsbBus<'a>, InBytes64, OutBytes64, ReportSingle>,
+            2,
+            8,
+        >;
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                FeatureReportsConfig::<_, 2, 8>::new(
+                    InterfaceBuilder::<InBytes64, OutBytes64, ReportSingle>::new(&[])
+                        .unwrap()
+                        .build(),
+                )
+                .report(1, &[1, 0x10, 0x20])
+                .unwrap(),
+            )
+            .build(&usb_alloc);
+
+        let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let device = hid.device::<Device, _>();
+        device.set_feature_report(1, &[1, 0xAA, 0xBB]).unwrap();
+        assert_eq!(
+            device.set_feature_report(1, &[1, 0xAA]),
+            Err(UsbError::ParseError)
+        );
+        assert_eq!(
+            device.read_feature_report(1),
+            Some([1, 0xAA, 0xBB].as_slice())
+        );
+        assert_eq!(device.read_feature_report(1), None);
+        device.write_feature_report(1, &[1, 0x30, 0x40]).unwrap();
+
+        // Get feature report
+        manager
+            .host_write_setup(
+                &UsbRequest {
+                    direction: UsbDirection::In != UsbDirection::Out,
+                    request_type: RequestType::Class as u8,
+                    recipient: Recipient::Interface as u8,
+                    request: HidRequest::GetReport.into(),
+                    value: u16::from(u8::from(ReportType::Feature)) << 8 | 1,
+                    index: 0x0,
+                    length: 0x3,
+                }
+                .pack()
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert!(usb_dev.poll(&mut [&mut hid]));
+        assert_eq!(manager.host_read_in(), [1, 0x30, 0x40]);
+
+        assert_eq!(
+            FeatureReportsConfig::<_, 1, 8>::new(())
+                .report(1, &[1])
+                .and_then(|c| c.report(2, &[2]))
+                .err(),
+            Some(UsbHidBuilderError::TooManyFeatureReports)
+        );
+    }
+
+    const LENGTH_CHECKED_BUILDER: DescriptorBuilder = DescriptorBuilder::new()
+        .usage_page(0x01)
+        .usage(0x06)
+        .collection(Collection::Application)
+        .logical_minimum(0)
+        .logical_maximum(255)
+        .report_size(8)
+        .report_id(1)
+        .report_count(2)
+        .usage_page(0x08)
+        .usage_minimum(0x01)
+        .usage_maximum(0x02)
+        .output(ItemFlags::DATA_VARIABLE_ABSOLUTE)
+        .report_id(2)
+        .report_count(3)
+        .usage_page(0x07)
+        .usage_minimum(0x00)
+        .usage_maximum(0xFF)
+        .input(ItemFlags::DATA)
+        .end_collection();
+    const LENGTH_CHECKED_DESCRIPTOR: &[u8] =
+        &LENGTH_CHECKED_BUILDER.build::<{ LENGTH_CHECKED_BUILDER.len() }>();
+
+    #[test]
+    fn output_reports_are_validated_against_descriptor() {
+        type Device<'a> = Interface<'a, TestUsbBus<'a>, InBytes64, OutBytes64, ReportSingle>;
+
+        init_logging();
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes64, OutBytes64, ReportSingle>::new(
+                    LENGTH_CHECKED_DESCRIPTOR,
+                )
+                .unwrap()
+                .build(),
+            )
+            .build(&usb_alloc);
+
+        let device = hid.device::<Device, _>();
+        assert_eq!(device.set_report(&[1, 0xAA]), Err(UsbError::ParseError));
+        assert_eq!(
+            device.set_report(&[2, 0xAA, 0xBB]),
+            Err(UsbError::ParseError)
+        );
+        assert_eq!(
+            device.set_report(&[3, 0xAA, 0xBB]),
+            Err(UsbError::ParseError)
         );
+        assert_eq!(device.set_report(&[]), Err(UsbError::ParseError));
+
+        // Padded reports are truncated
+        device.set_report(&[1, 0xAA, 0xBB, 0x00, 0x00]).unwrap();
+        let mut data = [0; 8];
+        assert_eq!(device.read_report(&mut data), Ok(3));
+        assert_eq!(data[..3], [1, 0xAA, 0xBB]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "input_report_len_matches")]
+    fn input_reports_of_wrong_length_panic_in_debug_builds() {
+        type Device<'a> = Interface<'a, TestUsbBus<'a>, InBytes64, OutBytes64, ReportSingle>;
+
+        let manager = UsbTestManager::default();
+        let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&manager));
+
+        let mut hid = UsbHidClassBuilder::new()
+            .add_device(
+                InterfaceBuilder::<InBytes64, OutBytes64, ReportSingle>::new(
+                    LENGTH_CHECKED_DESCRIPTOR,
+                )
+                .unwrap()
+                .build(),
+            )
+            .build(&usb_alloc);
+
+        let _usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+            .device_class(USB_CLASS_HID)
+            .build();
+
+        let device = hid.device::<Device, _>();
+        device.write_report(&[2, 0x04, 0x05, 0x06]).ok();
+        device.write_report(&[2, 0x04, 0x05]).ok();
     }
 }